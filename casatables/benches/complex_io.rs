@@ -0,0 +1,76 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Benchmark writing and reading a Measurement-Set-like DATA column.
+//!
+//! Complex buffers are handed to casacore without any per-element conversion,
+//! so these should run at speeds comparable to the `memcpy` baseline of the
+//! same number of bytes.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use ndarray::Array2;
+use rubbl_casatables::{
+    Complex, GlueDataType, Table, TableCreateMode, TableDesc, TableDescCreateMode,
+};
+use tempfile::tempdir;
+
+const N_ROWS: u64 = 256;
+const N_CHANS: usize = 768;
+const N_POLS: usize = 4;
+
+fn make_table(path: &std::path::Path) -> Table {
+    let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+    table_desc
+        .add_array_column(
+            GlueDataType::TpComplex,
+            "DATA",
+            None,
+            Some(&[N_CHANS as u64, N_POLS as u64]),
+            true,
+            false,
+        )
+        .unwrap();
+    Table::new(path, table_desc, N_ROWS as usize, TableCreateMode::New).unwrap()
+}
+
+fn data_column(c: &mut Criterion) {
+    let tmp_dir = tempdir().unwrap();
+    let mut table = make_table(&tmp_dir.path().join("bench.tab"));
+    let cell = Array2::from_shape_fn((N_CHANS, N_POLS), |(i, j)| Complex::new(i as f32, j as f32));
+    let cell_bytes = (N_CHANS * N_POLS * std::mem::size_of::<Complex<f32>>()) as u64;
+
+    let mut group = c.benchmark_group("data_column");
+    group.throughput(Throughput::Bytes(cell_bytes * N_ROWS));
+
+    group.bench_function("memcpy", |b| {
+        let src = cell.as_slice().unwrap();
+        let mut dest = vec![Complex::new(0f32, 0f32); src.len() * N_ROWS as usize];
+        b.iter(|| {
+            for chunk in dest.chunks_exact_mut(src.len()) {
+                chunk.copy_from_slice(black_box(src));
+            }
+        })
+    });
+
+    group.bench_function("put_cell", |b| {
+        b.iter(|| {
+            for row in 0..N_ROWS {
+                table.put_cell("DATA", row, black_box(&cell)).unwrap();
+            }
+        })
+    });
+
+    group.bench_function("get_cell", |b| {
+        b.iter(|| {
+            for row in 0..N_ROWS {
+                let v: Array2<Complex<f32>> = table.get_cell("DATA", row).unwrap();
+                black_box(v);
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, data_column);
+criterion_main!(benches);
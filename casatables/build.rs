@@ -1,25 +1,48 @@
 // Copyright 2017-2020 Peter Williams <peter@newton.cx> and collaborators
 // Licensed under the MIT License.
 
-use std::env;
+use std::{env, path::Path};
 
 const FILES: &[&str] = &["src/glue.cc"];
+const AOFLAGGER_FILES: &[&str] = &["src/aoflagger_glue.cc"];
 
 fn main() {
+    // rubbl_casatables_impl refuses to build for targets that casacore does
+    // not support, and reports why; there are no headers to compile against.
+    if let Some(os) = env::var_os("DEP_CASA_UNSUPPORTED") {
+        println!(
+            "cargo:warning=not building the casacore glue for unsupported target OS `{}`",
+            os.to_string_lossy()
+        );
+        return;
+    }
+
+    // If rubbl_casatables_impl was built to use a system casacore, it tells us
+    // so, and the headers are the vanilla ones.
+    let system = env::var_os("DEP_CASA_SYSTEM").is_some();
     let mut builder = cc::Build::new();
 
-    builder
-        .cpp(true)
-        .warnings(true)
-        .flag_if_supported("-std=c++11")
-        // This allows us to treat rubbl's modified casacore as a separate 
-        // namespace, so that both vanilla casacore and rubbl can be linked
-        // at the same time. 
-        .define("casacore", "rubbl_casacore")
-        .include("src")
-        .include(env::var_os("DEP_CASA_INCLUDE").unwrap())
-        .files(FILES)
-        .compile("libcasatables_glue.a");
+    builder.cpp(true).warnings(true).include("src");
+
+    if system {
+        // Recent casacore releases require C++17.
+        builder
+            .flag_if_supported("-std=c++17")
+            .define("RUBBL_SYSTEM_CASACORE", "1");
+    } else {
+        builder
+            .flag_if_supported("-std=c++11")
+            // This allows us to treat rubbl's modified casacore as a separate
+            // namespace, so that both vanilla casacore and rubbl can be linked
+            // at the same time.
+            .define("casacore", "rubbl_casacore");
+    }
+
+    for dir in env::split_paths(&env::var_os("DEP_CASA_INCLUDE").unwrap()) {
+        builder.include(dir);
+    }
+
+    builder.files(FILES).compile("libcasatables_glue.a");
 
     for file in FILES {
         println!("cargo:rerun-if-changed={}", file);
@@ -28,6 +51,52 @@ fn main() {
     // Because our glue.cc references casatables C++ directly, we need to make
     // sure to explicitly link with it. If not, it looks like the dead code
     // elimination may cause link issues when we actually try to link
-    // executables.
-    println!("cargo:rustc-link-lib=static=casatables_impl");
+    // executables. A system casacore is linked by rubbl_casatables_impl's
+    // pkg-config probe instead.
+    if !system {
+        println!("cargo:rustc-link-lib=static=casatables_impl");
+    }
+
+    if env::var_os("CARGO_FEATURE_AOFLAGGER").is_some() {
+        build_aoflagger_shim();
+    }
+
+    // Pass along the build configuration of casacore so that it can be
+    // reported at runtime.
+    let features = env::var("DEP_CASA_FEATURES").unwrap_or_default();
+    println!("cargo:rustc-env=RUBBL_CASACORE_FEATURES={}", features);
+    let unknown = env::var("DEP_CASA_UNKNOWN_FEATURES").unwrap_or_default();
+    println!("cargo:rustc-env=RUBBL_CASACORE_UNKNOWN_FEATURES={}", unknown);
+    println!(
+        "cargo:rustc-env=RUBBL_CASACORE_SYSTEM={}",
+        if system { "1" } else { "0" }
+    );
+}
+
+/// Build the shim around AOFlagger's C++ API used by the `aoflagger` feature,
+/// against an installed AOFlagger.
+fn build_aoflagger_shim() {
+    println!("cargo:rerun-if-env-changed=AOFLAGGER_INCLUDE_DIR");
+    println!("cargo:rerun-if-env-changed=AOFLAGGER_LIB_DIR");
+
+    let mut builder = cc::Build::new();
+    builder.cpp(true).warnings(true).flag_if_supported("-std=c++17");
+
+    if let Some(dir) = env::var_os("AOFLAGGER_INCLUDE_DIR") {
+        builder.include(dir);
+    }
+
+    builder
+        .files(AOFLAGGER_FILES)
+        .compile("libcasatables_aoflagger.a");
+
+    for file in AOFLAGGER_FILES {
+        println!("cargo:rerun-if-changed={}", file);
+    }
+
+    if let Some(dir) = env::var_os("AOFLAGGER_LIB_DIR") {
+        println!("cargo:rustc-link-search=native={}", Path::new(&dir).display());
+    }
+
+    println!("cargo:rustc-link-lib=aoflagger");
 }
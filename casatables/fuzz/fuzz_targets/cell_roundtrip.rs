@@ -0,0 +1,188 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Write arbitrary cells to a table and read them back.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use ndarray::Array2;
+use rubbl_casatables::{
+    CasaScalarData, Complex, GlueDataType, Table, TableCreateMode, TableDesc, TableDescCreateMode,
+};
+
+#[derive(Arbitrary, Debug)]
+enum Cells {
+    Bool(Vec<bool>),
+    UChar(Vec<u8>),
+    Int(Vec<i32>),
+    Int64(Vec<i64>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    Complex(Vec<(f32, f32)>),
+    String(Vec<String>),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    cells: Cells,
+    /// The cell shape: `[]` for a scalar column, otherwise one or two
+    /// dimensions.
+    shape: Vec<u8>,
+    /// Whether an array column has a fixed shape.
+    fixed: bool,
+    row: u8,
+}
+
+/// Compare values, treating floats as equal if their bits are.
+trait BitEq {
+    fn bit_eq(&self, other: &Self) -> bool;
+}
+
+macro_rules! exact_bit_eq {
+    ($($t:ty),*) => {
+        $(impl BitEq for $t {
+            fn bit_eq(&self, other: &Self) -> bool {
+                self == other
+            }
+        })*
+    };
+}
+
+exact_bit_eq! { bool, u8, i32, i64 }
+
+impl BitEq for f32 {
+    fn bit_eq(&self, other: &Self) -> bool {
+        self.to_bits() == other.to_bits()
+    }
+}
+
+impl BitEq for f64 {
+    fn bit_eq(&self, other: &Self) -> bool {
+        self.to_bits() == other.to_bits()
+    }
+}
+
+impl BitEq for Complex<f32> {
+    fn bit_eq(&self, other: &Self) -> bool {
+        self.re.bit_eq(&other.re) && self.im.bit_eq(&other.im)
+    }
+}
+
+const N_ROWS: u64 = 3;
+
+fn make_table(tmp_dir: &tempfile::TempDir, ty: GlueDataType, shape: &[u64], fixed: bool) -> Table {
+    let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+
+    if shape.is_empty() {
+        desc.add_scalar_column(ty, "X", None, false, false).unwrap();
+    } else {
+        let dims = if fixed { Some(shape) } else { None };
+        desc.add_array_column(ty, "X", None, dims, false, false)
+            .unwrap();
+    }
+
+    Table::new(
+        tmp_dir.path().join("t"),
+        desc,
+        N_ROWS as usize,
+        TableCreateMode::New,
+    )
+    .unwrap()
+}
+
+fn round_trip<T>(values: &[T], shape: &[u64], fixed: bool, row: u64)
+where
+    T: CasaScalarData + BitEq + Copy + std::fmt::Debug,
+{
+    let n: u64 = shape.iter().product();
+
+    if (values.len() as u64) < n {
+        return;
+    }
+
+    let values = &values[..n as usize];
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let mut t = make_table(&tmp_dir, T::DATA_TYPE, shape, fixed);
+
+    match shape.len() {
+        0 => {
+            t.put_cell("X", row, &values[0]).unwrap();
+            let back: T = t.get_cell("X", row).unwrap();
+            assert!(back.bit_eq(&values[0]), "{:?} != {:?}", back, values[0]);
+        }
+
+        1 => {
+            t.put_cell("X", row, &values.to_vec()).unwrap();
+            let back: Vec<T> = t.get_cell("X", row).unwrap();
+            assert_eq!(back.len(), values.len());
+            assert!(back.iter().zip(values).all(|(a, b)| a.bit_eq(b)));
+        }
+
+        _ => {
+            let shape = (shape[0] as usize, shape[1] as usize);
+            let arr = Array2::from_shape_vec(shape, values.to_vec()).unwrap();
+            t.put_cell("X", row, &arr).unwrap();
+            let back: Array2<T> = t.get_cell("X", row).unwrap();
+            assert_eq!(back.dim(), shape);
+            assert!(back.iter().zip(arr.iter()).all(|(a, b)| a.bit_eq(b)));
+        }
+    }
+}
+
+fn round_trip_strings(values: &[String], shape: &[u64], fixed: bool, row: u64) {
+    let tmp_dir = tempfile::tempdir().unwrap();
+
+    // String arrays are only exposed as vectors.
+    if shape.len() > 1 {
+        return;
+    }
+
+    if shape.is_empty() {
+        let value = match values.first() {
+            Some(v) => v,
+            None => return,
+        };
+
+        let mut t = make_table(&tmp_dir, GlueDataType::TpString, shape, fixed);
+        t.put_cell("X", row, value).unwrap();
+        assert_eq!(&t.get_cell::<String>("X", row).unwrap(), value);
+    } else {
+        let n = shape[0] as usize;
+
+        if values.len() < n {
+            return;
+        }
+
+        let values = values[..n].to_vec();
+        let mut t = make_table(&tmp_dir, GlueDataType::TpString, shape, fixed);
+        t.put_cell("X", row, &values).unwrap();
+        assert_eq!(t.get_cell::<Vec<String>>("X", row).unwrap(), values);
+    }
+}
+
+fuzz_target!(|input: Input| {
+    // Keep cells small; zero-length axes are not interesting here.
+    let shape: Vec<u64> = input
+        .shape
+        .iter()
+        .take(2)
+        .map(|d| (*d % 6) as u64 + 1)
+        .collect();
+    let row = input.row as u64 % N_ROWS;
+
+    match input.cells {
+        Cells::Bool(ref v) => round_trip(v, &shape, input.fixed, row),
+        Cells::UChar(ref v) => round_trip(v, &shape, input.fixed, row),
+        Cells::Int(ref v) => round_trip(v, &shape, input.fixed, row),
+        Cells::Int64(ref v) => round_trip(v, &shape, input.fixed, row),
+        Cells::Float(ref v) => round_trip(v, &shape, input.fixed, row),
+        Cells::Double(ref v) => round_trip(v, &shape, input.fixed, row),
+        Cells::Complex(ref v) => {
+            let v: Vec<Complex<f32>> = v.iter().map(|(re, im)| Complex::new(*re, *im)).collect();
+            round_trip(&v, &shape, input.fixed, row)
+        }
+        Cells::String(ref v) => round_trip_strings(v, &shape, input.fixed, row),
+    }
+});
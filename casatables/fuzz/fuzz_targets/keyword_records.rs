@@ -0,0 +1,71 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Store arbitrary fields in keyword records and read them back.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use rubbl_casatables::TableRecord;
+use std::collections::HashMap;
+
+#[derive(Arbitrary, Clone, Debug)]
+enum Value {
+    Bool(bool),
+    Int(i32),
+    Double(f64),
+    String(String),
+    Ints(Vec<i32>),
+    Strings(Vec<String>),
+}
+
+impl Value {
+    fn put(&self, rec: &mut TableRecord, name: &str) -> bool {
+        match self {
+            Value::Bool(v) => rec.put_field(name, v),
+            Value::Int(v) => rec.put_field(name, v),
+            Value::Double(v) => rec.put_field(name, v),
+            Value::String(v) => rec.put_field(name, v),
+            Value::Ints(v) => rec.put_field(name, v),
+            Value::Strings(v) => rec.put_field(name, v),
+        }
+        .is_ok()
+    }
+
+    fn check(&self, rec: &mut TableRecord, name: &str) {
+        match self {
+            Value::Bool(v) => assert_eq!(rec.get_field::<bool>(name).unwrap(), *v),
+            Value::Int(v) => assert_eq!(rec.get_field::<i32>(name).unwrap(), *v),
+            Value::Double(v) => {
+                assert_eq!(rec.get_field::<f64>(name).unwrap().to_bits(), v.to_bits())
+            }
+            Value::String(v) => assert_eq!(&rec.get_field::<String>(name).unwrap(), v),
+            Value::Ints(v) => assert_eq!(&rec.get_field::<Vec<i32>>(name).unwrap(), v),
+            Value::Strings(v) => assert_eq!(&rec.get_field::<Vec<String>>(name).unwrap(), v),
+        }
+    }
+}
+
+fuzz_target!(|fields: Vec<(String, Value)>| {
+    let mut rec = TableRecord::new().unwrap();
+    let mut expected = HashMap::new();
+
+    for (name, value) in fields.iter().take(32) {
+        // casacore may refuse some names, or a change of a field's type;
+        // that must be an error and leave the record as it was.
+        if value.put(&mut rec, name) {
+            expected.insert(name.clone(), value.clone());
+        }
+    }
+
+    let mut names = rec.keyword_names().unwrap();
+    names.sort();
+    let mut expected_names: Vec<_> = expected.keys().cloned().collect();
+    expected_names.sort();
+    assert_eq!(names, expected_names);
+
+    for (name, value) in &expected {
+        value.check(&mut rec, name);
+    }
+});
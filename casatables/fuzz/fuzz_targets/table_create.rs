@@ -0,0 +1,137 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Create tables with arbitrary column names, types, and shapes.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use rubbl_casatables::{GlueDataType, Table, TableCreateMode, TableDesc, TableDescCreateMode};
+use std::path::PathBuf;
+
+#[derive(Arbitrary, Debug)]
+enum ColumnType {
+    Bool,
+    UChar,
+    Short,
+    Int,
+    UInt,
+    Int64,
+    Float,
+    Double,
+    Complex,
+    DComplex,
+    String,
+}
+
+impl ColumnType {
+    fn glue(&self) -> GlueDataType {
+        match self {
+            ColumnType::Bool => GlueDataType::TpBool,
+            ColumnType::UChar => GlueDataType::TpUChar,
+            ColumnType::Short => GlueDataType::TpShort,
+            ColumnType::Int => GlueDataType::TpInt,
+            ColumnType::UInt => GlueDataType::TpUInt,
+            ColumnType::Int64 => GlueDataType::TpInt64,
+            ColumnType::Float => GlueDataType::TpFloat,
+            ColumnType::Double => GlueDataType::TpDouble,
+            ColumnType::Complex => GlueDataType::TpComplex,
+            ColumnType::DComplex => GlueDataType::TpDComplex,
+            ColumnType::String => GlueDataType::TpString,
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+struct ColumnSpec {
+    name: String,
+    ty: ColumnType,
+    comment: Option<String>,
+    /// `None` for a scalar column, otherwise the dimensions of an array
+    /// column, which is variable-shaped if the list is empty.
+    dims: Option<Vec<u8>>,
+    direct: bool,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    columns: Vec<ColumnSpec>,
+    n_rows: u8,
+    /// The raw bytes of the table's file name, which need not be UTF-8.
+    file_name: Vec<u8>,
+}
+
+#[cfg(unix)]
+fn file_name(bytes: &[u8]) -> PathBuf {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    // Stay inside the temporary directory.
+    let bytes: Vec<u8> = bytes
+        .iter()
+        .map(|b| if *b == b'/' || *b == 0 { b'_' } else { *b })
+        .collect();
+
+    match &bytes[..] {
+        b"" | b"." | b".." => PathBuf::from("t"),
+        b => PathBuf::from(OsStr::from_bytes(b)),
+    }
+}
+
+#[cfg(not(unix))]
+fn file_name(bytes: &[u8]) -> PathBuf {
+    let name: String = String::from_utf8_lossy(bytes)
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+
+    PathBuf::from(if name.is_empty() {
+        "t".to_owned()
+    } else {
+        name
+    })
+}
+
+fuzz_target!(|input: Input| {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+    let mut n_added = 0;
+
+    for col in input.columns.iter().take(8) {
+        let comment = col.comment.as_deref();
+
+        let result = match col.dims {
+            None => desc.add_scalar_column(col.ty.glue(), &col.name, comment, col.direct, false),
+
+            Some(ref dims) => {
+                let dims: Vec<u64> = dims.iter().take(4).map(|d| (*d % 8) as u64).collect();
+                let dims = if dims.is_empty() {
+                    None
+                } else {
+                    Some(&dims[..])
+                };
+                desc.add_array_column(col.ty.glue(), &col.name, comment, dims, col.direct, false)
+            }
+        };
+
+        if result.is_ok() {
+            n_added += 1;
+        }
+    }
+
+    let path = tmp_dir.path().join(file_name(&input.file_name));
+
+    // Invalid names and paths must be reported as errors, not crashes.
+    let mut t = match Table::new(&path, desc, input.n_rows as usize, TableCreateMode::New) {
+        Ok(t) => t,
+        Err(_) => return,
+    };
+
+    assert_eq!(t.n_rows(), input.n_rows as u64);
+    assert_eq!(t.column_names().unwrap().len(), n_added);
+    drop(t);
+
+    if let Ok(t) = Table::open(&path, rubbl_casatables::TableOpenMode::Read) {
+        assert_eq!(t.n_rows(), input.n_rows as u64);
+    }
+});
@@ -0,0 +1,115 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+// A C interface to the parts of the AOFlagger C++ API used by the `aoflagger`
+// feature; see src/ms/aoflagger.rs. AOFlagger images are indexed by time along
+// their width and by frequency along their height, and a complex visibility
+// with N polarizations is held as 2N images, real and imaginary parts
+// alternating.
+
+#include <aoflagger.h>
+
+#include <stddef.h>
+#include <string.h>
+#include <exception>
+#include <string>
+
+struct RubblAoflagger {
+    aoflagger::AOFlagger flagger;
+    aoflagger::Strategy strategy;
+
+    explicit RubblAoflagger(const char *strategy_path) :
+        flagger(),
+        strategy(flagger.LoadStrategyFile(
+            strategy_path != NULL ? std::string(strategy_path) : flagger.FindStrategyFile()))
+    {
+    }
+};
+
+extern "C" {
+    static void
+    aoflagger_exception(char *message, size_t message_len)
+    {
+        if (message_len == 0)
+            return;
+
+        try {
+            throw;
+        } catch (const std::exception &e) {
+            strncpy(message, e.what(), message_len - 1);
+            message[message_len - 1] = '\0';
+        } catch (...) {
+            strncpy(message, "unidentifiable C++ exception occurred", message_len - 1);
+            message[message_len - 1] = '\0';
+        }
+    }
+
+    // Load a strategy, or the default one for a generic telescope if
+    // `strategy_path` is NULL. Returns NULL on failure.
+    RubblAoflagger *
+    rubbl_aoflagger_new(const char *strategy_path, char *message, size_t message_len)
+    {
+        try {
+            return new RubblAoflagger(strategy_path);
+        } catch (...) {
+            aoflagger_exception(message, message_len);
+            return NULL;
+        }
+    }
+
+    void
+    rubbl_aoflagger_free(RubblAoflagger *flagger)
+    {
+        delete flagger;
+    }
+
+    // Run the strategy on one baseline. `data` holds interleaved real and
+    // imaginary parts and `flags_in` one byte per sample, both in C order
+    // with shape [n_times, n_chans, n_pols]. The strategy flags all
+    // polarizations together, so `flags_out` has shape [n_times, n_chans].
+    int
+    rubbl_aoflagger_run(RubblAoflagger *flagger, size_t n_times, size_t n_chans,
+                        size_t n_pols, const float *data, const unsigned char *flags_in,
+                        unsigned char *flags_out, char *message, size_t message_len)
+    {
+        try {
+            aoflagger::ImageSet images =
+                flagger->flagger.MakeImageSet(n_times, n_chans, 2 * n_pols);
+            aoflagger::FlagMask existing =
+                flagger->flagger.MakeFlagMask(n_times, n_chans, false);
+            const size_t image_stride = images.HorizontalStride();
+            const size_t existing_stride = existing.HorizontalStride();
+            bool *existing_buf = existing.Buffer();
+
+            for (size_t p = 0; p < n_pols; p++) {
+                float *re = images.ImageBuffer(2 * p);
+                float *im = images.ImageBuffer(2 * p + 1);
+
+                for (size_t t = 0; t < n_times; t++) {
+                    for (size_t c = 0; c < n_chans; c++) {
+                        const size_t i = (t * n_chans + c) * n_pols + p;
+                        re[c * image_stride + t] = data[2 * i];
+                        im[c * image_stride + t] = data[2 * i + 1];
+
+                        if (flags_in[i])
+                            existing_buf[c * existing_stride + t] = true;
+                    }
+                }
+            }
+
+            aoflagger::FlagMask result = flagger->strategy.Run(images, existing);
+            const size_t result_stride = result.HorizontalStride();
+            const bool *result_buf = result.Buffer();
+
+            for (size_t t = 0; t < n_times; t++) {
+                for (size_t c = 0; c < n_chans; c++)
+                    flags_out[t * n_chans + c] = result_buf[c * result_stride + t] ? 1 : 0;
+            }
+        } catch (...) {
+            aoflagger_exception(message, message_len);
+            return 1;
+        }
+
+        return 0;
+    }
+}
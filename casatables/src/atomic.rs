@@ -0,0 +1,203 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Creating tables that appear all at once.
+//!
+//! A casacore table is a directory of files, written piecemeal as the table
+//! is filled. If a conversion crashes or is killed partway through, the
+//! half-built directory is left behind, and downstream tools that find it
+//! will happily try to use it. [`Table::create_atomic`] builds the table
+//! under a hidden temporary name next to its destination and renames it into
+//! place only when it is complete, so that the destination either does not
+//! exist or holds a finished table.
+
+use std::{
+    fs, io,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{Table, TableError, TableOpenMode};
+
+/// Distinguishes the temporary names of tables created by this process.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A table being built under a temporary name, as returned by
+/// [`Table::create_atomic`].
+///
+/// This dereferences to the [`Table`] being built. Call [`Self::commit`]
+/// once it is complete to move it into place. If it is dropped without being
+/// committed — for instance, because an error cut the conversion short — the
+/// temporary table is deleted.
+pub struct PendingTable {
+    table: Option<Table>,
+    temp_path: PathBuf,
+    final_path: PathBuf,
+}
+
+impl PendingTable {
+    /// Get the path at which the table is being built.
+    pub fn temp_path(&self) -> &Path {
+        &self.temp_path
+    }
+
+    /// Get the path to which the table will be moved when committed.
+    pub fn final_path(&self) -> &Path {
+        &self.final_path
+    }
+
+    /// Finish the table and move it into place.
+    ///
+    /// The table is flushed and closed, renamed to its final path, and then
+    /// reopened for reading and writing at that path. The rename is atomic,
+    /// so other processes see either no table or the complete one. It fails
+    /// if something already exists at the final path, in which case the
+    /// temporary table is deleted.
+    pub fn commit(mut self) -> Result<Table, TableError> {
+        let mut table = self.table.take().unwrap();
+        table.flush()?;
+        drop(table);
+
+        if fs::symlink_metadata(&self.final_path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("\"{}\" already exists", self.final_path.display()),
+            )
+            .into());
+        }
+
+        fs::rename(&self.temp_path, &self.final_path)?;
+        Table::open(&self.final_path, TableOpenMode::ReadWrite)
+    }
+}
+
+impl Deref for PendingTable {
+    type Target = Table;
+
+    fn deref(&self) -> &Table {
+        self.table.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PendingTable {
+    fn deref_mut(&mut self) -> &mut Table {
+        self.table.as_mut().unwrap()
+    }
+}
+
+impl Drop for PendingTable {
+    fn drop(&mut self) {
+        // The table must be closed before its files are removed. After a
+        // successful commit, the temporary path no longer exists and this
+        // does nothing.
+        self.table = None;
+        let _ = fs::remove_dir_all(&self.temp_path);
+    }
+}
+
+impl Table {
+    /// Create a table that only appears at *path* once it is complete.
+    ///
+    /// *create* is called with a temporary path, a hidden name in the same
+    /// directory as *path*, and should create the table there — with
+    /// [`Table::new`], [`Table::create_with_default_subtables`], or the like.
+    /// The returned [`PendingTable`] can then be filled in as usual, and
+    /// [`PendingTable::commit`] renames it to *path*. If the process dies
+    /// first, only the hidden temporary table is left behind; if the
+    /// `PendingTable` is dropped first, that is deleted too.
+    ///
+    /// Sub-tables created inside the temporary table’s directory, as those of
+    /// a Measurement Set are, move along with it.
+    ///
+    /// ```rust
+    /// use rubbl_casatables::{Table, TableOpenMode};
+    /// use tempfile::tempdir;
+    ///
+    /// let tmp_dir = tempdir().unwrap();
+    /// let ms_path = tmp_dir.path().join("out.ms");
+    ///
+    /// let mut pending =
+    ///     Table::create_atomic(&ms_path, |p| Table::create_with_default_subtables(p, 0)).unwrap();
+    /// pending.add_rows(10).unwrap();
+    /// assert!(!ms_path.exists());
+    ///
+    /// let ms = pending.commit().unwrap();
+    /// assert_eq!(ms.n_rows(), 10);
+    /// ```
+    pub fn create_atomic<P, F>(path: P, create: F) -> Result<PendingTable, TableError>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&Path) -> Result<Table, TableError>,
+    {
+        let final_path = path.as_ref().to_owned();
+        let name = final_path
+            .file_name()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("\"{}\" does not name a table", final_path.display()),
+                )
+            })?
+            .to_string_lossy();
+        let temp_path = final_path.with_file_name(format!(
+            ".{}.partial-{}-{}",
+            name,
+            process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+
+        // If creation fails partway, clean up whatever it left.
+        let mut pending = PendingTable {
+            table: None,
+            temp_path,
+            final_path,
+        };
+        pending.table = Some(create(&pending.temp_path)?);
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    fn make(path: &Path) -> Result<Table, TableError> {
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH)?;
+        desc.add_scalar_column(GlueDataType::TpInt, "X", None, false, false)?;
+        Table::new(path, desc, 2, TableCreateMode::New)
+    }
+
+    #[test]
+    fn atomic_creation() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("t");
+        let n_entries = || fs::read_dir(tmp_dir.path()).unwrap().count();
+
+        // Abandoned tables leave nothing behind.
+        let mut pending = Table::create_atomic(&path, make).unwrap();
+        pending.put_cell("X", 1, &5i32).unwrap();
+        assert!(pending.temp_path().exists());
+        assert!(!path.exists());
+        drop(pending);
+        assert_eq!(n_entries(), 0);
+
+        // Committed ones appear at their final path.
+        let mut pending = Table::create_atomic(&path, make).unwrap();
+        pending.put_cell("X", 1, &7i32).unwrap();
+        let mut t = pending.commit().unwrap();
+        assert_eq!(t.get_cell::<i32>("X", 1).unwrap(), 7);
+        drop(t);
+        assert_eq!(n_entries(), 1);
+
+        // Existing tables are not overwritten.
+        let pending = Table::create_atomic(&path, make).unwrap();
+        assert!(pending.commit().is_err());
+        assert_eq!(n_entries(), 1);
+        let mut t = Table::open(&path, TableOpenMode::Read).unwrap();
+        assert_eq!(t.get_cell::<i32>("X", 1).unwrap(), 7);
+    }
+}
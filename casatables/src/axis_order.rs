@@ -0,0 +1,189 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Reading and writing array cells with an explicit axis order.
+//!
+//! casacore stores arrays in Fortran (column-major) order and lists their
+//! axes fastest-varying first: a visibility cell in a Measurement Set has
+//! shape `[n_pols, n_chans]`. This crate, like python-casacore, presents the
+//! same memory as a C-order (row-major) array with the axes listed in the
+//! reverse order, so that the cell becomes an array of shape
+//! `(n_chans, n_pols)`. Both views are correct, but mixing them up silently
+//! transposes data. The methods here make the choice explicit.
+
+use ndarray::{ArrayBase, Data, Dimension};
+use rubbl_core::num::DimFromShapeSlice;
+
+use crate::{glue, io_stats::IoDirection, Array, CasaScalarData, Table, TableError};
+
+/// The order in which the axes of an array cell are presented.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum AxisOrder {
+    /// C (row-major) order, with the last axis varying fastest. Axes are
+    /// listed in the reverse of the order that casacore uses, as with
+    /// [`Table::get_cell`] and [`Table::put_cell`] and in python-casacore.
+    #[default]
+    RowMajor,
+
+    /// Fortran (column-major) order, with the first axis varying fastest.
+    /// Axes are listed in the order that casacore and CASA documentation
+    /// use.
+    ColumnMajor,
+}
+
+impl Table {
+    /// Get the value of one array cell, with its axes in *order*.
+    ///
+    /// With [`AxisOrder::RowMajor`], this is the same as [`Self::get_cell`].
+    /// With [`AxisOrder::ColumnMajor`], the array has the shape that casacore
+    /// reports for the cell and a Fortran memory layout; no data are copied
+    /// to produce it.
+    ///
+    /// ```rust
+    /// use ndarray::Array2;
+    /// use rubbl_casatables::{
+    ///     AxisOrder, GlueDataType, Table, TableCreateMode, TableDesc, TableDescCreateMode,
+    /// };
+    /// use tempfile::tempdir;
+    ///
+    /// let tmp_dir = tempdir().unwrap();
+    /// let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+    /// desc.add_array_column(GlueDataType::TpFloat, "WEIGHT", None, Some(&[3, 2]), true, false)
+    ///     .unwrap();
+    /// let mut t = Table::new(tmp_dir.path().join("t"), desc, 1, TableCreateMode::New).unwrap();
+    ///
+    /// // Three channels of two polarizations, in casacore's axis order.
+    /// let w = Array2::from_shape_fn((2, 3), |(pol, chan)| (10 * chan + pol) as f32);
+    /// t.put_cell_with_order("WEIGHT", 0, &w, AxisOrder::ColumnMajor).unwrap();
+    ///
+    /// let c: Array2<f32> = t.get_cell_with_order("WEIGHT", 0, AxisOrder::RowMajor).unwrap();
+    /// assert_eq!(c.shape(), &[3, 2]);
+    /// assert_eq!(c[[2, 1]], 21.);
+    /// let f: Array2<f32> = t.get_cell_with_order("WEIGHT", 0, AxisOrder::ColumnMajor).unwrap();
+    /// assert_eq!(f, w);
+    /// ```
+    pub fn get_cell_with_order<T, D>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+        order: AxisOrder,
+    ) -> Result<Array<T, D>, TableError>
+    where
+        T: CasaScalarData + Copy,
+        D: Dimension + DimFromShapeSlice<u64>,
+    {
+        let value: Array<T, D> = self.get_cell(col_name, row)?;
+
+        Ok(match order {
+            AxisOrder::RowMajor => value,
+            AxisOrder::ColumnMajor => value.reversed_axes(),
+        })
+    }
+
+    /// Put a value for one array cell, with its axes in *order*.
+    ///
+    /// With [`AxisOrder::RowMajor`], *value* is written like an array passed
+    /// to [`Self::put_cell`]. With [`AxisOrder::ColumnMajor`], its shape is
+    /// taken to be in casacore’s axis order. Unlike [`Self::put_cell`], this
+    /// accepts arrays with any memory layout: an array whose layout matches
+    /// *order* is written directly, and any other array is first copied into
+    /// that layout.
+    pub fn put_cell_with_order<T, S, D>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+        value: &ArrayBase<S, D>,
+        order: AxisOrder,
+    ) -> Result<(), TableError>
+    where
+        T: CasaScalarData + Copy,
+        S: Data<Elem = T>,
+        D: Dimension,
+    {
+        let value = match order {
+            AxisOrder::RowMajor => value.view(),
+            AxisOrder::ColumnMajor => value.view().reversed_axes(),
+        };
+        let value = value.as_standard_layout();
+        let shape: Vec<u64> = value.shape().iter().map(|n| *n as u64).collect();
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+
+        let rv = unsafe {
+            glue::table_put_cell(
+                self.handle,
+                &ccol_name,
+                row,
+                T::VECTOR_TYPE,
+                shape.len() as u64,
+                shape.as_ptr(),
+                value.as_ptr() as _,
+                &mut self.exc_info,
+            )
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        self.record_io(
+            IoDirection::Put,
+            col_name,
+            1,
+            T::DATA_TYPE,
+            value.len() as u64,
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use ndarray::{Array3, ShapeBuilder};
+    use tempfile::tempdir;
+
+    #[test]
+    fn axis_orders() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_array_column(
+            GlueDataType::TpInt,
+            "X",
+            None,
+            Some(&[4, 3, 2]),
+            true,
+            false,
+        )
+        .unwrap();
+        let mut t = Table::new(tmp_dir.path().join("t"), desc, 3, TableCreateMode::New).unwrap();
+
+        // Cell values encode their casacore indices.
+        let casa = |i: usize, j: usize, k: usize| (100 * i + 10 * j + k) as i32;
+        let f = Array3::from_shape_fn((2, 3, 4).f(), |(i, j, k)| casa(i, j, k));
+        let c = Array3::from_shape_fn((4, 3, 2), |(k, j, i)| casa(i, j, k));
+
+        // All layouts and orders describing the same cell agree.
+        t.put_cell_with_order("X", 0, &f, AxisOrder::ColumnMajor)
+            .unwrap();
+        t.put_cell_with_order("X", 1, &c, AxisOrder::RowMajor)
+            .unwrap();
+        t.put_cell_with_order("X", 2, &c.t(), AxisOrder::ColumnMajor)
+            .unwrap();
+
+        for row in 0..3 {
+            let back: Array3<i32> = t.get_cell("X", row).unwrap();
+            assert_eq!(back, c);
+            let back: Array3<i32> = t
+                .get_cell_with_order("X", row, AxisOrder::ColumnMajor)
+                .unwrap();
+            assert_eq!(back, f);
+            assert_eq!(back.shape(), &[2, 3, 4]);
+        }
+
+        // Mismatched shapes are caught by casacore.
+        assert!(t
+            .put_cell_with_order("X", 0, &c, AxisOrder::ColumnMajor)
+            .is_err());
+    }
+}
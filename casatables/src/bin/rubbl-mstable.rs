@@ -0,0 +1,573 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Utilities for working with Measurement Sets, invoked as `rubbl mstable`.
+
+use anyhow::{bail, Error};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use rubbl_casatables::{
+    configure,
+    ms::{
+        fitsidi_to_ms, miriad_to_ms, run_write_bench, shrink_ms, summary, MsSelection,
+        ShrinkOptions, WriteBenchOptions, WritePattern,
+    },
+    CasacoreConfig, Table, TableOpenMode,
+};
+use rubbl_core::{
+    ctry,
+    notify::{ClapNotificationArgsExt, NotificationBackend},
+    rn_note,
+};
+use std::{fs, io, path::PathBuf, process};
+
+fn main() {
+    let matches = Command::new("rubbl-mstable")
+        .bin_name("rubbl mstable")
+        .version(clap::crate_version!())
+        .about("Work with CASA Measurement Sets")
+        .rubbl_notify_args()
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("bench")
+                .about("Benchmark writing a visibility data column")
+                .arg(
+                    Arg::new("OUT-TABLE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The path of the scratch table, which must not exist")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("pattern")
+                        .long("pattern")
+                        .short('p')
+                        .value_parser(["cell", "cell-cached", "grow", "chunks"])
+                        .default_value("cell")
+                        .help("How the writes are issued"),
+                )
+                .arg(
+                    Arg::new("storage_manager")
+                        .long("storage-manager")
+                        .short('s')
+                        .value_name("TYPE")
+                        .default_value("StandardStMan")
+                        .help("The data manager storing the column"),
+                )
+                .arg(
+                    Arg::new("tsm_option")
+                        .long("tsm-option")
+                        .value_parser(["default", "cache", "mmap", "buffer"])
+                        .help("The access method of the tiled storage managers"),
+                )
+                .arg(
+                    Arg::new("tile_shape")
+                        .long("tile-shape")
+                        .value_name("ROWS,CHANS,POLS")
+                        .value_parser(clap::value_parser!(usize))
+                        .value_delimiter(',')
+                        .help("The tile shape to use with a tiled storage manager"),
+                )
+                .arg(
+                    Arg::new("rows")
+                        .long("rows")
+                        .short('r')
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("10000")
+                        .help("The number of rows to write"),
+                )
+                .arg(
+                    Arg::new("channels")
+                        .long("channels")
+                        .short('c')
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("768")
+                        .help("The number of channels in each cell"),
+                )
+                .arg(
+                    Arg::new("pols")
+                        .long("pols")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("4")
+                        .help("The number of polarizations in each cell"),
+                )
+                .arg(
+                    Arg::new("chunk_rows")
+                        .long("chunk-rows")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("The number of rows per chunk with the \"chunks\" pattern"),
+                )
+                .arg(
+                    Arg::new("repeat")
+                        .long("repeat")
+                        .short('n')
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("1")
+                        .help("Run the benchmark N times"),
+                )
+                .arg(
+                    Arg::new("keep")
+                        .long("keep")
+                        .action(ArgAction::SetTrue)
+                        .help("Keep the table written by the last run"),
+                )
+                .arg(
+                    Arg::new("pause")
+                        .long("pause")
+                        .action(ArgAction::SetTrue)
+                        .help("Wait for a line on standard input before starting, to allow a profiler or tracer to attach"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print results as JSON, one object per line, on standard output"),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Compare the columns and keywords of two tables")
+                .arg(
+                    Arg::new("OLD-TABLE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The path of the first table")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("NEW-TABLE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The path of the second table")
+                        .required(true)
+                        .index(2),
+                ),
+        )
+        .subcommand(
+            Command::new("fitsidi-to-ms")
+                .about("Convert a FITS-IDI file into a Measurement Set")
+                .arg(
+                    Arg::new("IN-PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The path of the input FITS-IDI file")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("OUT-TABLE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The path of the output data set, which must not exist")
+                        .required(true)
+                        .index(2),
+                ),
+        )
+        .subcommand(
+            Command::new("miriad-to-ms")
+                .about("Convert a MIRIAD UV data set into a Measurement Set")
+                .arg(
+                    Arg::new("IN-PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The path of the input MIRIAD data set")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("OUT-TABLE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The path of the output data set, which must not exist")
+                        .required(true)
+                        .index(2),
+                ),
+        )
+        .subcommand(
+            Command::new("select")
+                .about("Count the rows and channels matching a CASA-style data selection")
+                .arg(
+                    Arg::new("IN-TABLE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The path of the data set")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("antenna")
+                        .long("antenna")
+                        .value_name("SEL")
+                        .help("Select baselines, such as \"1&2\" or \"ANT0*&&&\""),
+                )
+                .arg(
+                    Arg::new("spw")
+                        .long("spw")
+                        .value_name("SEL")
+                        .help("Select spectral windows and channels, such as \"0:3~10\""),
+                )
+                .arg(
+                    Arg::new("field")
+                        .long("field")
+                        .value_name("SEL")
+                        .help("Select fields by ID or name"),
+                )
+                .arg(
+                    Arg::new("scan")
+                        .long("scan")
+                        .value_name("SEL")
+                        .help("Select scans, such as \"1~3,>10\""),
+                )
+                .arg(
+                    Arg::new("time")
+                        .long("time")
+                        .value_name("SEL")
+                        .help("Select time ranges, such as \"2024/01/01/10:00~10:30\""),
+                )
+                .arg(
+                    Arg::new("taql")
+                        .long("taql")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the TaQL query that the selection compiles to"),
+                ),
+        )
+        .subcommand(
+            Command::new("shrink")
+                .about("Copy a Measurement Set, keeping only a small subset of its data")
+                .arg(
+                    Arg::new("IN-TABLE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The path of the input data set")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("OUT-TABLE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The path of the output data set, which must not exist")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    Arg::new("timesteps")
+                        .long("timesteps")
+                        .short('t')
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Keep only the first N timesteps"),
+                )
+                .arg(
+                    Arg::new("channels")
+                        .long("channels")
+                        .short('c')
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Keep only the first N channels of each spectral window"),
+                )
+                .arg(
+                    Arg::new("zero_data")
+                        .long("zero-data")
+                        .action(ArgAction::SetTrue)
+                        .help("Replace the visibility data with zeros"),
+                )
+                .arg(
+                    Arg::new("anonymize")
+                        .long("anonymize")
+                        .action(ArgAction::SetTrue)
+                        .help("Blank out the observer and project names"),
+                ),
+        )
+        .subcommand(
+            Command::new("summary")
+                .about("Summarize the scans, fields, spectral windows, and antennas of a Measurement Set")
+                .arg(
+                    Arg::new("IN-TABLE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The path of the data set")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the summary as JSON"),
+                ),
+        )
+        .get_matches();
+
+    process::exit(rubbl_core::notify::run_with_notifications(
+        matches,
+        |matches, nbe| -> Result<i32, Error> {
+            match matches.subcommand() {
+                Some(("bench", m)) => bench(m, nbe),
+                Some(("diff", m)) => diff_cmd(m, nbe),
+                Some(("fitsidi-to-ms", m)) => fitsidi_to_ms_cmd(m, nbe),
+                Some(("miriad-to-ms", m)) => miriad_to_ms_cmd(m, nbe),
+                Some(("select", m)) => select_cmd(m, nbe),
+                Some(("shrink", m)) => shrink(m, nbe),
+                Some(("summary", m)) => summary_cmd(m, nbe),
+                Some((other, _)) => bail!("unrecognized subcommand \"{}\"", other),
+                None => bail!("a subcommand must be specified"),
+            }
+        },
+    ));
+}
+
+fn bench(matches: &ArgMatches, nbe: &mut dyn NotificationBackend) -> Result<i32, Error> {
+    let outpath = matches.get_one::<PathBuf>("OUT-TABLE").unwrap();
+
+    if let Some(option) = matches.get_one::<String>("tsm_option") {
+        ctry!(
+            configure(CasacoreConfig::new().tsm_option(option));
+            "failed to set the tiled storage manager option"
+        );
+    }
+
+    let options = WriteBenchOptions {
+        pattern: matches.get_one::<String>("pattern").unwrap().parse()?,
+        storage_manager: matches
+            .get_one::<String>("storage_manager")
+            .unwrap()
+            .clone(),
+        tile_shape: matches
+            .get_many::<usize>("tile_shape")
+            .map(|v| v.copied().collect()),
+        n_rows: *matches.get_one::<usize>("rows").unwrap(),
+        n_chans: *matches.get_one::<usize>("channels").unwrap(),
+        n_pols: *matches.get_one::<usize>("pols").unwrap(),
+        chunk_rows: matches.get_one::<usize>("chunk_rows").copied(),
+    };
+    let repeat = *matches.get_one::<usize>("repeat").unwrap();
+    let json = matches.get_flag("json");
+
+    if matches.get_flag("pause") {
+        eprintln!(
+            "process {} is ready; press Enter to start the benchmark",
+            process::id()
+        );
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+    }
+
+    for i in 0..repeat {
+        let result = ctry!(
+            run_write_bench(outpath, &options);
+            "failed to benchmark writing \"{}\"", outpath.display()
+        );
+
+        if json {
+            println!("{}", result.to_json());
+        } else {
+            rn_note!(
+                nbe,
+                "run {}: wrote {} rows with {} ({}) in {:.3} s (create {:.3} s, write {:.3} s, close {:.3} s): {:.1} MB/s",
+                i + 1,
+                result.n_rows,
+                result.pattern,
+                result.storage_manager,
+                result.total_seconds(),
+                result.create_seconds,
+                result.write_seconds,
+                result.close_seconds,
+                result.bytes_per_second() / 1e6
+            );
+        }
+
+        if i + 1 < repeat || !matches.get_flag("keep") {
+            ctry!(
+                fs::remove_dir_all(outpath);
+                "failed to remove \"{}\"", outpath.display()
+            );
+        }
+    }
+
+    Ok(0)
+}
+
+fn diff_cmd(matches: &ArgMatches, nbe: &mut dyn NotificationBackend) -> Result<i32, Error> {
+    let oldpath = matches.get_one::<PathBuf>("OLD-TABLE").unwrap();
+    let newpath = matches.get_one::<PathBuf>("NEW-TABLE").unwrap();
+
+    let open_desc = |path: &PathBuf| -> Result<_, Error> {
+        let mut table = ctry!(
+            Table::open(path, TableOpenMode::Read);
+            "failed to open \"{}\"", path.display()
+        );
+        Ok(ctry!(
+            table.table_desc();
+            "failed to read the description of \"{}\"", path.display()
+        ))
+    };
+
+    let mut old_desc = open_desc(oldpath)?;
+    let mut new_desc = open_desc(newpath)?;
+    let differences = ctry!(
+        old_desc.diff(&mut new_desc);
+        "failed to compare \"{}\" and \"{}\"", oldpath.display(), newpath.display()
+    );
+
+    for difference in &differences {
+        println!("{}", difference);
+    }
+
+    if differences.is_empty() {
+        rn_note!(nbe, "the table descriptions are identical");
+        Ok(0)
+    } else {
+        Ok(1)
+    }
+}
+
+fn fitsidi_to_ms_cmd(
+    matches: &ArgMatches,
+    nbe: &mut dyn NotificationBackend,
+) -> Result<i32, Error> {
+    let inpath = matches.get_one::<PathBuf>("IN-PATH").unwrap();
+    let outpath = matches.get_one::<PathBuf>("OUT-TABLE").unwrap();
+
+    let summary = ctry!(
+        fitsidi_to_ms(inpath, outpath);
+        "failed to convert \"{}\" into \"{}\"", inpath.display(), outpath.display()
+    );
+
+    rn_note!(
+        nbe,
+        "converted {} records into {} rows ({} antennas, {} fields, {} spectral windows) in \"{}\"",
+        summary.n_records,
+        summary.n_rows,
+        summary.n_antennas,
+        summary.n_fields,
+        summary.n_spws,
+        outpath.display()
+    );
+
+    Ok(0)
+}
+
+fn miriad_to_ms_cmd(matches: &ArgMatches, nbe: &mut dyn NotificationBackend) -> Result<i32, Error> {
+    let inpath = matches.get_one::<PathBuf>("IN-PATH").unwrap();
+    let outpath = matches.get_one::<PathBuf>("OUT-TABLE").unwrap();
+
+    let summary = ctry!(
+        miriad_to_ms(inpath, outpath);
+        "failed to convert \"{}\" into \"{}\"", inpath.display(), outpath.display()
+    );
+
+    rn_note!(
+        nbe,
+        "converted {} records into {} rows ({} antennas, {} fields, {} spectral windows) in \"{}\"",
+        summary.n_records,
+        summary.n_rows,
+        summary.n_antennas,
+        summary.n_fields,
+        summary.n_spws,
+        outpath.display()
+    );
+
+    Ok(0)
+}
+
+fn select_cmd(matches: &ArgMatches, nbe: &mut dyn NotificationBackend) -> Result<i32, Error> {
+    let inpath = matches.get_one::<PathBuf>("IN-TABLE").unwrap();
+    let arg = |name: &str| {
+        matches
+            .get_one::<String>(name)
+            .map(String::as_str)
+            .unwrap_or("")
+    };
+
+    let sel = MsSelection::new()
+        .antenna(arg("antenna"))
+        .spw(arg("spw"))
+        .field(arg("field"))
+        .scan(arg("scan"))
+        .time(arg("time"));
+
+    let mut table = ctry!(
+        Table::open(inpath, TableOpenMode::Read);
+        "failed to open \"{}\"", inpath.display()
+    );
+    let resolved = ctry!(
+        sel.resolve(&mut table);
+        "failed to resolve the selection against \"{}\"", inpath.display()
+    );
+
+    if matches.get_flag("taql") {
+        println!("{}", resolved.to_select());
+    }
+
+    let selected = ctry!(
+        table.select(&resolved.to_select());
+        "failed to select rows of \"{}\"", inpath.display()
+    );
+
+    rn_note!(
+        nbe,
+        "selected {} of {} rows",
+        selected.n_rows(),
+        table.n_rows()
+    );
+
+    for (spw, ranges) in &resolved.channels {
+        let ranges: Vec<String> = ranges
+            .iter()
+            .map(|r| format!("{}~{}", r.start, r.end - 1))
+            .collect();
+        rn_note!(
+            nbe,
+            "spectral window {}: channels {}",
+            spw,
+            ranges.join(";")
+        );
+    }
+
+    Ok(0)
+}
+
+fn shrink(matches: &ArgMatches, nbe: &mut dyn NotificationBackend) -> Result<i32, Error> {
+    let inpath = matches.get_one::<PathBuf>("IN-TABLE").unwrap();
+    let outpath = matches.get_one::<PathBuf>("OUT-TABLE").unwrap();
+
+    let options = ShrinkOptions {
+        n_timesteps: matches.get_one::<usize>("timesteps").copied(),
+        n_channels: matches.get_one::<usize>("channels").copied(),
+        zero_data: matches.get_flag("zero_data"),
+        anonymize: matches.get_flag("anonymize"),
+    };
+
+    let summary = ctry!(
+        shrink_ms(inpath, outpath, &options);
+        "failed to shrink \"{}\" into \"{}\"", inpath.display(), outpath.display()
+    );
+
+    rn_note!(
+        nbe,
+        "wrote {} of {} rows ({} timesteps) to \"{}\"",
+        summary.n_rows_out,
+        summary.n_rows_in,
+        summary.n_timesteps_out,
+        outpath.display()
+    );
+
+    Ok(0)
+}
+
+fn summary_cmd(matches: &ArgMatches, _nbe: &mut dyn NotificationBackend) -> Result<i32, Error> {
+    let inpath = matches.get_one::<PathBuf>("IN-TABLE").unwrap();
+
+    let mut table = ctry!(
+        Table::open(inpath, TableOpenMode::Read);
+        "failed to open \"{}\"", inpath.display()
+    );
+    let s = ctry!(
+        summary(&mut table);
+        "failed to summarize \"{}\"", inpath.display()
+    );
+
+    if matches.get_flag("json") {
+        print!("{}", s.to_json());
+    } else {
+        print!("{}", s);
+    }
+
+    Ok(0)
+}
@@ -0,0 +1,1437 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Image coordinate systems.
+//!
+//! casacore describes how the pixels of an image map onto world coordinates
+//! with a `CoordinateSystem`: a list of coordinates (direction, spectral,
+//! Stokes, linear), each of which covers one or more image axes. Images store
+//! this as the record written by `CoordinateSystem::save`. The
+//! [`CoordinateSystem`] type here parses that record, converts between pixel
+//! and world positions, and can write the record back out.
+//!
+//! The conventions follow casacore. Pixel positions are zero-based, direction
+//! world coordinates are in radians, spectral world coordinates are in the
+//! unit recorded for the spectral axis (usually Hz), and Stokes world
+//! coordinates are casacore `Stokes::StokesTypes` codes. Only the zenithal
+//! `SIN`, `TAN`, `ARC` and `ZEA` projections are supported for conversions.
+//!
+//! As in the rest of [`crate::casaimages`], pixel and world vectors are given
+//! in C order, so that they line up with image shapes.
+
+use ndarray::Array2;
+use rubbl_core::constants::SPEED_OF_LIGHT;
+use std::{
+    f64::consts::{FRAC_PI_2, PI},
+    str::FromStr,
+};
+use thiserror::Error;
+
+use crate::{CasacoreError, GlueDataType, TableError, TableRecord};
+
+/// A polarization product, following casacore’s `Stokes::StokesTypes`.
+pub use rubbl_core::stokes::Stokes as StokesType;
+
+/// An error that can occur when working with a coordinate system.
+#[derive(Error, Debug)]
+pub enum CoordinateError {
+    /// The coordinate record could not be accessed.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The coordinate record does not have the expected structure.
+    #[error("malformed coordinate record: {0}")]
+    Malformed(String),
+
+    /// The coordinate system uses a feature that is not supported.
+    #[error("unsupported coordinate system feature: {0}")]
+    Unsupported(String),
+
+    /// A position vector had the wrong number of elements.
+    #[error("expected {expected} coordinate values but got {actual}")]
+    AxisCount {
+        /// The number of values that were expected.
+        expected: usize,
+        /// The number of values that were provided.
+        actual: usize,
+    },
+
+    /// A position could not be converted.
+    #[error("position cannot be converted: {0}")]
+    OutOfRange(String),
+}
+
+impl From<CasacoreError> for CoordinateError {
+    fn from(e: CasacoreError) -> Self {
+        CoordinateError::Table(e.into())
+    }
+}
+
+/// A spherical projection used by a [`DirectionCoordinate`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Projection {
+    /// The orthographic projection.
+    Sin,
+    /// The gnomonic projection.
+    Tan,
+    /// The zenithal equidistant projection.
+    Arc,
+    /// The zenithal equal-area projection.
+    Zea,
+    /// Some other projection, which can be stored but not used for
+    /// conversions.
+    Other(String),
+}
+
+impl Projection {
+    /// Get the three-letter WCS code for this projection.
+    pub fn name(&self) -> &str {
+        match self {
+            Projection::Sin => "SIN",
+            Projection::Tan => "TAN",
+            Projection::Arc => "ARC",
+            Projection::Zea => "ZEA",
+            Projection::Other(s) => s.as_str(),
+        }
+    }
+
+    /// Convert intermediate world coordinates, in radians, to native
+    /// spherical coordinates `(phi, theta)`.
+    fn deproject(&self, x: f64, y: f64) -> Result<(f64, f64), CoordinateError> {
+        let r = x.hypot(y);
+        let phi = if r == 0. { 0. } else { x.atan2(-y) };
+        let beyond =
+            || CoordinateError::OutOfRange(format!("({x}, {y}) is outside the projection"));
+
+        let theta = match self {
+            Projection::Sin => {
+                if r > 1. {
+                    return Err(beyond());
+                }
+                r.acos()
+            }
+            Projection::Tan => 1f64.atan2(r),
+            Projection::Arc => FRAC_PI_2 - r,
+            Projection::Zea => {
+                if r > 2. {
+                    return Err(beyond());
+                }
+                FRAC_PI_2 - 2. * (0.5 * r).asin()
+            }
+            Projection::Other(s) => {
+                return Err(CoordinateError::Unsupported(format!("the {s} projection")))
+            }
+        };
+
+        Ok((phi, theta))
+    }
+
+    /// Convert native spherical coordinates to intermediate world
+    /// coordinates, in radians.
+    fn project(&self, phi: f64, theta: f64) -> Result<(f64, f64), CoordinateError> {
+        let r = match self {
+            Projection::Sin => {
+                if theta < 0. {
+                    return Err(CoordinateError::OutOfRange(
+                        "position is on the far side of the SIN projection".into(),
+                    ));
+                }
+                theta.cos()
+            }
+            Projection::Tan => {
+                if theta <= 0. {
+                    return Err(CoordinateError::OutOfRange(
+                        "position is on the far side of the TAN projection".into(),
+                    ));
+                }
+                theta.cos() / theta.sin()
+            }
+            Projection::Arc => FRAC_PI_2 - theta,
+            Projection::Zea => 2. * (0.5 * (FRAC_PI_2 - theta)).sin(),
+            Projection::Other(s) => {
+                return Err(CoordinateError::Unsupported(format!("the {s} projection")))
+            }
+        };
+
+        Ok((r * phi.sin(), -r * phi.cos()))
+    }
+}
+
+impl FromStr for Projection {
+    type Err = CoordinateError;
+
+    fn from_str(s: &str) -> Result<Self, CoordinateError> {
+        Ok(match s {
+            "SIN" => Projection::Sin,
+            "TAN" => Projection::Tan,
+            "ARC" => Projection::Arc,
+            "ZEA" => Projection::Zea,
+            other => Projection::Other(other.to_owned()),
+        })
+    }
+}
+
+/// A coordinate mapping two pixel axes onto a celestial sphere.
+///
+/// Positions are given in casacore axis order: longitude first, then
+/// latitude. World values are in radians.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirectionCoordinate {
+    /// The reference frame, such as `J2000` or `GALACTIC`.
+    pub system: String,
+
+    /// The projection.
+    pub projection: Projection,
+
+    /// The projection parameters, which must be zero for conversions.
+    pub projection_parameters: Vec<f64>,
+
+    /// The world coordinates of the reference pixel, in radians.
+    pub crval: [f64; 2],
+
+    /// The reference pixel, zero-based.
+    pub crpix: [f64; 2],
+
+    /// The pixel increments, in radians.
+    pub cdelt: [f64; 2],
+
+    /// The linear transformation matrix.
+    pub pc: [[f64; 2]; 2],
+
+    /// The names of the world axes.
+    pub axis_names: [String; 2],
+}
+
+impl DirectionCoordinate {
+    /// Create a new direction coordinate with no rotation.
+    pub fn new(
+        system: &str,
+        projection: Projection,
+        crval: [f64; 2],
+        crpix: [f64; 2],
+        cdelt: [f64; 2],
+    ) -> Self {
+        let projection_parameters = if projection == Projection::Sin {
+            vec![0.; 2]
+        } else {
+            Vec::new()
+        };
+
+        let axis_names = match system {
+            "GALACTIC" | "SUPERGAL" | "ECLIPTIC" | "MECLIPTIC" | "TECLIPTIC" => {
+                ["Longitude".to_owned(), "Latitude".to_owned()]
+            }
+            _ => ["Right Ascension".to_owned(), "Declination".to_owned()],
+        };
+
+        DirectionCoordinate {
+            system: system.to_owned(),
+            projection,
+            projection_parameters,
+            crval,
+            crpix,
+            cdelt,
+            pc: [[1., 0.], [0., 1.]],
+            axis_names,
+        }
+    }
+
+    /// Convert a pixel position to a `[longitude, latitude]` pair.
+    pub fn to_world(&self, pixel: [f64; 2]) -> Result<[f64; 2], CoordinateError> {
+        self.check_parameters()?;
+
+        if pixel == self.crpix {
+            return Ok(self.crval);
+        }
+
+        let dx = [pixel[0] - self.crpix[0], pixel[1] - self.crpix[1]];
+        let x = self.cdelt[0] * (self.pc[0][0] * dx[0] + self.pc[0][1] * dx[1]);
+        let y = self.cdelt[1] * (self.pc[1][0] * dx[0] + self.pc[1][1] * dx[1]);
+        let (phi, theta) = self.projection.deproject(x, y)?;
+
+        // Rotate from native to celestial coordinates. For the zenithal
+        // projections the native pole is at the reference point and the
+        // default native longitude of the celestial pole is 180°.
+        let (sin_d0, cos_d0) = self.crval[1].sin_cos();
+        let (sin_t, cos_t) = theta.sin_cos();
+        let (sin_dp, cos_dp) = (phi - PI).sin_cos();
+
+        let lon = self.crval[0] + (-cos_t * sin_dp).atan2(sin_t * cos_d0 - cos_t * sin_d0 * cos_dp);
+        let lat = (sin_t * sin_d0 + cos_t * cos_d0 * cos_dp)
+            .clamp(-1., 1.)
+            .asin();
+        Ok([lon, lat])
+    }
+
+    /// Convert a `[longitude, latitude]` pair to a pixel position.
+    pub fn to_pixel(&self, world: [f64; 2]) -> Result<[f64; 2], CoordinateError> {
+        self.check_parameters()?;
+
+        let (sin_d0, cos_d0) = self.crval[1].sin_cos();
+        let (sin_d, cos_d) = world[1].sin_cos();
+        let (sin_da, cos_da) = (world[0] - self.crval[0]).sin_cos();
+
+        let phi = PI + (-cos_d * sin_da).atan2(sin_d * cos_d0 - cos_d * sin_d0 * cos_da);
+        let theta = (sin_d * sin_d0 + cos_d * cos_d0 * cos_da)
+            .clamp(-1., 1.)
+            .asin();
+        let (x, y) = self.projection.project(phi, theta)?;
+
+        let matrix = vec![
+            vec![self.cdelt[0] * self.pc[0][0], self.cdelt[0] * self.pc[0][1]],
+            vec![self.cdelt[1] * self.pc[1][0], self.cdelt[1] * self.pc[1][1]],
+        ];
+        let dx = solve(matrix, vec![x, y])?;
+        Ok([self.crpix[0] + dx[0], self.crpix[1] + dx[1]])
+    }
+
+    fn check_parameters(&self) -> Result<(), CoordinateError> {
+        if self.projection_parameters.iter().any(|p| *p != 0.) {
+            return Err(CoordinateError::Unsupported(format!(
+                "{} projection parameters {:?}",
+                self.projection.name(),
+                self.projection_parameters
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn from_record(rec: &mut TableRecord) -> Result<Self, CoordinateError> {
+        let units = get_strings(rec, "units")?;
+        let scale = |i: usize| match units.get(i).map(|s| s.as_str()) {
+            Some("rad") | None => Ok(1.),
+            Some("deg") => Ok(PI / 180.),
+            Some("arcmin") => Ok(PI / (180. * 60.)),
+            Some("arcsec") => Ok(PI / (180. * 3600.)),
+            Some(other) => Err(CoordinateError::Unsupported(format!(
+                "direction axis unit \"{other}\""
+            ))),
+        };
+        let scale = [scale(0)?, scale(1)?];
+
+        let crval = get_pair(rec, "crval")?;
+        let cdelt = get_pair(rec, "cdelt")?;
+        let pc = get_matrix(rec, "pc", 2)?;
+        let names = get_strings(rec, "axes")?;
+
+        if names.len() != 2 {
+            return Err(CoordinateError::Malformed(
+                "direction coordinate does not have two axes".into(),
+            ));
+        }
+
+        Ok(DirectionCoordinate {
+            system: get_string(rec, "system")?,
+            projection: get_string(rec, "projection")?.parse()?,
+            projection_parameters: if has_field(rec, "projection_parameters")? {
+                get_f64s(rec, "projection_parameters")?
+            } else {
+                Vec::new()
+            },
+            crval: [crval[0] * scale[0], crval[1] * scale[1]],
+            crpix: get_pair(rec, "crpix")?,
+            cdelt: [cdelt[0] * scale[0], cdelt[1] * scale[1]],
+            pc: [[pc[0][0], pc[0][1]], [pc[1][0], pc[1][1]]],
+            axis_names: [names[0].clone(), names[1].clone()],
+        })
+    }
+
+    fn to_record(&self) -> Result<TableRecord, CoordinateError> {
+        let mut rec = TableRecord::new()?;
+        rec.put_field("system", &self.system)?;
+        rec.put_field("projection", &self.projection.name().to_owned())?;
+        rec.put_field("projection_parameters", &self.projection_parameters)?;
+        rec.put_field("crval", &self.crval.to_vec())?;
+        rec.put_field("crpix", &self.crpix.to_vec())?;
+        rec.put_field("cdelt", &self.cdelt.to_vec())?;
+        rec.put_field(
+            "pc",
+            &matrix_to_array(&[self.pc[0].to_vec(), self.pc[1].to_vec()]),
+        )?;
+        rec.put_field("axes", &self.axis_names.to_vec())?;
+        rec.put_field("units", &vec!["rad".to_owned(); 2])?;
+        rec.put_field("conversionSystem", &self.system)?;
+        Ok(rec)
+    }
+}
+
+/// The mapping between pixels and world values along a spectral axis.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpectralAxis {
+    /// A linear mapping.
+    Linear {
+        /// The world value of the reference pixel.
+        crval: f64,
+        /// The reference pixel, zero-based.
+        crpix: f64,
+        /// The world increment per pixel.
+        cdelt: f64,
+    },
+
+    /// A lookup table with at least two entries, interpolated linearly and
+    /// extrapolated from the end points.
+    Tabular {
+        /// The pixel positions of the table entries.
+        pixels: Vec<f64>,
+        /// The world values of the table entries.
+        worlds: Vec<f64>,
+    },
+}
+
+/// A coordinate mapping one pixel axis onto frequency.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpectralCoordinate {
+    /// The frequency reference frame, such as `LSRK` or `TOPO`.
+    pub system: String,
+
+    /// The rest frequency of the line of interest, in Hz, or zero if it is
+    /// not known.
+    pub rest_frequency: f64,
+
+    /// The unit of the world values, such as `Hz`.
+    pub unit: String,
+
+    /// The pixel-to-world mapping.
+    pub axis: SpectralAxis,
+}
+
+impl SpectralCoordinate {
+    /// Create a new linear spectral coordinate with world values in Hz.
+    pub fn new(system: &str, crval: f64, crpix: f64, cdelt: f64, rest_frequency: f64) -> Self {
+        SpectralCoordinate {
+            system: system.to_owned(),
+            rest_frequency,
+            unit: "Hz".to_owned(),
+            axis: SpectralAxis::Linear {
+                crval,
+                crpix,
+                cdelt,
+            },
+        }
+    }
+
+    /// Convert a pixel position to a world value, in the coordinate’s unit.
+    pub fn to_world(&self, pixel: f64) -> f64 {
+        match &self.axis {
+            SpectralAxis::Linear {
+                crval,
+                crpix,
+                cdelt,
+            } => crval + cdelt * (pixel - crpix),
+            SpectralAxis::Tabular { pixels, worlds } => interpolate(pixels, worlds, pixel),
+        }
+    }
+
+    /// Convert a world value, in the coordinate’s unit, to a pixel position.
+    pub fn to_pixel(&self, world: f64) -> Result<f64, CoordinateError> {
+        match &self.axis {
+            SpectralAxis::Linear {
+                crval,
+                crpix,
+                cdelt,
+            } => {
+                if *cdelt == 0. {
+                    return Err(CoordinateError::OutOfRange(
+                        "the spectral axis has a zero increment".into(),
+                    ));
+                }
+                Ok(crpix + (world - crval) / cdelt)
+            }
+            SpectralAxis::Tabular { pixels, worlds } => Ok(interpolate(worlds, pixels, world)),
+        }
+    }
+
+    /// Get the frequency of a pixel position, in Hz.
+    pub fn frequency(&self, pixel: f64) -> Result<f64, CoordinateError> {
+        Ok(self.to_world(pixel) * frequency_unit_scale(&self.unit)?)
+    }
+
+    /// Get the frequencies of the centers of the first *n* pixels, in Hz.
+    pub fn frequencies(&self, n: usize) -> Result<Vec<f64>, CoordinateError> {
+        (0..n).map(|i| self.frequency(i as f64)).collect()
+    }
+
+    /// Get the radio-convention velocity of a pixel position relative to the
+    /// rest frequency, in meters per second.
+    ///
+    /// Returns `None` if no rest frequency is defined.
+    pub fn radio_velocity(&self, pixel: f64) -> Result<Option<f64>, CoordinateError> {
+        if self.rest_frequency <= 0. {
+            return Ok(None);
+        }
+
+        let nu = self.frequency(pixel)?;
+        Ok(Some(SPEED_OF_LIGHT * (1. - nu / self.rest_frequency)))
+    }
+
+    fn from_record(rec: &mut TableRecord) -> Result<Self, CoordinateError> {
+        let rest_frequency = if has_field(rec, "restfreq")? {
+            get_f64(rec, "restfreq")?
+        } else {
+            0.
+        };
+
+        let unit = if has_field(rec, "unit")? {
+            get_string(rec, "unit")?
+        } else {
+            "Hz".to_owned()
+        };
+
+        let axis = if has_field(rec, "tabular")? {
+            let mut tab: TableRecord = rec.get_field("tabular")?;
+            let pixels = get_f64s(&mut tab, "pixelvalues")?;
+            let worlds = get_f64s(&mut tab, "worldvalues")?;
+
+            if pixels.len() != worlds.len() {
+                return Err(CoordinateError::Malformed(
+                    "spectral table has mismatched pixel and world values".into(),
+                ));
+            }
+
+            if pixels.len() > 1 {
+                SpectralAxis::Tabular { pixels, worlds }
+            } else {
+                linear_spectral_axis(&mut tab)?
+            }
+        } else if has_field(rec, "wcs")? {
+            let mut wcs: TableRecord = rec.get_field("wcs")?;
+            linear_spectral_axis(&mut wcs)?
+        } else {
+            return Err(CoordinateError::Malformed(
+                "spectral coordinate has neither \"wcs\" nor \"tabular\" parameters".into(),
+            ));
+        };
+
+        Ok(SpectralCoordinate {
+            system: get_string(rec, "system")?,
+            rest_frequency,
+            unit,
+            axis,
+        })
+    }
+
+    fn to_record(&self) -> Result<TableRecord, CoordinateError> {
+        let mut rec = TableRecord::new()?;
+        rec.put_field("version", &2i32)?;
+        rec.put_field("system", &self.system)?;
+        rec.put_field("restfreq", &self.rest_frequency)?;
+        rec.put_field("restfreqs", &vec![self.rest_frequency])?;
+        rec.put_field("velType", &0i32)?;
+        rec.put_field("nativeType", &0i32)?;
+        rec.put_field("velUnit", &"km/s".to_owned())?;
+        rec.put_field("waveUnit", &"mm".to_owned())?;
+        rec.put_field("formatUnit", &String::new())?;
+
+        match &self.axis {
+            SpectralAxis::Linear {
+                crval,
+                crpix,
+                cdelt,
+            } => {
+                let mut wcs = TableRecord::new()?;
+                wcs.put_field("crval", crval)?;
+                wcs.put_field("crpix", crpix)?;
+                wcs.put_field("cdelt", cdelt)?;
+                wcs.put_field("pc", &1f64)?;
+                wcs.put_field("ctype", &"FREQ".to_owned())?;
+                rec.put_field("wcs", &wcs)?;
+            }
+
+            SpectralAxis::Tabular { pixels, worlds } => {
+                let cdelt =
+                    (worlds[worlds.len() - 1] - worlds[0]) / (pixels[pixels.len() - 1] - pixels[0]);
+                let mut tab = TableRecord::new()?;
+                tab.put_field("crval", &vec![worlds[0]])?;
+                tab.put_field("crpix", &vec![pixels[0]])?;
+                tab.put_field("cdelt", &vec![cdelt])?;
+                tab.put_field("pc", &matrix_to_array(&[vec![1.]]))?;
+                tab.put_field("axes", &vec!["Frequency".to_owned()])?;
+                tab.put_field("units", &vec![self.unit.clone()])?;
+                tab.put_field("pixelvalues", pixels)?;
+                tab.put_field("worldvalues", worlds)?;
+                rec.put_field("tabular", &tab)?;
+            }
+        }
+
+        rec.put_field("unit", &self.unit)?;
+        rec.put_field("name", &"Frequency".to_owned())?;
+        Ok(rec)
+    }
+}
+
+fn linear_spectral_axis(rec: &mut TableRecord) -> Result<SpectralAxis, CoordinateError> {
+    let pc = if has_field(rec, "pc")? {
+        get_f64s_any_shape(rec, "pc")?
+            .first()
+            .copied()
+            .unwrap_or(1.)
+    } else {
+        1.
+    };
+
+    Ok(SpectralAxis::Linear {
+        crval: get_f64(rec, "crval")?,
+        crpix: get_f64(rec, "crpix")?,
+        cdelt: get_f64(rec, "cdelt")? * pc,
+    })
+}
+
+/// Get the factor that converts a frequency unit to Hz.
+fn frequency_unit_scale(unit: &str) -> Result<f64, CoordinateError> {
+    match unit {
+        "Hz" => Ok(1.),
+        "kHz" => Ok(1e3),
+        "MHz" => Ok(1e6),
+        "GHz" => Ok(1e9),
+        other => Err(CoordinateError::Unsupported(format!(
+            "spectral axis unit \"{other}\""
+        ))),
+    }
+}
+
+/// A coordinate mapping one pixel axis onto a list of polarization products.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StokesCoordinate {
+    /// The polarization product of each pixel along the axis.
+    pub stokes: Vec<StokesType>,
+}
+
+impl StokesCoordinate {
+    /// Create a new Stokes coordinate.
+    pub fn new(stokes: Vec<StokesType>) -> Self {
+        StokesCoordinate { stokes }
+    }
+
+    /// Get the polarization product at a pixel position.
+    pub fn to_world(&self, pixel: f64) -> Result<StokesType, CoordinateError> {
+        let index = pixel.round();
+
+        if index < 0. || index as usize >= self.stokes.len() {
+            return Err(CoordinateError::OutOfRange(format!(
+                "pixel {pixel} is outside the Stokes axis"
+            )));
+        }
+
+        Ok(self.stokes[index as usize])
+    }
+
+    /// Get the pixel position of a polarization product.
+    pub fn to_pixel(&self, stokes: StokesType) -> Result<usize, CoordinateError> {
+        self.stokes
+            .iter()
+            .position(|s| *s == stokes)
+            .ok_or_else(|| {
+                CoordinateError::OutOfRange(format!("the image has no Stokes {stokes} plane"))
+            })
+    }
+
+    fn from_record(rec: &mut TableRecord) -> Result<Self, CoordinateError> {
+        let stokes = get_strings(rec, "stokes")?
+            .iter()
+            .map(|s| s.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|e: rubbl_core::stokes::StokesError| {
+                CoordinateError::Malformed(e.to_string())
+            })?;
+        Ok(StokesCoordinate { stokes })
+    }
+
+    fn to_record(&self) -> Result<TableRecord, CoordinateError> {
+        let names: Vec<String> = self.stokes.iter().map(|s| s.name().to_owned()).collect();
+        let crval = self.stokes.first().map(|s| s.code() as f64).unwrap_or(1.);
+
+        let mut rec = TableRecord::new()?;
+        rec.put_field("axes", &vec!["Stokes".to_owned()])?;
+        rec.put_field("stokes", &names)?;
+        rec.put_field("crval", &vec![crval])?;
+        rec.put_field("crpix", &vec![0f64])?;
+        rec.put_field("cdelt", &vec![1f64])?;
+        rec.put_field("pc", &matrix_to_array(&[vec![1.]]))?;
+        rec.put_field("units", &vec![String::new()])?;
+        Ok(rec)
+    }
+}
+
+/// A coordinate mapping pixel axes linearly onto world axes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearCoordinate {
+    /// The names of the world axes.
+    pub names: Vec<String>,
+    /// The units of the world axes.
+    pub units: Vec<String>,
+    /// The world coordinates of the reference pixel.
+    pub crval: Vec<f64>,
+    /// The reference pixel, zero-based.
+    pub crpix: Vec<f64>,
+    /// The world increments per pixel.
+    pub cdelt: Vec<f64>,
+    /// The linear transformation matrix, indexed as `pc[world][pixel]`.
+    pub pc: Vec<Vec<f64>>,
+}
+
+impl LinearCoordinate {
+    /// Convert a pixel position to a world position.
+    pub fn to_world(&self, pixel: &[f64]) -> Vec<f64> {
+        (0..self.crval.len())
+            .map(|i| {
+                let offset: f64 = (0..self.crpix.len())
+                    .map(|j| self.pc[i][j] * (pixel[j] - self.crpix[j]))
+                    .sum();
+                self.crval[i] + self.cdelt[i] * offset
+            })
+            .collect()
+    }
+
+    /// Convert a world position to a pixel position.
+    pub fn to_pixel(&self, world: &[f64]) -> Result<Vec<f64>, CoordinateError> {
+        let matrix = self
+            .pc
+            .iter()
+            .zip(&self.cdelt)
+            .map(|(row, d)| row.iter().map(|v| v * d).collect())
+            .collect();
+        let rhs = world.iter().zip(&self.crval).map(|(w, v)| w - v).collect();
+        let dx = solve(matrix, rhs)?;
+        Ok(dx.iter().zip(&self.crpix).map(|(d, p)| d + p).collect())
+    }
+
+    fn from_record(rec: &mut TableRecord) -> Result<Self, CoordinateError> {
+        let crval = get_f64s(rec, "crval")?;
+        let n = crval.len();
+        let coord = LinearCoordinate {
+            names: get_strings(rec, "axes")?,
+            units: get_strings(rec, "units")?,
+            crval,
+            crpix: get_f64s(rec, "crpix")?,
+            cdelt: get_f64s(rec, "cdelt")?,
+            pc: get_matrix(rec, "pc", n)?,
+        };
+
+        if coord.crpix.len() != n || coord.cdelt.len() != n {
+            return Err(CoordinateError::Malformed(
+                "linear coordinate parameters have inconsistent sizes".into(),
+            ));
+        }
+
+        Ok(coord)
+    }
+
+    fn to_record(&self) -> Result<TableRecord, CoordinateError> {
+        let mut rec = TableRecord::new()?;
+        rec.put_field("axes", &self.names)?;
+        rec.put_field("units", &self.units)?;
+        rec.put_field("crval", &self.crval)?;
+        rec.put_field("crpix", &self.crpix)?;
+        rec.put_field("cdelt", &self.cdelt)?;
+        rec.put_field("pc", &matrix_to_array(&self.pc))?;
+        Ok(rec)
+    }
+}
+
+/// One coordinate of a [`CoordinateSystem`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Coordinate {
+    /// A direction on the sky.
+    Direction(DirectionCoordinate),
+    /// A frequency.
+    Spectral(SpectralCoordinate),
+    /// A polarization product.
+    Stokes(StokesCoordinate),
+    /// A generic linear coordinate.
+    Linear(LinearCoordinate),
+}
+
+impl Coordinate {
+    /// Get the number of axes covered by this coordinate.
+    pub fn n_axes(&self) -> usize {
+        match self {
+            Coordinate::Direction(_) => 2,
+            Coordinate::Spectral(_) | Coordinate::Stokes(_) => 1,
+            Coordinate::Linear(c) => c.crval.len(),
+        }
+    }
+
+    /// Get the casacore name for this kind of coordinate.
+    fn kind(&self) -> &'static str {
+        match self {
+            Coordinate::Direction(_) => "direction",
+            Coordinate::Spectral(_) => "spectral",
+            Coordinate::Stokes(_) => "stokes",
+            Coordinate::Linear(_) => "linear",
+        }
+    }
+
+    fn to_world(&self, pixel: &[f64]) -> Result<Vec<f64>, CoordinateError> {
+        Ok(match self {
+            Coordinate::Direction(c) => c.to_world([pixel[0], pixel[1]])?.to_vec(),
+            Coordinate::Spectral(c) => vec![c.to_world(pixel[0])],
+            Coordinate::Stokes(c) => vec![c.to_world(pixel[0])?.code() as f64],
+            Coordinate::Linear(c) => c.to_world(pixel),
+        })
+    }
+
+    fn to_pixel(&self, world: &[f64]) -> Result<Vec<f64>, CoordinateError> {
+        Ok(match self {
+            Coordinate::Direction(c) => c.to_pixel([world[0], world[1]])?.to_vec(),
+            Coordinate::Spectral(c) => vec![c.to_pixel(world[0])?],
+            Coordinate::Stokes(c) => {
+                let stokes = StokesType::from_code(world[0].round() as i32).ok_or_else(|| {
+                    CoordinateError::OutOfRange(format!("{} is not a Stokes code", world[0]))
+                })?;
+                vec![c.to_pixel(stokes)? as f64]
+            }
+            Coordinate::Linear(c) => c.to_pixel(world)?,
+        })
+    }
+
+    fn to_record(&self) -> Result<TableRecord, CoordinateError> {
+        match self {
+            Coordinate::Direction(c) => c.to_record(),
+            Coordinate::Spectral(c) => c.to_record(),
+            Coordinate::Stokes(c) => c.to_record(),
+            Coordinate::Linear(c) => c.to_record(),
+        }
+    }
+}
+
+/// A coordinate along with the system axes that it covers.
+#[derive(Clone, Debug, PartialEq)]
+struct CoordinateEntry {
+    coord: Coordinate,
+    /// The casacore-order system world axis of each coordinate world axis,
+    /// or `None` if it has been removed.
+    world_axes: Vec<Option<usize>>,
+    /// The values used for removed world axes.
+    world_replace: Vec<f64>,
+    /// The casacore-order system pixel axis of each coordinate pixel axis,
+    /// or `None` if it has been removed.
+    pixel_axes: Vec<Option<usize>>,
+    /// The values used for removed pixel axes.
+    pixel_replace: Vec<f64>,
+}
+
+/// The coordinate system of an image.
+///
+/// # Example
+///
+/// ```rust
+/// use rubbl_casatables::casaimages::coordinates::{
+///     CoordinateSystem, DirectionCoordinate, Projection, SpectralCoordinate,
+///     StokesCoordinate, StokesType,
+/// };
+///
+/// let mut cs = CoordinateSystem::new();
+/// cs.add_coordinate(DirectionCoordinate::new(
+///     "J2000",
+///     Projection::Sin,
+///     [1.0, -0.5],
+///     [16., 16.],
+///     [-1e-5, 1e-5],
+/// ).into());
+/// cs.add_coordinate(StokesCoordinate::new(vec![StokesType::I]).into());
+/// cs.add_coordinate(SpectralCoordinate::new("LSRK", 1.4e9, 0., 1e6, 0.).into());
+///
+/// // Axes are in C order: frequency, Stokes, declination, right ascension.
+/// let world = cs.to_world(&[2., 0., 16., 16.]).unwrap();
+/// assert_eq!(world, vec![1.402e9, 1., -0.5, 1.0]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CoordinateSystem {
+    entries: Vec<CoordinateEntry>,
+    n_pixel_axes: usize,
+    n_world_axes: usize,
+
+    /// The name of the telescope, or an empty string if it is unknown.
+    pub telescope: String,
+
+    /// The name of the observer, or an empty string if it is unknown.
+    pub observer: String,
+
+    obsdate: Option<TableRecord>,
+}
+
+impl CoordinateSystem {
+    /// Create a new coordinate system with no coordinates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a coordinate to the system.
+    ///
+    /// As with casacore, the new coordinate’s axes are appended in casacore
+    /// order, which means that in C order they are *prepended*. The first
+    /// coordinate added therefore covers the last, fastest-varying, image
+    /// axes.
+    pub fn add_coordinate(&mut self, coord: Coordinate) {
+        let n = coord.n_axes();
+        let axes: Vec<Option<usize>> = (self.n_pixel_axes..self.n_pixel_axes + n)
+            .map(Some)
+            .collect();
+
+        self.entries.push(CoordinateEntry {
+            coord,
+            world_axes: axes.clone(),
+            world_replace: vec![0.; n],
+            pixel_axes: axes,
+            pixel_replace: vec![0.; n],
+        });
+        self.n_pixel_axes += n;
+        self.n_world_axes += n;
+    }
+
+    /// Parse a coordinate system from the record written by casacore’s
+    /// `CoordinateSystem::save` method.
+    pub fn from_record(rec: &mut TableRecord) -> Result<Self, CoordinateError> {
+        let mut found = Vec::new();
+
+        for name in rec.keyword_names()? {
+            let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+
+            let index: usize = match name[prefix.len()..].parse() {
+                Ok(i) => i,
+                Err(_) => continue,
+            };
+
+            match prefix {
+                "direction" | "spectral" | "stokes" | "linear" => {}
+                "tabular" | "quality" => {
+                    return Err(CoordinateError::Unsupported(format!(
+                        "{prefix} coordinates"
+                    )))
+                }
+                _ => continue,
+            }
+
+            found.push((index, name));
+        }
+
+        found.sort();
+        let mut cs = CoordinateSystem::new();
+
+        for (i, (index, name)) in found.into_iter().enumerate() {
+            if index != i {
+                return Err(CoordinateError::Malformed(format!(
+                    "coordinate {i} is missing"
+                )));
+            }
+
+            let mut sub: TableRecord = rec.get_field(&name)?;
+
+            let coord = if name.starts_with("direction") {
+                Coordinate::Direction(DirectionCoordinate::from_record(&mut sub)?)
+            } else if name.starts_with("spectral") {
+                Coordinate::Spectral(SpectralCoordinate::from_record(&mut sub)?)
+            } else if name.starts_with("stokes") {
+                Coordinate::Stokes(StokesCoordinate::from_record(&mut sub)?)
+            } else {
+                Coordinate::Linear(LinearCoordinate::from_record(&mut sub)?)
+            };
+
+            let n = coord.n_axes();
+            let world_axes = get_axis_map(rec, &format!("worldmap{i}"), n)?;
+            let pixel_axes = get_axis_map(rec, &format!("pixelmap{i}"), n)?;
+            let world_replace = get_replacements(rec, &format!("worldreplace{i}"), n)?;
+            let pixel_replace = get_replacements(rec, &format!("pixelreplace{i}"), n)?;
+
+            for a in world_axes.iter().flatten() {
+                cs.n_world_axes = cs.n_world_axes.max(a + 1);
+            }
+
+            for a in pixel_axes.iter().flatten() {
+                cs.n_pixel_axes = cs.n_pixel_axes.max(a + 1);
+            }
+
+            cs.entries.push(CoordinateEntry {
+                coord,
+                world_axes,
+                world_replace,
+                pixel_axes,
+                pixel_replace,
+            });
+        }
+
+        if has_field(rec, "telescope")? {
+            cs.telescope = get_string(rec, "telescope")?;
+        }
+
+        if has_field(rec, "observer")? {
+            cs.observer = get_string(rec, "observer")?;
+        }
+
+        if has_field(rec, "obsdate")? {
+            cs.obsdate = Some(rec.get_field("obsdate")?);
+        }
+
+        Ok(cs)
+    }
+
+    /// Serialize this coordinate system into the record format used by
+    /// casacore’s `CoordinateSystem::save` method.
+    ///
+    /// The result can be passed to [`super::Image::create`].
+    pub fn to_record(&self) -> Result<TableRecord, CoordinateError> {
+        let mut rec = TableRecord::new()?;
+        let to_map = |axes: &[Option<usize>]| -> Vec<i32> {
+            axes.iter()
+                .map(|a| a.map(|a| a as i32).unwrap_or(-1))
+                .collect()
+        };
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            rec.put_field(
+                &format!("{}{}", entry.coord.kind(), i),
+                &entry.coord.to_record()?,
+            )?;
+            rec.put_field(&format!("worldmap{i}"), &to_map(&entry.world_axes))?;
+            rec.put_field(&format!("worldreplace{i}"), &entry.world_replace)?;
+            rec.put_field(&format!("pixelmap{i}"), &to_map(&entry.pixel_axes))?;
+            rec.put_field(&format!("pixelreplace{i}"), &entry.pixel_replace)?;
+        }
+
+        rec.put_field("telescope", &self.telescope)?;
+        rec.put_field("observer", &self.observer)?;
+
+        if let Some(obsdate) = &self.obsdate {
+            rec.put_field("obsdate", obsdate)?;
+        }
+
+        Ok(rec)
+    }
+
+    /// Get the number of pixel axes.
+    pub fn n_pixel_axes(&self) -> usize {
+        self.n_pixel_axes
+    }
+
+    /// Get the number of world axes.
+    pub fn n_world_axes(&self) -> usize {
+        self.n_world_axes
+    }
+
+    /// Iterate over the coordinates of the system, along with the C-order
+    /// pixel axis covered by each of their axes.
+    ///
+    /// An axis is `None` if it has been removed from the system.
+    pub fn coordinates(&self) -> impl Iterator<Item = (&Coordinate, Vec<Option<usize>>)> {
+        self.entries.iter().map(move |e| {
+            let axes = e
+                .pixel_axes
+                .iter()
+                .map(|a| a.map(|a| self.n_pixel_axes - 1 - a))
+                .collect();
+            (&e.coord, axes)
+        })
+    }
+
+    /// Get the first direction coordinate of the system, along with its
+    /// C-order longitude and latitude pixel axes.
+    pub fn direction(&self) -> Option<(&DirectionCoordinate, Vec<Option<usize>>)> {
+        self.coordinates().find_map(|(c, axes)| match c {
+            Coordinate::Direction(d) => Some((d, axes)),
+            _ => None,
+        })
+    }
+
+    /// Get the first spectral coordinate of the system, along with its
+    /// C-order pixel axis.
+    pub fn spectral(&self) -> Option<(&SpectralCoordinate, Option<usize>)> {
+        self.coordinates().find_map(|(c, axes)| match c {
+            Coordinate::Spectral(s) => Some((s, axes[0])),
+            _ => None,
+        })
+    }
+
+    /// Get the first Stokes coordinate of the system, along with its C-order
+    /// pixel axis.
+    pub fn stokes(&self) -> Option<(&StokesCoordinate, Option<usize>)> {
+        self.coordinates().find_map(|(c, axes)| match c {
+            Coordinate::Stokes(s) => Some((s, axes[0])),
+            _ => None,
+        })
+    }
+
+    /// Convert a C-order pixel position to a C-order world position.
+    pub fn to_world(&self, pixel: &[f64]) -> Result<Vec<f64>, CoordinateError> {
+        self.convert(pixel, self.n_pixel_axes, self.n_world_axes, true)
+    }
+
+    /// Convert a C-order world position to a C-order pixel position.
+    pub fn to_pixel(&self, world: &[f64]) -> Result<Vec<f64>, CoordinateError> {
+        self.convert(world, self.n_world_axes, self.n_pixel_axes, false)
+    }
+
+    fn convert(
+        &self,
+        input: &[f64],
+        n_in: usize,
+        n_out: usize,
+        to_world: bool,
+    ) -> Result<Vec<f64>, CoordinateError> {
+        if input.len() != n_in {
+            return Err(CoordinateError::AxisCount {
+                expected: n_in,
+                actual: input.len(),
+            });
+        }
+
+        let mut output = vec![0.; n_out];
+
+        for e in &self.entries {
+            let (in_axes, in_replace, out_axes) = if to_world {
+                (&e.pixel_axes, &e.pixel_replace, &e.world_axes)
+            } else {
+                (&e.world_axes, &e.world_replace, &e.pixel_axes)
+            };
+
+            let values: Vec<f64> = in_axes
+                .iter()
+                .zip(in_replace)
+                .map(|(a, r)| a.map(|a| input[n_in - 1 - a]).unwrap_or(*r))
+                .collect();
+
+            let result = if to_world {
+                e.coord.to_world(&values)?
+            } else {
+                e.coord.to_pixel(&values)?
+            };
+
+            for (a, v) in out_axes.iter().zip(result) {
+                if let Some(a) = a {
+                    output[n_out - 1 - a] = v;
+                }
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+impl From<DirectionCoordinate> for Coordinate {
+    fn from(c: DirectionCoordinate) -> Self {
+        Coordinate::Direction(c)
+    }
+}
+
+impl From<SpectralCoordinate> for Coordinate {
+    fn from(c: SpectralCoordinate) -> Self {
+        Coordinate::Spectral(c)
+    }
+}
+
+impl From<StokesCoordinate> for Coordinate {
+    fn from(c: StokesCoordinate) -> Self {
+        Coordinate::Stokes(c)
+    }
+}
+
+impl From<LinearCoordinate> for Coordinate {
+    fn from(c: LinearCoordinate) -> Self {
+        Coordinate::Linear(c)
+    }
+}
+
+// Record access helpers. casacore is not always consistent about whether
+// single values are saved as scalars or one-element vectors, so these accept
+// either.
+
+fn field_type(rec: &mut TableRecord, name: &str) -> Result<Option<GlueDataType>, CoordinateError> {
+    Ok(rec
+        .keyword_names_types_reprs()?
+        .into_iter()
+        .find(|(n, _, _)| n == name)
+        .map(|(_, t, _)| t))
+}
+
+fn has_field(rec: &mut TableRecord, name: &str) -> Result<bool, CoordinateError> {
+    Ok(field_type(rec, name)?.is_some())
+}
+
+fn missing(name: &str) -> CoordinateError {
+    CoordinateError::Malformed(format!("missing or mistyped field \"{name}\""))
+}
+
+fn get_string(rec: &mut TableRecord, name: &str) -> Result<String, CoordinateError> {
+    match field_type(rec, name)? {
+        Some(GlueDataType::TpString) => Ok(rec.get_field(name)?),
+        _ => Err(missing(name)),
+    }
+}
+
+fn get_strings(rec: &mut TableRecord, name: &str) -> Result<Vec<String>, CoordinateError> {
+    match field_type(rec, name)? {
+        Some(GlueDataType::TpString) => Ok(vec![rec.get_field(name)?]),
+        Some(GlueDataType::TpArrayString) => Ok(rec.get_field(name)?),
+        _ => Err(missing(name)),
+    }
+}
+
+fn get_f64s(rec: &mut TableRecord, name: &str) -> Result<Vec<f64>, CoordinateError> {
+    match field_type(rec, name)? {
+        Some(GlueDataType::TpDouble) => Ok(vec![rec.get_field(name)?]),
+        Some(GlueDataType::TpArrayDouble) => Ok(rec.get_field(name)?),
+        Some(GlueDataType::TpFloat) => Ok(vec![rec.get_field::<f32>(name)? as f64]),
+        Some(GlueDataType::TpArrayFloat) => Ok(rec
+            .get_field::<Vec<f32>>(name)?
+            .into_iter()
+            .map(|v| v as f64)
+            .collect()),
+        _ => Err(missing(name)),
+    }
+}
+
+/// Get a numeric field that may be a scalar, a vector, or a matrix, in
+/// casacore storage order.
+fn get_f64s_any_shape(rec: &mut TableRecord, name: &str) -> Result<Vec<f64>, CoordinateError> {
+    match get_f64s(rec, name) {
+        Ok(v) => Ok(v),
+        Err(_) => Ok(rec
+            .get_field::<Array2<f64>>(name)?
+            .iter()
+            .copied()
+            .collect()),
+    }
+}
+
+fn get_f64(rec: &mut TableRecord, name: &str) -> Result<f64, CoordinateError> {
+    get_f64s(rec, name)?
+        .first()
+        .copied()
+        .ok_or_else(|| missing(name))
+}
+
+fn get_pair(rec: &mut TableRecord, name: &str) -> Result<[f64; 2], CoordinateError> {
+    match &get_f64s(rec, name)?[..] {
+        [a, b] => Ok([*a, *b]),
+        _ => Err(CoordinateError::Malformed(format!(
+            "field \"{name}\" does not have two elements"
+        ))),
+    }
+}
+
+/// Get a square matrix, indexed as `m[row][column]` in casacore terms.
+fn get_matrix(
+    rec: &mut TableRecord,
+    name: &str,
+    n: usize,
+) -> Result<Vec<Vec<f64>>, CoordinateError> {
+    // Because shapes are reversed on the way out of casacore, the array that
+    // we get back is the transpose of the casacore matrix.
+    let values = get_f64s_any_shape(rec, name)?;
+
+    if values.len() != n * n {
+        return Err(CoordinateError::Malformed(format!(
+            "field \"{name}\" is not a {n}×{n} matrix"
+        )));
+    }
+
+    Ok((0..n)
+        .map(|i| (0..n).map(|j| values[j * n + i]).collect())
+        .collect())
+}
+
+/// Convert a matrix indexed as `m[row][column]` into an array that will be
+/// stored in casacore with the same indexing.
+fn matrix_to_array(m: &[Vec<f64>]) -> Array2<f64> {
+    let n = m.len();
+    Array2::from_shape_fn((n, n), |(a, b)| m[b][a])
+}
+
+fn get_axis_map(
+    rec: &mut TableRecord,
+    name: &str,
+    n: usize,
+) -> Result<Vec<Option<usize>>, CoordinateError> {
+    let map: Vec<i32> = match field_type(rec, name)? {
+        Some(GlueDataType::TpArrayInt) => rec.get_field(name)?,
+        _ => return Err(missing(name)),
+    };
+
+    if map.len() != n {
+        return Err(CoordinateError::Malformed(format!(
+            "field \"{name}\" has {} entries but the coordinate has {n} axes",
+            map.len()
+        )));
+    }
+
+    Ok(map
+        .into_iter()
+        .map(|a| if a < 0 { None } else { Some(a as usize) })
+        .collect())
+}
+
+fn get_replacements(
+    rec: &mut TableRecord,
+    name: &str,
+    n: usize,
+) -> Result<Vec<f64>, CoordinateError> {
+    if !has_field(rec, name)? {
+        return Ok(vec![0.; n]);
+    }
+
+    let mut values = get_f64s(rec, name)?;
+    values.resize(n, 0.);
+    Ok(values)
+}
+
+/// Solve the linear system `m x = b` by Gaussian elimination with partial
+/// pivoting.
+fn solve(mut m: Vec<Vec<f64>>, mut b: Vec<f64>) -> Result<Vec<f64>, CoordinateError> {
+    let n = b.len();
+
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|i, j| m[*i][col].abs().total_cmp(&m[*j][col].abs()))
+            .unwrap();
+
+        if m[pivot][col] == 0. {
+            return Err(CoordinateError::OutOfRange(
+                "the coordinate transformation is singular".into(),
+            ));
+        }
+
+        m.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let pivot_row = m[col].clone();
+
+        for row in col + 1..n {
+            let f = m[row][col] / pivot_row[col];
+
+            for k in col..n {
+                m[row][k] -= f * pivot_row[k];
+            }
+
+            b[row] -= f * b[col];
+        }
+    }
+
+    let mut x = vec![0.; n];
+
+    for row in (0..n).rev() {
+        let s: f64 = (row + 1..n).map(|k| m[row][k] * x[k]).sum();
+        x[row] = (b[row] - s) / m[row][row];
+    }
+
+    Ok(x)
+}
+
+/// Linearly interpolate `ys` as a function of `xs`, extrapolating from the
+/// end segments. `xs` may be increasing or decreasing.
+fn interpolate(xs: &[f64], ys: &[f64], x: f64) -> f64 {
+    let n = xs.len();
+    let increasing = xs[n - 1] >= xs[0];
+
+    let mut i = 0;
+
+    while i + 2 < n && ((x > xs[i + 1]) == increasing) {
+        i += 1;
+    }
+
+    let (x0, x1, y0, y1) = (xs[i], xs[i + 1], ys[i], ys[i + 1]);
+
+    if x1 == x0 {
+        y0
+    } else {
+        y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_system(projection: Projection) -> CoordinateSystem {
+        let mut cs = CoordinateSystem::new();
+        cs.add_coordinate(
+            DirectionCoordinate::new("J2000", projection, [1.0, -0.5], [16., 16.], [-1e-4, 1e-4])
+                .into(),
+        );
+        cs.add_coordinate(StokesCoordinate::new(vec![StokesType::XX, StokesType::YY]).into());
+        cs.add_coordinate(SpectralCoordinate::new("TOPO", 1.4e9, 3., 1e6, 1.42e9).into());
+        cs
+    }
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        assert_eq!(a.len(), b.len());
+
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() <= 1e-9 * (1. + y.abs()), "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn pixel_world_round_trip() {
+        for projection in [
+            Projection::Sin,
+            Projection::Tan,
+            Projection::Arc,
+            Projection::Zea,
+        ] {
+            let cs = test_system(projection);
+            let pixel = [7., 1., 40.5, -3.25];
+            let world = cs.to_world(&pixel).unwrap();
+            assert_eq!(world[1], StokesType::YY.code() as f64);
+            assert_close(&cs.to_pixel(&world).unwrap(), &pixel);
+        }
+
+        let cs = test_system(Projection::Sin);
+        let world = cs.to_world(&[3., 0., 16., 17.]).unwrap();
+        assert_close(&world[..2], &[1.4e9, 9.]);
+        assert!(world[3] < 1.0);
+        assert!((world[2] + 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let cs = test_system(Projection::Tan);
+        let mut rec = cs.to_record().unwrap();
+        let cs2 = CoordinateSystem::from_record(&mut rec).unwrap();
+
+        assert_eq!(cs2.n_pixel_axes(), 4);
+        assert_eq!(cs2.spectral().unwrap().1, Some(0));
+        assert_eq!(cs2.stokes().unwrap().1, Some(1));
+        assert_eq!(cs2.direction().unwrap().1, vec![Some(3), Some(2)]);
+
+        let (spec, _) = cs2.spectral().unwrap();
+        assert_eq!(spec.frequencies(2).unwrap(), vec![1.397e9, 1.398e9]);
+        assert!(spec.radio_velocity(0.).unwrap().unwrap() > 0.);
+
+        let pixel = [2., 0., 5., 9.];
+        assert_close(
+            &cs2.to_world(&pixel).unwrap(),
+            &cs.to_world(&pixel).unwrap(),
+        );
+    }
+
+    #[test]
+    fn tabular_spectral_axis() {
+        let spec = SpectralCoordinate {
+            system: "LSRK".into(),
+            rest_frequency: 0.,
+            unit: "MHz".into(),
+            axis: SpectralAxis::Tabular {
+                pixels: vec![0., 1., 2.],
+                worlds: vec![100., 102., 106.],
+            },
+        };
+
+        assert_eq!(spec.to_world(1.5), 104.);
+        assert_eq!(spec.to_world(-1.), 98.);
+        assert_eq!(spec.to_pixel(103.).unwrap(), 1.25);
+        assert_eq!(spec.frequency(0.).unwrap(), 1e8);
+    }
+
+    #[test]
+    fn stokes_names() {
+        for code in 1..=32 {
+            let s = StokesType::from_code(code).unwrap();
+            assert_eq!(s.code(), code);
+            assert_eq!(s.name().parse::<StokesType>().unwrap(), s);
+        }
+
+        assert!(StokesType::from_code(0).is_none());
+        assert!("Q".parse::<StokesType>().is_ok());
+        assert!("Z".parse::<StokesType>().is_err());
+    }
+}
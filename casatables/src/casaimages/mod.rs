@@ -0,0 +1,322 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Access to CASA images.
+//!
+//! A CASA image, as written by casacore’s `PagedImage` class and therefore by
+//! tasks such as `tclean`, is an ordinary CASA table with a single row. The
+//! pixels are stored in one tiled array cell of the `map` column, and the
+//! metadata are stored as table keywords: `coords` holds the coordinate
+//! system, `imageinfo` holds the image type and restoring beam, and `units`
+//! holds the brightness unit. Pixel masks are stored in sub-tables that are
+//! listed in the `masks` keyword.
+//!
+//! As elsewhere in this crate, array shapes and pixel positions are given in
+//! C order, which is the reverse of the casacore order. A typical image with
+//! casacore shape `[n_ra, n_dec, n_stokes, n_freq]` therefore has the shape
+//! `[n_freq, n_stokes, n_dec, n_ra]` here.
+//!
+//! The [`coordinates`] module interprets the coordinate system record.
+
+use ndarray::{ArrayD, ArrayViewD};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::{
+    CasacoreError, GlueDataType, Table, TableCreateMode, TableDesc, TableDescCreateMode,
+    TableError, TableOpenMode, TableRecord,
+};
+
+pub mod coordinates;
+
+use self::coordinates::{CoordinateError, CoordinateSystem};
+
+/// The column holding the pixel data.
+const PIXEL_COLUMN: &str = "map";
+
+/// The column holding the data of a mask sub-table, which is a casacore
+/// `PagedArray`.
+const MASK_COLUMN: &str = "PagedArray";
+
+/// The keyword naming the default mask.
+const DEFAULT_MASK_KEYWORD: &str = "Image_defaultmask";
+
+/// An error that can occur when working with a CASA image.
+#[derive(Error, Debug)]
+pub enum ImageError {
+    /// The underlying table could not be accessed.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The table does not have the structure of a CASA image.
+    #[error("\"{0}\" is not a CASA image: {1}")]
+    NotAnImage(String, String),
+
+    /// The requested mask does not exist.
+    #[error("the image has no mask named \"{0}\"")]
+    NoSuchMask(String),
+
+    /// The image’s coordinate system could not be interpreted.
+    #[error(transparent)]
+    Coordinates(#[from] CoordinateError),
+}
+
+impl From<CasacoreError> for ImageError {
+    fn from(e: CasacoreError) -> Self {
+        ImageError::Table(e.into())
+    }
+}
+
+/// A CASA image of single-precision floating-point pixels.
+///
+/// # Example
+///
+/// ```rust
+/// use rubbl_casatables::{casaimages::Image, TableOpenMode, TableRecord};
+/// use tempfile::tempdir;
+///
+/// let tmp_dir = tempdir().unwrap();
+/// let path = tmp_dir.path().join("test.image");
+///
+/// let coords = TableRecord::new().unwrap();
+/// let mut image = Image::create(&path, &[1, 1, 32, 32], &coords, "Jy/beam").unwrap();
+/// let pixels = rubbl_core::ndarray::ArrayD::from_elem(vec![1, 1, 4, 4], 1f32);
+/// image.put_chunk(&[0, 0, 8, 8], pixels.view()).unwrap();
+/// drop(image);
+///
+/// let mut image = Image::open(&path, TableOpenMode::Read).unwrap();
+/// assert_eq!(image.shape(), &[1, 1, 32, 32]);
+/// assert_eq!(image.units().unwrap(), "Jy/beam");
+/// let chunk = image.get_chunk(&[0, 0, 7, 7], &[1, 1, 2, 2]).unwrap();
+/// assert_eq!(chunk.sum(), 1.);
+/// ```
+#[derive(Debug)]
+pub struct Image {
+    table: Table,
+    path: PathBuf,
+    shape: Vec<usize>,
+}
+
+impl Image {
+    /// Open an existing CASA image.
+    pub fn open<P: AsRef<Path>>(path: P, mode: TableOpenMode) -> Result<Self, ImageError> {
+        let path = path.as_ref().to_owned();
+        let mut table = Table::open(&path, mode)?;
+        let not_image = |why: &str| ImageError::NotAnImage(path.display().to_string(), why.into());
+
+        if !table.column_names()?.iter().any(|c| c == PIXEL_COLUMN) {
+            return Err(not_image("it has no \"map\" column"));
+        }
+
+        if table.n_rows() != 1 {
+            return Err(not_image("it does not have exactly one row"));
+        }
+
+        let desc = table.get_col_desc(PIXEL_COLUMN)?;
+
+        if desc.data_type() != GlueDataType::TpFloat {
+            return Err(not_image("its pixels are not single-precision floats"));
+        }
+
+        let shape = table.get_cell_shape(PIXEL_COLUMN, 0)?;
+        Ok(Image { table, path, shape })
+    }
+
+    /// Create a new CASA image, which must not already exist.
+    ///
+    /// The pixels are initialized to zero. The coordinate system record,
+    /// which will be stored in the `coords` keyword, should be in the format
+    /// produced by casacore’s `CoordinateSystem::save` method, such as the
+    /// output of [`CoordinateSystem::to_record`]. The pixels are
+    /// stored with casacore’s tiled storage manager using its default tile
+    /// shape.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        shape: &[usize],
+        coords: &TableRecord,
+        units: &str,
+    ) -> Result<Self, ImageError> {
+        let path = path.as_ref().to_owned();
+        let cell_shape: Vec<u64> = shape.iter().map(|n| *n as u64).collect();
+
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH)?;
+        desc.add_array_column(
+            GlueDataType::TpFloat,
+            PIXEL_COLUMN,
+            None,
+            Some(&cell_shape),
+            false,
+            false,
+        )?;
+        desc.set_data_manager(PIXEL_COLUMN, "TiledCellStMan", "TiledCellStMan")?;
+
+        let mut table = Table::new(&path, desc, 1, TableCreateMode::NewNoReplace)?;
+
+        // Tiled storage managers don't guarantee initialized contents.
+        let zeros = ArrayD::<f32>::zeros(shape);
+        table.put_cell_slice(PIXEL_COLUMN, 0, &vec![0; shape.len()], zeros.view())?;
+
+        let mut info = TableRecord::new()?;
+        info.put_field("imagetype", &"Intensity".to_owned())?;
+        info.put_field("objectname", &String::new())?;
+
+        table.put_keyword("coords", coords)?;
+        table.put_keyword("imageinfo", &info)?;
+        table.put_keyword("units", &units.to_owned())?;
+
+        Ok(Image {
+            table,
+            path,
+            shape: shape.to_vec(),
+        })
+    }
+
+    /// Get the path of this image.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the shape of this image, in C order.
+    pub fn shape(&self) -> &[usize] {
+        &self.shape[..]
+    }
+
+    /// Read a rectangular chunk of pixels.
+    ///
+    /// The *start* and *shape* of the chunk are given in C order. Only the
+    /// requested pixels are read from disk.
+    pub fn get_chunk(
+        &mut self,
+        start: &[usize],
+        shape: &[usize],
+    ) -> Result<ArrayD<f32>, ImageError> {
+        Ok(self.table.get_cell_slice(PIXEL_COLUMN, 0, start, shape)?)
+    }
+
+    /// Read all of the pixels of the image into memory.
+    pub fn get_data(&mut self) -> Result<ArrayD<f32>, ImageError> {
+        let start = vec![0; self.shape.len()];
+        Ok(self
+            .table
+            .get_cell_slice(PIXEL_COLUMN, 0, &start, &self.shape)?)
+    }
+
+    /// Write a rectangular chunk of pixels, starting at *start*.
+    pub fn put_chunk(&mut self, start: &[usize], data: ArrayViewD<f32>) -> Result<(), ImageError> {
+        Ok(self.table.put_cell_slice(PIXEL_COLUMN, 0, start, data)?)
+    }
+
+    /// Get the brightness unit of the image, or an empty string if it is not
+    /// specified.
+    pub fn units(&mut self) -> Result<String, ImageError> {
+        let mut kws = self.table.get_keyword_record()?;
+
+        if kws.keyword_names()?.iter().any(|n| n == "units") {
+            Ok(kws.get_field("units")?)
+        } else {
+            Ok(String::new())
+        }
+    }
+
+    /// Get the record describing the image’s coordinate system, as stored by
+    /// casacore’s `CoordinateSystem::save` method.
+    pub fn coordinate_record(&mut self) -> Result<TableRecord, ImageError> {
+        self.get_record_keyword("coords")
+    }
+
+    /// Get the image’s coordinate system.
+    pub fn coordinate_system(&mut self) -> Result<CoordinateSystem, ImageError> {
+        let mut rec = self.coordinate_record()?;
+        Ok(CoordinateSystem::from_record(&mut rec)?)
+    }
+
+    /// Get the record describing miscellaneous information about the image,
+    /// such as its restoring beam.
+    pub fn image_info(&mut self) -> Result<TableRecord, ImageError> {
+        self.get_record_keyword("imageinfo")
+    }
+
+    fn get_record_keyword(&mut self, name: &str) -> Result<TableRecord, ImageError> {
+        let mut kws = self.table.get_keyword_record()?;
+
+        if !kws.keyword_names()?.iter().any(|n| n == name) {
+            return Err(ImageError::NotAnImage(
+                self.path.display().to_string(),
+                format!("it has no \"{name}\" keyword"),
+            ));
+        }
+
+        Ok(kws.get_field(name)?)
+    }
+
+    /// Get the names of the pixel masks associated with this image.
+    pub fn mask_names(&mut self) -> Result<Vec<String>, ImageError> {
+        let mut kws = self.table.get_keyword_record()?;
+
+        if !kws.keyword_names()?.iter().any(|n| n == "masks") {
+            return Ok(Vec::new());
+        }
+
+        let mut masks: TableRecord = kws.get_field("masks")?;
+        Ok(masks.keyword_names()?)
+    }
+
+    /// Get the name of the default pixel mask, if there is one.
+    pub fn default_mask_name(&mut self) -> Result<Option<String>, ImageError> {
+        let mut kws = self.table.get_keyword_record()?;
+
+        if !kws
+            .keyword_names()?
+            .iter()
+            .any(|n| n == DEFAULT_MASK_KEYWORD)
+        {
+            return Ok(None);
+        }
+
+        let name: String = kws.get_field(DEFAULT_MASK_KEYWORD)?;
+        Ok(if name.is_empty() { None } else { Some(name) })
+    }
+
+    /// Read a rectangular chunk of a named pixel mask.
+    ///
+    /// In the result, `true` indicates a good pixel, following the casacore
+    /// convention. The mask is assumed to be stored in a sub-table of the
+    /// image directory with the same name as the mask, as casacore does.
+    pub fn get_mask_chunk(
+        &mut self,
+        name: &str,
+        start: &[usize],
+        shape: &[usize],
+    ) -> Result<ArrayD<bool>, ImageError> {
+        if !self.mask_names()?.iter().any(|n| n == name) {
+            return Err(ImageError::NoSuchMask(name.to_owned()));
+        }
+
+        let mut mask = Table::open(self.path.join(name), TableOpenMode::Read)?;
+        Ok(mask.get_cell_slice(MASK_COLUMN, 0, start, shape)?)
+    }
+
+    /// Read a rectangular chunk of the default pixel mask.
+    ///
+    /// If the image has no default mask, all pixels are good.
+    pub fn get_default_mask_chunk(
+        &mut self,
+        start: &[usize],
+        shape: &[usize],
+    ) -> Result<ArrayD<bool>, ImageError> {
+        match self.default_mask_name()? {
+            Some(name) => self.get_mask_chunk(&name, start, shape),
+            None => Ok(ArrayD::from_elem(shape, true)),
+        }
+    }
+
+    /// Get mutable access to the underlying table.
+    pub fn table(&mut self) -> &mut Table {
+        &mut self.table
+    }
+
+    /// Consume this image and return the underlying table.
+    pub fn into_table(self) -> Table {
+        self.table
+    }
+}
@@ -0,0 +1,642 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Dynamically typed cell values.
+//!
+//! The cell I/O methods of [`Table`] are generic over the Rust type of the
+//! values, which suits code that knows the schema of the tables it works
+//! with. Code that does not — a format converter filling columns whose types
+//! are only known at run time, say — would otherwise need a `match` on
+//! [`GlueDataType`] around every call. A [`CellValue`] holds a value of any
+//! of the types that a cell can have. [`Table::get_cell_dyn`] reads a cell
+//! of any type into one, and [`Table::put_cell_dyn`] writes one, converting
+//! it to the type of the column if needed.
+//!
+//! ```rust
+//! use rubbl_casatables::{
+//!     CellValue, Complex, GlueDataType, Table, TableCreateMode, TableDesc, TableDescCreateMode,
+//! };
+//! use tempfile::tempdir;
+//!
+//! let tmp_dir = tempdir().unwrap();
+//! let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+//! desc.add_scalar_column(GlueDataType::TpFloat, "X", None, false, false).unwrap();
+//! desc.add_array_column(GlueDataType::TpComplex, "DATA", None, None, false, false).unwrap();
+//! let mut t = Table::new(tmp_dir.path().join("t"), desc, 1, TableCreateMode::New).unwrap();
+//!
+//! t.put_cell_dyn("X", 0, 1.5f64).unwrap();
+//! t.put_cell_dyn("DATA", 0, vec![Complex::new(1f64, 2.), Complex::new(3., 4.)]).unwrap();
+//!
+//! assert_eq!(t.get_cell::<f32>("X", 0).unwrap(), 1.5);
+//! assert_eq!(t.get_cell_dyn("X", 0).unwrap(), CellValue::F32(1.5));
+//!
+//! for col_name in t.column_names().unwrap() {
+//!     let value = t.get_cell_dyn(&col_name, 0).unwrap();
+//!     println!("{}: {} of shape {:?}", col_name, value.data_type(), value.shape());
+//! }
+//! ```
+
+use ndarray::{ArrayD, Dimension, IxDyn};
+use std::convert::TryFrom;
+
+use crate::{
+    glue::GlueDataType, Array, CasaScalarData, Complex, DimensionMismatchError, Table, TableError,
+    UnexpectedDataTypeError,
+};
+
+/// A cell value of any of the types that casacore tables can hold.
+///
+/// Array values are held as dynamically shaped `ndarray` arrays, in C
+/// order like the rest of this crate. Values can be created with [`From`]
+/// from the corresponding Rust scalars, from `Vec`s, which become
+/// one-dimensional arrays, and from `ndarray` arrays of any dimensionality.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CellValue {
+    /// A `Bool` scalar.
+    Bool(bool),
+    /// A `Char` scalar.
+    I8(i8),
+    /// A `UChar` scalar.
+    U8(u8),
+    /// A `Short` scalar.
+    I16(i16),
+    /// A `UShort` scalar.
+    U16(u16),
+    /// An `Int` scalar.
+    I32(i32),
+    /// A `UInt` scalar.
+    U32(u32),
+    /// An `Int64` scalar.
+    I64(i64),
+    /// A `Float` scalar.
+    F32(f32),
+    /// A `Double` scalar.
+    F64(f64),
+    /// A `Complex` scalar.
+    C32(Complex<f32>),
+    /// A `DComplex` scalar.
+    C64(Complex<f64>),
+    /// A `String` scalar.
+    String(String),
+    /// A `Bool` array.
+    BoolArray(ArrayD<bool>),
+    /// A `Char` array.
+    I8Array(ArrayD<i8>),
+    /// A `UChar` array.
+    U8Array(ArrayD<u8>),
+    /// A `Short` array.
+    I16Array(ArrayD<i16>),
+    /// A `UShort` array.
+    U16Array(ArrayD<u16>),
+    /// An `Int` array.
+    I32Array(ArrayD<i32>),
+    /// A `UInt` array.
+    U32Array(ArrayD<u32>),
+    /// An `Int64` array.
+    I64Array(ArrayD<i64>),
+    /// A `Float` array.
+    F32Array(ArrayD<f32>),
+    /// A `Double` array.
+    F64Array(ArrayD<f64>),
+    /// A `Complex` array.
+    C32Array(ArrayD<Complex<f32>>),
+    /// A `DComplex` array.
+    C64Array(ArrayD<Complex<f64>>),
+    /// A `String` array.
+    StringArray(ArrayD<String>),
+}
+
+macro_rules! impl_from {
+    ($($t:ty => $scalar:ident, $array:ident;)*) => {
+        $(
+            impl From<$t> for CellValue {
+                fn from(v: $t) -> Self {
+                    CellValue::$scalar(v)
+                }
+            }
+
+            impl<D: Dimension> From<Array<$t, D>> for CellValue {
+                fn from(a: Array<$t, D>) -> Self {
+                    CellValue::$array(a.into_dyn())
+                }
+            }
+
+            impl From<Vec<$t>> for CellValue {
+                fn from(v: Vec<$t>) -> Self {
+                    CellValue::$array(ArrayD::from_shape_vec(IxDyn(&[v.len()]), v).unwrap())
+                }
+            }
+        )*
+    };
+}
+
+impl_from! {
+    bool => Bool, BoolArray;
+    i8 => I8, I8Array;
+    u8 => U8, U8Array;
+    i16 => I16, I16Array;
+    u16 => U16, U16Array;
+    i32 => I32, I32Array;
+    u32 => U32, U32Array;
+    i64 => I64, I64Array;
+    f32 => F32, F32Array;
+    f64 => F64, F64Array;
+    Complex<f32> => C32, C32Array;
+    Complex<f64> => C64, C64Array;
+    String => String, StringArray;
+}
+
+impl From<&str> for CellValue {
+    fn from(v: &str) -> Self {
+        CellValue::String(v.to_owned())
+    }
+}
+
+/// The elements of a value, widened to a common type for each broad class
+/// of data type.
+enum Elements {
+    Bool(Vec<bool>),
+    Int(Vec<i64>),
+    Float(Vec<f64>),
+    Complex(Vec<Complex<f64>>),
+    String(Vec<String>),
+}
+
+impl Elements {
+    fn bools(self) -> Option<Vec<bool>> {
+        match self {
+            Elements::Bool(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn ints<T: TryFrom<i64>>(self) -> Option<Vec<T>> {
+        match self {
+            Elements::Int(v) => v.into_iter().map(|x| T::try_from(x).ok()).collect(),
+            _ => None,
+        }
+    }
+
+    fn floats(self) -> Option<Vec<f64>> {
+        match self {
+            Elements::Int(v) => Some(v.into_iter().map(|x| x as f64).collect()),
+            Elements::Float(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn complexes(self) -> Option<Vec<Complex<f64>>> {
+        match self {
+            Elements::Int(v) => Some(v.into_iter().map(|x| Complex::new(x as f64, 0.)).collect()),
+            Elements::Float(v) => Some(v.into_iter().map(|x| Complex::new(x, 0.)).collect()),
+            Elements::Complex(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn strings(self) -> Option<Vec<String>> {
+        match self {
+            Elements::String(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+fn widen<T: Clone, U>(a: &ArrayD<T>, f: impl Fn(T) -> U) -> Vec<U> {
+    a.iter().cloned().map(f).collect()
+}
+
+/// Get the array type whose elements have the type *element_type*.
+fn array_type(element_type: GlueDataType) -> GlueDataType {
+    use GlueDataType::*;
+
+    match element_type {
+        TpBool => TpArrayBool,
+        TpChar => TpArrayChar,
+        TpUChar => TpArrayUChar,
+        TpShort => TpArrayShort,
+        TpUShort => TpArrayUShort,
+        TpInt => TpArrayInt,
+        TpUInt => TpArrayUInt,
+        TpInt64 => TpArrayInt64,
+        TpFloat => TpArrayFloat,
+        TpDouble => TpArrayDouble,
+        TpComplex => TpArrayComplex,
+        TpDComplex => TpArrayDComplex,
+        TpString => TpArrayString,
+        TpQuantity => TpArrayQuantity,
+        other => other,
+    }
+}
+
+impl CellValue {
+    /// Get the casacore data type of this value: a `TpArrayX` type for
+    /// arrays and a `TpX` type for scalars.
+    pub fn data_type(&self) -> GlueDataType {
+        use GlueDataType::*;
+
+        match self {
+            CellValue::Bool(_) => TpBool,
+            CellValue::I8(_) => TpChar,
+            CellValue::U8(_) => TpUChar,
+            CellValue::I16(_) => TpShort,
+            CellValue::U16(_) => TpUShort,
+            CellValue::I32(_) => TpInt,
+            CellValue::U32(_) => TpUInt,
+            CellValue::I64(_) => TpInt64,
+            CellValue::F32(_) => TpFloat,
+            CellValue::F64(_) => TpDouble,
+            CellValue::C32(_) => TpComplex,
+            CellValue::C64(_) => TpDComplex,
+            CellValue::String(_) => TpString,
+            CellValue::BoolArray(_) => TpArrayBool,
+            CellValue::I8Array(_) => TpArrayChar,
+            CellValue::U8Array(_) => TpArrayUChar,
+            CellValue::I16Array(_) => TpArrayShort,
+            CellValue::U16Array(_) => TpArrayUShort,
+            CellValue::I32Array(_) => TpArrayInt,
+            CellValue::U32Array(_) => TpArrayUInt,
+            CellValue::I64Array(_) => TpArrayInt64,
+            CellValue::F32Array(_) => TpArrayFloat,
+            CellValue::F64Array(_) => TpArrayDouble,
+            CellValue::C32Array(_) => TpArrayComplex,
+            CellValue::C64Array(_) => TpArrayDComplex,
+            CellValue::StringArray(_) => TpArrayString,
+        }
+    }
+
+    /// Get the shape of this value in C order. Scalars have an empty shape.
+    pub fn shape(&self) -> &[usize] {
+        match self {
+            CellValue::BoolArray(a) => a.shape(),
+            CellValue::I8Array(a) => a.shape(),
+            CellValue::U8Array(a) => a.shape(),
+            CellValue::I16Array(a) => a.shape(),
+            CellValue::U16Array(a) => a.shape(),
+            CellValue::I32Array(a) => a.shape(),
+            CellValue::U32Array(a) => a.shape(),
+            CellValue::I64Array(a) => a.shape(),
+            CellValue::F32Array(a) => a.shape(),
+            CellValue::F64Array(a) => a.shape(),
+            CellValue::C32Array(a) => a.shape(),
+            CellValue::C64Array(a) => a.shape(),
+            CellValue::StringArray(a) => a.shape(),
+            _ => &[],
+        }
+    }
+
+    /// Split the value into its shape, or `None` for a scalar, and its
+    /// widened elements in C order.
+    fn into_parts(self) -> (Option<Vec<usize>>, Elements) {
+        let shape = if self.data_type().is_array() {
+            Some(self.shape().to_vec())
+        } else {
+            None
+        };
+
+        let elements = match self {
+            CellValue::Bool(v) => Elements::Bool(vec![v]),
+            CellValue::I8(v) => Elements::Int(vec![i64::from(v)]),
+            CellValue::U8(v) => Elements::Int(vec![i64::from(v)]),
+            CellValue::I16(v) => Elements::Int(vec![i64::from(v)]),
+            CellValue::U16(v) => Elements::Int(vec![i64::from(v)]),
+            CellValue::I32(v) => Elements::Int(vec![i64::from(v)]),
+            CellValue::U32(v) => Elements::Int(vec![i64::from(v)]),
+            CellValue::I64(v) => Elements::Int(vec![v]),
+            CellValue::F32(v) => Elements::Float(vec![f64::from(v)]),
+            CellValue::F64(v) => Elements::Float(vec![v]),
+            CellValue::C32(v) => {
+                Elements::Complex(vec![Complex::new(f64::from(v.re), f64::from(v.im))])
+            }
+            CellValue::C64(v) => Elements::Complex(vec![v]),
+            CellValue::String(v) => Elements::String(vec![v]),
+            CellValue::BoolArray(a) => Elements::Bool(widen(&a, |x| x)),
+            CellValue::I8Array(a) => Elements::Int(widen(&a, i64::from)),
+            CellValue::U8Array(a) => Elements::Int(widen(&a, i64::from)),
+            CellValue::I16Array(a) => Elements::Int(widen(&a, i64::from)),
+            CellValue::U16Array(a) => Elements::Int(widen(&a, i64::from)),
+            CellValue::I32Array(a) => Elements::Int(widen(&a, i64::from)),
+            CellValue::U32Array(a) => Elements::Int(widen(&a, i64::from)),
+            CellValue::I64Array(a) => Elements::Int(widen(&a, |x| x)),
+            CellValue::F32Array(a) => Elements::Float(widen(&a, f64::from)),
+            CellValue::F64Array(a) => Elements::Float(widen(&a, |x| x)),
+            CellValue::C32Array(a) => Elements::Complex(widen(&a, |z| {
+                Complex::new(f64::from(z.re), f64::from(z.im))
+            })),
+            CellValue::C64Array(a) => Elements::Complex(widen(&a, |z| z)),
+            CellValue::StringArray(a) => Elements::String(widen(&a, |s| s)),
+        };
+
+        (shape, elements)
+    }
+
+    /// Convert this value to the casacore data type *data_type*.
+    ///
+    /// Arrays can only be converted to array types, and scalars to scalar
+    /// types. Numbers can be converted to any numeric type that can
+    /// represent them: integers to any integer type wide enough for their
+    /// values, to floating-point types, and to complex types, and real
+    /// numbers to complex numbers. Floating-point and complex values are
+    /// rounded when converted to single precision, but are never converted to
+    /// integers. Booleans and strings are only converted to their own types.
+    pub fn cast(self, data_type: GlueDataType) -> Result<CellValue, UnexpectedDataTypeError> {
+        let from = self.data_type();
+
+        if from == data_type {
+            return Ok(self);
+        }
+
+        let mismatch = || UnexpectedDataTypeError(data_type, from);
+
+        if from.is_array() != data_type.is_array() {
+            return Err(mismatch());
+        }
+
+        let (shape, elements) = self.into_parts();
+
+        macro_rules! build {
+            ($scalar:ident, $array:ident, $values:expr) => {{
+                let values = $values.ok_or_else(mismatch)?;
+
+                match shape {
+                    None => values
+                        .into_iter()
+                        .next()
+                        .map(CellValue::$scalar)
+                        .ok_or_else(mismatch)?,
+                    Some(shape) => CellValue::$array(
+                        ArrayD::from_shape_vec(IxDyn(&shape), values)
+                            .expect("element count matches shape"),
+                    ),
+                }
+            }};
+        }
+
+        use GlueDataType::*;
+
+        Ok(match data_type.element_type() {
+            TpBool => build!(Bool, BoolArray, elements.bools()),
+            TpChar => build!(I8, I8Array, elements.ints()),
+            TpUChar => build!(U8, U8Array, elements.ints()),
+            TpShort => build!(I16, I16Array, elements.ints()),
+            TpUShort => build!(U16, U16Array, elements.ints()),
+            TpInt => build!(I32, I32Array, elements.ints()),
+            TpUInt => build!(U32, U32Array, elements.ints()),
+            TpInt64 => build!(I64, I64Array, elements.ints()),
+            TpFloat => build!(
+                F32,
+                F32Array,
+                elements
+                    .floats()
+                    .map(|v| v.into_iter().map(|x| x as f32).collect::<Vec<_>>())
+            ),
+            TpDouble => build!(F64, F64Array, elements.floats()),
+            TpComplex => build!(
+                C32,
+                C32Array,
+                elements.complexes().map(|v| v
+                    .into_iter()
+                    .map(|z| Complex::new(z.re as f32, z.im as f32))
+                    .collect::<Vec<_>>())
+            ),
+            TpDComplex => build!(C64, C64Array, elements.complexes()),
+            TpString => build!(String, StringArray, elements.strings()),
+            _ => return Err(mismatch()),
+        })
+    }
+}
+
+/// Flatten an array into a vector in C order.
+fn flat<T: Clone>(a: &ArrayD<T>) -> Vec<T> {
+    a.iter().cloned().collect()
+}
+
+impl Table {
+    /// Read one cell of any type.
+    ///
+    /// The value has the type of the cell, as reported by
+    /// [`CellValue::data_type`]. Array cells are returned in C order. Cells
+    /// of types that [`CellValue`] cannot hold, such as records, are
+    /// reported as [`TableError::UnsupportedDataType`].
+    pub fn get_cell_dyn(&mut self, col_name: &str, row: u64) -> Result<CellValue, TableError> {
+        let (data_type, shape) = self.cell_info(col_name, row)?;
+
+        macro_rules! read {
+            ($scalar:ident, $array:ident, $t:ty) => {
+                if data_type.is_array() {
+                    let flat: Vec<$t> = self.get_cell_as_vec(col_name, row)?;
+                    CellValue::$array(
+                        ArrayD::from_shape_vec(IxDyn(&shape), flat)
+                            .expect("cell contents match its shape"),
+                    )
+                } else {
+                    CellValue::$scalar(self.get_cell(col_name, row)?)
+                }
+            };
+        }
+
+        use GlueDataType::*;
+
+        Ok(match data_type.element_type() {
+            TpBool => read!(Bool, BoolArray, bool),
+            TpChar => read!(I8, I8Array, i8),
+            TpUChar => read!(U8, U8Array, u8),
+            TpShort => read!(I16, I16Array, i16),
+            TpUShort => read!(U16, U16Array, u16),
+            TpInt => read!(I32, I32Array, i32),
+            TpUInt => read!(U32, U32Array, u32),
+            TpInt64 => read!(I64, I64Array, i64),
+            TpFloat => read!(F32, F32Array, f32),
+            TpDouble => read!(F64, F64Array, f64),
+            TpComplex => read!(C32, C32Array, Complex<f32>),
+            TpDComplex => read!(C64, C64Array, Complex<f64>),
+
+            TpString => {
+                if data_type.is_array() {
+                    let flat: Vec<String> = self.get_cell(col_name, row)?;
+                    CellValue::StringArray(
+                        ArrayD::from_shape_vec(IxDyn(&[flat.len()]), flat)
+                            .expect("vector length matches shape"),
+                    )
+                } else {
+                    CellValue::String(self.get_cell(col_name, row)?)
+                }
+            }
+
+            other => return Err(TableError::UnsupportedDataType(col_name.to_owned(), other)),
+        })
+    }
+
+    /// Write one cell from a dynamically typed value.
+    ///
+    /// The value is converted to the data type of the column with
+    /// [`CellValue::cast`], so that, for instance, an `f64` can be written
+    /// to a `Float` column. Anything convertible to a [`CellValue`] can be
+    /// passed, including Rust scalars, `Vec`s, and `ndarray` arrays.
+    ///
+    /// String arrays must be one-dimensional.
+    pub fn put_cell_dyn(
+        &mut self,
+        col_name: &str,
+        row: u64,
+        value: impl Into<CellValue>,
+    ) -> Result<(), TableError> {
+        let desc = self.get_col_desc(col_name)?;
+        let data_type = if desc.is_scalar() {
+            desc.data_type()
+        } else {
+            array_type(desc.data_type())
+        };
+
+        let value = value.into().cast(data_type)?;
+
+        fn put_array<T: CasaScalarData>(
+            table: &mut Table,
+            col_name: &str,
+            row: u64,
+            a: &ArrayD<T>,
+        ) -> Result<(), TableError> {
+            table.put_cell_shaped(col_name, row, a.shape(), &flat(a))
+        }
+
+        match &value {
+            CellValue::Bool(v) => self.put_cell(col_name, row, v)?,
+            CellValue::I8(v) => self.put_cell(col_name, row, v)?,
+            CellValue::U8(v) => self.put_cell(col_name, row, v)?,
+            CellValue::I16(v) => self.put_cell(col_name, row, v)?,
+            CellValue::U16(v) => self.put_cell(col_name, row, v)?,
+            CellValue::I32(v) => self.put_cell(col_name, row, v)?,
+            CellValue::U32(v) => self.put_cell(col_name, row, v)?,
+            CellValue::I64(v) => self.put_cell(col_name, row, v)?,
+            CellValue::F32(v) => self.put_cell(col_name, row, v)?,
+            CellValue::F64(v) => self.put_cell(col_name, row, v)?,
+            CellValue::C32(v) => self.put_cell(col_name, row, v)?,
+            CellValue::C64(v) => self.put_cell(col_name, row, v)?,
+            CellValue::String(v) => self.put_cell(col_name, row, v)?,
+            CellValue::BoolArray(a) => put_array(self, col_name, row, a)?,
+            CellValue::I8Array(a) => put_array(self, col_name, row, a)?,
+            CellValue::U8Array(a) => put_array(self, col_name, row, a)?,
+            CellValue::I16Array(a) => put_array(self, col_name, row, a)?,
+            CellValue::U16Array(a) => put_array(self, col_name, row, a)?,
+            CellValue::I32Array(a) => put_array(self, col_name, row, a)?,
+            CellValue::U32Array(a) => put_array(self, col_name, row, a)?,
+            CellValue::I64Array(a) => put_array(self, col_name, row, a)?,
+            CellValue::F32Array(a) => put_array(self, col_name, row, a)?,
+            CellValue::F64Array(a) => put_array(self, col_name, row, a)?,
+            CellValue::C32Array(a) => put_array(self, col_name, row, a)?,
+            CellValue::C64Array(a) => put_array(self, col_name, row, a)?,
+
+            CellValue::StringArray(a) => {
+                if a.ndim() != 1 {
+                    return Err(DimensionMismatchError {
+                        expected: 1,
+                        actual: a.ndim(),
+                    }
+                    .into());
+                }
+
+                self.put_cell(col_name, row, &flat(a))?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TableCreateMode, TableDesc, TableDescCreateMode};
+    use ndarray::array;
+    use tempfile::tempdir;
+
+    #[test]
+    fn casts() {
+        assert_eq!(
+            CellValue::from(3i64).cast(GlueDataType::TpShort).unwrap(),
+            CellValue::I16(3)
+        );
+        assert_eq!(
+            CellValue::from(2.5f64)
+                .cast(GlueDataType::TpComplex)
+                .unwrap(),
+            CellValue::C32(Complex::new(2.5, 0.))
+        );
+        assert_eq!(
+            CellValue::from(vec![1u8, 2])
+                .cast(GlueDataType::TpArrayFloat)
+                .unwrap(),
+            CellValue::F32Array(array![1f32, 2.].into_dyn())
+        );
+        assert!(CellValue::from(70_000i32)
+            .cast(GlueDataType::TpShort)
+            .is_err());
+        assert!(CellValue::from(1.5f64).cast(GlueDataType::TpInt).is_err());
+        assert!(CellValue::from(true).cast(GlueDataType::TpInt).is_err());
+        assert!(CellValue::from(1i32)
+            .cast(GlueDataType::TpArrayInt)
+            .is_err());
+    }
+
+    #[test]
+    fn put_cell_dyn() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpFloat, "X", None, false, false)
+            .unwrap();
+        desc.add_scalar_column(GlueDataType::TpString, "NAME", None, false, false)
+            .unwrap();
+        desc.add_array_column(GlueDataType::TpDouble, "UVW", None, Some(&[3]), true, false)
+            .unwrap();
+        desc.add_array_column(GlueDataType::TpComplex, "DATA", None, None, false, false)
+            .unwrap();
+        desc.add_array_column(GlueDataType::TpString, "NAMES", None, None, false, false)
+            .unwrap();
+        let mut t = Table::new(tmp_dir.path().join("t"), desc, 1, TableCreateMode::New).unwrap();
+
+        t.put_cell_dyn("X", 0, 7i32).unwrap();
+        t.put_cell_dyn("NAME", 0, "ANT001").unwrap();
+        t.put_cell_dyn("UVW", 0, vec![1f32, 2., 3.]).unwrap();
+        t.put_cell_dyn("DATA", 0, array![[1f64, 2.], [3., 4.], [5., 6.]])
+            .unwrap();
+        t.put_cell_dyn("NAMES", 0, vec!["a".to_owned(), "b".to_owned()])
+            .unwrap();
+
+        assert_eq!(t.get_cell::<f32>("X", 0).unwrap(), 7.);
+        assert_eq!(t.get_cell::<String>("NAME", 0).unwrap(), "ANT001");
+        assert_eq!(
+            t.get_cell_as_vec::<f64>("UVW", 0).unwrap(),
+            vec![1., 2., 3.]
+        );
+        assert_eq!(t.get_cell_shape("DATA", 0).unwrap(), vec![3, 2]);
+        assert_eq!(
+            t.get_cell_as_vec::<Complex<f32>>("DATA", 0).unwrap()[3],
+            Complex::new(4., 0.)
+        );
+        assert_eq!(
+            t.get_cell::<Vec<String>>("NAMES", 0).unwrap(),
+            vec!["a", "b"]
+        );
+
+        assert_eq!(t.get_cell_dyn("X", 0).unwrap(), CellValue::F32(7.));
+        assert_eq!(
+            t.get_cell_dyn("NAME", 0).unwrap(),
+            CellValue::String("ANT001".to_owned())
+        );
+        assert_eq!(
+            t.get_cell_dyn("UVW", 0).unwrap(),
+            CellValue::F64Array(array![1., 2., 3.].into_dyn())
+        );
+        let data = t.get_cell_dyn("DATA", 0).unwrap();
+        assert_eq!(data.data_type(), GlueDataType::TpArrayComplex);
+        assert_eq!(data.shape(), &[3, 2]);
+        assert_eq!(
+            t.get_cell_dyn("NAMES", 0).unwrap(),
+            CellValue::from(vec!["a".to_owned(), "b".to_owned()])
+        );
+
+        assert!(t.put_cell_dyn("X", 0, "seven").is_err());
+        assert!(t.put_cell_dyn("UVW", 0, 1.).is_err());
+        assert!(t.put_cell_dyn("NO_SUCH_COLUMN", 0, 1.).is_err());
+    }
+}
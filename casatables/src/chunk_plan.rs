@@ -0,0 +1,337 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Choosing chunk sizes for streaming column I/O.
+//!
+//! [`Table::read_column_chunks`] and [`Table::write_column_chunks`] hold a
+//! fixed number of rows in memory at a time. Too few rows and the overhead of
+//! each call into casacore dominates; too many and the buffers crowd out
+//! everything else, including casacore’s own caches. A [`ChunkPlanner`]
+//! turns a description of the columns involved into a row count that keeps
+//! the buffers within a memory budget, capped at a size past which larger
+//! chunks stop paying off, and aligned with the tiles of tiled storage
+//! managers so that no tile is read or written piecemeal.
+
+use crate::{glue, Table, TableError};
+
+/// A calculator for the number of rows to process per chunk when streaming
+/// column data.
+///
+/// ```rust
+/// use rubbl_casatables::ChunkPlanner;
+///
+/// // One visibility column of 64 channels by 4 correlations of single-precision
+/// // complex data, tiled in blocks of 100 rows.
+/// let mut planner = ChunkPlanner::new(1 << 20);
+/// planner.add_column(64 * 4, 8, Some(100));
+/// assert_eq!(planner.bytes_per_row(), 2048);
+/// assert_eq!(planner.rows_per_chunk(100_000), 500);
+/// assert_eq!(planner.rows_per_chunk(123), 123);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ChunkPlanner {
+    mem_budget: usize,
+    max_chunk_bytes: usize,
+    bytes_per_row: usize,
+    tile_rows: usize,
+}
+
+impl ChunkPlanner {
+    /// The default limit on the size of a chunk, in bytes.
+    ///
+    /// Beyond this size, the per-call overhead of bulk I/O is negligible and
+    /// larger chunks only use more memory.
+    pub const DEFAULT_MAX_CHUNK_BYTES: usize = 64 * 1024 * 1024;
+
+    /// The memory budget used if the amount of free memory cannot be
+    /// determined, in bytes.
+    pub const FALLBACK_MEM_BUDGET: usize = 256 * 1024 * 1024;
+
+    /// Create a planner that will keep the buffers for all of its columns
+    /// within *mem_budget* bytes.
+    pub fn new(mem_budget: usize) -> Self {
+        ChunkPlanner {
+            mem_budget: mem_budget.max(1),
+            max_chunk_bytes: Self::DEFAULT_MAX_CHUNK_BYTES,
+            bytes_per_row: 0,
+            tile_rows: 1,
+        }
+    }
+
+    /// Create a planner whose memory budget is a quarter of the memory that
+    /// is currently free on this host.
+    ///
+    /// If the amount of free memory cannot be determined,
+    /// [`Self::FALLBACK_MEM_BUDGET`] is used.
+    pub fn with_available_memory() -> Self {
+        let budget = available_memory()
+            .map(|b| b / 4)
+            .unwrap_or(Self::FALLBACK_MEM_BUDGET);
+        Self::new(budget)
+    }
+
+    /// Set the limit on the size of a chunk, in bytes.
+    ///
+    /// The default is [`Self::DEFAULT_MAX_CHUNK_BYTES`]. The memory budget
+    /// still applies if it is smaller.
+    pub fn max_chunk_bytes(mut self, max_chunk_bytes: usize) -> Self {
+        self.max_chunk_bytes = max_chunk_bytes.max(1);
+        self
+    }
+
+    /// Account for a column that will be streamed alongside the others.
+    ///
+    /// Each cell of the column holds *cell_len* elements of *element_size*
+    /// bytes; *cell_len* is 1 for scalar columns. If the column is stored
+    /// with a tiled storage manager, *tile_rows* is the number of rows
+    /// spanned by each tile.
+    pub fn add_column(
+        &mut self,
+        cell_len: usize,
+        element_size: usize,
+        tile_rows: Option<usize>,
+    ) -> &mut Self {
+        self.bytes_per_row = self
+            .bytes_per_row
+            .saturating_add(cell_len.saturating_mul(element_size));
+
+        if let Some(t) = tile_rows {
+            if t > 1 {
+                self.tile_rows = lcm(self.tile_rows, t);
+            }
+        }
+
+        self
+    }
+
+    /// The number of bytes needed to buffer one row of all of the columns.
+    pub fn bytes_per_row(&self) -> usize {
+        self.bytes_per_row
+    }
+
+    /// Get the recommended number of rows per chunk for a table with
+    /// *n_rows* rows.
+    ///
+    /// The result is at least 1 and at most *n_rows* (unless that is zero).
+    /// When the columns are tiled, it is a multiple of the number of rows per
+    /// tile if that fits within the memory budget.
+    pub fn rows_per_chunk(&self, n_rows: u64) -> usize {
+        let row_bytes = self.bytes_per_row.max(1);
+        let target = self.mem_budget.min(self.max_chunk_bytes);
+        let mut rows = (target / row_bytes).max(1);
+
+        if self.tile_rows > 1 {
+            if rows >= self.tile_rows {
+                rows -= rows % self.tile_rows;
+            } else if self.tile_rows.saturating_mul(row_bytes) <= self.mem_budget {
+                // A partial tile costs as much I/O as a whole one, so it is
+                // worth going over the chunk size limit to avoid one.
+                rows = self.tile_rows;
+            }
+        }
+
+        if n_rows > 0 && (rows as u64) > n_rows {
+            rows = n_rows as usize;
+        }
+
+        rows
+    }
+}
+
+/// Get the amount of free memory on this host, in bytes, if it can be
+/// determined.
+pub fn available_memory() -> Option<usize> {
+    let kib = unsafe { glue::host_memory_free_kib() };
+
+    if kib < 0 {
+        None
+    } else {
+        Some((kib as usize).saturating_mul(1024))
+    }
+}
+
+fn gcd(mut a: usize, mut b: usize) -> usize {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+
+    a
+}
+
+fn lcm(a: usize, b: usize) -> usize {
+    (a / gcd(a, b)).saturating_mul(b)
+}
+
+impl Table {
+    /// Get the tile shape of a column stored with one of casacore’s tiled
+    /// storage managers.
+    ///
+    /// The shape is in C order, so that its first element is the number of
+    /// rows spanned by each tile. Returns `None` if the column is stored with
+    /// a storage manager that does not use tiles, or if no data have been
+    /// stored yet. If the storage manager holds several hypercubes, the
+    /// shape of the first is returned.
+    pub fn column_tile_shape(&mut self, col_name: &str) -> Result<Option<Vec<usize>>, TableError> {
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut n_dim = 0;
+        let mut dims = [0; 8];
+
+        if unsafe {
+            glue::table_get_column_tile_shape(
+                self.handle,
+                &ccol_name,
+                &mut n_dim,
+                dims.as_mut_ptr(),
+                &mut self.exc_info,
+            )
+        } != 0
+        {
+            return self.exc_info.as_err();
+        }
+
+        if n_dim == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(
+                dims[..n_dim as usize].iter().map(|d| *d as usize).collect(),
+            ))
+        }
+    }
+
+    /// Suggest the number of rows per chunk for streaming the named columns
+    /// together with [`Self::read_column_chunks`] or
+    /// [`Self::write_column_chunks`].
+    ///
+    /// The columns must be of a type with a fixed element size, and each must
+    /// be scalar or have cells of a uniform shape. If *mem_budget* is `None`, a quarter of the free
+    /// memory on this host is used as the budget. See [`ChunkPlanner`] for
+    /// details.
+    pub fn suggest_chunk_rows(
+        &mut self,
+        cols: &[&str],
+        mem_budget: Option<usize>,
+    ) -> Result<usize, TableError> {
+        let mut planner = match mem_budget {
+            Some(b) => ChunkPlanner::new(b),
+            None => ChunkPlanner::with_available_memory(),
+        };
+
+        for col_name in cols {
+            let ccol_name = glue::StringBridge::from_rust(col_name);
+            let mut n_rows = 0;
+            let mut data_type = glue::GlueDataType::TpOther;
+            let mut is_scalar = 0;
+            let mut is_fixed_shape = 0;
+            let mut n_dim = 0;
+            let mut dims = [0; 8];
+
+            if unsafe {
+                glue::table_get_column_info(
+                    self.handle,
+                    &ccol_name,
+                    &mut n_rows,
+                    &mut data_type,
+                    &mut is_scalar,
+                    &mut is_fixed_shape,
+                    &mut n_dim,
+                    dims.as_mut_ptr(),
+                    &mut self.exc_info,
+                )
+            } != 0
+            {
+                return self.exc_info.as_err();
+            }
+
+            let element_size = data_type.element_size();
+
+            if element_size < 0 {
+                return Err(TableError::UnsupportedDataType(
+                    (*col_name).to_owned(),
+                    data_type,
+                ));
+            }
+
+            let cell_len: usize = if is_fixed_shape == 0 || n_dim < 0 {
+                self.uniform_cell_shape(col_name, n_rows)?
+                    .iter()
+                    .product()
+            } else {
+                dims[..n_dim as usize].iter().map(|d| *d as usize).product()
+            };
+            let tile_rows = self.column_tile_shape(col_name)?.map(|s| s[0]);
+            planner.add_column(cell_len, element_size as usize, tile_rows);
+        }
+
+        Ok(planner.rows_per_chunk(self.n_rows()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn planner_limits() {
+        let mut planner = ChunkPlanner::new(1000).max_chunk_bytes(100);
+        planner.add_column(1, 8, None).add_column(3, 4, None);
+        assert_eq!(planner.bytes_per_row(), 20);
+        assert_eq!(planner.rows_per_chunk(1000), 5);
+        assert_eq!(planner.rows_per_chunk(2), 2);
+        assert_eq!(planner.rows_per_chunk(0), 5);
+
+        // Tiles of 4 and 6 rows align at 12, which fits in the budget but not
+        // in the chunk size limit.
+        planner.add_column(1, 0, Some(4)).add_column(1, 0, Some(6));
+        assert_eq!(planner.rows_per_chunk(1000), 12);
+
+        let planner = ChunkPlanner::new(10).max_chunk_bytes(1 << 20);
+        assert_eq!(planner.rows_per_chunk(1000), 10);
+    }
+
+    #[test]
+    fn suggest_for_tiled_column() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_array_column(
+                GlueDataType::TpComplex,
+                "DATA",
+                None,
+                Some(&[3, 2]),
+                true,
+                false,
+            )
+            .unwrap();
+        table_desc
+            .set_data_manager("DATA", "TiledColumnStMan", "TiledData")
+            .unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpDouble, "TIME", None, true, false)
+            .unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpString, "NAME", None, false, false)
+            .unwrap();
+
+        let mut table = Table::new(&table_path, table_desc, 1000, TableCreateMode::New).unwrap();
+
+        let tile = table.column_tile_shape("DATA").unwrap().unwrap();
+        assert_eq!(&tile[1..], &[3, 2]);
+        assert_eq!(table.column_tile_shape("TIME").unwrap(), None);
+
+        // Each row needs 48 bytes of DATA and 8 of TIME. A budget of one and a
+        // half tiles gets rounded down to one tile.
+        let budget = 56 * tile[0] * 3 / 2;
+        let rows = table
+            .suggest_chunk_rows(&["DATA", "TIME"], Some(budget))
+            .unwrap();
+        assert_eq!(rows, tile[0].min(1000));
+
+        assert_eq!(table.suggest_chunk_rows(&["TIME"], Some(80)).unwrap(), 10);
+        assert!(table.suggest_chunk_rows(&["NAME"], None).is_err());
+    }
+}
@@ -0,0 +1,391 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Copying column data between tables.
+//!
+//! Building a derived data set — a subset of the rows of a Measurement Set,
+//! say, or a copy with single-precision visibilities — mostly consists of
+//! moving column data from one table to another. [`copy_column`] and
+//! [`copy_column_rows`] do this chunk by chunk without the data ever passing
+//! through user code, optionally converting the data type along the way.
+
+use std::os::raw::c_ulong;
+
+use crate::{
+    glue, io_stats::IoDirection, CasaScalarData, Complex, GlueDataType, Progress, Table, TableError,
+};
+
+/// The number of bytes of data to copy per chunk.
+const CHUNK_BYTES: usize = 16 * 1024 * 1024;
+
+/// A data type conversion to apply when copying a column.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Conversion {
+    /// Copy the data unchanged. The two columns must have the same type,
+    /// which may be any numeric or boolean type.
+    Identity,
+
+    /// Convert `Double` data to `Float`.
+    DoubleToFloat,
+
+    /// Convert `Float` data to `Double`.
+    FloatToDouble,
+
+    /// Convert `DComplex` data to `Complex`.
+    DComplexToComplex,
+
+    /// Convert `Complex` data to `DComplex`.
+    ComplexToDComplex,
+}
+
+/// Copy a whole column from one table to another.
+///
+/// Row *i* of *src_col* in *src* is copied to row *i* of *dst_col* in *dst*,
+/// with rows added to *dst* as needed. Both columns must be scalar or have
+/// fixed shapes, their cell shapes must be the same, and their data types
+/// must be as required by *conversion*. Returns the number of rows copied.
+///
+/// ```rust
+/// use rubbl_casatables::{
+///     copy_column, Conversion, GlueDataType, Table, TableCreateMode, TableDesc,
+///     TableDescCreateMode,
+/// };
+/// use tempfile::tempdir;
+///
+/// let tmp_dir = tempdir().unwrap();
+/// let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+/// desc.add_scalar_column(GlueDataType::TpDouble, "X", None, true, false).unwrap();
+/// let mut src = Table::new(tmp_dir.path().join("src.tab"), desc, 3, TableCreateMode::New).unwrap();
+/// src.put_cell("X", 2, &1.5f64).unwrap();
+///
+/// let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+/// desc.add_scalar_column(GlueDataType::TpFloat, "X", None, true, false).unwrap();
+/// let mut dst = Table::new(tmp_dir.path().join("dst.tab"), desc, 0, TableCreateMode::New).unwrap();
+///
+/// assert_eq!(copy_column(&mut src, "X", &mut dst, "X", Conversion::DoubleToFloat).unwrap(), 3);
+/// assert_eq!(dst.get_cell::<f32>("X", 2).unwrap(), 1.5);
+/// ```
+pub fn copy_column(
+    src: &mut Table,
+    src_col: &str,
+    dst: &mut Table,
+    dst_col: &str,
+    conversion: Conversion,
+) -> Result<u64, TableError> {
+    copy_dispatch(
+        src,
+        src_col,
+        Some(dst),
+        dst_col,
+        conversion,
+        None,
+        &Progress::new(),
+    )
+}
+
+/// Copy a whole column from one table to another, reporting progress and
+/// allowing cancellation.
+///
+/// This works like [`copy_column`], except that *progress* is told the
+/// number of rows copied after each chunk, and the copy stops with
+/// [`TableError::Cancelled`] before the next chunk if cancellation is
+/// requested.
+pub fn copy_column_with_progress(
+    src: &mut Table,
+    src_col: &str,
+    dst: &mut Table,
+    dst_col: &str,
+    conversion: Conversion,
+    progress: &Progress,
+) -> Result<u64, TableError> {
+    copy_dispatch(src, src_col, Some(dst), dst_col, conversion, None, progress)
+}
+
+/// Copy selected rows of a column from one table to another.
+///
+/// Row `src_rows[i]` of *src_col* in *src* is copied to row *i* of *dst_col*
+/// in *dst*, with rows added to *dst* as needed. The source rows may be in
+/// any order and may repeat, although reading is fastest when they are
+/// sorted. Otherwise, this works like [`copy_column`].
+pub fn copy_column_rows(
+    src: &mut Table,
+    src_col: &str,
+    dst: &mut Table,
+    dst_col: &str,
+    conversion: Conversion,
+    src_rows: &[u64],
+) -> Result<u64, TableError> {
+    copy_dispatch(
+        src,
+        src_col,
+        Some(dst),
+        dst_col,
+        conversion,
+        Some(src_rows),
+        &Progress::new(),
+    )
+}
+
+/// Copy a whole column to another column of the same table.
+///
+/// This works like [`copy_column`], which cannot be used for this because
+/// it would need two mutable references to the table.
+pub(crate) fn copy_column_within(
+    table: &mut Table,
+    src_col: &str,
+    dst_col: &str,
+    conversion: Conversion,
+) -> Result<u64, TableError> {
+    copy_dispatch(
+        table,
+        src_col,
+        None,
+        dst_col,
+        conversion,
+        None,
+        &Progress::new(),
+    )
+}
+
+/// Copy between *src* and *dst*, where a *dst* of `None` means that the
+/// destination column is in *src*.
+fn copy_dispatch(
+    src: &mut Table,
+    src_col: &str,
+    dst: Option<&mut Table>,
+    dst_col: &str,
+    conversion: Conversion,
+    src_rows: Option<&[u64]>,
+    progress: &Progress,
+) -> Result<u64, TableError> {
+    macro_rules! copy {
+        ($s:ty, $d:ty, $f:expr) => {
+            copy_typed::<$s, $d, _>(src, src_col, dst, dst_col, src_rows, progress, $f)
+        };
+    }
+
+    match conversion {
+        Conversion::DoubleToFloat => copy!(f64, f32, |x| x as f32),
+        Conversion::FloatToDouble => copy!(f32, f64, |x| x as f64),
+        Conversion::DComplexToComplex => {
+            copy!(Complex<f64>, Complex<f32>, |x| Complex::new(
+                x.re as f32,
+                x.im as f32
+            ))
+        }
+        Conversion::ComplexToDComplex => {
+            copy!(Complex<f32>, Complex<f64>, |x| Complex::new(
+                x.re as f64,
+                x.im as f64
+            ))
+        }
+        Conversion::Identity => {
+            use GlueDataType::*;
+
+            match src.get_col_desc(src_col)?.data_type().element_type() {
+                TpBool => copy!(bool, bool, |x| x),
+                TpChar => copy!(i8, i8, |x| x),
+                TpUChar => copy!(u8, u8, |x| x),
+                TpShort => copy!(i16, i16, |x| x),
+                TpUShort => copy!(u16, u16, |x| x),
+                TpInt => copy!(i32, i32, |x| x),
+                TpUInt => copy!(u32, u32, |x| x),
+                TpInt64 => copy!(i64, i64, |x| x),
+                TpFloat => copy!(f32, f32, |x| x),
+                TpDouble => copy!(f64, f64, |x| x),
+                TpComplex => copy!(Complex<f32>, Complex<f32>, |x| x),
+                TpDComplex => copy!(Complex<f64>, Complex<f64>, |x| x),
+                other => Err(TableError::UnsupportedDataType(src_col.to_owned(), other)),
+            }
+        }
+    }
+}
+
+fn copy_typed<S, D, F>(
+    src: &mut Table,
+    src_col: &str,
+    mut dst: Option<&mut Table>,
+    dst_col: &str,
+    src_rows: Option<&[u64]>,
+    progress: &Progress,
+    convert: F,
+) -> Result<u64, TableError>
+where
+    S: CasaScalarData + Copy + Default,
+    D: CasaScalarData + Copy + Default,
+    F: Fn(S) -> D,
+{
+    let src_shape = src.bulk_column_cell_shape::<S>(src_col)?;
+    let dst_shape = target(src, &mut dst).bulk_column_cell_shape::<D>(dst_col)?;
+
+    if src_shape != dst_shape {
+        return Err(TableError::ChunkShapeMismatch(dst_shape, src_shape));
+    }
+
+    let cell_len: usize = src_shape.iter().product();
+    let cell_bytes = (cell_len * std::mem::size_of::<S>()).max(1);
+    let rows_per_chunk = (CHUNK_BYTES / cell_bytes).max(1);
+    let n_total = match src_rows {
+        Some(rows) => rows.len() as u64,
+        None => src.n_rows(),
+    };
+
+    let csrc_col = glue::StringBridge::from_rust(src_col);
+    let cdst_col = glue::StringBridge::from_rust(dst_col);
+    let mut src_buf = vec![S::default(); rows_per_chunk * cell_len];
+    let mut dst_buf = vec![D::default(); rows_per_chunk * cell_len];
+    let mut row_numbers: Vec<c_ulong> = Vec::with_capacity(rows_per_chunk);
+    let mut done = 0;
+
+    while done < n_total {
+        progress.check(done)?;
+        let n = (rows_per_chunk as u64).min(n_total - done);
+        let data = &mut src_buf[..n as usize * cell_len];
+
+        let rv = match src_rows {
+            None => unsafe {
+                glue::table_get_column_range(
+                    src.handle,
+                    &csrc_col,
+                    done,
+                    n,
+                    data.as_mut_ptr() as _,
+                    &mut src.exc_info,
+                )
+            },
+            Some(rows) => {
+                row_numbers.clear();
+                row_numbers.extend(
+                    rows[done as usize..(done + n) as usize]
+                        .iter()
+                        .map(|r| *r as c_ulong),
+                );
+
+                unsafe {
+                    glue::table_get_column_cells(
+                        src.handle,
+                        &csrc_col,
+                        row_numbers.as_ptr(),
+                        n as _,
+                        data.as_mut_ptr() as _,
+                        &mut src.exc_info,
+                    )
+                }
+            }
+        };
+
+        if rv != 0 {
+            return src.exc_info.as_err();
+        }
+
+        src.record_io(
+            IoDirection::Get,
+            src_col,
+            n,
+            S::DATA_TYPE,
+            data.len() as u64,
+        );
+
+        for (d, s) in dst_buf.iter_mut().zip(data.iter()) {
+            *d = convert(*s);
+        }
+
+        let dst = target(src, &mut dst);
+
+        if done + n > dst.n_rows() {
+            dst.add_rows((done + n - dst.n_rows()) as usize)?;
+        }
+
+        if unsafe {
+            glue::table_put_column_range(
+                dst.handle,
+                &cdst_col,
+                done,
+                n,
+                dst_buf.as_ptr() as _,
+                &mut dst.exc_info,
+            )
+        } != 0
+        {
+            return dst.exc_info.as_err();
+        }
+
+        dst.record_io(
+            IoDirection::Put,
+            dst_col,
+            n,
+            D::DATA_TYPE,
+            data.len() as u64,
+        );
+
+        done += n;
+        progress.report(done, Some(n_total));
+    }
+
+    Ok(done)
+}
+
+fn target<'a>(src: &'a mut Table, dst: &'a mut Option<&mut Table>) -> &'a mut Table {
+    match dst {
+        Some(d) => d,
+        None => src,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    fn make_table(path: std::path::PathBuf, ty: GlueDataType, n_rows: usize) -> Table {
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_array_column(ty, "DATA", None, Some(&[2, 3]), true, false)
+            .unwrap();
+        Table::new(path, desc, n_rows, TableCreateMode::New).unwrap()
+    }
+
+    #[test]
+    fn copy_with_conversion_and_remapping() {
+        let tmp_dir = tempdir().unwrap();
+        let mut src = make_table(tmp_dir.path().join("src.tab"), GlueDataType::TpDComplex, 5);
+
+        for row in 0..5 {
+            let cell = ndarray::Array2::from_elem((2, 3), Complex::new(row as f64, 0.5));
+            src.put_cell("DATA", row, &cell).unwrap();
+        }
+
+        let mut dst = make_table(tmp_dir.path().join("dst.tab"), GlueDataType::TpComplex, 0);
+        let n = copy_column_rows(
+            &mut src,
+            "DATA",
+            &mut dst,
+            "DATA",
+            Conversion::DComplexToComplex,
+            &[4, 0, 4],
+        )
+        .unwrap();
+        assert_eq!(n, 3);
+        assert_eq!(dst.n_rows(), 3);
+
+        let cell: Vec<Complex<f32>> = dst.get_cell_as_vec("DATA", 0).unwrap();
+        assert_eq!(cell, vec![Complex::new(4., 0.5); 6]);
+        let cell: Vec<Complex<f32>> = dst.get_cell_as_vec("DATA", 1).unwrap();
+        assert_eq!(cell, vec![Complex::new(0., 0.5); 6]);
+
+        let mut same = make_table(tmp_dir.path().join("same.tab"), GlueDataType::TpDComplex, 0);
+        copy_column(&mut src, "DATA", &mut same, "DATA", Conversion::Identity).unwrap();
+        let cell: Vec<Complex<f64>> = same.get_cell_as_vec("DATA", 3).unwrap();
+        assert_eq!(cell, vec![Complex::new(3., 0.5); 6]);
+
+        assert!(copy_column(&mut src, "DATA", &mut dst, "DATA", Conversion::Identity).is_err());
+        assert!(copy_column(
+            &mut src,
+            "DATA",
+            &mut dst,
+            "DATA",
+            Conversion::FloatToDouble
+        )
+        .is_err());
+    }
+}
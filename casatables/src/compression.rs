@@ -0,0 +1,403 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Estimating how well a column’s data would compress.
+//!
+//! Rewriting a multi-terabyte Measurement Set with a different storage
+//! manager is expensive, so it is worth knowing in advance what it will
+//! gain. [`Table::estimate_compressibility`] reads a sample of a column’s
+//! cells and models a few candidate codecs on them. The estimates are
+//! cheap to compute and good enough to rank the options, but they are not
+//! the output of the codecs themselves: actual sizes will differ somewhat.
+
+use std::mem::size_of;
+
+use crate::{CasaScalarData, Complex, GlueDataType, Table, TableError};
+
+/// The Dysco bit depths that are modeled by
+/// [`Table::estimate_compressibility`].
+pub const DYSCO_BIT_DEPTHS: &[u32] = &[4, 6, 8, 10, 12];
+
+/// The truncation of the quantization range used to model Dysco, in units
+/// of the standard deviation of the data.
+const DYSCO_TRUNCATION: f64 = 2.5;
+
+/// A codec that might be used to store a column.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CandidateCodec {
+    /// A general-purpose lossless compressor, such as zstd, applied after
+    /// shuffling the bytes of the elements so that like bytes are adjacent.
+    ///
+    /// The ratio is estimated from the order-0 entropy of each byte of the
+    /// elements, which is about what such compressors achieve on noisy
+    /// numerical data; data with long repeated runs will do better.
+    Zstd,
+
+    /// The Dysco storage manager, quantizing each real number to the given
+    /// number of bits.
+    ///
+    /// This only applies to single-precision float and complex columns. The
+    /// error is modeled by uniform quantization of each cell’s values over
+    /// ±2.5 standard deviations, which approximates Dysco’s truncated
+    /// Gaussian quantizer; the ratio ignores Dysco’s small per-row overhead.
+    Dysco {
+        /// The number of bits per real number.
+        bits: u32,
+    },
+}
+
+/// The estimated performance of one codec on a column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CodecEstimate {
+    /// The codec.
+    pub codec: CandidateCodec,
+
+    /// The estimated ratio of the uncompressed size to the compressed size.
+    pub ratio: f64,
+
+    /// The estimated RMS error introduced by the codec, relative to the RMS
+    /// of the data. This is zero for lossless codecs.
+    pub relative_rms_error: f64,
+}
+
+/// An estimate of how well a column’s data would compress, as returned by
+/// [`Table::estimate_compressibility`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompressibilityEstimate {
+    /// The number of rows that were sampled.
+    pub n_sampled_rows: u64,
+
+    /// The number of bytes of uncompressed data in the sampled cells.
+    pub sampled_bytes: u64,
+
+    /// The order-0 entropy of the bytes of the sampled data, in bits per
+    /// byte. This ranges from zero, for data that are all the same byte, to
+    /// eight, for data that look random.
+    pub entropy_bits_per_byte: f64,
+
+    /// The estimates for each applicable codec.
+    pub codecs: Vec<CodecEstimate>,
+}
+
+impl CompressibilityEstimate {
+    /// Get the estimate with the highest compression ratio whose relative
+    /// RMS error does not exceed *max_error*.
+    pub fn best(&self, max_error: f64) -> Option<&CodecEstimate> {
+        self.codecs
+            .iter()
+            .filter(|c| c.relative_rms_error <= max_error)
+            .max_by(|a, b| a.ratio.total_cmp(&b.ratio))
+    }
+}
+
+/// Counts of byte values, overall and for each byte of an element.
+struct ByteHistograms {
+    overall: [u64; 256],
+    lanes: Vec<[u64; 256]>,
+}
+
+impl ByteHistograms {
+    fn new(elem_size: usize) -> Self {
+        ByteHistograms {
+            overall: [0; 256],
+            lanes: vec![[0; 256]; elem_size.max(1)],
+        }
+    }
+
+    fn add(&mut self, bytes: &[u8]) {
+        let n_lanes = self.lanes.len();
+
+        for (i, b) in bytes.iter().enumerate() {
+            self.overall[*b as usize] += 1;
+            self.lanes[i % n_lanes][*b as usize] += 1;
+        }
+    }
+
+    fn n_bytes(&self) -> u64 {
+        self.overall.iter().sum()
+    }
+}
+
+/// Get the order-0 entropy of a histogram, in bits per symbol.
+fn entropy(counts: &[u64; 256]) -> f64 {
+    let total: u64 = counts.iter().sum();
+
+    if total == 0 {
+        return 0.;
+    }
+
+    counts
+        .iter()
+        .filter(|c| **c > 0)
+        .map(|c| {
+            let p = *c as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Accumulates the squared error of modeled Dysco quantization.
+struct DyscoErrors {
+    signal: f64,
+    errors: Vec<f64>,
+}
+
+impl DyscoErrors {
+    fn new() -> Self {
+        DyscoErrors {
+            signal: 0.,
+            errors: vec![0.; DYSCO_BIT_DEPTHS.len()],
+        }
+    }
+
+    fn add(&mut self, values: &[f32]) {
+        let finite: Vec<f64> = values
+            .iter()
+            .filter(|v| v.is_finite())
+            .map(|v| *v as f64)
+            .collect();
+
+        if finite.is_empty() {
+            return;
+        }
+
+        let n = finite.len() as f64;
+        let mean = finite.iter().sum::<f64>() / n;
+        let sigma = (finite.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+        self.signal += finite.iter().map(|v| v * v).sum::<f64>();
+
+        if sigma == 0. {
+            return;
+        }
+
+        let lo = mean - DYSCO_TRUNCATION * sigma;
+        let hi = mean + DYSCO_TRUNCATION * sigma;
+
+        for (bits, err) in DYSCO_BIT_DEPTHS.iter().zip(self.errors.iter_mut()) {
+            let levels = ((1u64 << bits) - 1) as f64;
+            let step = (hi - lo) / levels;
+
+            for v in &finite {
+                let q = lo + ((v.clamp(lo, hi) - lo) / step).round() * step;
+                *err += (v - q).powi(2);
+            }
+        }
+    }
+}
+
+/// Get the elements of a sampled cell as bytes.
+fn as_bytes<T: Copy>(values: &[T]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values))
+    }
+}
+
+impl Table {
+    /// Read one cell of a column, scalar or array, as a vector.
+    fn sample_cell<T: CasaScalarData>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+        is_scalar: bool,
+    ) -> Result<Vec<T>, TableError> {
+        if is_scalar {
+            Ok(vec![self.get_cell(col_name, row)?])
+        } else {
+            self.get_cell_as_vec(col_name, row)
+        }
+    }
+
+    /// Estimate how well the data in a column would compress with various
+    /// codecs.
+    ///
+    /// Up to *sample_rows* rows, spread evenly through the table, are read.
+    /// The byte entropy of their data gives an estimate of what a lossless
+    /// compressor would achieve, and for single-precision float and complex
+    /// columns, Dysco is modeled at each of the bit depths in
+    /// [`DYSCO_BIT_DEPTHS`]. See [`CandidateCodec`] for how each estimate is
+    /// made. String columns are sampled as their UTF-8 bytes.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{Table, TableOpenMode};
+    ///
+    /// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+    /// let est = t.estimate_compressibility("DATA", 1000).unwrap();
+    ///
+    /// for codec in &est.codecs {
+    ///     println!("{:?}: {:.1}x, error {:.3}", codec.codec, codec.ratio, codec.relative_rms_error);
+    /// }
+    /// ```
+    pub fn estimate_compressibility(
+        &mut self,
+        col_name: &str,
+        sample_rows: usize,
+    ) -> Result<CompressibilityEstimate, TableError> {
+        let desc = self.get_col_desc(col_name)?;
+        let is_scalar = desc.is_scalar();
+        let data_type = desc.data_type();
+        let n_rows = self.n_rows();
+        let n_samples = (sample_rows as u64).min(n_rows);
+
+        let elem_size = match data_type {
+            GlueDataType::TpBool | GlueDataType::TpChar | GlueDataType::TpUChar => 1,
+            GlueDataType::TpShort | GlueDataType::TpUShort => 2,
+            GlueDataType::TpInt | GlueDataType::TpUInt | GlueDataType::TpFloat => 4,
+            GlueDataType::TpInt64 | GlueDataType::TpDouble => 8,
+            GlueDataType::TpComplex => size_of::<Complex<f32>>(),
+            GlueDataType::TpDComplex => size_of::<Complex<f64>>(),
+            GlueDataType::TpString => 1,
+            other => return Err(TableError::UnsupportedDataType(col_name.to_owned(), other)),
+        };
+
+        let mut hists = ByteHistograms::new(elem_size);
+        let mut dysco = match data_type {
+            GlueDataType::TpFloat | GlueDataType::TpComplex => Some(DyscoErrors::new()),
+            _ => None,
+        };
+
+        for i in 0..n_samples {
+            let row = i * n_rows / n_samples;
+
+            macro_rules! sample {
+                ($t:ty) => {{
+                    let values: Vec<$t> = self.sample_cell(col_name, row, is_scalar)?;
+                    hists.add(as_bytes(&values));
+                }};
+            }
+
+            match data_type {
+                GlueDataType::TpBool => sample!(bool),
+                GlueDataType::TpChar => sample!(i8),
+                GlueDataType::TpUChar => sample!(u8),
+                GlueDataType::TpShort => sample!(i16),
+                GlueDataType::TpUShort => sample!(u16),
+                GlueDataType::TpInt => sample!(i32),
+                GlueDataType::TpUInt => sample!(u32),
+                GlueDataType::TpInt64 => sample!(i64),
+                GlueDataType::TpDouble => sample!(f64),
+                GlueDataType::TpDComplex => sample!(Complex<f64>),
+
+                GlueDataType::TpFloat => {
+                    let values: Vec<f32> = self.sample_cell(col_name, row, is_scalar)?;
+                    hists.add(as_bytes(&values));
+                    dysco.as_mut().unwrap().add(&values);
+                }
+
+                GlueDataType::TpComplex => {
+                    let values: Vec<Complex<f32>> = self.sample_cell(col_name, row, is_scalar)?;
+                    hists.add(as_bytes(&values));
+                    let reals: Vec<f32> = values.iter().flat_map(|c| [c.re, c.im]).collect();
+                    dysco.as_mut().unwrap().add(&reals);
+                }
+
+                _ => {
+                    let values: Vec<String> = self.sample_cell(col_name, row, is_scalar)?;
+
+                    for s in &values {
+                        hists.add(s.as_bytes());
+                    }
+                }
+            }
+        }
+
+        let shuffled_bits: f64 = hists.lanes.iter().map(entropy).sum::<f64>() / elem_size as f64;
+        let mut codecs = vec![CodecEstimate {
+            codec: CandidateCodec::Zstd,
+            ratio: 8. / shuffled_bits.max(8. / 1024.),
+            relative_rms_error: 0.,
+        }];
+
+        if let Some(dysco) = dysco {
+            for (bits, err) in DYSCO_BIT_DEPTHS.iter().zip(dysco.errors.iter()) {
+                codecs.push(CodecEstimate {
+                    codec: CandidateCodec::Dysco { bits: *bits },
+                    ratio: 32. / *bits as f64,
+                    relative_rms_error: if dysco.signal > 0. {
+                        (err / dysco.signal).sqrt()
+                    } else {
+                        0.
+                    },
+                });
+            }
+        }
+
+        Ok(CompressibilityEstimate {
+            n_sampled_rows: n_samples,
+            sampled_bytes: hists.n_bytes(),
+            entropy_bits_per_byte: entropy(&hists.overall),
+            codecs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn compressibility() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "FLAT", None, false, false)
+            .unwrap();
+        desc.add_array_column(
+            GlueDataType::TpComplex,
+            "DATA",
+            None,
+            Some(&[64]),
+            false,
+            false,
+        )
+        .unwrap();
+        let mut t = Table::new(tmp_dir.path().join("t"), desc, 100, TableCreateMode::New).unwrap();
+
+        // Pseudo-random noise, which should barely compress losslessly.
+        let mut state = 12345u64;
+        let mut noise = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            ((state >> 11) as f64 / (1u64 << 53) as f64 - 0.5) as f32
+        };
+
+        for row in 0..100 {
+            t.put_cell("FLAT", row, &7i32).unwrap();
+            let data: Vec<Complex<f32>> = (0..64).map(|_| Complex::new(noise(), noise())).collect();
+            t.put_cell("DATA", row, &data).unwrap();
+        }
+
+        let flat = t.estimate_compressibility("FLAT", 10).unwrap();
+        assert_eq!(flat.n_sampled_rows, 10);
+        assert_eq!(flat.sampled_bytes, 40);
+        // The bytes are 7, 0, 0, 0, over and over.
+        assert!((flat.entropy_bits_per_byte - 0.811).abs() < 1e-3);
+        assert_eq!(flat.codecs.len(), 1);
+        assert!(flat.codecs[0].ratio > 100.);
+
+        let data = t.estimate_compressibility("DATA", 1000).unwrap();
+        assert_eq!(data.n_sampled_rows, 100);
+        assert_eq!(data.codecs.len(), 1 + DYSCO_BIT_DEPTHS.len());
+        assert!(data.codecs[0].ratio < 2.);
+
+        let dysco8 = data
+            .codecs
+            .iter()
+            .find(|c| c.codec == CandidateCodec::Dysco { bits: 8 })
+            .unwrap();
+        assert_eq!(dysco8.ratio, 4.);
+        assert!(dysco8.relative_rms_error > 0. && dysco8.relative_rms_error < 0.02);
+
+        // More bits mean less error.
+        let errors: Vec<f64> = data.codecs[1..]
+            .iter()
+            .map(|c| c.relative_rms_error)
+            .collect();
+        assert!(errors.windows(2).all(|w| w[0] > w[1]));
+
+        assert_eq!(data.best(0.).unwrap().codec, CandidateCodec::Zstd);
+        assert_eq!(
+            data.best(0.05).unwrap().codec,
+            CandidateCodec::Dysco { bits: 6 }
+        );
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Global configuration of casacore.
+//!
+//! casacore reads many settings — the location of the measures data, the
+//! caching behavior of the tiled storage managers, and so on — from
+//! “Aipsrc” resource files such as `~/.casarc`. Many of these settings are
+//! read once, the first time that they are needed, and then cached for the
+//! life of the process. [`configure`] supplies settings from Rust, and
+//! because of this caching it must be called before any table is opened or
+//! created.
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+use thiserror::Error;
+
+use crate::{glue, CasacoreError};
+
+/// Set once casacore has been used in a way that might read its settings.
+static CASACORE_USED: AtomicBool = AtomicBool::new(false);
+
+/// Set once [`configure`] has succeeded.
+static CONFIGURED: Mutex<bool> = Mutex::new(false);
+
+/// Record that casacore may have read its settings, after which it can no
+/// longer be configured.
+pub(crate) fn note_casacore_used() {
+    CASACORE_USED.store(true, Ordering::SeqCst);
+}
+
+/// An error that can occur when configuring casacore.
+#[derive(Error, Debug)]
+pub enum ConfigureError {
+    /// casacore has already been used, so it may have cached its settings.
+    #[error("casacore must be configured before any tables are opened or created")]
+    TooLate,
+
+    /// [`configure`] has already been called.
+    #[error("casacore has already been configured")]
+    AlreadyConfigured,
+
+    /// A keyword cannot be represented in a resource file.
+    #[error("invalid casacore resource setting \"{0}\"")]
+    InvalidSetting(String),
+
+    /// casacore failed to reload its settings.
+    #[error(transparent)]
+    Casacore(#[from] CasacoreError),
+}
+
+/// Settings to pass to casacore with [`configure`].
+///
+/// Settings are Aipsrc keyword/value pairs. Settings given here take
+/// precedence over those in the user’s resource files, which remain in
+/// effect otherwise.
+///
+/// ```rust
+/// use rubbl_casatables::CasacoreConfig;
+///
+/// let config = CasacoreConfig::new()
+///     .measures_directory("/opt/casa/data")
+///     .tsm_max_cache_size_mb(512);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CasacoreConfig {
+    settings: Vec<(String, String)>,
+}
+
+impl CasacoreConfig {
+    /// Create a configuration with no settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set an arbitrary Aipsrc keyword.
+    pub fn set(mut self, keyword: &str, value: &str) -> Self {
+        self.settings.retain(|(k, _)| k != keyword);
+        self.settings.push((keyword.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Set the directory containing the measures data (`measures.directory`).
+    pub fn measures_directory<P: AsRef<Path>>(self, path: P) -> Self {
+        let path = path.as_ref().to_string_lossy().into_owned();
+        self.set("measures.directory", &path)
+    }
+
+    /// Set the tiled storage manager access option (`table.tsm.option`),
+    /// which is one of `default`, `cache`, `mmap`, or `buffer`.
+    pub fn tsm_option(self, option: &str) -> Self {
+        self.set("table.tsm.option", option)
+    }
+
+    /// Set the maximum size of the tiled storage manager cache, in megabytes
+    /// (`table.tsm.maxcachesizemb`).
+    pub fn tsm_max_cache_size_mb(self, size: i32) -> Self {
+        self.set("table.tsm.maxcachesizemb", &size.to_string())
+    }
+
+    /// Set the byte order used for new tables (`table.endianformat`), which
+    /// is one of `big`, `little`, or `local`.
+    pub fn endian_format(self, format: &str) -> Self {
+        self.set("table.endianformat", format)
+    }
+
+    /// Get the settings, in the order that they were given.
+    pub fn settings(&self) -> &[(String, String)] {
+        &self.settings[..]
+    }
+
+    /// Check that every keyword could appear in an Aipsrc file.
+    fn validate(&self) -> Result<(), ConfigureError> {
+        for (k, _) in &self.settings {
+            if k.is_empty() || k.contains(|c: char| c == ':' || c.is_whitespace()) {
+                return Err(ConfigureError::InvalidSetting(k.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Configure casacore.
+///
+/// casacore is told to reload its resource files, and the settings are then
+/// added to the ones that it read, ahead of them. Neither the process
+/// environment nor any file is modified.
+///
+/// This function must be called at most once, before any table is opened or
+/// created. It fails with [`ConfigureError::TooLate`] or
+/// [`ConfigureError::AlreadyConfigured`] if these rules are broken.
+/// casacore does not expect its settings to change while it is in use, so
+/// nothing else may call `casacore::Aipsrc::reRead` afterwards, or the
+/// settings will be lost.
+pub fn configure(config: CasacoreConfig) -> Result<(), ConfigureError> {
+    let mut configured = CONFIGURED.lock().unwrap_or_else(|e| e.into_inner());
+
+    if *configured {
+        return Err(ConfigureError::AlreadyConfigured);
+    }
+
+    if CASACORE_USED.load(Ordering::SeqCst) {
+        return Err(ConfigureError::TooLate);
+    }
+
+    config.validate()?;
+
+    let keywords: Vec<_> = config
+        .settings
+        .iter()
+        .map(|(k, _)| glue::StringBridge::from_rust(k))
+        .collect();
+    let values: Vec<_> = config
+        .settings
+        .iter()
+        .map(|(_, v)| glue::StringBridge::from_rust(v))
+        .collect();
+    let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+
+    if unsafe {
+        glue::casacore_set_rc(
+            keywords.len() as std::os::raw::c_ulong,
+            keywords.as_ptr(),
+            values.as_ptr(),
+            &mut exc_info,
+        )
+    } != 0
+    {
+        return exc_info.as_err();
+    }
+
+    *configured = true;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings() {
+        let config = CasacoreConfig::new()
+            .measures_directory("/data")
+            .tsm_option("cache")
+            .tsm_option("mmap");
+        assert_eq!(
+            config.settings(),
+            &[
+                ("measures.directory".to_owned(), "/data".to_owned()),
+                ("table.tsm.option".to_owned(), "mmap".to_owned()),
+            ]
+        );
+        assert!(config.validate().is_ok());
+
+        let bad = CasacoreConfig::new().set("table tsm", "x");
+        assert!(bad.validate().is_err());
+        let bad = CasacoreConfig::new().set("a:b", "x");
+        assert!(bad.validate().is_err());
+    }
+}
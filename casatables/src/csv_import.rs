@@ -0,0 +1,547 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Creating tables from CSV files.
+//!
+//! Tables such as a Measurement Set’s `ANTENNA` table often start life as a
+//! spreadsheet: a site survey gives the name and position of each antenna
+//! pad, say. [`Table::import_csv`] creates a table from such a spreadsheet,
+//! exported as CSV, given a [`CsvSchema`] that maps the CSV fields to typed
+//! table columns.
+//!
+//! ```no_run
+//! use rubbl_casatables::{CsvSchema, GlueDataType, Table, TableCreateMode};
+//!
+//! // The CSV has the header `Pad,X,Y,Z,Diameter`.
+//! let mut schema = CsvSchema::new();
+//! schema
+//!     .scalar_from(GlueDataType::TpString, "NAME", "Pad")
+//!     .array(GlueDataType::TpDouble, "POSITION", &["X", "Y", "Z"])
+//!     .scalar_from(GlueDataType::TpDouble, "DISH_DIAMETER", "Diameter");
+//!
+//! let t = Table::import_csv("ANTENNA", "survey.csv", &schema, TableCreateMode::New).unwrap();
+//! ```
+
+use std::{fs, io, path::Path};
+use thiserror::Error;
+
+use crate::{
+    CasaDataType, CasaScalarData, Complex, GlueDataType, Table, TableCreateMode, TableDesc,
+    TableDescCreateMode, TableError,
+};
+
+/// An error that can occur when importing a CSV file.
+#[derive(Error, Debug)]
+pub enum CsvImportError {
+    /// An error occurred while creating or writing the table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// An error occurred while reading the CSV file.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The CSV file is malformed.
+    #[error("CSV syntax error on line {line}: {message}")]
+    Syntax {
+        /// The line number, starting at 1.
+        line: usize,
+
+        /// A description of the problem.
+        message: String,
+    },
+
+    /// The schema refers to a field that is not in the CSV header.
+    #[error("the CSV file has no field named \"{0}\"")]
+    MissingField(String),
+
+    /// A field’s value could not be parsed as the type of its column.
+    #[error("cannot parse \"{value}\" in field \"{field}\" on line {line} as {data_type}")]
+    Parse {
+        /// The line number, starting at 1.
+        line: usize,
+
+        /// The name of the CSV field.
+        field: String,
+
+        /// The text of the field.
+        value: String,
+
+        /// The element type of the column.
+        data_type: GlueDataType,
+    },
+}
+
+impl From<crate::CasacoreError> for CsvImportError {
+    fn from(e: crate::CasacoreError) -> Self {
+        CsvImportError::Table(e.into())
+    }
+}
+
+/// A column of a table to be created from a CSV file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CsvColumn {
+    /// The name of the column.
+    pub name: String,
+
+    /// The element type of the column, such as `TpDouble`.
+    pub data_type: GlueDataType,
+
+    /// The CSV fields holding the column’s values. If there is one, the
+    /// column is scalar. Otherwise, it is an array column whose cells have
+    /// one element per field.
+    pub fields: Vec<String>,
+}
+
+/// A mapping from the fields of a CSV file to the columns of a table.
+///
+/// Fields of the CSV file that are not mentioned are ignored.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CsvSchema {
+    /// The columns to create.
+    pub columns: Vec<CsvColumn>,
+
+    /// The character separating fields. The default is a comma.
+    pub delimiter: char,
+}
+
+impl Default for CsvSchema {
+    fn default() -> Self {
+        CsvSchema {
+            columns: Vec::new(),
+            delimiter: ',',
+        }
+    }
+}
+
+impl CsvSchema {
+    /// Create an empty schema.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a scalar column filled from the CSV field of the same name.
+    pub fn scalar(&mut self, data_type: GlueDataType, col_name: &str) -> &mut Self {
+        self.scalar_from(data_type, col_name, col_name)
+    }
+
+    /// Add a scalar column filled from the CSV field *field*.
+    pub fn scalar_from(
+        &mut self,
+        data_type: GlueDataType,
+        col_name: &str,
+        field: &str,
+    ) -> &mut Self {
+        self.columns.push(CsvColumn {
+            name: col_name.to_owned(),
+            data_type,
+            fields: vec![field.to_owned()],
+        });
+        self
+    }
+
+    /// Add a one-dimensional, fixed-shape array column whose cells gather
+    /// the values of several CSV fields, in order.
+    pub fn array(&mut self, data_type: GlueDataType, col_name: &str, fields: &[&str]) -> &mut Self {
+        self.columns.push(CsvColumn {
+            name: col_name.to_owned(),
+            data_type,
+            fields: fields.iter().map(|f| (*f).to_owned()).collect(),
+        });
+        self
+    }
+
+    /// Set the character separating fields.
+    pub fn delimiter(&mut self, delimiter: char) -> &mut Self {
+        self.delimiter = delimiter;
+        self
+    }
+}
+
+/// An element type that can be parsed from a CSV field.
+trait CsvElement: CasaScalarData + Sized {
+    fn parse_field(text: &str) -> Option<Self>;
+}
+
+impl CsvElement for bool {
+    fn parse_field(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "true" | "t" | "1" => Some(true),
+            "false" | "f" | "0" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_csv_parse {
+    ($($t:ty),*) => {
+        $(
+            impl CsvElement for $t {
+                fn parse_field(text: &str) -> Option<Self> {
+                    text.trim().parse().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_csv_parse! { i8, u8, i16, u16, i32, u32, i64, f32, f64, Complex<f32>, Complex<f64> }
+
+impl CsvElement for String {
+    fn parse_field(text: &str) -> Option<Self> {
+        Some(text.to_owned())
+    }
+}
+
+/// Split CSV text into records, following RFC 4180: fields may be quoted,
+/// and quoted fields may contain delimiters, newlines, and doubled quotes.
+/// Records may end with CRLF, LF, or a bare CR; line breaks inside quoted
+/// fields are kept as they are. Each record is returned with the line number
+/// on which it starts. Blank lines are skipped.
+fn parse_records(text: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, CsvImportError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut in_quotes = false;
+    let mut was_quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                '\r' => {
+                    if chars.peek() != Some(&'\n') {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() && !was_quoted => {
+                in_quotes = true;
+                was_quoted = true;
+            }
+
+            '"' => {
+                return Err(CsvImportError::Syntax {
+                    line,
+                    message: "unexpected quote".to_owned(),
+                })
+            }
+
+            c if c == delimiter => {
+                record.push(std::mem::take(&mut field));
+                was_quoted = false;
+            }
+
+            '\r' | '\n' => {
+                if c == '\r' && chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+
+                if !record.is_empty() || !field.is_empty() || was_quoted {
+                    record.push(std::mem::take(&mut field));
+                    records.push((record_line, std::mem::take(&mut record)));
+                }
+
+                was_quoted = false;
+                line += 1;
+                record_line = line;
+            }
+
+            _ if was_quoted => {
+                return Err(CsvImportError::Syntax {
+                    line,
+                    message: "unexpected text after a quoted field".to_owned(),
+                })
+            }
+
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(CsvImportError::Syntax {
+            line,
+            message: "unterminated quoted field".to_owned(),
+        });
+    }
+
+    if !record.is_empty() || !field.is_empty() || was_quoted {
+        record.push(field);
+        records.push((record_line, record));
+    }
+
+    Ok(records)
+}
+
+/// Parse and store one cell. Empty fields leave the cell with its default
+/// value, or undefined for array columns.
+fn put_csv_cell<T: CsvElement>(
+    table: &mut Table,
+    col: &CsvColumn,
+    row: u64,
+    line: usize,
+    values: &[&str],
+) -> Result<(), CsvImportError>
+where
+    Vec<T>: CasaDataType,
+{
+    if values.iter().all(|v| v.is_empty()) {
+        return Ok(());
+    }
+
+    let mut parsed = Vec::with_capacity(values.len());
+
+    for (field, value) in col.fields.iter().zip(values) {
+        parsed.push(T::parse_field(value).ok_or_else(|| CsvImportError::Parse {
+            line,
+            field: field.clone(),
+            value: (*value).to_owned(),
+            data_type: col.data_type,
+        })?);
+    }
+
+    if col.fields.len() == 1 {
+        table.put_cell(&col.name, row, &parsed[0])?;
+    } else {
+        table.put_cell(&col.name, row, &parsed)?;
+    }
+
+    Ok(())
+}
+
+impl Table {
+    /// Create a table at *path* from the CSV file *csv_path*.
+    ///
+    /// The first record of the CSV file must be a header naming its fields.
+    /// Each following record becomes a row of the table, with columns filled
+    /// from the fields as described by *schema*. Numeric fields may be
+    /// surrounded by whitespace; boolean fields may be `true`, `t`, or `1`,
+    /// or `false`, `f`, or `0`, in any case; and complex fields are written
+    /// like `1.5+2i`. Empty fields leave cells at their default values, and
+    /// array cells undefined.
+    pub fn import_csv<P: AsRef<Path>, Q: AsRef<Path>>(
+        path: P,
+        csv_path: Q,
+        schema: &CsvSchema,
+        mode: TableCreateMode,
+    ) -> Result<Table, CsvImportError> {
+        let text = fs::read_to_string(csv_path)?;
+        let mut records = parse_records(&text, schema.delimiter)?.into_iter();
+
+        let header = match records.next() {
+            Some((_, header)) => header,
+            None => {
+                return Err(CsvImportError::Syntax {
+                    line: 1,
+                    message: "missing header".to_owned(),
+                })
+            }
+        };
+
+        let mut indices = Vec::with_capacity(schema.columns.len());
+
+        for col in &schema.columns {
+            let mut col_indices = Vec::with_capacity(col.fields.len());
+
+            for field in &col.fields {
+                match header.iter().position(|h| h.trim() == field) {
+                    Some(i) => col_indices.push(i),
+                    None => return Err(CsvImportError::MissingField(field.clone())),
+                }
+            }
+
+            indices.push(col_indices);
+        }
+
+        let records: Vec<_> = records.collect();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH)?;
+
+        for col in &schema.columns {
+            if col.fields.len() == 1 {
+                desc.add_scalar_column(col.data_type, &col.name, None, true, false)?;
+            } else {
+                desc.add_array_column(
+                    col.data_type,
+                    &col.name,
+                    None,
+                    Some(&[col.fields.len() as u64]),
+                    true,
+                    false,
+                )?;
+            }
+        }
+
+        let mut table = Table::new(path, desc, records.len(), mode)?;
+
+        for (row, (line, record)) in records.iter().enumerate() {
+            if record.len() != header.len() {
+                return Err(CsvImportError::Syntax {
+                    line: *line,
+                    message: format!(
+                        "expected {} fields, but found {}",
+                        header.len(),
+                        record.len()
+                    ),
+                });
+            }
+
+            for (col, col_indices) in schema.columns.iter().zip(&indices) {
+                let values: Vec<&str> = col_indices.iter().map(|i| record[*i].as_str()).collect();
+                let row = row as u64;
+
+                macro_rules! put {
+                    ($t:ty) => {
+                        put_csv_cell::<$t>(&mut table, col, row, *line, &values)
+                    };
+                }
+
+                match col.data_type {
+                    GlueDataType::TpBool => put!(bool),
+                    GlueDataType::TpChar => put!(i8),
+                    GlueDataType::TpUChar => put!(u8),
+                    GlueDataType::TpShort => put!(i16),
+                    GlueDataType::TpUShort => put!(u16),
+                    GlueDataType::TpInt => put!(i32),
+                    GlueDataType::TpUInt => put!(u32),
+                    GlueDataType::TpInt64 => put!(i64),
+                    GlueDataType::TpFloat => put!(f32),
+                    GlueDataType::TpDouble => put!(f64),
+                    GlueDataType::TpComplex => put!(Complex<f32>),
+                    GlueDataType::TpDComplex => put!(Complex<f64>),
+                    GlueDataType::TpString => put!(String),
+                    other => Err(TableError::UnsupportedDataType(col.name.clone(), other).into()),
+                }?;
+            }
+        }
+
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn records() {
+        let records =
+            parse_records("a,b\r\n\"x, \"\"y\"\"\",2\n\n\"multi\nline\",\n", ',').unwrap();
+        assert_eq!(
+            records,
+            vec![
+                (1, vec!["a".to_owned(), "b".to_owned()]),
+                (2, vec!["x, \"y\"".to_owned(), "2".to_owned()]),
+                (4, vec!["multi\nline".to_owned(), "".to_owned()]),
+            ]
+        );
+
+        assert!(parse_records("\"a\"b\n", ',').is_err());
+        assert!(parse_records("\"a\n", ',').is_err());
+    }
+
+    #[test]
+    fn records_edge_cases() {
+        let rec = |line: usize, fields: &[&str]| {
+            (line, fields.iter().map(|f| (*f).to_owned()).collect::<Vec<_>>())
+        };
+
+        // Line breaks inside quoted fields, with CRLF line endings.
+        assert_eq!(
+            parse_records("\"a\r\nb\",\"c\nd\"\r\ne,f\r\n", ',').unwrap(),
+            vec![rec(1, &["a\r\nb", "c\nd"]), rec(4, &["e", "f"])]
+        );
+
+        // Doubled quotes at the start and end of fields, and empty quoted
+        // fields.
+        assert_eq!(
+            parse_records("\"\"\"a\",\"b\"\"\",\"\"\"\",\"\"\r\n\"\"\n", ',').unwrap(),
+            vec![rec(1, &["\"a", "b\"", "\"", ""]), rec(2, &[""])]
+        );
+
+        // Trailing delimiters, with and without a final line ending.
+        assert_eq!(
+            parse_records("a,b,\r\n,\r\nc,", ',').unwrap(),
+            vec![rec(1, &["a", "b", ""]), rec(2, &["", ""]), rec(3, &["c", ""])]
+        );
+
+        // A quoted field at the end of a CRLF line, a final CR, and bare CR
+        // line endings.
+        assert_eq!(
+            parse_records("\"a\"\r\n\"b\"\r", ',').unwrap(),
+            vec![rec(1, &["a"]), rec(2, &["b"])]
+        );
+        assert_eq!(
+            parse_records("a;b\rc;d\r", ';').unwrap(),
+            vec![rec(1, &["a", "b"]), rec(2, &["c", "d"])]
+        );
+
+        assert!(parse_records("a,\"b\"\"\n", ',').is_err());
+        assert!(parse_records("a,b\"\n", ',').is_err());
+    }
+
+    #[test]
+    fn import() {
+        let tmp_dir = tempdir().unwrap();
+        let csv_path = tmp_dir.path().join("survey.csv");
+        fs::write(
+            &csv_path,
+            "Pad,X,Y,Z,Diameter,Notes\n\
+             N01, 1.5,-2,3e3,12,\"resurveyed, 2019\"\n\
+             N02,4,5,6,,\n",
+        )
+        .unwrap();
+
+        let mut schema = CsvSchema::new();
+        schema
+            .scalar_from(GlueDataType::TpString, "NAME", "Pad")
+            .array(GlueDataType::TpDouble, "POSITION", &["X", "Y", "Z"])
+            .scalar_from(GlueDataType::TpFloat, "DISH_DIAMETER", "Diameter");
+
+        let mut t = Table::import_csv(
+            tmp_dir.path().join("ANTENNA"),
+            &csv_path,
+            &schema,
+            TableCreateMode::New,
+        )
+        .unwrap();
+
+        assert_eq!(t.n_rows(), 2);
+        assert_eq!(t.column_names().unwrap().len(), 3);
+        assert_eq!(t.get_cell::<String>("NAME", 1).unwrap(), "N02");
+        assert_eq!(
+            t.get_cell_as_vec::<f64>("POSITION", 0).unwrap(),
+            vec![1.5, -2., 3000.]
+        );
+        assert_eq!(t.get_cell::<f32>("DISH_DIAMETER", 0).unwrap(), 12.);
+        assert_eq!(t.get_cell::<f32>("DISH_DIAMETER", 1).unwrap(), 0.);
+
+        schema.scalar(GlueDataType::TpInt, "Notes");
+        assert!(matches!(
+            Table::import_csv(
+                tmp_dir.path().join("bad"),
+                &csv_path,
+                &schema,
+                TableCreateMode::New
+            ),
+            Err(CsvImportError::Parse { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,245 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Viewing table columns as Polars data frames.
+//!
+//! Much of the analysis of a Measurement Set’s metadata — which scans
+//! observed which fields, how the integration times are distributed, and so
+//! on — is naturally expressed as data frame operations. With the `polars`
+//! feature enabled, [`Table::to_polars`] copies scalar columns, and small
+//! fixed-shape array columns such as `UVW`, into a [`DataFrame`].
+
+use polars::prelude::{DataFrame, NamedFrom, PolarsError, Series};
+use std::ops::Range;
+use thiserror::Error;
+
+use crate::{glue, io_stats::IoDirection, CasaScalarData, GlueDataType, Table, TableError};
+
+/// The largest number of elements an array cell may have for its column to
+/// be exploded into one data frame column per element.
+pub const MAX_EXPLODED_ELEMENTS: usize = 16;
+
+/// An error that can occur when building a data frame.
+#[derive(Error, Debug)]
+pub enum PolarsViewError {
+    /// An error occurred while reading the table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// Polars could not build the data frame.
+    #[error(transparent)]
+    Polars(#[from] PolarsError),
+
+    /// A column’s cells are arrays with too many elements to explode.
+    #[error(
+        "column \"{0}\" has cells of {1} elements, but at most {} can be exploded",
+        MAX_EXPLODED_ELEMENTS
+    )]
+    TooManyElements(String, usize),
+}
+
+impl Table {
+    /// Copy columns of a range of rows into a Polars [`DataFrame`].
+    ///
+    /// Scalar columns of numeric, boolean, and string types become data
+    /// frame columns of the same name. Fixed-shape array columns of numeric
+    /// or boolean types with at most [`MAX_EXPLODED_ELEMENTS`] elements per
+    /// cell are exploded into one data frame column per element, named with
+    /// the element’s index in C order: `UVW` becomes `UVW_0`, `UVW_1`, and
+    /// `UVW_2`, while a column `X` with cells of shape `[2, 2]` becomes
+    /// `X_0_0`, `X_0_1`, and so on. Complex-valued columns are not
+    /// supported.
+    ///
+    /// This method is only available with the `polars` feature.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{Table, TableOpenMode};
+    ///
+    /// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+    /// let n_rows = t.n_rows();
+    /// let df = t.to_polars(&["TIME", "ANTENNA1", "ANTENNA2", "UVW"], 0..n_rows).unwrap();
+    /// println!("{}", df);
+    /// ```
+    pub fn to_polars(
+        &mut self,
+        columns: &[&str],
+        rows: Range<u64>,
+    ) -> Result<DataFrame, PolarsViewError> {
+        let n_rows = self.n_rows();
+
+        if rows.start > rows.end || rows.end > n_rows {
+            return Err(TableError::RowsOutOfBounds {
+                start: rows.start,
+                end: rows.end,
+                n_rows,
+            }
+            .into());
+        }
+
+        let mut series = Vec::new();
+
+        for col_name in columns {
+            let data_type = self.get_col_desc(col_name)?.data_type();
+
+            match data_type {
+                GlueDataType::TpString => {
+                    let mut values = Vec::with_capacity((rows.end - rows.start) as usize);
+
+                    for row in rows.clone() {
+                        values.push(self.get_cell::<String>(col_name, row)?);
+                    }
+
+                    series.push(Series::new(col_name, values));
+                }
+
+                _ => self.exploded_series(col_name, data_type, &rows, &mut series)?,
+            }
+        }
+
+        Ok(DataFrame::new(series)?)
+    }
+
+    fn exploded_series(
+        &mut self,
+        col_name: &str,
+        data_type: GlueDataType,
+        rows: &Range<u64>,
+        series: &mut Vec<Series>,
+    ) -> Result<(), PolarsViewError> {
+        use GlueDataType::*;
+
+        macro_rules! explode {
+            ($t:ty) => {
+                self.explode_typed::<$t>(col_name, rows, series)
+            };
+        }
+
+        match data_type.element_type() {
+            TpBool => explode!(bool),
+            TpChar => explode!(i8),
+            TpUChar => explode!(u8),
+            TpShort => explode!(i16),
+            TpUShort => explode!(u16),
+            TpInt => explode!(i32),
+            TpUInt => explode!(u32),
+            TpInt64 => explode!(i64),
+            TpFloat => explode!(f32),
+            TpDouble => explode!(f64),
+            other => Err(TableError::UnsupportedDataType(col_name.to_owned(), other).into()),
+        }
+    }
+
+    fn explode_typed<T>(
+        &mut self,
+        col_name: &str,
+        rows: &Range<u64>,
+        series: &mut Vec<Series>,
+    ) -> Result<(), PolarsViewError>
+    where
+        T: CasaScalarData + Copy + Default,
+        Series: NamedFrom<Vec<T>, [T]>,
+    {
+        let cell_shape = self.bulk_column_cell_shape::<T>(col_name)?;
+        let cell_len: usize = cell_shape.iter().product();
+
+        if cell_len > MAX_EXPLODED_ELEMENTS {
+            return Err(PolarsViewError::TooManyElements(
+                col_name.to_owned(),
+                cell_len,
+            ));
+        }
+
+        let n_rows = (rows.end - rows.start) as usize;
+        let mut buf = vec![T::default(); n_rows * cell_len];
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+
+        if n_rows > 0 {
+            if unsafe {
+                glue::table_get_column_range(
+                    self.handle,
+                    &ccol_name,
+                    rows.start,
+                    n_rows as u64,
+                    buf.as_mut_ptr() as _,
+                    &mut self.exc_info,
+                )
+            } != 0
+            {
+                return Err(TableError::from(self.exc_info.as_error()).into());
+            }
+
+            self.record_io(
+                IoDirection::Get,
+                col_name,
+                n_rows as u64,
+                T::DATA_TYPE,
+                buf.len() as u64,
+            );
+        }
+
+        if cell_shape.is_empty() {
+            series.push(Series::new(col_name, buf));
+            return Ok(());
+        }
+
+        for k in 0..cell_len {
+            let mut name = col_name.to_owned();
+            let mut rem = k;
+            let mut index = vec![0; cell_shape.len()];
+
+            for (i, n) in cell_shape.iter().enumerate().rev() {
+                index[i] = rem % n;
+                rem /= n;
+            }
+
+            for i in index {
+                name.push_str(&format!("_{}", i));
+            }
+
+            let values: Vec<T> = buf.iter().skip(k).step_by(cell_len).copied().collect();
+            series.push(Series::new(&name, values));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn scalar_and_exploded_columns() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpDouble, "TIME", None, true, false)
+            .unwrap();
+        desc.add_scalar_column(GlueDataType::TpString, "NAME", None, true, false)
+            .unwrap();
+        desc.add_array_column(GlueDataType::TpDouble, "UVW", None, Some(&[3]), true, false)
+            .unwrap();
+        let mut t =
+            Table::new(tmp_dir.path().join("t.tab"), desc, 4, TableCreateMode::New).unwrap();
+
+        for row in 0..4 {
+            t.put_cell("TIME", row, &(row as f64)).unwrap();
+            t.put_cell("NAME", row, &format!("r{}", row)).unwrap();
+            t.put_cell("UVW", row, &vec![row as f64, 10., -(row as f64)])
+                .unwrap();
+        }
+
+        let df = t.to_polars(&["TIME", "NAME", "UVW"], 1..3).unwrap();
+        assert_eq!(df.shape(), (2, 5));
+        assert_eq!(
+            df.get_column_names(),
+            &["TIME", "NAME", "UVW_0", "UVW_1", "UVW_2"]
+        );
+        assert_eq!(df.column("TIME").unwrap().f64().unwrap().get(1), Some(2.));
+        assert_eq!(df.column("NAME").unwrap().str().unwrap().get(0), Some("r1"));
+        assert_eq!(df.column("UVW_2").unwrap().f64().unwrap().get(1), Some(-2.));
+
+        assert!(t.to_polars(&["TIME"], 2..5).is_err());
+    }
+}
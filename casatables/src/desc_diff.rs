@@ -0,0 +1,370 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Comparing table descriptions.
+//!
+//! [`TableDesc::diff`] lists the ways in which one table description differs
+//! from another: columns that were added or removed, columns whose data types
+//! or shapes changed, and table or column keywords that were added, removed,
+//! or given new values. Combined with
+//! [`Table::table_desc`](crate::Table::table_desc), this makes it
+//! easy to check that a writer produced the schema it was meant to, or to
+//! see how two versions of a data set differ.
+//!
+//! ```no_run
+//! use rubbl_casatables::{Table, TableOpenMode};
+//!
+//! let mut old = Table::open("old.tab", TableOpenMode::Read).unwrap();
+//! let mut new = Table::open("new.tab", TableOpenMode::Read).unwrap();
+//! let mut old_desc = old.table_desc().unwrap();
+//! let mut new_desc = new.table_desc().unwrap();
+//!
+//! for difference in old_desc.diff(&mut new_desc).unwrap() {
+//!     println!("{}", difference);
+//! }
+//! ```
+
+use std::fmt;
+
+use crate::{ColumnShapeInfo, GlueDataType, TableDesc, TableError, TableRecord};
+
+/// One way in which a table description differs from another, as found by
+/// [`TableDesc::diff`].
+///
+/// Differences are stated as changes from the description on which `diff`
+/// is called to the one passed to it. Keyword differences name the column
+/// whose keywords differ, or `None` for table keywords.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DescDifference {
+    /// A column is present only in the other description.
+    ColumnAdded(String),
+
+    /// A column is present only in this description.
+    ColumnRemoved(String),
+
+    /// A column has a different element type.
+    DataTypeChanged {
+        /// The name of the column.
+        column: String,
+        /// The element type in this description.
+        from: GlueDataType,
+        /// The element type in the other description.
+        to: GlueDataType,
+    },
+
+    /// A column has a different cell shape.
+    ShapeChanged {
+        /// The name of the column.
+        column: String,
+        /// The shape in this description.
+        from: ColumnShapeInfo,
+        /// The shape in the other description.
+        to: ColumnShapeInfo,
+    },
+
+    /// A keyword is present only in the other description.
+    KeywordAdded {
+        /// The column with the keyword, or `None` for a table keyword.
+        column: Option<String>,
+        /// The name of the keyword.
+        name: String,
+    },
+
+    /// A keyword is present only in this description.
+    KeywordRemoved {
+        /// The column with the keyword, or `None` for a table keyword.
+        column: Option<String>,
+        /// The name of the keyword.
+        name: String,
+    },
+
+    /// A keyword has a different type or value.
+    KeywordChanged {
+        /// The column with the keyword, or `None` for a table keyword.
+        column: Option<String>,
+        /// The name of the keyword.
+        name: String,
+    },
+}
+
+impl fmt::Display for DescDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescDifference::ColumnAdded(name) => write!(f, "added column {}", name),
+            DescDifference::ColumnRemoved(name) => write!(f, "removed column {}", name),
+            DescDifference::DataTypeChanged { column, from, to } => {
+                write!(f, "column {} changed type from {} to {}", column, from, to)
+            }
+            DescDifference::ShapeChanged { column, from, to } => write!(
+                f,
+                "column {} changed shape from {} to {}",
+                column,
+                ShapeDisplay(from),
+                ShapeDisplay(to)
+            ),
+            DescDifference::KeywordAdded { column, name } => {
+                write!(f, "added ")?;
+                write_keyword(f, column, name)
+            }
+            DescDifference::KeywordRemoved { column, name } => {
+                write!(f, "removed ")?;
+                write_keyword(f, column, name)
+            }
+            DescDifference::KeywordChanged { column, name } => {
+                write!(f, "changed ")?;
+                write_keyword(f, column, name)
+            }
+        }
+    }
+}
+
+fn write_keyword(f: &mut fmt::Formatter<'_>, column: &Option<String>, name: &str) -> fmt::Result {
+    match column {
+        Some(column) => write!(f, "keyword {} of column {}", name, column),
+        None => write!(f, "table keyword {}", name),
+    }
+}
+
+/// Formats a column shape for [`DescDifference`]'s `Display` impl.
+struct ShapeDisplay<'a>(&'a ColumnShapeInfo);
+
+impl fmt::Display for ShapeDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            ColumnShapeInfo::Scalar => write!(f, "scalar"),
+            ColumnShapeInfo::Fixed(shape) => write!(f, "fixed {:?}", shape),
+            ColumnShapeInfo::Variable { ndim: Some(ndim) } => write!(f, "variable {}-d", ndim),
+            ColumnShapeInfo::Variable { ndim: None } => write!(f, "variable"),
+        }
+    }
+}
+
+impl TableDesc {
+    /// List the differences between this table description and *other*.
+    ///
+    /// Removed and changed columns are reported in the order of this
+    /// description's columns, followed by the added columns in the order of
+    /// *other*'s, then the differences in table keywords. Keyword values are
+    /// compared by their string representations. An empty result means that
+    /// the two descriptions have the same columns and keywords.
+    pub fn diff(&mut self, other: &mut TableDesc) -> Result<Vec<DescDifference>, TableError> {
+        let ours = self.column_names()?;
+        let theirs = other.column_names()?;
+        let mut result = Vec::new();
+
+        for name in &ours {
+            if !theirs.contains(name) {
+                result.push(DescDifference::ColumnRemoved(name.clone()));
+                continue;
+            }
+
+            let from = self.get_col_desc(name)?;
+            let to = other.get_col_desc(name)?;
+
+            if from.data_type() != to.data_type() {
+                result.push(DescDifference::DataTypeChanged {
+                    column: name.clone(),
+                    from: from.data_type(),
+                    to: to.data_type(),
+                });
+            }
+
+            let from_shape = self.column_shape(name)?;
+            let to_shape = other.column_shape(name)?;
+
+            if from_shape != to_shape {
+                result.push(DescDifference::ShapeChanged {
+                    column: name.clone(),
+                    from: from_shape,
+                    to: to_shape,
+                });
+            }
+
+            diff_keywords(
+                &mut self.get_column_keyword_record(name)?,
+                &mut other.get_column_keyword_record(name)?,
+                Some(name),
+                &mut result,
+            )?;
+        }
+
+        for name in theirs {
+            if !ours.contains(&name) {
+                result.push(DescDifference::ColumnAdded(name));
+            }
+        }
+
+        diff_keywords(
+            &mut self.get_keyword_record()?,
+            &mut other.get_keyword_record()?,
+            None,
+            &mut result,
+        )?;
+
+        Ok(result)
+    }
+}
+
+/// Append the differences between the keywords in *ours* and *theirs* to
+/// *result*.
+fn diff_keywords(
+    ours: &mut TableRecord,
+    theirs: &mut TableRecord,
+    column: Option<&str>,
+    result: &mut Vec<DescDifference>,
+) -> Result<(), TableError> {
+    let ours = ours.keyword_names_types_reprs()?;
+    let theirs = theirs.keyword_names_types_reprs()?;
+    let column = column.map(|c| c.to_owned());
+
+    for (name, data_type, repr) in &ours {
+        match theirs.iter().find(|(n, _, _)| n == name) {
+            None => result.push(DescDifference::KeywordRemoved {
+                column: column.clone(),
+                name: name.clone(),
+            }),
+
+            Some((_, t, r)) if t != data_type || r != repr => {
+                result.push(DescDifference::KeywordChanged {
+                    column: column.clone(),
+                    name: name.clone(),
+                })
+            }
+
+            Some(_) => {}
+        }
+    }
+
+    for (name, _, _) in theirs {
+        if !ours.iter().any(|(n, _, _)| *n == name) {
+            result.push(DescDifference::KeywordAdded {
+                column: column.clone(),
+                name,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Table, TableCreateMode, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    fn base_desc() -> TableDesc {
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpDouble, "TIME", None, false, false)
+            .unwrap();
+        desc.add_array_column(
+            GlueDataType::TpComplex,
+            "DATA",
+            None,
+            Some(&[4, 2]),
+            false,
+            false,
+        )
+        .unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "OLD", None, false, false)
+            .unwrap();
+        desc.put_column_keyword("TIME", "UNIT", &"s".to_owned())
+            .unwrap();
+        desc.put_keyword("VERSION", &"1.0".to_owned()).unwrap();
+        desc
+    }
+
+    #[test]
+    fn identical() {
+        let mut a = base_desc();
+        let mut b = base_desc();
+        assert_eq!(a.diff(&mut b).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn differences() {
+        let mut a = base_desc();
+
+        let mut b = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        b.add_scalar_column(GlueDataType::TpFloat, "TIME", None, false, false)
+            .unwrap();
+        b.add_array_column(
+            GlueDataType::TpComplex,
+            "DATA",
+            None,
+            Some(&[8, 2]),
+            false,
+            false,
+        )
+        .unwrap();
+        b.add_scalar_column(GlueDataType::TpBool, "NEW", None, false, false)
+            .unwrap();
+        b.put_column_keyword("TIME", "UNIT", &"d".to_owned())
+            .unwrap();
+        b.put_keyword("VERSION", &"2.0".to_owned()).unwrap();
+        b.put_keyword("TELESCOPE", &"VLA".to_owned()).unwrap();
+
+        let diffs = a.diff(&mut b).unwrap();
+
+        assert_eq!(
+            diffs,
+            vec![
+                DescDifference::DataTypeChanged {
+                    column: "TIME".to_owned(),
+                    from: GlueDataType::TpDouble,
+                    to: GlueDataType::TpFloat,
+                },
+                DescDifference::KeywordChanged {
+                    column: Some("TIME".to_owned()),
+                    name: "UNIT".to_owned(),
+                },
+                DescDifference::ShapeChanged {
+                    column: "DATA".to_owned(),
+                    from: ColumnShapeInfo::Fixed(vec![4, 2]),
+                    to: ColumnShapeInfo::Fixed(vec![8, 2]),
+                },
+                DescDifference::ColumnRemoved("OLD".to_owned()),
+                DescDifference::ColumnAdded("NEW".to_owned()),
+                DescDifference::KeywordChanged {
+                    column: None,
+                    name: "VERSION".to_owned(),
+                },
+                DescDifference::KeywordAdded {
+                    column: None,
+                    name: "TELESCOPE".to_owned(),
+                },
+            ]
+        );
+
+        assert_eq!(
+            diffs[2].to_string(),
+            "column DATA changed shape from fixed [4, 2] to fixed [8, 2]"
+        );
+        assert_eq!(diffs[5].to_string(), "changed table keyword VERSION");
+    }
+
+    #[test]
+    fn table_desc_round_trip() {
+        let tmp_dir = tempdir().unwrap();
+        let mut t = Table::new(
+            tmp_dir.path().join("t.tab"),
+            base_desc(),
+            2,
+            TableCreateMode::New,
+        )
+        .unwrap();
+
+        let mut written = t.table_desc().unwrap();
+        assert_eq!(base_desc().diff(&mut written).unwrap(), Vec::new());
+
+        t.put_keyword("EXTRA", &1i32).unwrap();
+        let mut updated = t.table_desc().unwrap();
+        assert_eq!(
+            written.diff(&mut updated).unwrap(),
+            vec![DescDifference::KeywordAdded {
+                column: None,
+                name: "EXTRA".to_owned(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,425 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Attributing the disk space of a table to its columns.
+//!
+//! casacore stores the data of each data manager in files named
+//! `table.f<N>`, possibly with a suffix such as `_TSM0` or `i`, where *N* is
+//! the sequence number of the data manager. [`Table::disk_usage`] matches
+//! these files up with the columns that each data manager holds, which is
+//! usually enough to see which columns dominate the size of a data set and
+//! whether they would benefit from a different storage manager or from
+//! compression.
+
+use std::{fmt, fs, io, path::Path};
+
+use crate::{
+    casatables_string_bridge_cb, finish_callbacks, glue, ColumnShapeInfo, Table, TableError,
+};
+
+/// The assumed size of a string element, used when apportioning the space of
+/// a data manager among its columns.
+const STRING_ELEMENT_SIZE: u64 = 16;
+
+/// The disk usage of one data manager of a table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DataManagerUsage {
+    /// The sequence number of the data manager.
+    pub seqnr: u32,
+
+    /// The type of the data manager, such as `StandardStMan`.
+    pub dm_type: String,
+
+    /// The name of the data manager.
+    pub name: String,
+
+    /// The total size of the data manager’s files, in bytes.
+    pub bytes: u64,
+
+    /// The columns stored by the data manager.
+    pub columns: Vec<String>,
+}
+
+/// The disk usage attributed to one column of a table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ColumnUsage {
+    /// The name of the column.
+    pub name: String,
+
+    /// The sequence number of the data manager storing the column.
+    pub seqnr: u32,
+
+    /// The number of bytes attributed to the column.
+    pub bytes: u64,
+
+    /// Whether the number of bytes is an estimate.
+    ///
+    /// If a data manager stores several columns, its space is divided among
+    /// them in proportion to the size of their cells. The cells of
+    /// variable-shape columns are assumed to have the shape of the first
+    /// row, and string elements are assumed to take 16 bytes each.
+    pub estimated: bool,
+}
+
+/// A breakdown of the disk space used by a table, as returned by
+/// [`Table::disk_usage`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DiskUsage {
+    /// The total size of all of the files in the table directory, including
+    /// those of sub-tables, in bytes.
+    pub total_bytes: u64,
+
+    /// The size of the files that belong to no data manager, such as
+    /// `table.dat`, in bytes.
+    pub metadata_bytes: u64,
+
+    /// The data managers of the table, in order of sequence number.
+    pub data_managers: Vec<DataManagerUsage>,
+
+    /// The columns of the table, in the order of the table description.
+    pub columns: Vec<ColumnUsage>,
+
+    /// The names and total sizes of the sub-directories of the table
+    /// directory, which are usually sub-tables.
+    pub subtables: Vec<(String, u64)>,
+}
+
+impl fmt::Display for DiskUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<24} {:>12}", "total", format_bytes(self.total_bytes))?;
+        writeln!(
+            f,
+            "{:<24} {:>12}",
+            "metadata",
+            format_bytes(self.metadata_bytes)
+        )?;
+
+        let mut columns: Vec<_> = self.columns.iter().collect();
+        columns.sort_by_key(|c| std::cmp::Reverse(c.bytes));
+
+        for col in columns {
+            let dm = self.data_managers.iter().find(|dm| dm.seqnr == col.seqnr);
+            write!(f, "{:<24} {:>12}", col.name, format_bytes(col.bytes))?;
+
+            if let Some(dm) = dm {
+                write!(f, "  {}", dm.dm_type)?;
+            }
+
+            if col.estimated {
+                write!(f, " (estimated)")?;
+            }
+
+            writeln!(f)?;
+        }
+
+        for (name, bytes) in &self.subtables {
+            writeln!(
+                f,
+                "{:<24} {:>12}",
+                format!("{}/", name),
+                format_bytes(*bytes)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Format a number of bytes with a binary prefix.
+fn format_bytes(n: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = n as f64;
+    let mut unit = 0;
+
+    while value >= 1024. && unit < UNITS.len() - 1 {
+        value /= 1024.;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", n)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Get the data manager sequence number encoded in a file name of the form
+/// `table.f<N>[suffix]`.
+fn data_manager_seqnr(file_name: &str) -> Option<u32> {
+    let rest = file_name.strip_prefix("table.f")?;
+    let n_digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    rest[..n_digits].parse().ok()
+}
+
+/// Get the total size of the files under *path*.
+fn tree_size(path: &Path) -> Result<u64, io::Error> {
+    let mut total = 0;
+
+    for item in fs::read_dir(path)? {
+        let item = item?;
+        let ty = item.file_type()?;
+
+        if ty.is_dir() {
+            total += tree_size(&item.path())?;
+        } else {
+            total += item.metadata()?.len();
+        }
+    }
+
+    Ok(total)
+}
+
+unsafe fn invoke_table_get_column_data_manager<F>(
+    handle: *mut glue::GlueTable,
+    ccol_name: &glue::StringBridge,
+    seqnr: &mut std::os::raw::c_uint,
+    exc_info: &mut glue::ExcInfo,
+    mut f: F,
+) -> std::os::raw::c_int
+where
+    F: FnMut(String),
+{
+    let rv = glue::table_get_column_data_manager(
+        handle,
+        ccol_name,
+        Some(casatables_string_bridge_cb::<F>),
+        &mut f as *mut _ as *mut std::os::raw::c_void,
+        seqnr,
+        exc_info,
+    );
+    finish_callbacks(rv, exc_info, "table_get_column_data_manager")
+}
+
+impl Table {
+    /// Get the data manager that stores a column.
+    ///
+    /// Returns the data manager’s sequence number, type, and name.
+    pub fn column_data_manager(
+        &mut self,
+        col_name: &str,
+    ) -> Result<(u32, String, String), TableError> {
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut seqnr = 0;
+        let mut strings = Vec::with_capacity(2);
+
+        let rv = unsafe {
+            invoke_table_get_column_data_manager(
+                self.handle,
+                &ccol_name,
+                &mut seqnr,
+                &mut self.exc_info,
+                |s| strings.push(s),
+            )
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        let name = strings.pop().unwrap_or_default();
+        let dm_type = strings.pop().unwrap_or_default();
+        Ok((seqnr, dm_type, name))
+    }
+
+    /// Work out how much disk space the table uses, and how it is divided
+    /// among the columns.
+    ///
+    /// The table is flushed first if it is writable, so that the sizes of
+    /// its files are up to date. Only files on disk are counted; virtual
+    /// columns show up with zero bytes.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{Table, TableOpenMode};
+    ///
+    /// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+    /// print!("{}", t.disk_usage().unwrap());
+    /// ```
+    pub fn disk_usage(&mut self) -> Result<DiskUsage, TableError> {
+        self.flush()?;
+        let path = self.file_name()?;
+        let mut usage = DiskUsage::default();
+
+        // First, the columns and their data managers.
+
+        let mut weights = Vec::new();
+
+        for name in self.column_names()? {
+            let (seqnr, dm_type, dm_name) = self.column_data_manager(&name)?;
+
+            match usage.data_managers.iter_mut().find(|dm| dm.seqnr == seqnr) {
+                Some(dm) => dm.columns.push(name.clone()),
+                None => usage.data_managers.push(DataManagerUsage {
+                    seqnr,
+                    dm_type,
+                    name: dm_name,
+                    bytes: 0,
+                    columns: vec![name.clone()],
+                }),
+            }
+
+            weights.push(self.column_cell_bytes(&name)?);
+            usage.columns.push(ColumnUsage {
+                name,
+                seqnr,
+                bytes: 0,
+                estimated: false,
+            });
+        }
+
+        usage.data_managers.sort_by_key(|dm| dm.seqnr);
+
+        // Next, the files.
+
+        for item in fs::read_dir(&path)? {
+            let item = item?;
+            let meta = item.metadata()?;
+            let file_name = item.file_name();
+            let file_name = file_name.to_string_lossy();
+
+            if meta.is_dir() {
+                let bytes = tree_size(&item.path())?;
+                usage.total_bytes += bytes;
+                usage.subtables.push((file_name.into_owned(), bytes));
+                continue;
+            }
+
+            usage.total_bytes += meta.len();
+
+            let dm = data_manager_seqnr(&file_name)
+                .and_then(|seqnr| usage.data_managers.iter_mut().find(|dm| dm.seqnr == seqnr));
+
+            match dm {
+                Some(dm) => dm.bytes += meta.len(),
+                None => usage.metadata_bytes += meta.len(),
+            }
+        }
+
+        usage.subtables.sort();
+
+        // Finally, divide up the space of each data manager.
+
+        for dm in &usage.data_managers {
+            let members: Vec<usize> = (0..usage.columns.len())
+                .filter(|i| usage.columns[*i].seqnr == dm.seqnr)
+                .collect();
+            let total_weight: u64 = members.iter().map(|i| weights[*i]).sum();
+            let estimated = members.len() > 1;
+            let mut remaining = dm.bytes;
+
+            for (k, i) in members.iter().enumerate() {
+                let bytes = if k == members.len() - 1 {
+                    remaining
+                } else if total_weight == 0 {
+                    dm.bytes / members.len() as u64
+                } else {
+                    (dm.bytes as u128 * weights[*i] as u128 / total_weight as u128) as u64
+                };
+
+                remaining -= bytes;
+                usage.columns[*i].bytes = bytes;
+                usage.columns[*i].estimated = estimated;
+            }
+        }
+
+        Ok(usage)
+    }
+
+    /// Estimate the number of bytes in one cell of a column.
+    fn column_cell_bytes(&mut self, col_name: &str) -> Result<u64, TableError> {
+        let element_size = match self.get_col_desc(col_name)?.data_type().element_size() {
+            n if n < 0 => STRING_ELEMENT_SIZE,
+            n => n as u64,
+        };
+
+        let n_elements: u64 = match self.column_shape(col_name)? {
+            ColumnShapeInfo::Scalar => 1,
+            ColumnShapeInfo::Fixed(shape) => shape.iter().product::<usize>() as u64,
+            ColumnShapeInfo::Variable { .. } => {
+                if self.n_rows() == 0 {
+                    1
+                } else {
+                    // Undefined cells have no shape; count them as one
+                    // element.
+                    self.get_cell_shape(col_name, 0)
+                        .map(|s| s.iter().product::<usize>() as u64)
+                        .unwrap_or(1)
+                }
+            }
+        };
+
+        Ok(element_size * n_elements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn seqnr_parsing() {
+        assert_eq!(data_manager_seqnr("table.f0"), Some(0));
+        assert_eq!(data_manager_seqnr("table.f12_TSM0"), Some(12));
+        assert_eq!(data_manager_seqnr("table.f3i"), Some(3));
+        assert_eq!(data_manager_seqnr("table.dat"), None);
+        assert_eq!(data_manager_seqnr("table.f"), None);
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(3 << 20), "3.0 MiB");
+    }
+
+    #[test]
+    fn attribute_columns() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_array_column(
+            GlueDataType::TpComplex,
+            "DATA",
+            None,
+            Some(&[16, 4]),
+            true,
+            false,
+        )
+        .unwrap();
+        desc.set_data_manager("DATA", "TiledColumnStMan", "TiledData")
+            .unwrap();
+        desc.add_scalar_column(GlueDataType::TpDouble, "TIME", None, true, false)
+            .unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "ANTENNA1", None, true, false)
+            .unwrap();
+
+        let mut t = Table::new(
+            tmp_dir.path().join("test.tab"),
+            desc,
+            100,
+            TableCreateMode::New,
+        )
+        .unwrap();
+        let cell = ndarray::Array2::from_elem((16, 4), crate::Complex::new(1f32, 0.));
+
+        for row in 0..100 {
+            t.put_cell("DATA", row, &cell).unwrap();
+            t.put_cell("TIME", row, &(row as f64)).unwrap();
+        }
+
+        let usage = t.disk_usage().unwrap();
+        assert_eq!(usage.columns.len(), 3);
+        assert_eq!(usage.data_managers.len(), 2);
+
+        let data = usage.columns.iter().find(|c| c.name == "DATA").unwrap();
+        assert!(!data.estimated);
+        assert!(data.bytes >= 100 * 64 * 8);
+
+        let time = usage.columns.iter().find(|c| c.name == "TIME").unwrap();
+        let ant = usage.columns.iter().find(|c| c.name == "ANTENNA1").unwrap();
+        assert!(time.estimated);
+        assert_eq!(time.seqnr, ant.seqnr);
+        assert!(time.bytes >= ant.bytes);
+
+        let dm_total: u64 = usage.data_managers.iter().map(|dm| dm.bytes).sum();
+        let col_total: u64 = usage.columns.iter().map(|c| c.bytes).sum();
+        assert_eq!(dm_total, col_total);
+        assert_eq!(usage.total_bytes, dm_total + usage.metadata_bytes);
+        assert!(usage.to_string().starts_with("total"));
+    }
+}
@@ -6,9 +6,29 @@
 // C ordering instead. So we must take care to reverse array shapes when
 // translating from C++-land to Rust-land.
 
+#include <algorithm>
+#include <limits>
 #include <stdexcept>
+#include <string>
+#include <vector>
 #include <casacore/tables/Tables.h>
 #include <casacore/casa/Containers/ValueHolder.h>
+#include <casacore/tables/Tables/BaseColumn.h>
+#include <casacore/tables/Tables/ConcatTable.h>
+#include <casacore/tables/Tables/PlainTable.h>
+#include <casacore/tables/Tables/RefTable.h>
+#include <casacore/tables/Tables/RefRows.h>
+#include <casacore/tables/DataMan/TiledStManAccessor.h>
+#ifdef RUBBL_SYSTEM_CASACORE
+#include <casacore/tables/TaQL/TableParse.h>
+#endif
+#include <casacore/casa/Arrays/Slicer.h>
+#include <casacore/casa/OS/HostInfo.h>
+#include <casacore/casa/Utilities/ValType.h>
+#include <casacore/casa/version.h>
+#include <casacore/casa/System/Aipsrc.h>
+#include <casacore/tables/DataMan/DataManager.h>
+#include <casacore/tables/DataMan/DataManError.h>
 
 #define CASA_TYPES_ALREADY_DECLARED
 #define GlueTable casacore::Table
@@ -18,8 +38,182 @@
 #define GlueTableRecord casacore::TableRecord
 #define GlueColumnDesc casacore::ColumnDesc
 
+// casacore 3.4 switched row numbers from 32 to 64 bits.
+#if CASACORE_MAJOR_VERSION > 3 || (CASACORE_MAJOR_VERSION == 3 && CASACORE_MINOR_VERSION >= 4)
+typedef casacore::rownr_t glue_rownr_t;
+#else
+typedef casacore::uInt glue_rownr_t;
+#endif
+
+// A column handle that can be kept around between cell accesses. The typed
+// casacore column classes (ScalarColumn<T>, ArrayColumn<T>) would force us to
+// keep track of the element type on the C++ side, so instead we hold a generic
+// TableColumn and write through its untyped BaseColumn. The caller is
+// responsible for making sure that the data type matches that of the column.
+class GlueColumn : public casacore::TableColumn
+{
+public:
+    GlueColumn(const casacore::Table &table, const casacore::String &name)
+        : casacore::TableColumn(table, name)
+    {}
+
+    void put_raw(glue_rownr_t row_number, const void *data)
+    {
+        baseColPtr()->put(row_number, data);
+    }
+};
+
+// A reference table that refuses to be written to, used for snapshots.
+// Writes to columns and keywords check whether the table that they go
+// through is writable, so they never reach the parent table.
+class ReadOnlyRefTable : public casacore::RefTable
+{
+public:
+    ReadOnlyRefTable(casacore::BaseTable *parent, const casacore::Vector<glue_rownr_t> &rows)
+        : casacore::RefTable(parent, rows)
+    {}
+
+    casacore::Bool isWritable() const { return casacore::False; }
+};
+
+// Only subclasses of casacore::Table may wrap a BaseTable that they created.
+class TableFromBase : public casacore::Table
+{
+public:
+    TableFromBase(casacore::BaseTable *base) : casacore::Table(base) {}
+};
+
+// casacore 3.1 keeps Table::baseTablePtr() private, but subclasses may name
+// the protected pointer that it returns.
+class TableBaseAccess : public casacore::Table
+{
+public:
+    static casacore::BaseTable *get(const casacore::Table &table)
+    {
+        return table.*(&TableBaseAccess::baseTabPtr_p);
+    }
+};
+
+// Stand-ins for data managers that casacore cannot load, so that a table
+// using one can still be opened with the affected columns masked out. The
+// columns report their data types but cannot be read or written: every
+// access falls through to the DataManagerColumn defaults, which throw.
+class MaskedColumn : public casacore::DataManagerColumn
+{
+public:
+    MaskedColumn(int data_type) : data_type_(data_type) {}
+
+    int dataType() const { return data_type_; }
+    casacore::Bool isWritable() const { return casacore::False; }
+    void setShapeColumn(const casacore::IPosition &) {}
+
+private:
+    int data_type_;
+};
+
+// Masking is only allowed while table_alloc_and_open_masked() is running;
+// once registered, the stand-in would otherwise be used by every later
+// attempt to open a table with the same data manager.
+static thread_local bool masking_allowed = false;
+
+// Report whether a data manager is registered, first trying to load a
+// shared library providing it if it is not. casacore throws if it cannot
+// find or initialize the library, which just means that the data manager is
+// not available.
+static bool
+try_load_data_manager(const casacore::String &type_name)
+{
+    try {
+        casacore::DataManager::getCtor(type_name);
+    } catch (const casacore::AipsError &) {
+    }
+
+    return casacore::DataManager::isRegistered(type_name);
+}
+
+class MaskedStMan : public casacore::DataManager
+{
+public:
+    MaskedStMan(const casacore::String &type) : type_(type) {}
+
+    ~MaskedStMan()
+    {
+        for (size_t i = 0; i < columns_.size(); i++)
+            delete columns_[i];
+    }
+
+    static casacore::DataManager *
+    make(const casacore::String &type, const casacore::Record &)
+    {
+        if (!masking_allowed)
+            throw casacore::DataManUnknownCtor("Data Manager class " + type + " is not registered");
+
+        return new MaskedStMan(type);
+    }
+
+    casacore::DataManager *clone() const { return new MaskedStMan(type_); }
+    casacore::String dataManagerType() const { return type_; }
+    casacore::Bool flush(casacore::AipsIO &, casacore::Bool) { return casacore::False; }
+
+#if CASACORE_MAJOR_VERSION > 3 || (CASACORE_MAJOR_VERSION == 3 && CASACORE_MINOR_VERSION >= 4)
+    void create64(casacore::rownr_t) { throw_masked(); }
+    casacore::rownr_t open64(casacore::rownr_t n_rows, casacore::AipsIO &) { return n_rows; }
+    casacore::rownr_t resync64(casacore::rownr_t n_rows) { return n_rows; }
+    void deleteManagerFiles() { throw_masked(); }
+#else
+    void create(casacore::uInt) { throw_masked(); }
+    void open(casacore::uInt, casacore::AipsIO &) {}
+    void resync(casacore::uInt) {}
+    void deleteManager() { throw_masked(); }
+#endif
+
+    casacore::DataManagerColumn *
+    makeScalarColumn(const casacore::String &, int data_type, const casacore::String &)
+    {
+        return make_column(data_type);
+    }
+
+    casacore::DataManagerColumn *
+    makeDirArrColumn(const casacore::String &, int data_type, const casacore::String &)
+    {
+        return make_column(data_type);
+    }
+
+    casacore::DataManagerColumn *
+    makeIndArrColumn(const casacore::String &, int data_type, const casacore::String &)
+    {
+        return make_column(data_type);
+    }
+
+private:
+    casacore::DataManagerColumn *
+    make_column(int data_type)
+    {
+        MaskedColumn *col = new MaskedColumn(data_type);
+        columns_.push_back(col);
+        return col;
+    }
+
+    void throw_masked() const
+    {
+        throw casacore::DataManInvOper("data manager " + type_ + " is not available, so its columns are masked");
+    }
+
+    casacore::String type_;
+    std::vector<MaskedColumn *> columns_;
+};
+
 #include "glue.h"
 
+// The Rust side passes buffers of num_complex::Complex values straight
+// through as casacore complexes; see the matching assertions in lib.rs.
+static_assert(sizeof(casacore::Complex) == 2 * sizeof(float), "unexpected casacore::Complex layout");
+static_assert(alignof(casacore::Complex) == alignof(float), "unexpected casacore::Complex alignment");
+static_assert(sizeof(casacore::DComplex) == 2 * sizeof(double), "unexpected casacore::DComplex layout");
+static_assert(alignof(casacore::DComplex) == alignof(double), "unexpected casacore::DComplex alignment");
+
+#include <stdio.h>
+#include <stdlib.h>
 #include <string.h>
 
 extern "C" {
@@ -32,8 +226,26 @@ extern "C" {
             strncpy(exc.message, e.what(), sizeof(exc.message) - 1);
             exc.message[sizeof(exc.message) - 1] = '\0';
         } catch (...) {
-            strcpy(exc.message, "unidentifiable C++ exception occurred");
+            // This prefix is recognized on the Rust side; see TableError::Ffi.
+            strcpy(exc.message, "FFI failure in C++ glue: unidentifiable C++ exception occurred");
+        }
+    }
+
+    // A few entry points have no way to report an error, because they are
+    // not expected to ever fail. Should one throw anyway, stop the process
+    // rather than let the exception unwind into Rust code.
+    [[noreturn]] void
+    fatal_exception(const char *context)
+    {
+        try {
+            throw;
+        } catch (const std::exception &e) {
+            fprintf(stderr, "rubbl_casatables: unexpected C++ exception in %s: %s\n", context, e.what());
+        } catch (...) {
+            fprintf(stderr, "rubbl_casatables: unidentifiable C++ exception in %s\n", context);
         }
+
+        std::abort();
     }
 
     // StringBridge
@@ -91,6 +303,120 @@ extern "C" {
         }
     }
 
+    // Version information
+
+    void
+    casacore_version(unsigned int *major, unsigned int *minor, unsigned int *patch)
+    {
+        *major = CASACORE_MAJOR_VERSION;
+        *minor = CASACORE_MINOR_VERSION;
+        *patch = CASACORE_PATCH_VERSION;
+    }
+
+    const char *
+    casacore_runtime_version()
+    {
+#ifdef RUBBL_SYSTEM_CASACORE
+        // This comes from the shared library, which may differ from the
+        // headers that we were compiled against.
+        return casacore::getVersion();
+#else
+        // The bundled casacore is linked statically, so it must match.
+        return CASACORE_VERSION;
+#endif
+    }
+
+    // Row numbers
+
+    unsigned long
+    casacore_max_rows()
+    {
+        return (unsigned long) std::numeric_limits<glue_rownr_t>::max();
+    }
+
+    // Row numbers and counts always cross the FFI boundary as 64-bit values.
+    // Older casacores would silently truncate ones that do not fit into
+    // their 32-bit row numbers, so refuse them instead. This checks that the
+    // rows [start_row, start_row + n_rows) can all be addressed, which also
+    // means that a table that ends with them is not too long.
+    static void
+    check_row_range(const unsigned long start_row, const unsigned long n_rows)
+    {
+        const unsigned long max = casacore_max_rows();
+
+        if (start_row > max || n_rows > max - start_row)
+            throw casacore::AipsError("rows " + std::to_string(start_row) + " to " +
+                                      std::to_string(start_row + n_rows) +
+                                      " are beyond the limit of " + std::to_string(max) +
+                                      " rows in this version of casacore");
+    }
+
+    // Configuration
+
+    int
+    casacore_set_rc(const unsigned long n_settings, const StringBridge *keywords,
+                    const StringBridge *values, ExcInfo &exc)
+    {
+        try {
+            // Start from the settings in the usual resource files.
+            casacore::Aipsrc::reRead();
+
+            // Aipsrc has no API for adding settings, but it hands out
+            // references to its static keyword tables. Keywords are matched
+            // in order, so ours go in front. The keywords are turned into
+            // patterns the same way that Aipsrc does when parsing a file.
+            casacore::Block<casacore::String> &old_patterns =
+                const_cast<casacore::Block<casacore::String> &>(casacore::Aipsrc::patterns());
+            casacore::Block<casacore::String> &old_values =
+                const_cast<casacore::Block<casacore::String> &>(casacore::Aipsrc::values());
+            const size_t n_old = old_patterns.nelements();
+            casacore::Block<casacore::String> new_patterns(n_settings + n_old);
+            casacore::Block<casacore::String> new_values(n_settings + n_old);
+
+            for (unsigned long i = 0; i < n_settings; i++) {
+                casacore::String keyword = bridge_string(keywords[i]);
+                keyword.gsub(".", "\\.");
+                keyword.gsub("*", ".*");
+                new_patterns[i] = casacore::String("^") + keyword + casacore::String("$");
+                new_values[i] = bridge_string(values[i]);
+            }
+
+            for (size_t i = 0; i < n_old; i++) {
+                new_patterns[n_settings + i] = old_patterns[i];
+                new_values[n_settings + i] = old_values[i];
+            }
+
+            old_patterns = new_patterns;
+            old_values = new_values;
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    int
+    data_manager_is_available(const StringBridge &type, ExcInfo &exc)
+    {
+        try {
+            return try_load_data_manager(bridge_string(type)) ? 1 : 0;
+        } catch (...) {
+            handle_exception(exc);
+            return -1;
+        }
+    }
+
+    long
+    host_memory_free_kib()
+    {
+        try {
+            return (long) casacore::HostInfo::memoryFree();
+        } catch (...) {
+            return -1;
+        }
+    }
+
     // Data Types
 
     int
@@ -163,7 +489,11 @@ extern "C" {
     bool 
     tablerec_eq(const GlueTableRecord& rec, const GlueTableRecord& other)
     {
-        return rec.description() == other.description();
+        try {
+            return rec.description() == other.description();
+        } catch (...) {
+            fatal_exception("tablerec_eq");
+        }
     }
 
     int
@@ -326,6 +656,7 @@ extern "C" {
             //SCALAR_CASE(TpUShort, casacore::uShort)
             SCALAR_CASE(TpInt, casacore::Int)
             SCALAR_CASE(TpUInt, casacore::uInt)
+            SCALAR_CASE(TpInt64, casacore::Int64)
             SCALAR_CASE(TpFloat, float)
             SCALAR_CASE(TpDouble, double)
             SCALAR_CASE(TpComplex, casacore::Complex)
@@ -338,6 +669,7 @@ extern "C" {
             //VECTOR_CASE(TpArrayUShort, casacore::uShort)
             VECTOR_CASE(TpArrayInt, casacore::Int)
             VECTOR_CASE(TpArrayUInt, casacore::uInt)
+            VECTOR_CASE(TpArrayInt64, casacore::Int64)
             VECTOR_CASE(TpArrayFloat, float)
             VECTOR_CASE(TpArrayDouble, double)
             VECTOR_CASE(TpArrayComplex, casacore::Complex)
@@ -496,6 +828,7 @@ extern "C" {
             //SCALAR_CASE(TpUShort, casacore::uShort)
             SCALAR_CASE(TpInt, casacore::Int)
             SCALAR_CASE(TpUInt, casacore::uInt)
+            SCALAR_CASE(TpInt64, casacore::Int64)
             SCALAR_CASE(TpFloat, float)
             SCALAR_CASE(TpDouble, double)
             SCALAR_CASE(TpComplex, casacore::Complex)
@@ -508,6 +841,7 @@ extern "C" {
             //VECTOR_CASE(TpArrayUShort, casacore::uShort)
             VECTOR_CASE(TpArrayInt, casacore::Int)
             VECTOR_CASE(TpArrayUInt, casacore::uInt)
+            VECTOR_CASE(TpArrayInt64, casacore::Int64)
             VECTOR_CASE(TpArrayFloat, float)
             VECTOR_CASE(TpArrayDouble, double)
             VECTOR_CASE(TpArrayComplex, casacore::Complex)
@@ -628,6 +962,7 @@ extern "C" {
             CASE(TpUShort, casacore::uShort)
             CASE(TpInt, casacore::Int)
             CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
             CASE(TpFloat, float)
             CASE(TpDouble, double)
             CASE(TpComplex, casacore::Complex)
@@ -693,6 +1028,7 @@ extern "C" {
             CASE(TpUShort, casacore::uShort)
             CASE(TpInt, casacore::Int)
             CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
             CASE(TpFloat, float)
             CASE(TpDouble, double)
             CASE(TpComplex, casacore::Complex)
@@ -761,6 +1097,7 @@ extern "C" {
             CASE(TpUShort, casacore::uShort)
             CASE(TpInt, casacore::Int)
             CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
             CASE(TpFloat, float)
             CASE(TpDouble, double)
             CASE(TpComplex, casacore::Complex)
@@ -798,6 +1135,57 @@ extern "C" {
         return 0;
     }
 
+    int
+    tabledesc_set_data_manager(
+        GlueTableDesc &table_desc,
+        const StringBridge &col_name,
+        const StringBridge &dm_type,
+        const StringBridge &dm_group,
+        ExcInfo &exc
+    )
+    {
+        try {
+            casacore::ColumnDesc& column_desc = table_desc.rwColumnDesc(bridge_string(col_name));
+            column_desc.dataManagerType() = bridge_string(dm_type);
+            column_desc.dataManagerGroup() = bridge_string(dm_group);
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+        return 0;
+    }
+
+    int
+    tabledesc_define_hypercolumn(
+        GlueTableDesc &table_desc,
+        const StringBridge &name,
+        const unsigned long n_dims,
+        const unsigned long n_data_cols,
+        const StringBridge *data_cols,
+        const unsigned long n_coord_cols,
+        const StringBridge *coord_cols,
+        const unsigned long n_id_cols,
+        const StringBridge *id_cols,
+        ExcInfo &exc
+    )
+    {
+        try {
+            casacore::Vector<casacore::String> data_names(
+                bridge_string_array(data_cols, casacore::IPosition(1, n_data_cols)));
+            casacore::Vector<casacore::String> coord_names(
+                bridge_string_array(coord_cols, casacore::IPosition(1, n_coord_cols)));
+            casacore::Vector<casacore::String> id_names(
+                bridge_string_array(id_cols, casacore::IPosition(1, n_id_cols)));
+
+            table_desc.defineHypercolumn(bridge_string(name), n_dims, data_names,
+                                         coord_names, id_names);
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+        return 0;
+    }
+
     int
     tabledesc_put_keyword(
         GlueTableDesc &table_desc,
@@ -860,6 +1248,50 @@ extern "C" {
         }
     }
 
+    int
+    tabledesc_get_column_names(const GlueTableDesc &table_desc, StringBridgeCallback callback,
+                               void *ctxt, ExcInfo &exc)
+    {
+        try {
+            unbridge_string_array(table_desc.columnNames(), callback, ctxt);
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    // TODO: dedup this from table_get_column_info
+    int
+    tabledesc_get_column_info(const GlueTableDesc &table_desc, const StringBridge &col_name,
+                              GlueDataType *data_type, int *is_scalar, int *is_fixed_shape,
+                              int *n_dim, unsigned long dims[8], ExcInfo &exc)
+    {
+        try {
+            const casacore::ColumnDesc &desc = table_desc.columnDesc(bridge_string(col_name));
+            const casacore::IPosition &shape = desc.shape();
+
+            if (shape.size() > 8)
+                throw std::runtime_error("cannot handle columns with data of dimensionality greater than 8");
+
+            *data_type = desc.dataType();
+            *is_scalar = (int) desc.isScalar();
+            *is_fixed_shape = (int) desc.isFixedShape();
+            *n_dim = (int) desc.ndim();
+
+            if ((int) shape.size() == *n_dim) {
+                for (int i = 0; i < *n_dim; i++)
+                    dims[*n_dim - 1 - i] = (unsigned long) shape[i];
+            }
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
     // Tables
 
     GlueTable *
@@ -870,6 +1302,9 @@ extern "C" {
         // number of rows
         unsigned long n_rows,
         const TableCreateMode mode,
+        const TableEndianFormat endian,
+        // Optional data manager info, as accepted by SetupNewTable::bindCreate
+        const GlueTableRecord *dminfo,
         ExcInfo &exc
     )
     {
@@ -884,10 +1319,12 @@ extern "C" {
         // TODO: expose this as an argument?
         casacore::Bool initialize = true;
 
-        // always use the local endianness
-        GlueTable::EndianFormat endian_format = GlueTable::EndianFormat::LocalEndian;
+        // The enumeration values match.
+        GlueTable::EndianFormat endian_format = (GlueTable::EndianFormat) endian;
 
         try {
+            check_row_range(0, n_rows);
+
             GlueTable::TableOption table_option;
 
             switch(mode) {
@@ -903,6 +1340,10 @@ extern "C" {
                 table_desc,
                 table_option
             );
+
+            if (dminfo != NULL)
+                newTable.bindCreate(*dminfo);
+
             return new GlueTable(newTable, type, n_rows, initialize, endian_format, casacore::TSMOption());
         } catch (...) {
             handle_exception(exc);
@@ -921,6 +1362,14 @@ extern "C" {
             option = GlueTable::NewNoReplace;
 
         try {
+            if (mode == TOM_OPEN_READONLY_NOLOCK)
+                return new GlueTable(
+                    bridge_string(path),
+                    casacore::TableLock(casacore::TableLock::NoLocking),
+                    option,
+                    casacore::TSMOption()
+                );
+
             return new GlueTable(bridge_string(path), option, casacore::TSMOption());
         } catch (...) {
             handle_exception(exc);
@@ -928,6 +1377,69 @@ extern "C" {
         }
     }
 
+    // Register a stand-in for a data manager type that cannot be loaded.
+    // Returns 1 if the stand-in was registered and 0 if the type turned out
+    // to be available after all.
+    int
+    data_manager_register_masked(const StringBridge &type, ExcInfo &exc)
+    {
+        try {
+            casacore::String type_name = bridge_string(type);
+
+            if (try_load_data_manager(type_name))
+                return 0;
+
+            casacore::DataManager::registerCtor(type_name, MaskedStMan::make);
+            return 1;
+        } catch (...) {
+            handle_exception(exc);
+            return -1;
+        }
+    }
+
+    GlueTable *
+    table_alloc_and_open_masked(const StringBridge &path, ExcInfo &exc)
+    {
+        masking_allowed = true;
+
+        try {
+            GlueTable *table = new GlueTable(bridge_string(path), GlueTable::Old, casacore::TSMOption());
+            masking_allowed = false;
+            return table;
+        } catch (...) {
+            masking_allowed = false;
+            handle_exception(exc);
+            return NULL;
+        }
+    }
+
+    // Find the columns whose data manager has been replaced by a stand-in,
+    // passing the name of each column and then the type of its data
+    // manager to the callback.
+    int
+    table_get_masked_columns(const GlueTable &table, StringBridgeCallback callback,
+                             void *ctxt, ExcInfo &exc)
+    {
+        try {
+            const casacore::TableDesc &desc = table.tableDesc();
+
+            for (casacore::uInt i = 0; i < desc.ncolumn(); i++) {
+                const casacore::String &name = desc[i].name();
+                casacore::DataManager *dm = table.findDataManager(name, casacore::True);
+
+                if (dynamic_cast<MaskedStMan *>(dm) != NULL) {
+                    unbridge_string(name, callback, ctxt);
+                    unbridge_string(dm->dataManagerType(), callback, ctxt);
+                }
+            }
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
     void
     table_close_and_free(GlueTable *table, ExcInfo &exc)
     {
@@ -942,14 +1454,22 @@ extern "C" {
     table_n_rows(const GlueTable &table)
     {
         // I *think* we can safely say that this code should never trigger an exception.
-        return table.nrow();
+        try {
+            return table.nrow();
+        } catch (...) {
+            fatal_exception("table_n_rows");
+        }
     }
 
     unsigned long
     table_n_columns(const GlueTable &table)
     {
         // I *think* we can safely say that this code should never trigger an exception.
-        return table.actualTableDesc().columnDescSet().ncolumn();
+        try {
+            return table.actualTableDesc().columnDescSet().ncolumn();
+        } catch (...) {
+            fatal_exception("table_n_columns");
+        }
     }
 
     int
@@ -970,6 +1490,43 @@ extern "C" {
         return 0;
     }
 
+    // Returns 0 for plain tables, 1 for reference tables, 2 for concatenated
+    // tables, and 3 for anything else, such as in-memory tables.
+    int
+    table_get_kind(const GlueTable &table)
+    {
+        try {
+            casacore::BaseTable *base = TableBaseAccess::get(table);
+
+            if (dynamic_cast<casacore::RefTable *>(base) != NULL)
+                return 1;
+            if (dynamic_cast<casacore::ConcatTable *>(base) != NULL)
+                return 2;
+            if (dynamic_cast<casacore::PlainTable *>(base) != NULL)
+                return 0;
+            return 3;
+        } catch (...) {
+            fatal_exception("table_get_kind");
+        }
+    }
+
+    int
+    table_get_part_names(const GlueTable &table, const int recursive,
+                         StringBridgeCallback callback, void *ctxt, ExcInfo &exc)
+    {
+        try {
+            casacore::Block<casacore::String> names = table.getPartNames(recursive != 0);
+
+            for (casacore::uInt i = 0; i < names.size(); i++)
+                unbridge_string(names[i], callback, ctxt);
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
     int
     table_get_column_names(const GlueTable &table, StringBridgeCallback callback,
                            void *ctxt, ExcInfo &exc)
@@ -984,6 +1541,17 @@ extern "C" {
         return 0;
     }
 
+    GlueTableDesc *
+    table_get_table_desc(const GlueTable &table, ExcInfo &exc)
+    {
+        try {
+            return new GlueTableDesc(table.actualTableDesc(), GlueTableDesc::TDOption::Scratch);
+        } catch (...) {
+            handle_exception(exc);
+            return NULL;
+        }
+    }
+
     int
     table_remove_column(GlueTable &table, const StringBridge &col_name, ExcInfo &exc)
     {
@@ -997,6 +1565,20 @@ extern "C" {
         return 0;
     }
 
+    int
+    table_rename_column(GlueTable &table, const StringBridge &old_name,
+                        const StringBridge &new_name, ExcInfo &exc)
+    {
+        try {
+            table.renameColumn(bridge_string(new_name), bridge_string(old_name));
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
     // TODO: dedup this from tabledesc_add_scalar_column
 
     int 
@@ -1040,6 +1622,7 @@ extern "C" {
             CASE(TpUShort, casacore::uShort)
             CASE(TpInt, casacore::Int)
             CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
             CASE(TpFloat, float)
             CASE(TpDouble, double)
             CASE(TpComplex, casacore::Complex)
@@ -1101,6 +1684,7 @@ extern "C" {
             CASE(TpUShort, casacore::uShort)
             CASE(TpInt, casacore::Int)
             CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
             CASE(TpFloat, float)
             CASE(TpDouble, double)
             CASE(TpComplex, casacore::Complex)
@@ -1170,6 +1754,7 @@ extern "C" {
             CASE(TpUShort, casacore::uShort)
             CASE(TpInt, casacore::Int)
             CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
             CASE(TpFloat, float)
             CASE(TpDouble, double)
             CASE(TpComplex, casacore::Complex)
@@ -1189,22 +1774,88 @@ extern "C" {
     }
 
 
-    unsigned long
-    table_n_keywords(const GlueTable &table)
-    {
-        return table.keywordSet().nfields();
-    }
-
     int
-    table_get_keyword_info(
-        const GlueTable &table, 
-        KeywordInfoCallback callback, 
-        void *ctxt, 
+    table_add_tiled_array_column(
+        GlueTable &table,
+        GlueDataType data_type,
+        const StringBridge &col_name,
+        const StringBridge &dm_name,
+        // number of dimensions of each cell
+        const unsigned long n_dims,
+        // cell dimensions in C order; ignored unless fixed_shape
+        const unsigned long *dims,
+        bool fixed_shape,
+        // tile dimensions in C order, starting with the row axis
+        const unsigned long *tile_dims,
         ExcInfo &exc
     )
     {
+        casacore::IPosition shape(n_dims);
+        casacore::IPosition tile_shape(n_dims + 1);
+
+        for (casacore::uInt i = 0; i < n_dims; i++)
+            shape[i] = dims[n_dims - 1 - i];
+
+        for (casacore::uInt i = 0; i <= n_dims; i++)
+            tile_shape[i] = tile_dims[n_dims - i];
+
         try {
-            return tablerec_get_keyword_info(table.keywordSet(), callback, ctxt, exc);
+            casacore::TiledShapeStMan stman(bridge_string(dm_name), tile_shape);
+
+            switch (data_type) {
+
+#define CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                if (fixed_shape) \
+                    table.addColumn(casacore::ArrayColumnDesc<CPPTYPE>( \
+                        bridge_string(col_name), "", shape, casacore::ColumnDesc::FixedShape \
+                    ), stman); \
+                else \
+                    table.addColumn(casacore::ArrayColumnDesc<CPPTYPE>( \
+                        bridge_string(col_name), "", (casacore::Int) n_dims \
+                    ), stman); \
+                break; \
+            }
+
+            CASE(TpBool, casacore::Bool)
+            CASE(TpFloat, float)
+            CASE(TpDouble, double)
+            CASE(TpComplex, casacore::Complex)
+            CASE(TpDComplex, casacore::DComplex)
+#undef CASE
+
+            default:
+                throw std::runtime_error("unhandled tiled column data type");
+            }
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+
+    unsigned long
+    table_n_keywords(const GlueTable &table)
+    {
+        try {
+            return table.keywordSet().nfields();
+        } catch (...) {
+            fatal_exception("table_n_keywords");
+        }
+    }
+
+    int
+    table_get_keyword_info(
+        const GlueTable &table, 
+        KeywordInfoCallback callback, 
+        void *ctxt, 
+        ExcInfo &exc
+    )
+    {
+        try {
+            return tablerec_get_keyword_info(table.keywordSet(), callback, ctxt, exc);
         } catch (...) {
             handle_exception(exc);
             return 1;
@@ -1313,15 +1964,17 @@ extern "C" {
     }
 
     int
-    table_deep_copy_no_rows(const GlueTable &table, const StringBridge &dest_path, ExcInfo &exc)
+    table_deep_copy(const GlueTable &table, const StringBridge &dest_path,
+                    const TableEndianFormat endian_format, const unsigned char no_rows,
+                    ExcInfo &exc)
     {
         try {
             table.deepCopy(
                 bridge_string(dest_path),
                 GlueTable::NewNoReplace,
                 casacore::True, // "valueCopy"
-                GlueTable::LocalEndian,
-                casacore::True // "noRows"
+                (GlueTable::EndianFormat) endian_format, // the enumeration values match
+                (casacore::Bool) no_rows
             );
         } catch (...) {
             handle_exception(exc);
@@ -1331,6 +1984,159 @@ extern "C" {
         return 0;
     }
 
+    int
+    table_endian_format(const GlueTable &table, TableEndianFormat *endian_format, ExcInfo &exc)
+    {
+        try {
+            *endian_format = (TableEndianFormat) table.endianFormat();
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    // Only TiledColumnStMan columns whose tiles each hold a whole number of
+    // complete cells, and whose data manager holds no other columns, have a
+    // data file that is one contiguous array of the column's cells. We check
+    // all of that here so that the caller can just map the file.
+    int
+    table_get_mappable_column_layout(GlueTable &table, const StringBridge &col_name,
+                                     StringBridgeCallback callback, void *ctxt,
+                                     GlueDataType *data_type, int *n_dim,
+                                     unsigned long dims[8], ExcInfo &exc)
+    {
+        try {
+            casacore::String name = bridge_string(col_name);
+            const casacore::ColumnDesc &desc = casacore::TableColumn(table, name).columnDesc();
+            const casacore::IPosition &shape = desc.shape();
+
+            if (desc.isScalar() || !desc.isFixedShape())
+                throw std::runtime_error("only fixed-shape array columns can be memory-mapped");
+
+            if (shape.size() > 8)
+                throw std::runtime_error("cannot handle columns with data of dimensionality greater than 8");
+
+            switch (desc.dataType()) {
+            case casacore::TpBool:
+            case casacore::TpString:
+                throw std::runtime_error("only numeric columns can be memory-mapped");
+            default:
+                break;
+            }
+
+            bool want_big = (table.endianFormat() == casacore::Table::BigEndian);
+
+            if (want_big != (bool) casacore::HostInfo::bigEndian())
+                throw std::runtime_error("the table byte order does not match that of this host");
+
+            // The data files of a reference table's root are laid out by the
+            // root's row numbers, not ours.
+            if (!table.isRootTable())
+                throw std::runtime_error("columns of reference tables cannot be memory-mapped; "
+                                         "open the root table instead");
+
+            casacore::ROTiledStManAccessor acc(table, name, casacore::True);
+
+            if (acc.dataManagerType() != "TiledColumnStMan")
+                throw std::runtime_error("column \"" + name + "\" is stored with " +
+                                         acc.dataManagerType() + ", not TiledColumnStMan");
+
+            if (acc.nhypercubes() != 1)
+                throw std::runtime_error("column data must be stored in a single hypercube");
+
+            const casacore::IPosition &tile_shape = acc.getTileShape(0);
+
+            for (casacore::uInt i = 0; i < shape.size(); i++) {
+                if (tile_shape[i] != shape[i])
+                    throw std::runtime_error("column tiles do not span whole cells");
+            }
+
+            casacore::uInt elem_size = casacore::ValType::getTypeSize(desc.dataType());
+
+            if (acc.getBucketSize(0) != tile_shape.product() * elem_size)
+                throw std::runtime_error("column data manager holds data other than this column");
+
+            if (table.isWritable())
+                table.flush();
+
+            char seqnr[16];
+            sprintf(seqnr, ".f%u_TSM0", acc.dataManagerSeqNr());
+            unbridge_string(table.tableName() + "/table" + seqnr, callback, ctxt);
+
+            *data_type = desc.dataType();
+            *n_dim = (int) shape.size();
+
+            for (int i = 0; i < *n_dim; i++)
+                dims[*n_dim - 1 - i] = (unsigned long) shape[i];
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    int
+    table_get_column_tile_shape(const GlueTable &table, const StringBridge &col_name,
+                                int *n_dim, unsigned long dims[8], ExcInfo &exc)
+    {
+        try {
+            casacore::String name = bridge_string(col_name);
+            casacore::DataManager *dm = table.findDataManager(name, casacore::True);
+
+            *n_dim = 0;
+
+            // All of the tiled storage managers have type names of the form
+            // "Tiled*StMan"; anything else has no notion of a tile shape.
+            if (dm->dataManagerType().find("Tiled") != 0)
+                return 0;
+
+            casacore::ROTiledStManAccessor acc(table, name, casacore::True);
+
+            if (acc.nhypercubes() < 1)
+                return 0;
+
+            const casacore::IPosition &tile_shape = acc.getTileShape(0);
+
+            if (tile_shape.size() > 8)
+                throw std::runtime_error("cannot handle tiles of dimensionality greater than 8");
+
+            *n_dim = (int) tile_shape.size();
+
+            for (int i = 0; i < *n_dim; i++)
+                dims[*n_dim - 1 - i] = (unsigned long) tile_shape[i];
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    // Identify the data manager that stores a column, passing its type and
+    // then its name to the callback.
+    int
+    table_get_column_data_manager(const GlueTable &table, const StringBridge &col_name,
+                                  StringBridgeCallback callback, void *ctxt,
+                                  unsigned int *seqnr, ExcInfo &exc)
+    {
+        try {
+            casacore::String name = bridge_string(col_name);
+            casacore::DataManager *dm = table.findDataManager(name, casacore::True);
+
+            *seqnr = dm->sequenceNr();
+            unbridge_string(dm->dataManagerType(), callback, ctxt);
+            unbridge_string(dm->dataManagerName(), callback, ctxt);
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
     int
     table_get_column_info(const GlueTable &table, const StringBridge &col_name,
                           unsigned long *n_rows, GlueDataType *data_type,
@@ -1351,8 +2157,12 @@ extern "C" {
             *is_fixed_shape = (int) desc.isFixedShape();
             *n_dim = (int) desc.ndim();
 
-            for (int i = 0; i < *n_dim; i++) // note: for empty cols, n_dim = -1; this is OK
-                dims[*n_dim - 1 - i] = (unsigned long) shape[i];
+            // For empty columns, n_dim = -1. Variable-shape columns may have a
+            // known dimensionality but no shape.
+            if ((int) shape.size() == *n_dim) {
+                for (int i = 0; i < *n_dim; i++)
+                    dims[*n_dim - 1 - i] = (unsigned long) shape[i];
+            }
         } catch (...) {
             handle_exception(exc);
             return 1;
@@ -1388,6 +2198,7 @@ extern "C" {
             CASE(TpUShort, casacore::uShort)
             CASE(TpInt, casacore::Int)
             CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
             CASE(TpFloat, float)
             CASE(TpDouble, double)
             CASE(TpComplex, casacore::Complex)
@@ -1434,12 +2245,13 @@ extern "C" {
                         int *n_dim, unsigned long dims[8], ExcInfo &exc)
     {
         try {
+            check_row_range(row_number, 1);
             casacore::TableColumn col(table, bridge_string(col_name));
             const casacore::ColumnDesc &desc = col.columnDesc();
 
-            *data_type = desc.dataType();
+            *data_type = desc.trueDataType();
 
-            if (desc.isScalar())
+            if (desc.isScalar() || !col.isDefined(row_number))
                 *n_dim = 0;
             else {
                 *n_dim = (int) col.ndim(row_number);
@@ -1467,6 +2279,7 @@ extern "C" {
                    const unsigned long row_number, void *data, ExcInfo &exc)
     {
         try {
+            check_row_range(row_number, 1);
             casacore::TableColumn col(table, bridge_string(col_name));
             const casacore::ColumnDesc &desc = col.columnDesc();
             casacore::IPosition shape;
@@ -1498,6 +2311,7 @@ extern "C" {
             SCALAR_CASE(TpUShort, casacore::uShort)
             SCALAR_CASE(TpInt, casacore::Int)
             SCALAR_CASE(TpUInt, casacore::uInt)
+            SCALAR_CASE(TpInt64, casacore::Int64)
             SCALAR_CASE(TpFloat, float)
             SCALAR_CASE(TpDouble, double)
             SCALAR_CASE(TpComplex, casacore::Complex)
@@ -1510,6 +2324,7 @@ extern "C" {
             VECTOR_CASE(TpArrayUShort, casacore::uShort)
             VECTOR_CASE(TpArrayInt, casacore::Int)
             VECTOR_CASE(TpArrayUInt, casacore::uInt)
+            VECTOR_CASE(TpArrayInt64, casacore::Int64)
             VECTOR_CASE(TpArrayFloat, float)
             VECTOR_CASE(TpArrayDouble, double)
             VECTOR_CASE(TpArrayComplex, casacore::Complex)
@@ -1535,33 +2350,83 @@ extern "C" {
         return 0;
     }
 
-    int
-    table_get_cell_string(const GlueTable &table, const StringBridge &col_name,
-                          const unsigned long row_number, StringBridgeCallback callback,
-                          void *ctxt, ExcInfo &exc)
+    // Get the shape of the data of *n_rows* rows of a column, starting with
+    // row *first_row*, in casacore's axis order, with the row axis last. The
+    // column must be scalar or fixed-shape, or else every cell must have the
+    // same shape as the first one; casacore checks the latter as the cells
+    // are transferred.
+    casacore::IPosition
+    column_range_shape(const casacore::TableColumn &col, const unsigned long first_row,
+                       const unsigned long n_rows)
     {
-        try {
-            casacore::ScalarColumn<casacore::String> col(table, bridge_string(col_name));
-            unbridge_string(col.get(row_number), callback, ctxt);
-        } catch (...) {
-            handle_exception(exc);
-            return 1;
-        }
+        const casacore::ColumnDesc &desc = col.columnDesc();
 
-        return 0;
+        if (desc.isScalar())
+            return casacore::IPosition(1, n_rows);
+
+        casacore::IPosition shape;
+
+        if (desc.isFixedShape())
+            shape = desc.shape();
+        else if (n_rows == 0)
+            shape = casacore::IPosition(std::max(desc.ndim(), 1), 0);
+        else if (col.isDefined(first_row))
+            shape = col.shape(first_row);
+        else
+            throw std::runtime_error("bulk column I/O requires a scalar column or cells of uniform shape");
+
+        shape.append(casacore::IPosition(1, n_rows));
+        return shape;
     }
 
+    // These functions assume that the caller has already vetted the types and
+    // has figured how big `data` needs to be.
     int
-    table_get_cell_string_array(const GlueTable &table, const StringBridge &col_name,
-                                const unsigned long row_number, StringBridgeCallback callback,
-                                void *ctxt, ExcInfo &exc)
+    table_get_column_range(const GlueTable &table, const StringBridge &col_name,
+                           const unsigned long start_row, const unsigned long n_rows,
+                           void *data, ExcInfo &exc)
     {
         try {
-            casacore::ArrayColumn<casacore::String> col(table, bridge_string(col_name));
-            casacore::IPosition shape = col.shape(row_number);
-            casacore::Array<casacore::String> array(shape);
-            col.get(row_number, array, casacore::False);
-            unbridge_string_array(array, callback, ctxt);
+            check_row_range(start_row, n_rows);
+            casacore::String name = bridge_string(col_name);
+            casacore::TableColumn tcol(table, name);
+            const casacore::ColumnDesc &desc = tcol.columnDesc();
+            casacore::IPosition shape = column_range_shape(tcol, start_row, n_rows);
+            casacore::Slicer rows(casacore::IPosition(1, start_row), casacore::IPosition(1, n_rows));
+
+            switch (desc.dataType()) {
+
+#define CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                if (desc.isScalar()) { \
+                    casacore::ScalarColumn<CPPTYPE> col(table, name); \
+                    casacore::Vector<CPPTYPE> vec(shape, (CPPTYPE *) data, casacore::SHARE); \
+                    col.getColumnRange(rows, vec); \
+                } else { \
+                    casacore::ArrayColumn<CPPTYPE> col(table, name); \
+                    casacore::Array<CPPTYPE> array(shape, (CPPTYPE *) data, casacore::SHARE); \
+                    col.getColumnRange(rows, array); \
+                } \
+                break; \
+            }
+
+            CASE(TpBool, casacore::Bool)
+            CASE(TpChar, casacore::Char)
+            CASE(TpUChar, casacore::uChar)
+            CASE(TpShort, casacore::Short)
+            CASE(TpUShort, casacore::uShort)
+            CASE(TpInt, casacore::Int)
+            CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
+            CASE(TpFloat, float)
+            CASE(TpDouble, double)
+            CASE(TpComplex, casacore::Complex)
+            CASE(TpDComplex, casacore::DComplex)
+#undef CASE
+
+            default:
+                throw std::runtime_error("unhandled column data type for bulk I/O");
+            }
         } catch (...) {
             handle_exception(exc);
             return 1;
@@ -1571,76 +2436,57 @@ extern "C" {
     }
 
     int
-    table_put_cell(GlueTable &table, const StringBridge &col_name,
-                   const unsigned long row_number, const GlueDataType data_type,
-                   const unsigned long n_dims, const unsigned long *dims,
-                   void *data, ExcInfo &exc)
+    table_get_column_cells(const GlueTable &table, const StringBridge &col_name,
+                           const unsigned long *row_numbers, const unsigned long n_rows,
+                           void *data, ExcInfo &exc)
     {
         try {
-            switch (data_type) {
-
-#define SCALAR_CASE(DTYPE, CPPTYPE) \
-            case casacore::DTYPE: { \
-                casacore::ScalarColumn<CPPTYPE> col(table, bridge_string(col_name)); \
-                col.put(row_number, *(CPPTYPE *) data); \
-                break; \
-            }
-
-#define VECTOR_CASE(DTYPE, CPPTYPE) \
-            case casacore::DTYPE: { \
-                casacore::ArrayColumn<CPPTYPE> col(table, bridge_string(col_name)); \
-                casacore::IPosition shape(n_dims); \
-                for (casacore::uInt i = 0; i < n_dims; i++) \
-                    shape[i] = dims[n_dims - 1 - i]; \
-                casacore::Array<CPPTYPE> array(shape, (CPPTYPE *) data, casacore::SHARE); \
-                col.put(row_number, array); \
-                break; \
+            casacore::String name = bridge_string(col_name);
+            casacore::TableColumn tcol(table, name);
+            const casacore::ColumnDesc &desc = tcol.columnDesc();
+            casacore::Vector<glue_rownr_t> rows_vec(n_rows);
+
+            for (unsigned long i = 0; i < n_rows; i++) {
+                check_row_range(row_numbers[i], 1);
+                rows_vec[i] = row_numbers[i];
             }
 
-            SCALAR_CASE(TpBool, casacore::Bool)
-            SCALAR_CASE(TpChar, casacore::Char)
-            SCALAR_CASE(TpUChar, casacore::uChar)
-            SCALAR_CASE(TpShort, casacore::Short)
-            SCALAR_CASE(TpUShort, casacore::uShort)
-            SCALAR_CASE(TpInt, casacore::Int)
-            SCALAR_CASE(TpUInt, casacore::uInt)
-            SCALAR_CASE(TpFloat, float)
-            SCALAR_CASE(TpDouble, double)
-            SCALAR_CASE(TpComplex, casacore::Complex)
-            SCALAR_CASE(TpDComplex, casacore::DComplex)
+            casacore::IPosition shape = column_range_shape(tcol, n_rows ? row_numbers[0] : 0, n_rows);
 
-            VECTOR_CASE(TpArrayBool, casacore::Bool)
-            VECTOR_CASE(TpArrayChar, casacore::Char)
-            VECTOR_CASE(TpArrayUChar, casacore::uChar)
-            VECTOR_CASE(TpArrayShort, casacore::Short)
-            VECTOR_CASE(TpArrayUShort, casacore::uShort)
-            VECTOR_CASE(TpArrayInt, casacore::Int)
-            VECTOR_CASE(TpArrayUInt, casacore::uInt)
-            VECTOR_CASE(TpArrayFloat, float)
-            VECTOR_CASE(TpArrayDouble, double)
-            VECTOR_CASE(TpArrayComplex, casacore::Complex)
-            VECTOR_CASE(TpArrayDComplex, casacore::DComplex)
+            casacore::RefRows rows(rows_vec);
 
-#undef SCALAR_CASE
-#undef VECTOR_CASE
+            switch (desc.dataType()) {
 
-            case casacore::TpString: {
-                casacore::ScalarColumn<casacore::String> col(table, bridge_string(col_name));
-                col.put(row_number, bridge_string(*((StringBridge *) data)));
-                break;
+#define CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                if (desc.isScalar()) { \
+                    casacore::ScalarColumn<CPPTYPE> col(table, name); \
+                    casacore::Vector<CPPTYPE> vec(shape, (CPPTYPE *) data, casacore::SHARE); \
+                    col.getColumnCells(rows, vec); \
+                } else { \
+                    casacore::ArrayColumn<CPPTYPE> col(table, name); \
+                    casacore::Array<CPPTYPE> array(shape, (CPPTYPE *) data, casacore::SHARE); \
+                    col.getColumnCells(rows, array); \
+                } \
+                break; \
             }
 
-            case casacore::TpArrayString: {
-                casacore::ArrayColumn<casacore::String> col(table, bridge_string(col_name));
-                casacore::IPosition shape(n_dims);
-                for (casacore::uInt i = 0; i < n_dims; i++)
-                    shape[i] = dims[n_dims - 1 - i];
-                col.put(row_number, bridge_string_array((const StringBridge *) data, shape));
-                break;
-            }
+            CASE(TpBool, casacore::Bool)
+            CASE(TpChar, casacore::Char)
+            CASE(TpUChar, casacore::uChar)
+            CASE(TpShort, casacore::Short)
+            CASE(TpUShort, casacore::uShort)
+            CASE(TpInt, casacore::Int)
+            CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
+            CASE(TpFloat, float)
+            CASE(TpDouble, double)
+            CASE(TpComplex, casacore::Complex)
+            CASE(TpDComplex, casacore::DComplex)
+#undef CASE
 
             default:
-                throw std::runtime_error("unhandled cell data type");
+                throw std::runtime_error("unhandled column data type for bulk I/O");
             }
         } catch (...) {
             handle_exception(exc);
@@ -1650,10 +2496,506 @@ extern "C" {
         return 0;
     }
 
-    int
-    table_add_rows(GlueTable &table, const unsigned long n_rows, ExcInfo &exc)
+    GlueTable *
+    table_snapshot(const GlueTable &table, ExcInfo &exc)
     {
         try {
+            casacore::Vector<glue_rownr_t> rows(table.nrow());
+
+            for (glue_rownr_t i = 0; i < rows.size(); i++)
+                rows[i] = i;
+
+            return new GlueTable(TableFromBase(new ReadOnlyRefTable(TableBaseAccess::get(table), rows)));
+        } catch (...) {
+            handle_exception(exc);
+            return NULL;
+        }
+    }
+
+    // Make a reference table of the given rows of `table`, in the given
+    // order, keeping only the named columns, or all of them if `n_cols` is
+    // zero.
+    GlueTable *
+    table_select_rows(const GlueTable &table, const unsigned long *row_numbers,
+                      const unsigned long n_rows, const StringBridge *col_names,
+                      const unsigned long n_cols, ExcInfo &exc)
+    {
+        try {
+            casacore::Vector<glue_rownr_t> rows(n_rows);
+
+            for (unsigned long i = 0; i < n_rows; i++) {
+                if (row_numbers[i] >= table.nrow())
+                    throw std::runtime_error("row number " + std::to_string(row_numbers[i]) +
+                                             " is beyond the end of the table");
+                rows[i] = row_numbers[i];
+            }
+
+            casacore::Table result = table(rows);
+
+            if (n_cols > 0) {
+                casacore::Block<casacore::String> names(n_cols);
+
+                for (unsigned long i = 0; i < n_cols; i++)
+                    names[i] = bridge_string(col_names[i]);
+
+                result = result.project(names);
+            }
+
+            return new GlueTable(result);
+        } catch (...) {
+            handle_exception(exc);
+            return NULL;
+        }
+    }
+
+#ifdef RUBBL_SYSTEM_CASACORE
+    // Run a TaQL command in which `$1` refers to `table`, returning the
+    // table that it produces. The bundled casacore does not include TaQL,
+    // so this is only available with a system casacore.
+    GlueTable *
+    table_query(const GlueTable &table, const StringBridge &command, ExcInfo &exc)
+    {
+        try {
+            casacore::TaQLResult result = casacore::tableCommand(bridge_string(command), table);
+
+            if (!result.isTable())
+                throw std::runtime_error("TaQL command did not produce a table");
+
+            return new GlueTable(result.table());
+        } catch (...) {
+            handle_exception(exc);
+            return NULL;
+        }
+    }
+#endif
+
+    // Get the numbers of the rows of `table` in its root table. The caller
+    // must provide room for `table.nrow()` of them.
+    int
+    table_get_row_numbers(const GlueTable &table, unsigned long *row_numbers, ExcInfo &exc)
+    {
+        try {
+            casacore::Vector<glue_rownr_t> rows = table.rowNumbers();
+
+            for (size_t i = 0; i < rows.size(); i++)
+                row_numbers[i] = rows[i];
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    int
+    table_put_column_range(GlueTable &table, const StringBridge &col_name,
+                           const unsigned long start_row, const unsigned long n_rows,
+                           const void *data, ExcInfo &exc)
+    {
+        try {
+            check_row_range(start_row, n_rows);
+            casacore::String name = bridge_string(col_name);
+            casacore::TableColumn tcol(table, name);
+            const casacore::ColumnDesc &desc = tcol.columnDesc();
+            casacore::IPosition shape = column_range_shape(tcol, start_row, n_rows);
+            casacore::Slicer rows(casacore::IPosition(1, start_row), casacore::IPosition(1, n_rows));
+
+            switch (desc.dataType()) {
+
+#define CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                if (desc.isScalar()) { \
+                    casacore::ScalarColumn<CPPTYPE> col(table, name); \
+                    const casacore::Vector<CPPTYPE> vec(shape, (CPPTYPE *) data, casacore::SHARE); \
+                    col.putColumnRange(rows, vec); \
+                } else { \
+                    casacore::ArrayColumn<CPPTYPE> col(table, name); \
+                    const casacore::Array<CPPTYPE> array(shape, (CPPTYPE *) data, casacore::SHARE); \
+                    col.putColumnRange(rows, array); \
+                } \
+                break; \
+            }
+
+            CASE(TpBool, casacore::Bool)
+            CASE(TpChar, casacore::Char)
+            CASE(TpUChar, casacore::uChar)
+            CASE(TpShort, casacore::Short)
+            CASE(TpUShort, casacore::uShort)
+            CASE(TpInt, casacore::Int)
+            CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
+            CASE(TpFloat, float)
+            CASE(TpDouble, double)
+            CASE(TpComplex, casacore::Complex)
+            CASE(TpDComplex, casacore::DComplex)
+#undef CASE
+
+            default:
+                throw std::runtime_error("unhandled column data type for bulk I/O");
+            }
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    // Like table_get_column_range, but for array columns whose cells need
+    // not have a fixed shape: every cell in the range must have the shape
+    // `cell_dims`, which is in C order.
+    int
+    table_get_column_range_shaped(const GlueTable &table, const StringBridge &col_name,
+                                  const unsigned long start_row, const unsigned long n_rows,
+                                  const unsigned long n_dims, const unsigned long *cell_dims,
+                                  void *data, ExcInfo &exc)
+    {
+        try {
+            check_row_range(start_row, n_rows);
+            casacore::String name = bridge_string(col_name);
+            const casacore::ColumnDesc &desc = casacore::TableColumn(table, name).columnDesc();
+
+            if (desc.isScalar())
+                throw std::runtime_error("shaped bulk column I/O requires an array column");
+
+            casacore::IPosition shape(n_dims + 1);
+
+            for (unsigned long i = 0; i < n_dims; i++)
+                shape[i] = cell_dims[n_dims - 1 - i];
+
+            shape[n_dims] = n_rows;
+            casacore::Slicer rows(casacore::IPosition(1, start_row), casacore::IPosition(1, n_rows));
+
+            switch (desc.dataType()) {
+
+#define CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                casacore::ArrayColumn<CPPTYPE> col(table, name); \
+                casacore::Array<CPPTYPE> array(shape, (CPPTYPE *) data, casacore::SHARE); \
+                col.getColumnRange(rows, array); \
+                break; \
+            }
+
+            CASE(TpBool, casacore::Bool)
+            CASE(TpChar, casacore::Char)
+            CASE(TpUChar, casacore::uChar)
+            CASE(TpShort, casacore::Short)
+            CASE(TpUShort, casacore::uShort)
+            CASE(TpInt, casacore::Int)
+            CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
+            CASE(TpFloat, float)
+            CASE(TpDouble, double)
+            CASE(TpComplex, casacore::Complex)
+            CASE(TpDComplex, casacore::DComplex)
+#undef CASE
+
+            default:
+                throw std::runtime_error("unhandled column data type for bulk I/O");
+            }
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    // Like table_put_column_range, but for array columns whose cells need
+    // not have a fixed shape: every cell in the range is given the shape
+    // `cell_dims`, which is in C order.
+    int
+    table_put_column_range_shaped(GlueTable &table, const StringBridge &col_name,
+                                  const unsigned long start_row, const unsigned long n_rows,
+                                  const unsigned long n_dims, const unsigned long *cell_dims,
+                                  const void *data, ExcInfo &exc)
+    {
+        try {
+            check_row_range(start_row, n_rows);
+            casacore::String name = bridge_string(col_name);
+            const casacore::ColumnDesc &desc = casacore::TableColumn(table, name).columnDesc();
+
+            if (desc.isScalar())
+                throw std::runtime_error("shaped bulk column I/O requires an array column");
+
+            casacore::IPosition shape(n_dims + 1);
+
+            for (unsigned long i = 0; i < n_dims; i++)
+                shape[i] = cell_dims[n_dims - 1 - i];
+
+            shape[n_dims] = n_rows;
+            casacore::Slicer rows(casacore::IPosition(1, start_row), casacore::IPosition(1, n_rows));
+
+            switch (desc.dataType()) {
+
+#define CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                casacore::ArrayColumn<CPPTYPE> col(table, name); \
+                const casacore::Array<CPPTYPE> array(shape, (CPPTYPE *) data, casacore::SHARE); \
+                col.putColumnRange(rows, array); \
+                break; \
+            }
+
+            CASE(TpBool, casacore::Bool)
+            CASE(TpChar, casacore::Char)
+            CASE(TpUChar, casacore::uChar)
+            CASE(TpShort, casacore::Short)
+            CASE(TpUShort, casacore::uShort)
+            CASE(TpInt, casacore::Int)
+            CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
+            CASE(TpFloat, float)
+            CASE(TpDouble, double)
+            CASE(TpComplex, casacore::Complex)
+            CASE(TpDComplex, casacore::DComplex)
+#undef CASE
+
+            default:
+                throw std::runtime_error("unhandled column data type for bulk I/O");
+            }
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    // Build the slicer for a cell slice. The Rust side passes the start and
+    // shape in C order, so we reverse them.
+    casacore::Slicer
+    cell_slicer(const unsigned long n_dims, const unsigned long *start,
+                const unsigned long *shape)
+    {
+        casacore::IPosition cstart(n_dims), cshape(n_dims);
+
+        for (unsigned long i = 0; i < n_dims; i++) {
+            cstart[i] = (ssize_t) start[n_dims - 1 - i];
+            cshape[i] = (ssize_t) shape[n_dims - 1 - i];
+        }
+
+        return casacore::Slicer(cstart, cshape);
+    }
+
+    int
+    table_get_cell_slice(const GlueTable &table, const StringBridge &col_name,
+                         const unsigned long row_number, const unsigned long n_dims,
+                         const unsigned long *start, const unsigned long *shape,
+                         void *data, ExcInfo &exc)
+    {
+        try {
+            check_row_range(row_number, 1);
+            casacore::String name = bridge_string(col_name);
+            const casacore::ColumnDesc &desc = casacore::TableColumn(table, name).columnDesc();
+            casacore::Slicer slicer = cell_slicer(n_dims, start, shape);
+
+            switch (desc.dataType()) {
+
+#define CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                casacore::ArrayColumn<CPPTYPE> col(table, name); \
+                casacore::Array<CPPTYPE> array(slicer.length(), (CPPTYPE *) data, casacore::SHARE); \
+                col.getSlice(row_number, slicer, array); \
+                break; \
+            }
+
+            CASE(TpBool, casacore::Bool)
+            CASE(TpChar, casacore::Char)
+            CASE(TpUChar, casacore::uChar)
+            CASE(TpShort, casacore::Short)
+            CASE(TpUShort, casacore::uShort)
+            CASE(TpInt, casacore::Int)
+            CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
+            CASE(TpFloat, float)
+            CASE(TpDouble, double)
+            CASE(TpComplex, casacore::Complex)
+            CASE(TpDComplex, casacore::DComplex)
+#undef CASE
+
+            default:
+                throw std::runtime_error("unhandled column data type for cell slice I/O");
+            }
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    int
+    table_put_cell_slice(GlueTable &table, const StringBridge &col_name,
+                         const unsigned long row_number, const unsigned long n_dims,
+                         const unsigned long *start, const unsigned long *shape,
+                         const void *data, ExcInfo &exc)
+    {
+        try {
+            check_row_range(row_number, 1);
+            casacore::String name = bridge_string(col_name);
+            const casacore::ColumnDesc &desc = casacore::TableColumn(table, name).columnDesc();
+            casacore::Slicer slicer = cell_slicer(n_dims, start, shape);
+
+            switch (desc.dataType()) {
+
+#define CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                casacore::ArrayColumn<CPPTYPE> col(table, name); \
+                const casacore::Array<CPPTYPE> array(slicer.length(), (CPPTYPE *) data, casacore::SHARE); \
+                col.putSlice(row_number, slicer, array); \
+                break; \
+            }
+
+            CASE(TpBool, casacore::Bool)
+            CASE(TpChar, casacore::Char)
+            CASE(TpUChar, casacore::uChar)
+            CASE(TpShort, casacore::Short)
+            CASE(TpUShort, casacore::uShort)
+            CASE(TpInt, casacore::Int)
+            CASE(TpUInt, casacore::uInt)
+            CASE(TpInt64, casacore::Int64)
+            CASE(TpFloat, float)
+            CASE(TpDouble, double)
+            CASE(TpComplex, casacore::Complex)
+            CASE(TpDComplex, casacore::DComplex)
+#undef CASE
+
+            default:
+                throw std::runtime_error("unhandled column data type for cell slice I/O");
+            }
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    int
+    table_get_cell_string(const GlueTable &table, const StringBridge &col_name,
+                          const unsigned long row_number, StringBridgeCallback callback,
+                          void *ctxt, ExcInfo &exc)
+    {
+        try {
+            check_row_range(row_number, 1);
+            casacore::ScalarColumn<casacore::String> col(table, bridge_string(col_name));
+            unbridge_string(col.get(row_number), callback, ctxt);
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    int
+    table_get_cell_string_array(const GlueTable &table, const StringBridge &col_name,
+                                const unsigned long row_number, StringBridgeCallback callback,
+                                void *ctxt, ExcInfo &exc)
+    {
+        try {
+            check_row_range(row_number, 1);
+            casacore::ArrayColumn<casacore::String> col(table, bridge_string(col_name));
+            casacore::IPosition shape = col.shape(row_number);
+            casacore::Array<casacore::String> array(shape);
+            col.get(row_number, array, casacore::False);
+            unbridge_string_array(array, callback, ctxt);
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    int
+    table_put_cell(GlueTable &table, const StringBridge &col_name,
+                   const unsigned long row_number, const GlueDataType data_type,
+                   const unsigned long n_dims, const unsigned long *dims,
+                   void *data, ExcInfo &exc)
+    {
+        try {
+            check_row_range(row_number, 1);
+            switch (data_type) {
+
+#define SCALAR_CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                casacore::ScalarColumn<CPPTYPE> col(table, bridge_string(col_name)); \
+                col.put(row_number, *(CPPTYPE *) data); \
+                break; \
+            }
+
+#define VECTOR_CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                casacore::ArrayColumn<CPPTYPE> col(table, bridge_string(col_name)); \
+                casacore::IPosition shape(n_dims); \
+                for (casacore::uInt i = 0; i < n_dims; i++) \
+                    shape[i] = dims[n_dims - 1 - i]; \
+                casacore::Array<CPPTYPE> array(shape, (CPPTYPE *) data, casacore::SHARE); \
+                col.put(row_number, array); \
+                break; \
+            }
+
+            SCALAR_CASE(TpBool, casacore::Bool)
+            SCALAR_CASE(TpChar, casacore::Char)
+            SCALAR_CASE(TpUChar, casacore::uChar)
+            SCALAR_CASE(TpShort, casacore::Short)
+            SCALAR_CASE(TpUShort, casacore::uShort)
+            SCALAR_CASE(TpInt, casacore::Int)
+            SCALAR_CASE(TpUInt, casacore::uInt)
+            SCALAR_CASE(TpInt64, casacore::Int64)
+            SCALAR_CASE(TpFloat, float)
+            SCALAR_CASE(TpDouble, double)
+            SCALAR_CASE(TpComplex, casacore::Complex)
+            SCALAR_CASE(TpDComplex, casacore::DComplex)
+
+            VECTOR_CASE(TpArrayBool, casacore::Bool)
+            VECTOR_CASE(TpArrayChar, casacore::Char)
+            VECTOR_CASE(TpArrayUChar, casacore::uChar)
+            VECTOR_CASE(TpArrayShort, casacore::Short)
+            VECTOR_CASE(TpArrayUShort, casacore::uShort)
+            VECTOR_CASE(TpArrayInt, casacore::Int)
+            VECTOR_CASE(TpArrayUInt, casacore::uInt)
+            VECTOR_CASE(TpArrayInt64, casacore::Int64)
+            VECTOR_CASE(TpArrayFloat, float)
+            VECTOR_CASE(TpArrayDouble, double)
+            VECTOR_CASE(TpArrayComplex, casacore::Complex)
+            VECTOR_CASE(TpArrayDComplex, casacore::DComplex)
+
+#undef SCALAR_CASE
+#undef VECTOR_CASE
+
+            case casacore::TpString: {
+                casacore::ScalarColumn<casacore::String> col(table, bridge_string(col_name));
+                col.put(row_number, bridge_string(*((StringBridge *) data)));
+                break;
+            }
+
+            case casacore::TpArrayString: {
+                casacore::ArrayColumn<casacore::String> col(table, bridge_string(col_name));
+                casacore::IPosition shape(n_dims);
+                for (casacore::uInt i = 0; i < n_dims; i++)
+                    shape[i] = dims[n_dims - 1 - i];
+                col.put(row_number, bridge_string_array((const StringBridge *) data, shape));
+                break;
+            }
+
+            default:
+                throw std::runtime_error("unhandled cell data type");
+            }
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    int
+    table_add_rows(GlueTable &table, const unsigned long n_rows, ExcInfo &exc)
+    {
+        try {
+            check_row_range(table.nrow(), n_rows);
             table.addRow(n_rows);
         } catch (...) {
             handle_exception(exc);
@@ -1663,8 +3005,163 @@ extern "C" {
         return 0;
     }
 
+    int
+    table_remove_rows(GlueTable &table, const unsigned long start_row,
+                      const unsigned long n_rows, ExcInfo &exc)
+    {
+        try {
+            check_row_range(start_row, n_rows);
+            casacore::Vector<glue_rownr_t> rows(n_rows);
+
+            for (unsigned long i = 0; i < n_rows; i++)
+                rows[i] = start_row + i;
+
+            table.removeRow(rows);
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    int
+    table_flush(GlueTable &table, ExcInfo &exc)
+    {
+        try {
+            if (table.isWritable())
+                table.flush();
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
     // Rows
 
+    // Cached column handles
+
+    GlueColumn *
+    column_alloc(GlueTable &table, const StringBridge &col_name,
+                 GlueDataType *data_type, ExcInfo &exc)
+    {
+        try {
+            GlueColumn *col = new GlueColumn(table, bridge_string(col_name));
+            *data_type = col->columnDesc().trueDataType();
+            return col;
+        } catch (...) {
+            handle_exception(exc);
+            return NULL;
+        }
+    }
+
+    int
+    column_free(GlueColumn *col, ExcInfo &exc)
+    {
+        try {
+            delete col;
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
+    int
+    column_put_cell(GlueColumn &col, const unsigned long row_number,
+                    const GlueDataType data_type, const unsigned long n_dims,
+                    const unsigned long *dims, void *data, ExcInfo &exc)
+    {
+        try {
+            check_row_range(row_number, 1);
+            // The typed column classes do these checks for us, but the
+            // untyped BaseColumn interface does not.
+            if (!col.isWritable())
+                throw std::runtime_error("cannot write to a read-only column");
+
+            if (row_number >= col.nrow())
+                throw std::runtime_error("row number out of range");
+
+            if (data_type != col.columnDesc().trueDataType())
+                throw std::runtime_error("data type does not match the column");
+
+            switch (data_type) {
+
+#define SCALAR_CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                col.put_raw(row_number, (CPPTYPE *) data); \
+                break; \
+            }
+
+#define VECTOR_CASE(DTYPE, CPPTYPE) \
+            case casacore::DTYPE: { \
+                casacore::IPosition shape(n_dims); \
+                for (casacore::uInt i = 0; i < n_dims; i++) \
+                    shape[i] = dims[n_dims - 1 - i]; \
+                casacore::Array<CPPTYPE> array(shape, (CPPTYPE *) data, casacore::SHARE); \
+                col.put_raw(row_number, &array); \
+                break; \
+            }
+
+            SCALAR_CASE(TpBool, casacore::Bool)
+            SCALAR_CASE(TpChar, casacore::Char)
+            SCALAR_CASE(TpUChar, casacore::uChar)
+            SCALAR_CASE(TpShort, casacore::Short)
+            SCALAR_CASE(TpUShort, casacore::uShort)
+            SCALAR_CASE(TpInt, casacore::Int)
+            SCALAR_CASE(TpUInt, casacore::uInt)
+            SCALAR_CASE(TpInt64, casacore::Int64)
+            SCALAR_CASE(TpFloat, float)
+            SCALAR_CASE(TpDouble, double)
+            SCALAR_CASE(TpComplex, casacore::Complex)
+            SCALAR_CASE(TpDComplex, casacore::DComplex)
+
+            VECTOR_CASE(TpArrayBool, casacore::Bool)
+            VECTOR_CASE(TpArrayChar, casacore::Char)
+            VECTOR_CASE(TpArrayUChar, casacore::uChar)
+            VECTOR_CASE(TpArrayShort, casacore::Short)
+            VECTOR_CASE(TpArrayUShort, casacore::uShort)
+            VECTOR_CASE(TpArrayInt, casacore::Int)
+            VECTOR_CASE(TpArrayUInt, casacore::uInt)
+            VECTOR_CASE(TpArrayInt64, casacore::Int64)
+            VECTOR_CASE(TpArrayFloat, float)
+            VECTOR_CASE(TpArrayDouble, double)
+            VECTOR_CASE(TpArrayComplex, casacore::Complex)
+            VECTOR_CASE(TpArrayDComplex, casacore::DComplex)
+
+#undef SCALAR_CASE
+#undef VECTOR_CASE
+
+            case casacore::TpString: {
+                casacore::String value = bridge_string(*((StringBridge *) data));
+                col.put_raw(row_number, &value);
+                break;
+            }
+
+            case casacore::TpArrayString: {
+                casacore::IPosition shape(n_dims);
+                for (casacore::uInt i = 0; i < n_dims; i++)
+                    shape[i] = dims[n_dims - 1 - i];
+                casacore::Array<casacore::String> array =
+                    bridge_string_array((StringBridge *) data, shape);
+                col.put_raw(row_number, &array);
+                break;
+            }
+
+            default:
+                throw std::runtime_error("unhandled cell data type");
+            }
+        } catch (...) {
+            handle_exception(exc);
+            return 1;
+        }
+
+        return 0;
+    }
+
     GlueTableRow *
     table_row_alloc(const GlueTable &table, const unsigned char is_read_only, ExcInfo &exc)
     {
@@ -1695,6 +3192,7 @@ extern "C" {
     table_row_read(GlueTableRow &row, const unsigned long row_number, ExcInfo &exc)
     {
         try {
+            check_row_range(row_number, 1);
             row.get(row_number);
             return 0;
         } catch (...) {
@@ -1710,6 +3208,7 @@ extern "C" {
         casacore::TableRow &dest_row = (casacore::TableRow &) wrap_dest_row;
 
         try {
+            check_row_range(dest_row_number, 1);
             dest_row.put(dest_row_number, src_row.record(), src_row.getDefined());
             return 0;
         } catch (...) {
@@ -1791,6 +3290,7 @@ extern "C" {
         casacore::TableRow &row = (casacore::TableRow &) wrap_row;
 
         try {
+            check_row_range(dest_row_number, 1);
             row.put(dest_row_number);
         } catch (...) {
             handle_exception(exc);
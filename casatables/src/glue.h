@@ -87,6 +87,7 @@ typedef struct GlueTable GlueTable;
 typedef struct GlueTableRow GlueTableRow;
 typedef struct GlueTableDesc GlueTableDesc;
 typedef struct GlueTableRecord GlueTableRecord; 
+typedef struct GlueColumn GlueColumn;
 
 #endif
 
@@ -127,6 +128,7 @@ typedef enum TableOpenMode
     TOM_OPEN_READONLY = 1,
     TOM_OPEN_RW = 2,
     TOM_CREATE = 3,
+    TOM_OPEN_READONLY_NOLOCK = 4,
 } TableOpenMode;
 
 typedef enum TableCreateMode
@@ -139,6 +141,15 @@ typedef enum TableCreateMode
     TCM_SCRATCH = 3,
 } TableCreateMode;
 
+// copied from tables/Tables/Table.h:
+typedef enum TableEndianFormat
+{
+    TEF_BIG_ENDIAN = 1,
+    TEF_LITTLE_ENDIAN = 2,
+    TEF_LOCAL_ENDIAN = 3,
+    TEF_AIPSRC_ENDIAN = 4,
+} TableEndianFormat;
+
 /**Different modes for creating a CASA table description.*/
 typedef enum TableDescCreateMode
 {
@@ -170,6 +181,14 @@ typedef enum TableDescCreateMode
 
 extern "C"
 {
+    void casacore_version(unsigned int *major, unsigned int *minor, unsigned int *patch);
+    const char *casacore_runtime_version();
+    unsigned long casacore_max_rows();
+    int casacore_set_rc(const unsigned long n_settings, const StringBridge *keywords,
+                        const StringBridge *values, ExcInfo &exc);
+    int data_manager_is_available(const StringBridge &type, ExcInfo &exc);
+    long host_memory_free_kib();
+
     int data_type_get_element_size(const GlueDataType ty);
 
     // Table Records
@@ -265,9 +284,31 @@ extern "C"
         const StringBridge &col_name,
         const unsigned long n_dims,
         ExcInfo &exc);
+    int tabledesc_set_data_manager(
+        GlueTableDesc &table_desc,
+        const StringBridge &col_name,
+        const StringBridge &dm_type,
+        const StringBridge &dm_group,
+        ExcInfo &exc);
+    int tabledesc_define_hypercolumn(
+        GlueTableDesc &table_desc,
+        const StringBridge &name,
+        const unsigned long n_dims,
+        const unsigned long n_data_cols,
+        const StringBridge *data_cols,
+        const unsigned long n_coord_cols,
+        const StringBridge *coord_cols,
+        const unsigned long n_id_cols,
+        const StringBridge *id_cols,
+        ExcInfo &exc);
     const GlueTableRecord * tabledesc_get_keywords(GlueTableDesc &table_desc, ExcInfo &exc);
     const GlueTableRecord * tabledesc_get_column_keywords(
         GlueTableDesc &table_desc, const StringBridge &col_name, ExcInfo &exc);
+    int tabledesc_get_column_names(const GlueTableDesc &table_desc, StringBridgeCallback callback,
+                                   void *ctxt, ExcInfo &exc);
+    int tabledesc_get_column_info(const GlueTableDesc &table_desc, const StringBridge &col_name,
+                                  GlueDataType *data_type, int *is_scalar, int *is_fixed_shape,
+                                  int *n_dim, unsigned long dims[8], ExcInfo &exc);
     int tabledesc_put_keyword(
         GlueTableDesc &table_desc,
         const StringBridge &kw_name,
@@ -291,14 +332,24 @@ extern "C"
     // Table
 
     GlueTable *table_create(const StringBridge &path, GlueTableDesc &table_desc,
-                            unsigned long n_rows, const TableCreateMode mode, ExcInfo &exc);
+                            unsigned long n_rows, const TableCreateMode mode,
+                            const TableEndianFormat endian_format,
+                            const GlueTableRecord *dminfo, ExcInfo &exc);
     GlueTable *table_alloc_and_open(const StringBridge &path, const TableOpenMode mode, ExcInfo &exc);
+    int data_manager_register_masked(const StringBridge &type, ExcInfo &exc);
+    GlueTable *table_alloc_and_open_masked(const StringBridge &path, ExcInfo &exc);
+    int table_get_masked_columns(const GlueTable &table, StringBridgeCallback callback,
+                                 void *ctxt, ExcInfo &exc);
     void table_close_and_free(GlueTable *table, ExcInfo &exc);
     unsigned long table_n_rows(const GlueTable &table);
     unsigned long table_n_columns(const GlueTable &table);
     int table_get_file_name(const GlueTable &table, StringBridgeCallback callback, void *ctxt, ExcInfo &exc);
+    int table_get_kind(const GlueTable &table);
+    int table_get_part_names(const GlueTable &table, const int recursive,
+                             StringBridgeCallback callback, void *ctxt, ExcInfo &exc);
     int table_get_column_names(const GlueTable &table, StringBridgeCallback callback,
                                void *ctxt, ExcInfo &exc);
+    GlueTableDesc *table_get_table_desc(const GlueTable &table, ExcInfo &exc);
     unsigned long table_n_keywords(const GlueTable &table);
     int table_get_keyword_info(const GlueTable &table, KeywordInfoCallback callback,
                                void *ctxt, ExcInfo &exc);
@@ -325,12 +376,26 @@ extern "C"
         const unsigned long n_dims, const unsigned long *dims, void *data,
         ExcInfo &exc);
     int table_copy_rows(const GlueTable &source, GlueTable &dest, ExcInfo &exc);
-    int table_deep_copy_no_rows(const GlueTable &table, const StringBridge &dest_path, ExcInfo &exc);
+    int table_deep_copy(const GlueTable &table, const StringBridge &dest_path,
+                        const TableEndianFormat endian_format, const unsigned char no_rows,
+                        ExcInfo &exc);
+    int table_endian_format(const GlueTable &table, TableEndianFormat *endian_format, ExcInfo &exc);
+    int table_get_mappable_column_layout(GlueTable &table, const StringBridge &col_name,
+                                         StringBridgeCallback callback, void *ctxt,
+                                         GlueDataType *data_type, int *n_dim,
+                                         unsigned long dims[8], ExcInfo &exc);
+    int table_get_column_tile_shape(const GlueTable &table, const StringBridge &col_name,
+                                    int *n_dim, unsigned long dims[8], ExcInfo &exc);
+    int table_get_column_data_manager(const GlueTable &table, const StringBridge &col_name,
+                                      StringBridgeCallback callback, void *ctxt,
+                                      unsigned int *seqnr, ExcInfo &exc);
     int table_get_column_info(const GlueTable &table, const StringBridge &col_name,
                               unsigned long *n_rows, GlueDataType *data_type,
                               int *is_scalar, int *is_fixed_shape, int *n_dim,
                               unsigned long dims[8], ExcInfo &exc);
     int table_remove_column(GlueTable &table, const StringBridge &col_name, ExcInfo &exc);
+    int table_rename_column(GlueTable &table, const StringBridge &old_name,
+                            const StringBridge &new_name, ExcInfo &exc);
     int table_add_scalar_column(GlueTable &table, GlueDataType data_type, const StringBridge &col_name,
                                 const StringBridge &comment, bool direct, bool undefined, ExcInfo &exc);
     int table_add_array_column(GlueTable &table, GlueDataType data_type, const StringBridge &col_name,
@@ -338,6 +403,41 @@ extern "C"
     int table_add_fixed_array_column(GlueTable &table, GlueDataType data_type, const StringBridge &col_name,
                                      const StringBridge &comment, const unsigned long n_dims,
                                      const unsigned long *dims, bool direct, bool undefined, ExcInfo &exc);
+    int table_add_tiled_array_column(GlueTable &table, GlueDataType data_type, const StringBridge &col_name,
+                                     const StringBridge &dm_name, const unsigned long n_dims,
+                                     const unsigned long *dims, bool fixed_shape,
+                                     const unsigned long *tile_dims, ExcInfo &exc);
+    int table_get_column_range(const GlueTable &table, const StringBridge &col_name,
+                               const unsigned long start_row, const unsigned long n_rows,
+                               void *data, ExcInfo &exc);
+    int table_get_column_cells(const GlueTable &table, const StringBridge &col_name,
+                               const unsigned long *row_numbers, const unsigned long n_rows,
+                               void *data, ExcInfo &exc);
+    GlueTable *table_snapshot(const GlueTable &table, ExcInfo &exc);
+    GlueTable *table_select_rows(const GlueTable &table, const unsigned long *row_numbers,
+                                 const unsigned long n_rows, const StringBridge *col_names,
+                                 const unsigned long n_cols, ExcInfo &exc);
+    GlueTable *table_query(const GlueTable &table, const StringBridge &command, ExcInfo &exc);
+    int table_get_row_numbers(const GlueTable &table, unsigned long *row_numbers, ExcInfo &exc);
+    int table_put_column_range(GlueTable &table, const StringBridge &col_name,
+                               const unsigned long start_row, const unsigned long n_rows,
+                               const void *data, ExcInfo &exc);
+    int table_get_column_range_shaped(const GlueTable &table, const StringBridge &col_name,
+                                      const unsigned long start_row, const unsigned long n_rows,
+                                      const unsigned long n_dims, const unsigned long *cell_dims,
+                                      void *data, ExcInfo &exc);
+    int table_put_column_range_shaped(GlueTable &table, const StringBridge &col_name,
+                                      const unsigned long start_row, const unsigned long n_rows,
+                                      const unsigned long n_dims, const unsigned long *cell_dims,
+                                      const void *data, ExcInfo &exc);
+    int table_get_cell_slice(const GlueTable &table, const StringBridge &col_name,
+                             const unsigned long row_number, const unsigned long n_dims,
+                             const unsigned long *start, const unsigned long *shape,
+                             void *data, ExcInfo &exc);
+    int table_put_cell_slice(GlueTable &table, const StringBridge &col_name,
+                             const unsigned long row_number, const unsigned long n_dims,
+                             const unsigned long *start, const unsigned long *shape,
+                             const void *data, ExcInfo &exc);
     int table_get_scalar_column_data(const GlueTable &table, const StringBridge &col_name,
                                      void *data, ExcInfo &exc);
     int table_get_scalar_column_data_string(const GlueTable &table, const StringBridge &col_name,
@@ -359,6 +459,18 @@ extern "C"
                        const unsigned long n_dims, const unsigned long *dims,
                        void *data, ExcInfo &exc);
     int table_add_rows(GlueTable &table, const unsigned long n_rows, ExcInfo &exc);
+    int table_remove_rows(GlueTable &table, const unsigned long start_row,
+                          const unsigned long n_rows, ExcInfo &exc);
+    int table_flush(GlueTable &table, ExcInfo &exc);
+
+    // Cached column handles
+
+    GlueColumn *column_alloc(GlueTable &table, const StringBridge &col_name,
+                             GlueDataType *data_type, ExcInfo &exc);
+    int column_free(GlueColumn *col, ExcInfo &exc);
+    int column_put_cell(GlueColumn &col, const unsigned long row_number,
+                        const GlueDataType data_type, const unsigned long n_dims,
+                        const unsigned long *dims, void *data, ExcInfo &exc);
 
     GlueTableRow *table_row_alloc(const GlueTable &table, const unsigned char is_read_only, ExcInfo &exc);
     int table_row_free(GlueTableRow *row, ExcInfo &exc);
@@ -90,6 +90,11 @@ pub struct GlueTableRecord {
 }
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
+pub struct GlueColumn {
+    _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
 pub struct StringBridge {
     pub data: *const ::std::os::raw::c_void,
     pub n_bytes: ::std::os::raw::c_ulong,
@@ -179,6 +184,7 @@ pub enum TableOpenMode {
     TOM_OPEN_READONLY = 1,
     TOM_OPEN_RW = 2,
     TOM_CREATE = 3,
+    TOM_OPEN_READONLY_NOLOCK = 4,
 }
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
@@ -188,6 +194,14 @@ pub enum TableCreateMode {
     TCM_SCRATCH = 3,
 }
 #[repr(u32)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum TableEndianFormat {
+    TEF_BIG_ENDIAN = 1,
+    TEF_LITTLE_ENDIAN = 2,
+    TEF_LOCAL_ENDIAN = 3,
+    TEF_AIPSRC_ENDIAN = 4,
+}
+#[repr(u32)]
 #[doc = "Different modes for creating a CASA table description."]
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub enum TableDescCreateMode {
@@ -200,6 +214,36 @@ pub enum TableDescCreateMode {
 }
 #[doc = "Different modes for creating a CASA table description."]
 pub use self::TableDescCreateMode as TableDescOption;
+extern "C" {
+    pub fn casacore_version(
+        major: *mut ::std::os::raw::c_uint,
+        minor: *mut ::std::os::raw::c_uint,
+        patch: *mut ::std::os::raw::c_uint,
+    );
+}
+extern "C" {
+    pub fn casacore_runtime_version() -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn casacore_max_rows() -> ::std::os::raw::c_ulong;
+}
+extern "C" {
+    pub fn casacore_set_rc(
+        n_settings: ::std::os::raw::c_ulong,
+        keywords: *const StringBridge,
+        values: *const StringBridge,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn data_manager_is_available(
+        type_: *const StringBridge,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn host_memory_free_kib() -> ::std::os::raw::c_long;
+}
 extern "C" {
     pub fn data_type_get_element_size(ty: GlueDataType) -> ::std::os::raw::c_int;
 }
@@ -336,6 +380,29 @@ extern "C" {
         exc: *mut ExcInfo,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn tabledesc_set_data_manager(
+        table_desc: *mut GlueTableDesc,
+        col_name: *const StringBridge,
+        dm_type: *const StringBridge,
+        dm_group: *const StringBridge,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn tabledesc_define_hypercolumn(
+        table_desc: *mut GlueTableDesc,
+        name: *const StringBridge,
+        n_dims: ::std::os::raw::c_ulong,
+        n_data_cols: ::std::os::raw::c_ulong,
+        data_cols: *const StringBridge,
+        n_coord_cols: ::std::os::raw::c_ulong,
+        coord_cols: *const StringBridge,
+        n_id_cols: ::std::os::raw::c_ulong,
+        id_cols: *const StringBridge,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn tabledesc_get_keywords(
         table_desc: *mut GlueTableDesc,
@@ -349,6 +416,26 @@ extern "C" {
         exc: *mut ExcInfo,
     ) -> *const GlueTableRecord;
 }
+extern "C" {
+    pub fn tabledesc_get_column_names(
+        table_desc: *const GlueTableDesc,
+        callback: StringBridgeCallback,
+        ctxt: *mut ::std::os::raw::c_void,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn tabledesc_get_column_info(
+        table_desc: *const GlueTableDesc,
+        col_name: *const StringBridge,
+        data_type: *mut GlueDataType,
+        is_scalar: *mut ::std::os::raw::c_int,
+        is_fixed_shape: *mut ::std::os::raw::c_int,
+        n_dim: *mut ::std::os::raw::c_int,
+        dims: *mut ::std::os::raw::c_ulong,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn tabledesc_put_keyword(
         table_desc: *mut GlueTableDesc,
@@ -378,6 +465,8 @@ extern "C" {
         table_desc: *mut GlueTableDesc,
         n_rows: ::std::os::raw::c_ulong,
         mode: TableCreateMode,
+        endian_format: TableEndianFormat,
+        dminfo: *const GlueTableRecord,
         exc: *mut ExcInfo,
     ) -> *mut GlueTable;
 }
@@ -388,6 +477,26 @@ extern "C" {
         exc: *mut ExcInfo,
     ) -> *mut GlueTable;
 }
+extern "C" {
+    pub fn data_manager_register_masked(
+        type_: *const StringBridge,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_alloc_and_open_masked(
+        path: *const StringBridge,
+        exc: *mut ExcInfo,
+    ) -> *mut GlueTable;
+}
+extern "C" {
+    pub fn table_get_masked_columns(
+        table: *const GlueTable,
+        callback: StringBridgeCallback,
+        ctxt: *mut ::std::os::raw::c_void,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn table_close_and_free(table: *mut GlueTable, exc: *mut ExcInfo);
 }
@@ -405,6 +514,18 @@ extern "C" {
         exc: *mut ExcInfo,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn table_get_kind(table: *const GlueTable) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_get_part_names(
+        table: *const GlueTable,
+        recursive: ::std::os::raw::c_int,
+        callback: StringBridgeCallback,
+        ctxt: *mut ::std::os::raw::c_void,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn table_get_column_names(
         table: *const GlueTable,
@@ -413,6 +534,9 @@ extern "C" {
         exc: *mut ExcInfo,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn table_get_table_desc(table: *const GlueTable, exc: *mut ExcInfo) -> *mut GlueTableDesc;
+}
 extern "C" {
     pub fn table_n_keywords(table: *const GlueTable) -> ::std::os::raw::c_ulong;
 }
@@ -474,9 +598,49 @@ extern "C" {
     ) -> ::std::os::raw::c_int;
 }
 extern "C" {
-    pub fn table_deep_copy_no_rows(
+    pub fn table_deep_copy(
         table: *const GlueTable,
         dest_path: *const StringBridge,
+        endian_format: TableEndianFormat,
+        no_rows: ::std::os::raw::c_uchar,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_endian_format(
+        table: *const GlueTable,
+        endian_format: *mut TableEndianFormat,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_get_mappable_column_layout(
+        table: *mut GlueTable,
+        col_name: *const StringBridge,
+        callback: StringBridgeCallback,
+        ctxt: *mut ::std::os::raw::c_void,
+        data_type: *mut GlueDataType,
+        n_dim: *mut ::std::os::raw::c_int,
+        dims: *mut ::std::os::raw::c_ulong,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_get_column_tile_shape(
+        table: *const GlueTable,
+        col_name: *const StringBridge,
+        n_dim: *mut ::std::os::raw::c_int,
+        dims: *mut ::std::os::raw::c_ulong,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_get_column_data_manager(
+        table: *const GlueTable,
+        col_name: *const StringBridge,
+        callback: StringBridgeCallback,
+        ctxt: *mut ::std::os::raw::c_void,
+        seqnr: *mut ::std::os::raw::c_uint,
         exc: *mut ExcInfo,
     ) -> ::std::os::raw::c_int;
 }
@@ -500,6 +664,14 @@ extern "C" {
         exc: *mut ExcInfo,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn table_rename_column(
+        table: *mut GlueTable,
+        old_name: *const StringBridge,
+        new_name: *const StringBridge,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn table_add_scalar_column(
         table: *mut GlueTable,
@@ -535,6 +707,124 @@ extern "C" {
         exc: *mut ExcInfo,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn table_add_tiled_array_column(
+        table: *mut GlueTable,
+        data_type: GlueDataType,
+        col_name: *const StringBridge,
+        dm_name: *const StringBridge,
+        n_dims: ::std::os::raw::c_ulong,
+        dims: *const ::std::os::raw::c_ulong,
+        fixed_shape: bool,
+        tile_dims: *const ::std::os::raw::c_ulong,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_get_column_range(
+        table: *const GlueTable,
+        col_name: *const StringBridge,
+        start_row: ::std::os::raw::c_ulong,
+        n_rows: ::std::os::raw::c_ulong,
+        data: *mut ::std::os::raw::c_void,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_get_column_cells(
+        table: *const GlueTable,
+        col_name: *const StringBridge,
+        row_numbers: *const ::std::os::raw::c_ulong,
+        n_rows: ::std::os::raw::c_ulong,
+        data: *mut ::std::os::raw::c_void,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_snapshot(table: *const GlueTable, exc: *mut ExcInfo) -> *mut GlueTable;
+}
+extern "C" {
+    pub fn table_select_rows(
+        table: *const GlueTable,
+        row_numbers: *const ::std::os::raw::c_ulong,
+        n_rows: ::std::os::raw::c_ulong,
+        col_names: *const StringBridge,
+        n_cols: ::std::os::raw::c_ulong,
+        exc: *mut ExcInfo,
+    ) -> *mut GlueTable;
+}
+extern "C" {
+    pub fn table_query(
+        table: *const GlueTable,
+        command: *const StringBridge,
+        exc: *mut ExcInfo,
+    ) -> *mut GlueTable;
+}
+extern "C" {
+    pub fn table_get_row_numbers(
+        table: *const GlueTable,
+        row_numbers: *mut ::std::os::raw::c_ulong,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_put_column_range(
+        table: *mut GlueTable,
+        col_name: *const StringBridge,
+        start_row: ::std::os::raw::c_ulong,
+        n_rows: ::std::os::raw::c_ulong,
+        data: *const ::std::os::raw::c_void,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_get_column_range_shaped(
+        table: *const GlueTable,
+        col_name: *const StringBridge,
+        start_row: ::std::os::raw::c_ulong,
+        n_rows: ::std::os::raw::c_ulong,
+        n_dims: ::std::os::raw::c_ulong,
+        cell_dims: *const ::std::os::raw::c_ulong,
+        data: *mut ::std::os::raw::c_void,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_put_column_range_shaped(
+        table: *mut GlueTable,
+        col_name: *const StringBridge,
+        start_row: ::std::os::raw::c_ulong,
+        n_rows: ::std::os::raw::c_ulong,
+        n_dims: ::std::os::raw::c_ulong,
+        cell_dims: *const ::std::os::raw::c_ulong,
+        data: *const ::std::os::raw::c_void,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_get_cell_slice(
+        table: *const GlueTable,
+        col_name: *const StringBridge,
+        row_number: ::std::os::raw::c_ulong,
+        n_dims: ::std::os::raw::c_ulong,
+        start: *const ::std::os::raw::c_ulong,
+        shape: *const ::std::os::raw::c_ulong,
+        data: *mut ::std::os::raw::c_void,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_put_cell_slice(
+        table: *mut GlueTable,
+        col_name: *const StringBridge,
+        row_number: ::std::os::raw::c_ulong,
+        n_dims: ::std::os::raw::c_ulong,
+        start: *const ::std::os::raw::c_ulong,
+        shape: *const ::std::os::raw::c_ulong,
+        data: *const ::std::os::raw::c_void,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn table_get_scalar_column_data(
         table: *const GlueTable,
@@ -611,6 +901,39 @@ extern "C" {
         exc: *mut ExcInfo,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn table_remove_rows(
+        table: *mut GlueTable,
+        start_row: ::std::os::raw::c_ulong,
+        n_rows: ::std::os::raw::c_ulong,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn table_flush(table: *mut GlueTable, exc: *mut ExcInfo) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn column_alloc(
+        table: *mut GlueTable,
+        col_name: *const StringBridge,
+        data_type: *mut GlueDataType,
+        exc: *mut ExcInfo,
+    ) -> *mut GlueColumn;
+}
+extern "C" {
+    pub fn column_free(col: *mut GlueColumn, exc: *mut ExcInfo) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn column_put_cell(
+        col: *mut GlueColumn,
+        row_number: ::std::os::raw::c_ulong,
+        data_type: GlueDataType,
+        n_dims: ::std::os::raw::c_ulong,
+        dims: *const ::std::os::raw::c_ulong,
+        data: *mut ::std::os::raw::c_void,
+        exc: *mut ExcInfo,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn table_row_alloc(
         table: *const GlueTable,
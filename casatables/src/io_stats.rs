@@ -0,0 +1,233 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Counting the column I/O done through a table.
+//!
+//! Working out why a pipeline is slow usually starts with finding out how it
+//! talks to casacore: how many calls it makes, on which columns, and how much
+//! data each moves. Rather than tracing system calls, call
+//! [`Table::enable_io_stats`] and read the counters back with
+//! [`Table::io_stats`]. Collection is off by default and costs nothing when
+//! disabled.
+
+use std::collections::BTreeMap;
+
+use crate::{glue, CasaDataType, Table};
+
+/// Counters for one direction of I/O on one column.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct IoCounters {
+    /// The number of calls into casacore.
+    pub n_calls: u64,
+
+    /// The number of rows read or written.
+    pub n_rows: u64,
+
+    /// The number of bytes of cell data read or written. Strings are counted
+    /// by their UTF-8 lengths.
+    pub n_bytes: u64,
+}
+
+impl IoCounters {
+    fn add(&mut self, other: &IoCounters) {
+        self.n_calls += other.n_calls;
+        self.n_rows += other.n_rows;
+        self.n_bytes += other.n_bytes;
+    }
+}
+
+/// The I/O counters of one column.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ColumnIoStats {
+    /// Counters for reads.
+    pub gets: IoCounters,
+
+    /// Counters for writes.
+    pub puts: IoCounters,
+}
+
+/// Column I/O statistics collected by a [`Table`].
+///
+/// Only cell data access is counted: the methods that get and put cells,
+/// cell slices, and ranges of rows, and the crate’s helpers built on them,
+/// such as [`crate::copy_column`] and [`crate::ms::StreamWriter`]. Keyword
+/// and metadata access is not.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IoStats {
+    /// The counters of each column that has been accessed, by name.
+    pub columns: BTreeMap<String, ColumnIoStats>,
+}
+
+impl IoStats {
+    /// Get the counters summed over all columns.
+    pub fn total(&self) -> ColumnIoStats {
+        let mut total = ColumnIoStats::default();
+
+        for col in self.columns.values() {
+            total.gets.add(&col.gets);
+            total.puts.add(&col.puts);
+        }
+
+        total
+    }
+
+    /// Get the counters of one column. Columns that have not been accessed
+    /// have all-zero counters.
+    pub fn column(&self, col_name: &str) -> ColumnIoStats {
+        self.columns.get(col_name).copied().unwrap_or_default()
+    }
+}
+
+/// The direction of a column access.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum IoDirection {
+    Get,
+    Put,
+}
+
+/// Get the number of bytes of data in a cell value about to be put.
+pub(crate) fn cell_bytes<T: CasaDataType>(value: &T, shape: &[u64]) -> u64 {
+    if T::DATA_TYPE == glue::GlueDataType::TpString {
+        T::casatables_string_pass_through_out(value).len() as u64
+    } else if T::DATA_TYPE == glue::GlueDataType::TpArrayString {
+        T::casatables_stringvec_pass_through_out(value)
+            .iter()
+            .map(|s| s.n_bytes)
+            .sum()
+    } else {
+        shape.iter().product::<u64>() * T::DATA_TYPE.element_size().max(0) as u64
+    }
+}
+
+impl Table {
+    /// Start collecting column I/O statistics.
+    ///
+    /// If collection is already enabled, the existing counters are kept.
+    pub fn enable_io_stats(&mut self) {
+        if self.io_stats.is_none() {
+            self.io_stats = Some(Box::default());
+        }
+    }
+
+    /// Stop collecting column I/O statistics, returning those collected so
+    /// far.
+    pub fn disable_io_stats(&mut self) -> Option<IoStats> {
+        self.io_stats.take().map(|s| *s)
+    }
+
+    /// Get the column I/O statistics collected so far, if collection is
+    /// enabled.
+    pub fn io_stats(&self) -> Option<&IoStats> {
+        self.io_stats.as_deref()
+    }
+
+    /// Zero the column I/O counters, if collection is enabled.
+    pub fn reset_io_stats(&mut self) {
+        if let Some(stats) = self.io_stats.as_mut() {
+            stats.columns.clear();
+        }
+    }
+
+    /// Record one call that accessed *n_rows* rows of *col_name*, moving
+    /// *n_elements* elements of type *data_type*.
+    pub(crate) fn record_io(
+        &mut self,
+        direction: IoDirection,
+        col_name: &str,
+        n_rows: u64,
+        data_type: glue::GlueDataType,
+        n_elements: u64,
+    ) {
+        let n_bytes = n_elements * data_type.element_size().max(0) as u64;
+        self.record_io_bytes(direction, col_name, n_rows, n_bytes);
+    }
+
+    /// Like [`Self::record_io`], but with a byte count computed by the
+    /// caller, as is needed for strings.
+    pub(crate) fn record_io_bytes(
+        &mut self,
+        direction: IoDirection,
+        col_name: &str,
+        n_rows: u64,
+        n_bytes: u64,
+    ) {
+        let stats = match self.io_stats.as_mut() {
+            Some(s) => s,
+            None => return,
+        };
+
+        let col = match stats.columns.get_mut(col_name) {
+            Some(c) => c,
+            None => stats.columns.entry(col_name.to_owned()).or_default(),
+        };
+
+        let counters = match direction {
+            IoDirection::Get => &mut col.gets,
+            IoDirection::Put => &mut col.puts,
+        };
+
+        counters.n_calls += 1;
+        counters.n_rows += n_rows;
+        counters.n_bytes += n_bytes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use ndarray::Array2;
+    use tempfile::tempdir;
+
+    #[test]
+    fn count_cell_io() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpDouble, "X", None, true, false)
+            .unwrap();
+        desc.add_array_column(GlueDataType::TpFloat, "A", None, Some(&[4, 2]), true, false)
+            .unwrap();
+        desc.add_scalar_column(GlueDataType::TpString, "S", None, true, false)
+            .unwrap();
+        let mut t =
+            Table::new(tmp_dir.path().join("t.tab"), desc, 3, TableCreateMode::New).unwrap();
+
+        // Nothing is counted until collection is enabled.
+        t.put_cell("X", 0, &1.0f64).unwrap();
+        assert!(t.io_stats().is_none());
+
+        t.enable_io_stats();
+        t.put_cell("X", 1, &2.0f64).unwrap();
+        t.put_cell("A", 0, &Array2::<f32>::zeros((4, 2))).unwrap();
+        t.put_cell("S", 0, &"hello".to_owned()).unwrap();
+        let _: f64 = t.get_cell("X", 1).unwrap();
+        let _: Vec<f32> = t.get_cell_as_vec("A", 0).unwrap();
+        let _: String = t.get_cell("S", 0).unwrap();
+
+        let stats = t.io_stats().unwrap();
+        assert_eq!(
+            stats.column("X"),
+            ColumnIoStats {
+                gets: IoCounters {
+                    n_calls: 1,
+                    n_rows: 1,
+                    n_bytes: 8
+                },
+                puts: IoCounters {
+                    n_calls: 1,
+                    n_rows: 1,
+                    n_bytes: 8
+                },
+            }
+        );
+        assert_eq!(stats.column("A").puts.n_bytes, 32);
+        assert_eq!(stats.column("A").gets.n_bytes, 32);
+        assert_eq!(stats.column("S").gets.n_bytes, 5);
+        assert_eq!(stats.total().puts.n_calls, 3);
+
+        t.reset_io_stats();
+        assert_eq!(t.io_stats().unwrap().total(), ColumnIoStats::default());
+        assert!(t.disable_io_stats().is_some());
+        assert!(t.io_stats().is_none());
+    }
+}
@@ -0,0 +1,467 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! A minimal JSON document model, used for the crate's JSON import and
+//! export features.
+//!
+//! The documents involved are small, so this favors simplicity over speed.
+//! Numbers are kept as their literal text so that integers of any size and
+//! floating-point values round-trip exactly.
+
+pub(crate) use rubbl_core::json::quote;
+use std::fmt::Write;
+use thiserror::Error;
+
+/// The deepest nesting of arrays and objects that [`JsonValue::parse`]
+/// accepts, so that hostile documents cannot exhaust the stack.
+const MAX_DEPTH: usize = 128;
+
+/// An error in the syntax of a JSON document.
+#[derive(Error, Debug)]
+#[error("invalid JSON at byte {offset}: {message}")]
+pub struct JsonSyntaxError {
+    /// The byte offset in the document at which the error was found.
+    pub offset: usize,
+
+    /// A description of the problem.
+    pub message: String,
+}
+
+/// A JSON value.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    /// A number, as its literal text.
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// An object, with its members in document order.
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Create a number from a value whose `Display` form is valid JSON.
+    pub fn number<T: std::fmt::Display>(x: T) -> JsonValue {
+        JsonValue::Number(x.to_string())
+    }
+
+    /// Get a member of an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            JsonValue::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// Parse a number, which must be a literal number rather than a string.
+    pub fn parse_number<T: std::str::FromStr>(&self) -> Option<T> {
+        match self {
+            JsonValue::Number(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Parse a complete document.
+    pub fn parse(text: &str) -> Result<JsonValue, JsonSyntaxError> {
+        let mut parser = Parser {
+            text: text.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+
+        if parser.pos != text.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+
+        Ok(value)
+    }
+
+    /// Serialize the value, indenting nested arrays and objects by two
+    /// spaces per level. Arrays whose items are all scalars are written on
+    /// one line.
+    pub fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    fn is_scalar(&self) -> bool {
+        !matches!(self, JsonValue::Array(_) | JsonValue::Object(_))
+    }
+
+    fn write(&self, out: &mut String, level: usize) {
+        match self {
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(b) => write!(out, "{}", b).unwrap(),
+            JsonValue::Number(text) => out.push_str(text),
+            JsonValue::String(s) => out.push_str(&quote(s)),
+
+            JsonValue::Array(items) if items.iter().all(|i| i.is_scalar()) => {
+                out.push('[');
+
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+
+                    item.write(out, level + 1);
+                }
+
+                out.push(']');
+            }
+
+            JsonValue::Array(items) => {
+                out.push('[');
+
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(if i > 0 { ",\n" } else { "\n" });
+                    indent(out, level + 1);
+                    item.write(out, level + 1);
+                }
+
+                out.push('\n');
+                indent(out, level);
+                out.push(']');
+            }
+
+            JsonValue::Object(members) if members.is_empty() => out.push_str("{}"),
+
+            JsonValue::Object(members) => {
+                out.push('{');
+
+                for (i, (key, value)) in members.iter().enumerate() {
+                    out.push_str(if i > 0 { ",\n" } else { "\n" });
+                    indent(out, level + 1);
+                    out.push_str(&quote(key));
+                    out.push_str(": ");
+                    value.write(out, level + 1);
+                }
+
+                out.push('\n');
+                indent(out, level);
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn indent(out: &mut String, level: usize) {
+    for _ in 0..level {
+        out.push_str("  ");
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> JsonSyntaxError {
+        JsonSyntaxError {
+            offset: self.pos,
+            message: message.to_owned(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.text.len() && self.text[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), JsonSyntaxError> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", c as char)))
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, JsonSyntaxError> {
+        if self.text[self.pos..].starts_with(word.as_bytes()) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("unexpected character"))
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue, JsonSyntaxError> {
+        match self.peek() {
+            None => Err(self.error("unexpected end of document")),
+            Some(b'n') => self.keyword("null", JsonValue::Null),
+            Some(b't') => self.keyword("true", JsonValue::Bool(true)),
+            Some(b'f') => self.keyword("false", JsonValue::Bool(false)),
+            Some(b'"') => Ok(JsonValue::String(self.string()?)),
+
+            Some(b'[') => self.nested(Self::array),
+            Some(b'{') => self.nested(Self::object),
+
+            Some(c) if c == b'-' || c.is_ascii_digit() => {
+                let start = self.pos;
+
+                while self.pos < self.text.len()
+                    && matches!(
+                        self.text[self.pos],
+                        b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'
+                    )
+                {
+                    self.pos += 1;
+                }
+
+                let text = std::str::from_utf8(&self.text[start..self.pos]).unwrap();
+
+                if text.parse::<f64>().is_err() {
+                    self.pos = start;
+                    return Err(self.error("invalid number"));
+                }
+
+                Ok(JsonValue::Number(text.to_owned()))
+            }
+
+            Some(_) => Err(self.error("unexpected character")),
+        }
+    }
+
+    /// Parse an array or object with *f*, keeping track of the depth.
+    fn nested(
+        &mut self,
+        f: fn(&mut Self) -> Result<JsonValue, JsonSyntaxError>,
+    ) -> Result<JsonValue, JsonSyntaxError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("arrays and objects are nested too deeply"));
+        }
+
+        self.depth += 1;
+        let value = f(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn array(&mut self) -> Result<JsonValue, JsonSyntaxError> {
+        self.pos += 1;
+        let mut items = Vec::new();
+
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, JsonSyntaxError> {
+        self.pos += 1;
+        let mut members = Vec::new();
+
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(members));
+        }
+
+        loop {
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a string key"));
+            }
+
+            let key = self.string()?;
+            self.expect(b':')?;
+            members.push((key, self.value()?));
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonSyntaxError> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+
+        loop {
+            let c = match self.text.get(self.pos) {
+                Some(c) => *c,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += 1;
+
+            match c {
+                b'"' => break,
+
+                b'\\' => {
+                    let e = match self.text.get(self.pos) {
+                        Some(e) => *e,
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    self.pos += 1;
+
+                    let decoded = match e {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(decoded.encode_utf8(&mut buf).as_bytes());
+                }
+
+                c => bytes.push(c),
+            }
+        }
+
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonSyntaxError> {
+        let digits = self
+            .text
+            .get(self.pos..self.pos + 4)
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| u32::from_str_radix(d, 16).ok())
+            .ok_or_else(|| self.error("invalid \\u escape"))?;
+        self.pos += 4;
+        Ok(digits)
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, JsonSyntaxError> {
+        let start = self.pos;
+        let mut code = self.hex4()?;
+
+        // Characters outside the Basic Multilingual Plane are written as
+        // surrogate pairs. A surrogate that is not part of a pair does not
+        // encode any character.
+        if (0xd800..0xdc00).contains(&code) && self.text[self.pos..].starts_with(b"\\u") {
+            self.pos += 2;
+            let low = self.hex4()?;
+
+            if !(0xdc00..0xe000).contains(&low) {
+                self.pos = start;
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+
+            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+        }
+
+        std::char::from_u32(code).ok_or_else(|| {
+            self.pos = start;
+            self.error("unpaired surrogate in \\u escape")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_write() {
+        let text = r#"{"a": [1, -2.5e3, true, null], "b": {"c": "x\"é😀"}, "d": []}"#;
+        let value = JsonValue::parse(text).unwrap();
+
+        assert_eq!(value.get("a").unwrap().as_array().unwrap().len(), 4);
+        assert_eq!(
+            value.get("a").unwrap().as_array().unwrap()[1].parse_number::<f64>(),
+            Some(-2500.)
+        );
+        assert_eq!(
+            value.get("b").unwrap().get("c").unwrap().as_str(),
+            Some("x\"é😀")
+        );
+
+        let again = JsonValue::parse(&value.to_pretty_string()).unwrap();
+        assert_eq!(again, value);
+
+        assert!(JsonValue::parse("[1, 2").is_err());
+        assert!(JsonValue::parse("{\"a\": 1} x").is_err());
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let ok = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert!(JsonValue::parse(&ok).is_ok());
+
+        let deep = format!("{}{}", "[".repeat(MAX_DEPTH + 1), "]".repeat(MAX_DEPTH + 1));
+        assert!(JsonValue::parse(&deep).is_err());
+
+        let deep = "{\"a\":".repeat(MAX_DEPTH + 1);
+        assert!(JsonValue::parse(&deep).is_err());
+
+        // Far deeper than the stack could take if parsing recursed without
+        // a limit.
+        assert!(JsonValue::parse(&"[".repeat(1_000_000)).is_err());
+    }
+
+    #[test]
+    fn surrogates() {
+        let value = JsonValue::parse(r#""\ud83d\ude00""#).unwrap();
+        assert_eq!(value.as_str(), Some("😀"));
+
+        for text in &[
+            r#""\ud83d""#,
+            r#""\ud83dx""#,
+            r#""\ud83d\u0041""#,
+            r#""\ud83d\ud83d""#,
+            r#""\ude00""#,
+            r#""\ude00\ud83d""#,
+        ] {
+            let e = JsonValue::parse(text).unwrap_err();
+            assert!(e.message.contains("surrogate"), "{}: {}", text, e);
+        }
+    }
+}
@@ -0,0 +1,505 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Iterating over large arrays in rectangular chunks.
+//!
+//! Image cubes and tiled visibility columns are often too large to read into
+//! memory at once, but many operations — smoothing, convolution, statistics —
+//! only need a neighborhood of each pixel. A [`Lattice`] is an
+//! N-dimensional array on disk that can be read one rectangular region at a
+//! time, and a [`ChunkIter`] walks over a lattice in chunks of a chosen
+//! shape, in the spirit of casacore’s `LatticeIterator`.
+//!
+//! Reads from disk are batched: the iterator holds a buffer of up to a
+//! configurable number of bytes and reads the largest slab around the
+//! current chunk that fits into it, so that neighboring chunks and the
+//! overlaps between them are served from memory.
+//!
+//! As elsewhere in this crate, shapes and positions are given in C order.
+//!
+//! ```rust
+//! use rubbl_casatables::{casaimages::Image, lattice::ChunkIter, TableRecord};
+//! use tempfile::tempdir;
+//!
+//! let tmp_dir = tempdir().unwrap();
+//! let path = tmp_dir.path().join("test.image");
+//! let coords = TableRecord::new().unwrap();
+//! let mut image = Image::create(&path, &[1, 1, 10, 10], &coords, "Jy/beam").unwrap();
+//!
+//! let mut n = 0;
+//!
+//! for chunk in ChunkIter::new(&mut image, &[1, 1, 4, 4]).overlap(&[0, 0, 1, 1]) {
+//!     let chunk = chunk.unwrap();
+//!     assert_eq!(chunk.core().shape(), &chunk.shape[..]);
+//!     n += 1;
+//! }
+//!
+//! assert_eq!(n, 9);
+//! ```
+
+use ndarray::{ArrayD, ArrayViewD, Axis, Slice};
+
+use crate::{
+    casaimages::{Image, ImageError},
+    CasaScalarData, Table, TableError,
+};
+
+/// The default size of the read buffer of a [`ChunkIter`], in bytes.
+pub const DEFAULT_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+/// An N-dimensional array that can be read in rectangular regions.
+pub trait Lattice<T> {
+    /// The error type returned when the lattice cannot be read.
+    type Error;
+
+    /// Get the shape of the lattice, in C order.
+    fn lattice_shape(&self) -> Vec<usize>;
+
+    /// Read a rectangular region of the lattice.
+    fn read_region(&mut self, start: &[usize], shape: &[usize]) -> Result<ArrayD<T>, Self::Error>;
+}
+
+impl Lattice<f32> for Image {
+    type Error = ImageError;
+
+    fn lattice_shape(&self) -> Vec<usize> {
+        self.shape().to_vec()
+    }
+
+    fn read_region(&mut self, start: &[usize], shape: &[usize]) -> Result<ArrayD<f32>, ImageError> {
+        self.get_chunk(start, shape)
+    }
+}
+
+/// A table column with fixed-shape cells, viewed as a single array.
+///
+/// The first axis of the array indexes the rows of the table and the rest
+/// index the cells. This is the natural way to traverse columns stored with
+/// casacore’s `TiledColumnStMan`, such as the `DATA` column of many
+/// Measurement Sets.
+#[derive(Debug)]
+pub struct ColumnLattice<'a> {
+    table: &'a mut Table,
+    col_name: String,
+    shape: Vec<usize>,
+}
+
+impl<'a> ColumnLattice<'a> {
+    /// View a column of *table* as a lattice.
+    ///
+    /// The cell shape is taken from the first row; all cells must have the
+    /// same shape.
+    pub fn new(table: &'a mut Table, col_name: &str) -> Result<Self, TableError> {
+        let n_rows = table.n_rows() as usize;
+        let mut shape = vec![n_rows];
+
+        if n_rows > 0 {
+            shape.extend(table.get_cell_shape(col_name, 0)?);
+        }
+
+        Ok(ColumnLattice {
+            table,
+            col_name: col_name.to_owned(),
+            shape,
+        })
+    }
+}
+
+impl<'a, T: CasaScalarData + Copy + Default> Lattice<T> for ColumnLattice<'a> {
+    type Error = TableError;
+
+    fn lattice_shape(&self) -> Vec<usize> {
+        self.shape.clone()
+    }
+
+    fn read_region(&mut self, start: &[usize], shape: &[usize]) -> Result<ArrayD<T>, TableError> {
+        let mut result = ArrayD::default(shape);
+
+        for i in 0..shape[0] {
+            let row = (start[0] + i) as u64;
+            let cell = self
+                .table
+                .get_cell_slice(&self.col_name, row, &start[1..], &shape[1..])?;
+            result.index_axis_mut(Axis(0), i).assign(&cell);
+        }
+
+        Ok(result)
+    }
+}
+
+/// One chunk produced by a [`ChunkIter`].
+#[derive(Clone, Debug)]
+pub struct Chunk<T> {
+    /// The position of the chunk in the lattice.
+    pub start: Vec<usize>,
+
+    /// The shape of the chunk, which is the requested chunk shape except at
+    /// the upper edges of the lattice.
+    pub shape: Vec<usize>,
+
+    /// The position of [`Self::data`] in the lattice. This differs from
+    /// [`Self::start`] if overlap was requested.
+    pub data_start: Vec<usize>,
+
+    /// The chunk’s data, extended by the requested overlap on each side
+    /// where the lattice allows it.
+    pub data: ArrayD<T>,
+}
+
+impl<T> Chunk<T> {
+    /// Get a view of the chunk’s data without the overlap.
+    pub fn core(&self) -> ArrayViewD<'_, T> {
+        self.data.slice_each_axis(|ax| {
+            let i = ax.axis.index();
+            let offset = self.start[i] - self.data_start[i];
+            Slice::from(offset..offset + self.shape[i])
+        })
+    }
+}
+
+/// An iterator over a [`Lattice`] in rectangular chunks.
+///
+/// Chunks are visited in C order: the chunk position along the last axis
+/// varies fastest. The chunks tile the lattice without overlapping; if
+/// overlap is requested with [`Self::overlap`], the data of each chunk are
+/// extended by that many pixels on both sides of each axis, clipped to the
+/// lattice.
+#[derive(Debug)]
+pub struct ChunkIter<'a, T, L: Lattice<T>> {
+    lattice: &'a mut L,
+    shape: Vec<usize>,
+    chunk_shape: Vec<usize>,
+    overlap: Vec<usize>,
+    cache_bytes: usize,
+    n_chunks: Vec<usize>,
+    next_index: Option<Vec<usize>>,
+    buffer: Option<Buffer<T>>,
+}
+
+/// A region of the lattice held in memory.
+#[derive(Debug)]
+struct Buffer<T> {
+    /// The leading chunk indices for which the buffer is valid.
+    key: Vec<usize>,
+    start: Vec<usize>,
+    data: ArrayD<T>,
+}
+
+impl<'a, T: Clone, L: Lattice<T>> ChunkIter<'a, T, L> {
+    /// Create an iterator over *lattice* with chunks of the given shape.
+    ///
+    /// Chunk dimensions of zero, and dimensions larger than the lattice, are
+    /// replaced with the size of the lattice along that axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *chunk_shape* does not have one entry per lattice axis.
+    pub fn new(lattice: &'a mut L, chunk_shape: &[usize]) -> Self {
+        let shape = lattice.lattice_shape();
+        assert_eq!(
+            chunk_shape.len(),
+            shape.len(),
+            "chunk shape must have one entry per lattice axis"
+        );
+
+        let chunk_shape: Vec<usize> = chunk_shape
+            .iter()
+            .zip(&shape)
+            .map(|(c, s)| if *c == 0 { *s } else { (*c).min(*s) })
+            .collect();
+
+        let n_chunks: Vec<usize> = chunk_shape
+            .iter()
+            .zip(&shape)
+            .map(|(c, s)| if *s == 0 { 0 } else { s.div_ceil(*c) })
+            .collect();
+
+        let next_index = if n_chunks.contains(&0) {
+            None
+        } else {
+            Some(vec![0; shape.len()])
+        };
+
+        ChunkIter {
+            lattice,
+            overlap: vec![0; shape.len()],
+            shape,
+            chunk_shape,
+            cache_bytes: DEFAULT_CACHE_BYTES,
+            n_chunks,
+            next_index,
+            buffer: None,
+        }
+    }
+
+    /// Set the number of pixels of overlap between neighboring chunks, on
+    /// each side of each axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *overlap* does not have one entry per lattice axis.
+    pub fn overlap(mut self, overlap: &[usize]) -> Self {
+        assert_eq!(
+            overlap.len(),
+            self.shape.len(),
+            "overlap must have one entry per lattice axis"
+        );
+        self.overlap = overlap.to_vec();
+        self
+    }
+
+    /// Set the maximum size of the in-memory read buffer, in bytes.
+    ///
+    /// The default is [`DEFAULT_CACHE_BYTES`]. The buffer is never smaller
+    /// than a single chunk, including its overlap.
+    pub fn cache_bytes(mut self, n: usize) -> Self {
+        self.cache_bytes = n;
+        self
+    }
+
+    /// Get the number of chunks along each axis.
+    pub fn n_chunks(&self) -> &[usize] {
+        &self.n_chunks[..]
+    }
+
+    /// Get the extent of the data of the chunk at *start* with the given
+    /// shape, including overlap.
+    fn data_extent(&self, start: &[usize], shape: &[usize]) -> (Vec<usize>, Vec<usize>) {
+        let mut data_start = Vec::with_capacity(start.len());
+        let mut data_shape = Vec::with_capacity(start.len());
+
+        for i in 0..start.len() {
+            let lo = start[i].saturating_sub(self.overlap[i]);
+            let hi = (start[i] + shape[i] + self.overlap[i]).min(self.shape[i]);
+            data_start.push(lo);
+            data_shape.push(hi - lo);
+        }
+
+        (data_start, data_shape)
+    }
+
+    /// Choose how many leading axes the buffer must follow the current chunk
+    /// along. The buffer spans the whole lattice along the remaining axes,
+    /// so the fewer leading axes, the fewer reads.
+    fn buffer_depth(&self) -> usize {
+        let elem_bytes = std::mem::size_of::<T>().max(1);
+        let n = self.shape.len();
+
+        // Size the buffer for the largest possible chunk so that the choice
+        // is the same for every chunk.
+        let data_shape: Vec<usize> = (0..n)
+            .map(|i| (self.chunk_shape[i] + 2 * self.overlap[i]).min(self.shape[i]))
+            .collect();
+
+        for depth in 0..n {
+            let n_elems: usize = data_shape[..depth]
+                .iter()
+                .chain(&self.shape[depth..])
+                .product();
+
+            if n_elems.saturating_mul(elem_bytes) <= self.cache_bytes {
+                return depth;
+            }
+        }
+
+        n
+    }
+
+    fn advance(&mut self, index: &[usize]) {
+        let mut next = index.to_vec();
+
+        for i in (0..next.len()).rev() {
+            next[i] += 1;
+
+            if next[i] < self.n_chunks[i] {
+                self.next_index = Some(next);
+                return;
+            }
+
+            next[i] = 0;
+        }
+
+        self.next_index = None;
+    }
+}
+
+impl<'a, T: Clone, L: Lattice<T>> Iterator for ChunkIter<'a, T, L> {
+    type Item = Result<Chunk<T>, L::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.next_index.take()?;
+        self.advance(&index);
+
+        let start: Vec<usize> = index
+            .iter()
+            .zip(&self.chunk_shape)
+            .map(|(i, c)| i * c)
+            .collect();
+        let shape: Vec<usize> = start
+            .iter()
+            .zip(&self.chunk_shape)
+            .zip(&self.shape)
+            .map(|((s, c), n)| (*c).min(n - s))
+            .collect();
+        let (data_start, data_shape) = self.data_extent(&start, &shape);
+
+        let depth = self.buffer_depth();
+        let key = index[..depth].to_vec();
+
+        if self.buffer.as_ref().map(|b| &b.key) != Some(&key) {
+            // The buffer follows the chunk along the leading axes, and covers
+            // the full lattice along the rest.
+            let buf_start: Vec<usize> = (0..self.shape.len())
+                .map(|i| if i < depth { data_start[i] } else { 0 })
+                .collect();
+            let buf_shape: Vec<usize> = (0..self.shape.len())
+                .map(|i| {
+                    if i < depth {
+                        data_shape[i]
+                    } else {
+                        self.shape[i]
+                    }
+                })
+                .collect();
+
+            self.buffer = None;
+
+            let data = match self.lattice.read_region(&buf_start, &buf_shape) {
+                Ok(d) => d,
+                Err(e) => {
+                    self.next_index = None;
+                    return Some(Err(e));
+                }
+            };
+
+            self.buffer = Some(Buffer {
+                key,
+                start: buf_start,
+                data,
+            });
+        }
+
+        let buffer = self.buffer.as_ref().unwrap();
+        let data = buffer
+            .data
+            .slice_each_axis(|ax| {
+                let i = ax.axis.index();
+                let lo = data_start[i] - buffer.start[i];
+                Slice::from(lo..lo + data_shape[i])
+            })
+            .to_owned();
+
+        Some(Ok(Chunk {
+            start,
+            shape,
+            data_start,
+            data,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    struct MemLattice {
+        data: ArrayD<i32>,
+        n_reads: usize,
+    }
+
+    impl Lattice<i32> for MemLattice {
+        type Error = Infallible;
+
+        fn lattice_shape(&self) -> Vec<usize> {
+            self.data.shape().to_vec()
+        }
+
+        fn read_region(
+            &mut self,
+            start: &[usize],
+            shape: &[usize],
+        ) -> Result<ArrayD<i32>, Infallible> {
+            self.n_reads += 1;
+            Ok(self
+                .data
+                .slice_each_axis(|ax| {
+                    let i = ax.axis.index();
+                    Slice::from(start[i]..start[i] + shape[i])
+                })
+                .to_owned())
+        }
+    }
+
+    fn mem_lattice() -> MemLattice {
+        MemLattice {
+            data: ArrayD::from_shape_fn(vec![5, 7], |ix| (ix[0] * 10 + ix[1]) as i32),
+            n_reads: 0,
+        }
+    }
+
+    #[test]
+    fn chunks_cover_lattice() {
+        for cache_bytes in [0, 4 * 7 * 4, DEFAULT_CACHE_BYTES] {
+            let mut lat = mem_lattice();
+            let mut total = ArrayD::zeros(vec![5, 7]);
+            let mut n = 0;
+
+            for chunk in ChunkIter::new(&mut lat, &[2, 3])
+                .overlap(&[1, 1])
+                .cache_bytes(cache_bytes)
+            {
+                let chunk = chunk.unwrap();
+                let expected = mem_lattice().read_region(&chunk.data_start, chunk.data.shape());
+                assert_eq!(chunk.data, expected.unwrap());
+
+                let mut dest = total.slice_each_axis_mut(|ax| {
+                    let i = ax.axis.index();
+                    Slice::from(chunk.start[i]..chunk.start[i] + chunk.shape[i])
+                });
+                dest += &chunk.core();
+                n += 1;
+            }
+
+            assert_eq!(n, 9);
+            assert_eq!(total, lat.data);
+
+            let expected_reads = match cache_bytes {
+                0 => 9,
+                DEFAULT_CACHE_BYTES => 1,
+                _ => 3,
+            };
+            assert_eq!(lat.n_reads, expected_reads);
+        }
+    }
+
+    #[test]
+    fn column_lattice() {
+        use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+        use tempfile::tempdir;
+
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_array_column(GlueDataType::TpInt, "DATA", None, Some(&[3]), true, false)
+            .unwrap();
+
+        let mut table = Table::new(&table_path, table_desc, 4, TableCreateMode::New).unwrap();
+
+        for row in 0..4 {
+            let cell = vec![row as i32 * 10, row as i32 * 10 + 1, row as i32 * 10 + 2];
+            table.put_cell("DATA", row, &cell).unwrap();
+        }
+
+        let mut lat = ColumnLattice::new(&mut table, "DATA").unwrap();
+        assert_eq!(Lattice::<i32>::lattice_shape(&lat), vec![4, 3]);
+
+        let chunks: Vec<Chunk<i32>> = ChunkIter::new(&mut lat, &[3, 2])
+            .map(|c| c.unwrap())
+            .collect();
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[3].start, vec![3, 2]);
+        assert_eq!(chunks[3].data.as_slice().unwrap(), &[32]);
+    }
+}
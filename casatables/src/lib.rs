@@ -9,9 +9,9 @@
 //! commonly used for storing radio astronomical visibility data in the
 //! [Measurement Set][MS] data model, but is not limited to that particular
 //! application — it is a generic data format for tabular scientific data. This
-//! crate provides only lower-level I/O interfaces and not higher-level
-//! abstractions for dealing with the specific semantics of Measurement Set
-//! data.
+//! crate mostly provides lower-level I/O interfaces; a few helpers that know
+//! about the specific semantics of Measurement Set data live in the [`ms`]
+//! module.
 //!
 //! [MS]: https://casa.nrao.edu/Memos/229.html
 //!
@@ -30,19 +30,138 @@
 #![deny(missing_docs)]
 
 use ndarray::Dimension;
-use rubbl_core::num::{DimFromShapeSlice, DimensionMismatchError};
+use rubbl_core::{
+    chunked::{ArrayChunkSink, ArrayChunkSource},
+    num::{DimFromShapeSlice, DimensionMismatchError},
+};
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     fmt::{self, Debug},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 use thiserror::Error;
 
 pub use rubbl_core::{Array, Complex, CowArray};
 
+mod atomic;
+pub use atomic::PendingTable;
+
+mod axis_order;
+pub use axis_order::AxisOrder;
+
+mod cell_value;
+pub use cell_value::CellValue;
+
+pub mod casaimages;
+
+mod chunk_plan;
+pub use chunk_plan::{available_memory, ChunkPlanner};
+
+mod column_copy;
+pub use column_copy::{copy_column, copy_column_rows, copy_column_with_progress, Conversion};
+
+mod compression;
+pub use compression::{CandidateCodec, CodecEstimate, CompressibilityEstimate, DYSCO_BIT_DEPTHS};
+
+mod config;
+pub use config::{configure, CasacoreConfig, ConfigureError};
+
+mod csv_import;
+pub use csv_import::{CsvColumn, CsvImportError, CsvSchema};
+
+#[cfg(feature = "polars")]
+mod dataframe;
+#[cfg(feature = "polars")]
+pub use dataframe::{PolarsViewError, MAX_EXPLODED_ELEMENTS};
+
+mod desc_diff;
+pub use desc_diff::DescDifference;
+
+mod disk_usage;
+pub use disk_usage::{ColumnUsage, DataManagerUsage, DiskUsage};
+
 #[allow(missing_docs)]
 mod glue;
 pub use glue::{GlueDataType, TableDescCreateMode};
 
+mod io_stats;
+use io_stats::IoDirection;
+pub use io_stats::{ColumnIoStats, IoCounters, IoStats};
+
+mod json;
+pub use json::JsonSyntaxError;
+
+pub mod lattice;
+
+mod manifest;
+pub use manifest::{
+    verify_manifest, write_manifest, ManifestError, ManifestReport, MANIFEST_FILE_NAME,
+};
+
+mod masked;
+pub use masked::MaskedColumn;
+
+pub mod measures_data;
+
+pub mod migrate;
+
+mod mmap;
+pub use mmap::ColumnMmap;
+
+pub mod ms;
+
+mod precision;
+pub use precision::{ElementCodec, HalfPrecision, LinearQuantizer, F16};
+
+mod prefetch;
+pub use prefetch::{PrefetchingReader, RowChunk, DEFAULT_PREFETCH_DEPTH};
+
+pub mod profiling;
+
+mod progress;
+pub use progress::{CancellationToken, Progress, ProgressSink};
+
+mod quantiles;
+pub use quantiles::{QuantileSketch, DEFAULT_SKETCH_COMPRESSION};
+
+mod quantity;
+pub use quantity::QuantityColumn;
+
+mod ragged;
+pub use ragged::RaggedArray;
+
+mod registry;
+pub use registry::{
+    open_tables, set_open_table_warning_handler, set_open_table_warning_threshold, OpenTableInfo,
+    OpenTableWarningHandler, TableOrigin, DEFAULT_OPEN_TABLE_WARNING_THRESHOLD,
+};
+
+#[cfg(feature = "remote")]
+mod remote;
+#[cfg(feature = "remote")]
+pub use remote::{open_remote_async, CachePolicy, RemoteError};
+
+mod retry;
+pub use retry::{is_transient_error, RetryPolicy};
+
+mod scalar_columns;
+pub use scalar_columns::ScalarColumns;
+
+pub mod select;
+
+mod sync_table;
+pub use sync_table::SyncTable;
+
+mod table_json;
+pub use table_json::{JsonExportOptions, TableJsonError};
+
+pub mod testing;
+
+mod virtual_column;
+pub use virtual_column::VirtualExpr;
+
 // Exceptions
 
 /// An error type used when the wrapped "casacore" C++ code raises an
@@ -69,6 +188,243 @@ impl glue::ExcInfo {
     {
         Err(self.as_error().into())
     }
+
+    /// Record a failure at the boundary between Rust and C++, so that it is
+    /// reported as [`TableError::Ffi`].
+    fn set_ffi_failure(&mut self, context: &str, message: &str) {
+        let text = format!("{}{}: {}", FFI_FAILURE_PREFIX, context, message);
+        let mut n = text.len().min(self.message.len() - 1);
+
+        while !text.is_char_boundary(n) {
+            n -= 1;
+        }
+
+        for (dest, src) in self.message.iter_mut().zip(text[..n].bytes()) {
+            *dest = src as std::os::raw::c_char;
+        }
+
+        self.message[n] = 0;
+    }
+}
+
+/// The start of the messages of errors that occurred at the boundary between
+/// Rust and C++. The glue code uses the same prefix for C++ exceptions that
+/// it cannot identify.
+const FFI_FAILURE_PREFIX: &str = "FFI failure in ";
+
+/// If an error message describes a failure at the boundary between Rust and
+/// C++, split it into its context and message.
+fn ffi_failure(message: &str) -> Option<(String, String)> {
+    let rest = message.strip_prefix(FFI_FAILURE_PREFIX)?;
+    let sep = rest.find(": ")?;
+    Some((rest[..sep].to_owned(), rest[sep + 2..].to_owned()))
+}
+
+// Version information
+
+/// The version and build configuration of the bundled casacore library.
+///
+/// Each of the build flags is `Some(enabled)` if it is known, and `None` if
+/// it could not be determined.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CasacoreVersion {
+    /// The major version number.
+    pub major: u32,
+
+    /// The minor version number.
+    pub minor: u32,
+
+    /// The patch version number.
+    pub patch: u32,
+
+    /// Whether casacore was built with thread safety (`USE_THREADS`).
+    pub threads: Option<bool>,
+
+    /// Whether casacore was built with MPI support (`HAVE_MPI`).
+    pub mpi: Option<bool>,
+
+    /// Whether casacore was built with the ADIOS2 storage manager
+    /// (`HAVE_ADIOS2`).
+    pub adios2: Option<bool>,
+
+    /// Whether casacore was built with HDF5 support (`HAVE_HDF5`).
+    pub hdf5: Option<bool>,
+
+    /// Whether this is an external casacore installation, linked with the
+    /// `system-casacore` feature, rather than the bundled one. The build
+    /// configuration of an external installation is worked out from its
+    /// `pkg-config` flags and its `casa/config.h` header, which do not always
+    /// settle every flag above.
+    pub system: bool,
+}
+
+impl std::fmt::Display for CasacoreVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+
+        let flags = [
+            (self.threads, "threads"),
+            (self.mpi, "mpi"),
+            (self.adios2, "adios2"),
+            (self.hdf5, "hdf5"),
+        ];
+
+        let mut features: Vec<&str> = Vec::new();
+
+        if self.system {
+            features.push("system");
+        }
+
+        features.extend(flags.iter().filter(|f| f.0 == Some(true)).map(|f| f.1));
+
+        let unknown: Vec<&str> = flags
+            .iter()
+            .filter(|f| f.0.is_none())
+            .map(|f| f.1)
+            .collect();
+
+        match (features.is_empty(), unknown.is_empty()) {
+            (true, true) => Ok(()),
+            (false, true) => write!(f, " ({})", features.join(", ")),
+            (true, false) => write!(f, " (unknown: {})", unknown.join(", ")),
+            (false, false) => write!(
+                f,
+                " ({}; unknown: {})",
+                features.join(", "),
+                unknown.join(", ")
+            ),
+        }
+    }
+}
+
+/// Get the version and build configuration of the bundled casacore library.
+///
+/// The version comes from the casacore headers that the glue code was
+/// compiled against, and the build configuration from the
+/// `rubbl_casatables_impl` build script.
+///
+/// ```rust
+/// let version = rubbl_casatables::casacore_version();
+/// assert!(version.major >= 3);
+/// println!("using casacore {}", version);
+/// ```
+pub fn casacore_version() -> CasacoreVersion {
+    let (mut major, mut minor, mut patch) = (0, 0, 0);
+    unsafe { glue::casacore_version(&mut major, &mut minor, &mut patch) };
+
+    let listed = |list: &'static str, name: &str| list.split(',').any(|f| f.trim() == name);
+    let flag = |name: &str| {
+        if listed(env!("RUBBL_CASACORE_UNKNOWN_FEATURES"), name) {
+            None
+        } else {
+            Some(listed(env!("RUBBL_CASACORE_FEATURES"), name))
+        }
+    };
+
+    CasacoreVersion {
+        major,
+        minor,
+        patch,
+        threads: flag("threads"),
+        mpi: flag("mpi"),
+        adios2: flag("adios2"),
+        hdf5: flag("hdf5"),
+        system: env!("RUBBL_CASACORE_SYSTEM") == "1",
+    }
+}
+
+/// Get the largest number of rows that a table can have.
+///
+/// Row numbers are always passed to casacore as 64-bit values, but casacore
+/// releases before 3.4, including the bundled one, only use 32 bits for
+/// them internally. Operations on rows beyond this limit fail rather than
+/// being silently redirected to the wrong row.
+///
+/// ```rust
+/// let max = rubbl_casatables::casacore_max_rows();
+/// assert!(max >= u32::MAX as u64);
+/// ```
+pub fn casacore_max_rows() -> u64 {
+    unsafe { glue::casacore_max_rows() as u64 }
+}
+
+/// Check that the casacore library in use at runtime matches the headers
+/// that this crate was compiled against.
+///
+/// This can only fail when linking with an external casacore installation
+/// through the `system-casacore` feature, in which case the shared library
+/// might have been upgraded or swapped out after this crate was built.
+/// Releases that differ only in their patch version are assumed to be
+/// compatible. On success, the version information is returned.
+pub fn check_casacore_abi() -> Result<CasacoreVersion, CasacoreError> {
+    let version = casacore_version();
+    let c_str = unsafe { std::ffi::CStr::from_ptr(glue::casacore_runtime_version()) };
+    let runtime = c_str.to_string_lossy();
+    let mut pieces = runtime.split('.').map(|p| p.trim().parse::<u32>());
+
+    match (pieces.next(), pieces.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) if major == version.major && minor == version.minor => {
+            Ok(version)
+        }
+
+        _ => Err(CasacoreError(format!(
+            "casacore library version {} does not match the headers used to build rubbl ({}.{}.{})",
+            runtime, version.major, version.minor, version.patch
+        ))),
+    }
+}
+
+/// Check whether casacore can use the named data manager type.
+///
+/// Data managers that are not built into casacore, such as the
+/// `DyscoStMan` compressing storage manager, are provided by plugin libraries
+/// that casacore loads on demand. This function attempts to load the plugin
+/// if needed and reports whether that worked. Plugins are compiled against a
+/// standard casacore installation, so they can generally only be used when
+/// this crate is built with the `system-casacore` feature.
+pub fn data_manager_available(dm_type: &str) -> Result<bool, CasacoreError> {
+    let ctype = glue::StringBridge::from_rust(dm_type);
+    let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+
+    match unsafe { glue::data_manager_is_available(&ctype, &mut exc_info) } {
+        1 => Ok(true),
+        0 => Ok(false),
+        _ => exc_info.as_err(),
+    }
+}
+
+/// If a casacore error message reports that a data manager type is not
+/// registered, extract the name of the type.
+fn unregistered_data_manager(message: &str) -> Option<String> {
+    // casacore says "Data Manager class FOO is not registered"
+    const PREFIX: &str = "anager class ";
+    let start = message.find(PREFIX)? + PREFIX.len();
+    let rest = &message[start..];
+    let end = rest.find(char::is_whitespace)?;
+
+    if !rest[end..].trim_start().starts_with("is not registered") {
+        return None;
+    }
+
+    Some(rest[..end].to_owned())
+}
+
+/// If a casacore error message reports that the shared library that should
+/// provide a data manager could not be found, extract the name of the
+/// library. casacore names it after the data manager type, lowercased and
+/// cut off at any `.` or `<`.
+fn missing_data_manager_library(message: &str) -> Option<&str> {
+    // casacore says "Shared library foo not found in CASACORE_LDPATH ..."
+    const PREFIX: &str = "Shared library ";
+    let start = message.find(PREFIX)? + PREFIX.len();
+    let rest = &message[start..];
+    let end = rest.find(char::is_whitespace)?;
+
+    if !rest[end..].trim_start().starts_with("not found") {
+        return None;
+    }
+
+    Some(&rest[..end])
 }
 
 // Data types
@@ -81,6 +437,36 @@ impl glue::GlueDataType {
     pub fn element_size(&self) -> i32 {
         unsafe { glue::data_type_get_element_size(*self) as i32 }
     }
+
+    /// Return whether this is one of the `TpArrayX` types.
+    pub fn is_array(&self) -> bool {
+        self.element_type() != *self
+    }
+
+    /// Return the type of the individual elements of this data type.
+    ///
+    /// For `TpArrayX`, this is `TpX`. Other types are returned unchanged.
+    pub fn element_type(&self) -> glue::GlueDataType {
+        use glue::GlueDataType::*;
+
+        match *self {
+            TpArrayBool => TpBool,
+            TpArrayChar => TpChar,
+            TpArrayUChar => TpUChar,
+            TpArrayShort => TpShort,
+            TpArrayUShort => TpUShort,
+            TpArrayInt => TpInt,
+            TpArrayUInt => TpUInt,
+            TpArrayInt64 => TpInt64,
+            TpArrayFloat => TpFloat,
+            TpArrayDouble => TpDouble,
+            TpArrayComplex => TpComplex,
+            TpArrayDComplex => TpDComplex,
+            TpArrayString => TpString,
+            TpArrayQuantity => TpQuantity,
+            other => other,
+        }
+    }
 }
 
 impl fmt::Display for glue::GlueDataType {
@@ -179,6 +565,13 @@ pub trait CasaDataType: Clone + PartialEq + Sized {
     #[doc(hidden)]
     fn casatables_alloc(shape: &[u64]) -> Result<Self, TableError>;
 
+    /// Get a version of this value whose buffer, as returned by
+    /// `casatables_as_buf`, is in the memory layout that the glue expects.
+    #[doc(hidden)]
+    fn casatables_standard_layout(&self) -> Cow<'_, Self> {
+        Cow::Borrowed(self)
+    }
+
     #[doc(hidden)]
     fn casatables_as_buf(&self) -> *const () {
         self as *const Self as _
@@ -226,6 +619,16 @@ impl_scalar_data_type! { f64, TpDouble, TpArrayDouble, 0. }
 impl_scalar_data_type! { Complex<f32>, TpComplex, TpArrayComplex, Complex::new(0., 0.) }
 impl_scalar_data_type! { Complex<f64>, TpDComplex, TpArrayDComplex, Complex::new(0., 0.) }
 
+// casacore's Complex and DComplex are std::complex<float> and
+// std::complex<double>, which C++ guarantees are laid out as two consecutive
+// values, real part first. num_complex's Complex is #[repr(C)] with the same
+// field order, so complex buffers are handed across the glue as-is, with no
+// per-element conversion. glue.cc checks the same facts on the C++ side.
+const _: () = assert!(std::mem::size_of::<Complex<f32>>() == 2 * std::mem::size_of::<f32>());
+const _: () = assert!(std::mem::align_of::<Complex<f32>>() == std::mem::align_of::<f32>());
+const _: () = assert!(std::mem::size_of::<Complex<f64>>() == 2 * std::mem::size_of::<f64>());
+const _: () = assert!(std::mem::align_of::<Complex<f64>>() == std::mem::align_of::<f64>());
+
 impl CasaDataType for String {
     const DATA_TYPE: glue::GlueDataType = glue::GlueDataType::TpString;
 
@@ -370,7 +773,18 @@ impl<I: CasaScalarData + Copy, D: Dimension + DimFromShapeSlice<u64>> CasaDataTy
         }
     }
 
+    // The glue reads the buffer directly as a C-ordered array, so an array
+    // with any other memory layout must be copied first.
+    fn casatables_standard_layout(&self) -> Cow<'_, Self> {
+        if self.is_standard_layout() {
+            Cow::Borrowed(self)
+        } else {
+            Cow::Owned(self.as_standard_layout().into_owned())
+        }
+    }
+
     fn casatables_as_buf(&self) -> *const () {
+        debug_assert!(self.is_standard_layout());
         self.as_ptr() as _
     }
 
@@ -410,6 +824,32 @@ impl glue::StringBridge {
         }
     }
 
+    /// Pass a filesystem path to casacore.
+    ///
+    /// casacore treats paths as plain byte strings, so on Unix any path can
+    /// be passed along unchanged, whatever its encoding. Elsewhere, paths
+    /// must be valid Unicode. Paths containing NUL bytes are rejected, since
+    /// casacore would silently truncate them.
+    fn from_path(path: &Path) -> Result<Self, TableError> {
+        #[cfg(unix)]
+        let bytes = {
+            use std::os::unix::ffi::OsStrExt;
+            path.as_os_str().as_bytes()
+        };
+
+        #[cfg(not(unix))]
+        let bytes = path.to_str().ok_or(TableError::InvalidUtf8)?.as_bytes();
+
+        if bytes.contains(&0) {
+            return Err(TableError::InvalidPath(path.to_owned()));
+        }
+
+        Ok(Self {
+            data: bytes.as_ptr() as _,
+            n_bytes: bytes.len() as std::os::raw::c_ulong,
+        })
+    }
+
     // This function should only be called inside a callback from the C++ code.
     // Otherwise, it is essentially impossible to ensure that the data pointer
     // is valid and that its contents are uncorrupted. (The only time you can be
@@ -429,6 +869,50 @@ impl glue::StringBridge {
 // valid after the C++ stack frames have exited, and even harder to be sure that
 // they'll remain valid. The first part of the requisite dance is an `extern "C"
 // fn` callback that the C++ code can call safely.
+//
+// A panic must never unwind out of one of these callbacks into the C++ code
+// that called it, so each one catches any panic and stashes its message
+// away. Once that has happened, later calls to the callback do nothing, and
+// the invoking wrapper reports the panic as an error after the C++ function
+// returns.
+
+thread_local! {
+    static CALLBACK_PANIC: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+}
+
+fn guard_callback<G: FnOnce()>(g: G) {
+    if CALLBACK_PANIC.with(|p| p.borrow().is_some()) {
+        return;
+    }
+
+    if let Err(payload) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(g)) {
+        let message = if let Some(s) = payload.downcast_ref::<&str>() {
+            (*s).to_owned()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "panic with a non-string payload".to_owned()
+        };
+
+        CALLBACK_PANIC.with(|p| *p.borrow_mut() = Some(message));
+    }
+}
+
+/// Finish a call to a glue function that was passed one of the callbacks
+/// above, turning a panic in the callback into an error return.
+fn finish_callbacks(
+    rv: std::os::raw::c_int,
+    exc_info: &mut glue::ExcInfo,
+    context: &str,
+) -> std::os::raw::c_int {
+    match CALLBACK_PANIC.with(|p| p.borrow_mut().take()) {
+        None => rv,
+        Some(message) => {
+            exc_info.set_ffi_failure(&format!("callback from {}", context), &message);
+            1
+        }
+    }
+}
 
 unsafe extern "C" fn casatables_string_bridge_cb<F>(
     name: *const glue::StringBridge,
@@ -436,8 +920,10 @@ unsafe extern "C" fn casatables_string_bridge_cb<F>(
 ) where
     F: FnMut(String),
 {
-    let f: &mut F = &mut *(ctxt as *mut F);
-    f((&*name).to_rust())
+    guard_callback(|| {
+        let f: &mut F = &mut *(ctxt as *mut F);
+        f((&*name).to_rust())
+    })
 }
 
 unsafe extern "C" fn casatables_keyword_info_cb<F>(
@@ -447,8 +933,10 @@ unsafe extern "C" fn casatables_keyword_info_cb<F>(
 ) where
     F: FnMut(String, glue::GlueDataType),
 {
-    let f: &mut F = &mut *(ctxt as *mut F);
-    f((&*name).to_rust(), dtype)
+    guard_callback(|| {
+        let f: &mut F = &mut *(ctxt as *mut F);
+        f((&*name).to_rust(), dtype)
+    })
 }
 
 unsafe extern "C" fn casatables_keyword_repr_cb<F>(
@@ -459,8 +947,10 @@ unsafe extern "C" fn casatables_keyword_repr_cb<F>(
 ) where
     F: FnMut(String, glue::GlueDataType, String),
 {
-    let f: &mut F = &mut *(ctxt as *mut F);
-    f((&*name).to_rust(), dtype, (&*repr).to_rust())
+    guard_callback(|| {
+        let f: &mut F = &mut *(ctxt as *mut F);
+        f((&*name).to_rust(), dtype, (&*repr).to_rust())
+    })
 }
 
 // The next part: wrappers that allow us to invoke the various callback-having
@@ -475,12 +965,30 @@ unsafe fn invoke_table_get_column_names<F>(
 where
     F: FnMut(String),
 {
-    glue::table_get_column_names(
+    let rv = glue::table_get_column_names(
+        handle,
+        Some(casatables_string_bridge_cb::<F>),
+        &mut f as *mut _ as *mut std::os::raw::c_void,
+        exc_info,
+    );
+    finish_callbacks(rv, exc_info, "table_get_column_names")
+}
+
+unsafe fn invoke_tabledesc_get_column_names<F>(
+    handle: *mut glue::GlueTableDesc,
+    exc_info: &mut glue::ExcInfo,
+    mut f: F,
+) -> std::os::raw::c_int
+where
+    F: FnMut(String),
+{
+    let rv = glue::tabledesc_get_column_names(
         handle,
         Some(casatables_string_bridge_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "tabledesc_get_column_names")
 }
 
 unsafe fn invoke_table_get_keyword_info<F>(
@@ -491,12 +999,13 @@ unsafe fn invoke_table_get_keyword_info<F>(
 where
     F: FnMut(String, glue::GlueDataType),
 {
-    glue::table_get_keyword_info(
+    let rv = glue::table_get_keyword_info(
         handle,
         Some(casatables_keyword_info_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "table_get_keyword_info")
 }
 
 unsafe fn invoke_table_get_column_keyword_info<F>(
@@ -508,13 +1017,14 @@ unsafe fn invoke_table_get_column_keyword_info<F>(
 where
     F: FnMut(String, glue::GlueDataType),
 {
-    glue::table_get_column_keyword_info(
+    let rv = glue::table_get_column_keyword_info(
         handle,
         ccol_name,
         Some(casatables_keyword_info_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "table_get_column_keyword_info")
 }
 
 unsafe fn invoke_table_get_scalar_column_data_string<F>(
@@ -526,13 +1036,14 @@ unsafe fn invoke_table_get_scalar_column_data_string<F>(
 where
     F: FnMut(String),
 {
-    glue::table_get_scalar_column_data_string(
+    let rv = glue::table_get_scalar_column_data_string(
         handle,
         ccol_name,
         Some(casatables_string_bridge_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "table_get_scalar_column_data_string")
 }
 
 unsafe fn invoke_table_get_cell_string<F>(
@@ -545,14 +1056,15 @@ unsafe fn invoke_table_get_cell_string<F>(
 where
     F: FnMut(String),
 {
-    glue::table_get_cell_string(
+    let rv = glue::table_get_cell_string(
         handle,
         ccol_name,
         row,
         Some(casatables_string_bridge_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "table_get_cell_string")
 }
 
 unsafe fn invoke_table_get_file_name<F>(
@@ -563,12 +1075,32 @@ unsafe fn invoke_table_get_file_name<F>(
 where
     F: FnMut(String),
 {
-    glue::table_get_file_name(
+    let rv = glue::table_get_file_name(
+        handle,
+        Some(casatables_string_bridge_cb::<F>),
+        &mut f as *mut _ as *mut std::os::raw::c_void,
+        exc_info,
+    );
+    finish_callbacks(rv, exc_info, "table_get_file_name")
+}
+
+unsafe fn invoke_table_get_part_names<F>(
+    handle: *mut glue::GlueTable,
+    recursive: bool,
+    exc_info: &mut glue::ExcInfo,
+    mut f: F,
+) -> std::os::raw::c_int
+where
+    F: FnMut(String),
+{
+    let rv = glue::table_get_part_names(
         handle,
+        recursive as std::os::raw::c_int,
         Some(casatables_string_bridge_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "table_get_part_names")
 }
 
 unsafe fn invoke_table_get_cell_string_array<F>(
@@ -581,14 +1113,15 @@ unsafe fn invoke_table_get_cell_string_array<F>(
 where
     F: FnMut(String),
 {
-    glue::table_get_cell_string_array(
+    let rv = glue::table_get_cell_string_array(
         handle,
         ccol_name,
         row,
         Some(casatables_string_bridge_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "table_get_cell_string_array")
 }
 
 unsafe fn invoke_tablerec_get_keyword_info<F>(
@@ -599,12 +1132,13 @@ unsafe fn invoke_tablerec_get_keyword_info<F>(
 where
     F: FnMut(String, glue::GlueDataType),
 {
-    glue::tablerec_get_keyword_info(
+    let rv = glue::tablerec_get_keyword_info(
         handle,
         Some(casatables_keyword_info_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "tablerec_get_keyword_info")
 }
 
 unsafe fn invoke_tablerec_get_keyword_repr<F>(
@@ -615,12 +1149,13 @@ unsafe fn invoke_tablerec_get_keyword_repr<F>(
 where
     F: FnMut(String, glue::GlueDataType, String),
 {
-    glue::tablerec_get_keyword_repr(
+    let rv = glue::tablerec_get_keyword_repr(
         handle,
         Some(casatables_keyword_repr_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "tablerec_get_keyword_repr")
 }
 
 unsafe fn invoke_tablerec_get_field_string<F>(
@@ -632,13 +1167,14 @@ unsafe fn invoke_tablerec_get_field_string<F>(
 where
     F: FnMut(String),
 {
-    glue::tablerec_get_field_string(
+    let rv = glue::tablerec_get_field_string(
         handle,
         ccol_name,
         Some(casatables_string_bridge_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "tablerec_get_field_string")
 }
 
 unsafe fn invoke_tablerec_get_field_string_array<F>(
@@ -650,13 +1186,14 @@ unsafe fn invoke_tablerec_get_field_string_array<F>(
 where
     F: FnMut(String),
 {
-    glue::tablerec_get_field_string_array(
+    let rv = glue::tablerec_get_field_string_array(
         handle,
         ccol_name,
         Some(casatables_string_bridge_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "tablerec_get_field_string_array")
 }
 
 unsafe fn invoke_table_row_get_cell_string<F>(
@@ -668,13 +1205,14 @@ unsafe fn invoke_table_row_get_cell_string<F>(
 where
     F: FnMut(String),
 {
-    glue::table_row_get_cell_string(
+    let rv = glue::table_row_get_cell_string(
         handle,
         ccol_name,
         Some(casatables_string_bridge_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "table_row_get_cell_string")
 }
 
 unsafe fn invoke_table_row_get_cell_string_array<F>(
@@ -686,13 +1224,14 @@ unsafe fn invoke_table_row_get_cell_string_array<F>(
 where
     F: FnMut(String),
 {
-    glue::table_row_get_cell_string_array(
+    let rv = glue::table_row_get_cell_string_array(
         handle,
         ccol_name,
         Some(casatables_string_bridge_cb::<F>),
         &mut f as *mut _ as *mut std::os::raw::c_void,
         exc_info,
-    )
+    );
+    finish_callbacks(rv, exc_info, "table_row_get_cell_string_array")
 }
 
 /// Information about the structure of a CASA table.
@@ -852,6 +1391,107 @@ impl TableDesc {
         Ok(())
     }
 
+    /// Set the data manager that will store a column.
+    ///
+    /// Columns default to being stored with `StandardStMan`. This records the
+    /// type name of the casacore data manager that should be used instead,
+    /// such as `TiledColumnStMan`, when a table is created from this
+    /// description. Columns with the same data manager type and group are
+    /// stored together by a single data manager instance.
+    pub fn set_data_manager(
+        &mut self,
+        col_name: &str,
+        dm_type: &str,
+        dm_group: &str,
+    ) -> Result<(), TableError> {
+        let cname = glue::StringBridge::from_rust(col_name);
+        let ctype = glue::StringBridge::from_rust(dm_type);
+        let cgroup = glue::StringBridge::from_rust(dm_group);
+        let rv = unsafe {
+            glue::tabledesc_set_data_manager(
+                self.handle,
+                &cname,
+                &ctype,
+                &cgroup,
+                &mut self.exc_info,
+            )
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        Ok(())
+    }
+
+    /// Define a hypercolumn, binding columns together for storage by a tiled
+    /// data manager.
+    ///
+    /// casacore's tiled storage managers store a hypercolumn: a set of
+    /// array columns whose cells, stacked along the row axis, form one
+    /// hypercube of *n_dims* dimensions — one more than the dimensionality of
+    /// their cells. Measurement Sets written by CASA store `DATA`, `FLAG`,
+    /// `WEIGHT_SPECTRUM`, and the like this way. The hypercolumn's name
+    /// should match the data manager group set for its columns with
+    /// [`Self::set_data_manager`].
+    ///
+    /// *data_cols* are the array columns holding the data. *coord_cols*,
+    /// which may be empty, name columns giving the coordinates along each
+    /// axis of the hypercube. *id_cols*, also possibly empty, name scalar
+    /// columns whose values distinguish separate hypercubes, as used by
+    /// `TiledDataStMan`.
+    ///
+    /// ```rust
+    /// use rubbl_casatables::{GlueDataType, TableDesc, TableDescCreateMode};
+    ///
+    /// let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+    /// desc.add_array_column(GlueDataType::TpComplex, "DATA", None, None, false, false)
+    ///     .unwrap();
+    /// desc.set_ndims("DATA", 2).unwrap();
+    /// desc.set_data_manager("DATA", "TiledShapeStMan", "TiledData").unwrap();
+    /// desc.define_hypercolumn("TiledData", 3, &["DATA"], &[], &[]).unwrap();
+    /// ```
+    pub fn define_hypercolumn(
+        &mut self,
+        name: &str,
+        n_dims: u64,
+        data_cols: &[&str],
+        coord_cols: &[&str],
+        id_cols: &[&str],
+    ) -> Result<(), TableError> {
+        let cname = glue::StringBridge::from_rust(name);
+        let bridge = |names: &[&str]| -> Vec<glue::StringBridge> {
+            names
+                .iter()
+                .map(|n| glue::StringBridge::from_rust(n))
+                .collect()
+        };
+        let cdata = bridge(data_cols);
+        let ccoord = bridge(coord_cols);
+        let cid = bridge(id_cols);
+
+        let rv = unsafe {
+            glue::tabledesc_define_hypercolumn(
+                self.handle,
+                &cname,
+                n_dims as _,
+                cdata.len() as _,
+                cdata.as_ptr(),
+                ccoord.len() as _,
+                ccoord.as_ptr(),
+                cid.len() as _,
+                cid.as_ptr(),
+                &mut self.exc_info,
+            )
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        Ok(())
+    }
+
     /// Return a copy of the keyword TableRecord
     pub fn get_keyword_record(&mut self) -> Result<TableRecord, CasacoreError> {
         let handle = unsafe { glue::tabledesc_get_keywords(self.handle, &mut self.exc_info) };
@@ -880,47 +1520,227 @@ impl TableDesc {
         TableRecord::copy_handle(unsafe { &*handle })
     }
 
-    /// Add a "keyword" to be associated with a particular column in this table
-    /// description.
-    ///
-    /// `col_name` - The name of the affected column.
-    ///
-    /// `kw_name` - The name of the keyword to apply to the column.
-    ///
-    /// `value` - The value to associate with the keyword.
-    pub fn put_column_keyword<T: CasaDataType>(
-        &mut self,
-        col_name: &str,
-        kw_name: &str,
-        value: &T,
-    ) -> Result<(), CasacoreError> {
-        let ccol_name = glue::StringBridge::from_rust(col_name);
-        let ckw_name = glue::StringBridge::from_rust(kw_name);
-        let mut shape = Vec::new();
+    /// Get the names of the columns in this table description.
+    pub fn column_names(&mut self) -> Result<Vec<String>, CasacoreError> {
+        let mut cnames = Vec::new();
 
-        value.casatables_put_shape(&mut shape);
+        let rv = unsafe {
+            invoke_tabledesc_get_column_names(self.handle, &mut self.exc_info, |name| {
+                cnames.push(name);
+            })
+        };
 
-        if T::DATA_TYPE == glue::GlueDataType::TpString {
-            let as_string = T::casatables_string_pass_through_out(value);
-            let glue_string = glue::StringBridge::from_rust(&as_string);
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
 
-            let rv = unsafe {
-                glue::tabledesc_put_column_keyword(
-                    self.handle,
-                    &ccol_name,
-                    &ckw_name,
-                    T::DATA_TYPE,
-                    shape.len() as u64,
-                    shape.as_ptr(),
-                    &glue_string as *const glue::StringBridge as _,
-                    &mut self.exc_info,
-                )
-            };
+        Ok(cnames)
+    }
 
-            if rv != 0 {
-                return self.exc_info.as_err();
-            }
-        } else if T::DATA_TYPE == glue::GlueDataType::TpArrayString {
+    /// Get the description of the named column.
+    ///
+    /// This is the counterpart of [`Table::get_col_desc`] for a table
+    /// description that has not necessarily been used to create a table.
+    pub fn get_col_desc(&mut self, col_name: &str) -> Result<ColumnDescription, CasacoreError> {
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut data_type = glue::GlueDataType::TpOther;
+        let mut is_scalar = 0;
+        let mut is_fixed_shape = 0;
+        let mut n_dim = 0;
+        let mut dims = [0; 8];
+
+        let rv = unsafe {
+            glue::tabledesc_get_column_info(
+                self.handle,
+                &ccol_name,
+                &mut data_type,
+                &mut is_scalar,
+                &mut is_fixed_shape,
+                &mut n_dim,
+                dims.as_mut_ptr(),
+                &mut self.exc_info,
+            )
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        let shape = if is_fixed_shape == 0 || n_dim < 0 {
+            None
+        } else {
+            Some(dims[..n_dim as usize].to_vec())
+        };
+
+        let keywords = self.get_column_keyword_record(col_name)?;
+
+        Ok(ColumnDescription {
+            name: col_name.to_owned(),
+            data_type,
+            is_scalar: is_scalar != 0,
+            is_fixed_shape: is_fixed_shape != 0,
+            shape,
+            keywords,
+        })
+    }
+
+    /// Get the shape information of the named column, as with
+    /// [`Table::column_shape`].
+    pub fn column_shape(&mut self, col_name: &str) -> Result<ColumnShapeInfo, TableError> {
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut data_type = glue::GlueDataType::TpOther;
+        let mut is_scalar = 0;
+        let mut is_fixed_shape = 0;
+        let mut n_dim = 0;
+        let mut dims = [0; 8];
+
+        if unsafe {
+            glue::tabledesc_get_column_info(
+                self.handle,
+                &ccol_name,
+                &mut data_type,
+                &mut is_scalar,
+                &mut is_fixed_shape,
+                &mut n_dim,
+                dims.as_mut_ptr(),
+                &mut self.exc_info,
+            )
+        } != 0
+        {
+            return self.exc_info.as_err();
+        }
+
+        Ok(if is_scalar != 0 {
+            ColumnShapeInfo::Scalar
+        } else if is_fixed_shape != 0 && n_dim > 0 {
+            ColumnShapeInfo::Fixed(dims[..n_dim as usize].iter().map(|d| *d as usize).collect())
+        } else {
+            ColumnShapeInfo::Variable {
+                ndim: if n_dim > 0 {
+                    Some(n_dim as usize)
+                } else {
+                    None
+                },
+            }
+        })
+    }
+
+    /// Add a "keyword" to be associated with this table description.
+    ///
+    /// `kw_name` - The name of the keyword.
+    ///
+    /// `value` - The value to associate with the keyword.
+    pub fn put_keyword<T: CasaDataType>(
+        &mut self,
+        kw_name: &str,
+        value: &T,
+    ) -> Result<(), CasacoreError> {
+        let ckw_name = glue::StringBridge::from_rust(kw_name);
+        let mut shape = Vec::new();
+
+        value.casatables_put_shape(&mut shape);
+
+        if T::DATA_TYPE == glue::GlueDataType::TpString {
+            let as_string = T::casatables_string_pass_through_out(value);
+            let glue_string = glue::StringBridge::from_rust(&as_string);
+
+            let rv = unsafe {
+                glue::tabledesc_put_keyword(
+                    self.handle,
+                    &ckw_name,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    &glue_string as *const glue::StringBridge as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+        } else if T::DATA_TYPE == glue::GlueDataType::TpArrayString {
+            let glue_strings = T::casatables_stringvec_pass_through_out(value);
+
+            let rv = unsafe {
+                glue::tabledesc_put_keyword(
+                    self.handle,
+                    &ckw_name,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    glue_strings.as_ptr() as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+        } else {
+            let value = value.casatables_standard_layout();
+
+            let rv = unsafe {
+                glue::tabledesc_put_keyword(
+                    self.handle,
+                    &ckw_name,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    value.casatables_as_buf() as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add a "keyword" to be associated with a particular column in this table
+    /// description.
+    ///
+    /// `col_name` - The name of the affected column.
+    ///
+    /// `kw_name` - The name of the keyword to apply to the column.
+    ///
+    /// `value` - The value to associate with the keyword.
+    pub fn put_column_keyword<T: CasaDataType>(
+        &mut self,
+        col_name: &str,
+        kw_name: &str,
+        value: &T,
+    ) -> Result<(), CasacoreError> {
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let ckw_name = glue::StringBridge::from_rust(kw_name);
+        let mut shape = Vec::new();
+
+        value.casatables_put_shape(&mut shape);
+
+        if T::DATA_TYPE == glue::GlueDataType::TpString {
+            let as_string = T::casatables_string_pass_through_out(value);
+            let glue_string = glue::StringBridge::from_rust(&as_string);
+
+            let rv = unsafe {
+                glue::tabledesc_put_column_keyword(
+                    self.handle,
+                    &ccol_name,
+                    &ckw_name,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    &glue_string as *const glue::StringBridge as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+        } else if T::DATA_TYPE == glue::GlueDataType::TpArrayString {
             let glue_strings = T::casatables_stringvec_pass_through_out(value);
 
             let rv = unsafe {
@@ -940,6 +1760,8 @@ impl TableDesc {
                 return self.exc_info.as_err();
             }
         } else {
+            let value = value.casatables_standard_layout();
+
             let rv = unsafe {
                 glue::tabledesc_put_column_keyword(
                     self.handle,
@@ -965,13 +1787,48 @@ impl TableDesc {
 // Tables
 
 /// A CASA data table.
+///
+/// casacore tables are not thread-safe, so a `Table` is neither [`Send`] nor
+/// [`Sync`]: it stays on the thread that opened it. Open it in a
+/// [`SyncTable`] to use it from other threads, for instance rayon workers.
+///
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<rubbl_casatables::Table>();
+/// ```
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<rubbl_casatables::Table>();
+/// ```
 pub struct Table {
     handle: *mut glue::GlueTable,
     exc_info: glue::ExcInfo,
+    column_cache: HashMap<String, CachedColumn>,
+    schema_generation: u64,
+    io_stats: Option<Box<IoStats>>,
+    retry: Option<RetryPolicy>,
+    virtual_columns: HashMap<String, Arc<VirtualExpr>>,
+}
+
+/// A column handle retained by a [`Table`] to speed up repeated writes to the
+/// same column. See [`Table::put_cell_cached`].
+struct CachedColumn {
+    handle: *mut glue::GlueColumn,
+    data_type: glue::GlueDataType,
+    generation: u64,
+}
+
+impl Drop for CachedColumn {
+    fn drop(&mut self) {
+        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+        unsafe { glue::column_free(self.handle, &mut exc_info) };
+    }
 }
 
 /// Modes in which a casacore table can be opened.
 ///
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum TableOpenMode {
     /// Open the table for read-only access.
     Read = 1,
@@ -981,6 +1838,46 @@ pub enum TableOpenMode {
 
     /// Create a new table.
     Create = 3,
+
+    /// Open the table for read-only access without taking any locks.
+    ///
+    /// Normally, readers acquire a read lock on the table, which can fail or
+    /// block if another process is writing to it. In this mode no locking is
+    /// done at all, so the table can be opened even while another process
+    /// holds a write lock. This is only safe if the table is known not to be
+    /// changing: if it is being modified, this process may see inconsistent
+    /// data.
+    ReadNoLock = 4,
+
+    /// Open the table for read-only access on an unreliable filesystem.
+    ///
+    /// This is like [`Self::Read`], except that the table is given the
+    /// default [`RetryPolicy`]: opening the table and reading its data are
+    /// retried after transient failures, such as the I/O errors and stale
+    /// file handles that network filesystems produce when a server hiccups.
+    /// See [`Table::set_retry_policy`].
+    ReadTolerant = 5,
+}
+
+/// The kinds of table that casacore can open.
+///
+/// All of them are accessed in the same way: casacore maps the row numbers
+/// of reference and concatenated tables onto those of the tables holding the
+/// data. See [`Table::kind`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TableKind {
+    /// A table that stores its own data.
+    Plain,
+
+    /// A table that refers to a selection of the rows and columns of another
+    /// table, as produced by a selection or sort.
+    Reference,
+
+    /// A table that concatenates the rows of several other tables.
+    Concatenated,
+
+    /// Some other kind of table, such as one held in memory.
+    Other,
 }
 
 /// Modes in which a casacore table can be created.
@@ -1009,6 +1906,38 @@ pub enum TableCreateMode {
     NewNoReplace = 2,
 }
 
+/// Byte orders in which the data of a casacore table can be stored.
+///
+/// Casacore can read tables in either byte order, so this generally only
+/// matters for performance or when the data are destined for software that
+/// expects a particular byte order.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EndianFormat {
+    /// Store data in big-endian byte order.
+    Big = 1,
+
+    /// Store data in little-endian byte order.
+    Little = 2,
+
+    /// Store data in the native byte order of the current machine.
+    Local = 3,
+
+    /// Use the byte order specified by the `table.endianformat` setting in
+    /// casacore's configuration, defaulting to [`EndianFormat::Local`].
+    Aipsrc = 4,
+}
+
+impl EndianFormat {
+    fn as_glue(self) -> glue::TableEndianFormat {
+        match self {
+            EndianFormat::Big => glue::TableEndianFormat::TEF_BIG_ENDIAN,
+            EndianFormat::Little => glue::TableEndianFormat::TEF_LITTLE_ENDIAN,
+            EndianFormat::Local => glue::TableEndianFormat::TEF_LOCAL_ENDIAN,
+            EndianFormat::Aipsrc => glue::TableEndianFormat::TEF_AIPSRC_ENDIAN,
+        }
+    }
+}
+
 /// An error type used when a scalar column was expected but a vector was found.
 #[derive(Error, Debug)]
 #[error("Expected a column with a scalar data type, but found a vector of {0}")]
@@ -1027,9 +1956,16 @@ pub struct UnexpectedDataTypeError(glue::GlueDataType, glue::GlueDataType);
 #[derive(Error, Debug)]
 pub enum TableError {
     /// Table paths must be representable as UTF-8 strings.
+    ///
+    /// This is only required on platforms other than Unix.
     #[error("table paths must be representable as UTF-8 strings")]
     InvalidUtf8,
 
+    /// The path cannot be passed to casacore, because it contains a NUL
+    /// byte.
+    #[error("the table path {0:?} contains a NUL byte")]
+    InvalidPath(PathBuf),
+
     /// Expected a scalar, but got a vector.
     #[error("Expected a column with a scalar data type, but found a vector of {0}")]
     NotScalarColumnError(glue::GlueDataType),
@@ -1040,12 +1976,221 @@ pub enum TableError {
 
     /// Generic casacore C++ exception.
     #[error(transparent)]
-    Casacore(#[from] CasacoreError),
+    Casacore(CasacoreError),
+
+    /// A failure at the boundary between Rust and C++: a panic in Rust code
+    /// called back from C++, or a C++ exception of a type that could not be
+    /// identified. Neither is allowed to unwind across the boundary; they
+    /// are caught and reported as this error instead.
+    #[error("FFI failure in {context}: {message}")]
+    Ffi {
+        /// Where the failure occurred.
+        context: String,
+        /// A description of the failure, such as the panic message.
+        message: String,
+    },
 
     /// An error type used when two arrays should have the same dimensionality,
     /// but do not.
     #[error(transparent)]
     DimensionMismatch(#[from] DimensionMismatchError),
+
+    /// An operation that requires an array column was given a scalar column.
+    #[error("column \"{0}\" is a scalar column, but this operation requires an array column")]
+    ScalarColumnError(String),
+
+    /// Bulk I/O requires a column whose cells all have the same shape.
+    #[error("column \"{0}\" is not a scalar column or one with cells of a uniform shape")]
+    NotFixedShapeColumnError(String),
+
+    /// The items of a chunked array stream do not have the shape of the cells
+    /// of the column that it is being written to.
+    #[error("column cells have shape {0:?}, but the stream has items of shape {1:?}")]
+    ChunkShapeMismatch(Vec<usize>, Vec<usize>),
+
+    /// The source or sink of a chunked array stream reported an error.
+    #[error("error in chunked array stream")]
+    ChunkStream(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// A range of rows extends beyond the end of the table.
+    #[error("rows {start}..{end} exceed the table length of {n_rows}")]
+    RowsOutOfBounds {
+        /// The first row of the range.
+        start: u64,
+        /// The end of the range, exclusive.
+        end: u64,
+        /// The number of rows in the table.
+        n_rows: u64,
+    },
+
+    /// A slice of a cell extends beyond the cell's bounds.
+    #[error("slice at {start:?} of shape {shape:?} exceeds cell shape {cell:?}")]
+    SliceOutOfBounds {
+        /// The shape of the cell.
+        cell: Vec<usize>,
+        /// The start of the requested slice.
+        start: Vec<usize>,
+        /// The shape of the requested slice.
+        shape: Vec<usize>,
+    },
+
+    /// An I/O error occurred while accessing table files directly.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// An operation was stopped through its [`CancellationToken`] after
+    /// *rows_done* rows had been processed. See the [`Progress`]
+    /// documentation for the state that this leaves the table in.
+    #[error("the operation was cancelled after {rows_done} rows")]
+    Cancelled {
+        /// The number of rows processed before the operation stopped.
+        rows_done: u64,
+    },
+
+    /// The table uses a data manager that casacore could not load, such as
+    /// one provided by an external plugin library. This is only returned if
+    /// the affected columns could not be identified; otherwise the error is
+    /// [`TableError::UnsupportedStorageManager`].
+    #[error(
+        "the table uses the data manager \"{0}\", which is not available; \
+         its plugin library may need to be installed or added to the library search path"
+    )]
+    MissingDataManager(String),
+
+    /// A column is stored with a data manager that casacore could not load,
+    /// such as one that the bundled casacore lacks or one provided by an
+    /// external plugin library. The rest of the table can still be read by
+    /// opening it with [`Table::open_masked`].
+    #[error(
+        "column \"{column}\" is stored with the data manager \"{manager}\", which is not available; \
+         its plugin library may need to be installed, or the table can be opened with the column masked"
+    )]
+    UnsupportedStorageManager {
+        /// The name of the column.
+        column: String,
+        /// The type of the data manager.
+        manager: String,
+    },
+
+    /// An operation that only works on numeric and boolean data was given a
+    /// column of some other type, such as a string column.
+    #[error("column \"{0}\" has the data type {1}, which is not supported by this operation")]
+    UnsupportedDataType(String, glue::GlueDataType),
+
+    /// A virtual column could not be registered or evaluated.
+    #[error("virtual column \"{column}\": {reason}")]
+    VirtualColumn {
+        /// The name of the virtual column.
+        column: String,
+        /// A description of the problem.
+        reason: String,
+    },
+
+    /// An argument passed to an operation is not valid.
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+impl From<CasacoreError> for TableError {
+    fn from(e: CasacoreError) -> Self {
+        match ffi_failure(&e.0) {
+            Some((context, message)) => TableError::Ffi { context, message },
+            None => TableError::Casacore(e),
+        }
+    }
+}
+
+/// The values of the Measurement Set index columns in one row of a table.
+///
+/// These are passed to the predicate of [`Table::read_column_chunks_where`]
+/// and the callback of [`Table::for_each_index_row`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IndexRow {
+    /// The row number.
+    pub row: u64,
+
+    /// The value of the `TIME` column.
+    pub time: f64,
+
+    /// The value of the `ANTENNA1` column.
+    pub antenna1: i32,
+
+    /// The value of the `ANTENNA2` column.
+    pub antenna2: i32,
+
+    /// The value of the `DATA_DESC_ID` column.
+    pub data_desc_id: i32,
+
+    /// The value of the `FIELD_ID` column.
+    pub field_id: i32,
+}
+
+/// Buffers for reading the index columns of a chunk of rows.
+struct IndexChunk {
+    columns: [glue::StringBridge; 5],
+    time: Vec<f64>,
+    ints: [Vec<i32>; 4],
+}
+
+impl IndexChunk {
+    fn new(table: &mut Table, rows_per_chunk: usize) -> Result<Self, TableError> {
+        for name in ["ANTENNA1", "ANTENNA2", "DATA_DESC_ID", "FIELD_ID"] {
+            if !table.bulk_column_cell_shape::<i32>(name)?.is_empty() {
+                return Err(TableError::NotScalarColumnError(i32::DATA_TYPE));
+            }
+        }
+
+        if !table.bulk_column_cell_shape::<f64>("TIME")?.is_empty() {
+            return Err(TableError::NotScalarColumnError(f64::DATA_TYPE));
+        }
+
+        Ok(IndexChunk {
+            columns: [
+                glue::StringBridge::from_rust("TIME"),
+                glue::StringBridge::from_rust("ANTENNA1"),
+                glue::StringBridge::from_rust("ANTENNA2"),
+                glue::StringBridge::from_rust("DATA_DESC_ID"),
+                glue::StringBridge::from_rust("FIELD_ID"),
+            ],
+            time: vec![0.; rows_per_chunk],
+            ints: [
+                vec![0; rows_per_chunk],
+                vec![0; rows_per_chunk],
+                vec![0; rows_per_chunk],
+                vec![0; rows_per_chunk],
+            ],
+        })
+    }
+
+    fn read(&mut self, table: &mut Table, start_row: u64, n: usize) -> Result<(), TableError> {
+        table.read_scalar_range(&self.columns[0], start_row, &mut self.time[..n])?;
+
+        for (name, buf) in self.columns[1..].iter().zip(self.ints.iter_mut()) {
+            table.read_scalar_range(name, start_row, &mut buf[..n])?;
+        }
+
+        if table.io_stats.is_some() {
+            let n = n as u64;
+            table.record_io(IoDirection::Get, "TIME", n, f64::DATA_TYPE, n);
+
+            for name in &["ANTENNA1", "ANTENNA2", "DATA_DESC_ID", "FIELD_ID"] {
+                table.record_io(IoDirection::Get, name, n, i32::DATA_TYPE, n);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn row(&self, start_row: u64, i: usize) -> IndexRow {
+        IndexRow {
+            row: start_row + i as u64,
+            time: self.time[i],
+            antenna1: self.ints[0][i],
+            antenna2: self.ints[1][i],
+            data_desc_id: self.ints[2][i],
+            field_id: self.ints[3][i],
+        }
+    }
 }
 
 /// A Rust wrapper for a casacore table.
@@ -1100,28 +2245,82 @@ impl Table {
         n_rows: usize,
         mode: TableCreateMode,
     ) -> Result<Self, TableError> {
-        let spath = match path.as_ref().to_str() {
-            Some(s) => s,
-            None => {
-                return Err(TableError::InvalidUtf8);
-            }
-        };
-
-        let cpath = glue::StringBridge::from_rust(spath);
-        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+        Self::new_with_endian(path, table_desc, n_rows, mode, EndianFormat::Local)
+    }
 
-        let cmode = match mode {
-            TableCreateMode::New => glue::TableCreateMode::TCM_NEW,
-            TableCreateMode::NewNoReplace => glue::TableCreateMode::TCM_NEW_NO_REPLACE,
+    /// Create a new casacore table, storing its data in the specified byte
+    /// order.
+    ///
+    /// This is the same as [`Table::new`], which always uses the native byte
+    /// order of the current machine, except that the byte order can be
+    /// chosen.
+    pub fn new_with_endian<P: AsRef<Path>>(
+        path: P,
+        table_desc: TableDesc,
+        n_rows: usize,
+        mode: TableCreateMode,
+        endian_format: EndianFormat,
+    ) -> Result<Self, TableError> {
+        Self::create(path, table_desc, n_rows, mode, endian_format, None)
+    }
+
+    /// Create a new casacore table, choosing the data managers of its
+    /// columns with a data manager info record.
+    ///
+    /// The record has the format used by casacore’s
+    /// `SetupNewTable::bindCreate`: each of its fields is a sub-record with
+    /// fields `TYPE` (the data manager type name), `NAME` (the data manager
+    /// group name), `COLUMNS` (an array of column names), and optionally
+    /// `SPEC` (a record of data manager options). Columns that are not listed
+    /// use the data managers set in the table description. See
+    /// [`ms::dysco::DyscoOptions::dminfo`] for an example.
+    ///
+    /// Data manager types that casacore does not know about are loaded from
+    /// shared libraries at runtime, following the usual casacore rules.
+    pub fn new_with_dminfo<P: AsRef<Path>>(
+        path: P,
+        table_desc: TableDesc,
+        n_rows: usize,
+        mode: TableCreateMode,
+        dminfo: &TableRecord,
+    ) -> Result<Self, TableError> {
+        Self::create(
+            path,
+            table_desc,
+            n_rows,
+            mode,
+            EndianFormat::Local,
+            Some(dminfo),
+        )
+    }
+
+    fn create<P: AsRef<Path>>(
+        path: P,
+        table_desc: TableDesc,
+        n_rows: usize,
+        mode: TableCreateMode,
+        endian_format: EndianFormat,
+        dminfo: Option<&TableRecord>,
+    ) -> Result<Self, TableError> {
+        let cpath = glue::StringBridge::from_path(path.as_ref())?;
+        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+
+        let cmode = match mode {
+            TableCreateMode::New => glue::TableCreateMode::TCM_NEW,
+            TableCreateMode::NewNoReplace => glue::TableCreateMode::TCM_NEW_NO_REPLACE,
             // TableCreateMode::Scratch => glue::TableCreateMode::TCM_SCRATCH,
         };
 
+        config::note_casacore_used();
+
         let handle = unsafe {
             glue::table_create(
                 &cpath,
                 table_desc.handle,
                 n_rows as u64,
                 cmode,
+                endian_format.as_glue(),
+                dminfo.map_or(std::ptr::null(), |r| r.handle as *const _),
                 &mut exc_info,
             )
         };
@@ -1129,10 +2328,46 @@ impl Table {
             return exc_info.as_err();
         }
 
-        Ok(Table {
-            handle: handle,
-            exc_info: exc_info,
-        })
+        Ok(Table::from_handle(handle, exc_info, TableOrigin::Created))
+    }
+
+    /// Create an empty Measurement Set with the standard set of sub-tables.
+    ///
+    /// The main table is created with `n_rows` rows and the columns listed in
+    /// [`ms::schema::MAIN`]. Each of the [`ms::schema::REQUIRED_SUBTABLES`] is
+    /// created, without any rows, in a directory inside the main table
+    /// directory and linked to the main table with a table-type keyword. The
+    /// `MS_VERSION` keyword is set to 2.0.
+    ///
+    /// The table definitions are built into this crate, so no template data
+    /// set or external tools are needed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rubbl_casatables::Table;
+    /// use tempfile::tempdir;
+    ///
+    /// let tmp_dir = tempdir().unwrap();
+    /// let ms_path = tmp_dir.path().join("empty.ms");
+    ///
+    /// let mut ms = Table::create_with_default_subtables(&ms_path, 0).unwrap();
+    /// assert!(ms.table_keyword_names().unwrap().contains(&"ANTENNA".to_owned()));
+    /// ```
+    pub fn create_with_default_subtables<P: AsRef<Path>>(
+        path: P,
+        n_rows: usize,
+    ) -> Result<Self, TableError> {
+        let path = path.as_ref();
+        let mut main = ms::schema::MAIN.create(path, n_rows)?;
+
+        for sub_spec in ms::schema::REQUIRED_SUBTABLES {
+            let sub = sub_spec.create(path.join(sub_spec.name), 0)?;
+            main.put_table_keyword(sub_spec.name, sub)?;
+        }
+
+        main.put_keyword("MS_VERSION", &2.0f32)?;
+        Ok(main)
     }
 
     /// Open an existing casacore table.
@@ -1191,27 +2426,61 @@ impl Table {
     ///
     /// # Errors
     ///
-    /// Can raise [`CasacoreError`] if there was an issue invoking casacore.
+    /// Can raise [`CasacoreError`] if there was an issue invoking casacore,
+    /// and [`TableError::UnsupportedStorageManager`] if a column is stored
+    /// with a data manager that is not available.
     pub fn open<P: AsRef<Path>>(path: P, mode: TableOpenMode) -> Result<Self, TableError> {
-        let spath = match path.as_ref().to_str() {
-            Some(s) => s,
-            None => return Err(TableError::InvalidUtf8),
-        };
-        let cpath = glue::StringBridge::from_rust(spath);
+        let cpath = glue::StringBridge::from_path(path.as_ref())?;
         let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
 
         let cmode = match mode {
             TableOpenMode::Read => glue::TableOpenMode::TOM_OPEN_READONLY,
             TableOpenMode::ReadWrite => glue::TableOpenMode::TOM_OPEN_RW,
             TableOpenMode::Create => glue::TableOpenMode::TOM_CREATE,
+            TableOpenMode::ReadNoLock => glue::TableOpenMode::TOM_OPEN_READONLY_NOLOCK,
+            TableOpenMode::ReadTolerant => {
+                return retry::open_tolerant(path.as_ref(), RetryPolicy::default());
+            }
         };
 
+        config::note_casacore_used();
         let handle = unsafe { glue::table_alloc_and_open(&cpath, cmode, &mut exc_info) };
         if handle.is_null() {
-            return exc_info.as_err();
+            let err = exc_info.as_error();
+
+            if let Some(dm_type) = masked::missing_data_manager(path.as_ref(), &err.0) {
+                return Err(masked::unsupported_storage_manager(path.as_ref(), dm_type));
+            }
+
+            return Err(err.into());
         }
 
-        Ok(Table { handle, exc_info })
+        Ok(Table::from_handle(
+            handle,
+            exc_info,
+            TableOrigin::Opened(mode),
+        ))
+    }
+
+    /// Wrap a handle to a newly opened table, recording it in the registry
+    /// of open tables.
+    fn from_handle(
+        handle: *mut glue::GlueTable,
+        exc_info: glue::ExcInfo,
+        origin: TableOrigin,
+    ) -> Table {
+        let table = Table {
+            handle,
+            exc_info,
+            column_cache: HashMap::new(),
+            schema_generation: 0,
+            io_stats: None,
+            retry: None,
+            virtual_columns: HashMap::new(),
+        };
+
+        registry::register(&table, origin);
+        table
     }
 
     /// Get the number of rows in the table.
@@ -1224,6 +2493,32 @@ impl Table {
         unsafe { glue::table_n_columns(self.handle) as usize }
     }
 
+    /// Create a read-only view of the table pinned to its current rows.
+    ///
+    /// The returned table is a casacore reference table selecting the rows
+    /// that exist now. Rows added afterwards — for instance by another
+    /// process appending to a Measurement Set that is being observed — do
+    /// not appear in it, so a monitoring loop can read the snapshot without
+    /// ever seeing trailing rows that are still being filled in. Take a new
+    /// snapshot to pick up new rows.
+    ///
+    /// The snapshot shares the data of this table, so changes made to the
+    /// existing rows are visible through it. The snapshot itself is
+    /// read-only: attempts to write cells or keywords through it fail.
+    pub fn snapshot(&mut self) -> Result<Table, TableError> {
+        let handle = unsafe { glue::table_snapshot(self.handle, &mut self.exc_info) };
+
+        if handle.is_null() {
+            return self.exc_info.as_err();
+        }
+
+        Ok(Table::from_handle(
+            handle,
+            unsafe { std::mem::zeroed::<glue::ExcInfo>() },
+            TableOrigin::Derived,
+        ))
+    }
+
     /// Get the filesystem path associated with the table.
     ///
     /// This function should only fail of the underlying C++ throws an
@@ -1245,6 +2540,85 @@ impl Table {
         Ok(result)
     }
 
+    /// Get what kind of table this is.
+    ///
+    /// [`Self::open`] accepts reference and concatenated tables as well as
+    /// plain ones, and reading and writing cells works the same way for all
+    /// of them. Operations that go straight to the data files, such as
+    /// [`Self::map_column`], only work on plain tables.
+    pub fn kind(&self) -> TableKind {
+        match unsafe { glue::table_get_kind(self.handle) } {
+            0 => TableKind::Plain,
+            1 => TableKind::Reference,
+            2 => TableKind::Concatenated,
+            _ => TableKind::Other,
+        }
+    }
+
+    /// Return true if this is a reference table: a selection of the rows of
+    /// another table.
+    pub fn is_reference(&self) -> bool {
+        self.kind() == TableKind::Reference
+    }
+
+    /// Get the paths of the tables that hold the data of this one.
+    ///
+    /// For a plain table, this is just its own path. For a reference table,
+    /// it is the path of the table that it refers to, and for a concatenated
+    /// table, the paths of all of its parts. Chains of reference and
+    /// concatenated tables are followed all the way down.
+    pub fn root_table_paths(&mut self) -> Result<Vec<String>, CasacoreError> {
+        let mut paths = Vec::new();
+
+        let rv = unsafe {
+            invoke_table_get_part_names(self.handle, true, &mut self.exc_info, |p| paths.push(p))
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        Ok(paths)
+    }
+
+    /// Get the path of the table that holds the data of this one.
+    ///
+    /// For a concatenated table, this is the path of the first part; use
+    /// [`Self::root_table_paths`] to get all of them.
+    pub fn root_table_path(&mut self) -> Result<String, CasacoreError> {
+        let paths = self.root_table_paths()?;
+
+        match paths.into_iter().next() {
+            Some(p) => Ok(p),
+            None => self.file_name(),
+        }
+    }
+
+    /// Get the numbers of this table's rows in its root table.
+    ///
+    /// For a reference table, such as a snapshot or the result of a
+    /// selection or sort, element *i* is the row of the root table that row
+    /// *i* of this table refers to. Changes computed from a filtered view can
+    /// be written back to the original table by way of these numbers. For a
+    /// plain table, the numbers simply count up from zero.
+    ///
+    /// For a concatenated table, the numbers refer to the rows of the
+    /// concatenation.
+    pub fn row_numbers(&mut self) -> Result<Vec<u64>, CasacoreError> {
+        let n_rows = self.n_rows() as usize;
+        let mut rows: Vec<std::os::raw::c_ulong> = vec![0; n_rows];
+
+        let rv = unsafe {
+            glue::table_get_row_numbers(self.handle, rows.as_mut_ptr(), &mut self.exc_info)
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        Ok(rows)
+    }
+
     /// Get a vector containing all of the column names in the table.
     ///
     /// # Errors
@@ -1267,6 +2641,23 @@ impl Table {
         Ok(cnames)
     }
 
+    /// Get a copy of the description of this table.
+    ///
+    /// The copy reflects the table's current columns and keywords. Modifying
+    /// it does not affect the table.
+    pub fn table_desc(&mut self) -> Result<TableDesc, CasacoreError> {
+        let handle = unsafe { glue::table_get_table_desc(self.handle, &mut self.exc_info) };
+
+        if handle.is_null() {
+            return self.exc_info.as_err();
+        }
+
+        Ok(TableDesc {
+            handle,
+            exc_info: unsafe { std::mem::zeroed::<glue::ExcInfo>() },
+        })
+    }
+
     /// Remove a column from the table.
     ///
     /// # Errors
@@ -1275,6 +2666,7 @@ impl Table {
     /// **To check:** this probably returns an error if the named column was not
     /// present?
     pub fn remove_column(&mut self, col_name: &str) -> Result<(), CasacoreError> {
+        self.clear_column_cache();
         let ccol_name = glue::StringBridge::from_rust(col_name);
 
         let rv = unsafe { glue::table_remove_column(self.handle, &ccol_name, &mut self.exc_info) };
@@ -1286,6 +2678,29 @@ impl Table {
         Ok(())
     }
 
+    /// Rename a column of the table.
+    ///
+    /// # Errors
+    ///
+    /// Can raise [`CasacoreError`] if there was an issue invoking casacore,
+    /// including if there is no column named `old_name` or there is already a
+    /// column named `new_name`.
+    pub fn rename_column(&mut self, old_name: &str, new_name: &str) -> Result<(), CasacoreError> {
+        self.clear_column_cache();
+        let cold_name = glue::StringBridge::from_rust(old_name);
+        let cnew_name = glue::StringBridge::from_rust(new_name);
+
+        let rv = unsafe {
+            glue::table_rename_column(self.handle, &cold_name, &cnew_name, &mut self.exc_info)
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        Ok(())
+    }
+
     /// Add a scalar column to the table.
     ///
     /// `col_name` - column name, must be unique
@@ -1306,6 +2721,7 @@ impl Table {
         direct: bool,
         undefined: bool,
     ) -> Result<(), CasacoreError> {
+        self.clear_column_cache();
         let ccol_name = glue::StringBridge::from_rust(col_name);
         let comment = if let Some(comment_) = comment {
             comment_
@@ -1346,6 +2762,7 @@ impl Table {
         direct: bool,
         undefined: bool,
     ) -> Result<(), TableError> {
+        self.clear_column_cache();
         let cname = glue::StringBridge::from_rust(col_name);
         let comment = if let Some(comment_) = comment {
             comment_
@@ -1386,6 +2803,68 @@ impl Table {
         Ok(())
     }
 
+    /// Add an array column stored with its own `TiledShapeStMan` data
+    /// manager.
+    ///
+    /// Unlike the other `add_*_column` methods, which leave the choice of
+    /// storage to casacore, this binds the new column to a tiled storage
+    /// manager named *dm_name*, which must not already be in use in the
+    /// table. This is how large data columns such as `MODEL_DATA` are
+    /// conventionally stored.
+    ///
+    /// If *shape* is given, the column has that fixed cell shape. Otherwise,
+    /// cell shapes may vary, but each cell has as many dimensions as
+    /// *tile_shape* has after its first element. *tile_shape* gives the
+    /// default shape of the tiles, with the number of rows per tile first and
+    /// the remaining dimensions in C order, as with cell shapes elsewhere in
+    /// this crate. Only boolean, floating-point, and complex columns are
+    /// supported.
+    pub fn add_tiled_array_column(
+        &mut self,
+        data_type: glue::GlueDataType,
+        col_name: &str,
+        shape: Option<&[u64]>,
+        tile_shape: &[u64],
+        dm_name: &str,
+    ) -> Result<(), TableError> {
+        let n_dims = tile_shape.len().saturating_sub(1);
+
+        let expected = shape.map_or(tile_shape.len().max(1), |dims| dims.len() + 1);
+
+        if tile_shape.len() != expected {
+            return Err(DimensionMismatchError {
+                expected,
+                actual: tile_shape.len(),
+            }
+            .into());
+        }
+
+        self.clear_column_cache();
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let cdm_name = glue::StringBridge::from_rust(dm_name);
+        let dims = shape.unwrap_or(&tile_shape[1..]);
+
+        let rv = unsafe {
+            glue::table_add_tiled_array_column(
+                self.handle,
+                data_type,
+                &ccol_name,
+                &cdm_name,
+                n_dims as std::os::raw::c_ulong,
+                dims.as_ptr(),
+                shape.is_some(),
+                tile_shape.as_ptr(),
+                &mut self.exc_info,
+            )
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        Ok(())
+    }
+
     /// Return all of the names of keywords whose values are type `TpTable`.
     pub fn table_keyword_names(&mut self) -> Result<Vec<String>, CasacoreError> {
         let mut result = Vec::new();
@@ -1451,6 +2930,47 @@ impl Table {
         Ok(())
     }
 
+    /// Define a keyword of type `TpTable` referring to the table stored at
+    /// *subtable_path*.
+    ///
+    /// A relative path is taken relative to the directory of this table, so
+    /// that a sub-table is usually referred to by its name alone. When the
+    /// sub-table lies inside this table's directory, casacore records the
+    /// reference relative to it, so that `::ANTENNA`-style references keep
+    /// working after the data set is moved or copied.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rubbl_casatables::ms;
+    /// use tempfile::tempdir;
+    ///
+    /// let tmp_dir = tempdir().unwrap();
+    /// let ms_path = tmp_dir.path().join("vis.ms");
+    ///
+    /// let mut main = ms::schema::MAIN.create(&ms_path, 0).unwrap();
+    /// ms::schema::ANTENNA.create(ms_path.join("ANTENNA"), 0).unwrap();
+    /// main.put_table_keyword_path("ANTENNA", "ANTENNA").unwrap();
+    ///
+    /// assert_eq!(main.table_keyword_names().unwrap(), ["ANTENNA"]);
+    /// ```
+    pub fn put_table_keyword_path<P: AsRef<Path>>(
+        &mut self,
+        kw_name: &str,
+        subtable_path: P,
+    ) -> Result<(), TableError> {
+        let subtable_path = subtable_path.as_ref();
+
+        let path = if subtable_path.is_relative() {
+            Path::new(&self.file_name()?).join(subtable_path)
+        } else {
+            subtable_path.to_owned()
+        };
+
+        let sub = Table::open(&path, TableOpenMode::Read)?;
+        Ok(self.put_table_keyword(kw_name, sub)?)
+    }
+
     // TODO: dedup from TableDesc::put_keyword
     /// Add a "keyword" to be associated with the table.
     ///
@@ -1506,6 +3026,8 @@ impl Table {
                 return self.exc_info.as_err();
             }
         } else {
+            let value = value.casatables_standard_layout();
+
             let rv = unsafe {
                 glue::table_put_keyword(
                     self.handle,
@@ -1585,6 +3107,8 @@ impl Table {
                 return self.exc_info.as_err();
             }
         } else {
+            let value = value.casatables_standard_layout();
+
             let rv = unsafe {
                 glue::table_put_column_keyword(
                     self.handle,
@@ -1697,6 +3221,17 @@ impl Table {
     pub fn get_col_as_vec<T: CasaScalarData>(
         &mut self,
         col_name: &str,
+    ) -> Result<Vec<T>, TableError> {
+        if let Some(result) = self.get_virtual_col_as_vec(col_name) {
+            return result;
+        }
+
+        self.with_retry(|t| t.get_col_as_vec_once(col_name))
+    }
+
+    fn get_col_as_vec_once<T: CasaScalarData>(
+        &mut self,
+        col_name: &str,
     ) -> Result<Vec<T>, TableError> {
         let ccol_name = glue::StringBridge::from_rust(col_name);
         let mut n_rows = 0;
@@ -1751,13 +3286,18 @@ impl Table {
             unsafe {
                 result.set_len(n_rows as usize);
             }
+
+            self.record_io(IoDirection::Get, col_name, n_rows, data_type, n_rows);
         } else {
+            let mut n_bytes = 0;
+
             let rv = unsafe {
                 invoke_table_get_scalar_column_data_string(
                     self.handle,
                     &ccol_name,
                     &mut self.exc_info,
                     |v| {
+                        n_bytes += v.len() as u64;
                         result.push(T::casatables_string_pass_through(v));
                     },
                 )
@@ -1766,13 +3306,31 @@ impl Table {
             if rv != 0 {
                 return self.exc_info.as_err();
             }
+
+            self.record_io_bytes(IoDirection::Get, col_name, n_rows, n_bytes);
         };
 
         Ok(result)
     }
 
     /// Get the value of one cell of the table.
+    ///
+    /// Array cells are returned in C order, with their axes in the reverse of
+    /// the order that casacore uses. Use [`Self::get_cell_with_order`] to
+    /// choose the order explicitly.
     pub fn get_cell<T: CasaDataType>(&mut self, col_name: &str, row: u64) -> Result<T, TableError> {
+        if let Some(result) = self.get_virtual_cell(col_name, row) {
+            return result;
+        }
+
+        self.with_retry(|t| t.get_cell_once(col_name, row))
+    }
+
+    fn get_cell_once<T: CasaDataType>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+    ) -> Result<T, TableError> {
         let ccol_name = glue::StringBridge::from_rust(col_name);
         let mut data_type = glue::GlueDataType::TpOther;
         let mut n_dim = 0;
@@ -1798,17 +3356,18 @@ impl Table {
             return Err(UnexpectedDataTypeError(T::DATA_TYPE, data_type).into());
         }
 
-        let result = if data_type != glue::GlueDataType::TpString {
-            let mut result =
-                T::casatables_alloc(&dims[..n_dim as usize]).map_err(|e| TableError::from(e))?;
+        let result = if data_type == glue::GlueDataType::TpString {
+            let mut value = None;
 
             let rv = unsafe {
-                glue::table_get_cell(
+                invoke_table_get_cell_string(
                     self.handle,
                     &ccol_name,
                     row,
-                    result.casatables_as_mut_buf() as _,
                     &mut self.exc_info,
+                    |v| {
+                        value = Some(v);
+                    },
                 )
             };
 
@@ -1816,18 +3375,20 @@ impl Table {
                 return self.exc_info.as_err();
             }
 
-            result
-        } else {
-            let mut value = None;
+            let value = value.unwrap();
+            self.record_io_bytes(IoDirection::Get, col_name, 1, value.len() as u64);
+            T::casatables_string_pass_through(value)
+        } else if data_type == glue::GlueDataType::TpArrayString {
+            let mut result = Vec::new();
 
             let rv = unsafe {
-                invoke_table_get_cell_string(
+                invoke_table_get_cell_string_array(
                     self.handle,
                     &ccol_name,
                     row,
                     &mut self.exc_info,
                     |v| {
-                        value = Some(v);
+                        result.push(v);
                     },
                 )
             };
@@ -1836,7 +3397,29 @@ impl Table {
                 return self.exc_info.as_err();
             }
 
-            T::casatables_string_pass_through(value.unwrap())
+            let n_bytes = result.iter().map(|v| v.len() as u64).sum();
+            self.record_io_bytes(IoDirection::Get, col_name, 1, n_bytes);
+            T::casatables_stringvec_pass_through(result)
+        } else {
+            let mut result = T::casatables_alloc(&dims[..n_dim as usize])?;
+
+            let rv = unsafe {
+                glue::table_get_cell(
+                    self.handle,
+                    &ccol_name,
+                    row,
+                    result.casatables_as_mut_buf() as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+
+            let n_items = dims[..n_dim as usize].iter().product();
+            self.record_io(IoDirection::Get, col_name, 1, data_type, n_items);
+            result
         };
 
         Ok(result)
@@ -1849,6 +3432,18 @@ impl Table {
         &mut self,
         col_name: &str,
         row: u64,
+    ) -> Result<Vec<T>, TableError> {
+        if let Some(result) = self.get_virtual_cell_as_vec(col_name, row) {
+            return result;
+        }
+
+        self.with_retry(|t| t.get_cell_as_vec_once(col_name, row))
+    }
+
+    fn get_cell_as_vec_once<T: CasaScalarData>(
+        &mut self,
+        col_name: &str,
+        row: u64,
     ) -> Result<Vec<T>, TableError> {
         let ccol_name = glue::StringBridge::from_rust(col_name);
         let mut data_type = glue::GlueDataType::TpOther;
@@ -1871,8 +3466,8 @@ impl Table {
             return self.exc_info.as_err();
         }
 
-        if data_type != T::DATA_TYPE {
-            return Err(UnexpectedDataTypeError(T::DATA_TYPE, data_type).into());
+        if data_type != T::VECTOR_TYPE {
+            return Err(UnexpectedDataTypeError(T::VECTOR_TYPE, data_type).into());
         }
 
         let n_items = dims[..n_dim as usize]
@@ -1881,7 +3476,7 @@ impl Table {
 
         let mut result = Vec::<T>::with_capacity(n_items);
 
-        if data_type != glue::GlueDataType::TpString {
+        if data_type != glue::GlueDataType::TpArrayString {
             let rv = unsafe {
                 glue::table_get_cell(
                     self.handle,
@@ -1899,7 +3494,11 @@ impl Table {
             unsafe {
                 result.set_len(n_items as usize);
             }
+
+            self.record_io(IoDirection::Get, col_name, 1, data_type, n_items as u64);
         } else {
+            let mut n_bytes = 0;
+
             let rv = unsafe {
                 invoke_table_get_cell_string_array(
                     self.handle,
@@ -1907,6 +3506,7 @@ impl Table {
                     row,
                     &mut self.exc_info,
                     |v| {
+                        n_bytes += v.len() as u64;
                         result.push(T::casatables_string_pass_through(v));
                     },
                 )
@@ -1915,6 +3515,8 @@ impl Table {
             if rv != 0 {
                 return self.exc_info.as_err();
             }
+
+            self.record_io_bytes(IoDirection::Get, col_name, 1, n_bytes);
         }
 
         Ok(result)
@@ -1932,6 +3534,11 @@ impl Table {
 
         value.casatables_put_shape(&mut shape);
 
+        let n_bytes = match self.io_stats {
+            Some(_) => io_stats::cell_bytes(value, &shape),
+            None => 0,
+        };
+
         if T::DATA_TYPE == glue::GlueDataType::TpString {
             let as_string = T::casatables_string_pass_through_out(value);
             let glue_string = glue::StringBridge::from_rust(&as_string);
@@ -1972,6 +3579,8 @@ impl Table {
                 return self.exc_info.as_err();
             }
         } else {
+            let value = value.casatables_standard_layout();
+
             let rv = unsafe {
                 glue::table_put_cell(
                     self.handle,
@@ -1990,253 +3599,211 @@ impl Table {
             }
         }
 
+        self.record_io_bytes(IoDirection::Put, col_name, 1, n_bytes);
         Ok(())
     }
 
-    /// Add additional, empty rows to the table.
-    pub fn add_rows(&mut self, n_rows: usize) -> Result<(), CasacoreError> {
-        if unsafe { glue::table_add_rows(self.handle, n_rows as u64, &mut self.exc_info) != 0 } {
-            self.exc_info.as_err()
-        } else {
-            Ok(())
-        }
-    }
-
-    fn get_row_handle(&mut self, is_read_only: bool) -> Result<TableRow, CasacoreError> {
-        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
-        let ro_flag = if is_read_only { 1 } else { 0 };
-
-        let handle = unsafe { glue::table_row_alloc(self.handle, ro_flag, &mut exc_info) };
-        if handle.is_null() {
-            return exc_info.as_err();
-        }
-
-        Ok(TableRow { handle, exc_info })
-    }
-
-    /// Get an object for read-only access to individual rows of the table.
+    /// Write a single cell of the table, reusing a column handle across
+    /// calls.
     ///
-    /// The row reader can be used to iterate through the rows of the table
-    /// efficiently.
+    /// This behaves like [`Self::put_cell`], but the first write to each
+    /// column sets up a handle that is kept for subsequent writes, avoiding
+    /// the cost of looking up the column every time. This makes a substantial
+    /// difference when writing many small cells, such as when filling a table
+    /// row by row.
     ///
-    /// See also [`Self::get_row_writer`].
-    pub fn get_row_reader(&mut self) -> Result<TableRow, CasacoreError> {
-        self.get_row_handle(true)
-    }
-
-    /// Get an object for read-write access to individual rows of the table.
+    /// The cached handles are discarded whenever the set of columns is changed
+    /// through this [`Table`] — by [`Self::add_scalar_column`],
+    /// [`Self::add_array_column`], [`Self::remove_column`], or
+    /// [`Self::rename_column`] — and can be discarded manually with
+    /// [`Self::clear_column_cache`].
     ///
-    /// The row writer can be used to iterate through the rows of the table
-    /// efficiently.
+    /// # Errors
     ///
-    /// See also [`Self::get_row_reader`].
-    pub fn get_row_writer(&mut self) -> Result<TableRow, CasacoreError> {
-        self.get_row_handle(false)
-    }
+    /// Can raise [`UnexpectedDataTypeError`] if the type of `value` does not
+    /// match the column exactly, and [`CasacoreError`] if there was an issue
+    /// invoking casacore.
+    pub fn put_cell_cached<T: CasaDataType>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+        value: &T,
+    ) -> Result<(), TableError> {
+        let generation = self.schema_generation;
+        let stale = match self.column_cache.get(col_name) {
+            Some(col) => col.generation != generation,
+            None => true,
+        };
 
-    /// Populate a [`TableRow`] accessor object with data from the specified
-    /// row.
-    pub fn read_row(&mut self, row: &mut TableRow, row_number: u64) -> Result<(), TableError> {
-        if unsafe { glue::table_row_read(row.handle, row_number, &mut row.exc_info) } != 0 {
-            return row.exc_info.as_err();
-        }
+        if stale {
+            let ccol_name = glue::StringBridge::from_rust(col_name);
+            let mut data_type = glue::GlueDataType::TpOther;
 
-        Ok(())
-    }
+            let handle = unsafe {
+                glue::column_alloc(self.handle, &ccol_name, &mut data_type, &mut self.exc_info)
+            };
 
-    /// Perform `func` on each row of the table.
-    pub fn for_each_row<F>(&mut self, mut func: F) -> Result<(), TableError>
-    where
-        F: FnMut(&mut TableRow) -> Result<(), TableError>,
-    {
-        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+            if handle.is_null() {
+                return self.exc_info.as_err();
+            }
 
-        let handle = unsafe { glue::table_row_alloc(self.handle, 1, &mut exc_info) };
-        if handle.is_null() {
-            return exc_info.as_err();
+            self.column_cache.insert(
+                col_name.to_owned(),
+                CachedColumn {
+                    handle,
+                    data_type,
+                    generation,
+                },
+            );
         }
 
-        let mut row = TableRow { handle, exc_info };
-
-        for row_number in 0..self.n_rows() {
-            if unsafe { glue::table_row_read(row.handle, row_number as u64, &mut row.exc_info) }
-                != 0
-            {
-                return row.exc_info.as_err();
-            }
+        let col = self.column_cache.get_mut(col_name).unwrap();
 
-            func(&mut row)?;
+        if col.data_type != T::DATA_TYPE {
+            return Err(UnexpectedDataTypeError(T::DATA_TYPE, col.data_type).into());
         }
 
-        Ok(())
-    }
+        let mut shape = Vec::new();
+        value.casatables_put_shape(&mut shape);
 
-    /// Perform `func` on each row in the range `row_range`.
-    pub fn for_each_row_in_range<F>(
-        &mut self,
-        row_range: std::ops::Range<u64>,
-        mut func: F,
-    ) -> Result<(), TableError>
-    where
-        F: FnMut(&mut TableRow) -> Result<(), TableError>,
-    {
-        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+        let n_bytes = match self.io_stats {
+            Some(_) => io_stats::cell_bytes(value, &shape),
+            None => 0,
+        };
 
-        let handle = unsafe { glue::table_row_alloc(self.handle, 1, &mut exc_info) };
-        if handle.is_null() {
-            return exc_info.as_err();
-        }
+        let rv = if T::DATA_TYPE == glue::GlueDataType::TpString {
+            let as_string = T::casatables_string_pass_through_out(value);
+            let glue_string = glue::StringBridge::from_rust(&as_string);
 
-        let mut row = TableRow {
-            handle: handle,
-            exc_info: exc_info,
-        };
+            unsafe {
+                glue::column_put_cell(
+                    col.handle,
+                    row,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    &glue_string as *const glue::StringBridge as _,
+                    &mut self.exc_info,
+                )
+            }
+        } else if T::DATA_TYPE == glue::GlueDataType::TpArrayString {
+            let glue_strings = T::casatables_stringvec_pass_through_out(value);
 
-        for row_number in row_range {
-            if unsafe { glue::table_row_read(row.handle, row_number as u64, &mut row.exc_info) }
-                != 0
-            {
-                return row.exc_info.as_err();
+            unsafe {
+                glue::column_put_cell(
+                    col.handle,
+                    row,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    glue_strings.as_ptr() as _,
+                    &mut self.exc_info,
+                )
             }
+        } else {
+            let value = value.casatables_standard_layout();
 
-            func(&mut row)?;
+            unsafe {
+                glue::column_put_cell(
+                    col.handle,
+                    row,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    value.casatables_as_buf() as _,
+                    &mut self.exc_info,
+                )
+            }
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
         }
 
+        self.record_io_bytes(IoDirection::Put, col_name, 1, n_bytes);
         Ok(())
     }
 
-    /// Perform `func` on each row indicated by `rows`.
-    pub fn for_each_specific_row<F>(&mut self, rows: &[u64], mut func: F) -> Result<(), TableError>
-    where
-        F: FnMut(&mut TableRow) -> Result<(), TableError>,
-    {
-        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
-
-        let handle = unsafe { glue::table_row_alloc(self.handle, 1, &mut exc_info) };
-        if handle.is_null() {
-            return exc_info.as_err();
-        }
-
-        let mut row = TableRow {
-            handle: handle,
-            exc_info: exc_info,
-        };
-
-        for &row_number in rows {
-            if unsafe { glue::table_row_read(row.handle, row_number as u64, &mut row.exc_info) }
-                != 0
-            {
-                return row.exc_info.as_err();
-            }
-
-            func(&mut row)?;
-        }
+    /// Discard the column handles retained by [`Self::put_cell_cached`].
+    ///
+    /// This happens automatically when columns are added, removed, or renamed
+    /// through this [`Table`]. If the columns might have been changed by other
+    /// means, such as through a different [`Table`] handle to the same data
+    /// set, call this function before writing with [`Self::put_cell_cached`]
+    /// again.
+    pub fn clear_column_cache(&mut self) {
+        self.column_cache.clear();
+        self.schema_generation += 1;
+    }
 
-        Ok(())
+    /// Get a counter that increases every time the set of columns of this
+    /// table changes.
+    ///
+    /// Code that caches information about the columns of a table can record
+    /// this value and compare it later to learn whether the cached information
+    /// may have gone stale. Only changes made through this [`Table`] are
+    /// tracked; [`Self::clear_column_cache`] also increments the counter.
+    pub fn schema_generation(&self) -> u64 {
+        self.schema_generation
     }
 
-    /// Copy all rows from this table to another table.
-    pub fn copy_rows_to(&mut self, dest: &mut Table) -> Result<(), CasacoreError> {
-        if unsafe { glue::table_copy_rows(self.handle, dest.handle, &mut self.exc_info) != 0 } {
+    /// Add additional, empty rows to the table.
+    pub fn add_rows(&mut self, n_rows: usize) -> Result<(), CasacoreError> {
+        if unsafe { glue::table_add_rows(self.handle, n_rows as u64, &mut self.exc_info) != 0 } {
             self.exc_info.as_err()
         } else {
             Ok(())
         }
     }
 
-    /// Copy the "description" of this table to a new filesystem path, without
-    /// copying any of the actual data contents.
-    pub fn deep_copy_no_rows(&mut self, dest_path: &str) -> Result<(), CasacoreError> {
-        let cdest_path = glue::StringBridge::from_rust(dest_path);
-
+    /// Remove *n_rows* rows from the table, starting at *start_row*.
+    ///
+    /// Rows after the removed ones are renumbered to close the gap.
+    pub fn remove_rows(&mut self, start_row: u64, n_rows: usize) -> Result<(), CasacoreError> {
         if unsafe {
-            glue::table_deep_copy_no_rows(self.handle, &cdest_path, &mut self.exc_info) != 0
+            glue::table_remove_rows(self.handle, start_row, n_rows as u64, &mut self.exc_info) != 0
         } {
             self.exc_info.as_err()
         } else {
             Ok(())
         }
     }
-}
-
-impl Debug for Table {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Table")
-            .field("handle", &self.handle)
-            .field("name", &self.file_name().unwrap())
-            .finish()
-    }
-}
-
-impl Drop for Table {
-    fn drop(&mut self) {
-        // FIXME: not sure if this function can actually produce useful
-        // exceptions anyway, but we can't do anything if it does!
-        unsafe { glue::table_close_and_free(self.handle, &mut self.exc_info) }
-    }
-}
-
-/// Information describing the properties of a particular column of a table.
-#[derive(PartialEq, Eq, Debug)]
-pub struct ColumnDescription {
-    name: String,
-    data_type: glue::GlueDataType,
-    is_scalar: bool,
-    is_fixed_shape: bool,
-    shape: Option<Vec<u64>>,
-    keywords: TableRecord,
-}
-
-impl ColumnDescription {
-    /// Get the name of the column.
-    pub fn name(&self) -> &str {
-        &self.name
-    }
-
-    /// Get the underlying CASA data type of the column.
-    pub fn data_type(&self) -> glue::GlueDataType {
-        self.data_type
-    }
-
-    /// Get whether the column is scalar-valued, as opposed to vector-valued.
-    pub fn is_scalar(&self) -> bool {
-        self.is_scalar
-    }
-
-    /// Get whether the column is vector-valued with a fixed array shape for
-    /// each cell.
-    pub fn is_fixed_shape(&self) -> bool {
-        self.is_fixed_shape
-    }
 
-    /// If the column is vector-valued with a fixed shape for each cell, get the
-    /// required shape.
-    pub fn shape(&self) -> Option<&[u64]> {
-        self.shape.as_ref().map(|v| &v[..])
+    /// Write any buffered changes to the table out to disk.
+    ///
+    /// This happens automatically when the table is closed; flushing
+    /// explicitly makes the data written so far visible to other processes
+    /// that open the table. Does nothing if the table is not writable.
+    pub fn flush(&mut self) -> Result<(), CasacoreError> {
+        if unsafe { glue::table_flush(self.handle, &mut self.exc_info) != 0 } {
+            self.exc_info.as_err()
+        } else {
+            Ok(())
+        }
     }
-}
-
-// Table Row handles
-
-/// A type for examining individual rows of a CASA table.
-pub struct TableRow {
-    handle: *mut glue::GlueTableRow,
-    exc_info: glue::ExcInfo,
-}
 
-impl TableRow {
-    /// Get the value of a specific cell in this row.
-    pub fn get_cell<T: CasaDataType>(&mut self, col_name: &str) -> Result<T, TableError> {
+    /// Check that a column can be used for bulk I/O with elements of type `T`,
+    /// returning the shape of its cells.
+    fn bulk_column_cell_shape<T: CasaScalarData>(
+        &mut self,
+        col_name: &str,
+    ) -> Result<Vec<usize>, TableError> {
         let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut n_rows = 0;
         let mut data_type = glue::GlueDataType::TpOther;
+        let mut is_scalar = 0;
+        let mut is_fixed_shape = 0;
         let mut n_dim = 0;
         let mut dims = [0; 8];
 
         let rv = unsafe {
-            glue::table_row_get_cell_info(
+            glue::table_get_column_info(
                 self.handle,
                 &ccol_name,
+                &mut n_rows,
                 &mut data_type,
+                &mut is_scalar,
+                &mut is_fixed_shape,
                 &mut n_dim,
                 dims.as_mut_ptr(),
                 &mut self.exc_info,
@@ -2251,273 +3818,687 @@ impl TableRow {
             return Err(UnexpectedDataTypeError(T::DATA_TYPE, data_type).into());
         }
 
-        let result = if data_type == glue::GlueDataType::TpString {
-            let mut value = None;
-
-            let rv = unsafe {
-                invoke_table_row_get_cell_string(self.handle, &ccol_name, &mut self.exc_info, |v| {
-                    value = Some(v);
-                })
-            };
-
-            if rv != 0 {
-                return self.exc_info.as_err();
-            }
+        if is_fixed_shape == 0 || n_dim < 0 {
+            return self.uniform_cell_shape(col_name, n_rows);
+        }
 
-            T::casatables_string_pass_through(value.unwrap())
-        } else if data_type == glue::GlueDataType::TpArrayString {
-            let mut result = Vec::new();
+        Ok(dims[..n_dim as usize].iter().map(|d| *d as usize).collect())
+    }
 
-            let rv = unsafe {
-                invoke_table_row_get_cell_string_array(
-                    self.handle,
-                    &ccol_name,
-                    &mut self.exc_info,
-                    |v| {
-                        result.push(v);
-                    },
-                )
-            };
+    /// Get the shape of the cells of a variable-shape column for bulk I/O.
+    ///
+    /// Such a column can be read and written in bulk as long as all of the
+    /// cells involved have the same shape, which is taken from the first
+    /// row. casacore checks the shapes of the other cells as it goes.
+    fn uniform_cell_shape(
+        &mut self,
+        col_name: &str,
+        n_rows: u64,
+    ) -> Result<Vec<usize>, TableError> {
+        let shape = if n_rows > 0 {
+            self.get_cell_shape(col_name, 0)?
+        } else {
+            Vec::new()
+        };
 
-            if rv != 0 {
-                return self.exc_info.as_err();
-            }
+        if shape.is_empty() {
+            return Err(TableError::NotFixedShapeColumnError(col_name.to_owned()));
+        }
 
-            T::casatables_stringvec_pass_through(result)
-        } else {
-            let mut result = T::casatables_alloc(&dims[..n_dim as usize])?;
+        Ok(shape)
+    }
 
-            let rv = unsafe {
-                glue::table_row_get_cell(
-                    self.handle,
-                    &ccol_name,
-                    result.casatables_as_mut_buf() as _,
-                    &mut self.exc_info,
-                )
-            };
+    /// Read a column in chunks of rows, streaming its data into *sink*.
+    ///
+    /// The column must be scalar or have cells that all have the same shape,
+    /// either because it is a fixed-shape column or because every cell has
+    /// the shape of the first one. Each item of the stream is the cell of one
+    /// row, so that each chunk passed to *sink* has shape
+    /// `[n, cell_shape...]`, where *n* is at most *rows_per_chunk*. Only one
+    /// chunk’s worth of data is held in memory at a time.
+    pub fn read_column_chunks<T, S>(
+        &mut self,
+        col_name: &str,
+        rows_per_chunk: usize,
+        sink: &mut S,
+    ) -> Result<(), TableError>
+    where
+        T: CasaScalarData + Copy + Default,
+        S: ArrayChunkSink<T>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.read_column_chunks_with_progress(col_name, rows_per_chunk, sink, &Progress::new())
+    }
 
-            if rv != 0 {
-                return self.exc_info.as_err();
-            }
+    /// Read a column in chunks of rows, reporting progress and allowing
+    /// cancellation.
+    ///
+    /// This works like [`Self::read_column_chunks`], except that *progress*
+    /// is told the number of rows read after each chunk, and the read stops
+    /// with [`TableError::Cancelled`] before the next chunk if cancellation
+    /// is requested.
+    pub fn read_column_chunks_with_progress<T, S>(
+        &mut self,
+        col_name: &str,
+        rows_per_chunk: usize,
+        sink: &mut S,
+        progress: &Progress,
+    ) -> Result<(), TableError>
+    where
+        T: CasaScalarData + Copy + Default,
+        S: ArrayChunkSink<T>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let cell_shape = self.bulk_column_cell_shape::<T>(col_name)?;
+        let cell_len: usize = cell_shape.iter().product();
+        let rows_per_chunk = rows_per_chunk.max(1);
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut buf = vec![T::default(); rows_per_chunk * cell_len];
+        let mut shape = vec![0];
+        shape.extend_from_slice(&cell_shape[..]);
+
+        let n_rows = self.n_rows();
+        let mut row = 0;
+
+        while row < n_rows {
+            progress.check(row)?;
+            let n = (rows_per_chunk as u64).min(n_rows - row);
+            let data = &mut buf[..n as usize * cell_len];
+
+            self.with_retry(|t| {
+                if unsafe {
+                    glue::table_get_column_range(
+                        t.handle,
+                        &ccol_name,
+                        row,
+                        n,
+                        data.as_mut_ptr() as _,
+                        &mut t.exc_info,
+                    )
+                } != 0
+                {
+                    return t.exc_info.as_err();
+                }
 
-            result
-        };
+                Ok(())
+            })?;
+
+            self.record_io(
+                IoDirection::Get,
+                col_name,
+                n,
+                T::DATA_TYPE,
+                data.len() as u64,
+            );
+            shape[0] = n as usize;
+            let chunk = ndarray::ArrayViewD::from_shape(&shape[..], data).unwrap();
+            sink.write_chunk(chunk)
+                .map_err(|e| TableError::ChunkStream(Box::new(e)))?;
+            row += n;
+            progress.report(row, Some(n_rows));
+        }
 
-        Ok(result)
+        Ok(())
     }
 
-    /// Write a new value for a specific cell in this row.
+    /// Read a column in chunks of rows, passing only rows that satisfy a
+    /// predicate on the Measurement Set index columns into *sink*.
     ///
-    /// Note: I am not sure if this function actually works! `Table.put_cell`
-    /// does work. Investigation required.
-    pub fn put_cell<T: CasaDataType>(
+    /// This works like [`Self::read_column_chunks`], except that for each
+    /// chunk of rows, the index columns (`TIME`, `ANTENNA1`, `ANTENNA2`,
+    /// `DATA_DESC_ID`, and `FIELD_ID`) are read first and *predicate* is
+    /// evaluated on each row. Only the cells of the rows for which it returns
+    /// true are read from *col_name*, so that rows that are rejected — such
+    /// as autocorrelations — never have their data decoded. Chunks passed to
+    /// *sink* may therefore have fewer than *rows_per_chunk* items, and empty
+    /// chunks are skipped. Returns the number of rows passed to *sink*.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{Complex, Table, TableOpenMode};
+    /// use rubbl_core::chunked::ArrayCollector;
+    ///
+    /// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+    /// let mut cross = ArrayCollector::<Complex<f32>>::new(&[64, 4]);
+    /// t.read_column_chunks_where("DATA", 1024, |row| row.antenna1 != row.antenna2, &mut cross)
+    ///     .unwrap();
+    /// ```
+    pub fn read_column_chunks_where<T, F, S>(
         &mut self,
         col_name: &str,
-        value: &T,
-    ) -> Result<(), CasacoreError> {
+        rows_per_chunk: usize,
+        mut predicate: F,
+        sink: &mut S,
+    ) -> Result<u64, TableError>
+    where
+        T: CasaScalarData + Copy + Default,
+        F: FnMut(&IndexRow) -> bool,
+        S: ArrayChunkSink<T>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let cell_shape = self.bulk_column_cell_shape::<T>(col_name)?;
+        let cell_len: usize = cell_shape.iter().product();
+        let rows_per_chunk = rows_per_chunk.max(1);
         let ccol_name = glue::StringBridge::from_rust(col_name);
-        let mut shape = Vec::new();
+        let mut buf = vec![T::default(); rows_per_chunk * cell_len];
+        let mut shape = vec![0];
+        shape.extend_from_slice(&cell_shape[..]);
+
+        let mut index = IndexChunk::new(self, rows_per_chunk)?;
+        let mut selected: Vec<std::os::raw::c_ulong> = Vec::with_capacity(rows_per_chunk);
+        let n_rows = self.n_rows();
+        let mut row = 0;
+        let mut n_passed = 0;
+
+        while row < n_rows {
+            let n = (rows_per_chunk as u64).min(n_rows - row);
+            index.read(self, row, n as usize)?;
+            selected.clear();
+
+            for i in 0..n as usize {
+                if predicate(&index.row(row, i)) {
+                    selected.push((row + i as u64) as _);
+                }
+            }
 
-        value.casatables_put_shape(&mut shape);
+            row += n;
 
-        if T::DATA_TYPE == glue::GlueDataType::TpString {
-            let as_string = T::casatables_string_pass_through_out(value);
-            let glue_string = glue::StringBridge::from_rust(&as_string);
+            if selected.is_empty() {
+                continue;
+            }
 
-            let rv = unsafe {
-                glue::table_row_put_cell(
-                    self.handle,
-                    &ccol_name,
-                    T::DATA_TYPE,
-                    shape.len() as u64,
-                    shape.as_ptr(),
-                    &glue_string as *const glue::StringBridge as _,
-                    &mut self.exc_info,
-                )
+            let data = &mut buf[..selected.len() * cell_len];
+
+            // If every row is wanted, a range read is cheaper.
+            let rv = if selected.len() as u64 == n {
+                unsafe {
+                    glue::table_get_column_range(
+                        self.handle,
+                        &ccol_name,
+                        row - n,
+                        n,
+                        data.as_mut_ptr() as _,
+                        &mut self.exc_info,
+                    )
+                }
+            } else {
+                unsafe {
+                    glue::table_get_column_cells(
+                        self.handle,
+                        &ccol_name,
+                        selected.as_ptr(),
+                        selected.len() as _,
+                        data.as_mut_ptr() as _,
+                        &mut self.exc_info,
+                    )
+                }
             };
 
             if rv != 0 {
                 return self.exc_info.as_err();
             }
-        } else if T::DATA_TYPE == glue::GlueDataType::TpArrayString {
-            let glue_strings = T::casatables_stringvec_pass_through_out(value);
 
-            let rv = unsafe {
-                glue::table_row_put_cell(
-                    self.handle,
-                    &ccol_name,
-                    T::DATA_TYPE,
-                    shape.len() as u64,
-                    shape.as_ptr(),
-                    glue_strings.as_ptr() as _,
-                    &mut self.exc_info,
-                )
-            };
+            self.record_io(
+                IoDirection::Get,
+                col_name,
+                selected.len() as u64,
+                T::DATA_TYPE,
+                data.len() as u64,
+            );
+            shape[0] = selected.len();
+            let chunk = ndarray::ArrayViewD::from_shape(&shape[..], data).unwrap();
+            sink.write_chunk(chunk)
+                .map_err(|e| TableError::ChunkStream(Box::new(e)))?;
+            n_passed += selected.len() as u64;
+        }
 
-            if rv != 0 {
-                return self.exc_info.as_err();
-            }
-        } else {
-            let rv = unsafe {
-                glue::table_row_put_cell(
-                    self.handle,
-                    &ccol_name,
-                    T::DATA_TYPE,
-                    shape.len() as u64,
-                    shape.as_ptr(),
-                    value.casatables_as_buf() as _,
-                    &mut self.exc_info,
-                )
-            };
+        Ok(n_passed)
+    }
 
-            if rv != 0 {
-                return self.exc_info.as_err();
+    /// Scan the Measurement Set index columns of every row of the table.
+    ///
+    /// The index columns (`TIME`, `ANTENNA1`, `ANTENNA2`, `DATA_DESC_ID`, and
+    /// `FIELD_ID`) are read in chunks of *rows_per_chunk* rows and *func* is
+    /// called on each row in order. This is a cheap way to build row indices
+    /// or check the layout of a table, since no other columns are read.
+    pub fn for_each_index_row<F>(
+        &mut self,
+        rows_per_chunk: usize,
+        mut func: F,
+    ) -> Result<(), TableError>
+    where
+        F: FnMut(&IndexRow),
+    {
+        let rows_per_chunk = rows_per_chunk.max(1);
+        let mut index = IndexChunk::new(self, rows_per_chunk)?;
+        let n_rows = self.n_rows();
+        let mut row = 0;
+
+        while row < n_rows {
+            let n = (rows_per_chunk as u64).min(n_rows - row);
+            index.read(self, row, n as usize)?;
+
+            for i in 0..n as usize {
+                func(&index.row(row, i));
             }
+
+            row += n;
         }
 
         Ok(())
     }
 
-    /// Copy the data associated with this row accessor to another one, and then
-    /// write the data to the specifed row of that other accessor's associated
-    /// table.
-    pub fn copy_and_put(
+    /// Read a range of rows of a scalar column into *data*, which must have
+    /// one element per row. The column type must already have been checked.
+    fn read_scalar_range<T: CasaScalarData>(
         &mut self,
-        dest: &mut TableRow,
-        row_number: u64,
-    ) -> Result<(), CasacoreError> {
-        let rv = unsafe {
-            glue::table_row_copy_and_put(self.handle, row_number, dest.handle, &mut self.exc_info)
-        };
+        col_name: &glue::StringBridge,
+        start_row: u64,
+        data: &mut [T],
+    ) -> Result<(), TableError> {
+        self.read_cells_range(col_name, start_row, data.len(), data)
+    }
 
-        if rv != 0 {
+    /// Read *n_rows* rows of a column, starting at *start_row*, into *data*,
+    /// which must have room for exactly that many cells. Unless the column
+    /// is scalar or fixed-shape, all of the cells must have the shape of the
+    /// first one. The column type must already have been checked.
+    pub(crate) fn read_cells_range<T: CasaScalarData>(
+        &mut self,
+        col_name: &glue::StringBridge,
+        start_row: u64,
+        n_rows: usize,
+        data: &mut [T],
+    ) -> Result<(), TableError> {
+        if unsafe {
+            glue::table_get_column_range(
+                self.handle,
+                col_name,
+                start_row,
+                n_rows as u64,
+                data.as_mut_ptr() as _,
+                &mut self.exc_info,
+            )
+        } != 0
+        {
             return self.exc_info.as_err();
         }
 
         Ok(())
     }
 
-    /// Write the data currently associated with this row accessor to the
-    /// specified row of its associated table.
-    pub fn put(&mut self, row_number: u64) -> Result<(), CasacoreError> {
-        let rv = unsafe { glue::table_row_write(self.handle, row_number, &mut self.exc_info) };
+    /// Read the cells of the listed rows of a column into *data*, which must
+    /// have room for exactly that many cells. Unless the column is scalar or
+    /// fixed-shape, all of the cells must have the shape of the first one
+    /// listed.
+    pub(crate) fn read_scalar_rows<T: CasaScalarData>(
+        &mut self,
+        col_name: &glue::StringBridge,
+        rows: &[u64],
+        data: &mut [T],
+    ) -> Result<(), TableError> {
+        let rows: Vec<std::os::raw::c_ulong> = rows.iter().map(|r| *r as _).collect();
 
-        if rv != 0 {
+        if unsafe {
+            glue::table_get_column_cells(
+                self.handle,
+                col_name,
+                rows.as_ptr(),
+                rows.len() as _,
+                data.as_mut_ptr() as _,
+                &mut self.exc_info,
+            )
+        } != 0
+        {
             return self.exc_info.as_err();
         }
 
         Ok(())
     }
-}
 
-impl Drop for TableRow {
-    fn drop(&mut self) {
-        // FIXME: not sure if this function can actually produce useful
-        // exceptions anyway, but we can't do anything if it does!
-        unsafe {
-            glue::table_row_free(self.handle, &mut self.exc_info);
-        }
+    /// Write a column in chunks of rows, streaming its data from *source*.
+    ///
+    /// The column must be scalar or have a fixed shape, and the item shape of
+    /// *source* must match the shape of the column’s cells. Data are written
+    /// starting at row *start_row* and continue until *source* is exhausted,
+    /// with rows added to the table as needed. At most *rows_per_chunk* rows
+    /// of data are held in memory at a time. Returns the number of rows
+    /// written.
+    pub fn write_column_chunks<T, S>(
+        &mut self,
+        col_name: &str,
+        start_row: u64,
+        rows_per_chunk: usize,
+        source: &mut S,
+    ) -> Result<u64, TableError>
+    where
+        T: CasaScalarData + Copy + Default,
+        S: ArrayChunkSource<T>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.write_column_chunks_with_progress(
+            col_name,
+            start_row,
+            rows_per_chunk,
+            source,
+            &Progress::new(),
+        )
     }
-}
-
-/// A dictionary-like data structured that can be stored in a CASA table.
-///
-/// FIXME: currently, to avoid potential double-frees, TableRecords are only
-/// created standalone, or as copies of TableRecords owned by something else.
-///
-/// Ideally, there would be a lot of utility in having rewritable TableRecords,
-/// however this would require keeping track of whether a TableRecord is owned,
-/// and not freeing owned TableRecords.
-///
-/// Further discussion [here][x].
-///
-/// [x]: https://github.com/pkgw/rubbl/pull/181#issuecomment-968493738
-#[derive(Clone)]
-pub struct TableRecord {
-    handle: *mut glue::GlueTableRecord,
-    exc_info: glue::ExcInfo,
-}
 
-impl TableRecord {
-    /// Create a new, empty [`TableRecord`].
+    /// Write a column in chunks of rows, reporting progress and allowing
+    /// cancellation.
     ///
-    /// This function will return an error if the underlying C++ code raises an
-    /// exception. This *should* only happen if there is a memory allocation
-    /// error.
-    pub fn new() -> Result<Self, TableError> {
-        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+    /// This works like [`Self::write_column_chunks`], except that *progress*
+    /// is told the number of rows written after each chunk, and the write
+    /// stops with [`TableError::Cancelled`] before the next chunk is taken
+    /// from *source* if cancellation is requested. Since the length of the
+    /// stream is not known in advance, no total is reported.
+    pub fn write_column_chunks_with_progress<T, S>(
+        &mut self,
+        col_name: &str,
+        start_row: u64,
+        rows_per_chunk: usize,
+        source: &mut S,
+        progress: &Progress,
+    ) -> Result<u64, TableError>
+    where
+        T: CasaScalarData + Copy + Default,
+        S: ArrayChunkSource<T>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let cell_shape = self.bulk_column_cell_shape::<T>(col_name)?;
 
-        let handle = unsafe { glue::tablerec_create(&mut exc_info) };
-        if handle.is_null() {
-            return exc_info.as_err();
+        if source.item_shape() != &cell_shape[..] {
+            return Err(TableError::ChunkShapeMismatch(
+                cell_shape,
+                source.item_shape().to_vec(),
+            ));
         }
 
-        Ok(Self { handle, exc_info })
-    }
+        let cell_len: usize = cell_shape.iter().product();
+        let rows_per_chunk = rows_per_chunk.max(1);
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut buf = vec![T::default(); rows_per_chunk * cell_len];
+        let mut shape = vec![rows_per_chunk];
+        shape.extend_from_slice(&cell_shape[..]);
 
-    fn copy_handle(other_handle: &glue::GlueTableRecord) -> Result<Self, CasacoreError> {
-        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+        let mut row = start_row;
 
-        let handle = unsafe { glue::tablerec_copy(other_handle, &mut exc_info) };
-        if handle.is_null() {
-            return exc_info.as_err();
-        }
+        loop {
+            progress.check(row - start_row)?;
+            let dest = ndarray::ArrayViewMutD::from_shape(&shape[..], &mut buf[..]).unwrap();
+            let n = source
+                .read_chunk(dest)
+                .map_err(|e| TableError::ChunkStream(Box::new(e)))? as u64;
 
-        Ok(Self { handle, exc_info })
-    }
+            if n == 0 {
+                break;
+            }
 
-    /// Return all of the keyword names in the record.
-    pub fn keyword_names(&mut self) -> Result<Vec<String>, CasacoreError> {
-        let mut result = Vec::new();
+            if row + n > self.n_rows() {
+                self.add_rows((row + n - self.n_rows()) as usize)?;
+            }
 
-        let rv = unsafe {
-            invoke_tablerec_get_keyword_info(self.handle, &mut self.exc_info, |name, _| {
-                result.push(name);
-            })
-        };
+            if unsafe {
+                glue::table_put_column_range(
+                    self.handle,
+                    &ccol_name,
+                    row,
+                    n,
+                    buf.as_ptr() as _,
+                    &mut self.exc_info,
+                )
+            } != 0
+            {
+                return self.exc_info.as_err();
+            }
 
-        if rv != 0 {
-            return self.exc_info.as_err();
+            self.record_io(
+                IoDirection::Put,
+                col_name,
+                n,
+                T::DATA_TYPE,
+                n * cell_len as u64,
+            );
+
+            row += n;
+            progress.report(row - start_row, None);
         }
 
-        Ok(result)
+        Ok(row - start_row)
     }
 
-    /// Get the record contents as a vector of field names, datatypes, and
-    /// string representations.
-    pub fn keyword_names_types_reprs(
+    /// Overwrite elements of a range of cells where a mask is set.
+    ///
+    /// The first axis of *values* and *mask* indexes rows, starting at
+    /// *start_row*, and the remaining axes must match the shape of the
+    /// column’s cells. Wherever *mask* is true, the corresponding element of
+    /// *values* is written; elsewhere, the existing data are kept. The rows
+    /// must already exist, and the column must be scalar or have a fixed
+    /// shape.
+    ///
+    /// The merge is done chunk by chunk: each chunk of rows is read, updated,
+    /// and written back. Chunks whose mask is entirely false are skipped, and
+    /// chunks whose mask is entirely true are written without being read.
+    /// This is handy for updating flags:
+    ///
+    /// ```no_run
+    /// use ndarray::Array3;
+    /// use rubbl_casatables::{Table, TableOpenMode};
+    ///
+    /// let mut t = Table::open("vis.ms", TableOpenMode::ReadWrite).unwrap();
+    /// let new_flags = Array3::from_elem((100, 64, 4), true);
+    /// let mut bad_channels = Array3::from_elem((100, 64, 4), false);
+    /// bad_channels.slice_mut(ndarray::s![.., 10..12, ..]).fill(true);
+    /// t.put_cells_masked("FLAG", 0, new_flags.view(), bad_channels.view())
+    ///     .unwrap();
+    /// ```
+    pub fn put_cells_masked<T, D>(
         &mut self,
-    ) -> Result<Vec<(String, GlueDataType, String)>, CasacoreError> {
-        let mut result = Vec::new();
+        col_name: &str,
+        start_row: u64,
+        values: ndarray::ArrayView<T, D>,
+        mask: ndarray::ArrayView<bool, D>,
+    ) -> Result<(), TableError>
+    where
+        T: CasaScalarData + Copy + Default,
+        D: ndarray::RemoveAxis,
+    {
+        let cell_shape = self.bulk_column_cell_shape::<T>(col_name)?;
 
-        let rv = unsafe {
-            invoke_tablerec_get_keyword_repr(
+        if values.shape() != mask.shape() {
+            return Err(TableError::ChunkShapeMismatch(
+                values.shape().to_vec(),
+                mask.shape().to_vec(),
+            ));
+        }
+
+        if values.ndim() != cell_shape.len() + 1 || values.shape()[1..] != cell_shape[..] {
+            return Err(TableError::ChunkShapeMismatch(
+                cell_shape,
+                values.shape().get(1..).unwrap_or(&[]).to_vec(),
+            ));
+        }
+
+        let n_rows = values.len_of(ndarray::Axis(0)) as u64;
+        let end = start_row + n_rows;
+
+        if end > self.n_rows() {
+            return Err(TableError::RowsOutOfBounds {
+                start: start_row,
+                end,
+                n_rows: self.n_rows(),
+            });
+        }
+
+        let cell_len: usize = cell_shape.iter().product();
+        let rows_per_chunk = self.suggest_chunk_rows(&[col_name], None)?;
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut buf = vec![T::default(); rows_per_chunk * cell_len];
+        let mut offset = 0;
+
+        while offset < n_rows {
+            let n = (rows_per_chunk as u64).min(n_rows - offset);
+            let rows = ndarray::Slice::from(offset as usize..(offset + n) as usize);
+            let chunk_values = values.slice_axis(ndarray::Axis(0), rows);
+            let chunk_mask = mask.slice_axis(ndarray::Axis(0), rows);
+            let row = start_row + offset;
+            let data = &mut buf[..n as usize * cell_len];
+            offset += n;
+
+            let n_set = chunk_mask.iter().filter(|m| **m).count();
+
+            if n_set == 0 {
+                continue;
+            }
+
+            if n_set < data.len() {
+                if unsafe {
+                    glue::table_get_column_range(
+                        self.handle,
+                        &ccol_name,
+                        row,
+                        n,
+                        data.as_mut_ptr() as _,
+                        &mut self.exc_info,
+                    )
+                } != 0
+                {
+                    return self.exc_info.as_err();
+                }
+
+                self.record_io(
+                    IoDirection::Get,
+                    col_name,
+                    n,
+                    T::DATA_TYPE,
+                    data.len() as u64,
+                );
+            }
+
+            for (d, (v, m)) in data
+                .iter_mut()
+                .zip(chunk_values.iter().zip(chunk_mask.iter()))
+            {
+                if *m {
+                    *d = *v;
+                }
+            }
+
+            if unsafe {
+                glue::table_put_column_range(
+                    self.handle,
+                    &ccol_name,
+                    row,
+                    n,
+                    data.as_ptr() as _,
+                    &mut self.exc_info,
+                )
+            } != 0
+            {
+                return self.exc_info.as_err();
+            }
+
+            self.record_io(
+                IoDirection::Put,
+                col_name,
+                n,
+                T::DATA_TYPE,
+                data.len() as u64,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Get the shape information of a column without reading any data.
+    ///
+    /// This reveals whether the column is scalar, has a fixed cell shape, or
+    /// has cells whose shapes vary; in the last case, the shape of each cell
+    /// can be obtained with [`Self::get_cell_shape`]. Readers can use this
+    /// to pre-allocate buffers or choose between bulk and per-cell code paths.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{ColumnShapeInfo, Table, TableOpenMode};
+    ///
+    /// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+    ///
+    /// match t.column_shape("DATA").unwrap() {
+    ///     ColumnShapeInfo::Fixed(shape) => println!("all cells have shape {:?}", shape),
+    ///     ColumnShapeInfo::Variable { .. } => {
+    ///         println!("the first cell has shape {:?}", t.get_cell_shape("DATA", 0).unwrap())
+    ///     }
+    ///     ColumnShapeInfo::Scalar => println!("not an array column"),
+    /// }
+    /// ```
+    pub fn column_shape(&mut self, col_name: &str) -> Result<ColumnShapeInfo, TableError> {
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut n_rows = 0;
+        let mut data_type = glue::GlueDataType::TpOther;
+        let mut is_scalar = 0;
+        let mut is_fixed_shape = 0;
+        let mut n_dim = 0;
+        let mut dims = [0; 8];
+
+        if unsafe {
+            glue::table_get_column_info(
                 self.handle,
+                &ccol_name,
+                &mut n_rows,
+                &mut data_type,
+                &mut is_scalar,
+                &mut is_fixed_shape,
+                &mut n_dim,
+                dims.as_mut_ptr(),
                 &mut self.exc_info,
-                |name, type_, repr| {
-                    result.push((name, type_, repr));
-                },
             )
-        };
-
-        if rv != 0 {
+        } != 0
+        {
             return self.exc_info.as_err();
         }
 
-        Ok(result)
+        Ok(if is_scalar != 0 {
+            ColumnShapeInfo::Scalar
+        } else if is_fixed_shape != 0 && n_dim > 0 {
+            ColumnShapeInfo::Fixed(dims[..n_dim as usize].iter().map(|d| *d as usize).collect())
+        } else {
+            ColumnShapeInfo::Variable {
+                ndim: if n_dim > 0 {
+                    Some(n_dim as usize)
+                } else {
+                    None
+                },
+            }
+        })
     }
 
-    /// Get the value of a particular field of this record.
-    pub fn get_field<T: CasaDataType>(&mut self, col_name: &str) -> Result<T, TableError> {
+    /// Get the shape of a cell of an array column.
+    ///
+    /// As elsewhere in this crate, the shape is given in C order, which is
+    /// the reverse of the order used by casacore. Scalar cells, and array
+    /// cells that have not been given a value, have an empty shape.
+    pub fn get_cell_shape(&mut self, col_name: &str, row: u64) -> Result<Vec<usize>, TableError> {
+        if let Some(result) = self.get_virtual_cell_shape(col_name, row) {
+            return result;
+        }
+
+        self.cell_info(col_name, row).map(|(_, shape)| shape)
+    }
+
+    fn cell_info(
+        &mut self,
+        col_name: &str,
+        row: u64,
+    ) -> Result<(glue::GlueDataType, Vec<usize>), TableError> {
         let ccol_name = glue::StringBridge::from_rust(col_name);
         let mut data_type = glue::GlueDataType::TpOther;
         let mut n_dim = 0;
         let mut dims = [0; 8];
 
         let rv = unsafe {
-            glue::tablerec_get_field_info(
+            glue::table_get_cell_info(
                 self.handle,
                 &ccol_name,
+                row,
                 &mut data_type,
                 &mut n_dim,
                 dims.as_mut_ptr(),
@@ -2529,703 +4510,2370 @@ impl TableRecord {
             return self.exc_info.as_err();
         }
 
-        if data_type != T::DATA_TYPE {
-            return Err(UnexpectedDataTypeError(T::DATA_TYPE, data_type).into());
-        }
+        let shape = dims[..n_dim as usize].iter().map(|d| *d as usize).collect();
+        Ok((data_type, shape))
+    }
 
-        let result = if data_type == glue::GlueDataType::TpString {
-            let mut value = None;
+    /// Check that a slice of an array cell is of type `T` and fits inside
+    /// it.
+    fn check_cell_slice<T: CasaScalarData>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+        start: &[usize],
+        shape: &[usize],
+    ) -> Result<(), TableError> {
+        let (data_type, cell_shape) = self.cell_info(col_name, row)?;
 
-            let rv = unsafe {
-                invoke_tablerec_get_field_string(self.handle, &ccol_name, &mut self.exc_info, |v| {
-                    value = Some(v);
-                })
-            };
+        if data_type != T::VECTOR_TYPE {
+            return Err(UnexpectedDataTypeError(T::VECTOR_TYPE, data_type).into());
+        }
 
-            if rv != 0 {
-                return self.exc_info.as_err();
-            }
+        let fits = start.len() == cell_shape.len()
+            && shape.len() == cell_shape.len()
+            && cell_shape
+                .iter()
+                .zip(start.iter().zip(shape))
+                .all(|(c, (s, n))| s + n <= *c);
+
+        if !fits {
+            return Err(TableError::SliceOutOfBounds {
+                cell: cell_shape,
+                start: start.to_vec(),
+                shape: shape.to_vec(),
+            });
+        }
 
-            T::casatables_string_pass_through(value.unwrap())
-        } else if data_type == glue::GlueDataType::TpArrayString {
-            let mut result = Vec::new();
+        Ok(())
+    }
 
-            let rv = unsafe {
-                invoke_tablerec_get_field_string_array(
-                    self.handle,
-                    &ccol_name,
-                    &mut self.exc_info,
-                    |v| {
-                        result.push(v);
-                    },
-                )
-            };
+    /// Read a rectangular slice of an array cell.
+    ///
+    /// The *start* and *shape* of the slice are given in C order, like the
+    /// shapes of the arrays returned by [`Self::get_cell`]. Only the requested
+    /// slice is read from disk, which makes this suitable for accessing parts
+    /// of very large cells, such as the pixels of an image.
+    pub fn get_cell_slice<T: CasaScalarData + Copy + Default>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+        start: &[usize],
+        shape: &[usize],
+    ) -> Result<ndarray::ArrayD<T>, TableError> {
+        self.with_retry(|t| t.get_cell_slice_once(col_name, row, start, shape))
+    }
 
-            if rv != 0 {
-                return self.exc_info.as_err();
-            }
+    fn get_cell_slice_once<T: CasaScalarData + Copy + Default>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+        start: &[usize],
+        shape: &[usize],
+    ) -> Result<ndarray::ArrayD<T>, TableError> {
+        self.check_cell_slice::<T>(col_name, row, start, shape)?;
 
-            T::casatables_stringvec_pass_through(result)
-        } else if data_type == glue::GlueDataType::TpRecord {
-            let result = TableRecord::new().unwrap();
-            let rv = unsafe {
-                glue::tablerec_get_field_subrecord(
-                    self.handle,
-                    &ccol_name,
-                    result.handle,
-                    &mut self.exc_info,
-                )
-            };
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let cstart: Vec<u64> = start.iter().map(|s| *s as u64).collect();
+        let cshape: Vec<u64> = shape.iter().map(|n| *n as u64).collect();
+        let mut buf = vec![T::default(); shape.iter().product()];
 
-            if rv != 0 {
-                return self.exc_info.as_err();
-            }
+        if unsafe {
+            glue::table_get_cell_slice(
+                self.handle,
+                &ccol_name,
+                row,
+                shape.len() as u64,
+                cstart.as_ptr(),
+                cshape.as_ptr(),
+                buf.as_mut_ptr() as _,
+                &mut self.exc_info,
+            )
+        } != 0
+        {
+            return self.exc_info.as_err();
+        }
 
-            T::casatables_tablerec_pass_through(result)
-        } else {
-            let mut result = T::casatables_alloc(&dims[..n_dim as usize])?;
+        self.record_io(
+            IoDirection::Get,
+            col_name,
+            1,
+            T::DATA_TYPE,
+            buf.len() as u64,
+        );
+        Ok(ndarray::ArrayD::from_shape_vec(shape, buf).unwrap())
+    }
 
-            let rv = unsafe {
-                glue::tablerec_get_field(
-                    self.handle,
-                    &ccol_name,
-                    result.casatables_as_mut_buf() as _,
-                    &mut self.exc_info,
-                )
-            };
+    /// Write a rectangular slice of an array cell.
+    ///
+    /// The *start* of the slice is given in C order, and the shape of the
+    /// slice is that of *data*. The cell must already have a shape, either
+    /// because the column has a fixed shape or because a complete value has
+    /// been written to it.
+    pub fn put_cell_slice<T: CasaScalarData + Copy + Default>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+        start: &[usize],
+        data: ndarray::ArrayViewD<T>,
+    ) -> Result<(), TableError> {
+        self.check_cell_slice::<T>(col_name, row, start, data.shape())?;
 
-            if rv != 0 {
-                return self.exc_info.as_err();
-            }
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let cstart: Vec<u64> = start.iter().map(|s| *s as u64).collect();
+        let cshape: Vec<u64> = data.shape().iter().map(|n| *n as u64).collect();
+        let data = data.as_standard_layout();
 
-            result
-        };
+        if unsafe {
+            glue::table_put_cell_slice(
+                self.handle,
+                &ccol_name,
+                row,
+                cshape.len() as u64,
+                cstart.as_ptr(),
+                cshape.as_ptr(),
+                data.as_ptr() as _,
+                &mut self.exc_info,
+            )
+        } != 0
+        {
+            return self.exc_info.as_err();
+        }
 
-        Ok(result)
+        self.record_io(
+            IoDirection::Put,
+            col_name,
+            1,
+            T::DATA_TYPE,
+            data.len() as u64,
+        );
+        Ok(())
     }
 
-    /// Set the value of a particular field of this record.
-    pub fn put_field<T: CasaDataType>(
-        &mut self,
-        field_name: &str,
-        value: &T,
-    ) -> Result<(), CasacoreError> {
-        let cfield_name = glue::StringBridge::from_rust(field_name);
-        let mut shape = Vec::new();
-
-        value.casatables_put_shape(&mut shape);
+    fn get_row_handle(&mut self, is_read_only: bool) -> Result<TableRow, CasacoreError> {
+        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+        let ro_flag = if is_read_only { 1 } else { 0 };
 
-        if T::DATA_TYPE == glue::GlueDataType::TpString {
-            let as_string = T::casatables_string_pass_through_out(value);
-            let glue_string = glue::StringBridge::from_rust(&as_string);
+        let handle = unsafe { glue::table_row_alloc(self.handle, ro_flag, &mut exc_info) };
+        if handle.is_null() {
+            return exc_info.as_err();
+        }
 
-            let rv = unsafe {
-                glue::tablerec_put_field(
-                    self.handle,
-                    &cfield_name,
-                    T::DATA_TYPE,
-                    shape.len() as u64,
-                    shape.as_ptr(),
-                    &glue_string as *const glue::StringBridge as _,
-                    &mut self.exc_info,
-                )
-            };
+        Ok(TableRow { handle, exc_info })
+    }
 
-            if rv != 0 {
-                return self.exc_info.as_err();
-            }
-        } else if T::DATA_TYPE == glue::GlueDataType::TpArrayString {
-            let glue_strings = T::casatables_stringvec_pass_through_out(value);
+    /// Get an object for read-only access to individual rows of the table.
+    ///
+    /// The row reader can be used to iterate through the rows of the table
+    /// efficiently.
+    ///
+    /// See also [`Self::get_row_writer`].
+    pub fn get_row_reader(&mut self) -> Result<TableRow, CasacoreError> {
+        self.get_row_handle(true)
+    }
 
-            let rv = unsafe {
-                glue::tablerec_put_field(
-                    self.handle,
-                    &cfield_name,
-                    T::DATA_TYPE,
-                    shape.len() as u64,
-                    shape.as_ptr(),
-                    glue_strings.as_ptr() as _,
-                    &mut self.exc_info,
-                )
-            };
+    /// Get an object for read-write access to individual rows of the table.
+    ///
+    /// The row writer can be used to iterate through the rows of the table
+    /// efficiently.
+    ///
+    /// See also [`Self::get_row_reader`].
+    pub fn get_row_writer(&mut self) -> Result<TableRow, CasacoreError> {
+        self.get_row_handle(false)
+    }
 
-            if rv != 0 {
-                return self.exc_info.as_err();
-            }
-        } else {
-            let rv = unsafe {
-                glue::tablerec_put_field(
-                    self.handle,
-                    &cfield_name,
-                    T::DATA_TYPE,
-                    shape.len() as u64,
-                    shape.as_ptr(),
-                    value.casatables_as_buf() as _,
-                    &mut self.exc_info,
-                )
-            };
+    /// Populate a [`TableRow`] accessor object with data from the specified
+    /// row.
+    pub fn read_row(&mut self, row: &mut TableRow, row_number: u64) -> Result<(), TableError> {
+        if unsafe { glue::table_row_read(row.handle, row_number, &mut row.exc_info) } != 0 {
+            return row.exc_info.as_err();
+        }
 
-            if rv != 0 {
-                return self.exc_info.as_err();
+        Ok(())
+    }
+
+    /// Perform `func` on each row of the table.
+    pub fn for_each_row<F>(&mut self, mut func: F) -> Result<(), TableError>
+    where
+        F: FnMut(&mut TableRow) -> Result<(), TableError>,
+    {
+        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+
+        let handle = unsafe { glue::table_row_alloc(self.handle, 1, &mut exc_info) };
+        if handle.is_null() {
+            return exc_info.as_err();
+        }
+
+        let mut row = TableRow { handle, exc_info };
+
+        for row_number in 0..self.n_rows() {
+            if unsafe { glue::table_row_read(row.handle, row_number as u64, &mut row.exc_info) }
+                != 0
+            {
+                return row.exc_info.as_err();
             }
+
+            func(&mut row)?;
         }
 
         Ok(())
     }
-}
 
-impl PartialEq for TableRecord {
-    fn eq(&self, other: &Self) -> bool {
-        unsafe { glue::tablerec_eq(self.handle, other.handle) }
-    }
-}
+    /// Perform `func` on each row in the range `row_range`.
+    pub fn for_each_row_in_range<F>(
+        &mut self,
+        row_range: std::ops::Range<u64>,
+        mut func: F,
+    ) -> Result<(), TableError>
+    where
+        F: FnMut(&mut TableRow) -> Result<(), TableError>,
+    {
+        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
 
-impl Eq for TableRecord {}
+        let handle = unsafe { glue::table_row_alloc(self.handle, 1, &mut exc_info) };
+        if handle.is_null() {
+            return exc_info.as_err();
+        }
 
-impl Debug for TableRecord {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // This is to avoid mutably borrowing self
+        let mut row = TableRow {
+            handle: handle,
+            exc_info: exc_info,
+        };
+
+        for row_number in row_range {
+            if unsafe { glue::table_row_read(row.handle, row_number as u64, &mut row.exc_info) }
+                != 0
+            {
+                return row.exc_info.as_err();
+            }
+
+            func(&mut row)?;
+        }
+
+        Ok(())
+    }
+
+    /// Perform `func` on each row indicated by `rows`.
+    pub fn for_each_specific_row<F>(&mut self, rows: &[u64], mut func: F) -> Result<(), TableError>
+    where
+        F: FnMut(&mut TableRow) -> Result<(), TableError>,
+    {
         let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
-        write!(f, "TableRecord {{ ").unwrap();
-        unsafe {
-            invoke_tablerec_get_keyword_repr(self.handle, &mut exc_info, |name, type_, repr| {
-                write!(f, "{}:{} = {}, ", name, type_, repr).unwrap();
-            })
+
+        let handle = unsafe { glue::table_row_alloc(self.handle, 1, &mut exc_info) };
+        if handle.is_null() {
+            return exc_info.as_err();
+        }
+
+        let mut row = TableRow {
+            handle: handle,
+            exc_info: exc_info,
         };
-        write!(f, " }}")
+
+        for &row_number in rows {
+            if unsafe { glue::table_row_read(row.handle, row_number as u64, &mut row.exc_info) }
+                != 0
+            {
+                return row.exc_info.as_err();
+            }
+
+            func(&mut row)?;
+        }
+
+        Ok(())
     }
-}
 
-impl CasaDataType for TableRecord {
-    const DATA_TYPE: glue::GlueDataType = glue::GlueDataType::TpRecord;
+    /// Copy all rows from this table to another table.
+    pub fn copy_rows_to(&mut self, dest: &mut Table) -> Result<(), CasacoreError> {
+        if unsafe { glue::table_copy_rows(self.handle, dest.handle, &mut self.exc_info) != 0 } {
+            self.exc_info.as_err()
+        } else {
+            Ok(())
+        }
+    }
 
-    fn casatables_alloc(_shape: &[u64]) -> Result<Self, TableError> {
-        TableRecord::new()
+    /// Copy the "description" of this table to a new filesystem path, without
+    /// copying any of the actual data contents.
+    ///
+    /// See [`Self::deep_copy_no_rows_to_path`] for paths that are not UTF-8.
+    pub fn deep_copy_no_rows(&mut self, dest_path: &str) -> Result<(), CasacoreError> {
+        let cdest_path = glue::StringBridge::from_rust(dest_path);
+        self.deep_copy_impl(&cdest_path, EndianFormat::Local, true)
     }
 
-    fn casatables_as_buf(&self) -> *const () {
-        self.handle as _
+    /// Copy this table, including all of its data, to a new filesystem path,
+    /// storing the data in the specified byte order.
+    ///
+    /// The destination must not already exist. See [`Self::deep_copy_to_path`]
+    /// for paths that are not UTF-8.
+    pub fn deep_copy(
+        &mut self,
+        dest_path: &str,
+        endian_format: EndianFormat,
+    ) -> Result<(), CasacoreError> {
+        let cdest_path = glue::StringBridge::from_rust(dest_path);
+        self.deep_copy_impl(&cdest_path, endian_format, false)
     }
 
-    fn casatables_as_mut_buf(&mut self) -> *mut () {
-        self.handle as _
+    /// Like [`Self::deep_copy_no_rows`], but accepting any path. On Unix, the
+    /// path need not be valid UTF-8; paths containing NUL bytes are rejected
+    /// with [`TableError::InvalidPath`].
+    pub fn deep_copy_no_rows_to_path<P: AsRef<Path>>(
+        &mut self,
+        dest_path: P,
+    ) -> Result<(), TableError> {
+        let cdest_path = glue::StringBridge::from_path(dest_path.as_ref())?;
+        Ok(self.deep_copy_impl(&cdest_path, EndianFormat::Local, true)?)
     }
 
-    fn casatables_tablerec_pass_through(r: TableRecord) -> Self {
-        r
+    /// Like [`Self::deep_copy`], but accepting any path, as for
+    /// [`Self::deep_copy_no_rows_to_path`].
+    pub fn deep_copy_to_path<P: AsRef<Path>>(
+        &mut self,
+        dest_path: P,
+        endian_format: EndianFormat,
+    ) -> Result<(), TableError> {
+        let cdest_path = glue::StringBridge::from_path(dest_path.as_ref())?;
+        Ok(self.deep_copy_impl(&cdest_path, endian_format, false)?)
     }
 
-    fn casatables_tablerec_pass_through_out(s: &Self) -> TableRecord {
-        s.to_owned()
+    fn deep_copy_impl(
+        &mut self,
+        cdest_path: &glue::StringBridge,
+        endian_format: EndianFormat,
+        no_rows: bool,
+    ) -> Result<(), CasacoreError> {
+        if unsafe {
+            glue::table_deep_copy(
+                self.handle,
+                cdest_path,
+                endian_format.as_glue(),
+                no_rows as u8,
+                &mut self.exc_info,
+            ) != 0
+        } {
+            self.exc_info.as_err()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the byte order in which the data of this table are stored.
+    ///
+    /// The result is always either [`EndianFormat::Big`] or
+    /// [`EndianFormat::Little`].
+    pub fn endian_format(&mut self) -> Result<EndianFormat, CasacoreError> {
+        let mut endian_format = glue::TableEndianFormat::TEF_LOCAL_ENDIAN;
+
+        if unsafe { glue::table_endian_format(self.handle, &mut endian_format, &mut self.exc_info) }
+            != 0
+        {
+            return self.exc_info.as_err();
+        }
+
+        Ok(match endian_format {
+            glue::TableEndianFormat::TEF_BIG_ENDIAN => EndianFormat::Big,
+            glue::TableEndianFormat::TEF_LITTLE_ENDIAN => EndianFormat::Little,
+            glue::TableEndianFormat::TEF_LOCAL_ENDIAN => EndianFormat::Local,
+            glue::TableEndianFormat::TEF_AIPSRC_ENDIAN => EndianFormat::Aipsrc,
+        })
     }
 }
 
-impl Drop for TableRecord {
-    /// Free the casacore::TableRecord handle
+impl Debug for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Table")
+            .field("handle", &self.handle)
+            .field("name", &self.file_name().unwrap())
+            .finish()
+    }
+}
+
+impl Drop for Table {
     fn drop(&mut self) {
-        // TODO: we want to free standalone tablerecords, but not those which
-        // are associated with a table, because they are freed automatically
-        // with the table. Not sure the best way to do this.
+        // The cached columns refer to the table, so they must go first.
+        self.column_cache.clear();
+        registry::unregister(self.handle);
 
         // FIXME: not sure if this function can actually produce useful
         // exceptions anyway, but we can't do anything if it does!
-        unsafe {
-            glue::tablerec_free(self.handle, &mut self.exc_info);
-        }
+        unsafe { glue::table_close_and_free(self.handle, &mut self.exc_info) }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::fs::OpenOptions;
+/// Information describing the properties of a particular column of a table.
+#[derive(PartialEq, Eq, Debug)]
+pub struct ColumnDescription {
+    name: String,
+    data_type: glue::GlueDataType,
+    is_scalar: bool,
+    is_fixed_shape: bool,
+    shape: Option<Vec<u64>>,
+    keywords: TableRecord,
+}
 
-    use super::*;
-    use crate::glue::{GlueDataType, TableDescCreateMode};
-    use ndarray::array;
-    use tempfile::tempdir;
+impl ColumnDescription {
+    /// Get the name of the column.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-    #[allow(non_camel_case_types)]
-    type c64 = Complex<f64>;
+    /// Get the underlying CASA data type of the column.
+    pub fn data_type(&self) -> glue::GlueDataType {
+        self.data_type
+    }
+
+    /// Get whether the column is scalar-valued, as opposed to vector-valued.
+    pub fn is_scalar(&self) -> bool {
+        self.is_scalar
+    }
+
+    /// Get whether the column is vector-valued with a fixed array shape for
+    /// each cell.
+    pub fn is_fixed_shape(&self) -> bool {
+        self.is_fixed_shape
+    }
+
+    /// If the column is vector-valued with a fixed shape for each cell, get the
+    /// required shape.
+    pub fn shape(&self) -> Option<&[u64]> {
+        self.shape.as_ref().map(|v| &v[..])
+    }
+}
+
+/// The shape information of a column, as returned by [`Table::column_shape`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ColumnShapeInfo {
+    /// The column holds scalar values.
+    Scalar,
+
+    /// All of the cells of the column are arrays of this shape, given in C
+    /// order.
+    Fixed(Vec<usize>),
+
+    /// The cells of the column are arrays whose shapes may differ from row to
+    /// row.
+    Variable {
+        /// The dimensionality of the cells, if the column requires one.
+        ndim: Option<usize>,
+    },
+}
+
+impl ColumnShapeInfo {
+    /// Get the dimensionality of the cells, if it is known. Scalar columns
+    /// have a dimensionality of zero.
+    pub fn ndim(&self) -> Option<usize> {
+        match self {
+            ColumnShapeInfo::Scalar => Some(0),
+            ColumnShapeInfo::Fixed(shape) => Some(shape.len()),
+            ColumnShapeInfo::Variable { ndim } => *ndim,
+        }
+    }
+
+    /// Get the shape shared by all of the cells, if there is one. Scalar
+    /// columns have an empty shape.
+    pub fn fixed_shape(&self) -> Option<&[usize]> {
+        match self {
+            ColumnShapeInfo::Scalar => Some(&[]),
+            ColumnShapeInfo::Fixed(shape) => Some(&shape[..]),
+            ColumnShapeInfo::Variable { .. } => None,
+        }
+    }
+}
+
+// Table Row handles
+
+/// A type for examining individual rows of a CASA table.
+pub struct TableRow {
+    handle: *mut glue::GlueTableRow,
+    exc_info: glue::ExcInfo,
+}
+
+impl TableRow {
+    /// Get the value of a specific cell in this row.
+    pub fn get_cell<T: CasaDataType>(&mut self, col_name: &str) -> Result<T, TableError> {
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut data_type = glue::GlueDataType::TpOther;
+        let mut n_dim = 0;
+        let mut dims = [0; 8];
+
+        let rv = unsafe {
+            glue::table_row_get_cell_info(
+                self.handle,
+                &ccol_name,
+                &mut data_type,
+                &mut n_dim,
+                dims.as_mut_ptr(),
+                &mut self.exc_info,
+            )
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        if data_type != T::DATA_TYPE {
+            return Err(UnexpectedDataTypeError(T::DATA_TYPE, data_type).into());
+        }
+
+        let result = if data_type == glue::GlueDataType::TpString {
+            let mut value = None;
+
+            let rv = unsafe {
+                invoke_table_row_get_cell_string(self.handle, &ccol_name, &mut self.exc_info, |v| {
+                    value = Some(v);
+                })
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+
+            T::casatables_string_pass_through(value.unwrap())
+        } else if data_type == glue::GlueDataType::TpArrayString {
+            let mut result = Vec::new();
+
+            let rv = unsafe {
+                invoke_table_row_get_cell_string_array(
+                    self.handle,
+                    &ccol_name,
+                    &mut self.exc_info,
+                    |v| {
+                        result.push(v);
+                    },
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+
+            T::casatables_stringvec_pass_through(result)
+        } else {
+            let mut result = T::casatables_alloc(&dims[..n_dim as usize])?;
+
+            let rv = unsafe {
+                glue::table_row_get_cell(
+                    self.handle,
+                    &ccol_name,
+                    result.casatables_as_mut_buf() as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+
+            result
+        };
+
+        Ok(result)
+    }
+
+    /// Write a new value for a specific cell in this row.
+    ///
+    /// Note: I am not sure if this function actually works! `Table.put_cell`
+    /// does work. Investigation required.
+    pub fn put_cell<T: CasaDataType>(
+        &mut self,
+        col_name: &str,
+        value: &T,
+    ) -> Result<(), CasacoreError> {
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut shape = Vec::new();
+
+        value.casatables_put_shape(&mut shape);
+
+        if T::DATA_TYPE == glue::GlueDataType::TpString {
+            let as_string = T::casatables_string_pass_through_out(value);
+            let glue_string = glue::StringBridge::from_rust(&as_string);
+
+            let rv = unsafe {
+                glue::table_row_put_cell(
+                    self.handle,
+                    &ccol_name,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    &glue_string as *const glue::StringBridge as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+        } else if T::DATA_TYPE == glue::GlueDataType::TpArrayString {
+            let glue_strings = T::casatables_stringvec_pass_through_out(value);
+
+            let rv = unsafe {
+                glue::table_row_put_cell(
+                    self.handle,
+                    &ccol_name,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    glue_strings.as_ptr() as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+        } else {
+            let value = value.casatables_standard_layout();
+
+            let rv = unsafe {
+                glue::table_row_put_cell(
+                    self.handle,
+                    &ccol_name,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    value.casatables_as_buf() as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copy the data associated with this row accessor to another one, and then
+    /// write the data to the specifed row of that other accessor's associated
+    /// table.
+    pub fn copy_and_put(
+        &mut self,
+        dest: &mut TableRow,
+        row_number: u64,
+    ) -> Result<(), CasacoreError> {
+        let rv = unsafe {
+            glue::table_row_copy_and_put(self.handle, row_number, dest.handle, &mut self.exc_info)
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        Ok(())
+    }
+
+    /// Write the data currently associated with this row accessor to the
+    /// specified row of its associated table.
+    pub fn put(&mut self, row_number: u64) -> Result<(), CasacoreError> {
+        let rv = unsafe { glue::table_row_write(self.handle, row_number, &mut self.exc_info) };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for TableRow {
+    fn drop(&mut self) {
+        // FIXME: not sure if this function can actually produce useful
+        // exceptions anyway, but we can't do anything if it does!
+        unsafe {
+            glue::table_row_free(self.handle, &mut self.exc_info);
+        }
+    }
+}
+
+/// A dictionary-like data structured that can be stored in a CASA table.
+///
+/// FIXME: currently, to avoid potential double-frees, TableRecords are only
+/// created standalone, or as copies of TableRecords owned by something else.
+///
+/// Ideally, there would be a lot of utility in having rewritable TableRecords,
+/// however this would require keeping track of whether a TableRecord is owned,
+/// and not freeing owned TableRecords.
+///
+/// Further discussion [here][x].
+///
+/// [x]: https://github.com/pkgw/rubbl/pull/181#issuecomment-968493738
+#[derive(Clone)]
+pub struct TableRecord {
+    handle: *mut glue::GlueTableRecord,
+    exc_info: glue::ExcInfo,
+}
+
+impl TableRecord {
+    /// Create a new, empty [`TableRecord`].
+    ///
+    /// This function will return an error if the underlying C++ code raises an
+    /// exception. This *should* only happen if there is a memory allocation
+    /// error.
+    pub fn new() -> Result<Self, TableError> {
+        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+
+        let handle = unsafe { glue::tablerec_create(&mut exc_info) };
+        if handle.is_null() {
+            return exc_info.as_err();
+        }
+
+        Ok(Self { handle, exc_info })
+    }
+
+    fn copy_handle(other_handle: &glue::GlueTableRecord) -> Result<Self, CasacoreError> {
+        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+
+        let handle = unsafe { glue::tablerec_copy(other_handle, &mut exc_info) };
+        if handle.is_null() {
+            return exc_info.as_err();
+        }
+
+        Ok(Self { handle, exc_info })
+    }
+
+    /// Return all of the keyword names in the record.
+    pub fn keyword_names(&mut self) -> Result<Vec<String>, CasacoreError> {
+        let mut result = Vec::new();
+
+        let rv = unsafe {
+            invoke_tablerec_get_keyword_info(self.handle, &mut self.exc_info, |name, _| {
+                result.push(name);
+            })
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        Ok(result)
+    }
+
+    /// Get the record contents as a vector of field names, datatypes, and
+    /// string representations.
+    pub fn keyword_names_types_reprs(
+        &mut self,
+    ) -> Result<Vec<(String, GlueDataType, String)>, CasacoreError> {
+        let mut result = Vec::new();
+
+        let rv = unsafe {
+            invoke_tablerec_get_keyword_repr(
+                self.handle,
+                &mut self.exc_info,
+                |name, type_, repr| {
+                    result.push((name, type_, repr));
+                },
+            )
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        Ok(result)
+    }
+
+    /// Get the value of a particular field of this record.
+    pub fn get_field<T: CasaDataType>(&mut self, col_name: &str) -> Result<T, TableError> {
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut data_type = glue::GlueDataType::TpOther;
+        let mut n_dim = 0;
+        let mut dims = [0; 8];
+
+        let rv = unsafe {
+            glue::tablerec_get_field_info(
+                self.handle,
+                &ccol_name,
+                &mut data_type,
+                &mut n_dim,
+                dims.as_mut_ptr(),
+                &mut self.exc_info,
+            )
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        if data_type != T::DATA_TYPE {
+            return Err(UnexpectedDataTypeError(T::DATA_TYPE, data_type).into());
+        }
+
+        let result = if data_type == glue::GlueDataType::TpString {
+            let mut value = None;
+
+            let rv = unsafe {
+                invoke_tablerec_get_field_string(self.handle, &ccol_name, &mut self.exc_info, |v| {
+                    value = Some(v);
+                })
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+
+            T::casatables_string_pass_through(value.unwrap())
+        } else if data_type == glue::GlueDataType::TpArrayString {
+            let mut result = Vec::new();
+
+            let rv = unsafe {
+                invoke_tablerec_get_field_string_array(
+                    self.handle,
+                    &ccol_name,
+                    &mut self.exc_info,
+                    |v| {
+                        result.push(v);
+                    },
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+
+            T::casatables_stringvec_pass_through(result)
+        } else if data_type == glue::GlueDataType::TpRecord {
+            let result = TableRecord::new().unwrap();
+            let rv = unsafe {
+                glue::tablerec_get_field_subrecord(
+                    self.handle,
+                    &ccol_name,
+                    result.handle,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+
+            T::casatables_tablerec_pass_through(result)
+        } else {
+            let mut result = T::casatables_alloc(&dims[..n_dim as usize])?;
+
+            let rv = unsafe {
+                glue::tablerec_get_field(
+                    self.handle,
+                    &ccol_name,
+                    result.casatables_as_mut_buf() as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+
+            result
+        };
+
+        Ok(result)
+    }
+
+    /// Set the value of a particular field of this record.
+    pub fn put_field<T: CasaDataType>(
+        &mut self,
+        field_name: &str,
+        value: &T,
+    ) -> Result<(), CasacoreError> {
+        let cfield_name = glue::StringBridge::from_rust(field_name);
+        let mut shape = Vec::new();
+
+        value.casatables_put_shape(&mut shape);
+
+        if T::DATA_TYPE == glue::GlueDataType::TpString {
+            let as_string = T::casatables_string_pass_through_out(value);
+            let glue_string = glue::StringBridge::from_rust(&as_string);
+
+            let rv = unsafe {
+                glue::tablerec_put_field(
+                    self.handle,
+                    &cfield_name,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    &glue_string as *const glue::StringBridge as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+        } else if T::DATA_TYPE == glue::GlueDataType::TpArrayString {
+            let glue_strings = T::casatables_stringvec_pass_through_out(value);
+
+            let rv = unsafe {
+                glue::tablerec_put_field(
+                    self.handle,
+                    &cfield_name,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    glue_strings.as_ptr() as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+        } else {
+            let value = value.casatables_standard_layout();
+
+            let rv = unsafe {
+                glue::tablerec_put_field(
+                    self.handle,
+                    &cfield_name,
+                    T::DATA_TYPE,
+                    shape.len() as u64,
+                    shape.as_ptr(),
+                    value.casatables_as_buf() as _,
+                    &mut self.exc_info,
+                )
+            };
+
+            if rv != 0 {
+                return self.exc_info.as_err();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PartialEq for TableRecord {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { glue::tablerec_eq(self.handle, other.handle) }
+    }
+}
+
+impl Eq for TableRecord {}
+
+impl Debug for TableRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // This is to avoid mutably borrowing self
+        let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+        write!(f, "TableRecord {{ ").unwrap();
+        unsafe {
+            invoke_tablerec_get_keyword_repr(self.handle, &mut exc_info, |name, type_, repr| {
+                write!(f, "{}:{} = {}, ", name, type_, repr).unwrap();
+            })
+        };
+        write!(f, " }}")
+    }
+}
+
+impl CasaDataType for TableRecord {
+    const DATA_TYPE: glue::GlueDataType = glue::GlueDataType::TpRecord;
+
+    fn casatables_alloc(_shape: &[u64]) -> Result<Self, TableError> {
+        TableRecord::new()
+    }
+
+    fn casatables_as_buf(&self) -> *const () {
+        self.handle as _
+    }
+
+    fn casatables_as_mut_buf(&mut self) -> *mut () {
+        self.handle as _
+    }
+
+    fn casatables_tablerec_pass_through(r: TableRecord) -> Self {
+        r
+    }
+
+    fn casatables_tablerec_pass_through_out(s: &Self) -> TableRecord {
+        s.to_owned()
+    }
+}
+
+impl Drop for TableRecord {
+    /// Free the casacore::TableRecord handle
+    fn drop(&mut self) {
+        // TODO: we want to free standalone tablerecords, but not those which
+        // are associated with a table, because they are freed automatically
+        // with the table. Not sure the best way to do this.
+
+        // FIXME: not sure if this function can actually produce useful
+        // exceptions anyway, but we can't do anything if it does!
+        unsafe {
+            glue::tablerec_free(self.handle, &mut self.exc_info);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+
+    use super::*;
+    use crate::glue::{GlueDataType, TableDescCreateMode};
+    use ndarray::{array, Array2};
+    use tempfile::tempdir;
+
+    #[allow(non_camel_case_types)]
+    type c64 = Complex<f64>;
+
+    #[test]
+    fn casacore_version_display() {
+        let bundled = casacore_version();
+
+        if !bundled.system {
+            assert_eq!(bundled.threads, Some(true));
+            assert_eq!(bundled.mpi, Some(false));
+        }
+
+        let system = CasacoreVersion {
+            major: 3,
+            minor: 5,
+            patch: 0,
+            threads: None,
+            mpi: Some(false),
+            adios2: Some(false),
+            hdf5: Some(true),
+            system: true,
+        };
+        assert_eq!(system.to_string(), "3.5.0 (system, hdf5; unknown: threads)");
+    }
+
+    #[test]
+    fn table_create_with_scalar_desc() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+
+        let col_name = "test_uint";
+
+        let mut table_desc = TableDesc::new("TEST", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpUInt, &col_name, None, false, false)
+            .unwrap();
+
+        let mut table = Table::new(table_path, table_desc, 123, TableCreateMode::New).unwrap();
+
+        assert_eq!(table.n_rows(), 123);
+        assert_eq!(table.n_columns(), 1);
+
+        let column_info = table.get_col_desc(&col_name).unwrap();
+        assert_eq!(column_info.data_type(), GlueDataType::TpUInt);
+        assert_eq!(column_info.name(), col_name);
+        assert!(column_info.is_scalar());
+    }
+
+    #[test]
+    fn table_create_with_scalar_string_desc() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+
+        let col_name = "test_string";
+
+        let mut table_desc = TableDesc::new("TEST", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpString, &col_name, None, false, false)
+            .unwrap();
+
+        let mut table = Table::new(table_path, table_desc, 123, TableCreateMode::New).unwrap();
+
+        assert_eq!(table.n_rows(), 123);
+        assert_eq!(table.n_columns(), 1);
+
+        let column_info = table.get_col_desc(&col_name).unwrap();
+        assert_eq!(column_info.data_type(), GlueDataType::TpString);
+        assert_eq!(column_info.name(), col_name);
+        assert!(column_info.is_scalar());
+    }
+
+    #[test]
+    fn table_create_no_replace() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+
+        // touch the file
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(table_path.clone())
+            .unwrap();
+
+        let col_name = "test_string";
+
+        let mut table_desc = TableDesc::new("TEST", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpString, &col_name, None, false, false)
+            .unwrap();
+
+        // NewNoReplace should fail if table exists.
+        assert!(matches!(
+            Table::new(table_path, table_desc, 123, TableCreateMode::NewNoReplace),
+            Err(TableError::Casacore { .. })
+        ));
+    }
+
+    #[test]
+    fn table_create_with_fixed_string_array_desc() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+
+        let col_name = "test_string_fixed";
+
+        let mut table_desc = TableDesc::new("TEST", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_array_column(
+                GlueDataType::TpString,
+                &col_name,
+                None,
+                Some(&[1, 2, 3]),
+                false,
+                false,
+            )
+            .unwrap();
+
+        let mut table = Table::new(table_path, table_desc, 123, TableCreateMode::New).unwrap();
+
+        assert_eq!(table.n_rows(), 123);
+        assert_eq!(table.n_columns(), 1);
+
+        let column_info = table.get_col_desc(&col_name).unwrap();
+        assert_eq!(column_info.data_type(), GlueDataType::TpString);
+        assert_eq!(column_info.name(), col_name);
+        assert!(!column_info.is_scalar());
+        assert!(column_info.is_fixed_shape());
+        assert_eq!(column_info.shape(), Some(&[1, 2, 3][..]));
+    }
+
+    #[test]
+    fn table_create_with_variable_string_array_desc() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+
+        let col_name = "test_string_var";
+
+        let mut table_desc = TableDesc::new("TEST", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_array_column(GlueDataType::TpString, &col_name, None, None, false, false)
+            .unwrap();
+
+        let mut table = Table::new(table_path, table_desc, 123, TableCreateMode::New).unwrap();
+
+        assert_eq!(table.n_rows(), 123);
+        assert_eq!(table.n_columns(), 1);
+
+        let column_info = table.get_col_desc(&col_name).unwrap();
+        assert_eq!(column_info.data_type(), GlueDataType::TpString);
+        assert_eq!(column_info.name(), col_name);
+        assert!(!column_info.is_scalar());
+        assert!(!column_info.is_fixed_shape());
+        assert_eq!(column_info.shape(), None);
+    }
+
+    #[test]
+    fn table_create_write_open_read_cell() {
+        // tempdir is only necessary to avoid writing to disk each time this example is run
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+        // First create a table description for our base table.
+        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        // Define the columns in your table description
+        table_desc
+            .add_array_column(
+                GlueDataType::TpDouble,
+                "UVW",
+                Some("Vector with uvw coordinates (in meters)"),
+                Some(&[3]),
+                true,
+                false,
+            )
+            .unwrap();
+        // Create your new table with 1 rows
+        let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
+        // write to the first row in the uvw column
+        let cell_value: Vec<f64> = vec![1.0, 2.0, 3.0];
+        table.put_cell("UVW", 0, &cell_value).unwrap();
+        // This writes the table to disk and closes the file pointer.
+        drop(table);
+
+        // now open the table again for reading cells
+        let mut table = Table::open(&table_path, TableOpenMode::Read).unwrap();
+        // and extract the cell value we wrote earlier
+        let extracted_cell_value: Vec<f64> = table.get_cell_as_vec("UVW", 0).unwrap();
+        assert_eq!(cell_value, extracted_cell_value);
+    }
+
+    #[test]
+    fn table_add_scalar_column() {
+        // tempdir is only necessary to avoid writing to disk each time this example is run
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+        // First create a table description for our base table.
+        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        // Define the columns in your table description
+        table_desc
+            .add_array_column(
+                GlueDataType::TpDouble,
+                "UVW",
+                Some("Vector with uvw coordinates (in meters)"),
+                Some(&[3]),
+                true,
+                false,
+            )
+            .unwrap();
+        // Create your new table with 1 rows
+        let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
+        // write to the first row in the uvw column
+        let cell_value: Vec<f64> = vec![1.0, 2.0, 3.0];
+        table.put_cell("UVW", 0, &cell_value).unwrap();
+        // This writes the table to disk and closes the file pointer.
+        drop(table);
+
+        // now open the table again for adding the column
+        let mut table = Table::open(&table_path, TableOpenMode::ReadWrite).unwrap();
+        table
+            .add_scalar_column(
+                GlueDataType::TpInt,
+                "second",
+                Some("comment2"),
+                false,
+                false,
+            )
+            .unwrap();
+
+        let col_names = table.column_names().unwrap();
+        assert!(col_names.len() == 2);
+    }
+
+    #[test]
+    fn table_add_array_column() {
+        // tempdir is only necessary to avoid writing to disk each time this example is run
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+        // First create a table description for our base table.
+        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        // Define the columns in your table description
+        table_desc
+            .add_array_column(
+                GlueDataType::TpDouble,
+                "UVW",
+                Some("Vector with uvw coordinates (in meters)"),
+                Some(&[3]),
+                true,
+                false,
+            )
+            .unwrap();
+        // Create your new table with 1 rows
+        let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
+        // write to the first row in the uvw column
+        let cell_value: Vec<f64> = vec![1.0, 2.0, 3.0];
+        table.put_cell("UVW", 0, &cell_value).unwrap();
+        // This writes the table to disk and closes the file pointer.
+        drop(table);
+
+        // now open the table again for adding the column
+        let mut table = Table::open(&table_path, TableOpenMode::ReadWrite).unwrap();
+        table
+            .add_array_column(
+                GlueDataType::TpDComplex,
+                "second",
+                Some("comment2"),
+                Some(&[4]),
+                false,
+                false,
+            )
+            .unwrap();
+        let cell_value: Vec<Complex<f64>> = vec![
+            c64::new(1.0, 2.0),
+            c64::new(3.0, 4.0),
+            c64::new(5.0, 6.0),
+            c64::new(7.0, 8.0),
+        ];
+        table.put_cell("second", 0, &cell_value).unwrap();
+
+        let col_names = table.column_names().unwrap();
+        assert!(col_names.len() == 2);
+    }
+
+    #[test]
+    fn table_add_multi_dimensional_array_column() {
+        // tempdir is only necessary to avoid writing to disk each time this example is run
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+        // First create a table description for our base table.
+        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        // Define the columns in your table description
+        table_desc
+            .add_array_column(
+                GlueDataType::TpDouble,
+                "UVW",
+                Some("Vector with uvw coordinates (in meters)"),
+                Some(&[3]),
+                true,
+                false,
+            )
+            .unwrap();
+        // Create your new table with 1 rows
+        let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
+        // write to the first row in the uvw column
+        let cell_value: Vec<f64> = vec![1.0, 2.0, 3.0];
+        table.put_cell("UVW", 0, &cell_value).unwrap();
+        // This writes the table to disk and closes the file pointer.
+        drop(table);
+
+        // now open the table again for adding the column
+        let mut table = Table::open(&table_path, TableOpenMode::ReadWrite).unwrap();
+        let data_shape = [2, 4, 1];
+        table
+            .add_array_column(
+                GlueDataType::TpDComplex,
+                "DATA",
+                None,
+                Some(&data_shape),
+                false,
+                false,
+            )
+            .unwrap();
+
+        let cell_value = array![
+            [
+                [c64::new(1.0, 2.0)],
+                [c64::new(-1.0, -2.0)],
+                [c64::new(3.0, 4.0)],
+                [c64::new(-3.0, -4.0)]
+            ],
+            [
+                [c64::new(5.0, 6.0)],
+                [c64::new(-5.0, -6.0)],
+                [c64::new(7.0, 8.0)],
+                [c64::new(-7.0, -8.0)]
+            ]
+        ];
+        table.put_cell("DATA", 0, &cell_value).unwrap();
+
+        drop(table);
+
+        let mut table = Table::open(&table_path, TableOpenMode::Read).unwrap();
+        let uvw_tabledesc = table.get_col_desc("UVW").unwrap();
+        let data_tabledesc = table.get_col_desc("DATA").unwrap();
+        assert_eq!(uvw_tabledesc.data_type(), GlueDataType::TpDouble);
+        assert_eq!(data_tabledesc.data_type(), GlueDataType::TpDComplex);
+        assert!(uvw_tabledesc.is_fixed_shape());
+        assert!(data_tabledesc.is_fixed_shape());
+        assert!(!uvw_tabledesc.is_scalar());
+        assert!(!data_tabledesc.is_scalar());
+        assert_eq!(data_tabledesc.shape().unwrap(), data_shape);
+        assert_eq!(uvw_tabledesc.shape().unwrap(), &[3]);
+    }
+
+    #[test]
+    fn table_dcomplex_columns() {
+        use rubbl_core::chunked::{ArrayChunks, ArrayCollector};
+
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpDComplex, "GAIN", None, false, false)
+            .unwrap();
+        table_desc
+            .add_array_column(GlueDataType::TpDComplex, "CPARAM", None, None, false, false)
+            .unwrap();
+        table_desc
+            .add_array_column(
+                GlueDataType::TpDComplex,
+                "FIXED",
+                None,
+                Some(&[3, 2]),
+                true,
+                false,
+            )
+            .unwrap();
+
+        let mut table = Table::new(&table_path, table_desc, 0, TableCreateMode::New).unwrap();
+
+        // Variable-shape array cells, with values that would not survive a
+        // trip through single precision.
+        let mut cparam = RaggedArray::new();
+        let a = ndarray::Array2::from_shape_fn((4, 2), |(i, j)| {
+            c64::new(1. + 1e-12 * i as f64, -(j as f64) / 3.)
+        });
+        let b = ndarray::Array2::from_shape_fn((1, 2), |(_, j)| c64::new(j as f64, 1e300));
+        cparam.push(a.view().into_dyn());
+        cparam.push(b.view().into_dyn());
+        cparam.push(a.view().into_dyn());
+        table.put_column("CPARAM", &cparam).unwrap();
+        assert_eq!(table.n_rows(), 3);
+
+        let back = table.get_column::<c64>("CPARAM").unwrap();
+        assert_eq!(back.shapes(), cparam.shapes());
+        assert_eq!(back.values(), cparam.values());
+        let cell: Array2<c64> = table.get_cell("CPARAM", 1).unwrap();
+        assert_eq!(cell, b);
+
+        // Scalar cells.
+        let gains: Vec<c64> = (0..3).map(|i| c64::new(i as f64 / 7., 1e-200)).collect();
+        table
+            .write_column_chunks(
+                "GAIN",
+                0,
+                2,
+                &mut ArrayChunks::new(ndarray::ArrayView1::from(&gains[..])),
+            )
+            .unwrap();
+        assert_eq!(table.get_col_as_vec::<c64>("GAIN").unwrap(), gains);
+        table.put_cell("GAIN", 1, &c64::new(-2.5, 0.125)).unwrap();
+        assert_eq!(
+            table.get_cell::<c64>("GAIN", 1).unwrap(),
+            c64::new(-2.5, 0.125)
+        );
+
+        // Fixed-shape cells through the bulk interfaces.
+        let fixed = ndarray::Array3::from_shape_fn((3, 3, 2), |(r, i, j)| {
+            c64::new(r as f64 + 0.1, (i * 2 + j) as f64 * std::f64::consts::PI)
+        });
+        table
+            .write_column_chunks("FIXED", 0, 2, &mut ArrayChunks::new(fixed.view()))
+            .unwrap();
+        let mut sink = ArrayCollector::<c64>::new(&[3, 2]);
+        table.read_column_chunks("FIXED", 2, &mut sink).unwrap();
+        assert_eq!(sink.into_array(), fixed.into_dyn());
+
+        drop(table);
+        let mut table = Table::open(&table_path, TableOpenMode::Read).unwrap();
+        assert_eq!(
+            table.get_col_desc("GAIN").unwrap().data_type(),
+            GlueDataType::TpDComplex
+        );
+        let cell: Vec<c64> = table.get_cell_as_vec("CPARAM", 0).unwrap();
+        assert_eq!(&cell[..], a.as_slice().unwrap());
+    }
+
+    #[test]
+    fn table_desc_hypercolumn() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_array_column(
+                GlueDataType::TpComplex,
+                "DATA",
+                None,
+                Some(&[3, 2]),
+                true,
+                false,
+            )
+            .unwrap();
+        table_desc
+            .add_array_column(
+                GlueDataType::TpFloat,
+                "WEIGHT_SPECTRUM",
+                None,
+                Some(&[3, 2]),
+                true,
+                false,
+            )
+            .unwrap();
+
+        for name in ["DATA", "WEIGHT_SPECTRUM"] {
+            table_desc
+                .set_data_manager(name, "TiledColumnStMan", "TiledData")
+                .unwrap();
+        }
+
+        table_desc
+            .define_hypercolumn("TiledData", 3, &["DATA", "WEIGHT_SPECTRUM"], &[], &[])
+            .unwrap();
+
+        let mut table = Table::new(&table_path, table_desc, 4, TableCreateMode::New).unwrap();
+        let data = Array2::from_elem((3, 2), Complex::new(1f32, -1.));
+        let weights = Array2::from_elem((3, 2), 0.5f32);
+        table.put_cell("DATA", 3, &data).unwrap();
+        table.put_cell("WEIGHT_SPECTRUM", 3, &weights).unwrap();
+        drop(table);
+
+        let mut table = Table::open(&table_path, TableOpenMode::Read).unwrap();
+        let dm = table.column_data_manager("DATA").unwrap();
+        assert_eq!(dm.1, "TiledColumnStMan");
+        assert_eq!(dm.2, "TiledData");
+        assert_eq!(table.column_data_manager("WEIGHT_SPECTRUM").unwrap(), dm);
+        let back: Array2<f32> = table.get_cell("WEIGHT_SPECTRUM", 3).unwrap();
+        assert_eq!(back, weights);
+    }
+
+    #[test]
+    fn table_add_ndim_1_string_array_column() {
+        // tempdir is only necessary to avoid writing to disk each time this example is run
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+        // First create a table description for our base table.
+        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        // Define the columns in your table description
+        table_desc
+            .add_array_column(
+                GlueDataType::TpString,
+                "APP_PARAMS",
+                Some("Application parameters"),
+                None,
+                false,
+                false,
+            )
+            .unwrap();
+        table_desc.set_ndims("APP_PARAMS", 1).unwrap();
+        // Create your new table with 1 rows
+        let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
+        // write to the first row in the uvw column
+        let cell_value: Vec<String> = vec!["app params".to_string()];
+        table.put_cell("APP_PARAMS", 0, &cell_value).unwrap();
+        // This writes the table to disk and closes the file pointer.
+        drop(table);
+
+        let mut table = Table::open(&table_path, TableOpenMode::Read).unwrap();
+        let aparams_tabledesc = table.get_col_desc("APP_PARAMS").unwrap();
+        assert_eq!(aparams_tabledesc.data_type(), GlueDataType::TpString);
+        assert_eq!(aparams_tabledesc.shape(), None);
+        assert!(!aparams_tabledesc.is_fixed_shape());
+        assert!(!aparams_tabledesc.is_scalar());
+
+        let cell_value_read: Vec<String> = table.get_cell_as_vec("APP_PARAMS", 0).unwrap();
+        assert_eq!(cell_value_read, cell_value);
+    }
+
+    #[test]
+    fn table_get_array_cells() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_array_column(
+                GlueDataType::TpDComplex,
+                "DATA",
+                None,
+                Some(&[2, 1]),
+                false,
+                false,
+            )
+            .unwrap();
+        table_desc
+            .add_array_column(GlueDataType::TpString, "NAMES", None, None, false, false)
+            .unwrap();
+        table_desc.set_ndims("NAMES", 1).unwrap();
+        let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
+
+        let data = array![[c64::new(1.0, 2.0)], [c64::new(-1.0, -2.0)]];
+        table.put_cell("DATA", 0, &data).unwrap();
+        let names = vec!["a".to_owned(), "b".to_owned()];
+        table.put_cell("NAMES", 0, &names).unwrap();
+        drop(table);
+
+        let mut table = Table::open(&table_path, TableOpenMode::Read).unwrap();
+
+        let data_read: Array<c64, ndarray::Ix2> = table.get_cell("DATA", 0).unwrap();
+        assert_eq!(data_read, data);
+        let data_read: Vec<c64> = table.get_cell_as_vec("DATA", 0).unwrap();
+        assert_eq!(data_read, data.iter().cloned().collect::<Vec<_>>());
+
+        let names_read: Vec<String> = table.get_cell("NAMES", 0).unwrap();
+        assert_eq!(names_read, names);
+        let names_read: Vec<String> = table.get_cell_as_vec("NAMES", 0).unwrap();
+        assert_eq!(names_read, names);
+    }
+
+    #[test]
+    pub fn table_put_table_keyword() {
+        let tmp_dir = tempdir().unwrap();
+        let root_table_path = tmp_dir.path().join("test.ms");
+        let sub_table_path = root_table_path.join("SUB");
+        // First create a table description for our base table.
+        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let mut root_table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        // Define the columns in your table description
+        root_table_desc
+            .add_array_column(
+                GlueDataType::TpDouble,
+                "UVW",
+                Some("Vector with uvw coordinates (in meters)"),
+                Some(&[3]),
+                true,
+                false,
+            )
+            .unwrap();
+        // Create your new table with 1 rows
+        let mut root_table =
+            Table::new(&root_table_path, root_table_desc, 1, TableCreateMode::New).unwrap();
+
+        let mut sub_table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        sub_table_desc
+            .add_scalar_column(GlueDataType::TpInt, "int", None, true, false)
+            .unwrap();
+
+        let sub_table =
+            Table::new(&sub_table_path, sub_table_desc, 1, TableCreateMode::New).unwrap();
+        root_table.put_table_keyword("SUB", sub_table).unwrap();
+
+        assert_eq!(root_table.table_keyword_names().unwrap(), ["SUB"]);
+    }
+
+    #[test]
+    pub fn table_put_table_keyword_path() {
+        let tmp_dir = tempdir().unwrap();
+        let root_table_path = tmp_dir.path().join("test.ms");
+        let moved_table_path = tmp_dir.path().join("moved.ms");
+
+        let mut root_table = crate::ms::schema::MAIN.create(&root_table_path, 1).unwrap();
+        crate::ms::schema::ANTENNA
+            .create(root_table_path.join("ANTENNA"), 2)
+            .unwrap();
+        root_table
+            .put_table_keyword_path("ANTENNA", "ANTENNA")
+            .unwrap();
+        assert!(root_table
+            .put_table_keyword_path("FEED", "NO_SUCH_TABLE")
+            .is_err());
+        drop(root_table);
+
+        // The reference is relative, so it survives moving the data set.
+        std::fs::rename(&root_table_path, &moved_table_path).unwrap();
+        let mut root_table = Table::open(&moved_table_path, TableOpenMode::Read).unwrap();
+        assert_eq!(root_table.table_keyword_names().unwrap(), ["ANTENNA"]);
+
+        let reprs = root_table
+            .get_keyword_record()
+            .unwrap()
+            .keyword_names_types_reprs()
+            .unwrap();
+        let (_, data_type, _) = reprs.iter().find(|(n, _, _)| n == "ANTENNA").unwrap();
+        assert_eq!(*data_type, GlueDataType::TpTable);
+    }
+
+    #[test]
+    pub fn tabledesc_put_frequency_meas_desc() {
+        let tmp_dir = tempdir().unwrap();
+        let root_table_path = tmp_dir.path().join("test.ms");
+        let source_table_path = root_table_path.join("SOURCE");
+        // First create a table description for our base table.
+        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let root_table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        Table::new(&root_table_path, root_table_desc, 1, TableCreateMode::New).unwrap();
+
+        let mut source_table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        source_table_desc
+            .add_array_column(
+                glue::GlueDataType::TpDouble,
+                "REST_FREQUENCY",
+                None,
+                None,
+                false,
+                false,
+            )
+            .unwrap();
+
+        source_table_desc
+            .put_column_keyword("REST_FREQUENCY", "QuantumUnits", &vec!["Hz".to_string()])
+            .unwrap();
+
+        let mut meas_info = TableRecord::new().unwrap();
+        meas_info
+            .put_field("type", &"frequency".to_string())
+            .unwrap();
+        meas_info.put_field("Ref", &"LSRK".to_string()).unwrap();
+
+        source_table_desc
+            .put_column_keyword("REST_FREQUENCY", "MEASINFO", &meas_info)
+            .unwrap();
+
+        let mut source_table = Table::new(
+            &source_table_path,
+            source_table_desc,
+            1,
+            TableCreateMode::New,
+        )
+        .unwrap();
+
+        let mut result_tablerec = source_table
+            .get_column_keyword_record("REST_FREQUENCY")
+            .unwrap();
+        let keywords = result_tablerec.keyword_names().unwrap();
+        assert_eq!(keywords, vec!["QuantumUnits", "MEASINFO"]);
+        let col_keywords = source_table.column_keyword_names("REST_FREQUENCY").unwrap();
+        assert_eq!(keywords, col_keywords);
+
+        let units: Vec<String> = result_tablerec.get_field("QuantumUnits").unwrap();
+        assert_eq!(units, ["Hz"]);
+
+        let mut result_meas_info: TableRecord = result_tablerec.get_field("MEASINFO").unwrap();
+
+        let result_meas_info_keywords = result_meas_info.keyword_names().unwrap();
+        assert_eq!(result_meas_info_keywords, vec!["type", "Ref"]);
+
+        let meas_type: String = result_meas_info.get_field("type").unwrap();
+        assert_eq!(meas_type, "frequency");
+        let meas_ref: String = result_meas_info.get_field("Ref").unwrap();
+        assert_eq!(meas_ref, "LSRK");
+    }
+
+    #[test]
+    pub fn tablerec_equality() {
+        let mut rec1 = TableRecord::new().unwrap();
+        rec1.put_field("field1", &"value1".to_string()).unwrap();
+        let mut rec2 = TableRecord::new().unwrap();
+        rec2.put_field("field1", &"value1".to_string()).unwrap();
+        assert_eq!(rec1, rec2);
+
+        rec2.put_field("field2", &"value2".to_string()).unwrap();
+        assert_ne!(rec1, rec2);
+    }
 
     #[test]
-    fn table_create_with_scalar_desc() {
+    pub fn table_put_cell_cached() {
         let tmp_dir = tempdir().unwrap();
         let table_path = tmp_dir.path().join("test.ms");
-
-        let col_name = "test_uint";
-
-        let mut table_desc = TableDesc::new("TEST", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
         table_desc
-            .add_scalar_column(GlueDataType::TpUInt, &col_name, None, false, false)
+            .add_scalar_column(GlueDataType::TpInt, "int", None, true, false)
+            .unwrap();
+        table_desc
+            .add_array_column(GlueDataType::TpDouble, "vec", None, Some(&[3]), true, false)
             .unwrap();
 
-        let mut table = Table::new(table_path, table_desc, 123, TableCreateMode::New).unwrap();
+        let mut table = Table::new(&table_path, table_desc, 3, TableCreateMode::New).unwrap();
 
-        assert_eq!(table.n_rows(), 123);
-        assert_eq!(table.n_columns(), 1);
+        for row in 0..3 {
+            table.put_cell_cached("int", row, &(row as i32)).unwrap();
+            table
+                .put_cell_cached("vec", row, &vec![row as f64; 3])
+                .unwrap();
+        }
 
-        let column_info = table.get_col_desc(&col_name).unwrap();
-        assert_eq!(column_info.data_type(), GlueDataType::TpUInt);
-        assert_eq!(column_info.name(), col_name);
-        assert!(column_info.is_scalar());
+        assert!(table.put_cell_cached("int", 0, &1.5f64).is_err());
+        assert!(table.put_cell_cached("int", 3, &1i32).is_err());
+
+        // Schema changes must invalidate the cached handles.
+        let generation = table.schema_generation();
+        table.rename_column("int", "renamed").unwrap();
+        assert!(table.schema_generation() > generation);
+        assert!(table.put_cell_cached("int", 0, &1i32).is_err());
+        table.put_cell_cached("renamed", 0, &10i32).unwrap();
+
+        table.remove_column("vec").unwrap();
+        assert!(table.put_cell_cached("vec", 0, &vec![1f64; 3]).is_err());
+
+        let values: Vec<i32> = table.get_col_as_vec("renamed").unwrap();
+        assert_eq!(values, [10, 1, 2]);
     }
 
     #[test]
-    fn table_create_with_scalar_string_desc() {
+    pub fn table_endian_format() {
         let tmp_dir = tempdir().unwrap();
-        let table_path = tmp_dir.path().join("test.ms");
-
-        let col_name = "test_string";
-
-        let mut table_desc = TableDesc::new("TEST", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        let table_path = tmp_dir.path().join("big.tab");
+        let copy_path = tmp_dir.path().join("little.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
         table_desc
-            .add_scalar_column(GlueDataType::TpString, &col_name, None, false, false)
+            .add_scalar_column(GlueDataType::TpDouble, "value", None, true, false)
             .unwrap();
 
-        let mut table = Table::new(table_path, table_desc, 123, TableCreateMode::New).unwrap();
+        let mut table = Table::new_with_endian(
+            &table_path,
+            table_desc,
+            1,
+            TableCreateMode::New,
+            EndianFormat::Big,
+        )
+        .unwrap();
+        table.put_cell("value", 0, &1.25f64).unwrap();
+        table
+            .deep_copy(copy_path.to_str().unwrap(), EndianFormat::Little)
+            .unwrap();
+        drop(table);
 
-        assert_eq!(table.n_rows(), 123);
-        assert_eq!(table.n_columns(), 1);
+        let mut table = Table::open(&table_path, TableOpenMode::Read).unwrap();
+        assert_eq!(table.endian_format().unwrap(), EndianFormat::Big);
 
-        let column_info = table.get_col_desc(&col_name).unwrap();
-        assert_eq!(column_info.data_type(), GlueDataType::TpString);
-        assert_eq!(column_info.name(), col_name);
-        assert!(column_info.is_scalar());
+        let mut copy = Table::open(&copy_path, TableOpenMode::Read).unwrap();
+        assert_eq!(copy.endian_format().unwrap(), EndianFormat::Little);
+        let value: f64 = copy.get_cell("value", 0).unwrap();
+        assert_eq!(value, 1.25);
     }
 
     #[test]
-    fn table_create_no_replace() {
+    fn rows_beyond_limit_are_rejected() {
         let tmp_dir = tempdir().unwrap();
-        let table_path = tmp_dir.path().join("test.ms");
-
-        // touch the file
-        OpenOptions::new()
-            .create(true)
-            .write(true)
-            .open(table_path.clone())
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpInt, "value", None, true, false)
             .unwrap();
+        let mut table = Table::new(
+            tmp_dir.path().join("t"),
+            table_desc,
+            3,
+            TableCreateMode::New,
+        )
+        .unwrap();
 
-        let col_name = "test_string";
+        // With 32-bit row numbers, this would wrap around to row 1.
+        let row = (1u64 << 32) + 1;
+        assert!(table.put_cell("value", row, &5i32).is_err());
+        assert!(table.put_cell_cached("value", row, &5i32).is_err());
+        assert!(table.get_cell::<i32>("value", row).is_err());
+        assert_eq!(table.get_cell::<i32>("value", 1).unwrap(), 0);
 
-        let mut table_desc = TableDesc::new("TEST", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        if casacore_max_rows() == u32::MAX as u64 {
+            assert!(table.add_rows(u32::MAX as usize).is_err());
+            assert_eq!(table.n_rows(), 3);
+        }
+    }
+
+    // This creates a table with more than 2^31 rows. The data are stored
+    // with the incremental storage manager, which only records changes of
+    // value, so the table is tiny on disk, but setting it up still takes a
+    // while: run it with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn large_row_count() {
+        let n_rows = (1u64 << 31) + 16;
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("large.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
         table_desc
-            .add_scalar_column(GlueDataType::TpString, &col_name, None, false, false)
+            .add_scalar_column(GlueDataType::TpInt, "value", None, false, false)
+            .unwrap();
+        table_desc
+            .set_data_manager("value", "IncrementalStMan", "ISMData")
             .unwrap();
 
-        // NewNoReplace should fail if table exists.
-        assert!(matches!(
-            Table::new(table_path, table_desc, 123, TableCreateMode::NewNoReplace),
-            Err(TableError::Casacore { .. })
-        ));
+        let mut table = Table::new(
+            &table_path,
+            table_desc,
+            (n_rows - 8) as usize,
+            TableCreateMode::New,
+        )
+        .unwrap();
+        table.add_rows(8).unwrap();
+        assert_eq!(table.n_rows(), n_rows);
+
+        let high = n_rows - 3;
+        table.put_cell("value", high, &7i32).unwrap();
+        table.put_cell("value", high + 1, &0i32).unwrap();
+        table.remove_rows(n_rows - 1, 1).unwrap();
+        drop(table);
+
+        let mut table = Table::open(&table_path, TableOpenMode::Read).unwrap();
+        assert_eq!(table.n_rows(), n_rows - 1);
+        assert_eq!(table.get_cell::<i32>("value", 0).unwrap(), 0);
+        assert_eq!(table.get_cell::<i32>("value", high).unwrap(), 7);
+        assert_eq!(table.get_cell::<i32>("value", high + 1).unwrap(), 0);
+
+        let mut sel = table
+            .select(&select::Select::all().offset(high - 1).limit(2))
+            .unwrap();
+        assert_eq!(sel.n_rows(), 2);
+        assert_eq!(sel.get_col_as_vec::<i32>("value").unwrap(), vec![0, 7]);
+        assert_eq!(sel.row_numbers().unwrap(), vec![high - 1, high]);
     }
 
+    // Like `large_row_count`, but with more than 2^32 rows, written through
+    // the cached column handles. Run it with `cargo test -- --ignored`.
     #[test]
-    fn table_create_with_fixed_string_array_desc() {
-        let tmp_dir = tempdir().unwrap();
-        let table_path = tmp_dir.path().join("test.ms");
+    #[ignore]
+    fn large_row_count_cached() {
+        let n_rows = (1u64 << 32) + 8;
 
-        let col_name = "test_string_fixed";
+        if casacore_max_rows() < n_rows {
+            return;
+        }
 
-        let mut table_desc = TableDesc::new("TEST", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        let tmp_dir = tempdir().unwrap();
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
         table_desc
-            .add_array_column(
-                GlueDataType::TpString,
-                &col_name,
-                None,
-                Some(&[1, 2, 3]),
-                false,
-                false,
-            )
+            .add_scalar_column(GlueDataType::TpInt, "value", None, false, false)
+            .unwrap();
+        table_desc
+            .set_data_manager("value", "IncrementalStMan", "ISMData")
             .unwrap();
 
-        let mut table = Table::new(table_path, table_desc, 123, TableCreateMode::New).unwrap();
+        let mut table = Table::new(
+            tmp_dir.path().join("large.tab"),
+            table_desc,
+            n_rows as usize,
+            TableCreateMode::New,
+        )
+        .unwrap();
 
-        assert_eq!(table.n_rows(), 123);
-        assert_eq!(table.n_columns(), 1);
+        // With 32-bit row numbers, these would land in rows 1 and 2.
+        let high = (1u64 << 32) + 1;
+        table.put_cell_cached("value", high, &7i32).unwrap();
+        table.put_cell_cached("value", high + 1, &0i32).unwrap();
 
-        let column_info = table.get_col_desc(&col_name).unwrap();
-        assert_eq!(column_info.data_type(), GlueDataType::TpString);
-        assert_eq!(column_info.name(), col_name);
-        assert!(!column_info.is_scalar());
-        assert!(column_info.is_fixed_shape());
-        assert_eq!(column_info.shape(), Some(&[1, 2, 3][..]));
+        assert_eq!(table.get_cell::<i32>("value", 1).unwrap(), 0);
+        assert_eq!(table.get_cell::<i32>("value", high).unwrap(), 7);
+        assert_eq!(table.get_cell::<i32>("value", high + 1).unwrap(), 0);
     }
 
     #[test]
-    fn table_create_with_variable_string_array_desc() {
+    fn unusual_paths() {
         let tmp_dir = tempdir().unwrap();
-        let table_path = tmp_dir.path().join("test.ms");
-
-        let col_name = "test_string_var";
-
-        let mut table_desc = TableDesc::new("TEST", TableDescCreateMode::TDM_SCRATCH).unwrap();
-        table_desc
-            .add_array_column(GlueDataType::TpString, &col_name, None, None, false, false)
-            .unwrap();
+        let mut paths = vec![
+            tmp_dir.path().join("with spaces.tab"),
+            tmp_dir.path().join("ünïcödé ☃.tab"),
+            tmp_dir.path().join("x".repeat(200)).join("y".repeat(200)),
+        ];
 
-        let mut table = Table::new(table_path, table_desc, 123, TableCreateMode::New).unwrap();
+        #[cfg(unix)]
+        {
+            use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+            paths.push(
+                tmp_dir
+                    .path()
+                    .join(OsStr::from_bytes(b"latin1 \xe9t\xe9.tab")),
+            );
+        }
 
-        assert_eq!(table.n_rows(), 123);
-        assert_eq!(table.n_columns(), 1);
+        for (i, path) in paths.iter().enumerate() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+            table_desc
+                .add_scalar_column(GlueDataType::TpInt, "value", None, true, false)
+                .unwrap();
+            let mut table = Table::new(path, table_desc, 1, TableCreateMode::New).unwrap();
+            table.put_cell("value", 0, &(i as i32)).unwrap();
+
+            let mut copy_name = path.file_name().unwrap().to_owned();
+            copy_name.push(" copy");
+            let copy_path = path.with_file_name(copy_name);
+            table
+                .deep_copy_to_path(&copy_path, EndianFormat::Local)
+                .unwrap();
+            drop(table);
+
+            for p in &[path, &copy_path] {
+                let mut table = Table::open(p, TableOpenMode::Read).unwrap();
+                assert_eq!(table.get_cell::<i32>("value", 0).unwrap(), i as i32);
+            }
+        }
 
-        let column_info = table.get_col_desc(&col_name).unwrap();
-        assert_eq!(column_info.data_type(), GlueDataType::TpString);
-        assert_eq!(column_info.name(), col_name);
-        assert!(!column_info.is_scalar());
-        assert!(!column_info.is_fixed_shape());
-        assert_eq!(column_info.shape(), None);
+        let nul_path = tmp_dir.path().join("nul\0byte.tab");
+        assert!(matches!(
+            Table::open(&nul_path, TableOpenMode::Read),
+            Err(TableError::InvalidPath(_))
+        ));
     }
 
     #[test]
-    fn table_create_write_open_read_cell() {
-        // tempdir is only necessary to avoid writing to disk each time this example is run
+    pub fn table_complex_array_zero_copy() {
         let tmp_dir = tempdir().unwrap();
-        let table_path = tmp_dir.path().join("test.ms");
-        // First create a table description for our base table.
-        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let table_path = tmp_dir.path().join("test.tab");
         let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
-        // Define the columns in your table description
         table_desc
             .add_array_column(
-                GlueDataType::TpDouble,
-                "UVW",
-                Some("Vector with uvw coordinates (in meters)"),
-                Some(&[3]),
+                GlueDataType::TpComplex,
+                "DATA",
+                None,
+                Some(&[3, 4]),
                 true,
                 false,
             )
             .unwrap();
-        // Create your new table with 1 rows
+
         let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
-        // write to the first row in the uvw column
-        let cell_value: Vec<f64> = vec![1.0, 2.0, 3.0];
-        table.put_cell("UVW", 0, &cell_value).unwrap();
-        // This writes the table to disk and closes the file pointer.
-        drop(table);
+        let data = Array2::from_shape_fn((3, 4), |(i, j)| Complex::new(i as f32, -(j as f32)));
+        table.put_cell("DATA", 0, &data).unwrap();
 
-        // now open the table again for reading cells
-        let mut table = Table::open(&table_path, TableOpenMode::Read).unwrap();
-        // and extract the cell value we wrote earlier
-        let extracted_cell_value: Vec<f64> = table.get_cell_as_vec("UVW", 0).unwrap();
-        assert_eq!(cell_value, extracted_cell_value);
+        let back: Array2<Complex<f32>> = table.get_cell("DATA", 0).unwrap();
+        assert_eq!(back, data);
     }
 
     #[test]
-    fn table_add_scalar_column() {
-        // tempdir is only necessary to avoid writing to disk each time this example is run
+    pub fn table_put_cell_non_standard_layout() {
         let tmp_dir = tempdir().unwrap();
-        let table_path = tmp_dir.path().join("test.ms");
-        // First create a table description for our base table.
-        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let table_path = tmp_dir.path().join("test.tab");
         let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
-        // Define the columns in your table description
         table_desc
             .add_array_column(
-                GlueDataType::TpDouble,
-                "UVW",
-                Some("Vector with uvw coordinates (in meters)"),
-                Some(&[3]),
+                GlueDataType::TpComplex,
+                "DATA",
+                None,
+                Some(&[3, 4]),
                 true,
                 false,
             )
             .unwrap();
-        // Create your new table with 1 rows
+
         let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
-        // write to the first row in the uvw column
-        let cell_value: Vec<f64> = vec![1.0, 2.0, 3.0];
-        table.put_cell("UVW", 0, &cell_value).unwrap();
-        // This writes the table to disk and closes the file pointer.
-        drop(table);
+        let data = Array2::from_shape_fn((4, 3), |(i, j)| Complex::new(i as f32, j as f32))
+            .reversed_axes();
+        assert!(!data.is_standard_layout());
+        table.put_cell("DATA", 0, &data).unwrap();
+
+        let back: Array2<Complex<f32>> = table.get_cell("DATA", 0).unwrap();
+        assert_eq!(back, data);
+        assert_eq!(back[[2, 1]], Complex::new(1., 2.));
+    }
 
-        // now open the table again for adding the column
-        let mut table = Table::open(&table_path, TableOpenMode::ReadWrite).unwrap();
-        table
-            .add_scalar_column(
-                GlueDataType::TpInt,
-                "second",
-                Some("comment2"),
-                false,
+    #[test]
+    pub fn table_column_chunks() {
+        use rubbl_core::chunked::{ArrayChunks, ArrayCollector};
+
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_array_column(
+                GlueDataType::TpComplex,
+                "DATA",
+                None,
+                Some(&[3, 2]),
+                true,
                 false,
             )
             .unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpDouble, "TIME", None, true, false)
+            .unwrap();
 
-        let col_names = table.column_names().unwrap();
-        assert!(col_names.len() == 2);
+        let mut table = Table::new(&table_path, table_desc, 0, TableCreateMode::New).unwrap();
+
+        let data = ndarray::Array3::from_shape_fn((7, 3, 2), |(r, i, j)| {
+            Complex::new(r as f32, (2 * i + j) as f32)
+        });
+        let n = table
+            .write_column_chunks("DATA", 0, 3, &mut ArrayChunks::new(data.view()))
+            .unwrap();
+        assert_eq!(n, 7);
+        assert_eq!(table.n_rows(), 7);
+
+        let times = ndarray::Array1::from_shape_fn(7, |r| r as f64 * 10.);
+        table
+            .write_column_chunks("TIME", 0, 4, &mut ArrayChunks::new(times.view()))
+            .unwrap();
+
+        let mut sink = ArrayCollector::<Complex<f32>>::new(&[3, 2]);
+        table.read_column_chunks("DATA", 2, &mut sink).unwrap();
+        assert_eq!(sink.into_array(), data.into_dyn());
+
+        let mut sink = ArrayCollector::<f64>::new(&[]);
+        table.read_column_chunks("TIME", 5, &mut sink).unwrap();
+        assert_eq!(sink.into_array(), times.into_dyn());
+
+        let wrong = ndarray::Array3::<Complex<f32>>::zeros((2, 2, 3));
+        assert!(table
+            .write_column_chunks("DATA", 0, 3, &mut ArrayChunks::new(wrong.view()))
+            .is_err());
     }
 
     #[test]
-    fn table_add_array_column() {
-        // tempdir is only necessary to avoid writing to disk each time this example is run
+    pub fn table_column_chunks_where() {
+        use rubbl_core::chunked::{ArrayChunks, ArrayCollector};
+
         let tmp_dir = tempdir().unwrap();
-        let table_path = tmp_dir.path().join("test.ms");
-        // First create a table description for our base table.
-        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let table_path = tmp_dir.path().join("test.tab");
         let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
-        // Define the columns in your table description
         table_desc
-            .add_array_column(
-                GlueDataType::TpDouble,
-                "UVW",
-                Some("Vector with uvw coordinates (in meters)"),
-                Some(&[3]),
-                true,
-                false,
-            )
+            .add_array_column(GlueDataType::TpFloat, "DATA", None, Some(&[2]), true, false)
+            .unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpDouble, "TIME", None, true, false)
             .unwrap();
-        // Create your new table with 1 rows
-        let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
-        // write to the first row in the uvw column
-        let cell_value: Vec<f64> = vec![1.0, 2.0, 3.0];
-        table.put_cell("UVW", 0, &cell_value).unwrap();
-        // This writes the table to disk and closes the file pointer.
-        drop(table);
 
-        // now open the table again for adding the column
-        let mut table = Table::open(&table_path, TableOpenMode::ReadWrite).unwrap();
+        for name in ["ANTENNA1", "ANTENNA2", "DATA_DESC_ID", "FIELD_ID"] {
+            table_desc
+                .add_scalar_column(GlueDataType::TpInt, name, None, true, false)
+                .unwrap();
+        }
+
+        let mut table = Table::new(&table_path, table_desc, 0, TableCreateMode::New).unwrap();
+        let data = Array2::from_shape_fn((9, 2), |(r, i)| (2 * r + i) as f32);
         table
-            .add_array_column(
-                GlueDataType::TpDComplex,
-                "second",
-                Some("comment2"),
-                Some(&[4]),
-                false,
-                false,
-            )
+            .write_column_chunks("DATA", 0, 4, &mut ArrayChunks::new(data.view()))
             .unwrap();
-        let cell_value: Vec<Complex<f64>> = vec![
-            c64::new(1.0, 2.0),
-            c64::new(3.0, 4.0),
-            c64::new(5.0, 6.0),
-            c64::new(7.0, 8.0),
-        ];
-        table.put_cell("second", 0, &cell_value).unwrap();
 
-        let col_names = table.column_names().unwrap();
-        assert!(col_names.len() == 2);
+        for row in 0..9 {
+            table.put_cell("TIME", row, &(row as f64)).unwrap();
+            table
+                .put_cell("ANTENNA1", row, &((row / 3) as i32))
+                .unwrap();
+            table
+                .put_cell("ANTENNA2", row, &((row % 3) as i32))
+                .unwrap();
+            table.put_cell("DATA_DESC_ID", row, &0i32).unwrap();
+            table.put_cell("FIELD_ID", row, &0i32).unwrap();
+        }
+
+        let mut sink = ArrayCollector::<f32>::new(&[2]);
+        let n = table
+            .read_column_chunks_where("DATA", 4, |r| r.antenna1 != r.antenna2, &mut sink)
+            .unwrap();
+        assert_eq!(n, 6);
+        let expected = data.select(ndarray::Axis(0), &[1, 2, 3, 5, 6, 7]);
+        assert_eq!(sink.into_array(), expected.into_dyn());
+
+        let mut sink = ArrayCollector::<f32>::new(&[2]);
+        let n = table
+            .read_column_chunks_where("DATA", 4, |_| true, &mut sink)
+            .unwrap();
+        assert_eq!(n, 9);
+        assert_eq!(sink.into_array(), data.into_dyn());
     }
 
     #[test]
-    fn table_add_multi_dimensional_array_column() {
-        // tempdir is only necessary to avoid writing to disk each time this example is run
+    pub fn table_put_cells_masked() {
         let tmp_dir = tempdir().unwrap();
-        let table_path = tmp_dir.path().join("test.ms");
-        // First create a table description for our base table.
-        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let table_path = tmp_dir.path().join("test.tab");
         let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
-        // Define the columns in your table description
         table_desc
             .add_array_column(
-                GlueDataType::TpDouble,
-                "UVW",
-                Some("Vector with uvw coordinates (in meters)"),
-                Some(&[3]),
+                GlueDataType::TpBool,
+                "FLAG",
+                None,
+                Some(&[2, 2]),
                 true,
                 false,
             )
             .unwrap();
-        // Create your new table with 1 rows
-        let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
-        // write to the first row in the uvw column
-        let cell_value: Vec<f64> = vec![1.0, 2.0, 3.0];
-        table.put_cell("UVW", 0, &cell_value).unwrap();
-        // This writes the table to disk and closes the file pointer.
-        drop(table);
+        let mut table = Table::new(&table_path, table_desc, 4, TableCreateMode::New).unwrap();
 
-        // now open the table again for adding the column
-        let mut table = Table::open(&table_path, TableOpenMode::ReadWrite).unwrap();
-        let data_shape = [2, 4, 1];
+        let initial = ndarray::Array2::from_shape_fn((2, 2), |(i, _)| i == 0);
+        for row in 0..4 {
+            table.put_cell("FLAG", row, &initial).unwrap();
+        }
+
+        // Set the second polarization of rows 1 and 2 to true.
+        let values = ndarray::Array3::from_elem((3, 2, 2), true);
+        let mask = ndarray::Array3::from_shape_fn((3, 2, 2), |(r, _, j)| r < 2 && j == 1);
         table
-            .add_array_column(
-                GlueDataType::TpDComplex,
-                "DATA",
-                None,
-                Some(&data_shape),
-                false,
-                false,
-            )
+            .put_cells_masked("FLAG", 1, values.view(), mask.view())
             .unwrap();
 
-        let cell_value = array![
-            [
-                [c64::new(1.0, 2.0)],
-                [c64::new(-1.0, -2.0)],
-                [c64::new(3.0, 4.0)],
-                [c64::new(-3.0, -4.0)]
-            ],
-            [
-                [c64::new(5.0, 6.0)],
-                [c64::new(-5.0, -6.0)],
-                [c64::new(7.0, 8.0)],
-                [c64::new(-7.0, -8.0)]
-            ]
-        ];
-        table.put_cell("DATA", 0, &cell_value).unwrap();
+        let expected = |row: u64| -> Vec<bool> {
+            if row == 1 || row == 2 {
+                vec![true, true, false, true]
+            } else {
+                vec![true, true, false, false]
+            }
+        };
 
-        drop(table);
+        for row in 0..4 {
+            let flags: Vec<bool> = table.get_cell_as_vec("FLAG", row).unwrap();
+            assert_eq!(flags, expected(row));
+        }
 
-        let mut table = Table::open(&table_path, TableOpenMode::Read).unwrap();
-        let uvw_tabledesc = table.get_col_desc("UVW").unwrap();
-        let data_tabledesc = table.get_col_desc("DATA").unwrap();
-        assert_eq!(uvw_tabledesc.data_type(), GlueDataType::TpDouble);
-        assert_eq!(data_tabledesc.data_type(), GlueDataType::TpDComplex);
-        assert!(uvw_tabledesc.is_fixed_shape());
-        assert!(data_tabledesc.is_fixed_shape());
-        assert!(!uvw_tabledesc.is_scalar());
-        assert!(!data_tabledesc.is_scalar());
-        assert_eq!(data_tabledesc.shape().unwrap(), data_shape);
-        assert_eq!(uvw_tabledesc.shape().unwrap(), &[3]);
+        assert!(table
+            .put_cells_masked("FLAG", 2, values.view(), mask.view())
+            .is_err());
+        assert!(table
+            .put_cells_masked("FLAG", 0, values.view(), mask.t())
+            .is_err());
     }
 
     #[test]
-    fn table_add_ndim_1_string_array_column() {
-        // tempdir is only necessary to avoid writing to disk each time this example is run
+    pub fn table_column_shape() {
         let tmp_dir = tempdir().unwrap();
-        let table_path = tmp_dir.path().join("test.ms");
-        // First create a table description for our base table.
-        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
+        let table_path = tmp_dir.path().join("test.tab");
         let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
-        // Define the columns in your table description
+        table_desc
+            .add_scalar_column(GlueDataType::TpDouble, "TIME", None, false, false)
+            .unwrap();
         table_desc
             .add_array_column(
-                GlueDataType::TpString,
-                "APP_PARAMS",
-                Some("Application parameters"),
+                GlueDataType::TpFloat,
+                "FIXED",
                 None,
-                false,
+                Some(&[3, 2]),
+                true,
                 false,
             )
             .unwrap();
-        table_desc.set_ndims("APP_PARAMS", 1).unwrap();
-        // Create your new table with 1 rows
-        let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
-        // write to the first row in the uvw column
-        let cell_value: Vec<String> = vec!["app params".to_string()];
-        table.put_cell("APP_PARAMS", 0, &cell_value).unwrap();
-        // This writes the table to disk and closes the file pointer.
-        drop(table);
+        table_desc
+            .add_array_column(GlueDataType::TpFloat, "ANY", None, None, false, false)
+            .unwrap();
+        table_desc
+            .add_array_column(GlueDataType::TpFloat, "VECTORS", None, None, false, false)
+            .unwrap();
+        table_desc.set_ndims("VECTORS", 1).unwrap();
+        let mut table = Table::new(&table_path, table_desc, 2, TableCreateMode::New).unwrap();
 
-        let mut table = Table::open(&table_path, TableOpenMode::Read).unwrap();
-        let aparams_tabledesc = table.get_col_desc("APP_PARAMS").unwrap();
-        assert_eq!(aparams_tabledesc.data_type(), GlueDataType::TpString);
-        assert_eq!(aparams_tabledesc.shape(), None);
-        assert!(!aparams_tabledesc.is_fixed_shape());
-        assert!(!aparams_tabledesc.is_scalar());
+        assert_eq!(table.column_shape("TIME").unwrap(), ColumnShapeInfo::Scalar);
 
-        let cell_value_read: Vec<String> = table.get_cell_as_vec("APP_PARAMS", 0).unwrap();
-        assert_eq!(cell_value_read, cell_value);
+        let fixed = table.column_shape("FIXED").unwrap();
+        assert_eq!(fixed, ColumnShapeInfo::Fixed(vec![3, 2]));
+        assert_eq!(fixed.ndim(), Some(2));
+
+        let any = table.column_shape("ANY").unwrap();
+        assert_eq!(any, ColumnShapeInfo::Variable { ndim: None });
+        assert_eq!(any.fixed_shape(), None);
+
+        assert_eq!(
+            table.column_shape("VECTORS").unwrap(),
+            ColumnShapeInfo::Variable { ndim: Some(1) }
+        );
+
+        table.put_cell("ANY", 1, &vec![1f32, 2., 3.]).unwrap();
+        assert_eq!(table.get_cell_shape("ANY", 1).unwrap(), vec![3]);
     }
 
     #[test]
-    pub fn table_put_table_keyword() {
+    pub fn table_snapshot() {
         let tmp_dir = tempdir().unwrap();
-        let root_table_path = tmp_dir.path().join("test.ms");
-        let sub_table_path = root_table_path.join("SUB");
-        // First create a table description for our base table.
-        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
-        let mut root_table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
-        // Define the columns in your table description
-        root_table_desc
-            .add_array_column(
-                GlueDataType::TpDouble,
-                "UVW",
-                Some("Vector with uvw coordinates (in meters)"),
-                Some(&[3]),
-                true,
-                false,
-            )
+        let table_path = tmp_dir.path().join("test.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpDouble, "TIME", None, true, false)
             .unwrap();
-        // Create your new table with 1 rows
-        let mut root_table =
-            Table::new(&root_table_path, root_table_desc, 1, TableCreateMode::New).unwrap();
+        let mut table = Table::new(&table_path, table_desc, 3, TableCreateMode::New).unwrap();
+        for row in 0..3 {
+            table.put_cell("TIME", row, &(row as f64)).unwrap();
+        }
 
-        let mut sub_table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
-        sub_table_desc
-            .add_scalar_column(GlueDataType::TpInt, "int", None, true, false)
-            .unwrap();
+        let mut snap = table.snapshot().unwrap();
+        table.add_rows(2).unwrap();
+        table.put_cell("TIME", 4, &9.).unwrap();
 
-        let sub_table =
-            Table::new(&sub_table_path, sub_table_desc, 1, TableCreateMode::New).unwrap();
-        root_table.put_table_keyword("SUB", sub_table).unwrap();
+        assert_eq!(table.n_rows(), 5);
+        assert_eq!(snap.n_rows(), 3);
+        assert_eq!(
+            snap.get_col_as_vec::<f64>("TIME").unwrap(),
+            vec![0., 1., 2.]
+        );
 
-        assert_eq!(root_table.table_keyword_names().unwrap(), ["SUB"]);
+        assert!(snap.put_cell("TIME", 1, &5.).is_err());
+        assert!(snap.put_cell_cached("TIME", 1, &5.).is_err());
+        assert!(snap.put_keyword("KW", &1i32).is_err());
+        assert_eq!(table.get_cell::<f64>("TIME", 1).unwrap(), 1.);
+        assert!(table
+            .get_keyword_record()
+            .unwrap()
+            .keyword_names()
+            .unwrap()
+            .is_empty());
     }
 
     #[test]
-    pub fn tabledesc_put_frequency_meas_desc() {
+    pub fn table_kinds() {
         let tmp_dir = tempdir().unwrap();
-        let root_table_path = tmp_dir.path().join("test.ms");
-        let source_table_path = root_table_path.join("SOURCE");
-        // First create a table description for our base table.
-        // Use TDM_SCRATCH to avoid writing the .tabdsc to disk.
-        let root_table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
-        Table::new(&root_table_path, root_table_desc, 1, TableCreateMode::New).unwrap();
-
-        let mut source_table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
-        source_table_desc
-            .add_array_column(
-                glue::GlueDataType::TpDouble,
-                "REST_FREQUENCY",
-                None,
-                None,
-                false,
-                false,
-            )
+        let table_path = tmp_dir.path().join("test.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpDouble, "TIME", None, true, false)
             .unwrap();
+        let mut table = Table::new(&table_path, table_desc, 3, TableCreateMode::New).unwrap();
+        let name = table.file_name().unwrap();
 
-        source_table_desc
-            .put_column_keyword("REST_FREQUENCY", "QuantumUnits", &vec!["Hz".to_string()])
-            .unwrap();
+        assert_eq!(table.kind(), TableKind::Plain);
+        assert!(!table.is_reference());
+        assert_eq!(table.root_table_path().unwrap(), name);
 
-        let mut meas_info = TableRecord::new().unwrap();
-        meas_info
-            .put_field("type", &"frequency".to_string())
+        let mut snap = table.snapshot().unwrap();
+        assert_eq!(snap.kind(), TableKind::Reference);
+        assert!(snap.is_reference());
+        assert_eq!(snap.root_table_paths().unwrap(), vec![name]);
+    }
+
+    #[test]
+    pub fn table_row_numbers() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpDouble, "TIME", None, true, false)
             .unwrap();
-        meas_info.put_field("Ref", &"LSRK".to_string()).unwrap();
+        let mut table = Table::new(&table_path, table_desc, 5, TableCreateMode::New).unwrap();
+        assert_eq!(table.row_numbers().unwrap(), vec![0, 1, 2, 3, 4]);
 
-        source_table_desc
-            .put_column_keyword("REST_FREQUENCY", "MEASINFO", &meas_info)
+        for row in 0..5 {
+            table
+                .put_cell("TIME", row, &((row * 7 % 5) as f64))
+                .unwrap();
+        }
+
+        // A snapshot refers to every row of the table.
+        let mut snap = table.snapshot().unwrap();
+        assert_eq!(snap.row_numbers().unwrap(), vec![0, 1, 2, 3, 4]);
+
+        // A selection of a sort refers back to the original rows.
+        let mut sorted = table
+            .select(&select::Select::all().order_by("TIME"))
             .unwrap();
+        let mut view = sorted
+            .select(&select::Select::all().filter(select::col("TIME").gt(1)))
+            .unwrap();
+        assert_eq!(
+            view.get_col_as_vec::<f64>("TIME").unwrap(),
+            vec![2., 3., 4.]
+        );
+        let rows = view.row_numbers().unwrap();
+        assert_eq!(rows, vec![1, 4, 2]);
 
-        let mut source_table = Table::new(
-            &source_table_path,
-            source_table_desc,
-            1,
-            TableCreateMode::New,
-        )
-        .unwrap();
+        for (i, row) in rows.into_iter().enumerate() {
+            let t: f64 = view.get_cell("TIME", i as u64).unwrap();
+            assert_eq!(table.get_cell::<f64>("TIME", row).unwrap(), t);
+        }
+    }
 
-        let mut result_tablerec = source_table
-            .get_column_keyword_record("REST_FREQUENCY")
+    #[test]
+    pub fn table_cell_slices() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_array_column(
+                GlueDataType::TpFloat,
+                "MAP",
+                None,
+                Some(&[4, 5]),
+                true,
+                false,
+            )
             .unwrap();
-        let keywords = result_tablerec.keyword_names().unwrap();
-        assert_eq!(keywords, vec!["QuantumUnits", "MEASINFO"]);
-        let col_keywords = source_table.column_keyword_names("REST_FREQUENCY").unwrap();
-        assert_eq!(keywords, col_keywords);
 
-        let units: Vec<String> = result_tablerec.get_field("QuantumUnits").unwrap();
-        assert_eq!(units, ["Hz"]);
+        let mut table = Table::new(&table_path, table_desc, 1, TableCreateMode::New).unwrap();
+        assert_eq!(table.get_cell_shape("MAP", 0).unwrap(), vec![4, 5]);
 
-        let mut result_meas_info: TableRecord = result_tablerec.get_field("MEASINFO").unwrap();
+        let data = Array2::from_shape_fn((4, 5), |(i, j)| (10 * i + j) as f32);
+        table.put_cell("MAP", 0, &data).unwrap();
 
-        let result_meas_info_keywords = result_meas_info.keyword_names().unwrap();
-        assert_eq!(result_meas_info_keywords, vec!["type", "Ref"]);
+        let slice = table
+            .get_cell_slice::<f32>("MAP", 0, &[1, 2], &[2, 3])
+            .unwrap();
+        assert_eq!(slice, data.slice(ndarray::s![1..3, 2..5]).into_dyn());
 
-        let meas_type: String = result_meas_info.get_field("type").unwrap();
-        assert_eq!(meas_type, "frequency");
-        let meas_ref: String = result_meas_info.get_field("Ref").unwrap();
-        assert_eq!(meas_ref, "LSRK");
+        let patch = Array2::from_elem((2, 2), -1f32);
+        table
+            .put_cell_slice("MAP", 0, &[2, 0], patch.view().into_dyn())
+            .unwrap();
+        let all: Array2<f32> = table.get_cell("MAP", 0).unwrap();
+        assert_eq!(all[[3, 1]], -1.);
+        assert_eq!(all[[3, 2]], 32.);
+
+        assert!(table
+            .get_cell_slice::<f32>("MAP", 0, &[3, 0], &[2, 1])
+            .is_err());
+        assert!(table
+            .get_cell_slice::<f64>("MAP", 0, &[0, 0], &[1, 1])
+            .is_err());
     }
 
     #[test]
-    pub fn tablerec_equality() {
-        let mut rec1 = TableRecord::new().unwrap();
-        rec1.put_field("field1", &"value1".to_string()).unwrap();
-        let mut rec2 = TableRecord::new().unwrap();
-        rec2.put_field("field1", &"value1".to_string()).unwrap();
-        assert_eq!(rec1, rec2);
+    pub fn table_open_no_lock() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.ms");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_scalar_column(GlueDataType::TpInt, "int", None, true, false)
+            .unwrap();
 
-        rec2.put_field("field2", &"value2".to_string()).unwrap();
-        assert_ne!(rec1, rec2);
+        let mut writer = Table::new(&table_path, table_desc, 2, TableCreateMode::New).unwrap();
+        writer.put_cell("int", 1, &17i32).unwrap();
+        drop(writer);
+
+        // Keep a writer open while the reader looks at the table. Within one
+        // process, casacore shares the open table between the two, so the
+        // writer does its work before the reader changes its locking.
+        let mut writer = Table::open(&table_path, TableOpenMode::ReadWrite).unwrap();
+        writer.put_cell("int", 0, &3i32).unwrap();
+
+        let mut reader = Table::open(&table_path, TableOpenMode::ReadNoLock).unwrap();
+        let value: i32 = reader.get_cell("int", 1).unwrap();
+        assert_eq!(value, 17);
     }
 
     #[test]
@@ -3247,4 +6895,67 @@ mod tests {
 
         assert!(table_debug.contains(root_table_path.to_str().unwrap()));
     }
+
+    #[test]
+    fn missing_data_manager_message() {
+        assert_eq!(
+            unregistered_data_manager(
+                "Data Manager class DyscoStMan is not registered\n  Check (DY)LD_LIBRARY_PATH"
+            ),
+            Some("DyscoStMan".to_owned())
+        );
+        assert_eq!(unregistered_data_manager("Table foo does not exist"), None);
+        assert_eq!(
+            missing_data_manager_library(
+                "Shared library dyscostman not found in CASACORE_LDPATH or (DY)LD_LIBRARY_PATH\n"
+            ),
+            Some("dyscostman")
+        );
+        assert_eq!(
+            missing_data_manager_library("Table foo does not exist"),
+            None
+        );
+        assert!(data_manager_available("StandardStMan").unwrap());
+        assert!(!data_manager_available("NoSuchStMan").unwrap());
+    }
+
+    #[test]
+    fn callback_panics_become_errors() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "A", None, false, false)
+            .unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "B", None, false, false)
+            .unwrap();
+        let mut t = Table::new(tmp_dir.path().join("t"), desc, 1, TableCreateMode::New).unwrap();
+
+        let mut n_calls = 0;
+        let rv = unsafe {
+            invoke_table_get_column_names(t.handle, &mut t.exc_info, |_| {
+                n_calls += 1;
+                panic!("boom");
+            })
+        };
+        assert_ne!(rv, 0);
+        assert_eq!(n_calls, 1);
+
+        match t.exc_info.as_err::<(), TableError>() {
+            Err(TableError::Ffi { context, message }) => {
+                assert_eq!(context, "callback from table_get_column_names");
+                assert_eq!(message, "boom");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // The table is still usable afterwards.
+        assert_eq!(t.column_names().unwrap(), vec!["A", "B"]);
+        assert_eq!(
+            ffi_failure("FFI failure in C++ glue: unidentifiable C++ exception occurred"),
+            Some((
+                "C++ glue".to_owned(),
+                "unidentifiable C++ exception occurred".to_owned()
+            ))
+        );
+        assert_eq!(ffi_failure("Table foo does not exist"), None);
+    }
 }
@@ -0,0 +1,325 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Checksum manifests for table directories.
+//!
+//! A CASA table is a directory of files, and a Measurement Set is a tree of
+//! them. Copies of these trees made over unreliable links or kept for years
+//! in an archive can end up truncated or corrupted without casacore noticing
+//! until it reads garbage. A manifest records the size and XXH64 checksum of
+//! every file in the tree so that a copy can be checked against the original
+//! later with [`verify_manifest`].
+//!
+//! The manifest is a text file with one line per file, sorted by path:
+//!
+//! ```text
+//! <16 hex digits of XXH64>  <size in bytes>  <path relative to the table>
+//! ```
+//!
+//! Lock files are left out, since casacore rewrites them whenever the table
+//! is opened.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use xxhash_rust::xxh64::Xxh64;
+
+use crate::{Table, TableError};
+
+/// The name of the manifest file written inside a table directory.
+pub const MANIFEST_FILE_NAME: &str = "MANIFEST.xxh64";
+
+/// The name of the lock files that casacore keeps in table directories.
+const LOCK_FILE_NAME: &str = "table.lock";
+
+/// An error that can occur when writing or verifying a manifest.
+#[derive(Error, Debug)]
+pub enum ManifestError {
+    /// An error occurred while flushing the table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// An I/O error occurred.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// A path in the table directory is not valid UTF-8.
+    #[error("path {0:?} in the table directory is not valid UTF-8")]
+    NonUtf8Path(PathBuf),
+
+    /// A line of the manifest could not be parsed.
+    #[error("malformed line {0} in the manifest")]
+    Malformed(usize),
+}
+
+/// One entry of a manifest.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Entry {
+    size: u64,
+    checksum: u64,
+}
+
+/// The result of [`verify_manifest`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ManifestReport {
+    /// The number of files listed in the manifest.
+    pub n_files: usize,
+
+    /// Files whose size differs from the one recorded: usually a sign of a
+    /// truncated copy.
+    pub size_mismatches: Vec<PathBuf>,
+
+    /// Files of the recorded size whose contents have a different checksum.
+    pub checksum_mismatches: Vec<PathBuf>,
+
+    /// Files listed in the manifest that do not exist.
+    pub missing: Vec<PathBuf>,
+
+    /// Files that exist but are not listed in the manifest.
+    pub unexpected: Vec<PathBuf>,
+}
+
+impl ManifestReport {
+    /// Return true if every file matches the manifest and there are no
+    /// extra files.
+    pub fn is_ok(&self) -> bool {
+        self.size_mismatches.is_empty()
+            && self.checksum_mismatches.is_empty()
+            && self.missing.is_empty()
+            && self.unexpected.is_empty()
+    }
+}
+
+/// Write a manifest of the table directory at *table_path* into the file
+/// [`MANIFEST_FILE_NAME`] within it.
+///
+/// The table should not be open for writing elsewhere, or the manifest may
+/// not match what ends up on disk; use [`Table::write_manifest`] on an open
+/// table. Returns the number of files listed.
+pub fn write_manifest<P: AsRef<Path>>(table_path: P) -> Result<usize, ManifestError> {
+    let table_path = table_path.as_ref();
+    let entries = scan(table_path)?;
+    let tmp_path = table_path.join(format!("{}.tmp", MANIFEST_FILE_NAME));
+
+    {
+        let mut f = BufWriter::new(File::create(&tmp_path)?);
+
+        for (path, entry) in &entries {
+            writeln!(f, "{:016x}  {}  {}", entry.checksum, entry.size, path)?;
+        }
+
+        f.flush()?;
+        f.get_ref().sync_all()?;
+    }
+
+    fs::rename(&tmp_path, table_path.join(MANIFEST_FILE_NAME))?;
+    Ok(entries.len())
+}
+
+/// Check the table directory at *table_path* against the manifest stored in
+/// it by [`write_manifest`].
+///
+/// Every file is read in full. Problems with the files are listed in the
+/// returned report; an error is only returned if the manifest itself cannot
+/// be read.
+pub fn verify_manifest<P: AsRef<Path>>(table_path: P) -> Result<ManifestReport, ManifestError> {
+    let table_path = table_path.as_ref();
+    let expected = read_manifest(&table_path.join(MANIFEST_FILE_NAME))?;
+    let actual = scan(table_path)?;
+    let mut report = ManifestReport {
+        n_files: expected.len(),
+        ..Default::default()
+    };
+
+    for (path, want) in &expected {
+        match actual.get(path) {
+            None => report.missing.push(path.into()),
+            Some(have) if have.size != want.size => report.size_mismatches.push(path.into()),
+            Some(have) if have.checksum != want.checksum => {
+                report.checksum_mismatches.push(path.into())
+            }
+            Some(_) => {}
+        }
+    }
+
+    for path in actual.keys() {
+        if !expected.contains_key(path) {
+            report.unexpected.push(path.into());
+        }
+    }
+
+    Ok(report)
+}
+
+fn read_manifest(path: &Path) -> Result<BTreeMap<String, Entry>, ManifestError> {
+    let mut entries = BTreeMap::new();
+
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        let mut pieces = line.splitn(3, "  ");
+        let checksum = pieces.next().and_then(|s| u64::from_str_radix(s, 16).ok());
+        let size = pieces.next().and_then(|s| s.parse().ok());
+        let path = pieces.next();
+
+        match (checksum, size, path) {
+            (Some(checksum), Some(size), Some(path)) => {
+                entries.insert(path.to_owned(), Entry { size, checksum });
+            }
+            _ => return Err(ManifestError::Malformed(i + 1)),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Checksum every file under *root*, keyed by its path relative to *root*
+/// with `/` as the separator.
+fn scan(root: &Path) -> Result<BTreeMap<String, Entry>, ManifestError> {
+    let mut entries = BTreeMap::new();
+    let mut dirs = vec![PathBuf::new()];
+
+    while let Some(rel_dir) = dirs.pop() {
+        for item in fs::read_dir(root.join(&rel_dir))? {
+            let item = item?;
+            let rel_path = rel_dir.join(item.file_name());
+
+            if item.file_type()?.is_dir() {
+                dirs.push(rel_path);
+                continue;
+            }
+
+            let name = item.file_name();
+
+            if name == LOCK_FILE_NAME
+                || (rel_dir.as_os_str().is_empty()
+                    && (name == MANIFEST_FILE_NAME
+                        || name == format!("{}.tmp", MANIFEST_FILE_NAME).as_str()))
+            {
+                continue;
+            }
+
+            let key = rel_path
+                .iter()
+                .map(|c| c.to_str())
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| ManifestError::NonUtf8Path(rel_path.clone()))?
+                .join("/");
+
+            entries.insert(key, checksum_file(&item.path())?);
+        }
+    }
+
+    Ok(entries)
+}
+
+fn checksum_file(path: &Path) -> Result<Entry, io::Error> {
+    let mut f = File::open(path)?;
+    let mut hasher = Xxh64::new(0);
+    let mut buf = vec![0; 1 << 20];
+    let mut size = 0;
+
+    loop {
+        let n = f.read(&mut buf)?;
+
+        if n == 0 {
+            break;
+        }
+
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+
+    Ok(Entry {
+        size,
+        checksum: hasher.digest(),
+    })
+}
+
+impl Table {
+    /// Flush the table and write a checksum manifest of its directory.
+    ///
+    /// See [`write_manifest`] for details. Returns the number of files
+    /// listed.
+    pub fn write_manifest(&mut self) -> Result<usize, ManifestError> {
+        self.flush().map_err(TableError::from)?;
+        write_manifest(self.file_name().map_err(TableError::from)?)
+    }
+
+    /// Check the table directory against the manifest stored in it.
+    ///
+    /// See [`verify_manifest`] for details.
+    pub fn verify_manifest(&self) -> Result<ManifestReport, ManifestError> {
+        verify_manifest(self.file_name().map_err(TableError::from)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn file_checksums() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("abc");
+        fs::write(&path, b"abc").unwrap();
+
+        assert_eq!(
+            checksum_file(&path).unwrap(),
+            Entry {
+                size: 3,
+                checksum: 0x44bc_2cf5_ad77_0999,
+            }
+        );
+    }
+
+    #[test]
+    fn manifest_roundtrip() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("test.ms");
+        let mut t = synthetic_ms(&path, &SyntheticMsSpec::default()).unwrap();
+        let n_files = t.write_manifest().unwrap();
+        assert!(n_files > 0);
+
+        let report = t.verify_manifest().unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.n_files, n_files);
+
+        // Closing the table may touch its files, so start afresh.
+        drop(t);
+        assert_eq!(write_manifest(&path).unwrap(), n_files);
+        assert!(verify_manifest(&path).unwrap().is_ok());
+
+        // Truncate one file, corrupt another, and add a third.
+        let dat = path.join("table.dat");
+        let len = fs::metadata(&dat).unwrap().len();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&dat)
+            .unwrap()
+            .set_len(len - 1)
+            .unwrap();
+
+        let info = path.join("ANTENNA").join("table.dat");
+        let mut bytes = fs::read(&info).unwrap();
+        bytes[0] ^= 0xff;
+        fs::write(&info, bytes).unwrap();
+
+        fs::write(path.join("extra"), b"hello").unwrap();
+
+        let report = verify_manifest(&path).unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.size_mismatches, vec![PathBuf::from("table.dat")]);
+        assert_eq!(
+            report.checksum_mismatches,
+            vec![PathBuf::from("ANTENNA/table.dat")]
+        );
+        assert_eq!(report.unexpected, vec![PathBuf::from("extra")]);
+        assert!(report.missing.is_empty());
+    }
+}
@@ -0,0 +1,294 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Opening tables whose storage managers are not available.
+//!
+//! Columns can be stored with data managers that the bundled casacore does
+//! not provide, such as `Adios2StMan`, or that live in plugin libraries that
+//! are not installed. casacore refuses to open such a table at all, even
+//! though its other columns are perfectly readable. [`Table::open`] reports
+//! this as [`TableError::UnsupportedStorageManager`], and
+//! [`Table::open_masked`] opens the table anyway, standing in an inert data
+//! manager for each one that is missing so that the affected columns are
+//! masked out.
+
+use std::path::Path;
+
+use crate::{
+    casatables_string_bridge_cb, config, finish_callbacks, glue, missing_data_manager_library,
+    unregistered_data_manager, Table, TableError, TableOpenMode, TableOrigin,
+};
+
+/// A column that has been masked out because its data manager is not
+/// available.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MaskedColumn {
+    /// The name of the column.
+    pub column: String,
+
+    /// The type of the data manager that stores the column.
+    pub manager: String,
+}
+
+unsafe fn invoke_table_get_masked_columns<F>(
+    handle: *mut glue::GlueTable,
+    exc_info: &mut glue::ExcInfo,
+    mut f: F,
+) -> std::os::raw::c_int
+where
+    F: FnMut(String),
+{
+    let rv = glue::table_get_masked_columns(
+        handle,
+        Some(casatables_string_bridge_cb::<F>),
+        &mut f as *mut _ as *mut std::os::raw::c_void,
+        exc_info,
+    );
+    finish_callbacks(rv, exc_info, "table_get_masked_columns")
+}
+
+/// Open a table, masking out the columns of any data managers that cannot be
+/// loaded.
+fn open_masked_impl(path: &Path) -> Result<Table, TableError> {
+    let cpath = glue::StringBridge::from_path(path)?;
+    let mut exc_info = unsafe { std::mem::zeroed::<glue::ExcInfo>() };
+    let mut masked_types = Vec::new();
+
+    config::note_casacore_used();
+
+    loop {
+        let handle = unsafe { glue::table_alloc_and_open_masked(&cpath, &mut exc_info) };
+
+        if !handle.is_null() {
+            return Ok(Table::from_handle(
+                handle,
+                exc_info,
+                TableOrigin::Opened(TableOpenMode::Read),
+            ));
+        }
+
+        // casacore reports missing data managers one at a time, so stand in
+        // for each in turn until the table opens or fails for some other
+        // reason.
+        let err = exc_info.as_error();
+
+        let dm_type = match missing_data_manager(path, &err.0) {
+            Some(t) if !masked_types.contains(&t) => t,
+            _ => return Err(err.into()),
+        };
+
+        let cdm_type = glue::StringBridge::from_rust(&dm_type);
+
+        if unsafe { glue::data_manager_register_masked(&cdm_type, &mut exc_info) } < 0 {
+            return exc_info.as_err();
+        }
+
+        masked_types.push(dm_type);
+    }
+}
+
+/// If opening the table at *path* failed with the casacore error *message*
+/// because a data manager could not be loaded, return the type of the data
+/// manager.
+pub(crate) fn missing_data_manager(path: &Path, message: &str) -> Option<String> {
+    unregistered_data_manager(message).or_else(|| {
+        let library = missing_data_manager_library(message)?;
+        data_manager_type_for_library(path, library)
+    })
+}
+
+/// Find the data manager type that casacore would try to load from the
+/// shared library *library* when opening the table at *path*.
+///
+/// casacore only reports the name of the library, so look through the
+/// table's `table.dat` for a string naming a matching type. Strings there are
+/// stored as a 32-bit length followed by their bytes.
+fn data_manager_type_for_library(path: &Path, library: &str) -> Option<String> {
+    let data = std::fs::read(path.join("table.dat")).ok()?;
+    let library = library.as_bytes();
+
+    (4..data.len()).find_map(|i| {
+        let name_start = data.get(i..i + library.len())?;
+
+        if !name_start.eq_ignore_ascii_case(library) {
+            return None;
+        }
+
+        let prefix = [data[i - 4], data[i - 3], data[i - 2], data[i - 1]];
+
+        [u32::from_be_bytes(prefix), u32::from_le_bytes(prefix)]
+            .iter()
+            .find_map(|&len| {
+                let name = data.get(i..i.checked_add(len as usize)?)?;
+
+                match name.get(library.len()) {
+                    Some(b'.') | Some(b'<') => {}
+                    Some(_) => return None,
+                    None if name.len() < library.len() => return None,
+                    None => {}
+                }
+
+                std::str::from_utf8(name).ok().map(|s| s.to_owned())
+            })
+    })
+}
+
+/// Work out which column of a table is stored with the data manager
+/// *dm_type*, which casacore could not load, and construct the error to
+/// report for it.
+pub(crate) fn unsupported_storage_manager(path: &Path, dm_type: String) -> TableError {
+    let column = open_masked_impl(path)
+        .and_then(|mut t| t.masked_columns())
+        .ok()
+        .and_then(|cols| cols.into_iter().find(|c| c.manager == dm_type));
+
+    match column {
+        Some(c) => TableError::UnsupportedStorageManager {
+            column: c.column,
+            manager: c.manager,
+        },
+        None => TableError::MissingDataManager(dm_type),
+    }
+}
+
+impl Table {
+    /// Open a table read-only, masking out any columns whose data managers
+    /// cannot be loaded.
+    ///
+    /// Where [`Self::open`] would fail with
+    /// [`TableError::UnsupportedStorageManager`], this opens the table anyway
+    /// and returns it along with the columns that were masked. Masked columns
+    /// still appear in the table description, but any attempt to read or
+    /// write their cells fails. All other columns can be used as normal.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::Table;
+    ///
+    /// let (mut t, masked) = Table::open_masked("adios.ms").unwrap();
+    ///
+    /// for m in &masked {
+    ///     println!("column {} is stored with {}, which is unavailable", m.column, m.manager);
+    /// }
+    ///
+    /// let times = t.get_col_as_vec::<f64>("TIME").unwrap();
+    /// ```
+    ///
+    /// The stand-in data managers are registered with casacore for the rest
+    /// of the life of the process, although only this method will make use
+    /// of them. Because casacore shares open tables, opening a table with
+    /// [`Self::open`] while it is already open here yields the masked table.
+    pub fn open_masked<P: AsRef<Path>>(path: P) -> Result<(Table, Vec<MaskedColumn>), TableError> {
+        let mut table = open_masked_impl(path.as_ref())?;
+        let masked = table.masked_columns()?;
+        Ok((table, masked))
+    }
+
+    /// List the columns of this table that have been masked out because
+    /// their data managers are not available.
+    ///
+    /// This is only ever non-empty for tables opened with
+    /// [`Self::open_masked`].
+    pub fn masked_columns(&mut self) -> Result<Vec<MaskedColumn>, TableError> {
+        let mut strings = Vec::new();
+
+        let rv = unsafe {
+            invoke_table_get_masked_columns(self.handle, &mut self.exc_info, |s| strings.push(s))
+        };
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        let mut masked = Vec::with_capacity(strings.len() / 2);
+        let mut strings = strings.into_iter();
+
+        while let (Some(column), Some(manager)) = (strings.next(), strings.next()) {
+            masked.push(MaskedColumn { column, manager });
+        }
+
+        Ok(masked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Replace every occurrence of *from* in a file with *to*, which must
+    /// have the same length.
+    fn patch_file(path: &Path, from: &[u8], to: &[u8]) {
+        let mut data = fs::read(path).unwrap();
+        let mut i = 0;
+
+        while i + from.len() <= data.len() {
+            if &data[i..i + from.len()] == from {
+                data[i..i + from.len()].copy_from_slice(to);
+                i += from.len();
+            } else {
+                i += 1;
+            }
+        }
+
+        fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn masked_columns() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("t");
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpDouble, "TIME", None, false, false)
+            .unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "FIELD_ID", None, false, false)
+            .unwrap();
+        desc.set_data_manager("FIELD_ID", "IncrementalStMan", "ISMData")
+            .unwrap();
+        let mut t = Table::new(&table_path, desc, 3, TableCreateMode::New).unwrap();
+
+        for row in 0..3 {
+            t.put_cell("TIME", row, &(row as f64)).unwrap();
+            t.put_cell("FIELD_ID", row, &7i32).unwrap();
+        }
+
+        drop(t);
+
+        // Pretend that the table was written with a data manager that we
+        // don't have.
+        patch_file(
+            &table_path.join("table.dat"),
+            b"IncrementalStMan",
+            b"IncrementalStMaX",
+        );
+
+        match Table::open(&table_path, TableOpenMode::Read) {
+            Err(TableError::UnsupportedStorageManager { column, manager }) => {
+                assert_eq!(column, "FIELD_ID");
+                assert_eq!(manager, "IncrementalStMaX");
+            }
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("table with a missing data manager opened"),
+        }
+
+        let (mut t, masked) = Table::open_masked(&table_path).unwrap();
+        assert_eq!(
+            masked,
+            vec![MaskedColumn {
+                column: "FIELD_ID".to_owned(),
+                manager: "IncrementalStMaX".to_owned(),
+            }]
+        );
+        assert_eq!(t.n_rows(), 3);
+        assert_eq!(t.get_col_as_vec::<f64>("TIME").unwrap(), vec![0., 1., 2.]);
+        assert!(t.get_cell::<i32>("FIELD_ID", 0).is_err());
+        drop(t);
+
+        // The stand-in is not used by ordinary opens.
+        assert!(matches!(
+            Table::open(&table_path, TableOpenMode::Read),
+            Err(TableError::UnsupportedStorageManager { .. })
+        ));
+    }
+}
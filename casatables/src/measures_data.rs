@@ -0,0 +1,446 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Locating and downloading the casacore measures data.
+//!
+//! casacore’s measures code needs a directory of data tables — Earth
+//! orientation parameters, leap seconds, observatory positions, ephemerides
+//! — to do its conversions. When the tables cannot be found it fails with
+//! unhelpful messages such as complaints about the “Leap second table”.
+//! [`MeasuresData`] finds an existing copy of the data, or downloads one into
+//! a cache directory, and the result can be handed to casacore with
+//! [`crate::CasacoreConfig::measures_directory`]:
+//!
+//! ```no_run
+//! use rubbl_casatables::{configure, measures_data::MeasuresData, CasacoreConfig};
+//!
+//! let dir = MeasuresData::new()
+//!     .sha256("<digest of a tarball that you have checked>")
+//!     .ensure()
+//!     .unwrap();
+//! configure(CasacoreConfig::new().measures_directory(dir)).unwrap();
+//! ```
+//!
+//! Downloads are only made over HTTPS, and only if the SHA-256 digest of
+//! the tarball is given, either with [`MeasuresData::sha256`] or in the
+//! `RUBBL_MEASURES_SHA256` environment variable. The tarball is checked
+//! against it before it is unpacked. The tarball is republished regularly,
+//! so no digest can be built in. Downloads are done with the `curl` and
+//! `tar` command-line programs, which must be available on the `PATH`.
+
+use sha2::{Digest, Sha256};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime},
+};
+use thiserror::Error;
+
+/// The default location of the measures data tarball, as maintained by
+/// ASTRON.
+pub const DEFAULT_MEASURES_URL: &str = "https://www.astron.nl/iers/WSRT_Measures.ztar";
+
+/// An environment variable that can be set to the measures data directory.
+pub const MEASURES_DATA_VAR: &str = "RUBBL_MEASURES_DATA";
+
+/// An environment variable that can be set to the expected SHA-256 digest
+/// of the measures data tarball, in hexadecimal.
+pub const MEASURES_SHA256_VAR: &str = "RUBBL_MEASURES_SHA256";
+
+/// The file recording when a download was made.
+const STAMP_FILE: &str = ".rubbl-downloaded";
+
+/// An error that can occur when locating or downloading measures data.
+#[derive(Error, Debug)]
+pub enum MeasuresDataError {
+    /// No data were found and downloading was disabled.
+    #[error("could not find the casacore measures data, and downloading is disabled")]
+    NotFound,
+
+    /// The data could not be downloaded or unpacked.
+    #[error("could not download the casacore measures data: {0}")]
+    Download(String),
+
+    /// Downloads must be made over HTTPS.
+    #[error(
+        "refusing to download the casacore measures data from {0}, which is not an https:// URL"
+    )]
+    InsecureUrl(String),
+
+    /// Downloads must be checked against a known digest.
+    #[error(
+        "refusing to download the casacore measures data without a SHA-256 digest to check; \
+         set one with `MeasuresData::sha256` or $RUBBL_MEASURES_SHA256"
+    )]
+    MissingChecksum,
+
+    /// The downloaded tarball does not have the expected digest.
+    #[error("the casacore measures data tarball has SHA-256 digest {actual}, but {expected} was expected")]
+    ChecksumMismatch {
+        /// The expected digest.
+        expected: String,
+
+        /// The digest of the downloaded file.
+        actual: String,
+    },
+
+    /// A program needed to download or unpack the data is not installed.
+    #[error(
+        "the `{0}` program is needed to download the casacore measures data, but it was not found"
+    )]
+    MissingTool(&'static str),
+
+    /// A filesystem operation failed.
+    #[error("I/O error while installing the casacore measures data")]
+    Io(#[from] io::Error),
+}
+
+/// Return whether *path* looks like a casacore measures data directory.
+pub fn is_measures_data_dir<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    path.join("geodetic").is_dir() && path.join("ephemerides").is_dir()
+}
+
+/// A helper for finding or downloading the casacore measures data.
+#[derive(Clone, Debug)]
+pub struct MeasuresData {
+    cache_dir: PathBuf,
+    url: String,
+    sha256: Option<String>,
+    allow_download: bool,
+    max_age: Option<Duration>,
+}
+
+impl Default for MeasuresData {
+    fn default() -> Self {
+        MeasuresData::new()
+    }
+}
+
+impl MeasuresData {
+    /// Create a helper with the default settings.
+    ///
+    /// Downloads are cached in `$XDG_CACHE_HOME/rubbl/measures`, or
+    /// `~/.cache/rubbl/measures`, and are refreshed once they are more than
+    /// 30 days old, since the Earth orientation tables go stale. The
+    /// expected digest of the tarball is taken from the
+    /// `RUBBL_MEASURES_SHA256` environment variable, if it is set.
+    pub fn new() -> Self {
+        let cache_root = env::var_os("XDG_CACHE_HOME")
+            .filter(|s| !s.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| Path::new(&h).join(".cache")))
+            .unwrap_or_else(env::temp_dir);
+
+        MeasuresData {
+            cache_dir: cache_root.join("rubbl").join("measures"),
+            url: DEFAULT_MEASURES_URL.to_owned(),
+            sha256: env::var(MEASURES_SHA256_VAR).ok().filter(|s| !s.is_empty()),
+            allow_download: true,
+            max_age: Some(Duration::from_secs(30 * 86400)),
+        }
+    }
+
+    /// Set the directory into which the data are downloaded.
+    pub fn cache_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.cache_dir = path.as_ref().to_owned();
+        self
+    }
+
+    /// Set the URL of the measures data tarball, which must use HTTPS.
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = url.to_owned();
+        self
+    }
+
+    /// Set the expected SHA-256 digest of the tarball, in hexadecimal.
+    ///
+    /// Nothing is downloaded unless this is known.
+    pub fn sha256(mut self, digest: &str) -> Self {
+        self.sha256 = Some(digest.trim().to_owned());
+        self
+    }
+
+    /// Set whether the data may be downloaded if no local copy is found.
+    pub fn allow_download(mut self, allow: bool) -> Self {
+        self.allow_download = allow;
+        self
+    }
+
+    /// Set the age after which downloaded data are refreshed, or `None` to
+    /// never refresh them.
+    pub fn max_age(mut self, age: Option<Duration>) -> Self {
+        self.max_age = age;
+        self
+    }
+
+    /// Get the list of directories that are searched for existing data, in
+    /// order of preference.
+    ///
+    /// These are the directory named by the `RUBBL_MEASURES_DATA`
+    /// environment variable, the `data` directory of a CASA installation
+    /// named by `CASAPATH`, `~/.casa/data`, the download cache directory,
+    /// and some common system-wide locations.
+    pub fn search_path(&self) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+
+        if let Some(d) = env::var_os(MEASURES_DATA_VAR) {
+            dirs.push(PathBuf::from(d));
+        }
+
+        if let Ok(casapath) = env::var("CASAPATH") {
+            if let Some(root) = casapath.split_whitespace().next() {
+                dirs.push(Path::new(root).join("data"));
+            }
+        }
+
+        if let Some(home) = env::var_os("HOME") {
+            dirs.push(Path::new(&home).join(".casa").join("data"));
+        }
+
+        dirs.push(self.cache_dir.clone());
+
+        for d in &[
+            "/usr/share/casacore/data",
+            "/usr/local/share/casacore/data",
+            "/opt/casacore/data",
+        ] {
+            dirs.push(PathBuf::from(d));
+        }
+
+        dirs
+    }
+
+    /// Find an existing copy of the measures data without downloading.
+    pub fn locate(&self) -> Option<PathBuf> {
+        self.search_path()
+            .into_iter()
+            .find(|d| is_measures_data_dir(d))
+    }
+
+    /// Find the measures data, downloading them if needed.
+    ///
+    /// If a previously downloaded copy is stale, a fresh copy is downloaded;
+    /// if that fails, the stale copy is used anyway.
+    pub fn ensure(&self) -> Result<PathBuf, MeasuresDataError> {
+        let found = self.locate();
+
+        if let Some(dir) = &found {
+            if *dir != self.cache_dir || !self.is_stale() || !self.allow_download {
+                return Ok(dir.clone());
+            }
+        }
+
+        if !self.allow_download {
+            return Err(MeasuresDataError::NotFound);
+        }
+
+        match self.download() {
+            Ok(()) => Ok(self.cache_dir.clone()),
+            Err(e) => found.ok_or(e),
+        }
+    }
+
+    /// Whether the downloaded data are older than the maximum age.
+    fn is_stale(&self) -> bool {
+        let max_age = match self.max_age {
+            Some(a) => a,
+            None => return false,
+        };
+
+        let stamp = self.cache_dir.join(STAMP_FILE);
+
+        match fs::metadata(stamp).and_then(|m| m.modified()) {
+            Ok(t) => SystemTime::now()
+                .duration_since(t)
+                .map(|age| age > max_age)
+                .unwrap_or(false),
+            Err(_) => true,
+        }
+    }
+
+    /// Download and unpack the data into the cache directory, replacing any
+    /// existing copy.
+    ///
+    /// The URL must use HTTPS, and the tarball must match the expected
+    /// digest; it is checked before anything is unpacked.
+    pub fn download(&self) -> Result<(), MeasuresDataError> {
+        if !self.url.starts_with("https://") {
+            return Err(MeasuresDataError::InsecureUrl(self.url.clone()));
+        }
+
+        let sha256 = self
+            .sha256
+            .as_deref()
+            .ok_or(MeasuresDataError::MissingChecksum)?;
+
+        let parent = self
+            .cache_dir
+            .parent()
+            .map(Path::to_owned)
+            .unwrap_or_else(|| PathBuf::from("."));
+        fs::create_dir_all(&parent)?;
+
+        // Work in a scratch directory next to the destination so that the
+        // final rename is atomic and an interrupted download leaves any
+        // existing data alone.
+        let scratch = parent.join(format!(".measures-download-{}", std::process::id()));
+        let _ignored = fs::remove_dir_all(&scratch);
+        fs::create_dir_all(scratch.join("data"))?;
+
+        let result = self.download_into(&scratch, sha256);
+
+        if result.is_err() {
+            let _ignored = fs::remove_dir_all(&scratch);
+            return result;
+        }
+
+        let old = parent.join(format!(".measures-old-{}", std::process::id()));
+
+        if self.cache_dir.exists() {
+            fs::rename(&self.cache_dir, &old)?;
+        }
+
+        fs::rename(scratch.join("data"), &self.cache_dir)?;
+        let _ignored = fs::remove_dir_all(&old);
+        let _ignored = fs::remove_dir_all(&scratch);
+        Ok(())
+    }
+
+    fn download_into(&self, scratch: &Path, sha256: &str) -> Result<(), MeasuresDataError> {
+        let tarball = scratch.join("measures.tar.gz");
+        let data = scratch.join("data");
+
+        run(
+            "curl",
+            Command::new("curl")
+                .args([
+                    "--fail",
+                    "--silent",
+                    "--show-error",
+                    "--location",
+                    "--proto",
+                    "=https",
+                    "--proto-redir",
+                    "=https",
+                    "--output",
+                ])
+                .arg(&tarball)
+                .arg(&self.url),
+        )?;
+
+        let actual = sha256_hex(&fs::read(&tarball)?);
+
+        if !actual.eq_ignore_ascii_case(sha256) {
+            return Err(MeasuresDataError::ChecksumMismatch {
+                expected: sha256.to_owned(),
+                actual,
+            });
+        }
+
+        run(
+            "tar",
+            Command::new("tar")
+                .arg("-xzf")
+                .arg(&tarball)
+                .arg("-C")
+                .arg(&data),
+        )?;
+
+        if !is_measures_data_dir(&data) {
+            return Err(MeasuresDataError::Download(format!(
+                "{} does not contain measures data",
+                self.url
+            )));
+        }
+
+        fs::write(data.join(STAMP_FILE), &self.url)?;
+        Ok(())
+    }
+}
+
+/// Get the SHA-256 digest of some data, in hexadecimal.
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Run *program*, turning failures into download errors.
+fn run(program: &'static str, cmd: &mut Command) -> Result<(), MeasuresDataError> {
+    let output = cmd.output().map_err(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            MeasuresDataError::MissingTool(program)
+        } else {
+            MeasuresDataError::Download(format!("could not run `{program}`: {e}"))
+        }
+    })?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(MeasuresDataError::Download(format!(
+            "`{program}` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn locate_cached_data() {
+        let tmp_dir = tempdir().unwrap();
+        let cache = tmp_dir.path().join("measures");
+        let md = MeasuresData::new()
+            .cache_dir(&cache)
+            .allow_download(false)
+            .max_age(None);
+
+        assert!(!is_measures_data_dir(&cache));
+
+        fs::create_dir_all(cache.join("geodetic")).unwrap();
+        fs::create_dir_all(cache.join("ephemerides")).unwrap();
+        assert!(is_measures_data_dir(&cache));
+        assert!(md.search_path().contains(&cache));
+        assert!(md.ensure().is_ok());
+    }
+
+    #[test]
+    fn download_requirements() {
+        let tmp_dir = tempdir().unwrap();
+        let md = MeasuresData::new()
+            .cache_dir(tmp_dir.path().join("measures"))
+            .url("http://example.com/measures.tgz")
+            .sha256("00");
+        assert!(matches!(
+            md.download(),
+            Err(MeasuresDataError::InsecureUrl(_))
+        ));
+
+        let mut md = md.url(DEFAULT_MEASURES_URL);
+        md.sha256 = None;
+        assert!(matches!(
+            md.download(),
+            Err(MeasuresDataError::MissingChecksum)
+        ));
+        assert!(!tmp_dir.path().join("measures").exists());
+
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(matches!(
+            run(
+                "no-such-program-rubbl",
+                &mut Command::new("no-such-program-rubbl")
+            ),
+            Err(MeasuresDataError::MissingTool(_))
+        ));
+    }
+}
@@ -0,0 +1,543 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Migrating existing tables to a new schema.
+//!
+//! Data formats evolve: a pipeline may start writing a new column, switch a
+//! column to single precision, or record a new table keyword. Rather than
+//! writing one-off upgrade code for each change, declare the schema that a
+//! table should have as a [`TargetSchema`] and let [`migrate`] work out and
+//! apply the differences. Use [`plan_migration`] to see what would be done
+//! without touching the table.
+//!
+//! ```no_run
+//! use rubbl_casatables::{GlueDataType, Table, TableOpenMode};
+//! use rubbl_casatables::migrate::{migrate, TargetSchema};
+//!
+//! let mut schema = TargetSchema::new().unwrap();
+//! schema.add_scalar_column(GlueDataType::TpDouble, "TIME", None);
+//! schema.add_array_column(GlueDataType::TpFloat, "WEIGHT", None, Some(&[4]));
+//! schema.put_keyword("VERSION", &"2.0".to_owned()).unwrap();
+//!
+//! let mut t = Table::open("data.tab", TableOpenMode::ReadWrite).unwrap();
+//! for step in migrate(&mut t, &mut schema).unwrap() {
+//!     println!("{}", step);
+//! }
+//! ```
+
+use std::fmt;
+use thiserror::Error;
+
+use crate::{
+    column_copy::copy_column_within, CasacoreError, ColumnShapeInfo, Complex, Conversion,
+    GlueDataType, Table, TableError, TableRecord,
+};
+
+/// The suffix given to the temporary copy of a column that is being
+/// converted.
+const CONVERSION_SUFFIX: &str = "_MIGRATE_TMP";
+
+/// An error that can occur when migrating a table.
+#[derive(Error, Debug)]
+pub enum MigrateError {
+    /// An error occurred while reading or modifying the table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// casacore raised an exception.
+    #[error(transparent)]
+    Casacore(#[from] CasacoreError),
+
+    /// A column has a data type that cannot be converted to the one required
+    /// by the schema.
+    #[error("column \"{0}\" cannot be converted from {1} to {2}")]
+    UnsupportedConversion(String, GlueDataType, GlueDataType),
+
+    /// A column has a different shape from the one required by the schema.
+    /// Reshaping columns is not supported.
+    #[error("column \"{0}\" has shape {1:?}, but the schema requires {2:?}")]
+    ShapeMismatch(String, ColumnShapeInfo, ColumnShapeInfo),
+
+    /// A keyword has a type that cannot be copied, such as a subtable.
+    #[error("keyword \"{0}\" has the type {1}, which cannot be copied")]
+    UnsupportedKeyword(String, GlueDataType),
+}
+
+/// A column of a [`TargetSchema`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ColumnSpec {
+    /// The name of the column.
+    pub name: String,
+
+    /// The element type of the column, such as `TpDouble` rather than
+    /// `TpArrayDouble`.
+    pub data_type: GlueDataType,
+
+    /// The shape of the column’s cells.
+    pub shape: ColumnShapeInfo,
+
+    /// The comment to attach to the column if it is added.
+    pub comment: Option<String>,
+}
+
+/// The schema that a table should have after migration.
+///
+/// This lists the columns that the table should have, and the table keywords
+/// that it should have with particular values. Columns and keywords that are
+/// not mentioned are left alone, unless [`Self::remove_unlisted_columns`] is
+/// set.
+#[derive(Debug)]
+pub struct TargetSchema {
+    /// The columns of the table.
+    pub columns: Vec<ColumnSpec>,
+
+    /// The keywords that the table should have.
+    pub keywords: TableRecord,
+
+    /// If true, columns that are not listed in [`Self::columns`] are
+    /// removed. The default is false.
+    pub remove_unlisted_columns: bool,
+}
+
+impl TargetSchema {
+    /// Create an empty schema.
+    pub fn new() -> Result<Self, TableError> {
+        Ok(TargetSchema {
+            columns: Vec::new(),
+            keywords: TableRecord::new()?,
+            remove_unlisted_columns: false,
+        })
+    }
+
+    /// Add a scalar column to the schema.
+    pub fn add_scalar_column(
+        &mut self,
+        data_type: GlueDataType,
+        col_name: &str,
+        comment: Option<&str>,
+    ) -> &mut Self {
+        self.add_column(data_type, col_name, comment, ColumnShapeInfo::Scalar)
+    }
+
+    /// Add an array column to the schema.
+    ///
+    /// If *dims* is given, the column has that fixed cell shape, in C order.
+    /// Otherwise, its cell shapes may vary.
+    pub fn add_array_column(
+        &mut self,
+        data_type: GlueDataType,
+        col_name: &str,
+        comment: Option<&str>,
+        dims: Option<&[u64]>,
+    ) -> &mut Self {
+        let shape = match dims {
+            Some(dims) => ColumnShapeInfo::Fixed(dims.iter().map(|d| *d as usize).collect()),
+            None => ColumnShapeInfo::Variable { ndim: None },
+        };
+        self.add_column(data_type, col_name, comment, shape)
+    }
+
+    fn add_column(
+        &mut self,
+        data_type: GlueDataType,
+        col_name: &str,
+        comment: Option<&str>,
+        shape: ColumnShapeInfo,
+    ) -> &mut Self {
+        self.columns.push(ColumnSpec {
+            name: col_name.to_owned(),
+            data_type: data_type.element_type(),
+            shape,
+            comment: comment.map(|c| c.to_owned()),
+        });
+        self
+    }
+
+    /// Require the table to have a keyword with the given value.
+    pub fn put_keyword<T: crate::CasaDataType>(
+        &mut self,
+        kw_name: &str,
+        value: &T,
+    ) -> Result<&mut Self, CasacoreError> {
+        self.keywords.put_field(kw_name, value)?;
+        Ok(self)
+    }
+}
+
+/// One change made by a migration.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MigrationStep {
+    /// Add a column, whose cells are left undefined.
+    AddColumn(String),
+
+    /// Remove a column that is not in the schema.
+    RemoveColumn(String),
+
+    /// Convert the data of a column to a new type. Its keywords are kept.
+    ConvertColumn {
+        /// The name of the column.
+        name: String,
+        /// The current element type.
+        from: GlueDataType,
+        /// The new element type.
+        to: GlueDataType,
+    },
+
+    /// Set a table keyword that is missing or has a different value.
+    PutKeyword(String),
+}
+
+impl fmt::Display for MigrationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationStep::AddColumn(name) => write!(f, "add column {}", name),
+            MigrationStep::RemoveColumn(name) => write!(f, "remove column {}", name),
+            MigrationStep::ConvertColumn { name, from, to } => {
+                write!(f, "convert column {} from {} to {}", name, from, to)
+            }
+            MigrationStep::PutKeyword(name) => write!(f, "set keyword {}", name),
+        }
+    }
+}
+
+/// Work out the changes needed to bring *table* into line with *schema*,
+/// without making them.
+///
+/// This is the “dry run” of [`migrate`]: it fails in the same cases, before
+/// any changes would be made.
+pub fn plan_migration(
+    table: &mut Table,
+    schema: &mut TargetSchema,
+) -> Result<Vec<MigrationStep>, MigrateError> {
+    let existing = table.column_names()?;
+    let mut steps = Vec::new();
+
+    for spec in &schema.columns {
+        if !existing.contains(&spec.name) {
+            steps.push(MigrationStep::AddColumn(spec.name.clone()));
+            continue;
+        }
+
+        let shape = table.column_shape(&spec.name)?;
+
+        if !shapes_compatible(&shape, &spec.shape) {
+            return Err(MigrateError::ShapeMismatch(
+                spec.name.clone(),
+                shape,
+                spec.shape.clone(),
+            ));
+        }
+
+        let from = table.get_col_desc(&spec.name)?.data_type().element_type();
+
+        if from != spec.data_type {
+            if conversion(from, spec.data_type).is_none() {
+                return Err(MigrateError::UnsupportedConversion(
+                    spec.name.clone(),
+                    from,
+                    spec.data_type,
+                ));
+            }
+
+            steps.push(MigrationStep::ConvertColumn {
+                name: spec.name.clone(),
+                from,
+                to: spec.data_type,
+            });
+        }
+    }
+
+    if schema.remove_unlisted_columns {
+        for name in existing {
+            if !schema.columns.iter().any(|c| c.name == name) {
+                steps.push(MigrationStep::RemoveColumn(name));
+            }
+        }
+    }
+
+    let current = table.get_keyword_record()?.keyword_names_types_reprs()?;
+
+    for (name, data_type, repr) in schema.keywords.keyword_names_types_reprs()? {
+        if !is_copyable(data_type) {
+            return Err(MigrateError::UnsupportedKeyword(name, data_type));
+        }
+
+        let unchanged = current
+            .iter()
+            .any(|(n, t, r)| *n == name && *t == data_type && *r == repr);
+
+        if !unchanged {
+            steps.push(MigrationStep::PutKeyword(name));
+        }
+    }
+
+    Ok(steps)
+}
+
+/// Bring *table* into line with *schema*, returning the changes that were
+/// made.
+///
+/// Missing columns are added, with their cells left undefined. Columns whose
+/// data types differ from the schema are converted by copying their data
+/// into a new column, which must be scalar or have a fixed shape; the
+/// supported conversions are those of [`Conversion`]. Keywords that are
+/// missing or have different values are set. Columns are never reshaped.
+/// The table must be open for writing.
+pub fn migrate(
+    table: &mut Table,
+    schema: &mut TargetSchema,
+) -> Result<Vec<MigrationStep>, MigrateError> {
+    let steps = plan_migration(table, schema)?;
+
+    for step in &steps {
+        match step {
+            MigrationStep::AddColumn(name) => {
+                let spec = schema.columns.iter().find(|c| c.name == *name).unwrap();
+                add_column(table, spec, name)?;
+            }
+
+            MigrationStep::RemoveColumn(name) => table.remove_column(name)?,
+
+            MigrationStep::ConvertColumn { name, from, to } => {
+                let spec = schema.columns.iter().find(|c| c.name == *name).unwrap();
+                let tmp_name = format!("{}{}", name, CONVERSION_SUFFIX);
+                let conversion = conversion(*from, *to).unwrap();
+
+                // Keep the same shape as the existing column, which is known
+                // to be compatible with the schema.
+                let spec = ColumnSpec {
+                    shape: table.column_shape(name)?,
+                    ..spec.clone()
+                };
+
+                add_column(table, &spec, &tmp_name)?;
+                copy_column_within(table, name, &tmp_name, conversion)?;
+
+                let mut keywords = table.get_column_keyword_record(name)?;
+
+                for (kw_name, data_type, _) in keywords.keyword_names_types_reprs()? {
+                    copy_keyword(
+                        &mut keywords,
+                        &kw_name,
+                        data_type,
+                        table,
+                        KeywordTarget::Column(&tmp_name),
+                    )?;
+                }
+
+                table.remove_column(name)?;
+                table.rename_column(&tmp_name, name)?;
+            }
+
+            MigrationStep::PutKeyword(name) => {
+                let data_type = schema
+                    .keywords
+                    .keyword_names_types_reprs()?
+                    .into_iter()
+                    .find(|(n, _, _)| n == name)
+                    .map(|(_, t, _)| t)
+                    .unwrap();
+
+                copy_keyword(
+                    &mut schema.keywords,
+                    name,
+                    data_type,
+                    table,
+                    KeywordTarget::Table,
+                )?;
+            }
+        }
+    }
+
+    Ok(steps)
+}
+
+fn add_column(table: &mut Table, spec: &ColumnSpec, name: &str) -> Result<(), TableError> {
+    let comment = spec.comment.as_deref();
+
+    match spec.shape {
+        ColumnShapeInfo::Scalar => {
+            table.add_scalar_column(spec.data_type, name, comment, true, false)?
+        }
+        ColumnShapeInfo::Fixed(ref dims) => {
+            let dims: Vec<u64> = dims.iter().map(|d| *d as u64).collect();
+            table.add_array_column(spec.data_type, name, comment, Some(&dims), true, false)?
+        }
+        ColumnShapeInfo::Variable { .. } => {
+            table.add_array_column(spec.data_type, name, comment, None, false, false)?
+        }
+    }
+
+    Ok(())
+}
+
+/// Decide whether a column of shape *actual* satisfies a schema that asks
+/// for *wanted*. A variable-shape requirement accepts any array column.
+fn shapes_compatible(actual: &ColumnShapeInfo, wanted: &ColumnShapeInfo) -> bool {
+    match (actual, wanted) {
+        (ColumnShapeInfo::Scalar, ColumnShapeInfo::Scalar) => true,
+        (ColumnShapeInfo::Fixed(a), ColumnShapeInfo::Fixed(w)) => a == w,
+        (ColumnShapeInfo::Scalar, _) | (_, ColumnShapeInfo::Scalar) => false,
+        (_, ColumnShapeInfo::Variable { ndim: None }) => true,
+        (_, ColumnShapeInfo::Variable { ndim }) => actual.ndim() == *ndim,
+        (ColumnShapeInfo::Variable { .. }, ColumnShapeInfo::Fixed(_)) => false,
+    }
+}
+
+fn conversion(from: GlueDataType, to: GlueDataType) -> Option<Conversion> {
+    use GlueDataType::*;
+
+    match (from, to) {
+        (TpDouble, TpFloat) => Some(Conversion::DoubleToFloat),
+        (TpFloat, TpDouble) => Some(Conversion::FloatToDouble),
+        (TpDComplex, TpComplex) => Some(Conversion::DComplexToComplex),
+        (TpComplex, TpDComplex) => Some(Conversion::ComplexToDComplex),
+        _ => None,
+    }
+}
+
+fn is_copyable(data_type: GlueDataType) -> bool {
+    !matches!(
+        data_type,
+        GlueDataType::TpTable | GlueDataType::TpOther | GlueDataType::TpQuantity
+    )
+}
+
+/// Where to put a copied keyword.
+#[derive(Clone, Copy)]
+enum KeywordTarget<'a> {
+    Table,
+    Column(&'a str),
+}
+
+/// Copy the field *name* of *record*, whose type is *data_type*, to a
+/// keyword of *table*.
+fn copy_keyword(
+    record: &mut TableRecord,
+    name: &str,
+    data_type: GlueDataType,
+    table: &mut Table,
+    target: KeywordTarget,
+) -> Result<(), MigrateError> {
+    use GlueDataType::*;
+
+    macro_rules! copy {
+        ($t:ty) => {{
+            let value = record.get_field::<$t>(name)?;
+
+            match target {
+                KeywordTarget::Table => table.put_keyword(name, &value)?,
+                KeywordTarget::Column(col) => table.put_column_keyword(col, name, &value)?,
+            }
+        }};
+    }
+
+    match data_type {
+        TpBool => copy!(bool),
+        TpChar => copy!(i8),
+        TpUChar => copy!(u8),
+        TpShort => copy!(i16),
+        TpUShort => copy!(u16),
+        TpInt => copy!(i32),
+        TpUInt => copy!(u32),
+        TpInt64 => copy!(i64),
+        TpFloat => copy!(f32),
+        TpDouble => copy!(f64),
+        TpComplex => copy!(Complex<f32>),
+        TpDComplex => copy!(Complex<f64>),
+        TpString => copy!(String),
+        TpArrayBool => copy!(Vec<bool>),
+        TpArrayChar => copy!(Vec<i8>),
+        TpArrayUChar => copy!(Vec<u8>),
+        TpArrayShort => copy!(Vec<i16>),
+        TpArrayUShort => copy!(Vec<u16>),
+        TpArrayInt => copy!(Vec<i32>),
+        TpArrayUInt => copy!(Vec<u32>),
+        TpArrayInt64 => copy!(Vec<i64>),
+        TpArrayFloat => copy!(Vec<f32>),
+        TpArrayDouble => copy!(Vec<f64>),
+        TpArrayComplex => copy!(Vec<Complex<f32>>),
+        TpArrayDComplex => copy!(Vec<Complex<f64>>),
+        TpArrayString => copy!(Vec<String>),
+        TpRecord => copy!(TableRecord),
+        other => return Err(MigrateError::UnsupportedKeyword(name.to_owned(), other)),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn plan_and_migrate() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpDouble, "X", None, true, false)
+            .unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "OLD", None, true, false)
+            .unwrap();
+        let mut t =
+            Table::new(tmp_dir.path().join("t.tab"), desc, 3, TableCreateMode::New).unwrap();
+
+        for row in 0..3 {
+            t.put_cell("X", row, &(row as f64 + 0.5)).unwrap();
+        }
+
+        t.put_column_keyword("X", "UNIT", &"s".to_owned()).unwrap();
+        t.put_keyword("VERSION", &"1.0".to_owned()).unwrap();
+
+        let mut schema = TargetSchema::new().unwrap();
+        schema
+            .add_scalar_column(GlueDataType::TpFloat, "X", None)
+            .add_array_column(GlueDataType::TpDouble, "UVW", None, Some(&[3]));
+        schema.put_keyword("VERSION", &"2.0".to_owned()).unwrap();
+        schema.remove_unlisted_columns = true;
+
+        let expected = vec![
+            MigrationStep::ConvertColumn {
+                name: "X".to_owned(),
+                from: GlueDataType::TpDouble,
+                to: GlueDataType::TpFloat,
+            },
+            MigrationStep::AddColumn("UVW".to_owned()),
+            MigrationStep::RemoveColumn("OLD".to_owned()),
+            MigrationStep::PutKeyword("VERSION".to_owned()),
+        ];
+
+        // The dry run leaves the table alone.
+        assert_eq!(plan_migration(&mut t, &mut schema).unwrap(), expected);
+        assert_eq!(t.n_columns(), 2);
+
+        assert_eq!(migrate(&mut t, &mut schema).unwrap(), expected);
+        let mut names = t.column_names().unwrap();
+        names.sort();
+        assert_eq!(names, vec!["UVW".to_owned(), "X".to_owned()]);
+        assert_eq!(t.get_cell::<f32>("X", 2).unwrap(), 2.5);
+        assert_eq!(
+            t.get_column_keyword_record("X")
+                .unwrap()
+                .get_field::<String>("UNIT")
+                .unwrap(),
+            "s"
+        );
+        assert_eq!(
+            t.get_keyword_record()
+                .unwrap()
+                .get_field::<String>("VERSION")
+                .unwrap(),
+            "2.0"
+        );
+        assert!(plan_migration(&mut t, &mut schema).unwrap().is_empty());
+
+        let mut schema = TargetSchema::new().unwrap();
+        schema.add_scalar_column(GlueDataType::TpInt, "X", None);
+        assert!(matches!(
+            plan_migration(&mut t, &mut schema),
+            Err(MigrateError::UnsupportedConversion(..))
+        ));
+    }
+}
@@ -0,0 +1,252 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Zero-copy, memory-mapped read access to column data.
+//!
+//! Going through casacore to read a column copies every cell at least once,
+//! which dominates the cost of simple whole-column scans such as computing
+//! statistics. For columns whose on-disk layout is a plain array of cells, we
+//! can instead map the data file into memory and read it directly.
+
+use memmap2::Mmap;
+use ndarray::{ArrayView, IxDyn};
+use std::{fs::File, io, marker::PhantomData, mem, slice};
+
+use crate::{
+    casatables_string_bridge_cb, finish_callbacks, glue, CasaScalarData, Table, TableError,
+    UnexpectedDataTypeError,
+};
+
+/// A read-only, memory-mapped view of the data of a fixed-shape column.
+///
+/// Created by [`Table::map_column`].
+pub struct ColumnMmap<T> {
+    map: Option<Mmap>,
+    n_rows: usize,
+    cell_shape: Vec<usize>,
+    _elem: PhantomData<T>,
+}
+
+impl<T: CasaScalarData + Copy> ColumnMmap<T> {
+    /// The number of rows in the mapped column.
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    /// The shape of each cell, in C (row-major) order.
+    pub fn cell_shape(&self) -> &[usize] {
+        &self.cell_shape[..]
+    }
+
+    /// The number of elements in each cell.
+    pub fn cell_len(&self) -> usize {
+        self.cell_shape.iter().product()
+    }
+
+    /// Get the data of the whole column as one flat slice.
+    ///
+    /// Cells are stored one after another, in row order.
+    pub fn as_slice(&self) -> &[T] {
+        match self.map {
+            None => &[],
+            Some(ref map) => unsafe {
+                slice::from_raw_parts(map.as_ptr() as *const T, self.n_rows * self.cell_len())
+            },
+        }
+    }
+
+    /// Get the data of a single cell as a flat slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *row* is out of bounds.
+    pub fn cell(&self, row: usize) -> &[T] {
+        assert!(row < self.n_rows, "row {} out of bounds", row);
+        let n = self.cell_len();
+        &self.as_slice()[row * n..(row + 1) * n]
+    }
+
+    /// Get the data of the whole column as an array view.
+    ///
+    /// The first axis of the view indexes rows; the remaining axes are those of
+    /// [`Self::cell_shape`].
+    pub fn as_array(&self) -> ArrayView<'_, T, IxDyn> {
+        let mut shape = Vec::with_capacity(self.cell_shape.len() + 1);
+        shape.push(self.n_rows);
+        shape.extend_from_slice(&self.cell_shape[..]);
+        ArrayView::from_shape(shape, self.as_slice()).unwrap()
+    }
+}
+
+unsafe fn invoke_table_get_mappable_column_layout<F>(
+    handle: *mut glue::GlueTable,
+    ccol_name: &glue::StringBridge,
+    data_type: &mut glue::GlueDataType,
+    n_dim: &mut std::os::raw::c_int,
+    dims: &mut [std::os::raw::c_ulong; 8],
+    exc_info: &mut glue::ExcInfo,
+    mut f: F,
+) -> std::os::raw::c_int
+where
+    F: FnMut(String),
+{
+    let rv = glue::table_get_mappable_column_layout(
+        handle,
+        ccol_name,
+        Some(casatables_string_bridge_cb::<F>),
+        &mut f as *mut _ as *mut std::os::raw::c_void,
+        data_type,
+        n_dim,
+        dims.as_mut_ptr(),
+        exc_info,
+    );
+    finish_callbacks(rv, exc_info, "table_get_mappable_column_layout")
+}
+
+impl Table {
+    /// Memory-map the data of a fixed-shape array column for reading.
+    ///
+    /// Only a narrow class of columns can be mapped. The column must be a
+    /// fixed-shape numeric array column stored with `TiledColumnStMan` (see
+    /// [`crate::TableDesc::set_data_manager`]), each tile must span whole
+    /// cells, the data manager must store no other columns, and the table byte
+    /// order must match that of the host. Columns stored with `StandardStMan`,
+    /// which interleaves the data of several columns in buckets, are not
+    /// supported. Other columns yield an error. If the table is writable, it is
+    /// flushed first so that the data file is up to date.
+    ///
+    /// # Safety
+    ///
+    /// The returned map aliases the table's data file directly. The caller
+    /// must ensure that the column's data are not modified for as long as the
+    /// map is alive, whether through this table handle, another handle, or
+    /// another process. Adding rows to the table is also forbidden, since it
+    /// may truncate and rewrite the data file.
+    pub unsafe fn map_column<T: CasaScalarData + Copy>(
+        &mut self,
+        col_name: &str,
+    ) -> Result<ColumnMmap<T>, TableError> {
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut path = String::new();
+        let mut data_type = glue::GlueDataType::TpOther;
+        let mut n_dim = 0;
+        let mut dims = [0; 8];
+
+        let rv = invoke_table_get_mappable_column_layout(
+            self.handle,
+            &ccol_name,
+            &mut data_type,
+            &mut n_dim,
+            &mut dims,
+            &mut self.exc_info,
+            |p| path = p,
+        );
+
+        if rv != 0 {
+            return self.exc_info.as_err();
+        }
+
+        if data_type != T::DATA_TYPE {
+            return Err(UnexpectedDataTypeError(T::DATA_TYPE, data_type).into());
+        }
+
+        let n_rows = self.n_rows() as usize;
+        let cell_shape: Vec<usize> = dims[..n_dim as usize].iter().map(|d| *d as usize).collect();
+        let n_bytes = n_rows * cell_shape.iter().product::<usize>() * mem::size_of::<T>();
+
+        let map = if n_bytes == 0 {
+            None
+        } else {
+            let map = Mmap::map(&File::open(&path)?)?;
+
+            if map.len() < n_bytes {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "data file \"{}\" is {} bytes long, but the column needs {}",
+                        path,
+                        map.len(),
+                        n_bytes
+                    ),
+                )
+                .into());
+            }
+
+            Some(map)
+        };
+
+        Ok(ColumnMmap {
+            map,
+            n_rows,
+            cell_shape,
+            _elem: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Complex, EndianFormat, GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode,
+    };
+    use ndarray::Array2;
+    use tempfile::tempdir;
+
+    #[test]
+    fn map_tiled_column() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("test.tab");
+        let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        table_desc
+            .add_array_column(
+                GlueDataType::TpComplex,
+                "DATA",
+                None,
+                Some(&[3, 2]),
+                true,
+                false,
+            )
+            .unwrap();
+        table_desc
+            .set_data_manager("DATA", "TiledColumnStMan", "TiledData")
+            .unwrap();
+        table_desc
+            .add_array_column(
+                GlueDataType::TpFloat,
+                "WEIGHT",
+                None,
+                Some(&[2]),
+                true,
+                false,
+            )
+            .unwrap();
+
+        let n_rows = 5;
+        let mut table = Table::new_with_endian(
+            &table_path,
+            table_desc,
+            n_rows,
+            TableCreateMode::New,
+            EndianFormat::Local,
+        )
+        .unwrap();
+
+        for row in 0..n_rows {
+            let cell = Array2::from_shape_fn((3, 2), |(i, j)| {
+                Complex::new(row as f32, (2 * i + j) as f32)
+            });
+            table.put_cell("DATA", row as u64, &cell).unwrap();
+        }
+
+        let map = unsafe { table.map_column::<Complex<f32>>("DATA").unwrap() };
+        assert_eq!(map.n_rows(), n_rows);
+        assert_eq!(map.cell_shape(), &[3, 2]);
+        assert_eq!(map.as_slice().len(), 30);
+        assert_eq!(map.cell(4)[5], Complex::new(4., 5.));
+        assert_eq!(map.as_array()[[2, 1, 0]], Complex::new(2., 2.));
+
+        assert!(unsafe { table.map_column::<f32>("DATA") }.is_err());
+        assert!(unsafe { table.map_column::<f32>("WEIGHT") }.is_err());
+    }
+}
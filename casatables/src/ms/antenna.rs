@@ -0,0 +1,65 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Reading and writing array layouts in `ANTENNA` tables.
+
+use rubbl_core::array_layout::AntennaLayout;
+
+use crate::{Table, TableError};
+
+/// Read the layout of an array from a Measurement Set’s `ANTENNA` table.
+///
+/// The names, positions, and dish diameters come from the `NAME`,
+/// `POSITION`, and `DISH_DIAMETER` columns. `POSITION` must hold ITRF
+/// coordinates, as it does in practically all data sets.
+pub fn read_antenna_layout(table: &mut Table) -> Result<AntennaLayout, TableError> {
+    let n_rows = table.n_rows();
+    let mut layout = AntennaLayout::new();
+
+    for row in 0..n_rows {
+        let name: String = table.get_cell("NAME", row)?;
+        let position: Vec<f64> = table.get_cell_as_vec("POSITION", row)?;
+        let diameter: f64 = table.get_cell("DISH_DIAMETER", row)?;
+
+        if position.len() < 3 {
+            return Err(TableError::SliceOutOfBounds {
+                cell: vec![position.len()],
+                start: vec![0],
+                shape: vec![3],
+            });
+        }
+
+        layout.push(name, [position[0], position[1], position[2]], diameter);
+    }
+
+    Ok(layout)
+}
+
+/// Write the layout of an array into a Measurement Set’s `ANTENNA` table.
+///
+/// Antenna *i* of the layout is written to row *i*, and rows are added to
+/// the table if it has too few. Besides the columns read by
+/// [`read_antenna_layout`], `STATION` is set to the antenna name, `TYPE` to
+/// `GROUND-BASED`, `MOUNT` to `ALT-AZ`, `OFFSET` to zero, and `FLAG_ROW` to
+/// false.
+pub fn write_antenna_layout(table: &mut Table, layout: &AntennaLayout) -> Result<(), TableError> {
+    let n_rows = table.n_rows() as usize;
+
+    if layout.len() > n_rows {
+        table.add_rows(layout.len() - n_rows)?;
+    }
+
+    for (i, name) in layout.names.iter().enumerate() {
+        let r = i as u64;
+        table.put_cell("NAME", r, name)?;
+        table.put_cell("STATION", r, name)?;
+        table.put_cell("TYPE", r, &"GROUND-BASED".to_owned())?;
+        table.put_cell("MOUNT", r, &"ALT-AZ".to_owned())?;
+        table.put_cell("POSITION", r, &layout.positions[i].to_vec())?;
+        table.put_cell("OFFSET", r, &vec![0f64; 3])?;
+        table.put_cell("DISH_DIAMETER", r, &layout.diameters[i])?;
+        table.put_cell("FLAG_ROW", r, &false)?;
+    }
+
+    Ok(())
+}
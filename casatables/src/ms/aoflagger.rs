@@ -0,0 +1,176 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Flagging with [AOFlagger].
+//!
+//! [AOFlagger]: https://gitlab.com/aroffringa/aoflagger
+//!
+//! This module is only available with the `aoflagger` feature, which builds a
+//! small C++ shim against an installed copy of AOFlagger 3. Its header and
+//! library are found in the compiler’s default search paths, or in the
+//! directories named by the `AOFLAGGER_INCLUDE_DIR` and `AOFLAGGER_LIB_DIR`
+//! environment variables at build time.
+
+use ndarray::{Array3, ArrayView3};
+use std::{
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int, c_uchar},
+    path::Path,
+    ptr,
+};
+use thiserror::Error;
+
+use super::FlaggerBackend;
+use crate::Complex;
+
+/// The size of the buffer for error messages from the shim.
+const MESSAGE_LEN: usize = 512;
+
+#[repr(C)]
+struct RubblAoflagger {
+    _private: [u8; 0],
+}
+
+extern "C" {
+    fn rubbl_aoflagger_new(
+        strategy_path: *const c_char,
+        message: *mut c_char,
+        message_len: usize,
+    ) -> *mut RubblAoflagger;
+
+    fn rubbl_aoflagger_free(flagger: *mut RubblAoflagger);
+
+    fn rubbl_aoflagger_run(
+        flagger: *mut RubblAoflagger,
+        n_times: usize,
+        n_chans: usize,
+        n_pols: usize,
+        data: *const f32,
+        flags_in: *const c_uchar,
+        flags_out: *mut c_uchar,
+        message: *mut c_char,
+        message_len: usize,
+    ) -> c_int;
+}
+
+/// An error reported by AOFlagger.
+#[derive(Error, Debug)]
+#[error("AOFlagger failed: {0}")]
+pub struct AoFlaggerError(pub String);
+
+impl AoFlaggerError {
+    fn from_message(message: &[c_char; MESSAGE_LEN]) -> Self {
+        let c_str = unsafe { CStr::from_ptr(message.as_ptr()) };
+        AoFlaggerError(c_str.to_string_lossy().into_owned())
+    }
+}
+
+/// A [`FlaggerBackend`] that runs an AOFlagger strategy.
+///
+/// Each block passed by [`super::run_flagger`] is flagged as one
+/// time–frequency image set, with the existing flags of any polarization
+/// marking the sample as flagged. AOFlagger flags all polarizations
+/// together, so the same flags are returned for each of them. Blocks must
+/// have 1, 2, or 4 polarizations.
+///
+/// ```no_run
+/// use rubbl_casatables::{
+///     ms::{run_flagger, AoFlagger},
+///     Table, TableOpenMode,
+/// };
+///
+/// let mut t = Table::open("vis.ms", TableOpenMode::ReadWrite).unwrap();
+/// let mut flagger = AoFlagger::with_strategy("mwa-default.lua").unwrap();
+/// let summary = run_flagger(&mut t, &mut flagger, &Default::default()).unwrap();
+/// println!("{} of {} samples flagged", summary.n_flagged_after, summary.n_samples);
+/// ```
+pub struct AoFlagger {
+    handle: *mut RubblAoflagger,
+}
+
+impl AoFlagger {
+    /// Load AOFlagger’s default strategy for a generic telescope.
+    pub fn new() -> Result<Self, AoFlaggerError> {
+        Self::load(None)
+    }
+
+    /// Load the strategy file at *path*.
+    pub fn with_strategy<P: AsRef<Path>>(path: P) -> Result<Self, AoFlaggerError> {
+        let path = path.as_ref();
+        let c_path = path
+            .to_str()
+            .and_then(|p| CString::new(p).ok())
+            .ok_or_else(|| {
+                AoFlaggerError(format!(
+                    "cannot pass strategy path `{}` to AOFlagger",
+                    path.display()
+                ))
+            })?;
+        Self::load(Some(&c_path))
+    }
+
+    fn load(path: Option<&CStr>) -> Result<Self, AoFlaggerError> {
+        let mut message = [0; MESSAGE_LEN];
+        let handle = unsafe {
+            rubbl_aoflagger_new(
+                path.map_or(ptr::null(), |p| p.as_ptr()),
+                message.as_mut_ptr(),
+                MESSAGE_LEN,
+            )
+        };
+
+        if handle.is_null() {
+            return Err(AoFlaggerError::from_message(&message));
+        }
+
+        Ok(AoFlagger { handle })
+    }
+}
+
+impl FlaggerBackend for AoFlagger {
+    type Error = AoFlaggerError;
+
+    fn flag_baseline(
+        &mut self,
+        _antennas: (i32, i32),
+        _data_desc_id: i32,
+        data: ArrayView3<Complex<f32>>,
+        flags: ArrayView3<bool>,
+    ) -> Result<Array3<bool>, AoFlaggerError> {
+        let (n_times, n_chans, n_pols) = data.dim();
+        let data = data.as_standard_layout();
+        let flags_in: Vec<c_uchar> = flags.iter().map(|f| *f as c_uchar).collect();
+        let mut flags_out = vec![0; n_times * n_chans];
+        let mut message = [0; MESSAGE_LEN];
+
+        // `Complex<f32>` is `repr(C)`, so the data are interleaved real and
+        // imaginary parts.
+        if unsafe {
+            rubbl_aoflagger_run(
+                self.handle,
+                n_times,
+                n_chans,
+                n_pols,
+                data.as_ptr() as *const f32,
+                flags_in.as_ptr(),
+                flags_out.as_mut_ptr(),
+                message.as_mut_ptr(),
+                MESSAGE_LEN,
+            )
+        } != 0
+        {
+            return Err(AoFlaggerError::from_message(&message));
+        }
+
+        Ok(Array3::from_shape_fn(
+            (n_times, n_chans, n_pols),
+            |(t, c, _)| flags_out[t * n_chans + c] != 0,
+        ))
+    }
+}
+
+impl Drop for AoFlagger {
+    fn drop(&mut self) {
+        unsafe { rubbl_aoflagger_free(self.handle) };
+    }
+}
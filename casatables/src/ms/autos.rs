@@ -0,0 +1,236 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Extracting autocorrelation spectra from Measurement Sets.
+//!
+//! The autocorrelations of each antenna — its bandpass as a function of time
+//! — are one of the first things to look at when commissioning a telescope.
+//! They make up only a small fraction of the rows of a typical data set, so
+//! [`extract_autos`] uses [`Table::read_column_chunks_where`] to decode only
+//! those rows.
+
+use ndarray::{Array4, ArrayView2, ArrayViewD};
+use rubbl_core::chunked::ArrayChunkSink;
+use std::{collections::BTreeMap, convert::Infallible};
+use thiserror::Error;
+
+use crate::{Complex, IndexRow, Table, TableError};
+
+/// An error that can occur when extracting autocorrelations.
+#[derive(Error, Debug)]
+pub enum AutoSpectraError {
+    /// An error occurred while reading the table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The autocorrelations span more than one `DATA_DESC_ID`, so that their
+    /// spectra are not comparable.
+    #[error("autocorrelations have DATA_DESC_ID values of both {0} and {1}")]
+    MultipleDataDescriptions(i32, i32),
+}
+
+/// Autocorrelation power spectra of each antenna as a function of time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutoSpectra {
+    /// The IDs of the antennas that have autocorrelations, in increasing
+    /// order.
+    pub antennas: Vec<i32>,
+
+    /// The distinct times of the autocorrelation rows, in increasing order.
+    pub times: Vec<f64>,
+
+    /// The `DATA_DESC_ID` of the rows, or `None` if there were none.
+    pub data_desc_id: Option<i32>,
+
+    /// The power, with shape `[n_antennas, n_times, n_chans, n_pols]`.
+    ///
+    /// Each value is the amplitude of the corresponding `DATA` element.
+    /// Flagged samples, and antennas that have no row at a given time, are
+    /// NaN.
+    pub power: Array4<f32>,
+}
+
+impl AutoSpectra {
+    /// Get the spectra of one antenna at one time, with shape `[n_chans,
+    /// n_pols]`, if the antenna has autocorrelations.
+    pub fn spectrum(&self, antenna: i32, time_index: usize) -> Option<ArrayView2<'_, f32>> {
+        let i = self.antennas.binary_search(&antenna).ok()?;
+
+        if time_index >= self.times.len() {
+            return None;
+        }
+
+        Some(self.power.slice(ndarray::s![i, time_index, .., ..]))
+    }
+}
+
+/// Collects the amplitudes, or flags, of the rows that pass the predicate.
+struct Collector<T> {
+    values: Vec<T>,
+    item_len: usize,
+}
+
+impl ArrayChunkSink<Complex<f32>> for Collector<f32> {
+    type Error = Infallible;
+
+    fn write_chunk(&mut self, chunk: ArrayViewD<Complex<f32>>) -> Result<(), Infallible> {
+        self.item_len = chunk.shape()[1..].iter().product();
+        self.values.extend(chunk.iter().map(|v| v.norm()));
+        Ok(())
+    }
+}
+
+impl ArrayChunkSink<bool> for Collector<bool> {
+    type Error = Infallible;
+
+    fn write_chunk(&mut self, chunk: ArrayViewD<bool>) -> Result<(), Infallible> {
+        self.values.extend(chunk.iter());
+        Ok(())
+    }
+}
+
+/// Extract the autocorrelation power spectra of a Measurement Set.
+///
+/// Only rows where `ANTENNA1 == ANTENNA2` are read. Their `DATA` amplitudes
+/// are gathered into an array indexed by antenna, time, channel, and
+/// polarization, with samples marked in the `FLAG` column set to NaN. The
+/// `DATA` and `FLAG` columns must have fixed shapes, and all of the
+/// autocorrelation rows must share one `DATA_DESC_ID`. If there are
+/// several rows for the same antenna and time, the last one wins.
+///
+/// ```no_run
+/// use rubbl_casatables::{Table, TableOpenMode};
+/// use rubbl_casatables::ms::extract_autos;
+///
+/// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+/// let autos = extract_autos(&mut t).unwrap();
+/// println!("antenna {} at t0: {:?}", autos.antennas[0], autos.spectrum(autos.antennas[0], 0));
+/// ```
+pub fn extract_autos(table: &mut Table) -> Result<AutoSpectra, AutoSpectraError> {
+    let rows_per_chunk = table.suggest_chunk_rows(&["DATA", "FLAG"], None)?;
+    let mut rows: Vec<IndexRow> = Vec::new();
+    let mut amps = Collector {
+        values: Vec::new(),
+        item_len: 0,
+    };
+
+    table.read_column_chunks_where(
+        "DATA",
+        rows_per_chunk,
+        |row| {
+            if row.antenna1 == row.antenna2 {
+                rows.push(*row);
+                true
+            } else {
+                false
+            }
+        },
+        &mut amps,
+    )?;
+
+    let mut flags = Collector {
+        values: Vec::new(),
+        item_len: 0,
+    };
+
+    table.read_column_chunks_where(
+        "FLAG",
+        rows_per_chunk,
+        |row| row.antenna1 == row.antenna2,
+        &mut flags,
+    )?;
+
+    let data_desc_id = rows.first().map(|r| r.data_desc_id);
+
+    if let Some(ddid) = data_desc_id {
+        if let Some(r) = rows.iter().find(|r| r.data_desc_id != ddid) {
+            return Err(AutoSpectraError::MultipleDataDescriptions(
+                ddid,
+                r.data_desc_id,
+            ));
+        }
+    }
+
+    // Lay out the axes.
+
+    let mut antennas: Vec<i32> = rows.iter().map(|r| r.antenna1).collect();
+    antennas.sort_unstable();
+    antennas.dedup();
+
+    let mut times: Vec<f64> = rows.iter().map(|r| r.time).collect();
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    times.dedup();
+
+    let cell_shape = if rows.is_empty() {
+        vec![0, 0]
+    } else {
+        table.get_cell_shape("DATA", rows[0].row)?
+    };
+    let n_chans = cell_shape.first().copied().unwrap_or(1);
+    let n_pols: usize = cell_shape.iter().skip(1).product();
+    let cell_len = amps.item_len;
+
+    let mut power = Array4::from_elem((antennas.len(), times.len(), n_chans, n_pols), f32::NAN);
+    let ant_index: BTreeMap<i32, usize> =
+        antennas.iter().enumerate().map(|(i, a)| (*a, i)).collect();
+
+    for (i, row) in rows.iter().enumerate() {
+        let a = ant_index[&row.antenna1];
+        let t = times
+            .binary_search_by(|x| x.partial_cmp(&row.time).unwrap())
+            .unwrap();
+        let cell = &amps.values[i * cell_len..(i + 1) * cell_len];
+        let flag = &flags.values[i * cell_len..(i + 1) * cell_len];
+
+        for (k, (amp, flagged)) in cell.iter().zip(flag.iter()).enumerate() {
+            power[[a, t, k / n_pols, k % n_pols]] = if *flagged { f32::NAN } else { *amp };
+        }
+    }
+
+    Ok(AutoSpectra {
+        antennas,
+        times,
+        data_desc_id,
+        power,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn synthetic_autos() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec::default();
+        let mut t = synthetic_ms(tmp_dir.path().join("test.ms"), &spec).unwrap();
+
+        // Flag one sample of the second autocorrelation row, which is
+        // baseline (1, 1) of the first timestep.
+        let row = spec.n_ants as u64;
+        let mut flags: Vec<bool> = t.get_cell_as_vec("FLAG", row).unwrap();
+        flags[3] = true;
+        let flags = ndarray::Array::from_shape_vec((spec.n_chans, spec.n_pols), flags).unwrap();
+        t.put_cell("FLAG", row, &flags).unwrap();
+
+        let autos = extract_autos(&mut t).unwrap();
+        assert_eq!(autos.antennas, vec![0, 1, 2, 3]);
+        assert_eq!(autos.times.len(), spec.n_timesteps);
+        assert_eq!(autos.data_desc_id, Some(0));
+        assert_eq!(
+            autos.power.shape(),
+            &[spec.n_ants, spec.n_timesteps, spec.n_chans, spec.n_pols]
+        );
+
+        let s = autos.spectrum(0, 1).unwrap();
+        let row = spec.n_baselines();
+        assert_eq!(s[[2, 1]], spec.expected_vis(row, 2, 1).norm());
+
+        let s = autos.spectrum(1, 0).unwrap();
+        assert!(s[[0, 3]].is_nan());
+        assert!(!s[[0, 2]].is_nan());
+        assert!(autos.spectrum(7, 0).is_none());
+    }
+}
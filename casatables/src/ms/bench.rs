@@ -0,0 +1,395 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Benchmarking how fast visibility data can be written.
+//!
+//! The cost of writing a `DATA` column depends heavily on how the writes are
+//! issued and on the storage manager behind the column. [`run_write_bench`]
+//! writes a synthetic column of a given shape with a chosen pattern and
+//! storage manager and reports how long each phase took. It is exposed on
+//! the command line as `rubbl mstable bench`, which is convenient to run
+//! under `perf`, `strace`, and the like.
+
+use ndarray::{Array2, ArrayViewMutD, Axis};
+use std::{convert::Infallible, fmt, path::Path, str::FromStr, time::Instant};
+use thiserror::Error;
+
+use crate::{
+    CasacoreError, Complex, GlueDataType, Table, TableCreateMode, TableDesc, TableDescCreateMode,
+    TableError, TableRecord,
+};
+use rubbl_core::chunked::ArrayChunkSource;
+
+/// The name of the benchmarked column.
+const COLUMN: &str = "DATA";
+
+/// An error that can occur when running a write benchmark.
+#[derive(Error, Debug)]
+pub enum WriteBenchError {
+    /// The benchmark options are inconsistent.
+    #[error("invalid benchmark options: {0}")]
+    InvalidOptions(String),
+
+    /// The table could not be created or written.
+    #[error(transparent)]
+    Table(#[from] TableError),
+}
+
+impl From<CasacoreError> for WriteBenchError {
+    fn from(e: CasacoreError) -> Self {
+        WriteBenchError::Table(e.into())
+    }
+}
+
+/// The way in which a write benchmark issues its writes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WritePattern {
+    /// Create the table with all of its rows, then write one cell at a time
+    /// with [`Table::put_cell`].
+    Cell,
+
+    /// Like [`WritePattern::Cell`], but with [`Table::put_cell_cached`].
+    CellCached,
+
+    /// Start with an empty table and add one row before writing each cell,
+    /// as a streaming writer would.
+    Grow,
+
+    /// Start with an empty table and write in chunks of rows with
+    /// [`Table::write_column_chunks`].
+    Chunks,
+}
+
+impl WritePattern {
+    /// All of the patterns.
+    pub const ALL: &'static [WritePattern] = &[
+        WritePattern::Cell,
+        WritePattern::CellCached,
+        WritePattern::Grow,
+        WritePattern::Chunks,
+    ];
+
+    /// Get the name of this pattern, as used on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            WritePattern::Cell => "cell",
+            WritePattern::CellCached => "cell-cached",
+            WritePattern::Grow => "grow",
+            WritePattern::Chunks => "chunks",
+        }
+    }
+}
+
+impl fmt::Display for WritePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for WritePattern {
+    type Err = WriteBenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WritePattern::ALL
+            .iter()
+            .find(|p| p.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                WriteBenchError::InvalidOptions(format!("unknown write pattern \"{s}\""))
+            })
+    }
+}
+
+/// Options controlling [`run_write_bench`].
+#[derive(Clone, Debug)]
+pub struct WriteBenchOptions {
+    /// How the writes are issued.
+    pub pattern: WritePattern,
+
+    /// The type of the data manager storing the column, such as
+    /// `StandardStMan` or `TiledColumnStMan`.
+    pub storage_manager: String,
+
+    /// The tile shape to use with a tiled storage manager, in C order: rows,
+    /// then channels, then polarizations. If `None`, the storage manager
+    /// chooses.
+    pub tile_shape: Option<Vec<usize>>,
+
+    /// The number of rows to write.
+    pub n_rows: usize,
+
+    /// The number of channels in each cell.
+    pub n_chans: usize,
+
+    /// The number of polarizations in each cell.
+    pub n_pols: usize,
+
+    /// The number of rows per chunk for [`WritePattern::Chunks`]. If `None`,
+    /// [`Table::suggest_chunk_rows`] chooses.
+    pub chunk_rows: Option<usize>,
+}
+
+impl Default for WriteBenchOptions {
+    fn default() -> Self {
+        WriteBenchOptions {
+            pattern: WritePattern::Cell,
+            storage_manager: "StandardStMan".to_owned(),
+            tile_shape: None,
+            n_rows: 10_000,
+            n_chans: 768,
+            n_pols: 4,
+            chunk_rows: None,
+        }
+    }
+}
+
+/// The timings measured by [`run_write_bench`].
+#[derive(Clone, Debug)]
+pub struct WriteBenchResult {
+    /// The pattern that was used.
+    pub pattern: WritePattern,
+
+    /// The data manager type that was used.
+    pub storage_manager: String,
+
+    /// The number of rows written.
+    pub n_rows: usize,
+
+    /// The number of rows per chunk, or 1 for the per-cell patterns.
+    pub chunk_rows: usize,
+
+    /// The number of bytes of visibility data written.
+    pub bytes: u64,
+
+    /// The time taken to create the table, in seconds.
+    pub create_seconds: f64,
+
+    /// The time taken to write the data, in seconds.
+    pub write_seconds: f64,
+
+    /// The time taken to close the table, including flushing it to disk, in
+    /// seconds.
+    pub close_seconds: f64,
+}
+
+impl WriteBenchResult {
+    /// Get the total time taken, in seconds.
+    pub fn total_seconds(&self) -> f64 {
+        self.create_seconds + self.write_seconds + self.close_seconds
+    }
+
+    /// Get the overall write throughput, in bytes per second.
+    pub fn bytes_per_second(&self) -> f64 {
+        self.bytes as f64 / self.total_seconds()
+    }
+
+    /// Render the result as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"pattern\":\"{}\",\"storage_manager\":\"{}\",\"n_rows\":{},\"chunk_rows\":{},\
+             \"bytes\":{},\"create_seconds\":{:e},\"write_seconds\":{:e},\
+             \"close_seconds\":{:e},\"bytes_per_second\":{:e}}}",
+            self.pattern,
+            self.storage_manager.escape_default(),
+            self.n_rows,
+            self.chunk_rows,
+            self.bytes,
+            self.create_seconds,
+            self.write_seconds,
+            self.close_seconds,
+            self.bytes_per_second(),
+        )
+    }
+}
+
+/// A chunk source that yields the same cell over and over.
+struct RepeatedCells {
+    cell: Array2<Complex<f32>>,
+    item_shape: Vec<usize>,
+    remaining: usize,
+}
+
+impl ArrayChunkSource<Complex<f32>> for RepeatedCells {
+    type Error = Infallible;
+
+    fn item_shape(&self) -> &[usize] {
+        &self.item_shape[..]
+    }
+
+    fn read_chunk(&mut self, mut dest: ArrayViewMutD<Complex<f32>>) -> Result<usize, Infallible> {
+        let n = dest.len_of(Axis(0)).min(self.remaining);
+
+        for i in 0..n {
+            dest.index_axis_mut(Axis(0), i).assign(&self.cell);
+        }
+
+        self.remaining -= n;
+        Ok(n)
+    }
+}
+
+/// Write a synthetic `DATA` column to a new table at *path* and time the
+/// process.
+///
+/// The table must not already exist. It is left in place afterwards; it is
+/// up to the caller to inspect or delete it.
+pub fn run_write_bench<P: AsRef<Path>>(
+    path: P,
+    options: &WriteBenchOptions,
+) -> Result<WriteBenchResult, WriteBenchError> {
+    if options.n_chans == 0 || options.n_pols == 0 {
+        return Err(WriteBenchError::InvalidOptions(
+            "cells must have at least one channel and polarization".to_owned(),
+        ));
+    }
+
+    let mut table_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH)?;
+    table_desc.add_array_column(
+        GlueDataType::TpComplex,
+        COLUMN,
+        None,
+        Some(&[options.n_chans as u64, options.n_pols as u64]),
+        true,
+        false,
+    )?;
+
+    let initial_rows = match options.pattern {
+        WritePattern::Cell | WritePattern::CellCached => options.n_rows,
+        WritePattern::Grow | WritePattern::Chunks => 0,
+    };
+
+    let t0 = Instant::now();
+
+    let mut table = if let Some(ref tile_shape) = options.tile_shape {
+        if tile_shape.len() != 3 {
+            return Err(WriteBenchError::InvalidOptions(format!(
+                "tile shapes must have three axes, but got {tile_shape:?}"
+            )));
+        }
+
+        // casacore wants the tile shape in Fortran order.
+        let fortran: Vec<i32> = tile_shape.iter().rev().map(|n| *n as i32).collect();
+        let mut spec = TableRecord::new()?;
+        spec.put_field("DEFAULTTILESHAPE", &fortran)?;
+
+        let mut dm = TableRecord::new()?;
+        dm.put_field("TYPE", &options.storage_manager)?;
+        dm.put_field("NAME", &"BenchData".to_owned())?;
+        dm.put_field("SPEC", &spec)?;
+        dm.put_field("COLUMNS", &vec![COLUMN.to_owned()])?;
+
+        let mut dminfo = TableRecord::new()?;
+        dminfo.put_field("*1", &dm)?;
+
+        Table::new_with_dminfo(
+            path,
+            table_desc,
+            initial_rows,
+            TableCreateMode::New,
+            &dminfo,
+        )?
+    } else {
+        table_desc.set_data_manager(COLUMN, &options.storage_manager, "BenchData")?;
+        Table::new(path, table_desc, initial_rows, TableCreateMode::New)?
+    };
+
+    let t1 = Instant::now();
+
+    let cell = Array2::from_shape_fn((options.n_chans, options.n_pols), |(chan, pol)| {
+        Complex::new(chan as f32, pol as f32)
+    });
+    let mut chunk_rows = 1;
+
+    match options.pattern {
+        WritePattern::Cell => {
+            for row in 0..options.n_rows {
+                table.put_cell(COLUMN, row as u64, &cell)?;
+            }
+        }
+
+        WritePattern::CellCached => {
+            for row in 0..options.n_rows {
+                table.put_cell_cached(COLUMN, row as u64, &cell)?;
+            }
+        }
+
+        WritePattern::Grow => {
+            for row in 0..options.n_rows {
+                table.add_rows(1)?;
+                table.put_cell_cached(COLUMN, row as u64, &cell)?;
+            }
+        }
+
+        WritePattern::Chunks => {
+            chunk_rows = match options.chunk_rows {
+                Some(n) => n.max(1),
+                None => table.suggest_chunk_rows(&[COLUMN], None)?,
+            };
+
+            let mut source = RepeatedCells {
+                item_shape: cell.shape().to_vec(),
+                cell,
+                remaining: options.n_rows,
+            };
+
+            table.write_column_chunks(COLUMN, 0, chunk_rows, &mut source)?;
+        }
+    }
+
+    let t2 = Instant::now();
+    drop(table);
+    let t3 = Instant::now();
+
+    Ok(WriteBenchResult {
+        pattern: options.pattern,
+        storage_manager: options.storage_manager.clone(),
+        n_rows: options.n_rows,
+        chunk_rows,
+        bytes: (options.n_rows * options.n_chans * options.n_pols) as u64
+            * std::mem::size_of::<Complex<f32>>() as u64,
+        create_seconds: (t1 - t0).as_secs_f64(),
+        write_seconds: (t2 - t1).as_secs_f64(),
+        close_seconds: (t3 - t2).as_secs_f64(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TableOpenMode;
+    use tempfile::tempdir;
+
+    #[test]
+    fn all_patterns() {
+        let tmp_dir = tempdir().unwrap();
+
+        for (i, pattern) in WritePattern::ALL.iter().enumerate() {
+            let path = tmp_dir.path().join(format!("bench{i}.tab"));
+            let options = WriteBenchOptions {
+                pattern: *pattern,
+                storage_manager: "TiledColumnStMan".to_owned(),
+                tile_shape: Some(vec![4, 8, 2]),
+                n_rows: 10,
+                n_chans: 8,
+                n_pols: 2,
+                chunk_rows: Some(3),
+            };
+
+            let result = run_write_bench(&path, &options).unwrap();
+            assert_eq!(result.bytes, 10 * 8 * 2 * 8);
+            assert!(result.to_json().starts_with("{\"pattern\":\""));
+
+            let mut table = Table::open(&path, TableOpenMode::Read).unwrap();
+            assert_eq!(table.n_rows(), 10);
+            assert_eq!(
+                table.column_tile_shape("DATA").unwrap(),
+                Some(vec![4, 8, 2])
+            );
+            let cell: Vec<Complex<f32>> = table.get_cell_as_vec("DATA", 9).unwrap();
+            assert_eq!(cell[15], Complex::new(7., 1.));
+        }
+
+        assert_eq!("grow".parse::<WritePattern>().unwrap(), WritePattern::Grow);
+        assert!("nope".parse::<WritePattern>().is_err());
+    }
+}
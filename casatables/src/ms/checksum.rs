@@ -0,0 +1,273 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Per-row checksums of Measurement Set data.
+//!
+//! A manifest (see [`crate::write_manifest`]) can say that a file of a
+//! table has been corrupted, but not which rows are affected, and a large
+//! data set that is still being appended to never has a stable manifest.
+//! Instead, [`super::StreamWriter::checksum_column`] makes the stream writer
+//! store a CRC-32 of selected columns of each row in a column of its own,
+//! and [`verify_row_checksums`] later recomputes them and lists the rows
+//! that no longer match.
+//!
+//! The checksum of a row covers the cells of the selected columns in order.
+//! Scalars contribute their values; arrays contribute their shape, as one
+//! little-endian `u64` per axis in C order, followed by their elements in C
+//! order. Numbers are encoded in little-endian byte order, booleans as one
+//! byte, complex numbers as their real part followed by their imaginary
+//! part, and strings as their length as a `u64` followed by their UTF-8
+//! bytes. The checksum column records the selected columns in its
+//! [`CHECKSUM_COLUMNS_KEYWORD`] keyword, and the first row that it covers in
+//! its [`CHECKSUM_START_ROW_KEYWORD`] keyword.
+
+use crc32fast::Hasher as Crc32;
+use ndarray::ArrayD;
+use thiserror::Error;
+
+use crate::{CellValue, Complex, Table, TableError};
+
+/// The name of the keyword of a checksum column that lists the columns
+/// covered by its checksums.
+pub const CHECKSUM_COLUMNS_KEYWORD: &str = "CHECKSUM_COLUMNS";
+
+/// The name of the keyword of a checksum column giving the first row that
+/// has a checksum. Rows that were in the table before the column was added
+/// are not covered.
+pub const CHECKSUM_START_ROW_KEYWORD: &str = "CHECKSUM_START_ROW";
+
+/// An error that can occur when verifying row checksums.
+#[derive(Error, Debug)]
+pub enum ChecksumError {
+    /// An error occurred while reading the table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The checksum column does not say which columns it covers.
+    #[error("column \"{0}\" does not have the keywords of a checksum column")]
+    NotChecksumColumn(String),
+}
+
+impl From<crate::CasacoreError> for ChecksumError {
+    fn from(e: crate::CasacoreError) -> Self {
+        ChecksumError::Table(e.into())
+    }
+}
+
+/// The result of [`verify_row_checksums`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChecksumReport {
+    /// The number of rows that were checked.
+    pub n_rows: u64,
+
+    /// The rows whose data no longer match their checksums, in increasing
+    /// order.
+    pub mismatched_rows: Vec<u64>,
+}
+
+impl ChecksumReport {
+    /// Return true if every row matches its checksum.
+    pub fn is_ok(&self) -> bool {
+        self.mismatched_rows.is_empty()
+    }
+}
+
+/// Check the data of *table* against the checksums stored in its column
+/// *checksum_col*.
+///
+/// Every covered cell is read. Rows whose data do not match are listed in
+/// the returned report; an error is only returned if the data cannot be
+/// read. Rows that a [`super::StreamWriter`] added in advance but has not
+/// yet filled do not match, so verify a table after the writer has
+/// finished.
+pub fn verify_row_checksums(
+    table: &mut Table,
+    checksum_col: &str,
+) -> Result<ChecksumReport, ChecksumError> {
+    let mut keywords = table.get_column_keyword_record(checksum_col)?;
+    let names = keywords.keyword_names()?;
+
+    if !names.iter().any(|n| n == CHECKSUM_COLUMNS_KEYWORD)
+        || !names.iter().any(|n| n == CHECKSUM_START_ROW_KEYWORD)
+    {
+        return Err(ChecksumError::NotChecksumColumn(checksum_col.to_owned()));
+    }
+
+    let columns: Vec<String> = keywords.get_field(CHECKSUM_COLUMNS_KEYWORD)?;
+    let start_row = keywords
+        .get_field::<i64>(CHECKSUM_START_ROW_KEYWORD)?
+        .max(0) as u64;
+    let stored: Vec<u32> = table.get_col_as_vec(checksum_col)?;
+    let mut report = ChecksumReport::default();
+
+    for row in start_row..table.n_rows() {
+        let mut crc = Crc32::new();
+
+        for col_name in &columns {
+            table.get_cell_dyn(col_name, row)?.feed(&mut crc);
+        }
+
+        report.n_rows += 1;
+
+        if crc.finalize() != stored[row as usize] {
+            report.mismatched_rows.push(row);
+        }
+    }
+
+    Ok(report)
+}
+
+impl Table {
+    /// Check the data of this table against the checksums stored in the
+    /// column *checksum_col*.
+    ///
+    /// See [`verify_row_checksums`] for details.
+    pub fn verify_row_checksums(
+        &mut self,
+        checksum_col: &str,
+    ) -> Result<ChecksumReport, ChecksumError> {
+        verify_row_checksums(self, checksum_col)
+    }
+}
+
+/// The checksums of a block of consecutive rows, built up one column at a
+/// time.
+pub(crate) struct RowChecksums(Vec<Crc32>);
+
+impl RowChecksums {
+    pub(crate) fn new(n_rows: usize) -> Self {
+        RowChecksums(vec![Crc32::new(); n_rows])
+    }
+
+    /// Add a scalar column, with one value per row.
+    pub(crate) fn add_scalars<T: ChecksumElement>(&mut self, values: &[T]) {
+        for (crc, v) in self.0.iter_mut().zip(values) {
+            v.feed(crc);
+        }
+    }
+
+    /// Add an array column whose cells have shape *cell_shape*. *data* holds
+    /// the cells contiguously in C order.
+    pub(crate) fn add_cells<T: ChecksumElement>(&mut self, cell_shape: &[usize], data: &[T]) {
+        let cell_len = cell_shape.iter().product::<usize>();
+
+        for (i, crc) in self.0.iter_mut().enumerate() {
+            feed_shape(crc, cell_shape);
+
+            for v in &data[i * cell_len..(i + 1) * cell_len] {
+                v.feed(crc);
+            }
+        }
+    }
+
+    pub(crate) fn finish(&self) -> Vec<u32> {
+        self.0.iter().map(|crc| crc.clone().finalize()).collect()
+    }
+}
+
+fn feed_shape(crc: &mut Crc32, shape: &[usize]) {
+    for d in shape {
+        crc.update(&(*d as u64).to_le_bytes());
+    }
+}
+
+/// A value that can be fed into a row checksum.
+pub(crate) trait ChecksumElement {
+    fn feed(&self, crc: &mut Crc32);
+}
+
+macro_rules! impl_checksum_element {
+    ($($t:ty),*) => {
+        $(
+            impl ChecksumElement for $t {
+                fn feed(&self, crc: &mut Crc32) {
+                    crc.update(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_checksum_element! { i8, u8, i16, u16, i32, u32, i64, f32, f64 }
+
+impl ChecksumElement for bool {
+    fn feed(&self, crc: &mut Crc32) {
+        crc.update(&[*self as u8]);
+    }
+}
+
+impl<T: ChecksumElement> ChecksumElement for Complex<T> {
+    fn feed(&self, crc: &mut Crc32) {
+        self.re.feed(crc);
+        self.im.feed(crc);
+    }
+}
+
+impl ChecksumElement for String {
+    fn feed(&self, crc: &mut Crc32) {
+        crc.update(&(self.len() as u64).to_le_bytes());
+        crc.update(self.as_bytes());
+    }
+}
+
+impl<T: ChecksumElement> ChecksumElement for ArrayD<T> {
+    fn feed(&self, crc: &mut Crc32) {
+        feed_shape(crc, self.shape());
+
+        for v in self.iter() {
+            v.feed(crc);
+        }
+    }
+}
+
+impl ChecksumElement for CellValue {
+    fn feed(&self, crc: &mut Crc32) {
+        match self {
+            CellValue::Bool(v) => v.feed(crc),
+            CellValue::I8(v) => v.feed(crc),
+            CellValue::U8(v) => v.feed(crc),
+            CellValue::I16(v) => v.feed(crc),
+            CellValue::U16(v) => v.feed(crc),
+            CellValue::I32(v) => v.feed(crc),
+            CellValue::U32(v) => v.feed(crc),
+            CellValue::I64(v) => v.feed(crc),
+            CellValue::F32(v) => v.feed(crc),
+            CellValue::F64(v) => v.feed(crc),
+            CellValue::C32(v) => v.feed(crc),
+            CellValue::C64(v) => v.feed(crc),
+            CellValue::String(v) => v.feed(crc),
+            CellValue::BoolArray(v) => v.feed(crc),
+            CellValue::I8Array(v) => v.feed(crc),
+            CellValue::U8Array(v) => v.feed(crc),
+            CellValue::I16Array(v) => v.feed(crc),
+            CellValue::U16Array(v) => v.feed(crc),
+            CellValue::I32Array(v) => v.feed(crc),
+            CellValue::U32Array(v) => v.feed(crc),
+            CellValue::I64Array(v) => v.feed(crc),
+            CellValue::F32Array(v) => v.feed(crc),
+            CellValue::F64Array(v) => v.feed(crc),
+            CellValue::C32Array(v) => v.feed(crc),
+            CellValue::C64Array(v) => v.feed(crc),
+            CellValue::StringArray(v) => v.feed(crc),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_match_cell_values() {
+        let mut rows = RowChecksums::new(2);
+        rows.add_scalars(&[1.5f64, 2.5]);
+        rows.add_cells(&[2], &[true, false, false, true]);
+        let sums = rows.finish();
+
+        let mut crc = Crc32::new();
+        CellValue::F64(2.5).feed(&mut crc);
+        CellValue::from(vec![false, true]).feed(&mut crc);
+        assert_eq!(crc.finalize(), sums[1]);
+        assert_ne!(sums[0], sums[1]);
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Comparing the visibilities of two Measurement Sets.
+//!
+//! Conversion pipelines rarely reproduce visibilities bit for bit — a change
+//! of precision or an extra rounding step is enough to spoil an exact
+//! comparison — so tests of such pipelines need to know how different two
+//! data sets are, not merely whether they differ. [`compare_data`] walks the
+//! `DATA` and `FLAG` columns of two data sets side by side and summarizes
+//! their differences in a [`DiffStats`].
+
+use std::fmt;
+use thiserror::Error;
+
+use crate::{Complex, Table, TableError};
+
+/// An error that can occur when comparing data sets.
+#[derive(Error, Debug)]
+pub enum CompareError {
+    /// An error occurred while reading one of the tables.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The data sets have different numbers of rows.
+    #[error("cannot compare data sets with {0} and {1} rows")]
+    RowCountMismatch(u64, u64),
+}
+
+/// A summary of the differences between the visibilities of two data sets,
+/// as computed by [`compare_data`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DiffStats {
+    /// The number of rows compared.
+    pub n_rows: u64,
+
+    /// The number of visibilities compared. Rows whose cells differ in shape
+    /// are not included.
+    pub n_values: u64,
+
+    /// The largest absolute difference between corresponding visibilities.
+    pub max_deviation: f64,
+
+    /// The mean absolute difference between corresponding visibilities.
+    pub mean_deviation: f64,
+
+    /// The number of visibilities whose difference exceeds the tolerance.
+    pub n_over_tolerance: u64,
+
+    /// The number of visibilities flagged in one data set but not the other.
+    pub n_flag_mismatches: u64,
+
+    /// The number of rows whose `DATA` or `FLAG` cells differ in shape.
+    pub n_shape_mismatches: u64,
+
+    /// The rows that differ in any of the above ways, in increasing order.
+    pub differing_rows: Vec<u64>,
+}
+
+impl DiffStats {
+    /// Test whether the data sets match to within the tolerance: every
+    /// visibility is close enough, the flags are identical, and all of the
+    /// cells have the same shapes.
+    ///
+    /// This makes for a convenient test assertion, since the `Display`
+    /// implementation describes what went wrong:
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{ms::compare_data, Table, TableOpenMode};
+    ///
+    /// let mut expected = Table::open("expected.ms", TableOpenMode::Read).unwrap();
+    /// let mut actual = Table::open("converted.ms", TableOpenMode::Read).unwrap();
+    /// let stats = compare_data(&mut expected, &mut actual, 1e-6).unwrap();
+    /// assert!(stats.is_match(), "{}", stats);
+    /// ```
+    pub fn is_match(&self) -> bool {
+        self.differing_rows.is_empty()
+    }
+}
+
+impl fmt::Display for DiffStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} rows differ: {} of {} visibilities over tolerance \
+             (max deviation {:.6e}, mean {:.6e}), {} flag mismatches, {} shape mismatches",
+            self.differing_rows.len(),
+            self.n_rows,
+            self.n_over_tolerance,
+            self.n_values,
+            self.max_deviation,
+            self.mean_deviation,
+            self.n_flag_mismatches,
+            self.n_shape_mismatches
+        )?;
+
+        if let Some(row) = self.differing_rows.first() {
+            write!(f, "; first differing row is {}", row)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compare the visibilities and flags of two Measurement Sets row by row.
+///
+/// Row *i* of *a* is compared with row *i* of *b*. The deviation of a
+/// visibility is the magnitude of the complex difference between the two
+/// values, and it is over tolerance if it exceeds *tol*. Values that are NaN
+/// in both data sets are considered equal, while a NaN in only one of them
+/// is infinitely far from anything. All visibilities are compared, flagged
+/// or not.
+///
+/// Both data sets must have the same number of rows; otherwise
+/// [`CompareError::RowCountMismatch`] is returned.
+pub fn compare_data(a: &mut Table, b: &mut Table, tol: f64) -> Result<DiffStats, CompareError> {
+    let n_rows = a.n_rows();
+
+    if b.n_rows() != n_rows {
+        return Err(CompareError::RowCountMismatch(n_rows, b.n_rows()));
+    }
+
+    let mut stats = DiffStats {
+        n_rows,
+        ..DiffStats::default()
+    };
+    let mut total_deviation = 0.;
+
+    for row in 0..n_rows {
+        let mut differs = false;
+
+        if a.get_cell_shape("DATA", row)? != b.get_cell_shape("DATA", row)?
+            || a.get_cell_shape("FLAG", row)? != b.get_cell_shape("FLAG", row)?
+        {
+            stats.n_shape_mismatches += 1;
+            stats.differing_rows.push(row);
+            continue;
+        }
+
+        let data_a: Vec<Complex<f32>> = a.get_cell_as_vec("DATA", row)?;
+        let data_b: Vec<Complex<f32>> = b.get_cell_as_vec("DATA", row)?;
+
+        for (va, vb) in data_a.iter().zip(&data_b) {
+            let dev = deviation(*va, *vb);
+            stats.max_deviation = stats.max_deviation.max(dev);
+            total_deviation += dev;
+
+            if dev > tol {
+                stats.n_over_tolerance += 1;
+                differs = true;
+            }
+        }
+
+        stats.n_values += data_a.len() as u64;
+
+        let flags_a: Vec<bool> = a.get_cell_as_vec("FLAG", row)?;
+        let flags_b: Vec<bool> = b.get_cell_as_vec("FLAG", row)?;
+        let n_mismatched = flags_a.iter().zip(&flags_b).filter(|(x, y)| x != y).count();
+
+        if n_mismatched > 0 {
+            stats.n_flag_mismatches += n_mismatched as u64;
+            differs = true;
+        }
+
+        if differs {
+            stats.differing_rows.push(row);
+        }
+    }
+
+    if stats.n_values > 0 {
+        stats.mean_deviation = total_deviation / stats.n_values as f64;
+    }
+
+    Ok(stats)
+}
+
+/// Compute the magnitude of the difference between two visibilities.
+fn deviation(a: Complex<f32>, b: Complex<f32>) -> f64 {
+    let a_nan = a.re.is_nan() || a.im.is_nan();
+    let b_nan = b.re.is_nan() || b.im.is_nan();
+
+    if a_nan || b_nan {
+        return if a_nan && b_nan { 0. } else { f64::INFINITY };
+    }
+
+    let dre = a.re as f64 - b.re as f64;
+    let dim = a.im as f64 - b.im as f64;
+    dre.hypot(dim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use ndarray::Array2;
+    use tempfile::tempdir;
+
+    #[test]
+    fn deviations_and_flags() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec::default();
+        let mut a = synthetic_ms(tmp_dir.path().join("a.ms"), &spec).unwrap();
+        let mut b = synthetic_ms(tmp_dir.path().join("b.ms"), &spec).unwrap();
+
+        let stats = compare_data(&mut a, &mut b, 0.).unwrap();
+        assert!(stats.is_match(), "{}", stats);
+        assert_eq!(stats.n_rows, spec.n_rows() as u64);
+        assert_eq!(
+            stats.n_values,
+            (spec.n_rows() * spec.n_chans * spec.n_pols) as u64
+        );
+        assert_eq!(stats.max_deviation, 0.);
+
+        // Nudge one visibility slightly and another a lot, and flag a third.
+        let mut data: Array2<Complex<f32>> = b.get_cell("DATA", 2).unwrap();
+        data[[0, 0]] += Complex::new(1e-4, 0.);
+        data[[1, 0]] += Complex::new(3., 4.);
+        b.put_cell("DATA", 2, &data).unwrap();
+
+        let mut flags: Array2<bool> = b.get_cell("FLAG", 5).unwrap();
+        flags[[3, 1]] = true;
+        b.put_cell("FLAG", 5, &flags).unwrap();
+
+        let stats = compare_data(&mut a, &mut b, 1e-3).unwrap();
+        assert!(!stats.is_match());
+        assert_eq!(stats.n_over_tolerance, 1);
+        assert_eq!(stats.n_flag_mismatches, 1);
+        assert_eq!(stats.n_shape_mismatches, 0);
+        assert_eq!(stats.differing_rows, vec![2, 5]);
+        assert!((stats.max_deviation - 5.).abs() < 1e-4);
+        assert!(stats.mean_deviation > 0.);
+
+        b.add_rows(1).unwrap();
+        assert!(matches!(
+            compare_data(&mut a, &mut b, 1e-3),
+            Err(CompareError::RowCountMismatch(_, _))
+        ));
+    }
+}
@@ -0,0 +1,242 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Support shared by the converters from other visibility data formats.
+//!
+//! The converters first create an empty Measurement Set with
+//! [`Table::create_with_default_subtables`], then fill in the main table row
+//! by row with [`put_main_row`], and finally write the sub-tables describing
+//! the observation from the metadata that they have accumulated.
+
+use ndarray::Array2;
+use rubbl_core::stokes::Stokes;
+use std::path::Path;
+
+use super::{Feed, SpectralWindow};
+use crate::{Complex, Table, TableError, TableOpenMode};
+
+/// The offset between Julian dates and Modified Julian Dates.
+pub(crate) const MJD_OFFSET: f64 = 2_400_000.5;
+
+/// A field, with coordinates in radians.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Field {
+    pub name: String,
+    pub ra: f64,
+    pub dec: f64,
+}
+
+/// An antenna, with an ITRF position in meters.
+#[derive(Clone, Debug)]
+pub(crate) struct Antenna {
+    pub name: String,
+    pub station: String,
+    pub position: [f64; 3],
+    pub diameter: f64,
+    pub mount: String,
+}
+
+/// The contents of one row of the main table.
+#[derive(Clone, Debug)]
+pub(crate) struct MainRow {
+    /// MJD seconds.
+    pub time: f64,
+    pub interval: f64,
+    pub ant1: usize,
+    pub ant2: usize,
+    pub data_desc_id: usize,
+    pub field_id: usize,
+    pub scan_number: i32,
+    /// Meters.
+    pub uvw: [f64; 3],
+    /// One per polarization.
+    pub weight: Vec<f32>,
+    /// Shape (n_chans, n_pols).
+    pub data: Array2<Complex<f32>>,
+    /// Shape (n_chans, n_pols); true means bad.
+    pub flags: Array2<bool>,
+}
+
+/// Fill in the cells of an existing row of the main table.
+pub(crate) fn put_main_row(main: &mut Table, row: u64, r: &MainRow) -> Result<(), TableError> {
+    let sigma: Vec<f32> = r
+        .weight
+        .iter()
+        .map(|w| if *w > 0. { 1. / w.sqrt() } else { 0. })
+        .collect();
+    let flag_row = r.flags.iter().all(|f| *f);
+
+    main.put_cell("TIME", row, &r.time)?;
+    main.put_cell("TIME_CENTROID", row, &r.time)?;
+    main.put_cell("ANTENNA1", row, &(r.ant1 as i32))?;
+    main.put_cell("ANTENNA2", row, &(r.ant2 as i32))?;
+    main.put_cell("FEED1", row, &0i32)?;
+    main.put_cell("FEED2", row, &0i32)?;
+    main.put_cell("DATA_DESC_ID", row, &(r.data_desc_id as i32))?;
+    main.put_cell("PROCESSOR_ID", row, &-1i32)?;
+    main.put_cell("FIELD_ID", row, &(r.field_id as i32))?;
+    main.put_cell("INTERVAL", row, &r.interval)?;
+    main.put_cell("EXPOSURE", row, &r.interval)?;
+    main.put_cell("SCAN_NUMBER", row, &r.scan_number)?;
+    main.put_cell("ARRAY_ID", row, &0i32)?;
+    main.put_cell("OBSERVATION_ID", row, &0i32)?;
+    main.put_cell("STATE_ID", row, &-1i32)?;
+    main.put_cell("UVW", row, &r.uvw.to_vec())?;
+    main.put_cell("SIGMA", row, &sigma)?;
+    main.put_cell("WEIGHT", row, &r.weight)?;
+    main.put_cell("FLAG", row, &r.flags)?;
+    main.put_cell("FLAG_ROW", row, &flag_row)?;
+    main.put_cell("DATA", row, &r.data)?;
+    Ok(())
+}
+
+fn open(dest_path: &Path, name: &str) -> Result<Table, TableError> {
+    Table::open(dest_path.join(name), TableOpenMode::ReadWrite)
+}
+
+/// Write the `SPECTRAL_WINDOW` sub-table, and a `DATA_DESCRIPTION` row for
+/// each window that pairs it with the sole polarization setup.
+pub(crate) fn write_spectral_windows(
+    dest_path: &Path,
+    spws: &[SpectralWindow],
+) -> Result<(), TableError> {
+    let mut spw_table = open(dest_path, "SPECTRAL_WINDOW")?;
+    let mut dd_table = open(dest_path, "DATA_DESCRIPTION")?;
+    spw_table.add_rows(spws.len())?;
+    dd_table.add_rows(spws.len())?;
+
+    for (i, spw) in spws.iter().enumerate() {
+        let row = i as u64;
+        spw.write(&mut spw_table, row)?;
+
+        dd_table.put_cell("SPECTRAL_WINDOW_ID", row, &(i as i32))?;
+        dd_table.put_cell("POLARIZATION_ID", row, &0i32)?;
+        dd_table.put_cell("FLAG_ROW", row, &false)?;
+    }
+
+    Ok(())
+}
+
+/// Write a single-row `POLARIZATION` sub-table. The polarizations are
+/// specified as `(corr_type, corr_product)` pairs, as returned by
+/// [`aips_pol_to_casa`].
+pub(crate) fn write_polarization(
+    dest_path: &Path,
+    pols: &[(i32, [i32; 2])],
+) -> Result<(), TableError> {
+    let corr_types: Vec<i32> = pols.iter().map(|p| p.0).collect();
+    let corr_products = Array2::from_shape_fn((pols.len(), 2), |(i, j)| pols[i].1[j]);
+
+    let mut pol_table = open(dest_path, "POLARIZATION")?;
+    pol_table.add_rows(1)?;
+    pol_table.put_cell("NUM_CORR", 0, &(pols.len() as i32))?;
+    pol_table.put_cell("CORR_TYPE", 0, &corr_types)?;
+    pol_table.put_cell("CORR_PRODUCT", 0, &corr_products)?;
+    pol_table.put_cell("FLAG_ROW", 0, &false)?;
+    Ok(())
+}
+
+/// Write the `FIELD` sub-table.
+pub(crate) fn write_fields(dest_path: &Path, fields: &[Field], t0: f64) -> Result<(), TableError> {
+    let mut field_table = open(dest_path, "FIELD")?;
+    field_table.add_rows(fields.len())?;
+
+    for (i, field) in fields.iter().enumerate() {
+        let row = i as u64;
+        let dir = Array2::from_shape_vec((1, 2), vec![field.ra, field.dec]).unwrap();
+
+        field_table.put_cell("NAME", row, &field.name)?;
+        field_table.put_cell("CODE", row, &String::new())?;
+        field_table.put_cell("TIME", row, &t0)?;
+        field_table.put_cell("NUM_POLY", row, &0i32)?;
+        field_table.put_cell("DELAY_DIR", row, &dir)?;
+        field_table.put_cell("PHASE_DIR", row, &dir)?;
+        field_table.put_cell("REFERENCE_DIR", row, &dir)?;
+        field_table.put_cell("SOURCE_ID", row, &-1i32)?;
+        field_table.put_cell("FLAG_ROW", row, &false)?;
+    }
+
+    Ok(())
+}
+
+/// Write the `ANTENNA` sub-table, and a `FEED` row for each antenna with two
+/// receptors that are either linear or circular.
+pub(crate) fn write_antennas(
+    dest_path: &Path,
+    antennas: &[Antenna],
+    linear: bool,
+    t0: f64,
+) -> Result<(), TableError> {
+    let receptors: &[&str] = if linear { &["X", "Y"] } else { &["R", "L"] };
+
+    let mut ant_table = open(dest_path, "ANTENNA")?;
+    let mut feed_table = open(dest_path, "FEED")?;
+    ant_table.add_rows(antennas.len())?;
+    feed_table.add_rows(antennas.len())?;
+
+    for (i, ant) in antennas.iter().enumerate() {
+        let row = i as u64;
+
+        ant_table.put_cell("NAME", row, &ant.name)?;
+        ant_table.put_cell("STATION", row, &ant.station)?;
+        ant_table.put_cell("TYPE", row, &"GROUND-BASED".to_owned())?;
+        ant_table.put_cell("MOUNT", row, &ant.mount)?;
+        ant_table.put_cell("POSITION", row, &ant.position.to_vec())?;
+        ant_table.put_cell("OFFSET", row, &vec![0f64; 3])?;
+        ant_table.put_cell("DISH_DIAMETER", row, &ant.diameter)?;
+        ant_table.put_cell("FLAG_ROW", row, &false)?;
+
+        let mut feed = Feed::ideal(i as i32, receptors);
+        feed.time = t0;
+        feed.write(&mut feed_table, row)?;
+    }
+
+    Ok(())
+}
+
+/// Write a single-row `OBSERVATION` sub-table.
+pub(crate) fn write_observation(
+    dest_path: &Path,
+    telescope: &str,
+    observer: &str,
+    project: &str,
+    time_range: (f64, f64),
+) -> Result<(), TableError> {
+    let mut obs_table = open(dest_path, "OBSERVATION")?;
+    obs_table.add_rows(1)?;
+    obs_table.put_cell("TELESCOPE_NAME", 0, &telescope.to_owned())?;
+    obs_table.put_cell("TIME_RANGE", 0, &vec![time_range.0, time_range.1])?;
+    obs_table.put_cell("OBSERVER", 0, &observer.to_owned())?;
+    obs_table.put_cell("SCHEDULE_TYPE", 0, &String::new())?;
+    obs_table.put_cell("PROJECT", 0, &project.to_owned())?;
+    obs_table.put_cell("RELEASE_DATE", 0, &0f64)?;
+    obs_table.put_cell("FLAG_ROW", 0, &false)?;
+    Ok(())
+}
+
+/// Map an AIPS polarization code, which is also used by MIRIAD and FITS-IDI,
+/// to a casacore Stokes type and the pair of receptors that produce it.
+pub(crate) fn aips_pol_to_casa(pol: i32) -> Option<(i32, [i32; 2])> {
+    let stokes = Stokes::from_aips_code(pol)?;
+    Some((stokes.code(), stokes.receptors().unwrap_or([0, 0])))
+}
+
+/// Whether an AIPS polarization code involves linear feeds.
+pub(crate) fn aips_pol_is_linear(pol: i32) -> bool {
+    Stokes::from_aips_code(pol).is_some_and(Stokes::is_linear)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polarization_codes() {
+        assert_eq!(aips_pol_to_casa(1), Some((1, [0, 0])));
+        assert_eq!(aips_pol_to_casa(-2), Some((8, [1, 1])));
+        assert_eq!(aips_pol_to_casa(-7), Some((10, [0, 1])));
+        assert_eq!(aips_pol_to_casa(0), None);
+        assert!(aips_pol_is_linear(-6));
+        assert!(!aips_pol_is_linear(-1));
+    }
+}
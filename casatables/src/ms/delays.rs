@@ -0,0 +1,345 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Applying per-antenna delay corrections to visibility data.
+//!
+//! A delay error on one antenna’s signal path — a cable of the wrong length,
+//! a misconfigured correlator model — shows up in the visibilities of its
+//! baselines as a phase that ramps linearly with frequency. [`apply_delays`]
+//! removes such ramps from the `DATA` column in place, a chunk of rows at a
+//! time, and notes the correction in the `HISTORY` sub-table.
+
+use std::{convert::TryFrom, f64::consts::PI, path::Path};
+use thiserror::Error;
+
+use super::{history::append_history, SpectralWindow};
+use crate::{
+    glue, io_stats::IoDirection, CasaDataType, Complex, IndexChunk, Table, TableError,
+    TableOpenMode,
+};
+
+/// The number of bytes of visibility data to process per chunk of rows.
+const CHUNK_BYTES: usize = 16 * 1024 * 1024;
+
+/// An error that can occur when applying delays.
+#[derive(Error, Debug)]
+pub enum DelayError {
+    /// An error occurred while reading or writing the tables.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The `DATA` column does not have two-dimensional cells.
+    #[error("DATA cells have shape {0:?}, but should have shape [n_chans, n_pols]")]
+    BadDataShape(Vec<usize>),
+
+    /// A row refers to an antenna for which no delay was given.
+    #[error(
+        "row {row} refers to antenna {antenna}, but delays were given for {n_delays} antennas"
+    )]
+    NoSuchAntenna {
+        /// The row number.
+        row: u64,
+
+        /// The antenna number.
+        antenna: i32,
+
+        /// The number of delays given.
+        n_delays: usize,
+    },
+
+    /// A row refers to a data description or spectral window that does not
+    /// exist.
+    #[error("row {0} has an invalid DATA_DESC_ID of {1}")]
+    BadDataDescription(u64, i32),
+
+    /// The `DATA` cells do not have one entry per channel of a row’s
+    /// spectral window.
+    #[error("DATA cells have shape {shape:?}, but row {row} has a spectral window of {n_chans} channels")]
+    ChannelMismatch {
+        /// The row number.
+        row: u64,
+
+        /// The shape of the `DATA` cells.
+        shape: Vec<usize>,
+
+        /// The number of channels in the row’s spectral window.
+        n_chans: usize,
+    },
+}
+
+/// Apply per-antenna delay corrections to the `DATA` column of a Measurement
+/// Set.
+///
+/// *delays* gives the delay of each antenna’s signal, in seconds, indexed by
+/// antenna number. A delay of τ on antenna *i* multiplies the visibilities
+/// of its baselines by exp(−2πiντ) when it is `ANTENNA1` and by exp(2πiντ)
+/// when it is `ANTENNA2`; this function undoes that, multiplying each
+/// visibility by
+///
+/// exp(2πiν(τ₁ − τ₂))
+///
+/// where ν is the frequency of the channel, taken from the spectral window
+/// of the row’s `DATA_DESC_ID`. Applying the negated delays reverses the
+/// correction. Autocorrelations are unchanged.
+///
+/// `DATA` must hold single-precision complex values and have a fixed shape
+/// of `[n_chans, n_pols]`. It is updated a chunk of rows at a time, so that
+/// data sets of any size can be processed. Once every row has been updated,
+/// a row describing the correction is added to the `HISTORY` sub-table.
+/// Returns the number of rows processed.
+///
+/// ```no_run
+/// use rubbl_casatables::{Table, TableOpenMode};
+/// use rubbl_casatables::ms::apply_delays;
+///
+/// let mut t = Table::open("vis.ms", TableOpenMode::ReadWrite).unwrap();
+/// // Antenna 3's cable is 2 m longer than recorded.
+/// let mut delays = vec![0.; 128];
+/// delays[3] = 2. / (0.7 * 299_792_458.);
+/// apply_delays(&mut t, &delays).unwrap();
+/// ```
+pub fn apply_delays(table: &mut Table, delays: &[f64]) -> Result<u64, DelayError> {
+    let ms_path = table.file_name().map_err(TableError::from)?;
+    let ms_path = Path::new(&ms_path);
+    let cell_shape = table.bulk_column_cell_shape::<Complex<f32>>("DATA")?;
+
+    if cell_shape.len() != 2 {
+        return Err(DelayError::BadDataShape(cell_shape));
+    }
+
+    let (n_chans, n_pols) = (cell_shape[0], cell_shape[1]);
+    let mut dd_table = Table::open(ms_path.join("DATA_DESCRIPTION"), TableOpenMode::Read)?;
+    let spw_ids: Vec<i32> = dd_table.get_col_as_vec("SPECTRAL_WINDOW_ID")?;
+    let mut spw_table = Table::open(ms_path.join("SPECTRAL_WINDOW"), TableOpenMode::Read)?;
+    let spws = SpectralWindow::read_all(&mut spw_table)?;
+    let dd_spws: Vec<Option<&SpectralWindow>> = spw_ids
+        .iter()
+        .map(|&id| usize::try_from(id).ok().and_then(|id| spws.get(id)))
+        .collect();
+
+    // Check every row before touching any data, so that a bad row cannot
+    // leave the data set half corrected.
+    let cell_len = n_chans * n_pols;
+    let rows_per_chunk = (CHUNK_BYTES / (cell_len.max(1) * 8)).max(1);
+    let mut problem = None;
+
+    table.for_each_index_row(rows_per_chunk, |irow| {
+        if problem.is_some() {
+            return;
+        }
+
+        for antenna in [irow.antenna1, irow.antenna2] {
+            if antenna < 0 || antenna as usize >= delays.len() {
+                problem = Some(DelayError::NoSuchAntenna {
+                    row: irow.row,
+                    antenna,
+                    n_delays: delays.len(),
+                });
+                return;
+            }
+        }
+
+        match usize::try_from(irow.data_desc_id)
+            .ok()
+            .and_then(|dd| dd_spws.get(dd).copied().flatten())
+        {
+            None => problem = Some(DelayError::BadDataDescription(irow.row, irow.data_desc_id)),
+
+            Some(spw) if spw.n_chans() != n_chans => {
+                problem = Some(DelayError::ChannelMismatch {
+                    row: irow.row,
+                    shape: cell_shape.clone(),
+                    n_chans: spw.n_chans(),
+                })
+            }
+
+            _ => {}
+        }
+    })?;
+
+    if let Some(e) = problem {
+        return Err(e);
+    }
+
+    // For each data description, the phasor exp(2πiντ) of each antenna in
+    // each channel.
+    let phasors: Vec<Vec<Complex<f64>>> = dd_spws
+        .iter()
+        .map(|spw| {
+            let freqs = spw.map(|s| &s.chan_freq[..]).unwrap_or(&[][..]);
+            delays
+                .iter()
+                .flat_map(|tau| {
+                    freqs
+                        .iter()
+                        .map(move |freq| Complex::from_polar(1., 2. * PI * freq * tau))
+                })
+                .collect()
+        })
+        .collect();
+
+    let data_name = glue::StringBridge::from_rust("DATA");
+    let mut index = IndexChunk::new(table, rows_per_chunk)?;
+    let mut buf = vec![Complex::<f32>::default(); rows_per_chunk * cell_len];
+    let n_rows = table.n_rows();
+    let mut row = 0;
+
+    while row < n_rows {
+        let n = (rows_per_chunk as u64).min(n_rows - row);
+        let data = &mut buf[..n as usize * cell_len];
+        index.read(table, row, n as usize)?;
+
+        if unsafe {
+            glue::table_get_column_range(
+                table.handle,
+                &data_name,
+                row,
+                n,
+                data.as_mut_ptr() as _,
+                &mut table.exc_info,
+            )
+        } != 0
+        {
+            return table.exc_info.as_err::<_, TableError>().map_err(Into::into);
+        }
+
+        table.record_io(
+            IoDirection::Get,
+            "DATA",
+            n,
+            Complex::<f32>::DATA_TYPE,
+            data.len() as u64,
+        );
+
+        for (i, cell) in data.chunks_mut(cell_len).enumerate() {
+            let irow = index.row(row, i);
+
+            if irow.antenna1 == irow.antenna2 {
+                continue;
+            }
+
+            let phasors = &phasors[irow.data_desc_id as usize];
+            let p1 = &phasors[irow.antenna1 as usize * n_chans..];
+            let p2 = &phasors[irow.antenna2 as usize * n_chans..];
+
+            for (c, chan) in cell.chunks_mut(n_pols).enumerate() {
+                let factor = p1[c] * p2[c].conj();
+                let factor = Complex::new(factor.re as f32, factor.im as f32);
+
+                for vis in chan {
+                    *vis *= factor;
+                }
+            }
+        }
+
+        if unsafe {
+            glue::table_put_column_range(
+                table.handle,
+                &data_name,
+                row,
+                n,
+                data.as_ptr() as _,
+                &mut table.exc_info,
+            )
+        } != 0
+        {
+            return table.exc_info.as_err::<_, TableError>().map_err(Into::into);
+        }
+
+        table.record_io(
+            IoDirection::Put,
+            "DATA",
+            n,
+            Complex::<f32>::DATA_TYPE,
+            data.len() as u64,
+        );
+        row += n;
+    }
+
+    let params: Vec<String> = delays
+        .iter()
+        .enumerate()
+        .filter(|(_, tau)| **tau != 0.)
+        .map(|(i, tau)| format!("delay[{}]={:e}", i, tau))
+        .collect();
+
+    append_history(
+        ms_path,
+        "rubbl_casatables::ms::apply_delays",
+        &format!(
+            "applied delay corrections to DATA for {} of {} antennas",
+            params.len(),
+            delays.len()
+        ),
+        &params,
+    )?;
+
+    Ok(n_rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn delays_round_trip() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("test.ms");
+        let spec = SyntheticMsSpec::default();
+        let mut t = synthetic_ms(&path, &spec).unwrap();
+
+        // A quarter turn of phase per channel on antenna 1.
+        let tau = 0.25 / spec.chan_width;
+        let delays = [0., tau, 0., 0.];
+        assert_eq!(apply_delays(&mut t, &delays).unwrap(), spec.n_rows() as u64);
+
+        for row in 0..spec.n_rows() as u64 {
+            let a1: i32 = t.get_cell("ANTENNA1", row).unwrap();
+            let a2: i32 = t.get_cell("ANTENNA2", row).unwrap();
+            let data: Vec<Complex<f32>> = t.get_cell_as_vec("DATA", row).unwrap();
+
+            for chan in 0..spec.n_chans {
+                let freq = spec.start_freq + chan as f64 * spec.chan_width;
+                let sign = (a1 == 1) as i32 - (a2 == 1) as i32;
+                let phase = 2. * PI * freq * tau * sign as f64;
+                let v = spec.expected_vis(row as usize, chan, 0);
+                let expected =
+                    Complex::new(v.re as f64, v.im as f64) * Complex::from_polar(1., phase);
+                let actual = data[chan * spec.n_pols];
+                let actual = Complex::new(actual.re as f64, actual.im as f64);
+                assert!((actual - expected).norm() < 1e-3 * (1. + expected.norm()));
+            }
+        }
+
+        let undo: Vec<f64> = delays.iter().map(|d| -d).collect();
+        apply_delays(&mut t, &undo).unwrap();
+
+        for row in 0..spec.n_rows() {
+            let data: Vec<Complex<f32>> = t.get_cell_as_vec("DATA", row as u64).unwrap();
+
+            for (i, vis) in data.iter().enumerate() {
+                let expected = spec.expected_vis(row, i / spec.n_pols, i % spec.n_pols);
+                assert!((vis - expected).norm() < 1e-3 * (1. + expected.norm()));
+            }
+        }
+
+        let mut history = Table::open(path.join("HISTORY"), TableOpenMode::Read).unwrap();
+        assert_eq!(history.n_rows(), 2);
+        let params: Vec<String> = history.get_cell_as_vec("APP_PARAMS", 0).unwrap();
+        assert_eq!(params, vec![format!("delay[1]={:e}", tau)]);
+
+        // Delays must be given for every antenna, and nothing is changed if
+        // they are not.
+        let before: Vec<Complex<f32>> = t.get_cell_as_vec("DATA", 1).unwrap();
+        assert!(matches!(
+            apply_delays(&mut t, &[0., 1e-6]),
+            Err(DelayError::NoSuchAntenna { antenna: 2, .. })
+        ));
+        let after: Vec<Complex<f32>> = t.get_cell_as_vec("DATA", 1).unwrap();
+        assert_eq!(before, after);
+        let history = Table::open(path.join("HISTORY"), TableOpenMode::Read).unwrap();
+        assert_eq!(history.n_rows(), 2);
+    }
+}
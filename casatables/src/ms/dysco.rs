@@ -0,0 +1,217 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Reading and writing visibility data compressed with the Dysco storage
+//! manager.
+//!
+//! [Dysco] compresses the `DATA` and `WEIGHT_SPECTRUM` columns of a
+//! Measurement Set by quantizing each value to a small number of bits, which
+//! typically shrinks the main table by a factor of four or more while keeping
+//! the added noise well below the thermal noise. CASA and the other casacore
+//! applications read Dysco-compressed data transparently, as long as the
+//! storage manager library is installed.
+//!
+//! [Dysco]: https://github.com/aroffringa/dysco
+//!
+//! The storage manager is not part of casacore: it lives in the separate
+//! `libdyscostman` library, which casacore loads at runtime when it
+//! encounters the `DyscoStMan` data manager type. This only works when this
+//! crate is built with the `system-casacore` feature, since the library is
+//! compiled against a standard casacore installation, and the library must
+//! be findable by the dynamic loader. Once it is, Dysco-compressed tables
+//! can be opened and read like any other; [`dysco_available`] checks whether
+//! this is the case.
+//!
+//! ```no_run
+//! use rubbl_casatables::{ms::dysco::DyscoOptions, Table, TableCreateMode, TableDesc, TableDescCreateMode};
+//!
+//! let desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+//! // ... add the DATA and WEIGHT_SPECTRUM columns with fixed shapes ...
+//! let dminfo = DyscoOptions::default()
+//!     .dminfo(&["DATA", "WEIGHT_SPECTRUM"])
+//!     .unwrap();
+//! let table = Table::new_with_dminfo("out.ms", desc, 0, TableCreateMode::New, &dminfo).unwrap();
+//! ```
+
+use crate::{CasacoreError, TableRecord};
+
+/// The name of the Dysco data manager type.
+pub const DYSCO_DATA_MANAGER: &str = "DyscoStMan";
+
+/// Check whether the Dysco storage manager can be loaded, which is needed
+/// both to read and to write Dysco-compressed data.
+///
+/// When it cannot, opening a Dysco-compressed table fails with
+/// [`crate::TableError::UnsupportedStorageManager`].
+pub fn dysco_available() -> Result<bool, CasacoreError> {
+    crate::data_manager_available(DYSCO_DATA_MANAGER)
+}
+
+/// The statistical distribution that Dysco assumes for the values it
+/// quantizes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DyscoDistribution {
+    /// A uniform distribution.
+    Uniform,
+
+    /// A Gaussian distribution.
+    Gaussian,
+
+    /// A Gaussian distribution truncated at
+    /// [`DyscoOptions::distribution_truncation`] standard deviations. This
+    /// is the recommended choice for visibilities.
+    TruncatedGaussian,
+
+    /// A Student’s t distribution with [`DyscoOptions::students_t_nu`]
+    /// degrees of freedom.
+    StudentsT,
+}
+
+impl DyscoDistribution {
+    /// Get the name used for this distribution in the storage manager
+    /// specification.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DyscoDistribution::Uniform => "Uniform",
+            DyscoDistribution::Gaussian => "Gaussian",
+            DyscoDistribution::TruncatedGaussian => "TruncatedGaussian",
+            DyscoDistribution::StudentsT => "StudentsT",
+        }
+    }
+}
+
+/// How Dysco normalizes the values in each block of data before quantizing
+/// them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DyscoNormalization {
+    /// Normalize per antenna and frequency. This is the recommended choice.
+    AF,
+
+    /// Normalize per row and frequency.
+    RF,
+
+    /// Normalize per row.
+    Row,
+}
+
+impl DyscoNormalization {
+    /// Get the name used for this normalization in the storage manager
+    /// specification.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DyscoNormalization::AF => "AF",
+            DyscoNormalization::RF => "RF",
+            DyscoNormalization::Row => "Row",
+        }
+    }
+}
+
+/// Options controlling Dysco compression.
+///
+/// The defaults are those recommended by the Dysco authors: 8 bits per data
+/// value, 12 bits per weight, a truncated Gaussian distribution with
+/// truncation at 2.5 standard deviations, and AF normalization.
+#[derive(Clone, Debug)]
+pub struct DyscoOptions {
+    /// The number of bits used to store each real or imaginary part of a
+    /// data value.
+    pub data_bit_count: u8,
+
+    /// The number of bits used to store each weight.
+    pub weight_bit_count: u8,
+
+    /// The assumed distribution of the data values.
+    pub distribution: DyscoDistribution,
+
+    /// The truncation point of a truncated Gaussian distribution, in
+    /// standard deviations.
+    pub distribution_truncation: f32,
+
+    /// The normalization applied to the data values.
+    pub normalization: DyscoNormalization,
+
+    /// The degrees of freedom of a Student’s t distribution.
+    pub students_t_nu: f32,
+
+    /// The name of the data manager group holding the compressed columns.
+    pub group_name: String,
+}
+
+impl Default for DyscoOptions {
+    fn default() -> Self {
+        DyscoOptions {
+            data_bit_count: 8,
+            weight_bit_count: 12,
+            distribution: DyscoDistribution::TruncatedGaussian,
+            distribution_truncation: 2.5,
+            normalization: DyscoNormalization::AF,
+            students_t_nu: 0.0,
+            group_name: "DyscoData".to_owned(),
+        }
+    }
+}
+
+impl DyscoOptions {
+    /// Build the storage manager specification record.
+    pub fn spec(&self) -> Result<TableRecord, CasacoreError> {
+        let mut spec = TableRecord::new().map_err(|e| CasacoreError(e.to_string()))?;
+        spec.put_field("dataBitCount", &(self.data_bit_count as i32))?;
+        spec.put_field("weightBitCount", &(self.weight_bit_count as i32))?;
+        spec.put_field("distribution", &self.distribution.name().to_owned())?;
+        spec.put_field("normalization", &self.normalization.name().to_owned())?;
+        spec.put_field("distributionTruncation", &self.distribution_truncation)?;
+        spec.put_field("studentTNu", &self.students_t_nu)?;
+        Ok(spec)
+    }
+
+    /// Build a data manager info record that stores the named columns with
+    /// Dysco, for use with [`crate::Table::new_with_dminfo`].
+    ///
+    /// Dysco can compress complex-valued data columns such as `DATA` and
+    /// float-valued weight columns such as `WEIGHT_SPECTRUM`. The columns
+    /// must have fixed shapes, and the table must have the standard
+    /// `ANTENNA1`, `ANTENNA2`, and `TIME` columns.
+    pub fn dminfo(&self, columns: &[&str]) -> Result<TableRecord, CasacoreError> {
+        let mut dm = TableRecord::new().map_err(|e| CasacoreError(e.to_string()))?;
+        dm.put_field("TYPE", &DYSCO_DATA_MANAGER.to_owned())?;
+        dm.put_field("NAME", &self.group_name)?;
+        dm.put_field("SPEC", &self.spec()?)?;
+        let columns: Vec<String> = columns.iter().map(|c| (*c).to_owned()).collect();
+        dm.put_field("COLUMNS", &columns)?;
+
+        let mut dminfo = TableRecord::new().map_err(|e| CasacoreError(e.to_string()))?;
+        dminfo.put_field("*1", &dm)?;
+        Ok(dminfo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dminfo_record() {
+        let opts = DyscoOptions {
+            data_bit_count: 10,
+            normalization: DyscoNormalization::Row,
+            ..Default::default()
+        };
+
+        let mut dminfo = opts.dminfo(&["DATA", "WEIGHT_SPECTRUM"]).unwrap();
+        let mut dm: TableRecord = dminfo.get_field("*1").unwrap();
+        let ty: String = dm.get_field("TYPE").unwrap();
+        assert_eq!(ty, "DyscoStMan");
+        let columns: Vec<String> = dm.get_field("COLUMNS").unwrap();
+        assert_eq!(columns, vec!["DATA", "WEIGHT_SPECTRUM"]);
+
+        let mut spec: TableRecord = dm.get_field("SPEC").unwrap();
+        let bits: i32 = spec.get_field("dataBitCount").unwrap();
+        assert_eq!(bits, 10);
+        let bits: i32 = spec.get_field("weightBitCount").unwrap();
+        assert_eq!(bits, 12);
+        let norm: String = spec.get_field("normalization").unwrap();
+        assert_eq!(norm, "Row");
+        let dist: String = spec.get_field("distribution").unwrap();
+        assert_eq!(dist, "TruncatedGaussian");
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! The receptors of a feed, as recorded in the `FEED` sub-table.
+
+use ndarray::Array2;
+
+use crate::{Complex, Table, TableError};
+
+/// The setup of one feed, as recorded in a row of the `FEED` sub-table of a
+/// Measurement Set.
+///
+/// A feed may be described by several rows, each valid for a range of times
+/// or for a particular spectral window; [`Feed::applies_to`] tells which one
+/// describes a given visibility. Array shapes are in Rust (C) order, so that
+/// `beam_offset` has one row per receptor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Feed {
+    /// The antenna to which the feed belongs.
+    pub antenna_id: i32,
+
+    /// The number of the feed on its antenna, as used in the `FEED1` and
+    /// `FEED2` columns of the main table.
+    pub feed_id: i32,
+
+    /// The spectral window to which this row applies, or -1 if it applies to
+    /// all of them.
+    pub spectral_window_id: i32,
+
+    /// The midpoint of the time range to which this row applies, in MJD
+    /// seconds.
+    pub time: f64,
+
+    /// The length of the time range to which this row applies, in seconds.
+    /// Zero or negative values mean that it applies at all times.
+    pub interval: f64,
+
+    /// The beam model of the feed, or -1 if there is none.
+    pub beam_id: i32,
+
+    /// The offset of each receptor’s beam from the pointing direction, in
+    /// radians, with shape `(n_receptors, 2)`.
+    pub beam_offset: Array2<f64>,
+
+    /// The polarization type of each receptor, such as `"X"` or `"R"`.
+    pub polarization_type: Vec<String>,
+
+    /// The response of each receptor to each polarization, with shape
+    /// `(n_receptors, n_receptors)`.
+    pub pol_response: Array2<Complex<f32>>,
+
+    /// The position of the feed relative to the antenna reference point, in
+    /// meters.
+    pub position: [f64; 3],
+
+    /// The angle of each receptor’s polarization, in radians.
+    pub receptor_angle: Vec<f64>,
+}
+
+impl Feed {
+    /// Create a feed with ideal receptors of the given polarization types.
+    ///
+    /// The feed has number 0, applies to all spectral windows and times, and
+    /// has no beam model. The receptors are on axis, respond only to their
+    /// own polarization, and have zero receptor angles.
+    ///
+    /// ```rust
+    /// use rubbl_casatables::ms::Feed;
+    ///
+    /// let feed = Feed::ideal(3, &["X", "Y"]);
+    /// assert_eq!(feed.n_receptors(), 2);
+    /// assert!(feed.applies_to(3, 0, 1, 5e9));
+    /// ```
+    pub fn ideal(antenna_id: i32, polarization_types: &[&str]) -> Self {
+        let n = polarization_types.len();
+
+        Feed {
+            antenna_id,
+            feed_id: 0,
+            spectral_window_id: -1,
+            time: 0.,
+            interval: 0.,
+            beam_id: -1,
+            beam_offset: Array2::zeros((n, 2)),
+            polarization_type: polarization_types.iter().map(|s| (*s).to_owned()).collect(),
+            pol_response: Array2::from_shape_fn((n, n), |(i, j)| {
+                Complex::new(if i == j { 1. } else { 0. }, 0.)
+            }),
+            position: [0.; 3],
+            receptor_angle: vec![0.; n],
+        }
+    }
+
+    /// Get the number of receptors.
+    pub fn n_receptors(&self) -> usize {
+        self.polarization_type.len()
+    }
+
+    /// Test whether this row describes feed *feed_id* of antenna
+    /// *antenna_id*, as used in spectral window *spw* at time *time*.
+    pub fn applies_to(&self, antenna_id: i32, feed_id: i32, spw: i32, time: f64) -> bool {
+        self.antenna_id == antenna_id
+            && self.feed_id == feed_id
+            && (self.spectral_window_id < 0 || self.spectral_window_id == spw)
+            && (self.interval <= 0. || (time - self.time).abs() <= 0.5 * self.interval)
+    }
+
+    /// Read a feed from a row of a `FEED` table.
+    pub fn read(table: &mut Table, row: u64) -> Result<Self, TableError> {
+        let position: Vec<f64> = table.get_cell_as_vec("POSITION", row)?;
+
+        if position.len() != 3 {
+            return Err(TableError::SliceOutOfBounds {
+                cell: vec![position.len()],
+                start: vec![0],
+                shape: vec![3],
+            });
+        }
+
+        Ok(Feed {
+            antenna_id: table.get_cell("ANTENNA_ID", row)?,
+            feed_id: table.get_cell("FEED_ID", row)?,
+            spectral_window_id: table.get_cell("SPECTRAL_WINDOW_ID", row)?,
+            time: table.get_cell("TIME", row)?,
+            interval: table.get_cell("INTERVAL", row)?,
+            beam_id: table.get_cell("BEAM_ID", row)?,
+            beam_offset: table.get_cell("BEAM_OFFSET", row)?,
+            polarization_type: table.get_cell("POLARIZATION_TYPE", row)?,
+            pol_response: table.get_cell("POL_RESPONSE", row)?,
+            position: [position[0], position[1], position[2]],
+            receptor_angle: table.get_cell_as_vec("RECEPTOR_ANGLE", row)?,
+        })
+    }
+
+    /// Read all of the feeds in a `FEED` table.
+    pub fn read_all(table: &mut Table) -> Result<Vec<Self>, TableError> {
+        (0..table.n_rows())
+            .map(|row| Self::read(table, row))
+            .collect()
+    }
+
+    /// Write this feed into an existing row of a `FEED` table.
+    ///
+    /// `NUM_RECEPTORS` is filled in from the length of
+    /// `polarization_type`.
+    pub fn write(&self, table: &mut Table, row: u64) -> Result<(), TableError> {
+        table.put_cell("ANTENNA_ID", row, &self.antenna_id)?;
+        table.put_cell("FEED_ID", row, &self.feed_id)?;
+        table.put_cell("SPECTRAL_WINDOW_ID", row, &self.spectral_window_id)?;
+        table.put_cell("TIME", row, &self.time)?;
+        table.put_cell("INTERVAL", row, &self.interval)?;
+        table.put_cell("NUM_RECEPTORS", row, &(self.n_receptors() as i32))?;
+        table.put_cell("BEAM_ID", row, &self.beam_id)?;
+        table.put_cell("BEAM_OFFSET", row, &self.beam_offset)?;
+        table.put_cell("POLARIZATION_TYPE", row, &self.polarization_type)?;
+        table.put_cell("POL_RESPONSE", row, &self.pol_response)?;
+        table.put_cell("POSITION", row, &self.position.to_vec())?;
+        table.put_cell("RECEPTOR_ANGLE", row, &self.receptor_angle)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use crate::TableOpenMode;
+    use tempfile::tempdir;
+
+    #[test]
+    fn table_round_trip() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("test.ms");
+        synthetic_ms(&path, &SyntheticMsSpec::default()).unwrap();
+
+        let mut t = Table::open(path.join("FEED"), TableOpenMode::ReadWrite).unwrap();
+        let n_rows = t.n_rows();
+        let mut feed = Feed::ideal(1, &["R", "L"]);
+        feed.spectral_window_id = 2;
+        feed.time = 1000.;
+        feed.interval = 100.;
+        feed.receptor_angle = vec![0.25, -0.25];
+        feed.beam_offset[[1, 0]] = 1e-3;
+        t.add_rows(1).unwrap();
+        feed.write(&mut t, n_rows).unwrap();
+
+        let feeds = Feed::read_all(&mut t).unwrap();
+        assert_eq!(feeds.len() as u64, n_rows + 1);
+        assert_eq!(feeds.last(), Some(&feed));
+
+        assert!(feed.applies_to(1, 0, 2, 1040.));
+        assert!(!feed.applies_to(1, 0, 2, 1060.));
+        assert!(!feed.applies_to(1, 0, 0, 1000.));
+        assert!(!feed.applies_to(0, 0, 2, 1000.));
+    }
+}
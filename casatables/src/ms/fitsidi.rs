@@ -0,0 +1,854 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Converting FITS-IDI files into Measurement Sets.
+//!
+//! FITS-IDI is the "Interferometry Data Interchange" convention used by most
+//! VLBI correlators. A file consists of a set of binary-table extensions,
+//! of which this module uses `ARRAY_GEOMETRY`, `FREQUENCY`, `SOURCE`, and
+//! one or more `UV_DATA` tables.
+
+use ndarray::{Array, Array1, Array2, Dimension, IntoDimension};
+use rubbl_core::{constants::SPEED_OF_LIGHT, time::SECONDS_PER_DAY};
+use rubbl_fits::{
+    bintable::BinTable,
+    header::{find_value, HeaderValue},
+    FitsError, FitsParser, HduKind,
+};
+use std::{collections::HashMap, fs::File, path::Path};
+use thiserror::Error;
+
+use super::convert::{
+    aips_pol_is_linear, aips_pol_to_casa, put_main_row, write_antennas, write_fields,
+    write_observation, write_polarization, write_spectral_windows, Antenna, Field, MainRow,
+    MJD_OFFSET,
+};
+use super::SpectralWindow;
+use crate::{CasacoreError, Complex, Table, TableError};
+
+/// An error that can occur when converting a FITS-IDI file.
+#[derive(Error, Debug)]
+pub enum FitsIdiConversionError {
+    /// The FITS-IDI file could not be read.
+    #[error(transparent)]
+    Fits(#[from] FitsError),
+
+    /// The Measurement Set could not be written.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The file lacks a table or column that the converter needs.
+    #[error("the FITS-IDI file has no {0}")]
+    Missing(String),
+
+    /// The file uses a feature that the converter can't handle.
+    #[error("{0}")]
+    Unsupported(String),
+}
+
+impl From<CasacoreError> for FitsIdiConversionError {
+    fn from(e: CasacoreError) -> Self {
+        FitsIdiConversionError::Table(e.into())
+    }
+}
+
+impl From<std::io::Error> for FitsIdiConversionError {
+    fn from(e: std::io::Error) -> Self {
+        FitsIdiConversionError::Fits(e.into())
+    }
+}
+
+/// Information about the result of a [`fitsidi_to_ms`] operation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FitsIdiConversionSummary {
+    /// The number of `UV_DATA` rows that were read.
+    pub n_records: u64,
+
+    /// The number of rows written to the main table.
+    pub n_rows: u64,
+
+    /// The number of antennas in the `ANTENNA` sub-table.
+    pub n_antennas: usize,
+
+    /// The number of fields in the `FIELD` sub-table.
+    pub n_fields: usize,
+
+    /// The number of spectral windows.
+    pub n_spws: usize,
+}
+
+/// Convert a FITS-IDI file into a new Measurement Set.
+///
+/// The FITS-IDI tables are mapped as follows:
+///
+/// - Each `ARRAY_GEOMETRY` row becomes an `ANTENNA` row, with `ANNAME` as
+///   the name and `STABXYZ` as the ITRF position. The antenna numbers in the
+///   `UV_DATA` tables are matched to the `NOSTA` column.
+/// - Each band of each `FREQUENCY` row becomes a spectral window, using the
+///   `REF_FREQ`, `NO_CHAN`, and `REF_PIXL` header keywords and the
+///   `BANDFREQ`, `CH_WIDTH`, and `SIDEBAND` columns.
+/// - Each `SOURCE` row with a distinct `SOURCE_ID` becomes a `FIELD` row,
+///   using the mean-epoch coordinates `RAEPO` and `DECEPO`.
+/// - Each band of each `UV_DATA` row becomes a main-table row. The
+///   polarizations are taken from the `STOKES` axis of the `FLUX` column.
+///   If that column's `COMPLEX` axis includes a weight, it fills in
+///   `WEIGHT`, and data with non-positive weights are flagged.
+///
+/// The `SCAN_NUMBER` is incremented whenever the source changes. Files
+/// containing more than one array, or whose `UV_DATA` tables have
+/// differing polarization setups, are not supported. The output path must
+/// not already exist.
+pub fn fitsidi_to_ms<P1: AsRef<Path>, P2: AsRef<Path>>(
+    src_path: P1,
+    dest_path: P2,
+) -> Result<FitsIdiConversionSummary, FitsIdiConversionError> {
+    let dest_path = dest_path.as_ref();
+    let mut parser = FitsParser::new(File::open(src_path.as_ref())?)?;
+    let mut tables: HashMap<String, Vec<BinTable>> = HashMap::new();
+
+    for i in 0..parser.hdus().len() {
+        if parser.hdus()[i].kind() == HduKind::BinaryTableExtension {
+            let table = BinTable::read(&mut parser, i)?;
+            tables
+                .entry(table.extname().to_owned())
+                .or_default()
+                .push(table);
+        }
+    }
+
+    let mut take = |name: &str| -> Result<Vec<BinTable>, FitsIdiConversionError> {
+        tables
+            .remove(name)
+            .ok_or_else(|| FitsIdiConversionError::Missing(format!("{name} table")))
+    };
+
+    let geometry = take("ARRAY_GEOMETRY")?;
+    let frequency = take("FREQUENCY")?;
+    let source = take("SOURCE")?;
+    let uv_data = take("UV_DATA")?;
+
+    if geometry.len() > 1 {
+        return Err(FitsIdiConversionError::Unsupported(
+            "FITS-IDI files with more than one array are not supported".to_owned(),
+        ));
+    }
+
+    let (antennas, ant_map) = read_array_geometry(&geometry[0])?;
+    let (spws, spw_map) = read_frequencies(&frequency)?;
+    let (fields, field_map) = read_sources(&source)?;
+    let mut main = Table::create_with_default_subtables(dest_path, 0)?;
+
+    let mut conv = Converter {
+        ant_map,
+        spw_map,
+        spw_n_chans: spws.iter().map(|w| w.n_chans()).collect(),
+        field_map,
+        ..Converter::default()
+    };
+
+    for table in &uv_data {
+        conv.convert_uv_data(&mut main, table)?;
+    }
+
+    let pols = conv.pols.clone().unwrap_or_default();
+    let casa_pols = pols
+        .iter()
+        .map(|&pol| {
+            aips_pol_to_casa(pol).ok_or_else(|| {
+                FitsIdiConversionError::Unsupported(format!(
+                    "unrecognized FITS-IDI polarization code {pol}"
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let (t0, t1) = conv.time_range.unwrap_or((0., 0.));
+    let linear = pols.iter().any(|p| aips_pol_is_linear(*p));
+
+    write_spectral_windows(dest_path, &spws)?;
+    write_polarization(dest_path, &casa_pols)?;
+    write_fields(dest_path, &fields, t0)?;
+    write_antennas(dest_path, &antennas, linear, t0)?;
+
+    let hdr = uv_data[0].headers();
+    let text = |kw: &str| {
+        find_value(hdr, kw)
+            .and_then(HeaderValue::as_str)
+            .unwrap_or("")
+            .to_owned()
+    };
+    write_observation(
+        dest_path,
+        &text("TELESCOP"),
+        &text("OBSERVER"),
+        "",
+        (t0, t1),
+    )?;
+
+    Ok(FitsIdiConversionSummary {
+        n_records: conv.n_records,
+        n_rows: conv.n_rows,
+        n_antennas: antennas.len(),
+        n_fields: fields.len(),
+        n_spws: spws.len(),
+    })
+}
+
+/// Read a numeric column into an array of shape *shape*, whose first axis
+/// indexes rows, returning an error if its cells have a different number of
+/// values.
+fn read_column<D: Dimension, S: IntoDimension<Dim = D>>(
+    table: &BinTable,
+    name: &str,
+    shape: S,
+) -> Result<Array<f64, D>, FitsIdiConversionError> {
+    let values = table.column_f64(name)?;
+    let cell_shape = values.shape()[1..].to_vec();
+
+    values.into_shape(shape).map_err(|_| {
+        FitsIdiConversionError::Unsupported(format!(
+            "the {} column of the {} table has cells of unexpected shape {:?}",
+            name,
+            table.extname(),
+            cell_shape
+        ))
+    })
+}
+
+/// Get the values of a numeric column with one value per row, trying a list
+/// of names that are used by different versions of the FITS-IDI convention.
+fn get_column(
+    table: &BinTable,
+    names: &[&str],
+) -> Result<Option<Array1<f64>>, FitsIdiConversionError> {
+    for name in names {
+        if table.column(name).is_some() {
+            return Ok(Some(read_column(table, name, table.n_rows())?));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Like [`get_column`], but for a column with *cell_len* values per row. The
+/// result has shape `(n_rows, cell_len)`.
+fn get_cells(
+    table: &BinTable,
+    names: &[&str],
+    cell_len: usize,
+) -> Result<Option<Array2<f64>>, FitsIdiConversionError> {
+    for name in names {
+        if table.column(name).is_some() {
+            return Ok(Some(read_column(table, name, (table.n_rows(), cell_len))?));
+        }
+    }
+
+    Ok(None)
+}
+
+fn missing_column(table: &BinTable, names: &[&str]) -> FitsIdiConversionError {
+    FitsIdiConversionError::Missing(format!(
+        "{} column in its {} table",
+        names[0],
+        table.extname()
+    ))
+}
+
+/// Like [`get_column`], but the column must exist.
+fn require_column(table: &BinTable, names: &[&str]) -> Result<Array1<f64>, FitsIdiConversionError> {
+    get_column(table, names)?.ok_or_else(|| missing_column(table, names))
+}
+
+/// Like [`get_cells`], but the column must exist.
+fn require_cells(
+    table: &BinTable,
+    names: &[&str],
+    cell_len: usize,
+) -> Result<Array2<f64>, FitsIdiConversionError> {
+    get_cells(table, names, cell_len)?.ok_or_else(|| missing_column(table, names))
+}
+
+fn header_f64(table: &BinTable, keyword: &str) -> Option<f64> {
+    find_value(table.headers(), keyword).and_then(HeaderValue::as_f64)
+}
+
+fn require_header(table: &BinTable, keyword: &str) -> Result<f64, FitsIdiConversionError> {
+    header_f64(table, keyword).ok_or_else(|| {
+        FitsIdiConversionError::Missing(format!(
+            "{} keyword in its {} table",
+            keyword,
+            table.extname()
+        ))
+    })
+}
+
+/// Read the antennas, returning them along with a map from FITS-IDI antenna
+/// numbers to indices into the list.
+fn read_array_geometry(
+    table: &BinTable,
+) -> Result<(Vec<Antenna>, HashMap<i64, usize>), FitsIdiConversionError> {
+    let names = table.string_column("ANNAME")?;
+    let xyz = require_cells(table, &["STABXYZ"], 3)?;
+    let nosta = require_column(table, &["NOSTA"])?;
+    let diameters = get_column(table, &["DIAMETER"])?;
+    let mounts = get_column(table, &["MNTSTA"])?;
+    let mut antennas = Vec::with_capacity(table.n_rows());
+    let mut ant_map = HashMap::new();
+
+    for i in 0..table.n_rows() {
+        // The MNTSTA codes are those of AIPS.
+        let mount = match mounts.as_ref().map_or(0, |m| m[i] as i32) {
+            1 => "EQUATORIAL",
+            2 => "ORBITING",
+            3 => "X-Y",
+            _ => "ALT-AZ",
+        };
+
+        ant_map.insert(nosta[i] as i64, i);
+        antennas.push(Antenna {
+            name: names[i].clone(),
+            station: names[i].clone(),
+            position: [xyz[[i, 0]], xyz[[i, 1]], xyz[[i, 2]]],
+            diameter: diameters.as_ref().map_or(0., |d| d[i]),
+            mount: mount.to_owned(),
+        });
+    }
+
+    Ok((antennas, ant_map))
+}
+
+/// Read the spectral windows, returning them along with a map from FITS-IDI
+/// `FREQID` values to the index of their first band in the list.
+fn read_frequencies(
+    tables: &[BinTable],
+) -> Result<(Vec<SpectralWindow>, HashMap<i64, usize>), FitsIdiConversionError> {
+    let mut spws = Vec::new();
+    let mut spw_map = HashMap::new();
+
+    for table in tables {
+        let ref_freq = require_header(table, "REF_FREQ")?;
+        let n_chans = require_header(table, "NO_CHAN")? as usize;
+        let ref_pixel = header_f64(table, "REF_PIXL").unwrap_or(1.);
+        let n_bands = header_f64(table, "NO_BAND").unwrap_or(1.) as usize;
+        let freq_ids = require_column(table, &["FREQID"])?;
+        let band_freqs = require_cells(table, &["BANDFREQ"], n_bands)?;
+        let chan_widths = require_cells(table, &["CH_WIDTH"], n_bands)?;
+        let sidebands = get_cells(table, &["SIDEBAND"], n_bands)?;
+
+        for i in 0..table.n_rows() {
+            let freq_id = freq_ids[i] as i64;
+
+            if spw_map.insert(freq_id, spws.len()).is_some() {
+                return Err(FitsIdiConversionError::Unsupported(format!(
+                    "FREQID {freq_id} is defined more than once"
+                )));
+            }
+
+            for b in 0..n_bands {
+                let sideband = sidebands.as_ref().map_or(1., |s| s[[i, b]]);
+                let chan_width = chan_widths[[i, b]] * sideband.signum();
+                let band_freq = ref_freq + band_freqs[[i, b]];
+
+                let mut spw = SpectralWindow::uniform(
+                    n_chans,
+                    band_freq + (1. - ref_pixel) * chan_width,
+                    chan_width,
+                );
+                spw.name = format!("WINDOW{}", spws.len() + 1);
+                spws.push(spw);
+            }
+        }
+    }
+
+    Ok((spws, spw_map))
+}
+
+/// Read the fields, returning them along with a map from FITS-IDI source IDs
+/// to indices into the list.
+fn read_sources(
+    tables: &[BinTable],
+) -> Result<(Vec<Field>, HashMap<i64, usize>), FitsIdiConversionError> {
+    let mut fields = Vec::new();
+    let mut field_map = HashMap::new();
+
+    for table in tables {
+        let ids = require_column(table, &["SOURCE_ID", "ID_NO."])?;
+        let names = table.string_column("SOURCE")?;
+        let ras = require_column(table, &["RAEPO"])?;
+        let decs = require_column(table, &["DECEPO"])?;
+
+        for i in 0..table.n_rows() {
+            // Sources may be listed once per FREQID.
+            field_map.entry(ids[i] as i64).or_insert_with(|| {
+                fields.push(Field {
+                    name: names[i].clone(),
+                    ra: ras[i].to_radians(),
+                    dec: decs[i].to_radians(),
+                });
+                fields.len() - 1
+            });
+        }
+    }
+
+    Ok((fields, field_map))
+}
+
+/// The layout of the `FLUX` column of a `UV_DATA` table, which is described
+/// by the `MAXISn`, `CTYPEn`, `CRVALn`, `CDELTn`, and `CRPIXn` keywords.
+#[derive(Clone, Debug, Default)]
+struct DataLayout {
+    n_values: usize,
+    n_complex: usize,
+    n_stokes: usize,
+    n_chans: usize,
+    n_bands: usize,
+    complex_stride: usize,
+    stokes_stride: usize,
+    chan_stride: usize,
+    band_stride: usize,
+    pols: Vec<i32>,
+}
+
+impl DataLayout {
+    fn new(table: &BinTable) -> Result<Self, FitsIdiConversionError> {
+        let n_axes = require_header(table, "MAXIS")? as usize;
+        let mut layout = DataLayout::default();
+        let mut stride = 1;
+
+        for n in 1..=n_axes {
+            let size = require_header(table, &format!("MAXIS{n}"))? as usize;
+            let ctype = find_value(table.headers(), &format!("CTYPE{n}"))
+                .and_then(HeaderValue::as_str)
+                .unwrap_or("");
+
+            match ctype {
+                "COMPLEX" => {
+                    layout.n_complex = size;
+                    layout.complex_stride = stride;
+                }
+
+                "STOKES" => {
+                    let crval = header_f64(table, &format!("CRVAL{n}")).unwrap_or(1.);
+                    let cdelt = header_f64(table, &format!("CDELT{n}")).unwrap_or(1.);
+                    let crpix = header_f64(table, &format!("CRPIX{n}")).unwrap_or(1.);
+                    layout.n_stokes = size;
+                    layout.stokes_stride = stride;
+                    layout.pols = (0..size)
+                        .map(|i| (crval + (i as f64 + 1. - crpix) * cdelt).round() as i32)
+                        .collect();
+                }
+
+                "FREQ" => {
+                    layout.n_chans = size;
+                    layout.chan_stride = stride;
+                }
+
+                "BAND" | "IF" => {
+                    layout.n_bands = size;
+                    layout.band_stride = stride;
+                }
+
+                _ if size == 1 => {}
+
+                other => {
+                    return Err(FitsIdiConversionError::Unsupported(format!(
+                        "unexpected FITS-IDI data axis {other:?} of size {size}"
+                    )));
+                }
+            }
+
+            stride *= size;
+        }
+
+        if layout.n_complex < 2 || layout.n_stokes == 0 || layout.n_chans == 0 {
+            return Err(FitsIdiConversionError::Missing(
+                "COMPLEX, STOKES, or FREQ data axis in its UV_DATA table".to_owned(),
+            ));
+        }
+
+        if layout.n_bands == 0 {
+            layout.n_bands = 1;
+        }
+
+        layout.n_values = stride;
+        Ok(layout)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Converter {
+    n_records: u64,
+    n_rows: u64,
+    ant_map: HashMap<i64, usize>,
+    spw_map: HashMap<i64, usize>,
+    spw_n_chans: Vec<usize>,
+    field_map: HashMap<i64, usize>,
+    pols: Option<Vec<i32>>,
+    prev_field_id: Option<usize>,
+    scan_number: i32,
+    time_range: Option<(f64, f64)>,
+}
+
+impl Converter {
+    fn convert_uv_data(
+        &mut self,
+        main: &mut Table,
+        table: &BinTable,
+    ) -> Result<(), FitsIdiConversionError> {
+        let layout = DataLayout::new(table)?;
+
+        match self.pols {
+            None => self.pols = Some(layout.pols.clone()),
+
+            Some(ref pols) => {
+                if *pols != layout.pols {
+                    return Err(FitsIdiConversionError::Unsupported(format!(
+                        "UV_DATA tables have differing polarizations {:?} and {:?}",
+                        pols, layout.pols
+                    )));
+                }
+            }
+        }
+
+        let uu = require_column(table, &["UU", "UU--SIN", "UU---SIN"])?;
+        let vv = require_column(table, &["VV", "VV--SIN", "VV---SIN"])?;
+        let ww = require_column(table, &["WW", "WW--SIN", "WW---SIN"])?;
+        let dates = require_column(table, &["DATE"])?;
+        let times = require_column(table, &["TIME"])?;
+        let baselines = get_column(table, &["BASELINE"])?;
+        let ant1s = get_column(table, &["ANTENNA1"])?;
+        let ant2s = get_column(table, &["ANTENNA2"])?;
+        let sources = get_column(table, &["SOURCE_ID", "SOURCE"])?;
+        let freq_ids = get_column(table, &["FREQID"])?;
+        let inttims = get_column(table, &["INTTIM"])?;
+
+        let n_pols = layout.n_stokes;
+        let n_chans = layout.n_chans;
+
+        for i in 0..table.n_rows() {
+            self.n_records += 1;
+
+            let (a1, a2) = match (&baselines, &ant1s, &ant2s) {
+                (_, Some(a1), Some(a2)) => (a1[i] as i64, a2[i] as i64),
+                (Some(bl), _, _) => decode_baseline(bl[i] as i64),
+                _ => {
+                    return Err(FitsIdiConversionError::Missing(
+                        "BASELINE column in its UV_DATA table".to_owned(),
+                    ))
+                }
+            };
+
+            let lookup = |map: &HashMap<i64, usize>, what: &str, value: i64| {
+                map.get(&value).copied().ok_or_else(|| {
+                    FitsIdiConversionError::Unsupported(format!(
+                        "UV_DATA row {} refers to undefined {} {}",
+                        i + 1,
+                        what,
+                        value
+                    ))
+                })
+            };
+
+            let ant1 = lookup(&self.ant_map, "antenna", a1)?;
+            let ant2 = lookup(&self.ant_map, "antenna", a2)?;
+            let field_id = lookup(
+                &self.field_map,
+                "source",
+                sources.as_ref().map_or(1, |s| s[i] as i64),
+            )?;
+            let spw0 = lookup(
+                &self.spw_map,
+                "FREQID",
+                freq_ids.as_ref().map_or(1, |f| f[i] as i64),
+            )?;
+
+            for band in 0..layout.n_bands {
+                if self.spw_n_chans.get(spw0 + band) != Some(&n_chans) {
+                    return Err(FitsIdiConversionError::Unsupported(format!(
+                        "UV_DATA row {} has {} bands of {} channels, which do not match \
+                         its FREQID's entry in the FREQUENCY table",
+                        i + 1,
+                        layout.n_bands,
+                        n_chans
+                    )));
+                }
+            }
+
+            if self.prev_field_id != Some(field_id) {
+                self.scan_number += 1;
+                self.prev_field_id = Some(field_id);
+            }
+
+            let time = (dates[i] + times[i] - MJD_OFFSET) * SECONDS_PER_DAY;
+
+            self.time_range = Some(match self.time_range {
+                None => (time, time),
+                Some((t0, t1)) => (t0.min(time), t1.max(time)),
+            });
+
+            let uvw = [
+                uu[i] * SPEED_OF_LIGHT,
+                vv[i] * SPEED_OF_LIGHT,
+                ww[i] * SPEED_OF_LIGHT,
+            ];
+            let interval = inttims.as_ref().map_or(0., |t| t[i]);
+
+            let flux = table.row(i).get_array::<f32>("FLUX")?;
+
+            if flux.len() != layout.n_values {
+                return Err(FitsIdiConversionError::Unsupported(format!(
+                    "the FLUX column has {} values per row, but the data axes describe {}",
+                    flux.len(),
+                    layout.n_values
+                )));
+            }
+
+            let flux: Vec<f32> = flux.iter().copied().collect();
+            main.add_rows(layout.n_bands)?;
+
+            for band in 0..layout.n_bands {
+                let index = |c: usize, p: usize, k: usize| {
+                    band * layout.band_stride
+                        + c * layout.chan_stride
+                        + p * layout.stokes_stride
+                        + k * layout.complex_stride
+                };
+
+                let weights = Array2::from_shape_fn((n_chans, n_pols), |(c, p)| {
+                    if layout.n_complex > 2 {
+                        flux[index(c, p, 2)]
+                    } else {
+                        1.
+                    }
+                });
+
+                let weight = (0..n_pols)
+                    .map(|p| {
+                        let good: Vec<f32> = weights
+                            .column(p)
+                            .iter()
+                            .copied()
+                            .filter(|w| *w > 0.)
+                            .collect();
+
+                        if good.is_empty() {
+                            0.
+                        } else {
+                            good.iter().sum::<f32>() / good.len() as f32
+                        }
+                    })
+                    .collect();
+
+                let row = MainRow {
+                    time,
+                    interval,
+                    ant1,
+                    ant2,
+                    data_desc_id: spw0 + band,
+                    field_id,
+                    scan_number: self.scan_number,
+                    uvw,
+                    weight,
+                    data: Array2::from_shape_fn((n_chans, n_pols), |(c, p)| {
+                        Complex::new(flux[index(c, p, 0)], flux[index(c, p, 1)])
+                    }),
+                    flags: weights.mapv(|w| w.is_nan() || w <= 0.),
+                };
+
+                put_main_row(main, self.n_rows, &row)?;
+                self.n_rows += 1;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Decode an AIPS-style baseline number into a pair of antenna numbers.
+///
+/// Values above 65536 use the convention for arrays with more than 255
+/// antennas.
+fn decode_baseline(bl: i64) -> (i64, i64) {
+    if bl > 65_536 {
+        let bl = bl - 65_536;
+        (bl / 2048, bl % 2048)
+    } else {
+        (bl / 256, bl % 256)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TableOpenMode;
+    use ndarray::array;
+    use rubbl_fits::{
+        bintable::BinTableBuilder,
+        header::{empty_primary_header, write_header, HeaderCard},
+    };
+    use tempfile::tempdir;
+
+    /// Write a minimal FITS-IDI file: two antennas, one source, and one
+    /// band of two channels, observed in RR and LL at two times. The
+    /// antenna positions are given with *n_xyz* values each.
+    fn write_fitsidi(path: &Path, n_xyz: usize) {
+        let mut file = Vec::new();
+        write_header(&mut file, &empty_primary_header()).unwrap();
+
+        let int = |k: &str, v: i64| HeaderCard::new(k, HeaderValue::Integer(v));
+        let float = |k: &str, v: f64| HeaderCard::new(k, HeaderValue::Float(v));
+        let string = |k: &str, v: &str| HeaderCard::new(k, HeaderValue::String(v.to_owned()));
+
+        let xyz = Array2::from_shape_fn((2, n_xyz), |(i, j)| (1000 * i + j) as f64);
+        BinTableBuilder::new("ARRAY_GEOMETRY")
+            .add_string_column("ANNAME", &["AA", "BB"])
+            .unwrap()
+            .add_column("STABXYZ", xyz.view())
+            .unwrap()
+            .add_column("NOSTA", array![1i32, 2].view())
+            .unwrap()
+            .add_column("MNTSTA", array![0i32, 0].view())
+            .unwrap()
+            .write(&mut file)
+            .unwrap();
+
+        BinTableBuilder::new("FREQUENCY")
+            .add_column("FREQID", array![1i32].view())
+            .unwrap()
+            .add_column("BANDFREQ", array![0f64].view())
+            .unwrap()
+            .add_column("CH_WIDTH", array![1e6f32].view())
+            .unwrap()
+            .add_column("SIDEBAND", array![1i32].view())
+            .unwrap()
+            .add_header(float("REF_FREQ", 5e9))
+            .add_header(int("NO_CHAN", 2))
+            .add_header(float("REF_PIXL", 1.))
+            .add_header(int("NO_BAND", 1))
+            .write(&mut file)
+            .unwrap();
+
+        BinTableBuilder::new("SOURCE")
+            .add_column("SOURCE_ID", array![1i32].view())
+            .unwrap()
+            .add_string_column("SOURCE", &["3C84"])
+            .unwrap()
+            .add_column("RAEPO", array![49.95f64].view())
+            .unwrap()
+            .add_column("DECEPO", array![41.5f64].view())
+            .unwrap()
+            .write(&mut file)
+            .unwrap();
+
+        // The FLUX axes are, from slowest to fastest varying: band,
+        // channel, Stokes, and complex (real, imaginary, weight).
+        let flux = ndarray::Array5::from_shape_fn((2, 1, 2, 2, 3), |(r, _, c, p, k)| {
+            if k == 2 {
+                1.
+            } else {
+                (100 * r + 10 * c + 2 * p + k) as f32
+            }
+        });
+
+        BinTableBuilder::new("UV_DATA")
+            .add_column("UU", array![1e-6f64, 2e-6].view())
+            .unwrap()
+            .add_column("VV", array![0f64, 0.].view())
+            .unwrap()
+            .add_column("WW", array![0f64, 0.].view())
+            .unwrap()
+            .add_column("DATE", array![2_460_000.5f64, 2_460_000.5].view())
+            .unwrap()
+            .add_column("TIME", array![0f64, 0.5].view())
+            .unwrap()
+            .add_column("BASELINE", array![258i32, 258].view())
+            .unwrap()
+            .add_column("SOURCE_ID", array![1i32, 1].view())
+            .unwrap()
+            .add_column("FREQID", array![1i32, 1].view())
+            .unwrap()
+            .add_column("INTTIM", array![2f32, 2.].view())
+            .unwrap()
+            .add_column("FLUX", flux.view())
+            .unwrap()
+            .add_header(string("TELESCOP", "VLBA"))
+            .add_header(int("MAXIS", 4))
+            .add_header(int("MAXIS1", 3))
+            .add_header(string("CTYPE1", "COMPLEX"))
+            .add_header(int("MAXIS2", 2))
+            .add_header(string("CTYPE2", "STOKES"))
+            .add_header(float("CRVAL2", -1.))
+            .add_header(float("CDELT2", -1.))
+            .add_header(float("CRPIX2", 1.))
+            .add_header(int("MAXIS3", 2))
+            .add_header(string("CTYPE3", "FREQ"))
+            .add_header(int("MAXIS4", 1))
+            .add_header(string("CTYPE4", "BAND"))
+            .write(&mut file)
+            .unwrap();
+
+        std::fs::write(path, file).unwrap();
+    }
+
+    #[test]
+    fn convert() {
+        let tmp_dir = tempdir().unwrap();
+        let idi_path = tmp_dir.path().join("vis.idifits");
+        let ms_path = tmp_dir.path().join("vis.ms");
+        write_fitsidi(&idi_path, 3);
+
+        let summary = fitsidi_to_ms(&idi_path, &ms_path).unwrap();
+        assert_eq!(
+            summary,
+            FitsIdiConversionSummary {
+                n_records: 2,
+                n_rows: 2,
+                n_antennas: 2,
+                n_fields: 1,
+                n_spws: 1,
+            }
+        );
+
+        let mut main = Table::open(&ms_path, TableOpenMode::Read).unwrap();
+        let times: Vec<f64> = main.get_col_as_vec("TIME").unwrap();
+        assert_eq!(times, vec![60000. * 86400., 60000.5 * 86400.]);
+
+        let uvw: Vec<f64> = main.get_cell("UVW", 1).unwrap();
+        assert!((uvw[0] - 2e-6 * SPEED_OF_LIGHT).abs() < 1e-9);
+
+        let data: Array2<Complex<f32>> = main.get_cell("DATA", 1).unwrap();
+        assert_eq!(
+            data,
+            array![
+                [Complex::new(100., 101.), Complex::new(102., 103.)],
+                [Complex::new(110., 111.), Complex::new(112., 113.)]
+            ]
+        );
+
+        let mut pol = Table::open(ms_path.join("POLARIZATION"), TableOpenMode::Read).unwrap();
+        let corr_type: Vec<i32> = pol.get_cell("CORR_TYPE", 0).unwrap();
+        assert_eq!(corr_type, vec![5, 8]);
+
+        let mut ants = Table::open(ms_path.join("ANTENNA"), TableOpenMode::Read).unwrap();
+        let position: Vec<f64> = ants.get_cell("POSITION", 1).unwrap();
+        assert_eq!(position, vec![1000., 1001., 1002.]);
+    }
+
+    #[test]
+    fn bad_positions() {
+        let tmp_dir = tempdir().unwrap();
+        let idi_path = tmp_dir.path().join("vis.idifits");
+        write_fitsidi(&idi_path, 2);
+
+        assert!(matches!(
+            fitsidi_to_ms(&idi_path, tmp_dir.path().join("vis.ms")),
+            Err(FitsIdiConversionError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn baseline_decoding() {
+        assert_eq!(decode_baseline(258), (1, 2));
+        assert_eq!(decode_baseline(256 * 12 + 40), (12, 40));
+        assert_eq!(decode_baseline(65_536 + 2048 * 300 + 301), (300, 301));
+    }
+}
@@ -0,0 +1,326 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Running RFI flagging algorithms over Measurement Sets.
+//!
+//! Most RFI flaggers work on one baseline at a time, looking at its
+//! visibilities as a time–frequency plane. [`FlaggerBackend`] is the
+//! interface to such an algorithm, and [`run_flagger`] is the driver that
+//! feeds it: it gathers the rows of each baseline, reads their data and
+//! existing flags, hands them to the backend, and writes the results back to
+//! the `FLAG` and `FLAG_ROW` columns. A backend wrapping an external library
+//! only needs to convert between its own buffers and the arrays passed here;
+//! `AoFlagger`, available with the `aoflagger` feature, does this for
+//! AOFlagger.
+
+use ndarray::{Array3, ArrayView3};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+use crate::{Complex, Table, TableError};
+
+/// The number of rows of index columns to read at a time.
+const INDEX_CHUNK_ROWS: usize = 65536;
+
+/// A flagging algorithm that can be run by [`run_flagger`].
+pub trait FlaggerBackend {
+    /// The error type returned when flagging fails.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Compute flags for a block of data from one baseline.
+    ///
+    /// *data* and *flags* have shape `[n_times, n_chans, n_pols]` and hold
+    /// the visibilities and existing flags of consecutive timesteps of the
+    /// baseline between *antennas* in spectral window setup
+    /// *data_desc_id*. The returned array must have the same shape and holds
+    /// the new flags.
+    fn flag_baseline(
+        &mut self,
+        antennas: (i32, i32),
+        data_desc_id: i32,
+        data: ArrayView3<Complex<f32>>,
+        flags: ArrayView3<bool>,
+    ) -> Result<Array3<bool>, Self::Error>;
+}
+
+/// An error that can occur when running a flagger.
+#[derive(Error, Debug)]
+pub enum FlaggingError<E: std::error::Error + Send + Sync + 'static> {
+    /// An error occurred while reading or writing the table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The backend failed.
+    #[error("flagging backend failed on baseline {0:?}")]
+    Backend((i32, i32), #[source] E),
+
+    /// A row has a `DATA` or `FLAG` cell whose size differs from that of the
+    /// other rows of its baseline.
+    #[error("row {row} has a cell with {actual} elements, but {expected} were expected")]
+    BadCellShape {
+        /// The row of the main table.
+        row: u64,
+        /// The expected number of elements.
+        expected: usize,
+        /// The actual number of elements.
+        actual: usize,
+    },
+
+    /// The backend returned flags of the wrong shape.
+    #[error("flagging backend returned flags of shape {actual:?} but {expected:?} was expected")]
+    BadFlagShape {
+        /// The shape of the input block.
+        expected: Vec<usize>,
+        /// The shape of the returned flags.
+        actual: Vec<usize>,
+    },
+}
+
+impl<E: std::error::Error + Send + Sync + 'static> From<crate::CasacoreError> for FlaggingError<E> {
+    fn from(e: crate::CasacoreError) -> Self {
+        FlaggingError::Table(e.into())
+    }
+}
+
+/// Options for [`run_flagger`].
+#[derive(Clone, Debug)]
+pub struct FlaggingOptions {
+    /// The column from which to read visibilities. The default is `DATA`.
+    pub data_column: String,
+
+    /// The maximum number of timesteps to pass to the backend at once. If
+    /// `None`, the default, each baseline is processed in one go, which is
+    /// what most algorithms prefer but may use a lot of memory for long
+    /// observations.
+    pub time_chunk: Option<usize>,
+
+    /// If true, the default, new flags are combined with the existing ones,
+    /// so that the backend can only add flags. If false, the `FLAG` column is
+    /// overwritten with whatever the backend returns.
+    pub keep_existing: bool,
+}
+
+impl Default for FlaggingOptions {
+    fn default() -> Self {
+        FlaggingOptions {
+            data_column: "DATA".to_owned(),
+            time_chunk: None,
+            keep_existing: true,
+        }
+    }
+}
+
+/// A summary of a run of [`run_flagger`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FlaggingSummary {
+    /// The number of blocks passed to the backend.
+    pub n_blocks: usize,
+
+    /// The number of samples examined.
+    pub n_samples: u64,
+
+    /// The number of samples that were flagged beforehand.
+    pub n_flagged_before: u64,
+
+    /// The number of samples that are flagged afterwards.
+    pub n_flagged_after: u64,
+}
+
+/// Run a flagging algorithm over every baseline of a Measurement Set.
+///
+/// The rows of the main table are grouped by `ANTENNA1`, `ANTENNA2`, and
+/// `DATA_DESC_ID`, and each group is processed in time order, in blocks of
+/// at most [`FlaggingOptions::time_chunk`] rows. For each block, the
+/// visibilities and flags are read, passed to *backend*, and the resulting
+/// flags are written back to the `FLAG` column. All rows of a group must
+/// have the same cell shape. The table must be open for writing.
+///
+/// If the table has a `FLAG_ROW` column, a row that it marks is passed to the
+/// backend with all of its samples flagged, and afterwards it is set for
+/// exactly those rows whose samples are all flagged.
+pub fn run_flagger<B: FlaggerBackend>(
+    table: &mut Table,
+    backend: &mut B,
+    options: &FlaggingOptions,
+) -> Result<FlaggingSummary, FlaggingError<B::Error>> {
+    let mut groups: BTreeMap<(i32, i32, i32), Vec<(f64, u64)>> = BTreeMap::new();
+
+    table.for_each_index_row(INDEX_CHUNK_ROWS, |row| {
+        groups
+            .entry((row.antenna1, row.antenna2, row.data_desc_id))
+            .or_default()
+            .push((row.time, row.row));
+    })?;
+
+    let has_flag_row = table.column_names()?.iter().any(|c| c == "FLAG_ROW");
+    let mut summary = FlaggingSummary::default();
+
+    for ((ant1, ant2, ddid), mut rows) in groups {
+        rows.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let cell_shape = table.get_cell_shape(&options.data_column, rows[0].1)?;
+        let n_chans = cell_shape.first().copied().unwrap_or(1);
+        let n_pols: usize = cell_shape.iter().skip(1).product();
+        let cell_len = n_chans * n_pols;
+        let block_rows = options.time_chunk.unwrap_or(rows.len()).max(1);
+
+        for block in rows.chunks(block_rows) {
+            let n = block.len();
+            let mut data = Vec::with_capacity(n * cell_len);
+            let mut flags = Vec::with_capacity(n * cell_len);
+
+            for (_, row) in block {
+                let row_data = table.get_cell_as_vec::<Complex<f32>>(&options.data_column, *row)?;
+                let mut row_flags = table.get_cell_as_vec::<bool>("FLAG", *row)?;
+
+                if has_flag_row && table.get_cell::<bool>("FLAG_ROW", *row)? {
+                    row_flags.iter_mut().for_each(|f| *f = true);
+                }
+
+                for &len in &[row_data.len(), row_flags.len()] {
+                    if len != cell_len {
+                        return Err(FlaggingError::BadCellShape {
+                            row: *row,
+                            expected: cell_len,
+                            actual: len,
+                        });
+                    }
+                }
+
+                data.extend(row_data);
+                flags.extend(row_flags);
+            }
+
+            let data = Array3::from_shape_vec((n, n_chans, n_pols), data).unwrap();
+            let flags = Array3::from_shape_vec((n, n_chans, n_pols), flags).unwrap();
+
+            let mut new_flags = backend
+                .flag_baseline((ant1, ant2), ddid, data.view(), flags.view())
+                .map_err(|e| FlaggingError::Backend((ant1, ant2), e))?;
+
+            if new_flags.shape() != flags.shape() {
+                return Err(FlaggingError::BadFlagShape {
+                    expected: flags.shape().to_vec(),
+                    actual: new_flags.shape().to_vec(),
+                });
+            }
+
+            if options.keep_existing {
+                new_flags.zip_mut_with(&flags, |new, old| *new |= *old);
+            }
+
+            summary.n_blocks += 1;
+            summary.n_samples += flags.len() as u64;
+            summary.n_flagged_before += flags.iter().filter(|f| **f).count() as u64;
+            summary.n_flagged_after += new_flags.iter().filter(|f| **f).count() as u64;
+
+            for (i, (_, row)) in block.iter().enumerate() {
+                let cell = new_flags.slice(ndarray::s![i, .., ..]).to_owned();
+                table.put_cell("FLAG", *row, &cell)?;
+
+                if has_flag_row {
+                    table.put_cell("FLAG_ROW", *row, &cell.iter().all(|f| *f))?;
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use std::convert::Infallible;
+    use tempfile::tempdir;
+
+    /// Flags every sample whose imaginary part exceeds a threshold, and
+    /// counts the blocks it sees.
+    struct Threshold(f32, usize);
+
+    impl FlaggerBackend for Threshold {
+        type Error = Infallible;
+
+        fn flag_baseline(
+            &mut self,
+            _antennas: (i32, i32),
+            _data_desc_id: i32,
+            data: ArrayView3<Complex<f32>>,
+            _flags: ArrayView3<bool>,
+        ) -> Result<Array3<bool>, Infallible> {
+            self.1 += 1;
+            Ok(data.mapv(|v| v.im > self.0))
+        }
+    }
+
+    /// Flags every sample whose real part is below a threshold.
+    struct BelowReal(f32);
+
+    impl FlaggerBackend for BelowReal {
+        type Error = Infallible;
+
+        fn flag_baseline(
+            &mut self,
+            _antennas: (i32, i32),
+            _data_desc_id: i32,
+            data: ArrayView3<Complex<f32>>,
+            _flags: ArrayView3<bool>,
+        ) -> Result<Array3<bool>, Infallible> {
+            Ok(data.mapv(|v| v.re < self.0))
+        }
+    }
+
+    #[test]
+    fn threshold_backend() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec::default();
+        let mut t = synthetic_ms(tmp_dir.path().join("test.ms"), &spec).unwrap();
+
+        // The imaginary part of the synthetic data is `chan * n_pols + pol`,
+        // so this flags the last 8 samples of each cell.
+        let threshold = (spec.n_chans * spec.n_pols - 9) as f32;
+        let mut backend = Threshold(threshold, 0);
+        let options = FlaggingOptions {
+            time_chunk: Some(2),
+            ..Default::default()
+        };
+        let summary = run_flagger(&mut t, &mut backend, &options).unwrap();
+
+        // Two blocks per baseline for three timesteps.
+        assert_eq!(summary.n_blocks, 2 * spec.n_baselines());
+        assert_eq!(backend.1, summary.n_blocks);
+        assert_eq!(
+            summary.n_samples,
+            (spec.n_rows() * spec.n_chans * spec.n_pols) as u64
+        );
+        assert_eq!(summary.n_flagged_before, 0);
+        assert_eq!(summary.n_flagged_after, 8 * spec.n_rows() as u64);
+
+        let flags: Vec<bool> = t.get_cell_as_vec("FLAG", 5).unwrap();
+        let n = flags.len();
+        assert!(flags[n - 8..].iter().all(|f| *f));
+        assert!(flags[..n - 8].iter().all(|f| !*f));
+    }
+
+    #[test]
+    fn flag_row() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec::default();
+        let mut t = synthetic_ms(tmp_dir.path().join("test.ms"), &spec).unwrap();
+        t.put_cell("FLAG_ROW", 1, &true).unwrap();
+
+        // The real part of the synthetic data is the row number, so this
+        // flags all of row 0 and, with row 1 already flagged, nothing else.
+        let summary = run_flagger(&mut t, &mut BelowReal(1.), &Default::default()).unwrap();
+        let cell_len = (spec.n_chans * spec.n_pols) as u64;
+        assert_eq!(summary.n_flagged_before, cell_len);
+        assert_eq!(summary.n_flagged_after, 2 * cell_len);
+
+        let flag_row: Vec<bool> = t.get_col_as_vec("FLAG_ROW").unwrap();
+        assert!(flag_row[0] && flag_row[1]);
+        assert!(flag_row[2..].iter().all(|f| !*f));
+
+        let flags: Vec<bool> = t.get_cell_as_vec("FLAG", 1).unwrap();
+        assert!(flags.iter().all(|f| *f));
+    }
+}
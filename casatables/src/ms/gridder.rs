@@ -0,0 +1,455 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Feeding visibilities to imaging gridders.
+//!
+//! Gridders want a flat list of samples: the baseline coordinates of each
+//! sample in wavelengths, its frequency, its visibility, and its weight,
+//! with flagged data already removed. A Measurement Set stores the same
+//! information spread across the `UVW`, `DATA`, `FLAG`, and weight columns
+//! of the main table and the `SPECTRAL_WINDOW` sub-table. [`gridder_feed`]
+//! does the translation a chunk of rows at a time, so that imagers can
+//! stream through data sets of any size.
+
+use rubbl_core::constants::SPEED_OF_LIGHT;
+use std::{convert::TryFrom, path::Path};
+use thiserror::Error;
+
+use super::subtables::{read_data_desc_spws, read_spectral_windows};
+use crate::{glue, Complex, Table, TableError};
+
+/// An error that can occur when feeding visibilities to a gridder.
+#[derive(Error, Debug)]
+pub enum GridderFeedError {
+    /// An error occurred while reading the tables.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The data column does not have two-dimensional cells.
+    #[error("{column} cells have shape {shape:?}, but should have shape [n_chans, n_pols]")]
+    BadDataShape {
+        /// The name of the data column.
+        column: String,
+
+        /// The shape of its cells.
+        shape: Vec<usize>,
+    },
+
+    /// A polarization index was requested that is not present in the data.
+    #[error("polarization index {0} is out of range; the data have {1} polarizations")]
+    NoSuchPolarization(usize, usize),
+
+    /// A row refers to a data description or spectral window that does not
+    /// exist.
+    #[error("row {0} has an invalid DATA_DESC_ID of {1}")]
+    BadDataDescription(u64, i32),
+
+    /// The data cells do not have one entry per channel of a row’s spectral
+    /// window.
+    #[error("data cells have {n_data_chans} channels, but row {row} has a spectral window of {n_chans} channels")]
+    ChannelMismatch {
+        /// The row number.
+        row: u64,
+
+        /// The number of channels in the data cells.
+        n_data_chans: usize,
+
+        /// The number of channels in the row’s spectral window.
+        n_chans: usize,
+    },
+}
+
+/// Options controlling [`gridder_feed`].
+#[derive(Clone, Debug)]
+pub struct GridderFeedOptions {
+    /// The column of visibilities to read. The default is `DATA`.
+    pub data_column: String,
+
+    /// The indices of the polarization products to include. If `None`, the
+    /// default, all of them are included.
+    pub polarizations: Option<Vec<usize>>,
+
+    /// If true, include autocorrelations, which have no place on a grid and
+    /// are skipped by default.
+    pub include_autocorrelations: bool,
+
+    /// The number of rows to read per chunk. If `None`, the default, this is
+    /// chosen with [`Table::suggest_chunk_rows`].
+    pub rows_per_chunk: Option<usize>,
+}
+
+impl Default for GridderFeedOptions {
+    fn default() -> Self {
+        GridderFeedOptions {
+            data_column: "DATA".to_owned(),
+            polarizations: None,
+            include_autocorrelations: false,
+            rows_per_chunk: None,
+        }
+    }
+}
+
+/// The samples from one chunk of rows, ready to be gridded.
+///
+/// The fields are parallel arrays with one entry per sample, where a sample
+/// is one polarization product of one channel of one row. Samples that are
+/// flagged, in rows marked by `FLAG_ROW`, or with weights that are not
+/// positive are omitted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GridderChunk {
+    /// The number of the first row of the chunk.
+    pub start_row: u64,
+
+    /// The number of rows in the chunk, including any that contributed no
+    /// samples.
+    pub n_rows: usize,
+
+    /// The row number of each sample.
+    pub row: Vec<u64>,
+
+    /// The u coordinate of each sample, in wavelengths.
+    pub u: Vec<f64>,
+
+    /// The v coordinate of each sample, in wavelengths.
+    pub v: Vec<f64>,
+
+    /// The w coordinate of each sample, in wavelengths.
+    pub w: Vec<f64>,
+
+    /// The frequency of each sample, in Hz.
+    pub freq: Vec<f64>,
+
+    /// The index of the polarization product of each sample within the data
+    /// cells.
+    pub pol: Vec<usize>,
+
+    /// The visibility of each sample.
+    pub vis: Vec<Complex<f32>>,
+
+    /// The weight of each sample, from `WEIGHT_SPECTRUM` if the table has
+    /// that column and from `WEIGHT` otherwise.
+    pub weight: Vec<f32>,
+}
+
+impl GridderChunk {
+    /// Get the number of samples in the chunk.
+    pub fn len(&self) -> usize {
+        self.vis.len()
+    }
+
+    /// Check whether the chunk has no samples.
+    pub fn is_empty(&self) -> bool {
+        self.vis.is_empty()
+    }
+}
+
+/// Read the visibilities of a Measurement Set in the form that gridders
+/// want.
+///
+/// The returned iterator reads the main table of *table* a chunk of rows
+/// at a time, yielding a [`GridderChunk`] of the usable samples of each
+/// chunk. The `UVW` coordinates are converted to wavelengths using the
+/// channel frequencies of the spectral window of each row’s
+/// `DATA_DESC_ID`. The data column must hold single-precision complex
+/// values with a fixed shape of `[n_chans, n_pols]`.
+///
+/// ```no_run
+/// use rubbl_casatables::{Table, TableOpenMode};
+/// use rubbl_casatables::ms::{gridder_feed, GridderFeedOptions};
+///
+/// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+/// let opts = GridderFeedOptions {
+///     polarizations: Some(vec![0, 3]),
+///     ..Default::default()
+/// };
+///
+/// for chunk in gridder_feed(&mut t, &opts).unwrap() {
+///     let chunk = chunk.unwrap();
+///
+///     for i in 0..chunk.len() {
+///         // grid chunk.vis[i] * chunk.weight[i] at (chunk.u[i], chunk.v[i]) ...
+///     }
+/// }
+/// ```
+pub fn gridder_feed<'a>(
+    table: &'a mut Table,
+    opts: &GridderFeedOptions,
+) -> Result<GridderFeed<'a>, GridderFeedError> {
+    let ms_path = table.file_name().map_err(TableError::from)?;
+    let ms_path = Path::new(&ms_path);
+    let cell_shape = table.bulk_column_cell_shape::<Complex<f32>>(&opts.data_column)?;
+
+    if cell_shape.len() != 2 {
+        return Err(GridderFeedError::BadDataShape {
+            column: opts.data_column.clone(),
+            shape: cell_shape,
+        });
+    }
+
+    let (n_chans, n_pols) = (cell_shape[0], cell_shape[1]);
+    let pols = match opts.polarizations {
+        Some(ref p) => p.clone(),
+        None => (0..n_pols).collect(),
+    };
+
+    if let Some(p) = pols.iter().find(|p| **p >= n_pols) {
+        return Err(GridderFeedError::NoSuchPolarization(*p, n_pols));
+    }
+
+    let spw_ids = read_data_desc_spws(ms_path)?;
+    let spws = read_spectral_windows(ms_path)?;
+    let dd_freqs = spw_ids
+        .iter()
+        .map(|&id| {
+            usize::try_from(id)
+                .ok()
+                .and_then(|id| spws.get(id))
+                .map(|spw| spw.chan_freq.clone())
+        })
+        .collect();
+
+    let columns = table.column_names().map_err(TableError::from)?;
+    let has = |name: &str| columns.iter().any(|c| c == name);
+    let weight_spectrum = has("WEIGHT_SPECTRUM")
+        && table
+            .bulk_column_cell_shape::<f32>("WEIGHT_SPECTRUM")
+            .map(|s| s == cell_shape)
+            .unwrap_or(false);
+    let flag_row = has("FLAG_ROW");
+
+    let rows_per_chunk = match opts.rows_per_chunk {
+        Some(n) => n.max(1),
+        None => table.suggest_chunk_rows(&[&opts.data_column, "FLAG"], None)?,
+    };
+
+    Ok(GridderFeed {
+        table,
+        data_column: glue::StringBridge::from_rust(&opts.data_column),
+        pols,
+        include_autocorrelations: opts.include_autocorrelations,
+        weight_spectrum,
+        flag_row,
+        dd_freqs,
+        n_chans,
+        n_pols,
+        rows_per_chunk,
+        next_row: 0,
+        failed: false,
+    })
+}
+
+/// An iterator over the chunks of samples of a Measurement Set, created by
+/// [`gridder_feed`].
+pub struct GridderFeed<'a> {
+    table: &'a mut Table,
+    data_column: glue::StringBridge,
+    pols: Vec<usize>,
+    include_autocorrelations: bool,
+    weight_spectrum: bool,
+    flag_row: bool,
+    dd_freqs: Vec<Option<Vec<f64>>>,
+    n_chans: usize,
+    n_pols: usize,
+    rows_per_chunk: usize,
+    next_row: u64,
+    failed: bool,
+}
+
+impl<'a> GridderFeed<'a> {
+    fn read_chunk(&mut self, start_row: u64, n: usize) -> Result<GridderChunk, GridderFeedError> {
+        let cell_len = self.n_chans * self.n_pols;
+        let col = |name: &str| glue::StringBridge::from_rust(name);
+
+        let mut uvw = vec![0f64; 3 * n];
+        let mut ant1 = vec![0i32; n];
+        let mut ant2 = vec![0i32; n];
+        let mut ddid = vec![0i32; n];
+        let mut row_flags = vec![false; n];
+        let mut vis = vec![Complex::<f32>::default(); n * cell_len];
+        let mut flags = vec![false; n * cell_len];
+
+        self.table
+            .read_cells_range(&col("UVW"), start_row, n, &mut uvw)?;
+        self.table
+            .read_cells_range(&col("ANTENNA1"), start_row, n, &mut ant1)?;
+        self.table
+            .read_cells_range(&col("ANTENNA2"), start_row, n, &mut ant2)?;
+        self.table
+            .read_cells_range(&col("DATA_DESC_ID"), start_row, n, &mut ddid)?;
+        self.table
+            .read_cells_range(&self.data_column, start_row, n, &mut vis)?;
+        self.table
+            .read_cells_range(&col("FLAG"), start_row, n, &mut flags)?;
+
+        if self.flag_row {
+            self.table
+                .read_cells_range(&col("FLAG_ROW"), start_row, n, &mut row_flags)?;
+        }
+
+        // Weights either have one value per sample, or one per polarization
+        // that applies to every channel.
+        let weights = if self.weight_spectrum {
+            let mut w = vec![0f32; n * cell_len];
+            self.table
+                .read_cells_range(&col("WEIGHT_SPECTRUM"), start_row, n, &mut w)?;
+            w
+        } else {
+            let mut w = vec![0f32; n * self.n_pols];
+            self.table
+                .read_cells_range(&col("WEIGHT"), start_row, n, &mut w)?;
+            w
+        };
+
+        let mut chunk = GridderChunk {
+            start_row,
+            n_rows: n,
+            ..Default::default()
+        };
+
+        for i in 0..n {
+            let row = start_row + i as u64;
+
+            if row_flags[i] || (ant1[i] == ant2[i] && !self.include_autocorrelations) {
+                continue;
+            }
+
+            let freqs = usize::try_from(ddid[i])
+                .ok()
+                .and_then(|dd| self.dd_freqs.get(dd))
+                .and_then(|f| f.as_ref())
+                .ok_or(GridderFeedError::BadDataDescription(row, ddid[i]))?;
+
+            if freqs.len() != self.n_chans {
+                return Err(GridderFeedError::ChannelMismatch {
+                    row,
+                    n_data_chans: self.n_chans,
+                    n_chans: freqs.len(),
+                });
+            }
+
+            for (chan, freq) in freqs.iter().enumerate() {
+                let scale = freq / SPEED_OF_LIGHT;
+
+                for &pol in &self.pols {
+                    let k = i * cell_len + chan * self.n_pols + pol;
+                    let weight = if self.weight_spectrum {
+                        weights[k]
+                    } else {
+                        weights[i * self.n_pols + pol]
+                    };
+
+                    if flags[k] || weight.is_nan() || weight <= 0. {
+                        continue;
+                    }
+
+                    chunk.row.push(row);
+                    chunk.u.push(uvw[3 * i] * scale);
+                    chunk.v.push(uvw[3 * i + 1] * scale);
+                    chunk.w.push(uvw[3 * i + 2] * scale);
+                    chunk.freq.push(*freq);
+                    chunk.pol.push(pol);
+                    chunk.vis.push(vis[k]);
+                    chunk.weight.push(weight);
+                }
+            }
+        }
+
+        Ok(chunk)
+    }
+}
+
+impl<'a> Iterator for GridderFeed<'a> {
+    type Item = Result<GridderChunk, GridderFeedError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n_rows = self.table.n_rows();
+
+        if self.failed || self.next_row >= n_rows {
+            return None;
+        }
+
+        let start_row = self.next_row;
+        let n = (self.rows_per_chunk as u64).min(n_rows - start_row) as usize;
+        self.next_row += n as u64;
+        let result = self.read_chunk(start_row, n);
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+impl std::fmt::Debug for GridderFeed<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GridderFeed")
+            .field("pols", &self.pols)
+            .field("next_row", &self.next_row)
+            .field("rows_per_chunk", &self.rows_per_chunk)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn synthetic_feed() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec::default();
+        let mut t = synthetic_ms(tmp_dir.path().join("test.ms"), &spec).unwrap();
+
+        // Rows 1 and 2 are cross-correlations. Flag one sample of the first,
+        // and zero the weight of one polarization of the second.
+        let mut flags: Vec<bool> = t.get_cell_as_vec("FLAG", 1).unwrap();
+        flags[2 * spec.n_pols] = true;
+        let flags = ndarray::Array::from_shape_vec((spec.n_chans, spec.n_pols), flags).unwrap();
+        t.put_cell("FLAG", 1, &flags).unwrap();
+        t.put_cell("WEIGHT", 2, &vec![1f32, 1., 1., 0.]).unwrap();
+
+        let uvw: Vec<f64> = t.get_cell_as_vec("UVW", 1).unwrap();
+        let opts = GridderFeedOptions {
+            polarizations: Some(vec![0, 3]),
+            rows_per_chunk: Some(4),
+            ..Default::default()
+        };
+        let chunks: Vec<GridderChunk> = gridder_feed(&mut t, &opts)
+            .unwrap()
+            .map(|c| c.unwrap())
+            .collect();
+
+        assert_eq!(chunks.len(), spec.n_rows().div_ceil(4));
+        assert_eq!(chunks[1].start_row, 4);
+
+        let n_cross_rows = spec.n_timesteps * spec.n_ants * (spec.n_ants - 1) / 2;
+        let n_samples: usize = chunks.iter().map(|c| c.len()).sum();
+        assert_eq!(
+            n_samples,
+            n_cross_rows * spec.n_chans * 2 - 1 - spec.n_chans
+        );
+
+        let first = &chunks[0];
+        assert_eq!(first.row[0], 1);
+        assert_eq!(first.pol[..2], [0, 3]);
+        assert_eq!(first.vis[1], spec.expected_vis(1, 0, 3));
+        let freq = spec.start_freq;
+        assert_eq!(first.freq[0], freq);
+        assert!((first.u[0] - uvw[0] * freq / SPEED_OF_LIGHT).abs() < 1e-9);
+        assert!((first.w[0] - uvw[2] * freq / SPEED_OF_LIGHT).abs() < 1e-9);
+        assert!(!first
+            .row
+            .iter()
+            .zip(first.freq.iter())
+            .zip(first.pol.iter())
+            .any(|((r, f), p)| *r == 1 && *f == spec.start_freq + 2. * spec.chan_width && *p == 0));
+
+        let opts = GridderFeedOptions {
+            polarizations: Some(vec![spec.n_pols]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            gridder_feed(&mut t, &opts),
+            Err(GridderFeedError::NoSuchPolarization(..))
+        ));
+    }
+}
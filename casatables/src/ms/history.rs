@@ -0,0 +1,45 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Recording processing steps in the `HISTORY` sub-table.
+
+use rubbl_core::time::unix_to_mjd_seconds;
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Table, TableError, TableOpenMode};
+
+/// Append a row to the `HISTORY` sub-table of the data set at *ms_path*.
+///
+/// The row is stamped with the current time and the command line of the
+/// running program. *origin* names the function that did the work, and
+/// *app_params* lists its parameters as `name=value` strings.
+pub(crate) fn append_history(
+    ms_path: &Path,
+    origin: &str,
+    message: &str,
+    app_params: &[String],
+) -> Result<(), TableError> {
+    let mut history = Table::open(ms_path.join("HISTORY"), TableOpenMode::ReadWrite)?;
+    let row = history.n_rows();
+    history.add_rows(1)?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.);
+    let cli_command: Vec<String> = std::env::args().collect();
+
+    history.put_cell("TIME", row, &unix_to_mjd_seconds(now))?;
+    history.put_cell("OBSERVATION_ID", row, &-1i32)?;
+    history.put_cell("MESSAGE", row, &message.to_owned())?;
+    history.put_cell("PRIORITY", row, &"NORMAL".to_owned())?;
+    history.put_cell("ORIGIN", row, &origin.to_owned())?;
+    history.put_cell("OBJECT_ID", row, &-1i32)?;
+    history.put_cell("APPLICATION", row, &"rubbl".to_owned())?;
+    history.put_cell("CLI_COMMAND", row, &cli_command)?;
+    history.put_cell("APP_PARAMS", row, &app_params.to_vec())?;
+    Ok(())
+}
@@ -0,0 +1,478 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Row lookup indices for Measurement Set main tables.
+//!
+//! Interactive tools often need the rows of one stretch of time out of a long
+//! observation. Finding them by scanning the `TIME` column costs a full pass
+//! each time; a [`TimeIndex`] makes that pass once and answers later queries
+//! with a binary search. Likewise, a [`BaselineIndex`] lists the rows of each
+//! baseline, and can be saved to a sidecar file so that later sessions need
+//! not scan the table at all.
+
+use rubbl_core::chunked::ArrayChunkSink;
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    ops::Range,
+    path::Path,
+};
+use thiserror::Error;
+
+use crate::{Table, TableError};
+
+/// An error that can occur when building a row index.
+#[derive(Error, Debug)]
+pub enum IndexError {
+    /// An error occurred while reading the table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The `TIME` column is not sorted.
+    #[error("the TIME column is not sorted: row {0} is earlier than the row before it")]
+    Unsorted(u64),
+
+    /// An I/O error occurred while reading or writing a sidecar file.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// A sidecar file is not a valid index.
+    #[error("invalid index sidecar file: {0}")]
+    BadSidecar(&'static str),
+}
+
+/// An index of the timesteps of a table whose rows are sorted by `TIME`.
+///
+/// A timestep is a run of consecutive rows with the same `TIME`. The index
+/// stores one time and one row number per timestep.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimeIndex {
+    times: Vec<f64>,
+    starts: Vec<u64>,
+    n_rows: u64,
+}
+
+/// Collects timesteps from chunks of the `TIME` column.
+struct TimeIndexBuilder {
+    index: TimeIndex,
+    unsorted: Option<u64>,
+}
+
+impl ArrayChunkSink<f64> for TimeIndexBuilder {
+    type Error = Infallible;
+
+    fn write_chunk(&mut self, chunk: ndarray::ArrayViewD<f64>) -> Result<(), Infallible> {
+        for &t in chunk.iter() {
+            let row = self.index.n_rows;
+            self.index.n_rows += 1;
+
+            match self.index.times.last() {
+                Some(&last) if t == last => continue,
+                Some(&last) if t < last => {
+                    self.unsorted.get_or_insert(row);
+                }
+                _ => {}
+            }
+
+            self.index.times.push(t);
+            self.index.starts.push(row);
+        }
+
+        Ok(())
+    }
+}
+
+impl TimeIndex {
+    /// Build an index by scanning the `TIME` column of *table*.
+    ///
+    /// The column is read in chunks sized with [`Table::suggest_chunk_rows`].
+    /// Fails with [`IndexError::Unsorted`] if the rows are not sorted by
+    /// time.
+    pub fn build(table: &mut Table) -> Result<Self, IndexError> {
+        let rows_per_chunk = table.suggest_chunk_rows(&["TIME"], None)?;
+        let mut builder = TimeIndexBuilder {
+            index: TimeIndex::default(),
+            unsorted: None,
+        };
+
+        table.read_column_chunks("TIME", rows_per_chunk, &mut builder)?;
+
+        if let Some(row) = builder.unsorted {
+            return Err(IndexError::Unsorted(row));
+        }
+
+        Ok(builder.index)
+    }
+
+    /// Get the number of rows in the indexed table.
+    pub fn n_rows(&self) -> u64 {
+        self.n_rows
+    }
+
+    /// Get the number of timesteps.
+    pub fn n_timesteps(&self) -> usize {
+        self.times.len()
+    }
+
+    /// Get the time of each timestep, in increasing order.
+    pub fn times(&self) -> &[f64] {
+        &self.times[..]
+    }
+
+    /// Get the rows of timestep number *index*.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *index* is not less than [`Self::n_timesteps`].
+    pub fn rows_for_timestep(&self, index: usize) -> Range<u64> {
+        self.starts[index]..self.end_of(index + 1)
+    }
+
+    /// Get the rows whose `TIME` is at least *t0* but less than *t1*.
+    ///
+    /// The range is empty if there are no such rows.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{ms::TimeIndex, Table, TableOpenMode};
+    ///
+    /// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+    /// let index = TimeIndex::build(&mut t).unwrap();
+    /// let t0 = index.times()[0];
+    /// let first_minute = index.rows_for_time_range(t0, t0 + 60.);
+    /// ```
+    pub fn rows_for_time_range(&self, t0: f64, t1: f64) -> Range<u64> {
+        let lo = self.times.partition_point(|t| *t < t0);
+        let hi = self.times.partition_point(|t| *t < t1).max(lo);
+        self.end_of(lo)..self.end_of(hi)
+    }
+
+    /// Get the first row of timestep number *index*, or the number of rows
+    /// if it is past the last timestep.
+    fn end_of(&self, index: usize) -> u64 {
+        self.starts.get(index).copied().unwrap_or(self.n_rows)
+    }
+}
+
+/// The columns scanned by [`Table::for_each_index_row`].
+const INDEX_COLUMNS: &[&str] = &["TIME", "ANTENNA1", "ANTENNA2", "DATA_DESC_ID", "FIELD_ID"];
+
+/// The magic bytes at the start of a [`BaselineIndex`] sidecar file.
+const BASELINE_INDEX_MAGIC: &[u8; 8] = b"RUBBLBLX";
+
+/// The version of the [`BaselineIndex`] sidecar file format.
+const BASELINE_INDEX_VERSION: u32 = 1;
+
+/// An index of the rows of each baseline of a table.
+///
+/// Baselines are identified by their `ANTENNA1` and `ANTENNA2` values, and
+/// the rows of each baseline are listed in increasing order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BaselineIndex {
+    rows: BTreeMap<(i32, i32), Vec<u64>>,
+    n_rows: u64,
+}
+
+impl BaselineIndex {
+    /// Build an index by scanning the index columns of *table* once.
+    pub fn build(table: &mut Table) -> Result<Self, IndexError> {
+        let rows_per_chunk = table.suggest_chunk_rows(INDEX_COLUMNS, None)?;
+        let mut index = BaselineIndex::default();
+
+        table.for_each_index_row(rows_per_chunk, |row| {
+            index
+                .rows
+                .entry((row.antenna1, row.antenna2))
+                .or_default()
+                .push(row.row);
+        })?;
+
+        index.n_rows = table.n_rows();
+        Ok(index)
+    }
+
+    /// Load an index from the sidecar file at *path* if it exists and
+    /// matches *table*, or else build one and save it there.
+    ///
+    /// An existing file is considered to match if it was built from a table
+    /// with the same number of rows. Tables whose rows have been rewritten in
+    /// place must have their sidecar files deleted.
+    pub fn load_or_build<P: AsRef<Path>>(table: &mut Table, path: P) -> Result<Self, IndexError> {
+        let path = path.as_ref();
+
+        match Self::load(path) {
+            Ok(index) if index.n_rows == table.n_rows() => return Ok(index),
+            Ok(_) | Err(IndexError::BadSidecar(_)) => {}
+            Err(IndexError::Io(ref e)) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let index = Self::build(table)?;
+        index.save(path)?;
+        Ok(index)
+    }
+
+    /// Get the number of rows in the indexed table.
+    pub fn n_rows(&self) -> u64 {
+        self.n_rows
+    }
+
+    /// Get the number of distinct baselines.
+    pub fn n_baselines(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Iterate over the baselines, as `(ANTENNA1, ANTENNA2)` pairs, in
+    /// increasing order.
+    pub fn baselines(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.rows.keys().copied()
+    }
+
+    /// Get the rows of the baseline between *ant1* and *ant2*, in increasing
+    /// order.
+    ///
+    /// The antennas must be given in the same order as in the table. The
+    /// slice is empty if there are no rows for the baseline.
+    pub fn rows(&self, ant1: i32, ant2: i32) -> &[u64] {
+        self.rows.get(&(ant1, ant2)).map(|v| &v[..]).unwrap_or(&[])
+    }
+
+    /// Save the index to a sidecar file at *path*, replacing any existing
+    /// file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), IndexError> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write_to(&mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Load an index from a sidecar file at *path*.
+    ///
+    /// Besides the checks made by [`Self::read_from`], the size of the file
+    /// must match the numbers of baselines and rows that it declares.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, IndexError> {
+        let f = File::open(path)?;
+        let len = f.metadata()?.len();
+        Self::read_sized(&mut BufReader::new(f), Some(len))
+    }
+
+    /// Write the index in the sidecar file format.
+    ///
+    /// The format is a magic number and version, the number of rows in the
+    /// table and of baselines, and then for each baseline its antenna
+    /// numbers, its number of rows, and its row numbers. All values are
+    /// little-endian.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), IndexError> {
+        w.write_all(BASELINE_INDEX_MAGIC)?;
+        w.write_all(&BASELINE_INDEX_VERSION.to_le_bytes())?;
+        w.write_all(&self.n_rows.to_le_bytes())?;
+        w.write_all(&(self.rows.len() as u64).to_le_bytes())?;
+
+        for ((ant1, ant2), rows) in &self.rows {
+            w.write_all(&ant1.to_le_bytes())?;
+            w.write_all(&ant2.to_le_bytes())?;
+            w.write_all(&(rows.len() as u64).to_le_bytes())?;
+
+            for row in rows {
+                w.write_all(&row.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read an index in the sidecar file format.
+    ///
+    /// Fails with [`IndexError::BadSidecar`] unless every row of the table
+    /// belongs to exactly one baseline, each baseline appears once, and the
+    /// rows of each baseline are listed in increasing order. Memory is only
+    /// allocated for rows that are actually present in the input, so a
+    /// corrupted header cannot cause a huge allocation.
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, IndexError> {
+        Self::read_sized(r, None)
+    }
+
+    /// Read an index, checking the declared sizes against the length of the
+    /// input if it is known.
+    fn read_sized<R: Read>(r: &mut R, len: Option<u64>) -> Result<Self, IndexError> {
+        fn read_bytes<R: Read, const N: usize>(r: &mut R) -> Result<[u8; N], IndexError> {
+            let mut buf = [0; N];
+
+            r.read_exact(&mut buf).map_err(|e| {
+                if e.kind() == io::ErrorKind::UnexpectedEof {
+                    IndexError::BadSidecar("file is truncated")
+                } else {
+                    e.into()
+                }
+            })?;
+
+            Ok(buf)
+        }
+
+        if &read_bytes::<_, 8>(r)? != BASELINE_INDEX_MAGIC {
+            return Err(IndexError::BadSidecar("not a baseline index file"));
+        }
+
+        if u32::from_le_bytes(read_bytes(r)?) != BASELINE_INDEX_VERSION {
+            return Err(IndexError::BadSidecar("unsupported format version"));
+        }
+
+        let n_rows = u64::from_le_bytes(read_bytes(r)?);
+        let n_baselines = u64::from_le_bytes(read_bytes(r)?);
+
+        if let Some(len) = len {
+            // The header, 16 bytes per baseline, and 8 bytes per row.
+            let expected = n_baselines
+                .checked_mul(16)
+                .and_then(|n| n.checked_add(28))
+                .and_then(|n| n.checked_add(n_rows.checked_mul(8)?));
+
+            if expected != Some(len) {
+                return Err(IndexError::BadSidecar(
+                    "file size does not match its header",
+                ));
+            }
+        }
+
+        let mut rows = BTreeMap::new();
+        let mut n_seen = 0u64;
+
+        for _ in 0..n_baselines {
+            let ant1 = i32::from_le_bytes(read_bytes(r)?);
+            let ant2 = i32::from_le_bytes(read_bytes(r)?);
+            let n = u64::from_le_bytes(read_bytes(r)?);
+
+            n_seen = match n_seen.checked_add(n) {
+                Some(n_seen) if n_seen <= n_rows => n_seen,
+                _ => return Err(IndexError::BadSidecar("too many rows")),
+            };
+
+            let mut bl_rows = Vec::new();
+
+            for _ in 0..n {
+                let row = u64::from_le_bytes(read_bytes(r)?);
+
+                if row >= n_rows {
+                    return Err(IndexError::BadSidecar("row number out of range"));
+                }
+
+                if bl_rows.last().is_some_and(|&last| row <= last) {
+                    return Err(IndexError::BadSidecar("rows are not in increasing order"));
+                }
+
+                bl_rows.push(row);
+            }
+
+            if rows.insert((ant1, ant2), bl_rows).is_some() {
+                return Err(IndexError::BadSidecar("duplicated baseline"));
+            }
+        }
+
+        if n_seen != n_rows {
+            return Err(IndexError::BadSidecar("too few rows"));
+        }
+
+        Ok(BaselineIndex { rows, n_rows })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn time_index() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec {
+            n_ants: 3,
+            autocorrelations: false,
+            n_timesteps: 5,
+            ..Default::default()
+        };
+        let mut t = synthetic_ms(tmp_dir.path().join("test.ms"), &spec).unwrap();
+
+        let index = TimeIndex::build(&mut t).unwrap();
+        assert_eq!(index.n_rows(), 15);
+        assert_eq!(index.n_timesteps(), 5);
+        assert_eq!(index.rows_for_timestep(4), 12..15);
+
+        let times = index.times().to_vec();
+        assert_eq!(index.rows_for_time_range(times[1], times[3]), 3..9);
+        assert_eq!(index.rows_for_time_range(times[1] + 0.1, times[3]), 6..9);
+        assert_eq!(index.rows_for_time_range(0., 1e12), 0..15);
+        assert!(index.rows_for_time_range(1e12, 2e12).is_empty());
+        assert!(index.rows_for_time_range(times[3], times[1]).is_empty());
+
+        t.put_cell("TIME", 7, &0f64).unwrap();
+        assert!(matches!(
+            TimeIndex::build(&mut t),
+            Err(IndexError::Unsorted(7))
+        ));
+    }
+    #[test]
+    fn baseline_index() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec {
+            n_ants: 3,
+            autocorrelations: true,
+            n_timesteps: 2,
+            ..Default::default()
+        };
+        let mut t = synthetic_ms(tmp_dir.path().join("test.ms"), &spec).unwrap();
+
+        let index = BaselineIndex::build(&mut t).unwrap();
+        assert_eq!(index.n_rows(), 12);
+        assert_eq!(index.n_baselines(), 6);
+        assert_eq!(index.rows(0, 0), &[0, 6]);
+        assert_eq!(index.rows(1, 2), &[4, 10]);
+        assert!(index.rows(2, 1).is_empty());
+
+        let sidecar = tmp_dir.path().join("test.blidx");
+        let loaded = BaselineIndex::load_or_build(&mut t, &sidecar).unwrap();
+        assert_eq!(loaded, index);
+        assert_eq!(BaselineIndex::load(&sidecar).unwrap(), index);
+
+        let mut bytes = Vec::new();
+        index.write_to(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        assert!(matches!(
+            BaselineIndex::read_from(&mut &bytes[..]),
+            Err(IndexError::BadSidecar(_))
+        ));
+
+        // A header claiming an enormous number of rows must be rejected
+        // without trying to allocate space for them.
+        let mut bytes = Vec::new();
+        index.write_to(&mut bytes).unwrap();
+        bytes[12..20].copy_from_slice(&u64::MAX.to_le_bytes());
+        bytes[36..44].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+        assert!(matches!(
+            BaselineIndex::read_from(&mut &bytes[..]),
+            Err(IndexError::BadSidecar(_))
+        ));
+        std::fs::write(&sidecar, &bytes).unwrap();
+        assert!(matches!(
+            BaselineIndex::load(&sidecar),
+            Err(IndexError::BadSidecar(_))
+        ));
+
+        // So must one whose rows do not add up to the table.
+        let mut bytes = Vec::new();
+        index.write_to(&mut bytes).unwrap();
+        bytes[12..20].copy_from_slice(&13u64.to_le_bytes());
+        assert!(matches!(
+            BaselineIndex::read_from(&mut &bytes[..]),
+            Err(IndexError::BadSidecar("too few rows"))
+        ));
+
+        let rebuilt = BaselineIndex::load_or_build(&mut t, &sidecar).unwrap();
+        assert_eq!(rebuilt, index);
+
+        t.add_rows(1).unwrap();
+        let rebuilt = BaselineIndex::load_or_build(&mut t, &sidecar).unwrap();
+        assert_eq!(rebuilt.n_rows(), 13);
+    }
+}
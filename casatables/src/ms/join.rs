@@ -0,0 +1,279 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Resolving the foreign keys of Measurement Set main-table rows.
+
+use std::path::Path;
+use thiserror::Error;
+
+use super::{
+    subtables::{read_data_descs, read_fields, read_polarizations, read_spectral_windows},
+    SpectralWindow,
+};
+use crate::{casaimages::coordinates::StokesType, Table, TableError, TableOpenMode};
+
+/// An error that can occur when joining a Measurement Set with its
+/// sub-tables.
+#[derive(Error, Debug)]
+pub enum JoinError {
+    /// An error occurred while reading a table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// A row refers to a sub-table row that does not exist.
+    #[error("row {row} has {column} = {value}, which does not refer to a valid {table} row")]
+    BadKey {
+        /// The row of the referring table.
+        row: u64,
+        /// The name of the foreign key column.
+        column: &'static str,
+        /// The value of the foreign key.
+        value: i32,
+        /// The name of the sub-table that was referenced.
+        table: &'static str,
+    },
+}
+
+/// Information about one row of the `POLARIZATION` sub-table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolarizationInfo {
+    /// The row number of this setup, i.e. its ID.
+    pub id: usize,
+
+    /// The raw correlation type codes, following casacore’s
+    /// `Stokes::StokesTypes`.
+    pub corr_type: Vec<i32>,
+
+    /// The correlation types, decoded. Codes that are not recognized are
+    /// `None`.
+    pub stokes: Vec<Option<StokesType>>,
+}
+
+impl PolarizationInfo {
+    /// Get labels for the correlations, such as `"XX"` or `"RL"`.
+    pub fn labels(&self) -> Vec<String> {
+        self.corr_type
+            .iter()
+            .zip(self.stokes.iter())
+            .map(|(code, s)| match s {
+                Some(s) => s.name().to_owned(),
+                None => format!("#{code}"),
+            })
+            .collect()
+    }
+}
+
+/// Information about one row of the `FIELD` sub-table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldInfo {
+    /// The row number of this field, i.e. its ID.
+    pub id: usize,
+
+    /// The name of the field.
+    pub name: String,
+
+    /// The phase center of the field, as longitude and latitude in radians.
+    /// Only the constant term of the `PHASE_DIR` polynomial is used.
+    pub phase_dir: [f64; 2],
+}
+
+/// A main-table row with its foreign keys resolved.
+#[derive(Clone, Copy, Debug)]
+pub struct JoinedRow<'a> {
+    /// The row number in the main table.
+    pub row: u64,
+
+    /// The value of the `TIME` column.
+    pub time: f64,
+
+    /// The value of the `ANTENNA1` column.
+    pub antenna1: i32,
+
+    /// The value of the `ANTENNA2` column.
+    pub antenna2: i32,
+
+    /// The value of the `DATA_DESC_ID` column.
+    pub data_desc_id: usize,
+
+    /// The spectral window referenced through `DATA_DESC_ID`.
+    pub spectral_window: &'a SpectralWindow,
+
+    /// The polarization setup referenced through `DATA_DESC_ID`.
+    pub polarization: &'a PolarizationInfo,
+
+    /// The field referenced by `FIELD_ID`.
+    pub field: &'a FieldInfo,
+}
+
+/// A reader that attaches sub-table information to the rows of a
+/// Measurement Set main table.
+///
+/// Opening the reader loads the `DATA_DESCRIPTION`, `SPECTRAL_WINDOW`,
+/// `POLARIZATION`, and `FIELD` sub-tables, along with the index columns of
+/// the main table, and checks that every foreign key is valid. The rows can
+/// then be iterated over with [`Self::rows`], and bulk data such as the
+/// visibilities can be read through [`Self::table`].
+///
+/// ```no_run
+/// use rubbl_casatables::ms::JoinedReader;
+///
+/// let reader = JoinedReader::open("vis.ms").unwrap();
+///
+/// for row in reader.rows() {
+///     let freqs = &row.spectral_window.chan_freq;
+///     let labels = row.polarization.labels();
+///     println!("{} {} {:?} {}", row.row, row.field.name, labels, freqs[0]);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct JoinedReader {
+    table: Table,
+    time: Vec<f64>,
+    antenna1: Vec<i32>,
+    antenna2: Vec<i32>,
+    data_desc_id: Vec<i32>,
+    field_id: Vec<i32>,
+    data_descs: Vec<(usize, usize)>,
+    spectral_windows: Vec<SpectralWindow>,
+    polarizations: Vec<PolarizationInfo>,
+    fields: Vec<FieldInfo>,
+}
+
+impl JoinedReader {
+    /// Open a Measurement Set for reading.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, JoinError> {
+        let path = path.as_ref();
+        let mut table = Table::open(path, TableOpenMode::Read)?;
+
+        let spectral_windows = read_spectral_windows(path)?;
+        let polarizations = read_polarizations(path)?;
+        let fields = read_fields(path)?;
+        let data_descs = read_data_descs(path, spectral_windows.len(), polarizations.len())?;
+
+        let time = table.get_col_as_vec("TIME")?;
+        let antenna1 = table.get_col_as_vec("ANTENNA1")?;
+        let antenna2 = table.get_col_as_vec("ANTENNA2")?;
+        let data_desc_id: Vec<i32> = table.get_col_as_vec("DATA_DESC_ID")?;
+        let field_id: Vec<i32> = table.get_col_as_vec("FIELD_ID")?;
+
+        for (row, (&dd, &field)) in data_desc_id.iter().zip(field_id.iter()).enumerate() {
+            check_key(
+                row as u64,
+                "DATA_DESC_ID",
+                dd,
+                "DATA_DESCRIPTION",
+                data_descs.len(),
+            )?;
+            check_key(row as u64, "FIELD_ID", field, "FIELD", fields.len())?;
+        }
+
+        Ok(JoinedReader {
+            table,
+            time,
+            antenna1,
+            antenna2,
+            data_desc_id,
+            field_id,
+            data_descs,
+            spectral_windows,
+            polarizations,
+            fields,
+        })
+    }
+
+    /// Get the number of rows in the main table.
+    pub fn n_rows(&self) -> u64 {
+        self.time.len() as u64
+    }
+
+    /// Get one main-table row with its foreign keys resolved.
+    ///
+    /// Returns `None` if *row* is out of bounds.
+    pub fn row(&self, row: u64) -> Option<JoinedRow<'_>> {
+        let i = row as usize;
+
+        if i >= self.time.len() {
+            return None;
+        }
+
+        let data_desc_id = self.data_desc_id[i] as usize;
+        let (spw, pol) = self.data_descs[data_desc_id];
+
+        Some(JoinedRow {
+            row,
+            time: self.time[i],
+            antenna1: self.antenna1[i],
+            antenna2: self.antenna2[i],
+            data_desc_id,
+            spectral_window: &self.spectral_windows[spw],
+            polarization: &self.polarizations[pol],
+            field: &self.fields[self.field_id[i] as usize],
+        })
+    }
+
+    /// Iterate over the main-table rows with their foreign keys resolved.
+    pub fn rows(&self) -> impl Iterator<Item = JoinedRow<'_>> {
+        (0..self.n_rows()).map(move |r| self.row(r).unwrap())
+    }
+
+    /// Get the spectral windows.
+    pub fn spectral_windows(&self) -> &[SpectralWindow] {
+        &self.spectral_windows[..]
+    }
+
+    /// Get the polarization setups.
+    pub fn polarizations(&self) -> &[PolarizationInfo] {
+        &self.polarizations[..]
+    }
+
+    /// Get the fields.
+    pub fn fields(&self) -> &[FieldInfo] {
+        &self.fields[..]
+    }
+
+    /// Get the main table, to read other columns.
+    pub fn table(&mut self) -> &mut Table {
+        &mut self.table
+    }
+}
+
+pub(super) fn check_key(
+    row: u64,
+    column: &'static str,
+    value: i32,
+    table: &'static str,
+    n: usize,
+) -> Result<usize, JoinError> {
+    if value < 0 || value as usize >= n {
+        Err(JoinError::BadKey {
+            row,
+            column,
+            value,
+            table,
+        })
+    } else {
+        Ok(value as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_keys() {
+        assert_eq!(check_key(0, "FIELD_ID", 2, "FIELD", 3).unwrap(), 2);
+        assert!(check_key(0, "FIELD_ID", 3, "FIELD", 3).is_err());
+        assert!(check_key(0, "FIELD_ID", -1, "FIELD", 3).is_err());
+    }
+
+    #[test]
+    fn polarization_labels() {
+        let info = PolarizationInfo {
+            id: 0,
+            corr_type: vec![9, 12, 99],
+            stokes: vec![StokesType::from_code(9), StokesType::from_code(12), None],
+        };
+        assert_eq!(info.labels(), vec!["XX", "YY", "#99"]);
+    }
+}
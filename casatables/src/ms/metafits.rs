@@ -0,0 +1,218 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Creating Measurement Sets for observations described by MWA metafits
+//! files.
+//!
+//! Each MWA observation comes with a metafits file giving its tile layout,
+//! frequency setup, pointing, and timing. [`metafits_simulation_spec`] turns
+//! one into a [`SimulationSpec`], so that an observation can be simulated
+//! with [`super::simulate`], and [`create_ms_from_metafits`] sets up an empty
+//! data set with the observation’s sub-tables, returning a [`StreamWriter`]
+//! to which the correlator’s output can be written.
+//!
+//! ```no_run
+//! use rubbl_casatables::ms::create_ms_from_metafits;
+//! use rubbl_fits::metafits::Metafits;
+//!
+//! let metafits = Metafits::open("1065880128.metafits").unwrap();
+//! let writer = create_ms_from_metafits("1065880128.ms", &metafits).unwrap();
+//! // ... writer.write_integration(...) for each timestep ...
+//! let ms = writer.finish().unwrap();
+//! ```
+
+use ndarray::Array2;
+use rubbl_fits::{metafits::Metafits, FitsError};
+use std::path::Path;
+use thiserror::Error;
+
+use super::{
+    simulate::{fill_subtables, SubtableContents},
+    SimulationSpec, SpectralWindow, StreamWriter,
+};
+use crate::{Table, TableError, TableOpenMode};
+
+/// An error that can occur when creating a data set from a metafits file.
+#[derive(Error, Debug)]
+pub enum MetafitsConversionError {
+    /// An error occurred while interpreting the metafits file.
+    #[error(transparent)]
+    Fits(#[from] FitsError),
+
+    /// An error occurred while writing the output tables.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The metafits file describes an observation that cannot be recorded.
+    #[error("unusable metafits file: {0}")]
+    Invalid(String),
+}
+
+/// Get the parameters of the observation described by a metafits file.
+///
+/// The layout comes from the tile positions, the spectral window from the
+/// fine channels, and the timing from `DATE-OBS`, `INTTIME`, and `EXPOSURE`.
+/// The phase center is `RAPHASE` and `DECPHASE`, or the pointing center if
+/// these are absent. All four polarization products are recorded, with
+/// autocorrelations, as the MWA correlator does. The specification has no
+/// sources; add some before passing it to [`super::simulate`].
+pub fn metafits_simulation_spec(
+    metafits: &Metafits,
+) -> Result<SimulationSpec, MetafitsConversionError> {
+    let layout = metafits.antenna_layout()?;
+
+    if layout.is_empty() {
+        return Err(MetafitsConversionError::Invalid(
+            "there are no tiles".to_owned(),
+        ));
+    }
+
+    let freqs = metafits.fine_channel_freqs()?;
+
+    if freqs.is_empty() {
+        return Err(MetafitsConversionError::Invalid(
+            "there are no fine channels".to_owned(),
+        ));
+    }
+
+    let mut spw = SpectralWindow::uniform(freqs.len(), freqs[0], metafits.fine_channel_width()?);
+    spw.name = metafits.obs_id()?.to_string();
+
+    let mut spec = SimulationSpec::new(layout, metafits.phase_center()?, spw);
+    spec.telescope_name = "MWA".to_owned();
+    spec.n_pols = 4;
+    spec.start_time = metafits.start_time()?;
+    spec.integration_time = metafits.integration_time()?;
+    spec.n_timesteps = metafits.n_timesteps()?;
+    spec.autocorrelations = true;
+    Ok(spec)
+}
+
+/// Create an empty Measurement Set at *path* for the observation described
+/// by a metafits file.
+///
+/// The sub-tables are filled in from [`metafits_simulation_spec`], with the
+/// field named after the `FILENAME` keyword and its `DELAY_DIR` set to the
+/// pointing center, so that the tiles’ beamformer direction is kept when the
+/// data are phased elsewhere. The returned writer expects every baseline,
+/// autocorrelations included, in each integration.
+pub fn create_ms_from_metafits<P: AsRef<Path>>(
+    path: P,
+    metafits: &Metafits,
+) -> Result<StreamWriter, MetafitsConversionError> {
+    let spec = metafits_simulation_spec(metafits)?;
+    let (pointing_ra, pointing_dec) = metafits.pointing_center()?;
+    let path = path.as_ref();
+    let main = Table::create_with_default_subtables(path, 0)?;
+
+    fill_subtables(
+        path,
+        &SubtableContents {
+            layout: &spec.layout,
+            telescope_name: &spec.telescope_name,
+            field_name: metafits.obs_name()?,
+            phase_dir: [spec.phase_center.0, spec.phase_center.1],
+            spw: &spec.spw,
+            corr_types: spec.corr_types().unwrap(),
+            start_time: spec.start_time,
+            end_time: spec.start_time + spec.n_timesteps as f64 * spec.integration_time,
+        },
+    )?;
+
+    let mut field = Table::open(path.join("FIELD"), TableOpenMode::ReadWrite)?;
+    let delay_dir = Array2::from_shape_vec((1, 2), vec![pointing_ra, pointing_dec]).unwrap();
+    field.put_cell("DELAY_DIR", 0, &delay_dir)?;
+
+    Ok(StreamWriter::new(
+        main,
+        spec.baselines(),
+        spec.integration_time,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use rubbl_fits::{
+        bintable::BinTableBuilder,
+        header::{self, HeaderCard, HeaderValue},
+        FitsParser,
+    };
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    fn fake_metafits() -> Metafits {
+        let mut file = Vec::new();
+        let mut primary = header::empty_primary_header();
+        let float = |k: &str, v: f64| HeaderCard::new(k, HeaderValue::Float(v));
+        primary.extend(vec![
+            HeaderCard::new("GPSTIME", HeaderValue::Integer(1065880128)),
+            HeaderCard::new("FILENAME", HeaderValue::String("EoR0".to_owned())),
+            HeaderCard::new(
+                "DATE-OBS",
+                HeaderValue::String("2013-10-15T13:48:32".to_owned()),
+            ),
+            HeaderCard::new("EXPOSURE", HeaderValue::Integer(4)),
+            float("INTTIME", 2.),
+            float("RA", 10.),
+            float("DEC", -27.),
+            float("RAPHASE", 0.),
+            float("DECPHASE", -27.),
+            HeaderCard::new("NCHANS", HeaderValue::Integer(4)),
+            float("FINECHAN", 40.),
+            float("FREQCENT", 154.24),
+            float("BANDWDTH", 0.16),
+        ]);
+        header::write_header(&mut file, &primary).unwrap();
+
+        let mut builder = BinTableBuilder::new("TILEDATA");
+        builder
+            .add_column("Antenna", Array1::from(vec![0i16, 0, 1, 1]).view())
+            .unwrap()
+            .add_string_column("TileName", &["Tile011", "Tile011", "Tile012", "Tile012"])
+            .unwrap()
+            .add_string_column("Pol", &["X", "Y", "X", "Y"])
+            .unwrap()
+            .add_column("North", Array1::from(vec![0f32, 0., 10., 10.]).view())
+            .unwrap()
+            .add_column("East", Array1::from(vec![0f32, 0., 5., 5.]).view())
+            .unwrap()
+            .add_column("Height", Array1::from(vec![377.827f32; 4]).view())
+            .unwrap();
+        builder.write(&mut file).unwrap();
+
+        let mut parser = FitsParser::new(Cursor::new(file)).unwrap();
+        Metafits::from_parser(&mut parser).unwrap()
+    }
+
+    #[test]
+    fn create() {
+        let metafits = fake_metafits();
+        let spec = metafits_simulation_spec(&metafits).unwrap();
+        assert_eq!(spec.layout.len(), 2);
+        assert_eq!(spec.spw.n_chans(), 4);
+        assert!((spec.spw.chan_freq[0] - 154.18e6).abs() < 1.);
+        assert_eq!(spec.n_timesteps, 2);
+        assert_eq!(spec.baselines(), vec![(0, 0), (0, 1), (1, 1)]);
+
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("mwa.ms");
+        let writer = create_ms_from_metafits(&path, &metafits).unwrap();
+        let main = writer.finish().unwrap();
+        assert_eq!(main.n_rows(), 0);
+        drop(main);
+
+        let mut field = Table::open(path.join("FIELD"), TableOpenMode::Read).unwrap();
+        let name: String = field.get_cell("NAME", 0).unwrap();
+        assert_eq!(name, "EoR0");
+        let delay_dir: Vec<f64> = field.get_cell_as_vec("DELAY_DIR", 0).unwrap();
+        assert!((delay_dir[0] - 10f64.to_radians()).abs() < 1e-12);
+        let phase_dir: Vec<f64> = field.get_cell_as_vec("PHASE_DIR", 0).unwrap();
+        assert_eq!(phase_dir[0], 0.);
+
+        let mut obs = Table::open(path.join("OBSERVATION"), TableOpenMode::Read).unwrap();
+        let telescope: String = obs.get_cell("TELESCOPE_NAME", 0).unwrap();
+        assert_eq!(telescope, "MWA");
+    }
+}
@@ -0,0 +1,597 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Converting MIRIAD UV data sets into Measurement Sets.
+
+use ndarray::{Array1, Array2};
+use rubbl_core::{
+    array_layout::geodetic_to_itrf, constants::SPEED_OF_LIGHT, time::SECONDS_PER_DAY,
+};
+use rubbl_miriad::{
+    visdata::{Decoder, UvRecord},
+    DataSet, MiriadFormatError, MiriadMappedType,
+};
+use std::path::Path;
+use thiserror::Error;
+
+use super::convert::{
+    aips_pol_is_linear, aips_pol_to_casa, put_main_row, write_antennas, write_fields,
+    write_observation, write_polarization, write_spectral_windows, Antenna, Field, MainRow,
+    MJD_OFFSET,
+};
+use super::SpectralWindow;
+use crate::{CasacoreError, Complex, Table, TableError};
+
+/// An error that can occur when converting a MIRIAD data set.
+#[derive(Error, Debug)]
+pub enum MiriadConversionError {
+    /// The MIRIAD data set could not be read.
+    #[error(transparent)]
+    Miriad(#[from] MiriadFormatError),
+
+    /// The Measurement Set could not be written.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The MIRIAD data set uses a feature that the converter can't handle.
+    #[error("{0}")]
+    Unsupported(String),
+}
+
+impl From<CasacoreError> for MiriadConversionError {
+    fn from(e: CasacoreError) -> Self {
+        MiriadConversionError::Table(e.into())
+    }
+}
+
+/// Information about the result of a [`miriad_to_ms`] operation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MiriadConversionSummary {
+    /// The number of MIRIAD visibility records that were read.
+    pub n_records: u64,
+
+    /// The number of rows written to the main table.
+    pub n_rows: u64,
+
+    /// The number of antennas in the `ANTENNA` sub-table.
+    pub n_antennas: usize,
+
+    /// The number of fields (MIRIAD sources) in the `FIELD` sub-table.
+    pub n_fields: usize,
+
+    /// The number of spectral windows.
+    pub n_spws: usize,
+}
+
+/// Convert a MIRIAD UV data set into a new Measurement Set.
+///
+/// MIRIAD stores each polarization of a baseline in its own record, so
+/// consecutive records with the same time and baseline are merged into one
+/// main-table row per spectral window. The MIRIAD UV variables are mapped as
+/// follows:
+///
+/// - `time` (Julian date) becomes `TIME` and `TIME_CENTROID` (MJD seconds).
+/// - `coord` (nanoseconds) becomes `UVW` (meters).
+/// - `inttime` becomes `INTERVAL` and `EXPOSURE`.
+/// - `pol` codes become the `CORR_TYPE` values of the single `POLARIZATION`
+///   row.
+/// - `nspect`, `nschan`, `sfreq`, and `sdf` define the `SPECTRAL_WINDOW`
+///   and `DATA_DESCRIPTION` rows.
+/// - Each distinct `source`, `ra`, and `dec` defines a `FIELD` row, and the
+///   `SCAN_NUMBER` is incremented whenever the field changes.
+/// - `antpos`, `latitud`, and `longitu` define the `ANTENNA` positions, which
+///   are converted to ITRF assuming that `antpos` is relative to the array
+///   reference location on the WGS84 ellipsoid.
+/// - `telescop` and `observer` fill in the `OBSERVATION` row.
+///
+/// Data sets whose spectral or polarization setups change partway through
+/// are not supported. The output path must not already exist.
+pub fn miriad_to_ms<P1: AsRef<Path>, P2: AsRef<Path>>(
+    src_path: P1,
+    dest_path: P2,
+) -> Result<MiriadConversionSummary, MiriadConversionError> {
+    let dest_path = dest_path.as_ref();
+    let mut ds = DataSet::open(src_path.as_ref())?;
+    let mut uv = ds.open_uv_stream()?;
+    let mut main = Table::create_with_default_subtables(dest_path, 0)?;
+
+    let mut conv = Converter::default();
+    let mut pending: Option<PendingRow> = None;
+
+    while let Some(rec) = uv.next_record()? {
+        conv.n_records += 1;
+        conv.track_record(uv.decoder(), &rec)?;
+
+        let pol = rec.preamble.pol.unwrap_or(1);
+
+        let continues = pending.as_ref().is_some_and(|p| {
+            p.time == rec.preamble.time
+                && p.ant1 == rec.preamble.ant1
+                && p.ant2 == rec.preamble.ant2
+                && !p.pols.contains(&pol)
+        });
+
+        if !continues {
+            if let Some(p) = pending.take() {
+                conv.write_row(&mut main, p)?;
+            }
+
+            pending = Some(PendingRow {
+                time: rec.preamble.time,
+                ant1: rec.preamble.ant1,
+                ant2: rec.preamble.ant2,
+                uvw: rec.preamble.uvw,
+                interval: conv.interval,
+                field_id: conv.field_id,
+                scan_number: conv.scan_number,
+                pols: Vec::new(),
+                data: Vec::new(),
+                flags: Vec::new(),
+            });
+        }
+
+        let p = pending.as_mut().unwrap();
+        p.pols.push(pol);
+        p.data.push(rec.data);
+        p.flags.push(rec.flags);
+    }
+
+    if let Some(p) = pending.take() {
+        conv.write_row(&mut main, p)?;
+    }
+
+    conv.write_subtables(uv.decoder(), dest_path)?;
+
+    Ok(MiriadConversionSummary {
+        n_records: conv.n_records,
+        n_rows: conv.n_rows,
+        n_antennas: conv.n_antennas(uv.decoder())?,
+        n_fields: conv.fields.len(),
+        n_spws: conv.spws.len(),
+    })
+}
+
+/// The records of one time and baseline, one per polarization.
+#[derive(Debug)]
+struct PendingRow {
+    time: f64,
+    ant1: usize,
+    ant2: usize,
+    uvw: [f64; 3],
+    interval: f64,
+    field_id: usize,
+    scan_number: i32,
+    pols: Vec<i32>,
+    data: Vec<Array1<Complex<f32>>>,
+    flags: Vec<Array1<bool>>,
+}
+
+#[derive(Debug, Default)]
+struct Converter {
+    n_records: u64,
+    n_rows: u64,
+    spws: Vec<SpectralWindow>,
+    pols: Option<Vec<i32>>,
+    fields: Vec<Field>,
+    field_id: usize,
+    scan_number: i32,
+    interval: f64,
+    max_ant: usize,
+    time_range: Option<(f64, f64)>,
+}
+
+impl Converter {
+    /// Update the slowly-varying state with the variables of a new record.
+    fn track_record(&mut self, dec: &Decoder, rec: &UvRecord) -> Result<(), MiriadConversionError> {
+        if self.spws.is_empty() {
+            self.spws = read_spectral_setup(dec, rec.data.len())?;
+        } else {
+            let n_chans: usize = self.spws.iter().map(|w| w.n_chans()).sum();
+
+            if rec.data.len() != n_chans {
+                return Err(MiriadConversionError::Unsupported(format!(
+                    "the number of channels changes from {} to {} in record {}; this is \
+                     not supported",
+                    n_chans,
+                    rec.data.len(),
+                    self.n_records
+                )));
+            }
+        }
+
+        if self.n_records == 1
+            || var_updated(dec, "source")
+            || var_updated(dec, "ra")
+            || var_updated(dec, "dec")
+        {
+            let field = Field {
+                name: get_var::<String>(dec, "source")?
+                    .map(|mut v| v.swap_remove(0))
+                    .unwrap_or_default(),
+                ra: get_scalar::<f64>(dec, "ra")?.unwrap_or(0.),
+                dec: get_scalar::<f64>(dec, "dec")?.unwrap_or(0.),
+            };
+
+            let field_id = match self.fields.iter().position(|f| *f == field) {
+                Some(i) => i,
+                None => {
+                    self.fields.push(field);
+                    self.fields.len() - 1
+                }
+            };
+
+            if self.n_records == 1 || field_id != self.field_id {
+                self.scan_number += 1;
+            }
+
+            self.field_id = field_id;
+        }
+
+        if let Some(t) = get_scalar::<f32>(dec, "inttime")? {
+            self.interval = t as f64;
+        }
+
+        self.max_ant = self.max_ant.max(rec.preamble.ant2);
+        Ok(())
+    }
+
+    fn write_row(&mut self, main: &mut Table, p: PendingRow) -> Result<(), MiriadConversionError> {
+        match self.pols {
+            None => self.pols = Some(p.pols.clone()),
+
+            Some(ref pols) => {
+                if *pols != p.pols {
+                    return Err(MiriadConversionError::Unsupported(format!(
+                        "the polarizations of baseline {}-{} at JD {} are {:?}, but {:?} \
+                         were expected; changing polarization setups are not supported",
+                        p.ant1 + 1,
+                        p.ant2 + 1,
+                        p.time,
+                        p.pols,
+                        pols
+                    )));
+                }
+            }
+        }
+
+        let time = (p.time - MJD_OFFSET) * SECONDS_PER_DAY;
+
+        self.time_range = Some(match self.time_range {
+            None => (time, time),
+            Some((t0, t1)) => (t0.min(time), t1.max(time)),
+        });
+
+        let ns_to_m = 1e-9 * SPEED_OF_LIGHT;
+        let uvw = [p.uvw[0] * ns_to_m, p.uvw[1] * ns_to_m, p.uvw[2] * ns_to_m];
+        let n_pols = p.pols.len();
+        let mut chan0 = 0;
+
+        main.add_rows(self.spws.len())?;
+
+        for (spw_id, spw) in self.spws.iter().enumerate() {
+            let row = MainRow {
+                time,
+                interval: p.interval,
+                ant1: p.ant1,
+                ant2: p.ant2,
+                data_desc_id: spw_id,
+                field_id: p.field_id,
+                scan_number: p.scan_number,
+                uvw,
+                weight: vec![1.; n_pols],
+                data: Array2::from_shape_fn((spw.n_chans(), n_pols), |(c, i)| p.data[i][chan0 + c]),
+                flags: Array2::from_shape_fn((spw.n_chans(), n_pols), |(c, i)| {
+                    p.flags[i][chan0 + c]
+                }),
+            };
+
+            put_main_row(main, self.n_rows, &row)?;
+            chan0 += spw.n_chans();
+            self.n_rows += 1;
+        }
+
+        Ok(())
+    }
+
+    fn n_antennas(&self, dec: &Decoder) -> Result<usize, MiriadConversionError> {
+        let nants = get_scalar::<i32>(dec, "nants")?.unwrap_or(0).max(0) as usize;
+        Ok(nants.max(self.max_ant + 1))
+    }
+
+    fn write_subtables(
+        &self,
+        dec: &Decoder,
+        dest_path: &Path,
+    ) -> Result<(), MiriadConversionError> {
+        write_spectral_windows(dest_path, &self.spws)?;
+
+        let pols = self.pols.clone().unwrap_or_default();
+        let casa_pols = pols
+            .iter()
+            .map(|&pol| {
+                aips_pol_to_casa(pol).ok_or_else(|| {
+                    MiriadConversionError::Unsupported(format!(
+                        "unrecognized MIRIAD polarization code {pol}"
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        write_polarization(dest_path, &casa_pols)?;
+
+        let (t0, t1) = self.time_range.unwrap_or((0., 0.));
+        write_fields(dest_path, &self.fields, t0)?;
+
+        let n_ants = self.n_antennas(dec)?;
+        let diameter = get_scalar::<f32>(dec, "antdiam")?.unwrap_or(0.) as f64;
+        let antennas: Vec<_> = antenna_positions(dec, n_ants)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, position)| Antenna {
+                name: format!("ANT{}", i + 1),
+                station: String::new(),
+                position,
+                diameter,
+                mount: "ALT-AZ".to_owned(),
+            })
+            .collect();
+        let linear = pols.iter().any(|p| aips_pol_is_linear(*p));
+        write_antennas(dest_path, &antennas, linear, t0)?;
+
+        let text = |name: &str| -> Result<String, MiriadConversionError> {
+            Ok(get_var::<String>(dec, name)?
+                .map(|mut v| v.swap_remove(0))
+                .unwrap_or_default())
+        };
+
+        write_observation(
+            dest_path,
+            &text("telescop")?,
+            &text("observer")?,
+            "",
+            (t0, t1),
+        )?;
+        Ok(())
+    }
+}
+
+/// Get the current value of a UV variable, if it has one.
+fn get_var<T: MiriadMappedType>(
+    dec: &Decoder,
+    name: &str,
+) -> Result<Option<Vec<T>>, MiriadConversionError> {
+    let var = match dec.lookup_variable(name) {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+
+    let info = dec.get_var(var);
+
+    if info.n_vals() < 0 {
+        return Ok(None);
+    }
+
+    if info.type_() != T::TYPE {
+        return Err(MiriadConversionError::Unsupported(format!(
+            "UV variable \"{}\" has unexpected type {}",
+            name,
+            info.type_().abbrev_char()
+        )));
+    }
+
+    let mut buf = Vec::new();
+    dec.get_data(var, &mut buf);
+    Ok(Some(buf))
+}
+
+/// Get the first value of a UV variable, if it has one.
+fn get_scalar<T: MiriadMappedType>(
+    dec: &Decoder,
+    name: &str,
+) -> Result<Option<T>, MiriadConversionError> {
+    Ok(get_var::<T>(dec, name)?.and_then(|mut v| {
+        if v.is_empty() {
+            None
+        } else {
+            Some(v.swap_remove(0))
+        }
+    }))
+}
+
+fn var_updated(dec: &Decoder, name: &str) -> bool {
+    dec.lookup_variable(name)
+        .is_some_and(|v| dec.get_var(v).just_updated())
+}
+
+fn read_spectral_setup(
+    dec: &Decoder,
+    n_data: usize,
+) -> Result<Vec<SpectralWindow>, MiriadConversionError> {
+    let missing =
+        |name: &str| MiriadConversionError::Unsupported(format!("no \"{name}\" UV variable"));
+
+    let sfreq = get_var::<f64>(dec, "sfreq")?.ok_or_else(|| missing("sfreq"))?;
+    let sdf = get_var::<f64>(dec, "sdf")?.ok_or_else(|| missing("sdf"))?;
+    let nschan = get_var::<i32>(dec, "nschan")?.unwrap_or_else(|| vec![n_data as i32]);
+    let nspect = get_scalar::<i32>(dec, "nspect")?.unwrap_or(1).max(0) as usize;
+
+    if sfreq.len() < nspect || sdf.len() < nspect || nschan.len() < nspect {
+        return Err(MiriadConversionError::Unsupported(format!(
+            "the spectral variables do not describe all {nspect} spectral windows"
+        )));
+    }
+
+    // MIRIAD frequencies are in GHz.
+
+    let spws: Vec<_> = (0..nspect)
+        .map(|i| {
+            let mut spw =
+                SpectralWindow::uniform(nschan[i].max(0) as usize, sfreq[i] * 1e9, sdf[i] * 1e9);
+            spw.name = format!("WINDOW{}", i + 1);
+            spw
+        })
+        .collect();
+
+    let n_chans: usize = spws.iter().map(|w| w.n_chans()).sum();
+
+    if n_chans != n_data {
+        return Err(MiriadConversionError::Unsupported(format!(
+            "the spectral windows have {n_chans} channels in total, but the records have {n_data}"
+        )));
+    }
+
+    Ok(spws)
+}
+
+/// Compute ITRF antenna positions from the MIRIAD `antpos` variable.
+///
+/// MIRIAD stores all of the X coordinates, then all of the Y coordinates, then
+/// all of the Z coordinates, in nanoseconds, in a frame that is rotated to the
+/// array longitude.
+fn antenna_positions(dec: &Decoder, n_ants: usize) -> Result<Vec<[f64; 3]>, MiriadConversionError> {
+    let lat = get_scalar::<f64>(dec, "latitud")?.unwrap_or(0.);
+    let lon = get_scalar::<f64>(dec, "longitu")?.unwrap_or(0.);
+    let center = geodetic_to_itrf(lon, lat, 0.);
+    let antpos = get_var::<f64>(dec, "antpos")?.unwrap_or_default();
+    let n_known = antpos.len() / 3;
+    let (sin_lon, cos_lon) = lon.sin_cos();
+
+    Ok((0..n_ants)
+        .map(|i| {
+            if i >= n_known {
+                return center;
+            }
+
+            let ns_to_m = 1e-9 * SPEED_OF_LIGHT;
+            let x = antpos[i] * ns_to_m;
+            let y = antpos[n_known + i] * ns_to_m;
+            let z = antpos[2 * n_known + i] * ns_to_m;
+
+            [
+                center[0] + x * cos_lon - y * sin_lon,
+                center[1] + x * sin_lon + y * cos_lon,
+                center[2] + z,
+            ]
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TableOpenMode;
+    use rubbl_miriad::{visdata::encode_baseline, Type};
+    use tempfile::tempdir;
+
+    /// Write a small UV data set: one baseline observed in two linear
+    /// polarizations at two times, with two channels.
+    fn write_uv(path: &Path) {
+        std::fs::create_dir(path).unwrap();
+        std::fs::write(path.join("header"), b"").unwrap();
+
+        let mut ds = DataSet::open(path).unwrap();
+        let mut enc = ds
+            .new_uv(&[
+                (Type::Float64, "coord"),
+                (Type::Float64, "time"),
+                (Type::Float32, "baseline"),
+                (Type::Int32, "pol"),
+                (Type::Float32, "corr"),
+                (Type::Float32, "inttime"),
+                (Type::Int32, "nspect"),
+                (Type::Int32, "nschan"),
+                (Type::Float64, "sfreq"),
+                (Type::Float64, "sdf"),
+                (Type::Text, "source"),
+                (Type::Float64, "ra"),
+                (Type::Float64, "dec"),
+                (Type::Int32, "nants"),
+                (Type::Float64, "antpos"),
+                (Type::Text, "telescop"),
+            ])
+            .unwrap();
+
+        enc.write_scalar("inttime", 10f32).unwrap();
+        enc.write_scalar("nspect", 1i32).unwrap();
+        enc.write_scalar("nschan", 2i32).unwrap();
+        enc.write_scalar("sfreq", 1.4f64).unwrap();
+        enc.write_scalar("sdf", 0.001f64).unwrap();
+        enc.write_scalar("source", "SRC".to_owned()).unwrap();
+        enc.write_scalar("ra", 1f64).unwrap();
+        enc.write_scalar("dec", -0.5f64).unwrap();
+        enc.write_scalar("nants", 2i32).unwrap();
+        enc.write("antpos", &[0., 100., 0., 0., 0., 0.]).unwrap();
+        enc.write_scalar("telescop", "ATCA".to_owned()).unwrap();
+        enc.write_scalar("baseline", encode_baseline(0, 1).unwrap())
+            .unwrap();
+
+        for (i, jd) in [2_460_000.5, 2_460_000.5 + 10. / 86400.].iter().enumerate() {
+            for (j, pol) in [-5i32, -6].iter().enumerate() {
+                let v = (2 * i + j) as f32;
+                enc.write("coord", &[1., 2., 3.]).unwrap();
+                enc.write_scalar("time", *jd).unwrap();
+                enc.write_scalar("pol", *pol).unwrap();
+                enc.write("corr", &[v, 0., v, 1.]).unwrap();
+                enc.finish_record().unwrap();
+            }
+        }
+
+        enc.flush(&mut ds).unwrap();
+    }
+
+    #[test]
+    fn convert() {
+        let tmp_dir = tempdir().unwrap();
+        let uv_path = tmp_dir.path().join("vis.uv");
+        let ms_path = tmp_dir.path().join("vis.ms");
+        write_uv(&uv_path);
+
+        let summary = miriad_to_ms(&uv_path, &ms_path).unwrap();
+        assert_eq!(
+            summary,
+            MiriadConversionSummary {
+                n_records: 4,
+                n_rows: 2,
+                n_antennas: 2,
+                n_fields: 1,
+                n_spws: 1,
+            }
+        );
+
+        let mut main = Table::open(&ms_path, TableOpenMode::Read).unwrap();
+        assert_eq!(main.n_rows(), 2);
+
+        let times: Vec<f64> = main.get_col_as_vec("TIME").unwrap();
+        assert_eq!(times[0], 60000. * SECONDS_PER_DAY);
+        assert!((times[1] - times[0] - 10.).abs() < 1e-3);
+
+        let uvw: Vec<f64> = main.get_cell("UVW", 1).unwrap();
+        assert!((uvw[2] - 3e-9 * SPEED_OF_LIGHT).abs() < 1e-9);
+
+        let data: Array2<Complex<f32>> = main.get_cell("DATA", 1).unwrap();
+        assert_eq!(
+            data,
+            ndarray::array![
+                [Complex::new(2., 0.), Complex::new(3., 0.)],
+                [Complex::new(2., 1.), Complex::new(3., 1.)]
+            ]
+        );
+
+        let mut pol = Table::open(ms_path.join("POLARIZATION"), TableOpenMode::Read).unwrap();
+        let corr_type: Vec<i32> = pol.get_cell("CORR_TYPE", 0).unwrap();
+        assert_eq!(corr_type, vec![9, 12]);
+
+        let mut spw = Table::open(ms_path.join("SPECTRAL_WINDOW"), TableOpenMode::Read).unwrap();
+        let freqs: Vec<f64> = spw.get_cell("CHAN_FREQ", 0).unwrap();
+        assert_eq!(freqs.len(), 2);
+        assert!((freqs[0] - 1.4e9).abs() < 1.);
+
+        let mut ants = Table::open(ms_path.join("ANTENNA"), TableOpenMode::Read).unwrap();
+        let p0: Vec<f64> = ants.get_cell("POSITION", 0).unwrap();
+        let p1: Vec<f64> = ants.get_cell("POSITION", 1).unwrap();
+        assert!((p1[0] - p0[0] - 100e-9 * SPEED_OF_LIGHT).abs() < 1e-6);
+
+        let mut field = Table::open(ms_path.join("FIELD"), TableOpenMode::Read).unwrap();
+        let name: String = field.get_cell("NAME", 0).unwrap();
+        assert_eq!(name, "SRC");
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Higher-level helpers for tables following the [Measurement Set][MS] data
+//! model.
+//!
+//! [MS]: https://casa.nrao.edu/Memos/229.html
+//!
+//! The rest of this crate deals with CASA tables generically. The functions in
+//! this module know about the conventional layout of a Measurement Set: a main
+//! table of visibility data whose rows are indexed by `TIME`, `ANTENNA1`,
+//! `ANTENNA2`, and so on, plus sub-tables such as `SPECTRAL_WINDOW` that are
+//! attached to the main table as table-type keywords.
+//!
+//! Helpers that work on a whole data set, such as [`summary`] or
+//! [`JoinedReader`], assume that each sub-table lives in a directory inside
+//! the main table directory named after its keyword, as is conventional.
+
+mod antenna;
+#[cfg(feature = "aoflagger")]
+mod aoflagger;
+mod autos;
+mod bench;
+mod checksum;
+mod compare;
+#[cfg(any(feature = "fitsidi", feature = "miriad"))]
+mod convert;
+mod delays;
+pub mod dysco;
+mod feed;
+#[cfg(feature = "fitsidi")]
+mod fitsidi;
+mod flagging;
+mod gridder;
+mod history;
+mod index;
+mod join;
+#[cfg(feature = "metafits")]
+mod metafits;
+#[cfg(feature = "miriad")]
+mod miriad;
+mod model_data;
+mod msv4;
+mod ordering;
+mod partition;
+mod scans;
+pub mod schema;
+mod selection;
+mod shrink;
+pub(crate) mod simulate;
+mod spw;
+mod stream;
+mod subtables;
+mod summary;
+mod sumthreshold;
+mod syscal;
+mod waterfall;
+mod weather;
+
+pub use self::antenna::{read_antenna_layout, write_antenna_layout};
+#[cfg(feature = "aoflagger")]
+pub use self::aoflagger::{AoFlagger, AoFlaggerError};
+pub use self::autos::{extract_autos, AutoSpectra, AutoSpectraError};
+pub use self::bench::{
+    run_write_bench, WriteBenchError, WriteBenchOptions, WriteBenchResult, WritePattern,
+};
+pub use self::checksum::{
+    verify_row_checksums, ChecksumError, ChecksumReport, CHECKSUM_COLUMNS_KEYWORD,
+    CHECKSUM_START_ROW_KEYWORD,
+};
+pub use self::compare::{compare_data, CompareError, DiffStats};
+pub use self::delays::{apply_delays, DelayError};
+pub use self::feed::Feed;
+#[cfg(feature = "fitsidi")]
+pub use self::fitsidi::{fitsidi_to_ms, FitsIdiConversionError, FitsIdiConversionSummary};
+pub use self::flagging::{
+    run_flagger, FlaggerBackend, FlaggingError, FlaggingOptions, FlaggingSummary,
+};
+pub use self::gridder::{
+    gridder_feed, GridderChunk, GridderFeed, GridderFeedError, GridderFeedOptions,
+};
+pub use self::index::{BaselineIndex, IndexError, TimeIndex};
+pub use self::join::{FieldInfo, JoinError, JoinedReader, JoinedRow, PolarizationInfo};
+#[cfg(feature = "metafits")]
+pub use self::metafits::{
+    create_ms_from_metafits, metafits_simulation_spec, MetafitsConversionError,
+};
+#[cfg(feature = "miriad")]
+pub use self::miriad::{miriad_to_ms, MiriadConversionError, MiriadConversionSummary};
+pub use self::model_data::{ensure_model_data, ColumnInit, ModelDataInit};
+pub use self::msv4::{
+    export_msv4, Msv4ExportError, Msv4ExportOptions, Msv4ExportSummary, Msv4Partition,
+};
+pub use self::ordering::{
+    check_ordering, OrderingReport, OrderingViolation, OrderingViolationKind,
+};
+pub use self::partition::{spw_partitions, SpwPartition};
+pub use self::scans::{assign_scans, AssignedScan, ScanAssignment, ScanError, DEFAULT_OBS_MODE};
+pub use self::selection::{MsSelection, MsSelectionError, ResolvedSelection};
+pub use self::shrink::{shrink_ms, ShrinkOptions, ShrinkSummary};
+pub use self::simulate::{
+    baseline_uvw, direction_cosines, earth_rotation_angle, simulate, PointSource, SimulationError,
+    SimulationSpec,
+};
+pub use self::spw::{Sideband, SpectralWindow, SpectralWindowError, FREQ_REF_TOPO};
+pub use self::stream::{BackgroundStreamWriter, StreamError, StreamQueueStats, StreamWriter};
+pub use self::summary::{
+    summary, AntennaSummary, FieldSummary, ObservationInfo, ObservationSummary, ScanSummary,
+    SpwSummary,
+};
+pub use self::sumthreshold::SumThresholdFlagger;
+pub use self::syscal::{open_or_create_syscal, SysCal, SysCalValues};
+pub use self::waterfall::{waterfall, Waterfall, WaterfallError};
+pub use self::weather::{read_weather, write_weather, Weather};
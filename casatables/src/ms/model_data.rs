@@ -0,0 +1,279 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Setting up the `MODEL_DATA` and `CORRECTED_DATA` columns.
+//!
+//! Imagers and calibrators such as WSClean expect these columns to exist
+//! alongside `DATA`, with the same cell shapes, and to be stored with a tiled
+//! storage manager rather than the default `StandardStMan`. Creating them by
+//! hand means getting the storage manager, tile shape, and initial contents
+//! right, which [`ensure_model_data`] takes care of.
+
+use crate::{
+    glue, io_stats::IoDirection, ColumnShapeInfo, Complex, GlueDataType, Table, TableError,
+    UnexpectedDataTypeError,
+};
+
+/// The number of bytes of visibility data to process per chunk of rows.
+const CHUNK_BYTES: usize = 16 * 1024 * 1024;
+
+/// The target size of a tile of the new columns, in bytes.
+const TILE_BYTES: usize = 1024 * 1024;
+
+/// How to fill a newly created visibility column.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ColumnInit {
+    /// Fill the column with zeros.
+    Zeros,
+
+    /// Copy the contents of the `DATA` column.
+    CopyData,
+}
+
+/// The columns that [`ensure_model_data`] should set up.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ModelDataInit {
+    /// How to fill `MODEL_DATA`, if it is created. The default is
+    /// [`ColumnInit::Zeros`].
+    pub model_data: ColumnInit,
+
+    /// Whether to create `CORRECTED_DATA` too, and if so, how to fill it.
+    /// The default is `None`; WSClean and CASA conventionally initialize it
+    /// with [`ColumnInit::CopyData`].
+    pub corrected_data: Option<ColumnInit>,
+}
+
+impl Default for ModelDataInit {
+    fn default() -> Self {
+        ModelDataInit {
+            model_data: ColumnInit::Zeros,
+            corrected_data: None,
+        }
+    }
+}
+
+/// Make sure that a Measurement Set has a `MODEL_DATA` column, and optionally
+/// a `CORRECTED_DATA` column.
+///
+/// Each requested column that does not already exist is created with the
+/// same cell shape as `DATA`, which must hold single-precision complex
+/// values, and stored with its own `TiledShapeStMan`, following the CASA
+/// conventions. Every row is then initialized as specified by *init*, a
+/// chunk of rows at a time, so that the whole column never has to fit in
+/// memory. Existing columns are left untouched. Returns the names of the
+/// columns that were created.
+///
+/// ```no_run
+/// use rubbl_casatables::{Table, TableOpenMode};
+/// use rubbl_casatables::ms::{ensure_model_data, ColumnInit, ModelDataInit};
+///
+/// let mut t = Table::open("vis.ms", TableOpenMode::ReadWrite).unwrap();
+/// let init = ModelDataInit {
+///     corrected_data: Some(ColumnInit::CopyData),
+///     ..Default::default()
+/// };
+/// let created = ensure_model_data(&mut t, &init).unwrap();
+/// println!("created: {:?}", created);
+/// ```
+pub fn ensure_model_data(
+    table: &mut Table,
+    init: &ModelDataInit,
+) -> Result<Vec<String>, TableError> {
+    let mut wanted = vec![("MODEL_DATA", "ModelTiled", init.model_data)];
+
+    if let Some(corrected) = init.corrected_data {
+        wanted.push(("CORRECTED_DATA", "CorrectedTiled", corrected));
+    }
+
+    let existing = table.column_names()?;
+    let mut created = Vec::new();
+
+    for (col_name, dm_name, col_init) in wanted {
+        if existing.iter().any(|c| c == col_name) {
+            continue;
+        }
+
+        add_column_like_data(table, col_name, dm_name)?;
+        fill_column(table, col_name, col_init)?;
+        created.push(col_name.to_owned());
+    }
+
+    Ok(created)
+}
+
+/// Add a tiled column with the same cell shape as `DATA`.
+fn add_column_like_data(
+    table: &mut Table,
+    col_name: &str,
+    dm_name: &str,
+) -> Result<(), TableError> {
+    let data_type = table.get_col_desc("DATA")?.data_type();
+
+    if data_type != GlueDataType::TpComplex {
+        return Err(UnexpectedDataTypeError(GlueDataType::TpComplex, data_type).into());
+    }
+
+    let (fixed, shape) = match table.column_shape("DATA")? {
+        ColumnShapeInfo::Fixed(shape) => (true, shape),
+
+        // For variable-shape columns, use the first cell to size the tiles.
+        other => {
+            let shape = if table.n_rows() > 0 {
+                table.get_cell_shape("DATA", 0)?
+            } else {
+                vec![1; other.ndim().unwrap_or(2)]
+            };
+            (false, shape)
+        }
+    };
+
+    let cell_bytes = shape.iter().product::<usize>().max(1) * std::mem::size_of::<Complex<f32>>();
+    let mut tile_shape = vec![(TILE_BYTES / cell_bytes).max(1) as u64];
+    tile_shape.extend(shape.iter().map(|d| *d as u64));
+    let shape: Vec<u64> = shape.iter().map(|d| *d as u64).collect();
+
+    table.add_tiled_array_column(
+        GlueDataType::TpComplex,
+        col_name,
+        if fixed { Some(&shape[..]) } else { None },
+        &tile_shape,
+        dm_name,
+    )
+}
+
+/// Initialize every row of a newly created column.
+fn fill_column(table: &mut Table, col_name: &str, init: ColumnInit) -> Result<(), TableError> {
+    let n_rows = table.n_rows();
+    let data_name = glue::StringBridge::from_rust("DATA");
+    let ccol_name = glue::StringBridge::from_rust(col_name);
+    let mut buf: Vec<Complex<f32>> = Vec::new();
+
+    // Work through runs of rows whose `DATA` cells have the same shape. For
+    // fixed-shape columns, there is just one run per chunk.
+    let fixed_shape = match table.column_shape("DATA")? {
+        ColumnShapeInfo::Fixed(shape) => Some(shape),
+        _ => None,
+    };
+
+    let mut row = 0;
+
+    while row < n_rows {
+        let shape = match fixed_shape {
+            Some(ref s) => s.clone(),
+            None => table.get_cell_shape("DATA", row)?,
+        };
+        let cell_len = shape.iter().product::<usize>();
+        let max_rows = (CHUNK_BYTES / (cell_len.max(1) * 8)).max(1) as u64;
+        let mut n = 1;
+
+        while n < max_rows && row + n < n_rows {
+            if fixed_shape.is_none() && table.get_cell_shape("DATA", row + n)? != shape {
+                break;
+            }
+
+            n += 1;
+        }
+
+        buf.clear();
+        buf.resize(n as usize * cell_len, Complex::new(0., 0.));
+
+        if init == ColumnInit::CopyData {
+            if fixed_shape.is_some() {
+                if unsafe {
+                    glue::table_get_column_range(
+                        table.handle,
+                        &data_name,
+                        row,
+                        n,
+                        buf.as_mut_ptr() as _,
+                        &mut table.exc_info,
+                    )
+                } != 0
+                {
+                    return table.exc_info.as_err();
+                }
+
+                table.record_io(
+                    IoDirection::Get,
+                    "DATA",
+                    n,
+                    GlueDataType::TpComplex,
+                    buf.len() as u64,
+                );
+            } else {
+                // Bulk reads need a fixed shape, so go cell by cell.
+                for (i, cell) in buf.chunks_mut(cell_len.max(1)).enumerate() {
+                    let data: Vec<Complex<f32>> = table.get_cell_as_vec("DATA", row + i as u64)?;
+                    cell.copy_from_slice(&data);
+                }
+            }
+        }
+
+        let dims: Vec<std::os::raw::c_ulong> = shape.iter().map(|d| *d as _).collect();
+
+        if unsafe {
+            glue::table_put_column_range_shaped(
+                table.handle,
+                &ccol_name,
+                row,
+                n,
+                dims.len() as _,
+                dims.as_ptr(),
+                buf.as_ptr() as _,
+                &mut table.exc_info,
+            )
+        } != 0
+        {
+            return table.exc_info.as_err();
+        }
+
+        table.record_io(
+            IoDirection::Put,
+            col_name,
+            n,
+            GlueDataType::TpComplex,
+            buf.len() as u64,
+        );
+        row += n;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn create_model_and_corrected() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec::default();
+        let mut t = synthetic_ms(tmp_dir.path().join("test.ms"), &spec).unwrap();
+
+        let init = ModelDataInit {
+            corrected_data: Some(ColumnInit::CopyData),
+            ..Default::default()
+        };
+        assert_eq!(
+            ensure_model_data(&mut t, &init).unwrap(),
+            vec!["MODEL_DATA".to_owned(), "CORRECTED_DATA".to_owned()]
+        );
+
+        let (_, dm_type, dm_name) = t.column_data_manager("MODEL_DATA").unwrap();
+        assert_eq!(dm_type, "TiledShapeStMan");
+        assert_eq!(dm_name, "ModelTiled");
+
+        let last = spec.n_rows() as u64 - 1;
+        let model: Vec<Complex<f32>> = t.get_cell_as_vec("MODEL_DATA", last).unwrap();
+        assert_eq!(model.len(), spec.n_chans * spec.n_pols);
+        assert!(model.iter().all(|v| *v == Complex::new(0., 0.)));
+
+        let corrected: Vec<Complex<f32>> = t.get_cell_as_vec("CORRECTED_DATA", last).unwrap();
+        assert_eq!(corrected[5], spec.expected_vis(last as usize, 1, 1));
+
+        // Running again is a no-op.
+        assert!(ensure_model_data(&mut t, &init).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,733 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Exporting Measurement Sets to the MSv4 processing-set layout.
+//!
+//! Version 4 of the Measurement Set data model, as implemented by the
+//! [xradio] package, stores visibilities as a *processing set*: a collection
+//! of xarray datasets, one per partition of the data, saved as [Zarr]
+//! groups. Each partition holds a single spectral window and field, so that
+//! its visibilities form a regular hypercube with dimensions `time`,
+//! `baseline_id`, `frequency`, and `polarization`.
+//!
+//! [`export_msv4`] converts a classic (v2) Measurement Set into this layout.
+//! It writes the core of the schema — the `VISIBILITY`, `FLAG`, `WEIGHT`,
+//! and `UVW` data variables, their coordinates, and an `antenna_xds` group
+//! per partition — as uncompressed Zarr version 2 arrays, with the
+//! `_ARRAY_DIMENSIONS` attributes that xarray uses to name dimensions. The
+//! MSv4 schema is still evolving, and optional parts of it, such as the
+//! pointing and weather datasets, are not written.
+//!
+//! [xradio]: https://github.com/casangi/xradio
+//! [Zarr]: https://zarr-specs.readthedocs.io/en/latest/v2/v2.0.html
+
+use rubbl_core::{array_layout::AntennaLayout, time::mjd_seconds_to_unix};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+use super::{subtables::read_antennas, JoinError, JoinedReader};
+use crate::{json::quote, Complex, TableError};
+
+/// The default target size of a chunk of the `VISIBILITY` array, in bytes.
+const DEFAULT_CHUNK_BYTES: usize = 64 * 1024 * 1024;
+
+/// An error that can occur when exporting to the MSv4 layout.
+#[derive(Error, Debug)]
+pub enum Msv4ExportError {
+    /// An error occurred while resolving the sub-tables of the input.
+    #[error(transparent)]
+    Join(#[from] JoinError),
+
+    /// An error occurred while reading the input.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// An error occurred while writing the output.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The output path already exists.
+    #[error("output path {0:?} already exists")]
+    OutputExists(PathBuf),
+
+    /// A cell of the input has a shape that does not match its spectral
+    /// window and polarization setup.
+    #[error("row {row} of column {column} has {actual} elements, but {expected} were expected")]
+    BadCellShape {
+        /// The row of the main table.
+        row: u64,
+        /// The name of the column.
+        column: String,
+        /// The expected number of elements.
+        expected: usize,
+        /// The actual number of elements.
+        actual: usize,
+    },
+}
+
+/// Options for [`export_msv4`].
+#[derive(Clone, Debug)]
+pub struct Msv4ExportOptions {
+    /// The main-table column from which to take the visibilities. It must
+    /// hold single-precision complex data. The default is `DATA`.
+    pub data_column: String,
+
+    /// The number of timesteps per chunk of the data arrays. If `None`, the
+    /// default, the number is chosen to make chunks of the `VISIBILITY`
+    /// array about 64 MiB in size.
+    pub time_chunk: Option<usize>,
+}
+
+impl Default for Msv4ExportOptions {
+    fn default() -> Self {
+        Msv4ExportOptions {
+            data_column: "DATA".to_owned(),
+            time_chunk: None,
+        }
+    }
+}
+
+/// A description of one partition written by [`export_msv4`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Msv4Partition {
+    /// The name of the partition’s group within the processing set.
+    pub name: String,
+
+    /// The `DATA_DESC_ID` of the partition’s rows.
+    pub data_desc_id: usize,
+
+    /// The `FIELD_ID` of the partition’s rows.
+    pub field_id: usize,
+
+    /// The number of timesteps.
+    pub n_times: usize,
+
+    /// The number of baselines.
+    pub n_baselines: usize,
+
+    /// The number of frequency channels.
+    pub n_chans: usize,
+
+    /// The number of polarization products.
+    pub n_pols: usize,
+
+    /// The number of main-table rows exported.
+    pub n_rows: u64,
+}
+
+/// A summary of an export done by [`export_msv4`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Msv4ExportSummary {
+    /// The partitions written, in order of `DATA_DESC_ID` and then
+    /// `FIELD_ID`.
+    pub partitions: Vec<Msv4Partition>,
+}
+
+/// Convert a Measurement Set into an MSv4 processing set.
+///
+/// The processing set is written to a new directory at *out_path*. The rows
+/// of the input are partitioned by `DATA_DESC_ID` — that is, by spectral
+/// window and polarization setup — and by `FIELD_ID`, and each partition
+/// becomes a Zarr group named after the input with a numeric suffix. Within
+/// a partition, baselines are ordered by `(ANTENNA1, ANTENNA2)` and times
+/// are converted to Unix seconds. Cells with no corresponding input row are
+/// flagged, with NaN visibilities and zero weight. Row weights are
+/// broadcast across frequency.
+///
+/// ```no_run
+/// use rubbl_casatables::ms::{export_msv4, Msv4ExportOptions};
+///
+/// let summary = export_msv4("vis.ms", "vis.ps", &Msv4ExportOptions::default()).unwrap();
+/// println!("wrote {} partitions", summary.partitions.len());
+/// ```
+pub fn export_msv4<P1: AsRef<Path>, P2: AsRef<Path>>(
+    ms_path: P1,
+    out_path: P2,
+    options: &Msv4ExportOptions,
+) -> Result<Msv4ExportSummary, Msv4ExportError> {
+    let ms_path = ms_path.as_ref();
+    let out_path = out_path.as_ref();
+
+    if out_path.exists() {
+        return Err(Msv4ExportError::OutputExists(out_path.to_owned()));
+    }
+
+    let mut reader = JoinedReader::open(ms_path)?;
+    let antennas = read_antennas(ms_path)?;
+
+    // Sort the rows into partitions.
+
+    let mut partitions: BTreeMap<(usize, usize), Vec<u64>> = BTreeMap::new();
+
+    for row in reader.rows() {
+        partitions
+            .entry((row.data_desc_id, row.field.id))
+            .or_default()
+            .push(row.row);
+    }
+
+    let stem = ms_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "ms".to_owned());
+
+    fs::create_dir_all(out_path)?;
+    write_group(out_path, "{\"type\":\"processing_set\"}")?;
+
+    let mut summary = Msv4ExportSummary::default();
+
+    for (i, ((data_desc_id, field_id), rows)) in partitions.into_iter().enumerate() {
+        let name = format!("{}_{}", stem, i);
+        let part = export_partition(
+            &mut reader,
+            &antennas,
+            &out_path.join(&name),
+            name,
+            data_desc_id,
+            field_id,
+            &rows,
+            options,
+        )?;
+        summary.partitions.push(part);
+    }
+
+    Ok(summary)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn export_partition(
+    reader: &mut JoinedReader,
+    antennas: &AntennaLayout,
+    dir: &Path,
+    name: String,
+    data_desc_id: usize,
+    field_id: usize,
+    rows: &[u64],
+    options: &Msv4ExportOptions,
+) -> Result<Msv4Partition, Msv4ExportError> {
+    // Gather the coordinates.
+
+    let first = reader.row(rows[0]).unwrap();
+    let spw = first.spectral_window.clone();
+    let pol_labels = first.polarization.labels();
+    let field_name = first.field.name.clone();
+    let n_chans = spw.n_chans();
+    let n_pols = pol_labels.len();
+
+    let mut times: Vec<f64> = rows.iter().map(|r| reader.row(*r).unwrap().time).collect();
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    times.dedup();
+
+    let mut baselines: Vec<(i32, i32)> = rows
+        .iter()
+        .map(|r| {
+            let row = reader.row(*r).unwrap();
+            (row.antenna1, row.antenna2)
+        })
+        .collect();
+    baselines.sort_unstable();
+    baselines.dedup();
+
+    let n_times = times.len();
+    let n_bl = baselines.len();
+    let bl_index: BTreeMap<(i32, i32), usize> =
+        baselines.iter().enumerate().map(|(i, b)| (*b, i)).collect();
+
+    // Order the rows by where they go in the hypercube.
+
+    let mut placed: Vec<(usize, usize, u64)> = rows
+        .iter()
+        .map(|r| {
+            let row = reader.row(*r).unwrap();
+            let t = times
+                .binary_search_by(|x| x.partial_cmp(&row.time).unwrap())
+                .unwrap();
+            (t, bl_index[&(row.antenna1, row.antenna2)], *r)
+        })
+        .collect();
+    placed.sort_unstable();
+
+    // Write the group, its coordinates, and its antenna dataset.
+
+    fs::create_dir_all(dir)?;
+    write_group(
+        dir,
+        &format!(
+            "{{\"type\":\"visibility\",\"data_groups\":{{\"base\":{{\
+             \"correlated_data\":\"VISIBILITY\",\"flag\":\"FLAG\",\
+             \"weight\":\"WEIGHT\",\"uvw\":\"UVW\"}}}},\
+             \"partition_info\":{{\"spectral_window_name\":{},\
+             \"field_name\":[{}],\"data_description_id\":{},\"field_id\":[{}],\
+             \"polarization_setup\":[{}]}}}}",
+            quote(&spw.name),
+            quote(&field_name),
+            data_desc_id,
+            field_id,
+            pol_labels
+                .iter()
+                .map(|l| quote(l))
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+    )?;
+
+    let unix_times: Vec<f64> = times.iter().map(|t| mjd_seconds_to_unix(*t)).collect();
+    write_coordinate(
+        dir,
+        "time",
+        &unix_times,
+        "{\"type\":\"time\",\"units\":[\"s\"],\"scale\":\"utc\",\"format\":\"unix\"}",
+    )?;
+
+    let baseline_ids: Vec<i64> = (0..n_bl as i64).collect();
+    write_coordinate(dir, "baseline_id", &baseline_ids, "{}")?;
+
+    let ant_name = |id: i32| {
+        antennas
+            .names
+            .get(id as usize)
+            .cloned()
+            .unwrap_or_else(|| id.to_string())
+    };
+    let names1: Vec<String> = baselines.iter().map(|b| ant_name(b.0)).collect();
+    let names2: Vec<String> = baselines.iter().map(|b| ant_name(b.1)).collect();
+    write_string_array(dir, "baseline_antenna1_name", "baseline_id", &names1)?;
+    write_string_array(dir, "baseline_antenna2_name", "baseline_id", &names2)?;
+
+    write_coordinate(
+        dir,
+        "frequency",
+        &spw.chan_freq,
+        &format!(
+            "{{\"type\":\"spectral_coord\",\"units\":[\"Hz\"],\"spectral_window_name\":{},\
+             \"reference_frequency\":{}}}",
+            quote(&spw.name),
+            json_number(spw.ref_frequency),
+        ),
+    )?;
+    write_string_array(dir, "polarization", "polarization", &pol_labels)?;
+    write_string_array(
+        dir,
+        "uvw_label",
+        "uvw_label",
+        &["u".to_owned(), "v".to_owned(), "w".to_owned()],
+    )?;
+
+    let ant_dir = dir.join("antenna_xds");
+    fs::create_dir_all(&ant_dir)?;
+    write_group(&ant_dir, "{\"type\":\"antenna\"}")?;
+    write_string_array(&ant_dir, "antenna_name", "antenna_name", &antennas.names)?;
+    write_string_array(
+        &ant_dir,
+        "cartesian_pos_label",
+        "cartesian_pos_label",
+        &["x".to_owned(), "y".to_owned(), "z".to_owned()],
+    )?;
+    let n_ants = antennas.names.len();
+    let pos_array = ZarrArray::<f64>::create(
+        &ant_dir,
+        "ANTENNA_POSITION",
+        &[n_ants, 3],
+        &[n_ants.max(1), 3],
+        &["antenna_name", "cartesian_pos_label"],
+        "{\"type\":\"location\",\"units\":[\"m\",\"m\",\"m\"],\"frame\":\"ITRS\"}",
+    )?;
+    let positions: Vec<f64> = antennas.positions.iter().flatten().copied().collect();
+    pos_array.write_chunk(0, &positions)?;
+
+    // Finally, the data variables, a chunk of timesteps at a time.
+
+    let cell_len = n_chans * n_pols;
+    let time_chunk = options
+        .time_chunk
+        .unwrap_or_else(|| DEFAULT_CHUNK_BYTES / (8 * n_bl * cell_len).max(1))
+        .max(1)
+        .min(n_times.max(1));
+    let dims4 = ["time", "baseline_id", "frequency", "polarization"];
+
+    let vis_array = ZarrArray::<Complex<f32>>::create(
+        dir,
+        "VISIBILITY",
+        &[n_times, n_bl, n_chans, n_pols],
+        &[time_chunk, n_bl, n_chans, n_pols],
+        &dims4,
+        "{\"type\":\"quantity\",\"units\":[\"Jy\"]}",
+    )?;
+    let flag_array = ZarrArray::<bool>::create(
+        dir,
+        "FLAG",
+        &[n_times, n_bl, n_chans, n_pols],
+        &[time_chunk, n_bl, n_chans, n_pols],
+        &dims4,
+        "{}",
+    )?;
+    let weight_array = ZarrArray::<f32>::create(
+        dir,
+        "WEIGHT",
+        &[n_times, n_bl, n_chans, n_pols],
+        &[time_chunk, n_bl, n_chans, n_pols],
+        &dims4,
+        "{}",
+    )?;
+    let uvw_array = ZarrArray::<f64>::create(
+        dir,
+        "UVW",
+        &[n_times, n_bl, 3],
+        &[time_chunk, n_bl, 3],
+        &["time", "baseline_id", "uvw_label"],
+        "{\"type\":\"uvw\",\"units\":[\"m\",\"m\",\"m\"],\"frame\":\"fk5\"}",
+    )?;
+
+    let nan = Complex::new(f32::NAN, f32::NAN);
+    let n_cells = time_chunk * n_bl;
+    let mut vis = vec![nan; n_cells * cell_len];
+    let mut flags = vec![true; n_cells * cell_len];
+    let mut weights = vec![0f32; n_cells * cell_len];
+    let mut uvw = vec![f64::NAN; n_cells * 3];
+    let mut next = 0;
+
+    for (chunk_index, t0) in (0..n_times).step_by(time_chunk).enumerate() {
+        vis.iter_mut().for_each(|x| *x = nan);
+        flags.iter_mut().for_each(|x| *x = true);
+        weights.iter_mut().for_each(|x| *x = 0.);
+        uvw.iter_mut().for_each(|x| *x = f64::NAN);
+
+        while next < placed.len() && placed[next].0 < t0 + time_chunk {
+            let (t, bl, row) = placed[next];
+            let cell = (t - t0) * n_bl + bl;
+            let table = reader.table();
+
+            let data: Vec<Complex<f32>> = table.get_cell_as_vec(&options.data_column, row)?;
+            check_len(row, &options.data_column, cell_len, data.len())?;
+            vis[cell * cell_len..(cell + 1) * cell_len].copy_from_slice(&data);
+
+            let flag: Vec<bool> = table.get_cell_as_vec("FLAG", row)?;
+            check_len(row, "FLAG", cell_len, flag.len())?;
+            flags[cell * cell_len..(cell + 1) * cell_len].copy_from_slice(&flag);
+
+            let weight: Vec<f32> = table.get_cell_as_vec("WEIGHT", row)?;
+            check_len(row, "WEIGHT", n_pols, weight.len())?;
+
+            for c in 0..n_chans {
+                let start = cell * cell_len + c * n_pols;
+                weights[start..start + n_pols].copy_from_slice(&weight);
+            }
+
+            let row_uvw: Vec<f64> = table.get_cell_as_vec("UVW", row)?;
+            check_len(row, "UVW", 3, row_uvw.len())?;
+            uvw[cell * 3..(cell + 1) * 3].copy_from_slice(&row_uvw);
+
+            next += 1;
+        }
+
+        // The last chunk may be partial, but Zarr chunks are always full
+        // size; the padding is ignored by readers.
+        vis_array.write_chunk(chunk_index, &vis)?;
+        flag_array.write_chunk(chunk_index, &flags)?;
+        weight_array.write_chunk(chunk_index, &weights)?;
+        uvw_array.write_chunk(chunk_index, &uvw)?;
+    }
+
+    Ok(Msv4Partition {
+        name,
+        data_desc_id,
+        field_id,
+        n_times,
+        n_baselines: n_bl,
+        n_chans,
+        n_pols,
+        n_rows: rows.len() as u64,
+    })
+}
+
+fn check_len(
+    row: u64,
+    column: &str,
+    expected: usize,
+    actual: usize,
+) -> Result<(), Msv4ExportError> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(Msv4ExportError::BadCellShape {
+            row,
+            column: column.to_owned(),
+            expected,
+            actual,
+        })
+    }
+}
+
+// Zarr version 2 output. Everything is written uncompressed, in C order and
+// little-endian byte order, with chunks split along the first axis only.
+
+/// A type that can be stored in a Zarr array.
+trait ZarrElement {
+    /// The Zarr (NumPy) data type string.
+    const DTYPE: &'static str;
+
+    /// Append the little-endian encoding of the value to *out*.
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+impl ZarrElement for bool {
+    const DTYPE: &'static str = "|b1";
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl ZarrElement for i64 {
+    const DTYPE: &'static str = "<i8";
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl ZarrElement for f32 {
+    const DTYPE: &'static str = "<f4";
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl ZarrElement for f64 {
+    const DTYPE: &'static str = "<f8";
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl ZarrElement for Complex<f32> {
+    const DTYPE: &'static str = "<c8";
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.re.to_le_bytes());
+        out.extend_from_slice(&self.im.to_le_bytes());
+    }
+}
+
+/// A Zarr array being written.
+struct ZarrArray<T> {
+    dir: PathBuf,
+    n_dims: usize,
+    _element: std::marker::PhantomData<T>,
+}
+
+impl<T: ZarrElement> ZarrArray<T> {
+    /// Create the array *name* in the group *group*, writing its metadata.
+    /// *attrs* is a JSON object of extra attributes.
+    fn create(
+        group: &Path,
+        name: &str,
+        shape: &[usize],
+        chunks: &[usize],
+        dims: &[&str],
+        attrs: &str,
+    ) -> Result<Self, io::Error> {
+        let dir = group.join(name);
+        fs::create_dir_all(&dir)?;
+
+        let list = |v: &[usize]| {
+            v.iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        fs::write(
+            dir.join(".zarray"),
+            format!(
+                "{{\"zarr_format\":2,\"shape\":[{}],\"chunks\":[{}],\"dtype\":\"{}\",\
+                 \"compressor\":null,\"fill_value\":null,\"order\":\"C\",\"filters\":null}}\n",
+                list(shape),
+                list(chunks),
+                T::DTYPE,
+            ),
+        )?;
+
+        // Merge the dimension names into the other attributes.
+        let dims = dims.iter().map(|d| quote(d)).collect::<Vec<_>>().join(",");
+        let rest = attrs.trim().trim_start_matches('{').trim_end_matches('}');
+        let sep = if rest.trim().is_empty() { "" } else { "," };
+        fs::write(
+            dir.join(".zattrs"),
+            format!("{{\"_ARRAY_DIMENSIONS\":[{}]{}{}}}\n", dims, sep, rest),
+        )?;
+
+        Ok(ZarrArray {
+            dir,
+            n_dims: shape.len(),
+            _element: std::marker::PhantomData,
+        })
+    }
+
+    /// Write chunk number *index* along the first axis.
+    fn write_chunk(&self, index: usize, data: &[T]) -> Result<(), io::Error> {
+        let mut key = index.to_string();
+
+        for _ in 1..self.n_dims {
+            key.push_str(".0");
+        }
+
+        let mut bytes = Vec::with_capacity(data.len() * 8);
+
+        for x in data {
+            x.encode(&mut bytes);
+        }
+
+        fs::write(self.dir.join(key), bytes)
+    }
+}
+
+/// Write a one-dimensional coordinate array, in a single chunk.
+fn write_coordinate<T: ZarrElement>(
+    group: &Path,
+    name: &str,
+    values: &[T],
+    attrs: &str,
+) -> Result<(), io::Error> {
+    let n = values.len();
+    let array = ZarrArray::<T>::create(group, name, &[n], &[n.max(1)], &[name], attrs)?;
+    array.write_chunk(0, values)
+}
+
+/// Write a one-dimensional array of strings, as fixed-width UTF-32.
+fn write_string_array(
+    group: &Path,
+    name: &str,
+    dim: &str,
+    values: &[String],
+) -> Result<(), io::Error> {
+    let width = values
+        .iter()
+        .map(|s| s.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let n = values.len();
+    let dir = group.join(name);
+    fs::create_dir_all(&dir)?;
+
+    fs::write(
+        dir.join(".zarray"),
+        format!(
+            "{{\"zarr_format\":2,\"shape\":[{}],\"chunks\":[{}],\"dtype\":\"<U{}\",\
+             \"compressor\":null,\"fill_value\":null,\"order\":\"C\",\"filters\":null}}\n",
+            n,
+            n.max(1),
+            width,
+        ),
+    )?;
+    fs::write(
+        dir.join(".zattrs"),
+        format!("{{\"_ARRAY_DIMENSIONS\":[{}]}}\n", quote(dim)),
+    )?;
+
+    let mut bytes = Vec::with_capacity(n.max(1) * width * 4);
+
+    for s in values {
+        let mut count = 0;
+
+        for c in s.chars() {
+            bytes.extend_from_slice(&(c as u32).to_le_bytes());
+            count += 1;
+        }
+
+        bytes.resize(bytes.len() + 4 * (width - count), 0);
+    }
+
+    fs::write(dir.join("0"), bytes)
+}
+
+/// Write the metadata of a Zarr group, with *attrs* as its attributes.
+fn write_group(dir: &Path, attrs: &str) -> Result<(), io::Error> {
+    fs::write(dir.join(".zgroup"), "{\"zarr_format\":2}\n")?;
+    fs::write(dir.join(".zattrs"), format!("{}\n", attrs))
+}
+
+/// Encode a number as JSON, which has no representation for NaN or the
+/// infinities.
+fn json_number(x: f64) -> String {
+    if x.is_finite() {
+        format!("{:?}", x)
+    } else {
+        "null".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn export_synthetic() {
+        let tmp_dir = tempdir().unwrap();
+        let ms_path = tmp_dir.path().join("test.ms");
+        let spec = SyntheticMsSpec {
+            n_timesteps: 5,
+            ..Default::default()
+        };
+        drop(synthetic_ms(&ms_path, &spec).unwrap());
+
+        let out = tmp_dir.path().join("test.ps");
+        let options = Msv4ExportOptions {
+            time_chunk: Some(2),
+            ..Default::default()
+        };
+        let summary = export_msv4(&ms_path, &out, &options).unwrap();
+        assert_eq!(summary.partitions.len(), 1);
+
+        let part = &summary.partitions[0];
+        assert_eq!(part.name, "test_0");
+        assert_eq!(part.n_times, 5);
+        assert_eq!(part.n_baselines, spec.n_baselines());
+        assert_eq!(part.n_chans, spec.n_chans);
+        assert_eq!(part.n_pols, spec.n_pols);
+
+        let vis_dir = out.join("test_0").join("VISIBILITY");
+        let meta = fs::read_to_string(vis_dir.join(".zarray")).unwrap();
+        assert!(meta.contains("\"shape\":[5,10,16,4]"));
+        assert!(meta.contains("\"chunks\":[2,10,16,4]"));
+
+        // The synthetic rows are already in (time, baseline) order, so each
+        // cell of the hypercube is the row with the same flat index. Check
+        // the first cell of the fourth timestep, the second in its chunk.
+        let chunk = fs::read(vis_dir.join("1.0.0.0")).unwrap();
+        assert_eq!(chunk.len(), 2 * 10 * 16 * 4 * 8);
+        let row = 3 * spec.n_baselines();
+        let offset = 10 * 16 * 4 * 8;
+        let re = f32::from_le_bytes([
+            chunk[offset],
+            chunk[offset + 1],
+            chunk[offset + 2],
+            chunk[offset + 3],
+        ]);
+        let im = f32::from_le_bytes([
+            chunk[offset + 4],
+            chunk[offset + 5],
+            chunk[offset + 6],
+            chunk[offset + 7],
+        ]);
+        assert_eq!(Complex::new(re, im), spec.expected_vis(row, 0, 0));
+
+        let pols = fs::read(out.join("test_0").join("polarization").join("0")).unwrap();
+        assert_eq!(pols.len(), 4 * 2 * 4);
+        assert!(out
+            .join("test_0")
+            .join("antenna_xds")
+            .join(".zgroup")
+            .exists());
+        assert!(export_msv4(&ms_path, &out, &options).is_err());
+    }
+}
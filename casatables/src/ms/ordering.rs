@@ -0,0 +1,252 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Checking the row ordering of a Measurement Set main table.
+//!
+//! Nothing in the Measurement Set specification requires the rows of the main
+//! table to be in any particular order, but almost every writer sorts them by
+//! time and then by baseline, and many readers quietly assume that they are.
+//! [`check_ordering`] verifies this assumption with one pass over the index
+//! columns.
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::{IndexRow, Table, TableError};
+
+/// The index columns scanned by [`check_ordering`].
+const INDEX_COLUMNS: &[&str] = &["TIME", "ANTENNA1", "ANTENNA2", "DATA_DESC_ID", "FIELD_ID"];
+
+/// A kind of row ordering violation.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum OrderingViolationKind {
+    /// The `TIME` value of the rows is smaller than that of the row before
+    /// them.
+    TimeDecreases,
+
+    /// Within a timestep, the rows are not in the same baseline order as in
+    /// earlier timesteps.
+    BaselineOrder,
+
+    /// Within a timestep, the same baseline and data description appear more
+    /// than once.
+    DuplicateBaseline,
+}
+
+/// A contiguous range of rows that violate the expected ordering.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OrderingViolation {
+    /// What is wrong with the rows.
+    pub kind: OrderingViolationKind,
+
+    /// The offending rows. For baseline violations, this is all of the rows
+    /// of the affected timestep.
+    pub rows: Range<u64>,
+}
+
+/// The result of [`check_ordering`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OrderingReport {
+    /// The number of rows checked.
+    pub n_rows: u64,
+
+    /// The number of timesteps: runs of consecutive rows with the same
+    /// `TIME`.
+    pub n_timesteps: u64,
+
+    /// The violations found, in row order.
+    pub violations: Vec<OrderingViolation>,
+}
+
+impl OrderingReport {
+    /// Return true if no violations were found.
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// The state of the checker for the timestep being scanned.
+struct Timestep {
+    start_row: u64,
+    time: f64,
+    last_rank: Option<usize>,
+    out_of_order: bool,
+    duplicated: bool,
+}
+
+struct Checker {
+    report: OrderingReport,
+    ranks: HashMap<(i32, i32, i32), usize>,
+    /// For each baseline rank, the number of the last timestep in which it
+    /// was seen, counting from 1.
+    last_seen: Vec<u64>,
+    current: Option<Timestep>,
+}
+
+impl Checker {
+    fn push(&mut self, kind: OrderingViolationKind, rows: Range<u64>) {
+        if let Some(last) = self.report.violations.last_mut() {
+            if last.kind == kind && last.rows.end == rows.start {
+                last.rows.end = rows.end;
+                return;
+            }
+        }
+
+        self.report
+            .violations
+            .push(OrderingViolation { kind, rows });
+    }
+
+    fn finish_timestep(&mut self, end_row: u64) {
+        if let Some(ts) = self.current.take() {
+            if ts.out_of_order {
+                self.push(OrderingViolationKind::BaselineOrder, ts.start_row..end_row);
+            }
+
+            if ts.duplicated {
+                self.push(
+                    OrderingViolationKind::DuplicateBaseline,
+                    ts.start_row..end_row,
+                );
+            }
+        }
+    }
+
+    fn row(&mut self, row: &IndexRow) {
+        let new_timestep = match self.current {
+            Some(ref ts) => ts.time != row.time,
+            None => true,
+        };
+
+        if new_timestep {
+            let prev_time = self.current.as_ref().map(|ts| ts.time);
+            self.finish_timestep(row.row);
+
+            if prev_time.map(|t| row.time < t).unwrap_or(false) {
+                self.push(OrderingViolationKind::TimeDecreases, row.row..row.row + 1);
+            }
+
+            self.report.n_timesteps += 1;
+            self.current = Some(Timestep {
+                start_row: row.row,
+                time: row.time,
+                last_rank: None,
+                out_of_order: false,
+                duplicated: false,
+            });
+        }
+
+        // Baselines are ranked in the order in which they are first seen.
+        // Within every timestep, ranks must then increase; baselines missing
+        // from some timesteps do not matter.
+        let next_rank = self.ranks.len();
+        let rank = *self
+            .ranks
+            .entry((row.data_desc_id, row.antenna1, row.antenna2))
+            .or_insert(next_rank);
+
+        if rank == self.last_seen.len() {
+            self.last_seen.push(0);
+        }
+
+        let ts = self.current.as_mut().unwrap();
+
+        if self.last_seen[rank] == self.report.n_timesteps {
+            ts.duplicated = true;
+        } else if ts.last_rank.map(|last| rank < last).unwrap_or(false) {
+            ts.out_of_order = true;
+        }
+
+        self.last_seen[rank] = self.report.n_timesteps;
+        ts.last_rank = Some(rank);
+        self.report.n_rows += 1;
+    }
+}
+
+/// Check that the rows of a Measurement Set main table are sorted by time,
+/// and by baseline within each timestep.
+///
+/// A timestep is a run of consecutive rows with the same `TIME`. The rows of
+/// each timestep must have a larger `TIME` than those of the previous one,
+/// and their baselines — here, `DATA_DESC_ID`, `ANTENNA1`, and `ANTENNA2`
+/// taken together — must appear in a consistent order: the order in which
+/// they first appear in the table. Timesteps may lack some baselines, but
+/// may not repeat any.
+///
+/// Only the index columns are read, in chunks sized with
+/// [`Table::suggest_chunk_rows`]. Consecutive offending rows are merged into
+/// a single [`OrderingViolation`].
+pub fn check_ordering(table: &mut Table) -> Result<OrderingReport, TableError> {
+    let rows_per_chunk = table.suggest_chunk_rows(INDEX_COLUMNS, None)?;
+    let mut checker = Checker {
+        report: OrderingReport::default(),
+        ranks: HashMap::new(),
+        last_seen: Vec::new(),
+        current: None,
+    };
+
+    table.for_each_index_row(rows_per_chunk, |row| checker.row(row))?;
+    let n_rows = checker.report.n_rows;
+    checker.finish_timestep(n_rows);
+    Ok(checker.report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn violations() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec {
+            n_ants: 3,
+            autocorrelations: false,
+            n_timesteps: 4,
+            ..Default::default()
+        };
+        let mut t = synthetic_ms(tmp_dir.path().join("test.ms"), &spec).unwrap();
+
+        // 3 baselines per timestep.
+        let report = check_ordering(&mut t).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.n_rows, 12);
+        assert_eq!(report.n_timesteps, 4);
+
+        // Swap two baselines in the second timestep.
+        t.put_cell("ANTENNA2", 3, &2i32).unwrap();
+        t.put_cell("ANTENNA2", 4, &1i32).unwrap();
+
+        // Move the last timestep before the third.
+        let t2: f64 = t.get_cell("TIME", 6).unwrap();
+        for row in 9..12 {
+            t.put_cell("TIME", row, &(t2 - 1.)).unwrap();
+        }
+
+        let report = check_ordering(&mut t).unwrap();
+        assert_eq!(
+            report.violations,
+            vec![
+                OrderingViolation {
+                    kind: OrderingViolationKind::BaselineOrder,
+                    rows: 3..6,
+                },
+                OrderingViolation {
+                    kind: OrderingViolationKind::TimeDecreases,
+                    rows: 9..10,
+                },
+            ]
+        );
+
+        // Duplicate a baseline in the first timestep.
+        t.put_cell("ANTENNA2", 1, &1i32).unwrap();
+        let report = check_ordering(&mut t).unwrap();
+        assert_eq!(
+            report.violations[0],
+            OrderingViolation {
+                kind: OrderingViolationKind::DuplicateBaseline,
+                rows: 0..3,
+            }
+        );
+    }
+}
@@ -0,0 +1,321 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Reading Measurement Sets one spectral window at a time.
+//!
+//! In a data set with several spectral windows, the `DATA` and `FLAG` cells
+//! of rows in different windows generally have different numbers of
+//! channels, so those columns cannot be read in bulk across the whole table.
+//! [`spw_partitions`] resolves each row’s `DATA_DESC_ID` into its spectral
+//! window and polarization setup and groups the rows accordingly. Within a
+//! partition every cell has the same shape, so each partition can be read
+//! like an ordinary table.
+
+use ndarray::{ArrayD, ArrayViewD};
+use rubbl_core::chunked::{ArrayChunkSink, ArrayCollector};
+use std::{collections::BTreeMap, path::Path};
+
+use super::{
+    join::check_key,
+    subtables::{read_data_descs, read_polarizations, read_spectral_windows},
+    JoinError, PolarizationInfo, SpectralWindow,
+};
+use crate::{glue, CasaScalarData, Table, TableError, UnexpectedDataTypeError};
+
+/// The rows of a Measurement Set that share a spectral window and
+/// polarization setup.
+///
+/// A partition only records which rows belong to it. Data are read when
+/// they are asked for, from the table that the partition was created from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpwPartition {
+    /// The row number of the spectral window in the `SPECTRAL_WINDOW`
+    /// table, i.e. its ID.
+    pub spectral_window_id: usize,
+
+    /// The spectral window.
+    pub spectral_window: SpectralWindow,
+
+    /// The polarization setup.
+    pub polarization: PolarizationInfo,
+
+    /// The `DATA_DESCRIPTION` rows that refer to this spectral window and
+    /// polarization setup. There is almost always exactly one.
+    pub data_desc_ids: Vec<usize>,
+
+    rows: Vec<u64>,
+}
+
+/// Partition the rows of a Measurement Set by spectral window.
+///
+/// The `DATA_DESCRIPTION`, `SPECTRAL_WINDOW`, and `POLARIZATION` sub-tables
+/// are loaded along with the `DATA_DESC_ID` column of the main table, and
+/// every row is assigned to the partition of its spectral window and
+/// polarization setup. Almost all data sets use one polarization setup, and
+/// so have one partition per spectral window. Partitions are ordered by
+/// spectral window ID and then polarization ID, and ones that would contain
+/// no rows are omitted.
+///
+/// ```no_run
+/// use rubbl_casatables::{Complex, Table, TableOpenMode};
+/// use rubbl_casatables::ms::spw_partitions;
+///
+/// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+///
+/// for part in spw_partitions(&mut t).unwrap() {
+///     let data = part.read_column::<Complex<f32>>(&mut t, "DATA").unwrap();
+///     println!(
+///         "spw {}: {} rows of {:?}",
+///         part.spectral_window_id,
+///         part.n_rows(),
+///         &data.shape()[1..]
+///     );
+/// }
+/// ```
+pub fn spw_partitions(table: &mut Table) -> Result<Vec<SpwPartition>, JoinError> {
+    let ms_path = table.file_name().map_err(TableError::from)?;
+    let ms_path = Path::new(&ms_path);
+
+    let spectral_windows = read_spectral_windows(ms_path)?;
+    let polarizations = read_polarizations(ms_path)?;
+
+    let data_descs = read_data_descs(ms_path, spectral_windows.len(), polarizations.len())?;
+
+    let data_desc_id: Vec<i32> = table.get_col_as_vec("DATA_DESC_ID")?;
+    let mut groups: BTreeMap<(usize, usize), (Vec<usize>, Vec<u64>)> = BTreeMap::new();
+
+    for (row, &dd) in data_desc_id.iter().enumerate() {
+        let dd = check_key(
+            row as u64,
+            "DATA_DESC_ID",
+            dd,
+            "DATA_DESCRIPTION",
+            data_descs.len(),
+        )?;
+        let (dd_ids, rows) = groups.entry(data_descs[dd]).or_default();
+
+        if !dd_ids.contains(&dd) {
+            dd_ids.push(dd);
+        }
+
+        rows.push(row as u64);
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|((spw, pol), (mut data_desc_ids, rows))| {
+            data_desc_ids.sort_unstable();
+
+            SpwPartition {
+                spectral_window_id: spw,
+                spectral_window: spectral_windows[spw].clone(),
+                polarization: polarizations[pol].clone(),
+                data_desc_ids,
+                rows,
+            }
+        })
+        .collect())
+}
+
+impl SpwPartition {
+    /// Get the numbers of the main-table rows in this partition, in
+    /// increasing order.
+    pub fn rows(&self) -> &[u64] {
+        &self.rows[..]
+    }
+
+    /// Get the number of rows in this partition.
+    pub fn n_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Get the expected shape of the `DATA` and `FLAG` cells of this
+    /// partition, `[n_chans, n_corrs]`.
+    pub fn data_shape(&self) -> [usize; 2] {
+        [
+            self.spectral_window.n_chans(),
+            self.polarization.corr_type.len(),
+        ]
+    }
+
+    /// Read one column of this partition’s rows in chunks, streaming the
+    /// data into *sink*.
+    ///
+    /// *table* must be the table that the partition was created from. This
+    /// works like [`Table::read_column_chunks`], except that only the rows of
+    /// this partition are read, and the column need only have cells of a
+    /// uniform shape within the partition. Returns the number of rows passed
+    /// to *sink*.
+    pub fn read_column_chunks<T, S>(
+        &self,
+        table: &mut Table,
+        col_name: &str,
+        rows_per_chunk: usize,
+        sink: &mut S,
+    ) -> Result<u64, TableError>
+    where
+        T: CasaScalarData + Copy + Default,
+        S: ArrayChunkSink<T>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        if self.rows.is_empty() {
+            return Ok(0);
+        }
+
+        let cell_shape = self.cell_shape::<T>(table, col_name)?;
+        let cell_len: usize = cell_shape.iter().product();
+        let rows_per_chunk = rows_per_chunk.max(1);
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut buf = vec![T::default(); rows_per_chunk.min(self.rows.len()) * cell_len];
+        let mut shape = vec![0];
+        shape.extend_from_slice(&cell_shape[..]);
+
+        for rows in self.rows.chunks(rows_per_chunk) {
+            let data = &mut buf[..rows.len() * cell_len];
+            table.read_scalar_rows(&ccol_name, rows, data)?;
+            shape[0] = rows.len();
+            let chunk = ArrayViewD::from_shape(&shape[..], &data[..]).unwrap();
+            sink.write_chunk(chunk)
+                .map_err(|e| TableError::ChunkStream(Box::new(e)))?;
+        }
+
+        Ok(self.rows.len() as u64)
+    }
+
+    /// Read one column of this partition’s rows into an array.
+    ///
+    /// *table* must be the table that the partition was created from. The
+    /// result has shape `[n_rows, cell_shape...]`. See
+    /// [`Self::read_column_chunks`] to read the data in pieces.
+    pub fn read_column<T>(&self, table: &mut Table, col_name: &str) -> Result<ArrayD<T>, TableError>
+    where
+        T: CasaScalarData + Copy + Default,
+    {
+        let cell_shape = if self.rows.is_empty() {
+            Vec::new()
+        } else {
+            self.cell_shape::<T>(table, col_name)?
+        };
+
+        let mut collector = ArrayCollector::new(&cell_shape);
+        let rows_per_chunk = table
+            .suggest_chunk_rows(&[col_name], None)
+            .unwrap_or(self.rows.len());
+        self.read_column_chunks(table, col_name, rows_per_chunk, &mut collector)?;
+        Ok(collector.into_array())
+    }
+
+    /// Check that a column holds data of type `T` and get the shape of its
+    /// cells in this partition, taken from the first row.
+    fn cell_shape<T: CasaScalarData>(
+        &self,
+        table: &mut Table,
+        col_name: &str,
+    ) -> Result<Vec<usize>, TableError> {
+        let desc = table.get_col_desc(col_name)?;
+        let data_type = desc.data_type().element_type();
+
+        if data_type != T::DATA_TYPE {
+            return Err(UnexpectedDataTypeError(T::DATA_TYPE, data_type).into());
+        }
+
+        if desc.is_scalar() {
+            Ok(Vec::new())
+        } else {
+            table.get_cell_shape(col_name, self.rows[0])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use crate::{Complex, TableOpenMode};
+    use ndarray::Array2;
+    use tempfile::tempdir;
+
+    #[test]
+    fn two_windows() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("test.ms");
+        let spec = SyntheticMsSpec::default();
+        let mut t = synthetic_ms(&path, &spec).unwrap();
+
+        // Add a narrower second window and move the last timestep into it.
+        let n_chans2 = 8;
+        let spw2 = SpectralWindow::uniform(n_chans2, 200e6, spec.chan_width);
+        let mut spw_table =
+            Table::open(path.join("SPECTRAL_WINDOW"), TableOpenMode::ReadWrite).unwrap();
+        spw_table.add_rows(1).unwrap();
+        spw2.write(&mut spw_table, 1).unwrap();
+        drop(spw_table);
+
+        let mut dd_table =
+            Table::open(path.join("DATA_DESCRIPTION"), TableOpenMode::ReadWrite).unwrap();
+        dd_table.add_rows(1).unwrap();
+        dd_table.put_cell("SPECTRAL_WINDOW_ID", 1, &1i32).unwrap();
+        dd_table.put_cell("POLARIZATION_ID", 1, &0i32).unwrap();
+        dd_table.put_cell("FLAG_ROW", 1, &false).unwrap();
+        drop(dd_table);
+
+        let n_rows = spec.n_rows() as u64;
+        let rows_per_time = n_rows / spec.n_timesteps as u64;
+        let first_moved = n_rows - rows_per_time;
+        let flags = Array2::from_elem((n_chans2, spec.n_pols), false);
+
+        for row in first_moved..n_rows {
+            let data = Array2::from_shape_fn((n_chans2, spec.n_pols), |(c, p)| {
+                Complex::new(row as f32, -((c * spec.n_pols + p) as f32))
+            });
+            t.put_cell("DATA_DESC_ID", row, &1i32).unwrap();
+            t.put_cell("DATA", row, &data).unwrap();
+            t.put_cell("FLAG", row, &flags).unwrap();
+        }
+
+        // The whole column can no longer be read in one go ...
+        assert!(
+            t.get_cell_shape("DATA", 0).unwrap() != t.get_cell_shape("DATA", n_rows - 1).unwrap()
+        );
+        let mut whole = ArrayCollector::<Complex<f32>>::new(&[spec.n_chans, spec.n_pols]);
+        assert!(t.read_column_chunks("DATA", 1024, &mut whole).is_err());
+
+        // ... but each partition can.
+        let parts = spw_partitions(&mut t).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].spectral_window_id, 0);
+        assert_eq!(parts[0].data_desc_ids, vec![0]);
+        assert_eq!(parts[0].rows(), &(0..first_moved).collect::<Vec<_>>()[..]);
+        assert_eq!(parts[0].data_shape(), [spec.n_chans, spec.n_pols]);
+        assert_eq!(parts[1].spectral_window_id, 1);
+        assert_eq!(parts[1].data_desc_ids, vec![1]);
+        assert_eq!(parts[1].n_rows() as u64, rows_per_time);
+        assert_eq!(parts[1].data_shape(), [n_chans2, spec.n_pols]);
+        assert_eq!(parts[1].spectral_window.chan_freq[0], 200e6);
+
+        let data = parts[0]
+            .read_column::<Complex<f32>>(&mut t, "DATA")
+            .unwrap();
+        assert_eq!(
+            data.shape(),
+            &[first_moved as usize, spec.n_chans, spec.n_pols]
+        );
+        assert_eq!(data[[5, 3, 2]], spec.expected_vis(5, 3, 2));
+
+        let data = parts[1]
+            .read_column::<Complex<f32>>(&mut t, "DATA")
+            .unwrap();
+        assert_eq!(
+            data.shape(),
+            &[rows_per_time as usize, n_chans2, spec.n_pols]
+        );
+        assert_eq!(
+            data[[1, 2, 3]],
+            Complex::new((first_moved + 1) as f32, -((2 * spec.n_pols + 3) as f32))
+        );
+
+        let times = parts[1].read_column::<f64>(&mut t, "TIME").unwrap();
+        assert_eq!(times.shape(), &[rows_per_time as usize]);
+        assert!(parts[1].read_column::<f32>(&mut t, "TIME").is_err());
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Reconstructing scan boundaries in Measurement Sets.
+//!
+//! Converters from formats that have no notion of scans tend to write zero
+//! (or some other constant) to every cell of `SCAN_NUMBER`, which defeats any
+//! downstream processing that works scan by scan, such as per-scan gain
+//! calibration. [`assign_scans`] rebuilds the scan structure from the data
+//! themselves: a new scan starts wherever there is a gap in time, or wherever
+//! the array moves to a different field or observation.
+
+use std::path::Path;
+use thiserror::Error;
+
+use super::history::append_history;
+use crate::{Table, TableError, TableOpenMode};
+
+/// The observing mode recorded in the `STATE` row that [`assign_scans`]
+/// creates when the `STATE` sub-table is empty.
+pub const DEFAULT_OBS_MODE: &str = "OBSERVE_TARGET#ON_SOURCE";
+
+/// An error that can occur when assigning scan numbers.
+#[derive(Error, Debug)]
+pub enum ScanError {
+    /// An error occurred while reading or writing the tables.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The gap threshold is negative or not a number.
+    #[error("invalid scan gap threshold {0}; it must be a non-negative number of seconds")]
+    BadThreshold(f64),
+}
+
+impl From<crate::CasacoreError> for ScanError {
+    fn from(e: crate::CasacoreError) -> Self {
+        ScanError::Table(e.into())
+    }
+}
+
+/// One of the scans found by [`assign_scans`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssignedScan {
+    /// The number written to the `SCAN_NUMBER` column for this scan.
+    pub scan_number: i32,
+
+    /// The `FIELD_ID` of the rows of the scan.
+    pub field_id: i32,
+
+    /// The `OBSERVATION_ID` of the rows of the scan.
+    pub observation_id: i32,
+
+    /// The start and end of the scan, in MJD seconds, taking into account
+    /// the integration times of its first and last rows.
+    pub time_range: (f64, f64),
+
+    /// The number of rows in the scan.
+    pub n_rows: u64,
+}
+
+/// The outcome of [`assign_scans`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScanAssignment {
+    /// The scans, in order of time and so of scan number.
+    pub scans: Vec<AssignedScan>,
+
+    /// If `STATE_ID` was filled in, the row of the `STATE` sub-table that
+    /// the main table rows now refer to.
+    pub state_id: Option<i32>,
+}
+
+impl ScanAssignment {
+    /// The number of scans that were found.
+    pub fn n_scans(&self) -> usize {
+        self.scans.len()
+    }
+}
+
+/// Detect the scans of a Measurement Set and rewrite its `SCAN_NUMBER`
+/// column to match.
+///
+/// The rows are considered in time order. A new scan begins whenever
+/// `FIELD_ID` or `OBSERVATION_ID` changes, or whenever the time between the
+/// end of one integration and the start of the next, as given by the `TIME`
+/// and `INTERVAL` columns, is more than *gap_threshold* seconds. Scans are
+/// numbered from 1. Rows that share a timestamp, field, and observation
+/// always end up in the same scan, whatever their order in the table.
+///
+/// `STATE_ID` is only touched if the `STATE` sub-table is empty, which is
+/// how converters that lack scan information usually leave it. In that case
+/// a single `STATE` row is added describing an on-source observation with
+/// mode [`DEFAULT_OBS_MODE`], and every row whose `STATE_ID` is negative is
+/// pointed at it, so that tools that select data by scan intent find
+/// something to select. Existing `STATE` rows are assumed to be meaningful
+/// and are left alone, as are the `STATE_ID` values that refer to them.
+///
+/// A note of the change is added to the `HISTORY` sub-table.
+pub fn assign_scans(table: &mut Table, gap_threshold: f64) -> Result<ScanAssignment, ScanError> {
+    if gap_threshold.is_nan() || gap_threshold < 0. {
+        return Err(ScanError::BadThreshold(gap_threshold));
+    }
+
+    let ms_path = table.file_name().map_err(TableError::from)?;
+    let ms_path = Path::new(&ms_path);
+
+    let times = table.get_col_as_vec::<f64>("TIME")?;
+    let intervals = table.get_col_as_vec::<f64>("INTERVAL")?;
+    let field_ids = table.get_col_as_vec::<i32>("FIELD_ID")?;
+    let obs_ids = table.get_col_as_vec::<i32>("OBSERVATION_ID")?;
+
+    // Sort the rows by time, and then group them into integrations: runs of
+    // rows sharing a timestamp, field, and observation.
+
+    let key = |r: usize| (times[r], field_ids[r], obs_ids[r]);
+    let mut order: Vec<usize> = (0..times.len()).collect();
+    order.sort_by(|&a, &b| {
+        times[a]
+            .total_cmp(&times[b])
+            .then(field_ids[a].cmp(&field_ids[b]))
+            .then(obs_ids[a].cmp(&obs_ids[b]))
+    });
+
+    let mut scan_numbers = vec![0i32; times.len()];
+    let mut scans: Vec<AssignedScan> = Vec::new();
+
+    for rows in order.chunk_by(|&a, &b| key(a) == key(b)) {
+        let (time, field_id, observation_id) = key(rows[0]);
+        let half_width = rows.iter().map(|&r| intervals[r]).fold(0., f64::max) / 2.;
+        let start = time - half_width;
+        let end = time + half_width;
+
+        let continues = match scans.last() {
+            Some(prev) => {
+                prev.field_id == field_id
+                    && prev.observation_id == observation_id
+                    && start - prev.time_range.1 <= gap_threshold
+            }
+            None => false,
+        };
+
+        if !continues {
+            scans.push(AssignedScan {
+                scan_number: scans.len() as i32 + 1,
+                field_id,
+                observation_id,
+                time_range: (start, end),
+                n_rows: 0,
+            });
+        }
+
+        let scan = scans.last_mut().unwrap();
+        scan.time_range.1 = scan.time_range.1.max(end);
+        scan.n_rows += rows.len() as u64;
+
+        for &row in rows {
+            scan_numbers[row] = scan.scan_number;
+        }
+    }
+
+    for (row, scan_number) in scan_numbers.iter().enumerate() {
+        table.put_cell("SCAN_NUMBER", row as u64, scan_number)?;
+    }
+
+    let state_id = fill_state_ids(table, ms_path)?;
+
+    append_history(
+        ms_path,
+        "rubbl_casatables::ms::assign_scans",
+        &format!(
+            "assigned {} scans to {} rows with a gap threshold of {} s",
+            scans.len(),
+            times.len(),
+            gap_threshold
+        ),
+        &[format!("gap_threshold={}", gap_threshold)],
+    )?;
+
+    Ok(ScanAssignment { scans, state_id })
+}
+
+/// If the `STATE` sub-table is empty, give it a generic on-source row and
+/// point all rows without a state at it.
+fn fill_state_ids(table: &mut Table, ms_path: &Path) -> Result<Option<i32>, TableError> {
+    let mut state_table = Table::open(ms_path.join("STATE"), TableOpenMode::ReadWrite)?;
+
+    if state_table.n_rows() > 0 {
+        return Ok(None);
+    }
+
+    state_table.add_rows(1)?;
+    state_table.put_cell("SIG", 0, &true)?;
+    state_table.put_cell("REF", 0, &false)?;
+    state_table.put_cell("CAL", 0, &0f64)?;
+    state_table.put_cell("LOAD", 0, &0f64)?;
+    state_table.put_cell("SUB_SCAN", 0, &1i32)?;
+    state_table.put_cell("OBS_MODE", 0, &DEFAULT_OBS_MODE.to_owned())?;
+    state_table.put_cell("FLAG_ROW", 0, &false)?;
+
+    let state_ids = table.get_col_as_vec::<i32>("STATE_ID")?;
+
+    for (row, state_id) in state_ids.iter().enumerate() {
+        if *state_id < 0 {
+            table.put_cell("STATE_ID", row as u64, &0i32)?;
+        }
+    }
+
+    Ok(Some(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn gaps_and_fields() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec {
+            n_timesteps: 6,
+            ..SyntheticMsSpec::default()
+        };
+        let mut t = synthetic_ms(tmp_dir.path().join("scans.ms"), &spec).unwrap();
+        let n_rows = spec.n_rows();
+        let rows_per_step = n_rows / spec.n_timesteps;
+
+        // Converters often leave every scan number at zero.
+        for row in 0..n_rows as u64 {
+            t.put_cell("SCAN_NUMBER", row, &0i32).unwrap();
+        }
+
+        // Timesteps 0-1 on field 0; a long gap; 2-3 on field 0; then 4-5 on
+        // field 1 with no gap.
+        let times = t.get_col_as_vec::<f64>("TIME").unwrap();
+
+        for (row, time) in times.iter().enumerate().skip(2 * rows_per_step) {
+            t.put_cell("TIME", row as u64, &(time + 600.)).unwrap();
+        }
+
+        for row in 4 * rows_per_step..n_rows {
+            t.put_cell("FIELD_ID", row as u64, &1i32).unwrap();
+        }
+
+        let result = assign_scans(&mut t, 60.).unwrap();
+        assert_eq!(result.n_scans(), 3);
+        assert_eq!(result.state_id, Some(0));
+        assert_eq!(
+            result.scans.iter().map(|s| s.field_id).collect::<Vec<_>>(),
+            vec![0, 0, 1]
+        );
+
+        for scan in &result.scans {
+            assert_eq!(scan.n_rows, 2 * rows_per_step as u64);
+        }
+
+        let scan_numbers = t.get_col_as_vec::<i32>("SCAN_NUMBER").unwrap();
+
+        for (row, scan_number) in scan_numbers.iter().enumerate() {
+            assert_eq!(*scan_number as usize, row / (2 * rows_per_step) + 1);
+        }
+
+        assert!(t
+            .get_col_as_vec::<i32>("STATE_ID")
+            .unwrap()
+            .iter()
+            .all(|s| *s == 0));
+
+        // A large threshold bridges the gap, and the existing STATE row is
+        // reused.
+        let result = assign_scans(&mut t, 1e4).unwrap();
+        assert_eq!(result.n_scans(), 2);
+        assert_eq!(result.state_id, None);
+        assert_eq!(result.scans[0].n_rows, 4 * rows_per_step as u64);
+
+        assert!(assign_scans(&mut t, -1.).is_err());
+    }
+}
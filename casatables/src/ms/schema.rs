@@ -0,0 +1,485 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Column definitions of the standard Measurement Set tables.
+//!
+//! These follow the MSv2 definition given in [CASA Memo 229][MS]. Only the
+//! required columns of each table are listed, with the exception of the main
+//! table, which also includes the `DATA` column since essentially every
+//! interferometric data set has one.
+//!
+//! [MS]: https://casa.nrao.edu/Memos/229.html
+//!
+//! Shapes are given in Rust (C) ordering, so that a `DATA` cell with `n_chan`
+//! channels and `n_pol` polarizations has shape `(n_chan, n_pol)`.
+
+use std::path::Path;
+
+use self::ColumnShape::{Fixed, Scalar, Variable};
+use crate::GlueDataType::{TpBool, TpComplex, TpDouble, TpFloat, TpInt, TpString};
+use crate::{
+    GlueDataType, Table, TableCreateMode, TableDesc, TableDescCreateMode, TableError,
+    TableOpenMode, TableRecord,
+};
+
+/// The shape of the cells in a column.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColumnShape {
+    /// Each cell contains a single value.
+    Scalar,
+
+    /// Each cell contains an array of the given shape.
+    Fixed(&'static [u64]),
+
+    /// Each cell contains an array with the given number of dimensions, whose
+    /// shape may vary from row to row.
+    Variable(u64),
+}
+
+/// The definition of one column of a standard table.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ColumnSpec {
+    /// The name of the column.
+    pub name: &'static str,
+
+    /// The type of the individual values stored in the column.
+    pub data_type: GlueDataType,
+
+    /// The shape of each cell of the column.
+    pub shape: ColumnShape,
+
+    /// The physical units of the column values, recorded in the
+    /// `QuantumUnits` column keyword. Empty if the values are unitless.
+    pub units: &'static [&'static str],
+
+    /// The measure type and reference frame of the column values, recorded in
+    /// the `MEASINFO` column keyword, if they represent a measure.
+    pub measure: Option<(&'static str, &'static str)>,
+}
+
+impl ColumnSpec {
+    const fn new(name: &'static str, data_type: GlueDataType, shape: ColumnShape) -> Self {
+        ColumnSpec {
+            name,
+            data_type,
+            shape,
+            units: &[],
+            measure: None,
+        }
+    }
+
+    const fn units(self, units: &'static [&'static str]) -> Self {
+        ColumnSpec { units, ..self }
+    }
+
+    const fn measure(self, kind: &'static str, reference: &'static str) -> Self {
+        ColumnSpec {
+            measure: Some((kind, reference)),
+            ..self
+        }
+    }
+
+    /// Add this column, along with its unit and measure keywords, to a table
+    /// description.
+    pub fn add_to(&self, desc: &mut TableDesc) -> Result<(), TableError> {
+        match self.shape {
+            ColumnShape::Scalar => {
+                desc.add_scalar_column(self.data_type, self.name, None, false, false)?;
+            }
+
+            ColumnShape::Fixed(shape) => {
+                desc.add_array_column(self.data_type, self.name, None, Some(shape), true, false)?;
+            }
+
+            ColumnShape::Variable(ndim) => {
+                desc.add_array_column(self.data_type, self.name, None, None, false, false)?;
+                desc.set_ndims(self.name, ndim)?;
+            }
+        }
+
+        if !self.units.is_empty() {
+            let units: Vec<String> = self.units.iter().map(|u| (*u).to_owned()).collect();
+            desc.put_column_keyword(self.name, "QuantumUnits", &units)?;
+        }
+
+        if let Some((kind, reference)) = self.measure {
+            let mut meas_info = TableRecord::new()?;
+            meas_info.put_field("type", &kind.to_owned())?;
+            meas_info.put_field("Ref", &reference.to_owned())?;
+            desc.put_column_keyword(self.name, "MEASINFO", &meas_info)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The definition of one of the standard Measurement Set tables.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TableSpec {
+    /// The name of the table. For sub-tables, this is both the name of the
+    /// keyword that links it to the main table and the name of its directory
+    /// within the main table directory.
+    pub name: &'static str,
+
+    /// The columns of the table.
+    pub columns: &'static [ColumnSpec],
+}
+
+impl TableSpec {
+    /// Look up a column of this table by name.
+    pub fn column(&self, name: &str) -> Option<&ColumnSpec> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    /// Build a table description containing all of the columns of this table.
+    pub fn table_desc(&self) -> Result<TableDesc, TableError> {
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH)?;
+
+        for col in self.columns {
+            col.add_to(&mut desc)?;
+        }
+
+        Ok(desc)
+    }
+
+    /// Create a new, empty table following this definition at the specified
+    /// path.
+    pub fn create<P: AsRef<Path>>(&self, path: P, n_rows: usize) -> Result<Table, TableError> {
+        Table::new(path, self.table_desc()?, n_rows, TableCreateMode::New)
+    }
+
+    /// Open this sub-table of the Measurement Set *ms* for writing, creating
+    /// it if it does not exist.
+    ///
+    /// A new sub-table is created without any rows, in a directory inside
+    /// the main table directory, and linked to the main table with a
+    /// table-type keyword. This is how optional sub-tables such as `SYSCAL`
+    /// are added to an existing data set.
+    pub fn open_or_create_subtable(&self, ms: &mut Table) -> Result<Table, TableError> {
+        let ms_path = ms.file_name()?;
+        let path = Path::new(&ms_path).join(self.name);
+
+        if !ms.table_keyword_names()?.iter().any(|k| k == self.name) {
+            let sub = self.create(&path, 0)?;
+            ms.put_table_keyword(self.name, sub)?;
+        }
+
+        Table::open(&path, TableOpenMode::ReadWrite)
+    }
+}
+
+/// The main table.
+pub const MAIN: TableSpec = TableSpec {
+    name: "MAIN",
+    columns: &[
+        ColumnSpec::new("TIME", TpDouble, Scalar)
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+        ColumnSpec::new("ANTENNA1", TpInt, Scalar),
+        ColumnSpec::new("ANTENNA2", TpInt, Scalar),
+        ColumnSpec::new("FEED1", TpInt, Scalar),
+        ColumnSpec::new("FEED2", TpInt, Scalar),
+        ColumnSpec::new("DATA_DESC_ID", TpInt, Scalar),
+        ColumnSpec::new("PROCESSOR_ID", TpInt, Scalar),
+        ColumnSpec::new("FIELD_ID", TpInt, Scalar),
+        ColumnSpec::new("INTERVAL", TpDouble, Scalar).units(&["s"]),
+        ColumnSpec::new("EXPOSURE", TpDouble, Scalar).units(&["s"]),
+        ColumnSpec::new("TIME_CENTROID", TpDouble, Scalar)
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+        ColumnSpec::new("SCAN_NUMBER", TpInt, Scalar),
+        ColumnSpec::new("ARRAY_ID", TpInt, Scalar),
+        ColumnSpec::new("OBSERVATION_ID", TpInt, Scalar),
+        ColumnSpec::new("STATE_ID", TpInt, Scalar),
+        ColumnSpec::new("UVW", TpDouble, Fixed(&[3]))
+            .units(&["m"])
+            .measure("uvw", "J2000"),
+        ColumnSpec::new("SIGMA", TpFloat, Variable(1)),
+        ColumnSpec::new("WEIGHT", TpFloat, Variable(1)),
+        ColumnSpec::new("FLAG", TpBool, Variable(2)),
+        ColumnSpec::new("FLAG_CATEGORY", TpBool, Variable(3)),
+        ColumnSpec::new("FLAG_ROW", TpBool, Scalar),
+        ColumnSpec::new("DATA", TpComplex, Variable(2)),
+    ],
+};
+
+/// The `ANTENNA` sub-table.
+pub const ANTENNA: TableSpec = TableSpec {
+    name: "ANTENNA",
+    columns: &[
+        ColumnSpec::new("NAME", TpString, Scalar),
+        ColumnSpec::new("STATION", TpString, Scalar),
+        ColumnSpec::new("TYPE", TpString, Scalar),
+        ColumnSpec::new("MOUNT", TpString, Scalar),
+        ColumnSpec::new("POSITION", TpDouble, Fixed(&[3]))
+            .units(&["m"])
+            .measure("position", "ITRF"),
+        ColumnSpec::new("OFFSET", TpDouble, Fixed(&[3]))
+            .units(&["m"])
+            .measure("position", "ITRF"),
+        ColumnSpec::new("DISH_DIAMETER", TpDouble, Scalar).units(&["m"]),
+        ColumnSpec::new("FLAG_ROW", TpBool, Scalar),
+    ],
+};
+
+/// The `DATA_DESCRIPTION` sub-table.
+pub const DATA_DESCRIPTION: TableSpec = TableSpec {
+    name: "DATA_DESCRIPTION",
+    columns: &[
+        ColumnSpec::new("SPECTRAL_WINDOW_ID", TpInt, Scalar),
+        ColumnSpec::new("POLARIZATION_ID", TpInt, Scalar),
+        ColumnSpec::new("FLAG_ROW", TpBool, Scalar),
+    ],
+};
+
+/// The `FEED` sub-table.
+pub const FEED: TableSpec = TableSpec {
+    name: "FEED",
+    columns: &[
+        ColumnSpec::new("ANTENNA_ID", TpInt, Scalar),
+        ColumnSpec::new("FEED_ID", TpInt, Scalar),
+        ColumnSpec::new("SPECTRAL_WINDOW_ID", TpInt, Scalar),
+        ColumnSpec::new("TIME", TpDouble, Scalar)
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+        ColumnSpec::new("INTERVAL", TpDouble, Scalar).units(&["s"]),
+        ColumnSpec::new("NUM_RECEPTORS", TpInt, Scalar),
+        ColumnSpec::new("BEAM_ID", TpInt, Scalar),
+        ColumnSpec::new("BEAM_OFFSET", TpDouble, Variable(2))
+            .units(&["rad"])
+            .measure("direction", "J2000"),
+        ColumnSpec::new("POLARIZATION_TYPE", TpString, Variable(1)),
+        ColumnSpec::new("POL_RESPONSE", TpComplex, Variable(2)),
+        ColumnSpec::new("POSITION", TpDouble, Fixed(&[3]))
+            .units(&["m"])
+            .measure("position", "ITRF"),
+        ColumnSpec::new("RECEPTOR_ANGLE", TpDouble, Variable(1)).units(&["rad"]),
+    ],
+};
+
+/// The `FIELD` sub-table.
+pub const FIELD: TableSpec = TableSpec {
+    name: "FIELD",
+    columns: &[
+        ColumnSpec::new("NAME", TpString, Scalar),
+        ColumnSpec::new("CODE", TpString, Scalar),
+        ColumnSpec::new("TIME", TpDouble, Scalar)
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+        ColumnSpec::new("NUM_POLY", TpInt, Scalar),
+        ColumnSpec::new("DELAY_DIR", TpDouble, Variable(2))
+            .units(&["rad"])
+            .measure("direction", "J2000"),
+        ColumnSpec::new("PHASE_DIR", TpDouble, Variable(2))
+            .units(&["rad"])
+            .measure("direction", "J2000"),
+        ColumnSpec::new("REFERENCE_DIR", TpDouble, Variable(2))
+            .units(&["rad"])
+            .measure("direction", "J2000"),
+        ColumnSpec::new("SOURCE_ID", TpInt, Scalar),
+        ColumnSpec::new("FLAG_ROW", TpBool, Scalar),
+    ],
+};
+
+/// The `FLAG_CMD` sub-table.
+pub const FLAG_CMD: TableSpec = TableSpec {
+    name: "FLAG_CMD",
+    columns: &[
+        ColumnSpec::new("TIME", TpDouble, Scalar)
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+        ColumnSpec::new("INTERVAL", TpDouble, Scalar).units(&["s"]),
+        ColumnSpec::new("TYPE", TpString, Scalar),
+        ColumnSpec::new("REASON", TpString, Scalar),
+        ColumnSpec::new("LEVEL", TpInt, Scalar),
+        ColumnSpec::new("SEVERITY", TpInt, Scalar),
+        ColumnSpec::new("APPLIED", TpBool, Scalar),
+        ColumnSpec::new("COMMAND", TpString, Scalar),
+    ],
+};
+
+/// The `HISTORY` sub-table.
+pub const HISTORY: TableSpec = TableSpec {
+    name: "HISTORY",
+    columns: &[
+        ColumnSpec::new("TIME", TpDouble, Scalar)
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+        ColumnSpec::new("OBSERVATION_ID", TpInt, Scalar),
+        ColumnSpec::new("MESSAGE", TpString, Scalar),
+        ColumnSpec::new("PRIORITY", TpString, Scalar),
+        ColumnSpec::new("ORIGIN", TpString, Scalar),
+        ColumnSpec::new("OBJECT_ID", TpInt, Scalar),
+        ColumnSpec::new("APPLICATION", TpString, Scalar),
+        ColumnSpec::new("CLI_COMMAND", TpString, Variable(1)),
+        ColumnSpec::new("APP_PARAMS", TpString, Variable(1)),
+    ],
+};
+
+/// The `OBSERVATION` sub-table.
+pub const OBSERVATION: TableSpec = TableSpec {
+    name: "OBSERVATION",
+    columns: &[
+        ColumnSpec::new("TELESCOPE_NAME", TpString, Scalar),
+        ColumnSpec::new("TIME_RANGE", TpDouble, Fixed(&[2]))
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+        ColumnSpec::new("OBSERVER", TpString, Scalar),
+        ColumnSpec::new("LOG", TpString, Variable(1)),
+        ColumnSpec::new("SCHEDULE_TYPE", TpString, Scalar),
+        ColumnSpec::new("SCHEDULE", TpString, Variable(1)),
+        ColumnSpec::new("PROJECT", TpString, Scalar),
+        ColumnSpec::new("RELEASE_DATE", TpDouble, Scalar)
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+        ColumnSpec::new("FLAG_ROW", TpBool, Scalar),
+    ],
+};
+
+/// The `POINTING` sub-table.
+pub const POINTING: TableSpec = TableSpec {
+    name: "POINTING",
+    columns: &[
+        ColumnSpec::new("ANTENNA_ID", TpInt, Scalar),
+        ColumnSpec::new("TIME", TpDouble, Scalar)
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+        ColumnSpec::new("INTERVAL", TpDouble, Scalar).units(&["s"]),
+        ColumnSpec::new("NAME", TpString, Scalar),
+        ColumnSpec::new("NUM_POLY", TpInt, Scalar),
+        ColumnSpec::new("TIME_ORIGIN", TpDouble, Scalar)
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+        ColumnSpec::new("DIRECTION", TpDouble, Variable(2))
+            .units(&["rad"])
+            .measure("direction", "J2000"),
+        ColumnSpec::new("TARGET", TpDouble, Variable(2))
+            .units(&["rad"])
+            .measure("direction", "J2000"),
+        ColumnSpec::new("TRACKING", TpBool, Scalar),
+    ],
+};
+
+/// The `POLARIZATION` sub-table.
+pub const POLARIZATION: TableSpec = TableSpec {
+    name: "POLARIZATION",
+    columns: &[
+        ColumnSpec::new("NUM_CORR", TpInt, Scalar),
+        ColumnSpec::new("CORR_TYPE", TpInt, Variable(1)),
+        ColumnSpec::new("CORR_PRODUCT", TpInt, Variable(2)),
+        ColumnSpec::new("FLAG_ROW", TpBool, Scalar),
+    ],
+};
+
+/// The `PROCESSOR` sub-table.
+pub const PROCESSOR: TableSpec = TableSpec {
+    name: "PROCESSOR",
+    columns: &[
+        ColumnSpec::new("TYPE", TpString, Scalar),
+        ColumnSpec::new("SUB_TYPE", TpString, Scalar),
+        ColumnSpec::new("TYPE_ID", TpInt, Scalar),
+        ColumnSpec::new("MODE_ID", TpInt, Scalar),
+        ColumnSpec::new("FLAG_ROW", TpBool, Scalar),
+    ],
+};
+
+/// The `SPECTRAL_WINDOW` sub-table.
+pub const SPECTRAL_WINDOW: TableSpec = TableSpec {
+    name: "SPECTRAL_WINDOW",
+    columns: &[
+        ColumnSpec::new("NUM_CHAN", TpInt, Scalar),
+        ColumnSpec::new("NAME", TpString, Scalar),
+        ColumnSpec::new("REF_FREQUENCY", TpDouble, Scalar)
+            .units(&["Hz"])
+            .measure("frequency", "TOPO"),
+        ColumnSpec::new("CHAN_FREQ", TpDouble, Variable(1))
+            .units(&["Hz"])
+            .measure("frequency", "TOPO"),
+        ColumnSpec::new("CHAN_WIDTH", TpDouble, Variable(1)).units(&["Hz"]),
+        ColumnSpec::new("MEAS_FREQ_REF", TpInt, Scalar),
+        ColumnSpec::new("EFFECTIVE_BW", TpDouble, Variable(1)).units(&["Hz"]),
+        ColumnSpec::new("RESOLUTION", TpDouble, Variable(1)).units(&["Hz"]),
+        ColumnSpec::new("TOTAL_BANDWIDTH", TpDouble, Scalar).units(&["Hz"]),
+        ColumnSpec::new("NET_SIDEBAND", TpInt, Scalar),
+        ColumnSpec::new("IF_CONV_CHAIN", TpInt, Scalar),
+        ColumnSpec::new("FREQ_GROUP", TpInt, Scalar),
+        ColumnSpec::new("FREQ_GROUP_NAME", TpString, Scalar),
+        ColumnSpec::new("FLAG_ROW", TpBool, Scalar),
+    ],
+};
+
+/// The `STATE` sub-table.
+pub const STATE: TableSpec = TableSpec {
+    name: "STATE",
+    columns: &[
+        ColumnSpec::new("SIG", TpBool, Scalar),
+        ColumnSpec::new("REF", TpBool, Scalar),
+        ColumnSpec::new("CAL", TpDouble, Scalar).units(&["K"]),
+        ColumnSpec::new("LOAD", TpDouble, Scalar).units(&["K"]),
+        ColumnSpec::new("SUB_SCAN", TpInt, Scalar),
+        ColumnSpec::new("OBS_MODE", TpString, Scalar),
+        ColumnSpec::new("FLAG_ROW", TpBool, Scalar),
+    ],
+};
+
+/// The `SYSCAL` sub-table.
+///
+/// This sub-table is optional, so it is not among the
+/// [`REQUIRED_SUBTABLES`]. Besides its required columns, the system
+/// temperature columns used by [`super::SysCal`] are listed: per-receptor
+/// `TCAL`, `TRX`, and `TSYS` values with their flags, and their per-channel
+/// counterparts, whose cells have shape `(n_chan, n_receptors)`.
+pub const SYSCAL: TableSpec = TableSpec {
+    name: "SYSCAL",
+    columns: &[
+        ColumnSpec::new("ANTENNA_ID", TpInt, Scalar),
+        ColumnSpec::new("FEED_ID", TpInt, Scalar),
+        ColumnSpec::new("SPECTRAL_WINDOW_ID", TpInt, Scalar),
+        ColumnSpec::new("TIME", TpDouble, Scalar)
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+        ColumnSpec::new("INTERVAL", TpDouble, Scalar).units(&["s"]),
+        ColumnSpec::new("TCAL", TpFloat, Variable(1)).units(&["K"]),
+        ColumnSpec::new("TCAL_FLAG", TpBool, Scalar),
+        ColumnSpec::new("TCAL_SPECTRUM", TpFloat, Variable(2)).units(&["K"]),
+        ColumnSpec::new("TRX", TpFloat, Variable(1)).units(&["K"]),
+        ColumnSpec::new("TRX_FLAG", TpBool, Scalar),
+        ColumnSpec::new("TRX_SPECTRUM", TpFloat, Variable(2)).units(&["K"]),
+        ColumnSpec::new("TSYS", TpFloat, Variable(1)).units(&["K"]),
+        ColumnSpec::new("TSYS_FLAG", TpBool, Scalar),
+        ColumnSpec::new("TSYS_SPECTRUM", TpFloat, Variable(2)).units(&["K"]),
+    ],
+};
+
+/// The `WEATHER` sub-table.
+///
+/// This sub-table is optional, so it is not among the
+/// [`REQUIRED_SUBTABLES`]. Only its required columns are listed; the
+/// optional columns holding the measurements themselves are added as needed
+/// by [`super::write_weather`].
+pub const WEATHER: TableSpec = TableSpec {
+    name: "WEATHER",
+    columns: &[
+        ColumnSpec::new("ANTENNA_ID", TpInt, Scalar),
+        ColumnSpec::new("INTERVAL", TpDouble, Scalar).units(&["s"]),
+        ColumnSpec::new("TIME", TpDouble, Scalar)
+            .units(&["s"])
+            .measure("epoch", "UTC"),
+    ],
+};
+
+/// All of the sub-tables that a valid Measurement Set must contain.
+pub const REQUIRED_SUBTABLES: &[TableSpec] = &[
+    ANTENNA,
+    DATA_DESCRIPTION,
+    FEED,
+    FIELD,
+    FLAG_CMD,
+    HISTORY,
+    OBSERVATION,
+    POINTING,
+    POLARIZATION,
+    PROCESSOR,
+    SPECTRAL_WINDOW,
+    STATE,
+];
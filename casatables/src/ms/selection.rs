@@ -0,0 +1,806 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Selecting data with CASA’s selection syntax.
+//!
+//! CASA tasks select data with short strings such as `antenna='1&2'`,
+//! `spw='0:3~10'`, or `timerange='2024/01/01/10:00~10:30'`, following the
+//! conventions of casacore’s `MSSelection`. Users already know this syntax,
+//! so tools that accept it are easier to pick up than ones that invent their
+//! own. [`MsSelection`] parses such strings, resolves the antenna and field
+//! names in them against the sub-tables of a data set, and compiles the
+//! result into a [`Select`] that can be run with [`Table::select`].
+//!
+//! ```no_run
+//! use rubbl_casatables::{ms::MsSelection, Table, TableOpenMode};
+//!
+//! let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+//!
+//! let sel = MsSelection::new()
+//!     .antenna("ANT001&ANT002; 3&&&")
+//!     .spw("0:3~10")
+//!     .time("2024/01/01/10:00~10:30");
+//! let resolved = sel.resolve(&mut t).unwrap();
+//! let selected = t.select(&resolved.to_select()).unwrap();
+//! println!("{} rows; channels {:?}", selected.n_rows(), resolved.channels);
+//! ```
+
+use rubbl_core::time::SECONDS_PER_DAY;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+    path::Path,
+};
+use thiserror::Error;
+
+use super::subtables::{open_subtable, read_data_desc_spws, read_names};
+use crate::{
+    select::{col, Expr, Select, SelectError},
+    Table, TableError,
+};
+
+/// An error that can occur when resolving or applying an [`MsSelection`].
+#[derive(Error, Debug)]
+pub enum MsSelectionError {
+    /// An error occurred while reading the tables.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The compiled selection could not be run.
+    #[error(transparent)]
+    Select(#[from] SelectError),
+
+    /// A selection string could not be understood, or refers to antennas,
+    /// fields, spectral windows, or channels that do not exist.
+    #[error("invalid {category} selection \"{input}\": {message}")]
+    Invalid {
+        /// The category of the selection, such as `antenna`.
+        category: &'static str,
+
+        /// The selection string.
+        input: String,
+
+        /// A description of the problem.
+        message: String,
+    },
+
+    /// A spectral window selection is valid, but no `DATA_DESCRIPTION` row
+    /// refers to the chosen spectral windows, so that no data could match.
+    #[error("the spw selection \"{0}\" matches no data description")]
+    NoDataDescription(String),
+}
+
+const ANTENNA: &str = "antenna";
+const SPW: &str = "spw";
+const FIELD: &str = "field";
+const SCAN: &str = "scan";
+const TIME: &str = "time";
+
+fn invalid(category: &'static str, input: &str, message: String) -> MsSelectionError {
+    MsSelectionError::Invalid {
+        category,
+        input: input.to_owned(),
+        message,
+    }
+}
+
+/// A selection of Measurement Set data in CASA’s syntax.
+///
+/// Each category is set with a string in the syntax described below; an
+/// empty string selects everything, as in CASA. The selections of the
+/// different categories are combined, so that a row is selected only if it
+/// matches all of them.
+///
+/// The categories and the subset of the syntax that is understood are:
+///
+/// - **antenna**: baseline specifications separated by `;`. Each is a list
+///   of antennas, separated by `,`, optionally followed by `&` and a second
+///   list. An antenna is an ID, a range of IDs such as `2~5`, or a name in
+///   which `*` matches any run of characters; a bare number is always taken
+///   to be an ID. A list alone selects all baselines involving its antennas;
+///   `L&M` selects the baselines between the two lists, and `L&` those
+///   between antennas of the list. Autocorrelations are only included if the
+///   `&` is doubled, as in `L&&M`, and `L&&&` selects only the
+///   autocorrelations of the antennas of `L`. A specification prefixed with
+///   `!` excludes the baselines that it would otherwise select, including
+///   autocorrelations.
+/// - **spw**: spectral windows separated by `,`, each an ID, a range of IDs,
+///   or `*` for all of them, optionally followed by `:` and channel ranges
+///   separated by `;`, such as `0:3~10;20~30`.
+/// - **field**: fields separated by `,`, each an ID, a range of IDs, or a
+///   name as for antennas.
+/// - **scan**: scan numbers separated by `,`, each a number, a range of
+///   numbers, or a bound such as `>10` or `<3`.
+/// - **time**: UTC time ranges separated by `,`, each `T1~T2`, `>T`, `<T`,
+///   or a single time `T`, which selects the integration containing it.
+///   Times are written `YYYY/MM/DD/hh:mm:ss`; the seconds, or the whole time
+///   of day, may be omitted, and a time of day alone falls on the date of
+///   the first row of the data set.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MsSelection {
+    antenna: Option<String>,
+    spw: Option<String>,
+    field: Option<String>,
+    scan: Option<String>,
+    time: Option<String>,
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+
+    if s.is_empty() {
+        None
+    } else {
+        Some(s.to_owned())
+    }
+}
+
+impl MsSelection {
+    /// Create a selection of all of the data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select baselines, as with CASA’s `antenna` parameter.
+    pub fn antenna(mut self, selection: &str) -> Self {
+        self.antenna = non_empty(selection);
+        self
+    }
+
+    /// Select spectral windows and channels, as with CASA’s `spw`
+    /// parameter.
+    pub fn spw(mut self, selection: &str) -> Self {
+        self.spw = non_empty(selection);
+        self
+    }
+
+    /// Select fields, as with CASA’s `field` parameter.
+    pub fn field(mut self, selection: &str) -> Self {
+        self.field = non_empty(selection);
+        self
+    }
+
+    /// Select scans, as with CASA’s `scan` parameter.
+    pub fn scan(mut self, selection: &str) -> Self {
+        self.scan = non_empty(selection);
+        self
+    }
+
+    /// Select time ranges, as with CASA’s `timerange` parameter.
+    pub fn time(mut self, selection: &str) -> Self {
+        self.time = non_empty(selection);
+        self
+    }
+
+    /// Parse the selection and resolve it against the Measurement Set *ms*.
+    ///
+    /// Only the sub-tables needed to resolve the selection are read.
+    pub fn resolve(&self, ms: &mut Table) -> Result<ResolvedSelection, MsSelectionError> {
+        let ms_path = ms.file_name().map_err(TableError::from)?;
+        let ms_path = Path::new(&ms_path);
+        let mut filters = Vec::new();
+        let mut channels = BTreeMap::new();
+
+        if let Some(input) = &self.antenna {
+            let names = read_names(ms_path, "ANTENNA")?;
+            filters.push(antenna_filter(input, &names)?);
+        }
+
+        if let Some(input) = &self.spw {
+            let (filter, chans) = spw_filter(input, ms_path)?;
+            filters.push(filter);
+            channels = chans;
+        }
+
+        if let Some(input) = &self.field {
+            let names = read_names(ms_path, "FIELD")?;
+            let ids = resolve_list(FIELD, input, input, &names)?;
+            filters.push(in_set("FIELD_ID", &ranges_of(&ids)));
+        }
+
+        if let Some(input) = &self.scan {
+            filters.push(scan_filter(input)?);
+        }
+
+        if let Some(input) = &self.time {
+            filters.push(time_filter(input, ms)?);
+        }
+
+        Ok(ResolvedSelection {
+            filter: filters.into_iter().reduce(|a, b| a.and(b)),
+            channels,
+        })
+    }
+
+    /// Resolve the selection and run it on *ms*, returning a reference
+    /// table of the selected rows.
+    ///
+    /// Channel selections cannot be applied to a table of rows; use
+    /// [`Self::resolve`] to obtain them.
+    pub fn apply(&self, ms: &mut Table) -> Result<Table, MsSelectionError> {
+        let resolved = self.resolve(ms)?;
+        Ok(ms.select(&resolved.to_select())?)
+    }
+}
+
+/// An [`MsSelection`] resolved against a particular Measurement Set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResolvedSelection {
+    /// The filter on the rows of the main table, or `None` if every row is
+    /// selected.
+    pub filter: Option<Expr>,
+
+    /// The channels selected in each spectral window, by spectral window
+    /// ID, as half-open ranges in increasing order. Spectral windows that
+    /// were selected without channel ranges are not listed, since all of
+    /// their channels are selected.
+    pub channels: BTreeMap<usize, Vec<Range<usize>>>,
+}
+
+impl ResolvedSelection {
+    /// Get a [`Select`] choosing all of the columns of the selected rows.
+    pub fn to_select(&self) -> Select {
+        match &self.filter {
+            Some(filter) => Select::all().filter(filter.clone()),
+            None => Select::all(),
+        }
+    }
+}
+
+/// Parse an ID or an inclusive range of IDs such as `2~5`.
+fn parse_range(item: &str) -> Option<(i64, i64)> {
+    match item.split_once('~') {
+        Some((lo, hi)) => Some((lo.trim().parse().ok()?, hi.trim().parse().ok()?)),
+        None => item.trim().parse().ok().map(|v| (v, v)),
+    }
+}
+
+/// Check that *range* lies within `0..n`.
+fn check_range(
+    category: &'static str,
+    input: &str,
+    what: &str,
+    (lo, hi): (i64, i64),
+    n: usize,
+) -> Result<(usize, usize), MsSelectionError> {
+    if lo < 0 || lo > hi {
+        return Err(invalid(
+            category,
+            input,
+            format!("{}~{} is not a valid range of {}s", lo, hi, what),
+        ));
+    }
+
+    if hi as u64 >= n as u64 {
+        return Err(invalid(
+            category,
+            input,
+            format!("{} {} does not exist; there are {}", what, hi, n),
+        ));
+    }
+
+    Ok((lo as usize, hi as usize))
+}
+
+/// Match *name* against *pattern*, in which `*` matches any run of
+/// characters.
+///
+/// Each literal piece between stars is matched at its leftmost possible
+/// position, which is enough when `*` is the only wildcard, so the time taken
+/// is linear in the number of stars rather than exponential.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let mut pieces = pattern.split('*');
+    let first = pieces.next().unwrap_or_default();
+
+    name.strip_prefix(first).is_some_and(|mut tail| {
+        let mut pieces: Vec<&str> = pieces.collect();
+
+        let last = match pieces.pop() {
+            None => return tail.is_empty(),
+            Some(last) => last,
+        };
+
+        for piece in pieces {
+            match tail.find(piece) {
+                None => return false,
+                Some(i) => tail = &tail[i + piece.len()..],
+            }
+        }
+
+        tail.ends_with(last)
+    })
+}
+
+/// Resolve a `,`-separated list of IDs, ranges of IDs, and names into row
+/// numbers of the sub-table whose `NAME` column is *names*.
+fn resolve_list(
+    category: &'static str,
+    input: &str,
+    list: &str,
+    names: &[String],
+) -> Result<BTreeSet<usize>, MsSelectionError> {
+    let mut ids = BTreeSet::new();
+
+    for item in list.split(',') {
+        let item = item.trim();
+
+        if item.is_empty() {
+            return Err(invalid(category, input, "a list item is empty".to_owned()));
+        }
+
+        if let Some(range) = parse_range(item) {
+            let (lo, hi) = check_range(category, input, category, range, names.len())?;
+            ids.extend(lo..=hi);
+            continue;
+        }
+
+        let mut matched = false;
+
+        for (i, name) in names.iter().enumerate() {
+            if glob_match(item, name) {
+                ids.insert(i);
+                matched = true;
+            }
+        }
+
+        if !matched {
+            return Err(invalid(
+                category,
+                input,
+                format!("there is no {} named \"{}\"", category, item),
+            ));
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Collapse a set of IDs into inclusive ranges of consecutive IDs.
+fn ranges_of(ids: &BTreeSet<usize>) -> Vec<(i64, i64)> {
+    let mut ranges: Vec<(i64, i64)> = Vec::new();
+
+    for &id in ids {
+        let id = id as i64;
+
+        match ranges.last_mut() {
+            Some((_, hi)) if *hi + 1 == id => *hi = id,
+            _ => ranges.push((id, id)),
+        }
+    }
+
+    ranges
+}
+
+/// Test whether *column* lies in one of the inclusive *ranges*.
+fn in_set(column: &str, ranges: &[(i64, i64)]) -> Expr {
+    ranges
+        .iter()
+        .map(|&(lo, hi)| {
+            if lo == hi {
+                col(column).eq(lo)
+            } else {
+                col(column).ge(lo).and(col(column).le(hi))
+            }
+        })
+        .reduce(|a, b| a.or(b))
+        .unwrap_or_else(|| Expr::from(false))
+}
+
+fn antenna_filter(input: &str, names: &[String]) -> Result<Expr, MsSelectionError> {
+    let mut include: Option<Expr> = None;
+    let mut exclude = Vec::new();
+
+    for spec in input.split(';') {
+        let spec = spec.trim();
+
+        let (negate, spec) = match spec.strip_prefix('!') {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, spec),
+        };
+
+        let (lhs, n_amps, rhs) = match spec.find('&') {
+            Some(i) => {
+                let n = spec[i..].chars().take_while(|c| *c == '&').count();
+                (&spec[..i], n, spec[i + n..].trim())
+            }
+            None => (spec, 0, ""),
+        };
+
+        let left = ranges_of(&resolve_list(ANTENNA, input, lhs, names)?);
+
+        let baselines = match (n_amps, rhs.is_empty()) {
+            (0, _) => in_set("ANTENNA1", &left).or(in_set("ANTENNA2", &left)),
+            (1 | 2, true) => in_set("ANTENNA1", &left).and(in_set("ANTENNA2", &left)),
+
+            (1 | 2, false) => {
+                let right = ranges_of(&resolve_list(ANTENNA, input, rhs, names)?);
+                in_set("ANTENNA1", &left)
+                    .and(in_set("ANTENNA2", &right))
+                    .or(in_set("ANTENNA1", &right).and(in_set("ANTENNA2", &left)))
+            }
+
+            (3, true) => col("ANTENNA1")
+                .eq(col("ANTENNA2"))
+                .and(in_set("ANTENNA1", &left)),
+
+            _ => {
+                return Err(invalid(
+                    ANTENNA,
+                    input,
+                    format!("cannot understand the baseline specification \"{}\"", spec),
+                ))
+            }
+        };
+
+        let baselines = if n_amps >= 2 || negate {
+            baselines
+        } else {
+            baselines.and(col("ANTENNA1").ne(col("ANTENNA2")))
+        };
+
+        if negate {
+            exclude.push(baselines.not());
+        } else {
+            include = Some(match include {
+                Some(e) => e.or(baselines),
+                None => baselines,
+            });
+        }
+    }
+
+    Ok(include
+        .into_iter()
+        .chain(exclude)
+        .reduce(|a, b| a.and(b))
+        .expect("every selection string has at least one specification"))
+}
+
+type SpwFilter = (Expr, BTreeMap<usize, Vec<Range<usize>>>);
+
+fn spw_filter(input: &str, ms_path: &Path) -> Result<SpwFilter, MsSelectionError> {
+    let num_chan: Vec<i32> =
+        open_subtable(ms_path, "SPECTRAL_WINDOW")?.get_col_as_vec("NUM_CHAN")?;
+    let dd_spws = read_data_desc_spws(ms_path)?;
+
+    let mut spws = BTreeSet::new();
+    let mut whole = BTreeSet::new();
+    let mut channels: BTreeMap<usize, Vec<Range<usize>>> = BTreeMap::new();
+
+    for item in input.split(',') {
+        let item = item.trim();
+
+        let (ids, chans) = match item.split_once(':') {
+            Some((ids, chans)) => (ids.trim(), Some(chans)),
+            None => (item, None),
+        };
+
+        let range = if ids == "*" {
+            (0, num_chan.len() as i64 - 1)
+        } else {
+            parse_range(ids).ok_or_else(|| {
+                invalid(
+                    SPW,
+                    input,
+                    format!("\"{}\" is not a spectral window ID or range of IDs", ids),
+                )
+            })?
+        };
+
+        let (lo, hi) = check_range(SPW, input, "spectral window", range, num_chan.len())?;
+
+        for (spw, n) in num_chan.iter().enumerate().take(hi + 1).skip(lo) {
+            spws.insert(spw);
+
+            let chans = match chans {
+                Some(c) => c,
+                None => {
+                    whole.insert(spw);
+                    continue;
+                }
+            };
+
+            let n_chans = (*n).max(0) as usize;
+            let ranges = channels.entry(spw).or_default();
+
+            for c in chans.split(';') {
+                let range = parse_range(c).ok_or_else(|| {
+                    invalid(
+                        SPW,
+                        input,
+                        format!("\"{}\" is not a channel number or range", c.trim()),
+                    )
+                })?;
+                let (first, last) = check_range(SPW, input, "channel", range, n_chans)?;
+                ranges.push(first..last + 1);
+            }
+        }
+    }
+
+    for spw in &whole {
+        channels.remove(spw);
+    }
+
+    for ranges in channels.values_mut() {
+        ranges.sort_by_key(|r| r.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+
+        for r in ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+                _ => merged.push(r),
+            }
+        }
+
+        *ranges = merged;
+    }
+
+    let dds: BTreeSet<usize> = dd_spws
+        .iter()
+        .enumerate()
+        .filter(|(_, &spw)| spw >= 0 && spws.contains(&(spw as usize)))
+        .map(|(i, _)| i)
+        .collect();
+
+    if dds.is_empty() {
+        return Err(MsSelectionError::NoDataDescription(input.to_owned()));
+    }
+
+    Ok((in_set("DATA_DESC_ID", &ranges_of(&dds)), channels))
+}
+
+fn scan_filter(input: &str) -> Result<Expr, MsSelectionError> {
+    let parse = |s: &str| {
+        s.trim().parse::<i64>().map_err(|_| {
+            invalid(
+                SCAN,
+                input,
+                format!("\"{}\" is not a scan number", s.trim()),
+            )
+        })
+    };
+
+    let mut clauses = Vec::new();
+
+    for item in input.split(',') {
+        let item = item.trim();
+
+        clauses.push(if let Some(n) = item.strip_prefix('>') {
+            col("SCAN_NUMBER").gt(parse(n)?)
+        } else if let Some(n) = item.strip_prefix('<') {
+            col("SCAN_NUMBER").lt(parse(n)?)
+        } else {
+            match parse_range(item) {
+                Some((lo, hi)) if lo <= hi => in_set("SCAN_NUMBER", &[(lo, hi)]),
+                _ => {
+                    return Err(invalid(
+                        SCAN,
+                        input,
+                        format!("\"{}\" is not a scan number or range", item),
+                    ))
+                }
+            }
+        });
+    }
+
+    Ok(clauses
+        .into_iter()
+        .reduce(|a, b| a.or(b))
+        .expect("every selection string has at least one item"))
+}
+
+fn time_filter(input: &str, ms: &mut Table) -> Result<Expr, MsSelectionError> {
+    let (ref_day, interval) = if ms.n_rows() > 0 {
+        let time: f64 = ms.get_cell("TIME", 0)?;
+        let interval: f64 = ms.get_cell("INTERVAL", 0)?;
+        ((time / SECONDS_PER_DAY).floor(), interval)
+    } else {
+        (0., 0.)
+    };
+
+    let parse = |s: &str| {
+        parse_time(s.trim(), ref_day).ok_or_else(|| {
+            invalid(
+                TIME,
+                input,
+                format!(
+                    "\"{}\" is not a time of the form YYYY/MM/DD/hh:mm:ss",
+                    s.trim()
+                ),
+            )
+        })
+    };
+
+    let mut clauses = Vec::new();
+
+    for item in input.split(',') {
+        let item = item.trim();
+
+        clauses.push(if let Some(t) = item.strip_prefix('>') {
+            col("TIME").gt(parse(t)?)
+        } else if let Some(t) = item.strip_prefix('<') {
+            col("TIME").lt(parse(t)?)
+        } else if let Some((start, end)) = item.split_once('~') {
+            let (start, end) = (parse(start)?, parse(end)?);
+
+            if start > end {
+                return Err(invalid(
+                    TIME,
+                    input,
+                    format!("the range \"{}\" ends before it starts", item),
+                ));
+            }
+
+            col("TIME").ge(start).and(col("TIME").le(end))
+        } else {
+            let t = parse(item)?;
+            col("TIME")
+                .ge(t - 0.5 * interval)
+                .and(col("TIME").le(t + 0.5 * interval))
+        });
+    }
+
+    Ok(clauses
+        .into_iter()
+        .reduce(|a, b| a.or(b))
+        .expect("every selection string has at least one item"))
+}
+
+/// Parse a UTC time such as `2024/01/01/10:30:00` into CASA seconds. A
+/// time of day alone falls on the MJD *ref_day*.
+fn parse_time(s: &str, ref_day: f64) -> Option<f64> {
+    let parts: Vec<&str> = s.split('/').collect();
+
+    let (day, tod) = match parts.len() {
+        1 => (ref_day, parts[0]),
+        3 | 4 => {
+            let year: i64 = parts[0].trim().parse().ok()?;
+            let month: i64 = parts[1].trim().parse().ok()?;
+            let day: i64 = parts[2].trim().parse().ok()?;
+
+            if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+                return None;
+            }
+
+            (
+                mjd_from_civil(year, month, day) as f64,
+                parts.get(3).copied().unwrap_or(""),
+            )
+        }
+        _ => return None,
+    };
+
+    let mut seconds = 0.;
+
+    if !tod.is_empty() {
+        let fields: Vec<&str> = tod.split(':').collect();
+
+        if fields.len() < 2 || fields.len() > 3 {
+            return None;
+        }
+
+        let hours: u32 = fields[0].trim().parse().ok()?;
+        let minutes: u32 = fields[1].trim().parse().ok()?;
+        let secs: f64 = match fields.get(2) {
+            Some(f) => f.trim().parse().ok()?,
+            None => 0.,
+        };
+
+        if hours > 23 || minutes > 59 || !(0. ..61.).contains(&secs) {
+            return None;
+        }
+
+        seconds = (hours * 3600 + minutes * 60) as f64 + secs;
+    }
+
+    Some(day * SECONDS_PER_DAY + seconds)
+}
+
+/// Get the MJD of a civil date, following the algorithm of Howard Hinnant's
+/// `days_from_civil`, shifted from the Unix epoch to MJD 0.
+fn mjd_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468 + 40587
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn times() {
+        assert_eq!(mjd_from_civil(2024, 1, 1), 60310);
+        assert_eq!(mjd_from_civil(1858, 11, 17), 0);
+        assert_eq!(
+            parse_time("2024/01/01/10:30:15.5", 0.),
+            Some(60310. * 86400. + 37815.5)
+        );
+        assert_eq!(parse_time("2024/01/01", 0.), Some(60310. * 86400.));
+        assert_eq!(parse_time("00:01", 2.), Some(2. * 86400. + 60.));
+        assert_eq!(parse_time("25:00", 0.), None);
+        assert_eq!(parse_time("yesterday", 0.), None);
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("ANT000", "ANT000"));
+        assert!(!glob_match("ANT000", "ANT0001"));
+        assert!(glob_match("ANT*", "ANT000"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*0*1", "ANT001"));
+        assert!(!glob_match("*0*2", "ANT001"));
+        assert!(glob_match("A*T*T", "ANTT"));
+        assert!(!glob_match("AN*NT", "ANT"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+
+        let pattern = "a".to_owned() + &"*a".repeat(100) + "b";
+        assert!(!glob_match(&pattern, &"a".repeat(200)));
+    }
+
+    #[test]
+    fn selections() {
+        let tmp_dir = tempdir().unwrap();
+        let mut t =
+            synthetic_ms(tmp_dir.path().join("vis.ms"), &SyntheticMsSpec::default()).unwrap();
+
+        let resolved = MsSelection::new()
+            .spw("0:8~9;2~5;4~6")
+            .resolve(&mut t)
+            .unwrap();
+        assert_eq!(resolved.channels[&0], vec![2..7, 8..10]);
+        let channel_select = resolved.to_select();
+        let resolved = MsSelection::new().spw("*,0:3").resolve(&mut t).unwrap();
+        assert!(resolved.channels.is_empty());
+
+        for bad in [
+            MsSelection::new().antenna("ANT009"),
+            MsSelection::new().antenna("7"),
+            MsSelection::new().antenna("0&&&1"),
+            MsSelection::new().spw("1"),
+            MsSelection::new().spw("0:10~20"),
+            MsSelection::new().field("nope"),
+            MsSelection::new().scan("x"),
+            MsSelection::new().time("noon"),
+        ] {
+            assert!(matches!(
+                bad.resolve(&mut t),
+                Err(MsSelectionError::Invalid { .. })
+            ));
+        }
+
+        assert_eq!(t.select(&channel_select).unwrap().n_rows(), 30);
+
+        // Four antennas with autocorrelations make ten baselines, each with
+        // three integrations starting at 2017/04/27/08:53:20.
+        let mut count = |sel: MsSelection| sel.apply(&mut t).unwrap().n_rows();
+
+        assert_eq!(count(MsSelection::new()), 30);
+        assert_eq!(count(MsSelection::new().antenna("0&1")), 3);
+        assert_eq!(count(MsSelection::new().antenna("ANT000")), 9);
+        assert_eq!(count(MsSelection::new().antenna("0&&")), 3);
+        assert_eq!(count(MsSelection::new().antenna("0,1&&")), 9);
+        assert_eq!(count(MsSelection::new().antenna("0~1&")), 3);
+        assert_eq!(count(MsSelection::new().antenna("ANT*&&&")), 12);
+        assert_eq!(count(MsSelection::new().antenna("!0")), 18);
+        assert_eq!(count(MsSelection::new().antenna("0&1; 2&&&")), 6);
+        assert_eq!(count(MsSelection::new().field("synth*")), 30);
+        assert_eq!(count(MsSelection::new().scan("1~3")), 30);
+        assert_eq!(count(MsSelection::new().scan(">1")), 0);
+        assert_eq!(
+            count(MsSelection::new().time("2017/04/27/08:53:20~2017/04/27/08:53:30")),
+            10
+        );
+        assert_eq!(count(MsSelection::new().time("08:53:30~08:53:50")), 20);
+        assert_eq!(count(MsSelection::new().time(">2017/04/27/08:53:40")), 10);
+        assert_eq!(count(MsSelection::new().time("08:53:35")), 10);
+        assert_eq!(
+            count(MsSelection::new().antenna("0&1").time("<08:53:30")),
+            1
+        );
+    }
+}
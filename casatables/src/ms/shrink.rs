@@ -0,0 +1,717 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Shrinking Measurement Sets down to small reproducers.
+
+use ndarray::{s, Array2};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use super::subtables::read_data_desc_spws;
+use crate::{
+    CasaScalarData, Complex, GlueDataType, Table, TableError, TableOpenMode,
+    UnexpectedDataTypeError,
+};
+
+/// Main-table columns whose cells have a frequency axis.
+const CHANNEL_COLUMNS: &[&str] = &[
+    "DATA",
+    "MODEL_DATA",
+    "CORRECTED_DATA",
+    "FLOAT_DATA",
+    "FLAG",
+    "WEIGHT_SPECTRUM",
+    "SIGMA_SPECTRUM",
+];
+
+/// Main-table columns holding visibility values, which may be zeroed out.
+const VISIBILITY_COLUMNS: &[&str] = &["DATA", "MODEL_DATA", "CORRECTED_DATA", "FLOAT_DATA"];
+
+/// `SPECTRAL_WINDOW` columns holding one value per channel.
+const SPW_CHANNEL_COLUMNS: &[&str] = &["CHAN_FREQ", "CHAN_WIDTH", "EFFECTIVE_BW", "RESOLUTION"];
+
+/// Sub-tables that are pruned to the rows that the output refers to, with
+/// the names of the columns in other tables that hold their row numbers.
+const INDEXED_SUBTABLES: &[(&str, &[&str])] = &[
+    ("ANTENNA", &["ANTENNA1", "ANTENNA2", "ANTENNA_ID"]),
+    ("FIELD", &["FIELD_ID"]),
+    ("DATA_DESCRIPTION", &["DATA_DESC_ID"]),
+    ("SPECTRAL_WINDOW", &["SPECTRAL_WINDOW_ID"]),
+];
+
+/// A mapping from the row numbers of a sub-table in the input to those in
+/// the output, for the rows that are kept.
+type IdMap = BTreeMap<i32, i32>;
+
+/// Options controlling [`shrink_ms`].
+#[derive(Clone, Debug, Default)]
+pub struct ShrinkOptions {
+    /// Keep only the rows belonging to the first this-many distinct values of
+    /// the `TIME` column. If `None`, all timesteps are kept.
+    pub n_timesteps: Option<usize>,
+
+    /// Keep only the first this-many channels of every spectral window. If
+    /// `None`, all channels are kept.
+    pub n_channels: Option<usize>,
+
+    /// If true, replace the contents of the visibility columns (`DATA`,
+    /// `MODEL_DATA`, `CORRECTED_DATA`, `FLOAT_DATA`) with zeros.
+    pub zero_data: bool,
+
+    /// If true, blank out the observer and project names in the
+    /// `OBSERVATION` sub-table.
+    pub anonymize: bool,
+}
+
+/// Information about the result of a [`shrink_ms`] operation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ShrinkSummary {
+    /// The number of rows in the input main table.
+    pub n_rows_in: u64,
+
+    /// The number of rows written to the output main table.
+    pub n_rows_out: u64,
+
+    /// The number of distinct timesteps in the output.
+    pub n_timesteps_out: usize,
+}
+
+/// Copy a Measurement Set, keeping only a small subset of its data.
+///
+/// The output has the same structure as the input, including its sub-tables.
+/// Rows of the main table are kept if their `TIME`
+/// is one of the first `options.n_timesteps` distinct values, in the order in
+/// which they appear in the input. If `options.n_channels` is set, the
+/// per-channel columns of the main table and of the `SPECTRAL_WINDOW`
+/// sub-table are trimmed to that many channels, and the `NUM_CHAN` and
+/// `TOTAL_BANDWIDTH` columns are updated to match. Fixed-shape columns that
+/// need trimming are recreated with their new shape, which loses any custom
+/// storage manager binding and column keywords that they had.
+///
+/// The `ANTENNA`, `FIELD`, `DATA_DESCRIPTION`, and `SPECTRAL_WINDOW`
+/// sub-tables are pruned to the rows that the kept data refer to, and the
+/// columns that index them — `ANTENNA1`, `ANTENNA2`, `FIELD_ID`,
+/// `DATA_DESC_ID`, and the `ANTENNA_ID` and `SPECTRAL_WINDOW_ID` columns of
+/// sub-tables such as `FEED` — are renumbered to match. Sub-table rows that
+/// refer to a pruned row are dropped; an ID of -1, which conventionally
+/// means “any”, is left alone. Other sub-tables are copied in full.
+///
+/// The output path must not already exist.
+pub fn shrink_ms<P1: AsRef<Path>, P2: AsRef<Path>>(
+    src_path: P1,
+    dest_path: P2,
+    options: &ShrinkOptions,
+) -> Result<ShrinkSummary, TableError> {
+    let src_path = src_path.as_ref();
+    let dest_path = dest_path.as_ref();
+
+    let mut src = Table::open(src_path, TableOpenMode::Read)?;
+    src.deep_copy_no_rows_to_path(dest_path)?;
+    let mut dest = Table::open(dest_path, TableOpenMode::ReadWrite)?;
+
+    // Figure out which rows survive.
+
+    let times: Vec<f64> = src.get_col_as_vec("TIME")?;
+    let (rows, n_timesteps_out) = select_rows(&times, options.n_timesteps);
+
+    // Copy them over wholesale, then go back and rewrite the columns that need
+    // to be trimmed or blanked.
+
+    dest.add_rows(rows.len())?;
+
+    {
+        let mut reader = src.get_row_reader()?;
+        let mut writer = dest.get_row_writer()?;
+
+        for (dest_row, &src_row) in rows.iter().enumerate() {
+            src.read_row(&mut reader, src_row)?;
+            reader.copy_and_put(&mut writer, dest_row as u64)?;
+        }
+    }
+
+    let col_names = src.column_names()?;
+
+    for &col_name in CHANNEL_COLUMNS {
+        if !col_names.iter().any(|n| n == col_name) {
+            continue;
+        }
+
+        let zero = options.zero_data && VISIBILITY_COLUMNS.contains(&col_name);
+
+        if options.n_channels.is_none() && !zero {
+            continue;
+        }
+
+        let n_channels = options.n_channels;
+        let data_type = src.get_col_desc(col_name)?.data_type();
+
+        match data_type {
+            GlueDataType::TpBool => rewrite_channel_column::<bool>(
+                &mut src, &mut dest, col_name, &rows, n_channels, None,
+            )?,
+
+            GlueDataType::TpFloat => rewrite_channel_column(
+                &mut src,
+                &mut dest,
+                col_name,
+                &rows,
+                n_channels,
+                if zero { Some(0f32) } else { None },
+            )?,
+
+            GlueDataType::TpDouble => rewrite_channel_column(
+                &mut src,
+                &mut dest,
+                col_name,
+                &rows,
+                n_channels,
+                if zero { Some(0f64) } else { None },
+            )?,
+
+            GlueDataType::TpComplex => rewrite_channel_column(
+                &mut src,
+                &mut dest,
+                col_name,
+                &rows,
+                n_channels,
+                if zero {
+                    Some(Complex::<f32>::new(0., 0.))
+                } else {
+                    None
+                },
+            )?,
+
+            GlueDataType::TpDComplex => rewrite_channel_column(
+                &mut src,
+                &mut dest,
+                col_name,
+                &rows,
+                n_channels,
+                if zero {
+                    Some(Complex::<f64>::new(0., 0.))
+                } else {
+                    None
+                },
+            )?,
+
+            other => {
+                return Err(UnexpectedDataTypeError(channel_column_type(col_name), other).into())
+            }
+        }
+    }
+
+    // Now the sub-tables, renumbering the IDs that refer to them.
+
+    let subtable_names = src.table_keyword_names()?;
+    let id_maps = kept_ids(&mut dest, src_path, &subtable_names)?;
+    remap_ids(&mut dest, None, &id_maps)?;
+
+    for kw_name in subtable_names {
+        let mut src_sub = Table::open(src_path.join(&kw_name), TableOpenMode::Read)?;
+        let mut dest_sub = Table::open(dest_path.join(&kw_name), TableOpenMode::ReadWrite)?;
+
+        if dest_sub.n_rows() == 0 {
+            src_sub.copy_rows_to(&mut dest_sub)?;
+        }
+
+        let own = id_maps
+            .iter()
+            .find(|(name, _)| *name == kw_name)
+            .map(|(_, map)| map);
+        remap_ids(&mut dest_sub, own, &id_maps)?;
+
+        if kw_name == "SPECTRAL_WINDOW" {
+            if let Some(n) = options.n_channels {
+                trim_spectral_windows(&mut dest_sub, n)?;
+            }
+        } else if kw_name == "OBSERVATION" && options.anonymize {
+            anonymize_observations(&mut dest_sub)?;
+        }
+    }
+
+    Ok(ShrinkSummary {
+        n_rows_in: src.n_rows(),
+        n_rows_out: rows.len() as u64,
+        n_timesteps_out,
+    })
+}
+
+/// Determine which rows to keep given the contents of the TIME column.
+///
+/// Returns the row numbers and the number of distinct timesteps that they
+/// span.
+fn select_rows(times: &[f64], n_timesteps: Option<usize>) -> (Vec<u64>, usize) {
+    let mut seen = Vec::new();
+    let mut rows = Vec::new();
+
+    for (row, &t) in times.iter().enumerate() {
+        if !seen.contains(&t) {
+            if let Some(n) = n_timesteps {
+                if seen.len() >= n {
+                    continue;
+                }
+            }
+
+            seen.push(t);
+        }
+
+        rows.push(row as u64);
+    }
+
+    (rows, seen.len())
+}
+
+/// The data type that a channelized main-table column conventionally has.
+fn channel_column_type(col_name: &str) -> GlueDataType {
+    match col_name {
+        "FLAG" => GlueDataType::TpBool,
+        "DATA" | "MODEL_DATA" | "CORRECTED_DATA" => GlueDataType::TpComplex,
+        _ => GlueDataType::TpFloat,
+    }
+}
+
+/// Work out which rows of the indexed sub-tables are referred to by the
+/// main table *main*, and how they will be renumbered.
+///
+/// A sub-table gets a mapping only if the main table has a column that
+/// refers to it, or, for `SPECTRAL_WINDOW`, if the data descriptions do.
+fn kept_ids(
+    main: &mut Table,
+    src_path: &Path,
+    subtable_names: &[String],
+) -> Result<Vec<(&'static str, IdMap)>, TableError> {
+    let col_names: BTreeSet<String> = main.column_names()?.into_iter().collect();
+    let mut maps: Vec<(&'static str, IdMap)> = Vec::new();
+
+    for &(subtable, id_cols) in INDEXED_SUBTABLES {
+        if !subtable_names.iter().any(|n| n == subtable) {
+            continue;
+        }
+
+        let mut used = BTreeSet::new();
+        let mut found = false;
+
+        for &col_name in id_cols {
+            if col_names.contains(col_name) {
+                found = true;
+                used.extend(main.get_col_as_vec::<i32>(col_name)?);
+            }
+        }
+
+        if subtable == "SPECTRAL_WINDOW" {
+            // The main table refers to spectral windows indirectly, through
+            // the data descriptions that it uses.
+            if let Some((_, ddids)) = maps.iter().find(|(n, _)| *n == "DATA_DESCRIPTION") {
+                let spws = read_data_desc_spws(src_path)?;
+                found = true;
+                used.extend(ddids.keys().filter_map(|&i| spws.get(i as usize).copied()));
+            }
+        }
+
+        if found {
+            let map = used
+                .into_iter()
+                .filter(|&id| id >= 0)
+                .enumerate()
+                .map(|(new, old)| (old, new as i32))
+                .collect();
+            maps.push((subtable, map));
+        }
+    }
+
+    Ok(maps)
+}
+
+/// Renumber the ID columns of *table* according to *maps*, removing any rows
+/// that refer to rows that are not kept.
+///
+/// If *own* is given, *table* is the sub-table that it indexes, and its rows
+/// that are not kept are removed as well.
+fn remap_ids(
+    table: &mut Table,
+    own: Option<&IdMap>,
+    maps: &[(&'static str, IdMap)],
+) -> Result<(), TableError> {
+    let col_names: BTreeSet<String> = table.column_names()?.into_iter().collect();
+    let n_rows = table.n_rows() as usize;
+    let mut keep: Vec<bool> = match own {
+        Some(map) => (0..n_rows)
+            .map(|row| map.contains_key(&(row as i32)))
+            .collect(),
+        None => vec![true; n_rows],
+    };
+
+    for (subtable, map) in maps {
+        let id_cols = INDEXED_SUBTABLES
+            .iter()
+            .find(|(name, _)| name == subtable)
+            .map_or(&[][..], |(_, cols)| cols);
+
+        for &col_name in id_cols {
+            if !col_names.contains(col_name) {
+                continue;
+            }
+
+            let mut ids: Vec<i32> = table.get_col_as_vec(col_name)?;
+
+            for (row, id) in ids.iter_mut().enumerate() {
+                if *id < 0 {
+                    continue;
+                }
+
+                match map.get(id) {
+                    Some(&new) => *id = new,
+                    None => keep[row] = false,
+                }
+            }
+
+            for (row, id) in ids.iter().enumerate() {
+                if keep[row] {
+                    table.put_cell(col_name, row as u64, id)?;
+                }
+            }
+        }
+    }
+
+    for row in (0..n_rows).rev() {
+        if !keep[row] {
+            table.remove_rows(row as u64, 1)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrite a channelized main-table column, trimming the channel axis and
+/// optionally overwriting the values.
+///
+/// In Rust ordering, MS cells have shape `(n_chan, n_pol)`.
+fn rewrite_channel_column<T: CasaScalarData + Copy>(
+    src: &mut Table,
+    dest: &mut Table,
+    col_name: &str,
+    rows: &[u64],
+    n_channels: Option<usize>,
+    fill: Option<T>,
+) -> Result<(), TableError> {
+    let desc = src.get_col_desc(col_name)?;
+    let mut cells = Vec::with_capacity(rows.len());
+
+    for &row in rows {
+        let cell: Array2<T> = src.get_cell(col_name, row)?;
+        let n_chan = n_channels.map_or(cell.shape()[0], |n| n.min(cell.shape()[0]));
+        let mut cell = cell.slice(s![..n_chan, ..]).to_owned();
+
+        if let Some(v) = fill {
+            cell.fill(v);
+        }
+
+        cells.push(cell);
+    }
+
+    if let (Some(old_shape), Some(first)) = (desc.shape(), cells.first()) {
+        let new_shape = [first.shape()[0] as u64, first.shape()[1] as u64];
+
+        if old_shape != &new_shape[..] {
+            dest.remove_column(col_name)?;
+            dest.add_array_column(
+                desc.data_type(),
+                col_name,
+                None,
+                Some(&new_shape),
+                false,
+                false,
+            )?;
+        }
+    }
+
+    for (i, cell) in cells.iter().enumerate() {
+        dest.put_cell(col_name, i as u64, cell)?;
+    }
+
+    Ok(())
+}
+
+/// Trim every spectral window in a `SPECTRAL_WINDOW` table to at most
+/// `n_channels` channels.
+fn trim_spectral_windows(spw: &mut Table, n_channels: usize) -> Result<(), TableError> {
+    let col_names: BTreeSet<String> = spw.column_names()?.into_iter().collect();
+
+    for row in 0..spw.n_rows() {
+        let mut n_chan_out = None;
+
+        for &col_name in SPW_CHANNEL_COLUMNS {
+            if !col_names.contains(col_name) {
+                continue;
+            }
+
+            let mut values: Vec<f64> = spw.get_cell(col_name, row)?;
+            values.truncate(n_channels);
+            spw.put_cell(col_name, row, &values)?;
+            n_chan_out = Some(values.len());
+
+            if col_name == "CHAN_WIDTH" && col_names.contains("TOTAL_BANDWIDTH") {
+                let total: f64 = values.iter().map(|w| w.abs()).sum();
+                spw.put_cell("TOTAL_BANDWIDTH", row, &total)?;
+            }
+        }
+
+        if let Some(n) = n_chan_out {
+            if col_names.contains("NUM_CHAN") {
+                spw.put_cell("NUM_CHAN", row, &(n as i32))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Blank out identifying information in an `OBSERVATION` table.
+fn anonymize_observations(obs: &mut Table) -> Result<(), TableError> {
+    let col_names: BTreeSet<String> = obs.column_names()?.into_iter().collect();
+    let blank = "anonymous".to_owned();
+
+    for row in 0..obs.n_rows() {
+        for &col_name in &["OBSERVER", "PROJECT"] {
+            if col_names.contains(col_name) {
+                obs.put_cell(col_name, row, &blank)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn select_rows_by_timestep() {
+        let times = [1., 1., 2., 2., 3., 3.];
+        assert_eq!(select_rows(&times, Some(2)), (vec![0, 1, 2, 3], 2));
+        assert_eq!(select_rows(&times, None), (vec![0, 1, 2, 3, 4, 5], 3));
+        assert_eq!(select_rows(&times, Some(0)), (vec![], 0));
+    }
+
+    #[test]
+    fn shrink_small_ms() {
+        let tmp_dir = tempdir().unwrap();
+        let src_path = tmp_dir.path().join("src.ms");
+        let dest_path = tmp_dir.path().join("dest.ms");
+
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpDouble, "TIME", None, false, false)
+            .unwrap();
+        desc.add_array_column(GlueDataType::TpComplex, "DATA", None, None, false, false)
+            .unwrap();
+        desc.set_ndims("DATA", 2).unwrap();
+        desc.add_array_column(
+            GlueDataType::TpBool,
+            "FLAG",
+            None,
+            Some(&[4, 2]),
+            false,
+            false,
+        )
+        .unwrap();
+
+        let mut table = Table::new(&src_path, desc, 6, TableCreateMode::New).unwrap();
+        let data = Array2::from_elem((4, 2), Complex::<f32>::new(1., 2.));
+        let flags = Array2::from_elem((4, 2), true);
+
+        for row in 0..6 {
+            table.put_cell("TIME", row, &((row / 2) as f64)).unwrap();
+            table.put_cell("DATA", row, &data).unwrap();
+            table.put_cell("FLAG", row, &flags).unwrap();
+        }
+
+        let mut spw_desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        spw_desc
+            .add_scalar_column(GlueDataType::TpInt, "NUM_CHAN", None, false, false)
+            .unwrap();
+        spw_desc
+            .add_array_column(
+                GlueDataType::TpDouble,
+                "CHAN_FREQ",
+                None,
+                None,
+                false,
+                false,
+            )
+            .unwrap();
+        spw_desc.set_ndims("CHAN_FREQ", 1).unwrap();
+
+        let mut spw = Table::new(
+            src_path.join("SPECTRAL_WINDOW"),
+            spw_desc,
+            1,
+            TableCreateMode::New,
+        )
+        .unwrap();
+        spw.put_cell("NUM_CHAN", 0, &4i32).unwrap();
+        spw.put_cell("CHAN_FREQ", 0, &vec![1e9, 2e9, 3e9, 4e9])
+            .unwrap();
+        table.put_table_keyword("SPECTRAL_WINDOW", spw).unwrap();
+        drop(table);
+
+        let options = ShrinkOptions {
+            n_timesteps: Some(2),
+            n_channels: Some(2),
+            zero_data: true,
+            anonymize: false,
+        };
+
+        let summary = shrink_ms(&src_path, &dest_path, &options).unwrap();
+        assert_eq!(summary.n_rows_in, 6);
+        assert_eq!(summary.n_rows_out, 4);
+        assert_eq!(summary.n_timesteps_out, 2);
+
+        let mut dest = Table::open(&dest_path, TableOpenMode::Read).unwrap();
+        assert_eq!(dest.n_rows(), 4);
+
+        let data: Array2<Complex<f32>> = dest.get_cell("DATA", 3).unwrap();
+        assert_eq!(data.shape(), &[2, 2]);
+        assert!(data.iter().all(|v| *v == Complex::new(0., 0.)));
+
+        assert_eq!(
+            dest.get_col_desc("FLAG").unwrap().shape(),
+            Some(&[2, 2][..])
+        );
+        let flags: Array2<bool> = dest.get_cell("FLAG", 0).unwrap();
+        assert!(flags.iter().all(|f| *f));
+
+        let mut spw = Table::open(dest_path.join("SPECTRAL_WINDOW"), TableOpenMode::Read).unwrap();
+        assert_eq!(spw.n_rows(), 1);
+        let freqs: Vec<f64> = spw.get_cell("CHAN_FREQ", 0).unwrap();
+        assert_eq!(freqs, vec![1e9, 2e9]);
+        let n_chan: i32 = spw.get_cell("NUM_CHAN", 0).unwrap();
+        assert_eq!(n_chan, 2);
+    }
+
+    /// Create a table at *path* with the given integer columns.
+    /// Named columns of integers, in the form taken by [`int_table`].
+    type IntColumns<'a> = [(&'a str, &'a [i32])];
+
+    fn int_table(path: &Path, cols: &IntColumns) -> Table {
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+
+        for (name, _) in cols {
+            desc.add_scalar_column(GlueDataType::TpInt, name, None, false, false)
+                .unwrap();
+        }
+
+        let n_rows = cols[0].1.len();
+        let mut table = Table::new(path, desc, n_rows, TableCreateMode::New).unwrap();
+
+        for (name, values) in cols {
+            for (row, v) in values.iter().enumerate() {
+                table.put_cell(name, row as u64, v).unwrap();
+            }
+        }
+
+        table
+    }
+
+    #[test]
+    fn shrink_remaps_ids() {
+        let tmp_dir = tempdir().unwrap();
+        let src_path = tmp_dir.path().join("src.ms");
+        let dest_path = tmp_dir.path().join("dest.ms");
+
+        let mut table = int_table(
+            &src_path,
+            &[
+                ("ANTENNA1", &[1, 3, 0, 0]),
+                ("ANTENNA2", &[3, 3, 2, 2]),
+                ("FIELD_ID", &[2, 2, 0, 0]),
+                ("DATA_DESC_ID", &[1, 1, 0, 0]),
+            ],
+        );
+        table
+            .add_scalar_column(GlueDataType::TpDouble, "TIME", None, false, false)
+            .unwrap();
+
+        for row in 0..4 {
+            table.put_cell("TIME", row, &((row / 2) as f64)).unwrap();
+        }
+
+        let subtables: &[(&str, &IntColumns)] = &[
+            ("ANTENNA", &[("TAG", &[10, 11, 12, 13])]),
+            ("FIELD", &[("TAG", &[20, 21, 22])]),
+            ("DATA_DESCRIPTION", &[("SPECTRAL_WINDOW_ID", &[0, 2])]),
+            ("SPECTRAL_WINDOW", &[("TAG", &[30, 31, 32])]),
+            (
+                "FEED",
+                &[
+                    ("ANTENNA_ID", &[0, 1, 3, 3]),
+                    ("SPECTRAL_WINDOW_ID", &[-1, -1, 2, 0]),
+                ],
+            ),
+        ];
+
+        for (name, cols) in subtables {
+            let sub = int_table(&src_path.join(name), cols);
+            table.put_table_keyword(name, sub).unwrap();
+        }
+
+        drop(table);
+
+        let options = ShrinkOptions {
+            n_timesteps: Some(1),
+            ..Default::default()
+        };
+
+        shrink_ms(&src_path, &dest_path, &options).unwrap();
+
+        let mut dest = Table::open(&dest_path, TableOpenMode::Read).unwrap();
+        let col = |t: &mut Table, name: &str| t.get_col_as_vec::<i32>(name).unwrap();
+        assert_eq!(col(&mut dest, "ANTENNA1"), vec![0, 1]);
+        assert_eq!(col(&mut dest, "ANTENNA2"), vec![1, 1]);
+        assert_eq!(col(&mut dest, "FIELD_ID"), vec![0, 0]);
+        assert_eq!(col(&mut dest, "DATA_DESC_ID"), vec![0, 0]);
+
+        let sub = |name: &str| Table::open(dest_path.join(name), TableOpenMode::Read).unwrap();
+        assert_eq!(col(&mut sub("ANTENNA"), "TAG"), vec![11, 13]);
+        assert_eq!(col(&mut sub("FIELD"), "TAG"), vec![22]);
+        assert_eq!(
+            col(&mut sub("DATA_DESCRIPTION"), "SPECTRAL_WINDOW_ID"),
+            vec![0]
+        );
+        assert_eq!(col(&mut sub("SPECTRAL_WINDOW"), "TAG"), vec![32]);
+
+        let mut feed = sub("FEED");
+        assert_eq!(col(&mut feed, "ANTENNA_ID"), vec![0, 1]);
+        assert_eq!(col(&mut feed, "SPECTRAL_WINDOW_ID"), vec![-1, 0]);
+    }
+
+    #[test]
+    fn wrong_channel_column_type() {
+        let tmp_dir = tempdir().unwrap();
+        let src_path = tmp_dir.path().join("src.ms");
+
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpDouble, "TIME", None, false, false)
+            .unwrap();
+        desc.add_array_column(GlueDataType::TpInt, "FLAG", None, Some(&[4]), false, false)
+            .unwrap();
+        Table::new(&src_path, desc, 1, TableCreateMode::New).unwrap();
+
+        let options = ShrinkOptions {
+            n_channels: Some(2),
+            ..Default::default()
+        };
+
+        match shrink_ms(&src_path, tmp_dir.path().join("dest.ms"), &options) {
+            Err(TableError::UnexpectedDataType(UnexpectedDataTypeError(expected, found))) => {
+                assert_eq!(expected, GlueDataType::TpBool);
+                assert_eq!(found, GlueDataType::TpInt);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,537 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Simulating interferometric observations of point sources.
+//!
+//! [`crate::testing::synthetic_ms`] fills a data set with arbitrary but
+//! easily checked values. [`simulate`] instead computes physically
+//! meaningful visibilities: given an array layout, a list of point sources,
+//! and the parameters of an observation, it works out each baseline’s UVW
+//! coordinates as the Earth rotates and sums the sources’ contributions in
+//! every channel, writing a complete Measurement Set with a
+//! [`StreamWriter`]. The results can be fed to imaging and calibration code
+//! whose output is known in advance.
+//!
+//! The geometry is deliberately simple. The Earth’s orientation is given by
+//! the Earth rotation angle alone, ignoring precession, nutation, polar
+//! motion, and the difference between UT1 and UTC, and source and phase
+//! center coordinates are taken to be apparent coordinates of date. This is
+//! self-consistent, which is what matters for test data, but the UVW
+//! coordinates will not match those computed by casacore to better than
+//! about a part in a thousand.
+//!
+//! ```no_run
+//! use rubbl_casatables::ms::{simulate, PointSource, SimulationSpec, SpectralWindow};
+//! use rubbl_core::array_layout::AntennaLayout;
+//!
+//! let layout = AntennaLayout::open_itrf_csv("vla.csv").unwrap();
+//! let mut spec = SimulationSpec::new(layout, (3.0, 0.5), SpectralWindow::uniform(64, 1.4e9, 1e6));
+//! spec.sources.push(PointSource::new(3.0, 0.5, 1.0));
+//! spec.n_timesteps = 100;
+//!
+//! let ms = simulate("sim.ms", &spec).unwrap();
+//! ```
+
+use ndarray::{Array2, Array3};
+use rubbl_core::{array_layout::AntennaLayout, constants::SPEED_OF_LIGHT, stokes::Stokes};
+use std::{f64::consts::PI, path::Path};
+use thiserror::Error;
+
+use super::{schema, write_antenna_layout, Feed, SpectralWindow, StreamError, StreamWriter};
+use crate::{Complex, Table, TableError, TableOpenMode};
+
+/// An error that can occur when simulating a data set.
+#[derive(Error, Debug)]
+pub enum SimulationError {
+    /// An error occurred while creating the data set.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// An error occurred while writing the visibilities.
+    #[error(transparent)]
+    Stream(#[from] StreamError),
+
+    /// The simulation parameters are not valid.
+    #[error("invalid simulation parameters: {0}")]
+    InvalidSpec(String),
+}
+
+/// An unpolarized point source with a power-law spectrum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PointSource {
+    /// The right ascension of the source, in radians.
+    pub ra: f64,
+
+    /// The declination of the source, in radians.
+    pub dec: f64,
+
+    /// The total flux density of the source at [`Self::ref_freq`], in Jy.
+    pub flux: f64,
+
+    /// The spectral index α, so that the flux density scales as (ν/ν₀)^α.
+    pub spectral_index: f64,
+
+    /// The reference frequency ν₀ of the spectrum, in Hz.
+    pub ref_freq: f64,
+}
+
+impl PointSource {
+    /// Create a source with a flat spectrum.
+    pub fn new(ra: f64, dec: f64, flux: f64) -> Self {
+        PointSource {
+            ra,
+            dec,
+            flux,
+            spectral_index: 0.,
+            ref_freq: 1e9,
+        }
+    }
+
+    /// Get the flux density of the source at frequency *freq*, in Jy.
+    pub fn flux_at(&self, freq: f64) -> f64 {
+        if self.spectral_index == 0. {
+            self.flux
+        } else {
+            self.flux * (freq / self.ref_freq).powf(self.spectral_index)
+        }
+    }
+}
+
+/// The parameters of a simulated observation, used by [`simulate`].
+#[derive(Clone, Debug)]
+pub struct SimulationSpec {
+    /// The names, positions, and sizes of the antennas.
+    pub layout: AntennaLayout,
+
+    /// The name of the telescope.
+    pub telescope_name: String,
+
+    /// The right ascension and declination of the phase center, in radians.
+    pub phase_center: (f64, f64),
+
+    /// The sources to observe.
+    pub sources: Vec<PointSource>,
+
+    /// The spectral window of the observation.
+    pub spw: SpectralWindow,
+
+    /// The number of polarization products: 1 (XX only), 2 (XX and YY), or 4
+    /// (XX, XY, YX, and YY).
+    pub n_pols: usize,
+
+    /// The start of the first integration, as an MJD in seconds.
+    pub start_time: f64,
+
+    /// The duration of each integration, in seconds.
+    pub integration_time: f64,
+
+    /// The number of integrations.
+    pub n_timesteps: usize,
+
+    /// Whether to include autocorrelation baselines.
+    pub autocorrelations: bool,
+
+    /// Whether to include the w term in the phases of the visibilities. If
+    /// false, the visibilities are those of a perfectly coplanar array, which
+    /// can be imaged exactly with a plain Fourier transform.
+    pub w_term: bool,
+}
+
+impl SimulationSpec {
+    /// Create the parameters of an observation with no sources.
+    ///
+    /// The observation has one 10-second integration, starting at
+    /// 2024 January 1, 00:00 UTC, with XX and YY polarization products and no
+    /// autocorrelations.
+    pub fn new(layout: AntennaLayout, phase_center: (f64, f64), spw: SpectralWindow) -> Self {
+        SimulationSpec {
+            layout,
+            telescope_name: "SIMULATED".to_owned(),
+            phase_center,
+            sources: Vec::new(),
+            spw,
+            n_pols: 2,
+            start_time: 60310. * 86400.,
+            integration_time: 10.,
+            n_timesteps: 1,
+            autocorrelations: false,
+            w_term: true,
+        }
+    }
+
+    /// Get the `(ANTENNA1, ANTENNA2)` pairs of the baselines, in the order in
+    /// which they appear in each integration.
+    pub fn baselines(&self) -> Vec<(i32, i32)> {
+        let n_ants = self.layout.len() as i32;
+        let mut baselines = Vec::new();
+
+        for ant1 in 0..n_ants {
+            for ant2 in ant1..n_ants {
+                if ant1 != ant2 || self.autocorrelations {
+                    baselines.push((ant1, ant2));
+                }
+            }
+        }
+
+        baselines
+    }
+
+    pub(crate) fn corr_types(&self) -> Result<&'static [Stokes], SimulationError> {
+        match self.n_pols {
+            1 => Ok(&[Stokes::XX]),
+            2 => Ok(&[Stokes::XX, Stokes::YY]),
+            4 => Ok(&[Stokes::XX, Stokes::XY, Stokes::YX, Stokes::YY]),
+            n => Err(SimulationError::InvalidSpec(format!(
+                "unsupported number of polarizations: {}",
+                n
+            ))),
+        }
+    }
+}
+
+/// Get the Earth rotation angle, in radians, at a time given as an MJD in
+/// seconds.
+///
+/// This is the angle between the Greenwich meridian and the celestial
+/// intermediate origin, and is used here as an approximation to Greenwich
+/// apparent sidereal time.
+pub fn earth_rotation_angle(mjd_seconds: f64) -> f64 {
+    let days = mjd_seconds / 86400. - 51544.5;
+    let turns = 0.779_057_273_264 + 0.002_737_811_911_354_48 * days + days.fract();
+    2. * PI * turns.rem_euclid(1.)
+}
+
+/// Get the UVW coordinates, in meters, of a baseline.
+///
+/// *baseline* is the difference between two antennas’ ITRF positions,
+/// *hour_angle* is the Greenwich hour angle of the phase center, and *dec*
+/// its declination, both in radians.
+pub fn baseline_uvw(baseline: [f64; 3], hour_angle: f64, dec: f64) -> [f64; 3] {
+    let (sh, ch) = hour_angle.sin_cos();
+    let (sd, cd) = dec.sin_cos();
+    let [x, y, z] = baseline;
+
+    [
+        sh * x + ch * y,
+        -sd * ch * x + sd * sh * y + cd * z,
+        cd * ch * x - cd * sh * y + sd * z,
+    ]
+}
+
+/// Get the direction cosines (l, m, n) of a source relative to a phase
+/// center. All angles are in radians.
+pub fn direction_cosines(ra: f64, dec: f64, center_ra: f64, center_dec: f64) -> [f64; 3] {
+    let (sd, cd) = dec.sin_cos();
+    let (sd0, cd0) = center_dec.sin_cos();
+    let (sa, ca) = (ra - center_ra).sin_cos();
+
+    [cd * sa, sd * cd0 - cd * sd0 * ca, sd * sd0 + cd * cd0 * ca]
+}
+
+/// Create a Measurement Set at *path* holding the simulated visibilities of
+/// an observation.
+///
+/// The data set has a single field, spectral window, and polarization setup,
+/// and contains the standard sub-tables defined in [`crate::ms::schema`].
+/// Rows are ordered by time and then by baseline, as given by
+/// [`SimulationSpec::baselines`], and `UVW` is the position of `ANTENNA2`
+/// minus that of `ANTENNA1`. Each visibility is
+///
+/// V = Σ S(ν) exp(−2πi ν (ul + vm + w(n − 1)) / c)
+///
+/// summed over the sources, where *S*(ν) is a source’s flux density and
+/// (*l*, *m*, *n*) its direction cosines. The sources are unpolarized, so
+/// the XX and YY products hold V while XY and YX are zero. Weights are unity
+/// and nothing is flagged.
+pub fn simulate<P: AsRef<Path>>(path: P, spec: &SimulationSpec) -> Result<Table, SimulationError> {
+    let corr_types = spec.corr_types()?;
+    let n_ants = spec.layout.len();
+
+    if n_ants == 0 {
+        return Err(SimulationError::InvalidSpec(
+            "there are no antennas".to_owned(),
+        ));
+    }
+
+    if spec.layout.names.len() != n_ants || spec.layout.diameters.len() != n_ants {
+        return Err(SimulationError::InvalidSpec(
+            "the antenna layout has inconsistent lengths".to_owned(),
+        ));
+    }
+
+    spec.spw
+        .validate()
+        .map_err(|e| SimulationError::InvalidSpec(e.to_string()))?;
+
+    let path = path.as_ref();
+    let main = Table::create_with_default_subtables(path, 0)?;
+
+    fill_subtables(
+        path,
+        &SubtableContents {
+            layout: &spec.layout,
+            telescope_name: &spec.telescope_name,
+            field_name: "simulated",
+            phase_dir: [spec.phase_center.0, spec.phase_center.1],
+            spw: &spec.spw,
+            corr_types,
+            start_time: spec.start_time,
+            end_time: spec.start_time + spec.n_timesteps as f64 * spec.integration_time,
+        },
+    )?;
+
+    let baselines = spec.baselines();
+    let n_bl = baselines.len();
+    let n_chans = spec.spw.n_chans();
+    let n_pols = spec.n_pols;
+    let (ra0, dec0) = spec.phase_center;
+
+    let lmn: Vec<[f64; 3]> = spec
+        .sources
+        .iter()
+        .map(|s| direction_cosines(s.ra, s.dec, ra0, dec0))
+        .collect();
+    let fluxes: Vec<Vec<f64>> = spec
+        .sources
+        .iter()
+        .map(|s| spec.spw.chan_freq.iter().map(|f| s.flux_at(*f)).collect())
+        .collect();
+    let parallel_hands: Vec<usize> = corr_types
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| matches!(s, Stokes::XX | Stokes::YY))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut writer = StreamWriter::new(main, baselines.clone(), spec.integration_time)
+        .grow_rows(n_bl * spec.n_timesteps);
+    let mut uvw = Array2::<f64>::zeros((n_bl, 3));
+    let mut data = Array3::<Complex<f32>>::zeros((n_bl, n_chans, n_pols));
+    let flags = Array3::from_elem((n_bl, n_chans, n_pols), false);
+    let weights = Array2::from_elem((n_bl, n_pols), 1f32);
+
+    for t in 0..spec.n_timesteps {
+        let time = spec.start_time + (t as f64 + 0.5) * spec.integration_time;
+        let hour_angle = earth_rotation_angle(time) - ra0;
+
+        for (b, (ant1, ant2)) in baselines.iter().enumerate() {
+            let baseline = spec.layout.baseline(*ant1 as usize, *ant2 as usize);
+            let bl_uvw = baseline_uvw(baseline, hour_angle, dec0);
+
+            for i in 0..3 {
+                uvw[[b, i]] = bl_uvw[i];
+            }
+
+            for c in 0..n_chans {
+                let scale = -2. * PI * spec.spw.chan_freq[c] / SPEED_OF_LIGHT;
+                let mut vis = Complex::new(0f64, 0.);
+
+                for (s, [l, m, n]) in lmn.iter().enumerate() {
+                    let mut delay = bl_uvw[0] * l + bl_uvw[1] * m;
+
+                    if spec.w_term {
+                        delay += bl_uvw[2] * (n - 1.);
+                    }
+
+                    vis += Complex::from_polar(fluxes[s][c], scale * delay);
+                }
+
+                for p in &parallel_hands {
+                    data[[b, c, *p]] = Complex::new(vis.re as f32, vis.im as f32);
+                }
+            }
+        }
+
+        writer.write_integration(time, uvw.view(), data.view(), flags.view(), weights.view())?;
+    }
+
+    Ok(writer.finish()?)
+}
+
+/// The contents of the standard sub-tables of a data set with one field,
+/// spectral window, and polarization setup.
+pub(crate) struct SubtableContents<'a> {
+    pub layout: &'a AntennaLayout,
+    pub telescope_name: &'a str,
+    pub field_name: &'a str,
+    pub phase_dir: [f64; 2],
+    pub spw: &'a SpectralWindow,
+    pub corr_types: &'a [Stokes],
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+/// Fill in the sub-tables of a newly created data set at *path*.
+pub(crate) fn fill_subtables(path: &Path, contents: &SubtableContents) -> Result<(), TableError> {
+    let n_ants = contents.layout.len();
+
+    for sub_spec in schema::REQUIRED_SUBTABLES {
+        let n_rows = match sub_spec.name {
+            "ANTENNA" | "FEED" => n_ants,
+            "DATA_DESCRIPTION" | "FIELD" | "OBSERVATION" | "POLARIZATION" | "PROCESSOR"
+            | "SPECTRAL_WINDOW" => 1,
+            _ => 0,
+        };
+
+        if n_rows == 0 {
+            continue;
+        }
+
+        let mut sub = Table::open(path.join(sub_spec.name), TableOpenMode::ReadWrite)?;
+        sub.add_rows(n_rows)?;
+        fill_subtable(sub_spec.name, &mut sub, contents)?;
+    }
+
+    Ok(())
+}
+
+fn fill_subtable(
+    name: &str,
+    sub: &mut Table,
+    contents: &SubtableContents,
+) -> Result<(), TableError> {
+    match name {
+        "ANTENNA" => write_antenna_layout(sub, contents.layout)?,
+
+        "DATA_DESCRIPTION" => {
+            sub.put_cell("SPECTRAL_WINDOW_ID", 0, &0i32)?;
+            sub.put_cell("POLARIZATION_ID", 0, &0i32)?;
+            sub.put_cell("FLAG_ROW", 0, &false)?;
+        }
+
+        "FEED" => {
+            for i in 0..contents.layout.len() {
+                let mut feed = Feed::ideal(i as i32, &["X", "Y"]);
+                feed.time = contents.start_time;
+                feed.receptor_angle = vec![0., std::f64::consts::FRAC_PI_2];
+                feed.write(sub, i as u64)?;
+            }
+        }
+
+        "FIELD" => {
+            let dir = Array2::from_shape_vec((1, 2), contents.phase_dir.to_vec()).unwrap();
+            sub.put_cell("NAME", 0, &contents.field_name.to_owned())?;
+            sub.put_cell("CODE", 0, &"".to_owned())?;
+            sub.put_cell("TIME", 0, &contents.start_time)?;
+            sub.put_cell("NUM_POLY", 0, &0i32)?;
+            sub.put_cell("DELAY_DIR", 0, &dir)?;
+            sub.put_cell("PHASE_DIR", 0, &dir)?;
+            sub.put_cell("REFERENCE_DIR", 0, &dir)?;
+            sub.put_cell("SOURCE_ID", 0, &-1i32)?;
+            sub.put_cell("FLAG_ROW", 0, &false)?;
+        }
+
+        "OBSERVATION" => {
+            sub.put_cell("TELESCOPE_NAME", 0, &contents.telescope_name.to_owned())?;
+            sub.put_cell(
+                "TIME_RANGE",
+                0,
+                &vec![contents.start_time, contents.end_time],
+            )?;
+            sub.put_cell("OBSERVER", 0, &"rubbl".to_owned())?;
+            sub.put_cell("SCHEDULE_TYPE", 0, &"".to_owned())?;
+            sub.put_cell("PROJECT", 0, &contents.field_name.to_owned())?;
+            sub.put_cell("RELEASE_DATE", 0, &0f64)?;
+            sub.put_cell("FLAG_ROW", 0, &false)?;
+        }
+
+        "POLARIZATION" => {
+            let corr_types = contents.corr_types;
+            let corr_product = Array2::from_shape_fn((corr_types.len(), 2), |(i, j)| {
+                corr_types[i].receptors().unwrap()[j]
+            });
+            let codes: Vec<i32> = corr_types.iter().map(|s| s.code()).collect();
+
+            sub.put_cell("NUM_CORR", 0, &(corr_types.len() as i32))?;
+            sub.put_cell("CORR_TYPE", 0, &codes)?;
+            sub.put_cell("CORR_PRODUCT", 0, &corr_product)?;
+            sub.put_cell("FLAG_ROW", 0, &false)?;
+        }
+
+        "PROCESSOR" => {
+            sub.put_cell("TYPE", 0, &"CORRELATOR".to_owned())?;
+            sub.put_cell("SUB_TYPE", 0, &"".to_owned())?;
+            sub.put_cell("TYPE_ID", 0, &-1i32)?;
+            sub.put_cell("MODE_ID", 0, &-1i32)?;
+            sub.put_cell("FLAG_ROW", 0, &false)?;
+        }
+
+        "SPECTRAL_WINDOW" => {
+            contents.spw.write(sub, 0)?;
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ms::read_antenna_layout;
+    use tempfile::tempdir;
+
+    #[test]
+    fn geometry() {
+        // A baseline along the Earth's axis always points at the pole.
+        let uvw = baseline_uvw([0., 0., 100.], 1.234, 0.3);
+        assert!(uvw[0].abs() < 1e-9);
+        assert!((uvw[1] - 100. * 0.3f64.cos()).abs() < 1e-9);
+        assert!((uvw[2] - 100. * 0.3f64.sin()).abs() < 1e-9);
+
+        // The length of a baseline is preserved.
+        let uvw = baseline_uvw([30., -40., 0.], 0.7, -0.2);
+        let len = uvw.iter().map(|x| x * x).sum::<f64>().sqrt();
+        assert!((len - 50.).abs() < 1e-9);
+
+        // One stellar day later, the Earth has turned once.
+        let t0 = 5e9;
+        let era0 = earth_rotation_angle(t0);
+        let era1 = earth_rotation_angle(t0 + 86164.0989);
+        assert!((era1 - era0).abs() < 1e-6 || (era1 - era0).abs() > 2. * PI - 1e-6);
+
+        assert_eq!(direction_cosines(1., 0.5, 1., 0.5), [0., 0., 1.]);
+    }
+
+    #[test]
+    fn point_source_at_phase_center() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("sim.ms");
+        let mut layout = AntennaLayout::new();
+        layout.push("A", [6378137., 0., 0.], 12.);
+        layout.push("B", [6378137., 100., 0.], 12.);
+        layout.push("C", [6378137., 0., 250.], 12.);
+        let mut spec =
+            SimulationSpec::new(layout, (0.5, -0.4), SpectralWindow::uniform(8, 1.4e9, 1e6));
+        spec.n_pols = 4;
+        spec.n_timesteps = 3;
+        spec.sources.push(PointSource::new(0.5, -0.4, 2.5));
+        spec.sources.push(PointSource {
+            spectral_index: -0.7,
+            ..PointSource::new(0.5 + 1e-3, -0.4, 1.)
+        });
+
+        let mut ms = simulate(&path, &spec).unwrap();
+        assert_eq!(ms.n_rows(), 9);
+
+        let mut antennas = Table::open(path.join("ANTENNA"), TableOpenMode::Read).unwrap();
+        assert_eq!(read_antenna_layout(&mut antennas).unwrap(), spec.layout);
+
+        let data: Vec<Complex<f32>> = ms.get_cell_as_vec("DATA", 4).unwrap();
+        assert_eq!(data.len(), 8 * 4);
+        assert_eq!(data[1], Complex::new(0., 0.));
+        assert_eq!(data[0], data[3]);
+
+        // With the second source removed, the visibilities are constant.
+        spec.sources.pop();
+        let mut ms = simulate(tmp_dir.path().join("sim2.ms"), &spec).unwrap();
+        let data: Vec<Complex<f32>> = ms.get_cell_as_vec("DATA", 7).unwrap();
+        assert!((data[0].re - 2.5).abs() < 1e-6);
+        assert!(data[0].im.abs() < 1e-6);
+
+        let uvw: Vec<f64> = ms.get_cell_as_vec("UVW", 0).unwrap();
+        let len = uvw.iter().map(|x| x * x).sum::<f64>().sqrt();
+        assert!((len - 100.).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,334 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! The frequency setup of a spectral window.
+
+use thiserror::Error;
+
+use crate::{Table, TableError};
+
+/// The `MEAS_FREQ_REF` code of the topocentric frequency frame.
+pub const FREQ_REF_TOPO: i32 = 5;
+
+/// An error that can occur when validating or transforming a
+/// [`SpectralWindow`].
+#[derive(Error, Debug, PartialEq)]
+pub enum SpectralWindowError {
+    /// The window has no channels.
+    #[error("spectral window has no channels")]
+    Empty,
+
+    /// A per-channel array has the wrong number of elements.
+    #[error("spectral window has {n_chans} channels but {column} has {actual} elements")]
+    LengthMismatch {
+        /// The name of the offending column.
+        column: &'static str,
+        /// The number of channels, as given by `CHAN_FREQ`.
+        n_chans: usize,
+        /// The number of elements in the offending column.
+        actual: usize,
+    },
+
+    /// A frequency or width is not a finite number.
+    #[error("spectral window channel {0} has a non-finite frequency or width")]
+    NonFinite(usize),
+
+    /// A channel has zero width.
+    #[error("spectral window channel {0} has zero width")]
+    ZeroWidth(usize),
+
+    /// The channel frequencies do not increase or decrease consistently with
+    /// the sign of the channel widths.
+    #[error("spectral window channel {0} is out of order")]
+    NotMonotonic(usize),
+
+    /// The number of channels is not a multiple of an averaging factor.
+    #[error("cannot average {n_chans} channels by a factor of {factor}")]
+    BadAveragingFactor {
+        /// The number of channels.
+        n_chans: usize,
+        /// The averaging factor.
+        factor: usize,
+    },
+}
+
+/// The sideband of a spectral window.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Sideband {
+    /// Frequencies decrease with channel number.
+    Lower,
+
+    /// Frequencies increase with channel number.
+    Upper,
+}
+
+impl Sideband {
+    /// Get the value used for this sideband in the `NET_SIDEBAND` column.
+    pub fn code(self) -> i32 {
+        match self {
+            Sideband::Lower => -1,
+            Sideband::Upper => 1,
+        }
+    }
+
+    /// Get the sideband for a `NET_SIDEBAND` value. Negative values indicate
+    /// the lower sideband, and all others the upper sideband.
+    pub fn from_code(code: i32) -> Self {
+        if code < 0 {
+            Sideband::Lower
+        } else {
+            Sideband::Upper
+        }
+    }
+}
+
+/// The frequency setup of one spectral window, as recorded in a row of the
+/// `SPECTRAL_WINDOW` sub-table of a Measurement Set.
+///
+/// Frequencies and widths are in Hz. Following the Measurement Set
+/// conventions, channel widths are negative if frequencies decrease with
+/// channel number, while effective bandwidths and resolutions are always
+/// positive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpectralWindow {
+    /// The name of the window.
+    pub name: String,
+
+    /// The center frequency of each channel.
+    pub chan_freq: Vec<f64>,
+
+    /// The width of each channel.
+    pub chan_width: Vec<f64>,
+
+    /// The effective noise bandwidth of each channel.
+    pub effective_bw: Vec<f64>,
+
+    /// The effective spectral resolution of each channel.
+    pub resolution: Vec<f64>,
+
+    /// The reference frequency of the window.
+    pub ref_frequency: f64,
+
+    /// The net sideband of the window.
+    pub net_sideband: Sideband,
+
+    /// The frequency reference frame, as a casacore `MFrequency::Types`
+    /// code.
+    pub meas_freq_ref: i32,
+}
+
+impl SpectralWindow {
+    /// Create a window of equally spaced, equally wide, topocentric channels.
+    ///
+    /// The reference frequency is the frequency of the first channel. A
+    /// negative *chan_width* gives a lower-sideband window.
+    ///
+    /// ```rust
+    /// use rubbl_casatables::ms::SpectralWindow;
+    ///
+    /// let spw = SpectralWindow::uniform(4, 1.4e9, 1e6);
+    /// assert_eq!(spw.chan_freq, vec![1.400e9, 1.401e9, 1.402e9, 1.403e9]);
+    /// assert_eq!(spw.total_bandwidth(), 4e6);
+    /// assert!(spw.validate().is_ok());
+    /// ```
+    pub fn uniform(n_chans: usize, first_freq: f64, chan_width: f64) -> Self {
+        SpectralWindow {
+            name: String::new(),
+            chan_freq: (0..n_chans)
+                .map(|c| first_freq + c as f64 * chan_width)
+                .collect(),
+            chan_width: vec![chan_width; n_chans],
+            effective_bw: vec![chan_width.abs(); n_chans],
+            resolution: vec![chan_width.abs(); n_chans],
+            ref_frequency: first_freq,
+            net_sideband: if chan_width < 0. {
+                Sideband::Lower
+            } else {
+                Sideband::Upper
+            },
+            meas_freq_ref: FREQ_REF_TOPO,
+        }
+    }
+
+    /// Get the number of channels.
+    pub fn n_chans(&self) -> usize {
+        self.chan_freq.len()
+    }
+
+    /// Get the total bandwidth, the sum of the absolute channel widths.
+    pub fn total_bandwidth(&self) -> f64 {
+        self.chan_width.iter().map(|w| w.abs()).sum()
+    }
+
+    /// Check that the window is self-consistent.
+    ///
+    /// The window must have at least one channel, all of the per-channel
+    /// arrays must have the same length, frequencies and widths must be
+    /// finite, widths must be nonzero, and frequencies must be strictly
+    /// monotonic in the direction given by the signs of the widths.
+    pub fn validate(&self) -> Result<(), SpectralWindowError> {
+        let n_chans = self.n_chans();
+
+        if n_chans == 0 {
+            return Err(SpectralWindowError::Empty);
+        }
+
+        for (column, actual) in [
+            ("CHAN_WIDTH", self.chan_width.len()),
+            ("EFFECTIVE_BW", self.effective_bw.len()),
+            ("RESOLUTION", self.resolution.len()),
+        ] {
+            if actual != n_chans {
+                return Err(SpectralWindowError::LengthMismatch {
+                    column,
+                    n_chans,
+                    actual,
+                });
+            }
+        }
+
+        for c in 0..n_chans {
+            if !self.chan_freq[c].is_finite() || !self.chan_width[c].is_finite() {
+                return Err(SpectralWindowError::NonFinite(c));
+            }
+
+            if self.chan_width[c] == 0. {
+                return Err(SpectralWindowError::ZeroWidth(c));
+            }
+
+            if c > 0 {
+                let step = self.chan_freq[c] - self.chan_freq[c - 1];
+
+                if step == 0. || step.signum() != self.chan_width[c].signum() {
+                    return Err(SpectralWindowError::NotMonotonic(c));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create a window with groups of *factor* adjacent channels averaged
+    /// together.
+    ///
+    /// The frequencies of the new channels are the means of those of the
+    /// old ones, and the widths, effective bandwidths, and resolutions are
+    /// the sums. The number of channels must be a multiple of *factor*.
+    pub fn averaged(&self, factor: usize) -> Result<Self, SpectralWindowError> {
+        let n_chans = self.n_chans();
+
+        if factor == 0 || !n_chans.is_multiple_of(factor) {
+            return Err(SpectralWindowError::BadAveragingFactor { n_chans, factor });
+        }
+
+        let mean = |v: &[f64]| -> Vec<f64> {
+            v.chunks(factor)
+                .map(|c| c.iter().sum::<f64>() / factor as f64)
+                .collect()
+        };
+        let sum = |v: &[f64]| -> Vec<f64> { v.chunks(factor).map(|c| c.iter().sum()).collect() };
+
+        Ok(SpectralWindow {
+            name: self.name.clone(),
+            chan_freq: mean(&self.chan_freq),
+            chan_width: sum(&self.chan_width),
+            effective_bw: sum(&self.effective_bw),
+            resolution: sum(&self.resolution),
+            ref_frequency: self.ref_frequency,
+            net_sideband: self.net_sideband,
+            meas_freq_ref: self.meas_freq_ref,
+        })
+    }
+
+    /// Read a window from a row of a `SPECTRAL_WINDOW` table.
+    pub fn read(table: &mut Table, row: u64) -> Result<Self, TableError> {
+        let net_sideband: i32 = table.get_cell("NET_SIDEBAND", row)?;
+
+        Ok(SpectralWindow {
+            name: table.get_cell("NAME", row)?,
+            chan_freq: table.get_cell_as_vec("CHAN_FREQ", row)?,
+            chan_width: table.get_cell_as_vec("CHAN_WIDTH", row)?,
+            effective_bw: table.get_cell_as_vec("EFFECTIVE_BW", row)?,
+            resolution: table.get_cell_as_vec("RESOLUTION", row)?,
+            ref_frequency: table.get_cell("REF_FREQUENCY", row)?,
+            net_sideband: Sideband::from_code(net_sideband),
+            meas_freq_ref: table.get_cell("MEAS_FREQ_REF", row)?,
+        })
+    }
+
+    /// Read all of the windows in a `SPECTRAL_WINDOW` table.
+    pub fn read_all(table: &mut Table) -> Result<Vec<Self>, TableError> {
+        (0..table.n_rows())
+            .map(|row| Self::read(table, row))
+            .collect()
+    }
+
+    /// Write this window into an existing row of a `SPECTRAL_WINDOW` table.
+    ///
+    /// Besides the fields of this type, this fills in `NUM_CHAN`,
+    /// `TOTAL_BANDWIDTH`, and the other required columns with default
+    /// values.
+    pub fn write(&self, table: &mut Table, row: u64) -> Result<(), TableError> {
+        table.put_cell("NUM_CHAN", row, &(self.n_chans() as i32))?;
+        table.put_cell("NAME", row, &self.name)?;
+        table.put_cell("REF_FREQUENCY", row, &self.ref_frequency)?;
+        table.put_cell("CHAN_FREQ", row, &self.chan_freq)?;
+        table.put_cell("CHAN_WIDTH", row, &self.chan_width)?;
+        table.put_cell("MEAS_FREQ_REF", row, &self.meas_freq_ref)?;
+        table.put_cell("EFFECTIVE_BW", row, &self.effective_bw)?;
+        table.put_cell("RESOLUTION", row, &self.resolution)?;
+        table.put_cell("TOTAL_BANDWIDTH", row, &self.total_bandwidth())?;
+        table.put_cell("NET_SIDEBAND", row, &self.net_sideband.code())?;
+        table.put_cell("IF_CONV_CHAIN", row, &0i32)?;
+        table.put_cell("FREQ_GROUP", row, &0i32)?;
+        table.put_cell("FREQ_GROUP_NAME", row, &String::new())?;
+        table.put_cell("FLAG_ROW", row, &false)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use crate::TableOpenMode;
+    use tempfile::tempdir;
+
+    #[test]
+    fn validation_and_averaging() {
+        let spw = SpectralWindow::uniform(6, 1e9, -2e6);
+        assert_eq!(spw.net_sideband, Sideband::Lower);
+        spw.validate().unwrap();
+
+        let avg = spw.averaged(3).unwrap();
+        assert_eq!(avg.chan_freq, vec![0.998e9, 0.992e9]);
+        assert_eq!(avg.chan_width, vec![-6e6, -6e6]);
+        assert_eq!(avg.total_bandwidth(), spw.total_bandwidth());
+        assert!(spw.averaged(4).is_err());
+
+        let mut bad = spw.clone();
+        bad.chan_freq.swap(1, 2);
+        assert_eq!(bad.validate(), Err(SpectralWindowError::NotMonotonic(2)));
+
+        let mut bad = spw;
+        bad.resolution.pop();
+        assert!(matches!(
+            bad.validate(),
+            Err(SpectralWindowError::LengthMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn table_round_trip() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("test.ms");
+        synthetic_ms(&path, &SyntheticMsSpec::default()).unwrap();
+
+        let mut t = Table::open(path.join("SPECTRAL_WINDOW"), TableOpenMode::ReadWrite).unwrap();
+        let mut spw = SpectralWindow::uniform(8, 1.2e9, 5e5);
+        spw.name = "test".to_owned();
+        spw.write(&mut t, 0).unwrap();
+
+        assert_eq!(SpectralWindow::read(&mut t, 0).unwrap(), spw);
+    }
+}
@@ -0,0 +1,1000 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Appending visibility data to a Measurement Set as it arrives.
+//!
+//! A correlator emits one block of data per integration: a cell for every
+//! baseline, all at the same time. Writing these blocks cell by cell, adding
+//! one row at a time, spends most of its time in per-call overhead (compare
+//! the `grow` and `chunks` patterns of [`super::run_write_bench`]). A
+//! [`StreamWriter`] instead writes each column of an integration with a single
+//! bulk call, grows the table in large batches of rows, and flushes to disk
+//! on a fixed cadence so that readers can follow along. For the highest
+//! rates, a [`BackgroundStreamWriter`] does the writing on a separate thread
+//! fed by a bounded queue.
+
+use ndarray::{Array, Array2, Array3, ArrayView, ArrayView2, ArrayView3};
+use std::{
+    os::raw::c_ulong,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{channel, sync_channel, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+use super::checksum::{RowChecksums, CHECKSUM_COLUMNS_KEYWORD, CHECKSUM_START_ROW_KEYWORD};
+use crate::{
+    glue, io_stats::IoDirection, sync_table::OwnerThread, CasaScalarData, Complex, GlueDataType,
+    Table, TableError, UnexpectedDataTypeError,
+};
+
+/// The columns that a [`StreamWriter`] fills.
+const WRITTEN_COLUMNS: &[&str] = &[
+    "TIME",
+    "TIME_CENTROID",
+    "INTERVAL",
+    "EXPOSURE",
+    "ANTENNA1",
+    "ANTENNA2",
+    "FLAG_ROW",
+    "UVW",
+    "DATA",
+    "FLAG",
+    "WEIGHT",
+    "SIGMA",
+];
+
+/// An error that can occur when streaming data into a table.
+#[derive(Error, Debug)]
+pub enum StreamError {
+    /// An error occurred while writing to the table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// An input block has the wrong shape.
+    #[error("expected the {name} block to have shape {expected:?}, but it has shape {actual:?}")]
+    BlockShape {
+        /// The name of the offending block.
+        name: &'static str,
+
+        /// The shape that the block should have.
+        expected: Vec<usize>,
+
+        /// The shape that the block actually has.
+        actual: Vec<usize>,
+    },
+
+    /// A background writer was used after it stopped because of an earlier
+    /// error.
+    #[error("the background writer has stopped")]
+    WriterStopped,
+
+    /// The thread of a background writer panicked.
+    #[error("the background writer thread panicked")]
+    WriterPanicked,
+
+    /// The row checksums requested with [`StreamWriter::checksum_column`]
+    /// cannot be maintained.
+    #[error("cannot maintain row checksums: {0}")]
+    Checksum(String),
+}
+
+impl From<crate::CasacoreError> for StreamError {
+    fn from(e: crate::CasacoreError) -> Self {
+        StreamError::Table(e.into())
+    }
+}
+
+/// A writer that appends whole integrations to a Measurement Set main table.
+///
+/// Each call to [`Self::write_integration`] appends one row per baseline, in
+/// the baseline order given when the writer was created, and fills in the
+/// `TIME`, `TIME_CENTROID`, `INTERVAL`, `EXPOSURE`, `ANTENNA1`, `ANTENNA2`,
+/// `UVW`, `DATA`, `FLAG`, `FLAG_ROW`, `WEIGHT`, and `SIGMA` columns. Other
+/// columns keep their default values.
+///
+/// Rows are added to the table [`Self::grow_rows`] at a time, so the table
+/// may briefly hold empty rows past the last integration written.
+/// [`Self::finish`] removes them; if the writer is dropped without being
+/// finished, they remain.
+///
+/// ```no_run
+/// use ndarray::{Array2, Array3};
+/// use rubbl_casatables::{ms::StreamWriter, Complex, Table};
+///
+/// let table = Table::create_with_default_subtables("out.ms", 0).unwrap();
+/// let baselines = vec![(0, 1), (0, 2), (1, 2)];
+/// let mut writer = StreamWriter::new(table, baselines, 2.0).flush_every(Some(10));
+///
+/// let uvw = Array2::zeros((3, 3));
+/// let data = Array3::from_elem((3, 64, 4), Complex::new(1f32, 0.));
+/// let flags = Array3::from_elem((3, 64, 4), false);
+/// let weights = Array2::from_elem((3, 4), 1f32);
+///
+/// for i in 0..100 {
+///     let time = 5e9 + 2. * i as f64;
+///     writer
+///         .write_integration(time, uvw.view(), data.view(), flags.view(), weights.view())
+///         .unwrap();
+/// }
+///
+/// let table = writer.finish().unwrap();
+/// assert_eq!(table.n_rows(), 300);
+/// ```
+pub struct StreamWriter {
+    table: Table,
+    antenna1: Vec<i32>,
+    antenna2: Vec<i32>,
+    interval: f64,
+    grow_rows: usize,
+    flush_every: Option<usize>,
+    next_row: u64,
+    n_integrations: u64,
+    unflushed: usize,
+    checked: bool,
+    checksum: Option<ChecksumSpec>,
+}
+
+/// The row checksums maintained by a [`StreamWriter`].
+struct ChecksumSpec {
+    column: String,
+    columns: Vec<String>,
+}
+
+impl StreamWriter {
+    /// The default number of rows to add to the table at a time.
+    pub const DEFAULT_GROW_ROWS: usize = 65536;
+
+    /// Create a writer that appends rows to *table*.
+    ///
+    /// Each integration holds one row for each of the `(ANTENNA1, ANTENNA2)`
+    /// pairs in *baselines*, in that order. *interval* is the length of an
+    /// integration in seconds, recorded in the `INTERVAL` and `EXPOSURE`
+    /// columns. Rows are appended after any that the table already has.
+    pub fn new(table: Table, baselines: Vec<(i32, i32)>, interval: f64) -> Self {
+        let next_row = table.n_rows();
+
+        StreamWriter {
+            table,
+            antenna1: baselines.iter().map(|b| b.0).collect(),
+            antenna2: baselines.iter().map(|b| b.1).collect(),
+            interval,
+            grow_rows: Self::DEFAULT_GROW_ROWS,
+            flush_every: None,
+            next_row,
+            n_integrations: 0,
+            unflushed: 0,
+            checked: false,
+            checksum: None,
+        }
+    }
+
+    /// Set the number of rows to add to the table whenever it fills up.
+    ///
+    /// The default is [`Self::DEFAULT_GROW_ROWS`]. If an integration has more
+    /// rows than this, the table is grown by exactly as many rows as the
+    /// integration needs.
+    pub fn grow_rows(mut self, grow_rows: usize) -> Self {
+        self.grow_rows = grow_rows;
+        self
+    }
+
+    /// Set how often the table is flushed to disk, in integrations.
+    ///
+    /// With `Some(n)`, the table is flushed after every *n* integrations;
+    /// with `None`, the default, it is only flushed by [`Self::finish`] or
+    /// [`Self::flush`].
+    pub fn flush_every(mut self, flush_every: Option<usize>) -> Self {
+        self.flush_every = flush_every.map(|n| n.max(1));
+        self
+    }
+
+    /// Maintain a CRC-32 checksum of each row in the column *col_name*.
+    ///
+    /// Each checksum covers the cells of *columns*, in the given order, which
+    /// must be among the columns that this writer fills. If the table does
+    /// not have the column *col_name*, it is added as a scalar `UInt` column,
+    /// with keywords recording the covered columns and the first row
+    /// written; if it does, those keywords must name the same columns.
+    /// Checksums are computed from the data as they are written, without
+    /// reading them back. See [`super::verify_row_checksums`] for checking
+    /// them.
+    pub fn checksum_column(mut self, col_name: &str, columns: &[&str]) -> Self {
+        self.checksum = Some(ChecksumSpec {
+            column: col_name.to_owned(),
+            columns: columns.iter().map(|c| (*c).to_owned()).collect(),
+        });
+        self
+    }
+
+    /// The number of rows per integration.
+    pub fn n_baselines(&self) -> usize {
+        self.antenna1.len()
+    }
+
+    /// The number of integrations written so far.
+    pub fn n_integrations(&self) -> u64 {
+        self.n_integrations
+    }
+
+    /// The number of the row that the next integration will start at.
+    pub fn next_row(&self) -> u64 {
+        self.next_row
+    }
+
+    /// Check that the table has the columns this writer fills, with the
+    /// types that it writes.
+    fn check_columns(&mut self) -> Result<(), TableError> {
+        check_column::<f64>(&mut self.table, "TIME", true)?;
+        check_column::<f64>(&mut self.table, "TIME_CENTROID", true)?;
+        check_column::<f64>(&mut self.table, "INTERVAL", true)?;
+        check_column::<f64>(&mut self.table, "EXPOSURE", true)?;
+        check_column::<i32>(&mut self.table, "ANTENNA1", true)?;
+        check_column::<i32>(&mut self.table, "ANTENNA2", true)?;
+        check_column::<bool>(&mut self.table, "FLAG_ROW", true)?;
+        check_column::<f64>(&mut self.table, "UVW", false)?;
+        check_column::<Complex<f32>>(&mut self.table, "DATA", false)?;
+        check_column::<bool>(&mut self.table, "FLAG", false)?;
+        check_column::<f32>(&mut self.table, "WEIGHT", false)?;
+        check_column::<f32>(&mut self.table, "SIGMA", false)?;
+        Ok(())
+    }
+
+    /// Check the options for row checksums, adding the checksum column if
+    /// needed.
+    fn setup_checksum(&mut self) -> Result<(), StreamError> {
+        let spec = match self.checksum {
+            Some(ref s) => s,
+            None => return Ok(()),
+        };
+
+        for name in &spec.columns {
+            if !WRITTEN_COLUMNS.contains(&name.as_str()) {
+                return Err(StreamError::Checksum(format!(
+                    "column \"{name}\" is not written by the stream writer"
+                )));
+            }
+        }
+
+        let t = &mut self.table;
+
+        if !t.column_names()?.contains(&spec.column) {
+            t.add_scalar_column(
+                GlueDataType::TpUInt,
+                &spec.column,
+                Some("CRC-32 checksum of the row"),
+                false,
+                false,
+            )?;
+            t.put_column_keyword(&spec.column, CHECKSUM_COLUMNS_KEYWORD, &spec.columns)?;
+            t.put_column_keyword(
+                &spec.column,
+                CHECKSUM_START_ROW_KEYWORD,
+                &(self.next_row as i64),
+            )?;
+            return Ok(());
+        }
+
+        check_column::<u32>(t, &spec.column, true)?;
+        let mut keywords = t.get_column_keyword_record(&spec.column)?;
+        let covered: Vec<String> = if keywords
+            .keyword_names()?
+            .iter()
+            .any(|n| n == CHECKSUM_COLUMNS_KEYWORD)
+        {
+            keywords.get_field(CHECKSUM_COLUMNS_KEYWORD)?
+        } else {
+            Vec::new()
+        };
+
+        if covered != spec.columns {
+            return Err(StreamError::Checksum(format!(
+                "column \"{}\" holds checksums of {:?}, not {:?}",
+                spec.column, covered, spec.columns
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Append the rows of one integration.
+    ///
+    /// The first axis of every block indexes baselines. *uvw_block* has
+    /// shape `[n_baselines, 3]`; *data_block* and *flag_block* have shape
+    /// `[n_baselines, n_chans, n_pols]`; and *weight_block* has shape
+    /// `[n_baselines, n_pols]`. `SIGMA` is derived from the weights as
+    /// `1 / sqrt(weight)`, or zero where the weight is not positive, and a
+    /// row is marked in `FLAG_ROW` if all of its data are flagged.
+    ///
+    /// Each column is written with one bulk call for the whole integration.
+    /// The `DATA`, `FLAG`, `WEIGHT`, and `SIGMA` columns need not have a
+    /// fixed shape, but if they do, it must match that of the blocks. If a
+    /// checksum column has been set up with [`Self::checksum_column`], it is
+    /// filled in too.
+    pub fn write_integration(
+        &mut self,
+        time: f64,
+        uvw_block: ArrayView2<f64>,
+        data_block: ArrayView3<Complex<f32>>,
+        flag_block: ArrayView3<bool>,
+        weight_block: ArrayView2<f32>,
+    ) -> Result<(), StreamError> {
+        let n_bl = self.n_baselines();
+        let (n_chans, n_pols) =
+            check_blocks(n_bl, &uvw_block, &data_block, &flag_block, &weight_block)?;
+
+        if !self.checked {
+            self.check_columns()?;
+            self.setup_checksum()?;
+            self.checked = true;
+        }
+
+        let start = self.next_row;
+        let end = start + n_bl as u64;
+        let n_rows = self.table.n_rows();
+
+        if end > n_rows {
+            let needed = (end - n_rows) as usize;
+            self.table.add_rows(needed.max(self.grow_rows))?;
+        }
+
+        let times = vec![time; n_bl];
+        let intervals = vec![self.interval; n_bl];
+        let flag_row: Vec<bool> = flag_block
+            .outer_iter()
+            .map(|f| f.iter().all(|x| *x))
+            .collect();
+        let sigma: Vec<f32> = weight_block
+            .iter()
+            .map(|w| if *w > 0. { 1. / w.sqrt() } else { 0. })
+            .collect();
+
+        let t = &mut self.table;
+        put_scalars(t, "TIME", start, &times)?;
+        put_scalars(t, "TIME_CENTROID", start, &times)?;
+        put_scalars(t, "INTERVAL", start, &intervals)?;
+        put_scalars(t, "EXPOSURE", start, &intervals)?;
+        put_scalars(t, "ANTENNA1", start, &self.antenna1)?;
+        put_scalars(t, "ANTENNA2", start, &self.antenna2)?;
+        put_scalars(t, "FLAG_ROW", start, &flag_row)?;
+        // `as_standard_layout` only copies blocks that are not already
+        // contiguous in C order.
+        let uvw = uvw_block.as_standard_layout();
+        let data = data_block.as_standard_layout();
+        let flags = flag_block.as_standard_layout();
+        let weights = weight_block.as_standard_layout();
+        let cell = [n_chans, n_pols];
+        put_cells(t, "UVW", start, n_bl, &[3], uvw.as_slice().unwrap())?;
+        put_cells(t, "DATA", start, n_bl, &cell, data.as_slice().unwrap())?;
+        put_cells(t, "FLAG", start, n_bl, &cell, flags.as_slice().unwrap())?;
+        put_cells(
+            t,
+            "WEIGHT",
+            start,
+            n_bl,
+            &[n_pols],
+            weights.as_slice().unwrap(),
+        )?;
+        put_cells(t, "SIGMA", start, n_bl, &[n_pols], &sigma)?;
+
+        if let Some(ref spec) = self.checksum {
+            let mut sums = RowChecksums::new(n_bl);
+
+            for name in &spec.columns {
+                match name.as_str() {
+                    "TIME" | "TIME_CENTROID" => sums.add_scalars(&times),
+                    "INTERVAL" | "EXPOSURE" => sums.add_scalars(&intervals),
+                    "ANTENNA1" => sums.add_scalars(&self.antenna1),
+                    "ANTENNA2" => sums.add_scalars(&self.antenna2),
+                    "FLAG_ROW" => sums.add_scalars(&flag_row),
+                    "UVW" => sums.add_cells(&[3], uvw.as_slice().unwrap()),
+                    "DATA" => sums.add_cells(&cell, data.as_slice().unwrap()),
+                    "FLAG" => sums.add_cells(&cell, flags.as_slice().unwrap()),
+                    "WEIGHT" => sums.add_cells(&[n_pols], weights.as_slice().unwrap()),
+                    "SIGMA" => sums.add_cells(&[n_pols], &sigma),
+                    other => unreachable!("unchecked checksum column {}", other),
+                }
+            }
+
+            put_scalars(t, &spec.column, start, &sums.finish())?;
+        }
+
+        self.next_row = end;
+        self.n_integrations += 1;
+        self.unflushed += 1;
+
+        if let Some(n) = self.flush_every {
+            if self.unflushed >= n {
+                self.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flush the table to disk now.
+    pub fn flush(&mut self) -> Result<(), StreamError> {
+        self.table.flush()?;
+        self.unflushed = 0;
+        Ok(())
+    }
+
+    /// Remove any rows added past the last integration, flush the table, and
+    /// return it.
+    pub fn finish(mut self) -> Result<Table, StreamError> {
+        let n_rows = self.table.n_rows();
+
+        if n_rows > self.next_row {
+            self.table
+                .remove_rows(self.next_row, (n_rows - self.next_row) as usize)?;
+        }
+
+        self.table.flush()?;
+        Ok(self.table)
+    }
+}
+
+/// The statistics of the queue of a [`BackgroundStreamWriter`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StreamQueueStats {
+    /// The maximum number of integrations that can wait in the queue.
+    pub capacity: usize,
+
+    /// The number of integrations currently waiting in the queue.
+    pub depth: usize,
+
+    /// The largest number of integrations that have waited in the queue at
+    /// once.
+    pub max_depth: usize,
+
+    /// The number of integrations submitted so far.
+    pub n_submitted: u64,
+
+    /// The number of integrations written to the table so far.
+    pub n_written: u64,
+
+    /// The total time that submitters have spent waiting for room in the
+    /// queue. If this keeps growing, the table cannot keep up with the data.
+    pub blocked: Duration,
+}
+
+/// The counters shared between a [`BackgroundStreamWriter`] and its thread.
+#[derive(Default)]
+struct SharedCounters {
+    depth: AtomicUsize,
+    max_depth: AtomicUsize,
+    n_written: AtomicU64,
+}
+
+/// The data of one integration, owned so that they can be sent to the
+/// writer thread.
+struct IntegrationBuffer {
+    time: f64,
+    uvw: Array2<f64>,
+    data: Array3<Complex<f32>>,
+    flags: Array3<bool>,
+    weights: Array2<f32>,
+}
+
+/// Copy *src* into *dest*, reusing its allocation if the shapes match.
+fn fill<T: Clone, D: ndarray::Dimension>(dest: &mut Array<T, D>, src: &ArrayView<T, D>) {
+    if dest.shape() == src.shape() {
+        dest.assign(src);
+    } else {
+        *dest = src.to_owned();
+    }
+}
+
+type WorkerResult = (OwnerThread<StreamWriter>, Result<(), StreamError>);
+
+/// A [`StreamWriter`] that does its writing on a background thread.
+///
+/// The writer, and the table that it fills, live on a thread of their own,
+/// since a [`Table`] cannot move between threads. The caller’s thread only
+/// checks and copies the data of each integration, so that preparing the
+/// next integration overlaps with writing the last one. Buffers are
+/// recycled once they have been written, so a steady stream of
+/// same-shaped integrations does not allocate.
+///
+/// If writing fails, the thread stops and the error is returned by the next
+/// call to [`Self::write_integration`] or [`Self::finish`].
+pub struct BackgroundStreamWriter {
+    n_baselines: usize,
+    sender: Option<SyncSender<IntegrationBuffer>>,
+    recycled: Receiver<IntegrationBuffer>,
+    worker: Option<JoinHandle<WorkerResult>>,
+    counters: Arc<SharedCounters>,
+    capacity: usize,
+    n_submitted: u64,
+    blocked: Duration,
+}
+
+impl BackgroundStreamWriter {
+    /// Start a thread, and create the writer on it by calling *open*.
+    ///
+    /// *open* typically opens or creates the table and sets up a
+    /// [`StreamWriter`] for it; errors that it returns are passed back. Each
+    /// integration is copied into a buffer and queued for the thread, which
+    /// writes it to the table while the caller prepares the next one. At
+    /// most *queue_depth* integrations wait in the queue; once it is full,
+    /// submitting another blocks until the thread catches up.
+    pub fn new<F>(open: F, queue_depth: usize) -> Result<Self, StreamError>
+    where
+        F: FnOnce() -> Result<StreamWriter, StreamError> + Send + 'static,
+    {
+        let owner = OwnerThread::spawn(open)?;
+        let capacity = queue_depth.max(1);
+        let n_baselines = owner.with(|w| w.n_baselines());
+        let (sender, receiver) = sync_channel::<IntegrationBuffer>(capacity);
+        let (recycler, recycled) = channel();
+        let counters = Arc::new(SharedCounters::default());
+        let worker_counters = counters.clone();
+        let worker = thread::spawn(move || {
+            let result = owner.with(move |writer| {
+                for buf in receiver {
+                    worker_counters.depth.fetch_sub(1, Ordering::SeqCst);
+
+                    writer.write_integration(
+                        buf.time,
+                        buf.uvw.view(),
+                        buf.data.view(),
+                        buf.flags.view(),
+                        buf.weights.view(),
+                    )?;
+
+                    worker_counters.n_written.fetch_add(1, Ordering::SeqCst);
+
+                    // The submitter may have gone away; that is fine.
+                    let _ = recycler.send(buf);
+                }
+
+                Ok::<_, StreamError>(())
+            });
+
+            (owner, result)
+        });
+
+        Ok(BackgroundStreamWriter {
+            n_baselines,
+            sender: Some(sender),
+            recycled,
+            worker: Some(worker),
+            counters,
+            capacity,
+            n_submitted: 0,
+            blocked: Duration::default(),
+        })
+    }
+
+    /// Queue the rows of one integration to be written.
+    ///
+    /// The arguments are as for [`StreamWriter::write_integration`]. Their
+    /// shapes are checked right away, but errors from writing the table only
+    /// come back from a later call. This call blocks while the queue is
+    /// full.
+    pub fn write_integration(
+        &mut self,
+        time: f64,
+        uvw_block: ArrayView2<f64>,
+        data_block: ArrayView3<Complex<f32>>,
+        flag_block: ArrayView3<bool>,
+        weight_block: ArrayView2<f32>,
+    ) -> Result<(), StreamError> {
+        check_blocks(
+            self.n_baselines,
+            &uvw_block,
+            &data_block,
+            &flag_block,
+            &weight_block,
+        )?;
+
+        let sender = match self.sender {
+            Some(ref s) => s,
+            None => return Err(StreamError::WriterStopped),
+        };
+
+        let buf = match self.recycled.try_recv() {
+            Ok(mut buf) => {
+                buf.time = time;
+                fill(&mut buf.uvw, &uvw_block);
+                fill(&mut buf.data, &data_block);
+                fill(&mut buf.flags, &flag_block);
+                fill(&mut buf.weights, &weight_block);
+                buf
+            }
+
+            Err(_) => IntegrationBuffer {
+                time,
+                uvw: uvw_block.to_owned(),
+                data: data_block.to_owned(),
+                flags: flag_block.to_owned(),
+                weights: weight_block.to_owned(),
+            },
+        };
+
+        let depth = self.counters.depth.fetch_add(1, Ordering::SeqCst) + 1;
+        self.counters.max_depth.fetch_max(depth, Ordering::SeqCst);
+
+        let sent = match sender.try_send(buf) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(buf)) => {
+                let t0 = Instant::now();
+                let result = sender.send(buf).map_err(|_| ());
+                self.blocked += t0.elapsed();
+                result
+            }
+            Err(TrySendError::Disconnected(_)) => Err(()),
+        };
+
+        if sent.is_err() {
+            // The thread has stopped, so it must have hit an error.
+            self.counters.depth.fetch_sub(1, Ordering::SeqCst);
+            return Err(self.stop().err().unwrap_or(StreamError::WriterStopped));
+        }
+
+        self.n_submitted += 1;
+        Ok(())
+    }
+
+    /// Get the current statistics of the queue.
+    pub fn queue_stats(&self) -> StreamQueueStats {
+        StreamQueueStats {
+            capacity: self.capacity,
+            depth: self.counters.depth.load(Ordering::SeqCst),
+            max_depth: self.counters.max_depth.load(Ordering::SeqCst),
+            n_submitted: self.n_submitted,
+            n_written: self.counters.n_written.load(Ordering::SeqCst),
+            blocked: self.blocked,
+        }
+    }
+
+    /// Close the queue and wait for the thread to finish, returning the
+    /// writer's thread or the error that stopped it.
+    fn stop(&mut self) -> Result<OwnerThread<StreamWriter>, StreamError> {
+        self.sender = None;
+
+        let worker = match self.worker.take() {
+            Some(w) => w,
+            None => return Err(StreamError::WriterStopped),
+        };
+
+        match worker.join() {
+            Ok((writer, Ok(()))) => Ok(writer),
+            Ok((_, Err(e))) => Err(e),
+            Err(_) => Err(StreamError::WriterPanicked),
+        }
+    }
+
+    /// Wait for all queued integrations to be written, then finish the
+    /// writer as with [`StreamWriter::finish`] and close the table.
+    pub fn finish(mut self) -> Result<(), StreamError> {
+        self.stop()?.finish(|writer| writer.finish().map(drop))
+    }
+}
+
+impl Drop for BackgroundStreamWriter {
+    fn drop(&mut self) {
+        // Let the thread drain the queue and close the table; there is no
+        // way to report any error here.
+        let _ = self.stop();
+    }
+}
+
+/// Check the shapes of the blocks of an integration with *n_bl* baselines,
+/// returning the numbers of channels and polarizations.
+fn check_blocks(
+    n_bl: usize,
+    uvw_block: &ArrayView2<f64>,
+    data_block: &ArrayView3<Complex<f32>>,
+    flag_block: &ArrayView3<bool>,
+    weight_block: &ArrayView2<f32>,
+) -> Result<(usize, usize), StreamError> {
+    let (_, n_chans, n_pols) = data_block.dim();
+    check_block_shape("UVW", &[n_bl, 3], uvw_block.shape())?;
+    check_block_shape("data", &[n_bl, n_chans, n_pols], data_block.shape())?;
+    check_block_shape("flag", &[n_bl, n_chans, n_pols], flag_block.shape())?;
+    check_block_shape("weight", &[n_bl, n_pols], weight_block.shape())?;
+    Ok((n_chans, n_pols))
+}
+
+fn check_block_shape(
+    name: &'static str,
+    expected: &[usize],
+    actual: &[usize],
+) -> Result<(), StreamError> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(StreamError::BlockShape {
+            name,
+            expected: expected.to_vec(),
+            actual: actual.to_vec(),
+        })
+    }
+}
+
+fn check_column<T: CasaScalarData>(
+    table: &mut Table,
+    col_name: &str,
+    scalar: bool,
+) -> Result<(), TableError> {
+    let desc = table.get_col_desc(col_name)?;
+
+    if desc.is_scalar() != scalar || desc.data_type().element_type() != T::DATA_TYPE {
+        let expected = if scalar { T::DATA_TYPE } else { T::VECTOR_TYPE };
+        return Err(UnexpectedDataTypeError(expected, desc.data_type()).into());
+    }
+
+    Ok(())
+}
+
+/// Write one value per row to a scalar column whose type has already been
+/// checked.
+fn put_scalars<T: CasaScalarData>(
+    table: &mut Table,
+    col_name: &str,
+    start_row: u64,
+    data: &[T],
+) -> Result<(), TableError> {
+    let ccol_name = glue::StringBridge::from_rust(col_name);
+
+    if unsafe {
+        glue::table_put_column_range(
+            table.handle,
+            &ccol_name,
+            start_row,
+            data.len() as u64,
+            data.as_ptr() as _,
+            &mut table.exc_info,
+        )
+    } != 0
+    {
+        return table.exc_info.as_err();
+    }
+
+    table.record_io(
+        IoDirection::Put,
+        col_name,
+        data.len() as u64,
+        T::DATA_TYPE,
+        data.len() as u64,
+    );
+    Ok(())
+}
+
+/// Write *n_rows* cells of shape *cell_shape* to an array column whose type
+/// has already been checked. *data* holds the cells contiguously in C order.
+fn put_cells<T: CasaScalarData>(
+    table: &mut Table,
+    col_name: &str,
+    start_row: u64,
+    n_rows: usize,
+    cell_shape: &[usize],
+    data: &[T],
+) -> Result<(), TableError> {
+    let dims: Vec<c_ulong> = cell_shape.iter().map(|d| *d as c_ulong).collect();
+    let ccol_name = glue::StringBridge::from_rust(col_name);
+
+    if unsafe {
+        glue::table_put_column_range_shaped(
+            table.handle,
+            &ccol_name,
+            start_row,
+            n_rows as u64,
+            dims.len() as u64,
+            dims.as_ptr(),
+            data.as_ptr() as _,
+            &mut table.exc_info,
+        )
+    } != 0
+    {
+        return table.exc_info.as_err();
+    }
+
+    table.record_io(
+        IoDirection::Put,
+        col_name,
+        n_rows as u64,
+        T::DATA_TYPE,
+        data.len() as u64,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode, TableOpenMode};
+    use ndarray::Axis;
+    use tempfile::tempdir;
+
+    #[test]
+    fn write_integrations() {
+        let tmp_dir = tempdir().unwrap();
+        let table =
+            Table::create_with_default_subtables(tmp_dir.path().join("test.ms"), 0).unwrap();
+        let mut writer = StreamWriter::new(table, vec![(0, 1), (0, 2), (1, 2)], 2.0)
+            .grow_rows(4)
+            .flush_every(Some(1));
+
+        let uvw = Array2::from_shape_fn((3, 3), |(b, i)| (b * 3 + i) as f64);
+        let mut flags = Array3::from_elem((3, 2, 2), false);
+        flags.index_axis_mut(Axis(0), 2).fill(true);
+        let weights = Array2::from_elem((3, 2), 4f32);
+
+        for i in 0..2 {
+            let data = Array3::from_shape_fn((3, 2, 2), |(b, c, p)| {
+                Complex::new((i * 100 + b * 10 + c) as f32, p as f32)
+            });
+            writer
+                .write_integration(
+                    10. + 2. * i as f64,
+                    uvw.view(),
+                    data.view(),
+                    flags.view(),
+                    weights.view(),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(writer.n_integrations(), 2);
+        assert_eq!(writer.next_row(), 6);
+
+        let bad = Array2::<f64>::zeros((2, 3));
+        let data = Array3::zeros((3, 2, 2));
+        assert!(matches!(
+            writer.write_integration(14., bad.view(), data.view(), flags.view(), weights.view()),
+            Err(StreamError::BlockShape { name: "UVW", .. })
+        ));
+
+        let mut t = writer.finish().unwrap();
+        assert_eq!(t.n_rows(), 6);
+        assert_eq!(t.get_cell::<f64>("TIME", 4).unwrap(), 12.);
+        assert_eq!(t.get_cell::<f64>("EXPOSURE", 4).unwrap(), 2.);
+        assert_eq!(t.get_cell::<i32>("ANTENNA2", 4).unwrap(), 2);
+        assert!(t.get_cell::<bool>("FLAG_ROW", 5).unwrap());
+        assert!(!t.get_cell::<bool>("FLAG_ROW", 4).unwrap());
+
+        let cell: Vec<f64> = t.get_cell_as_vec("UVW", 4).unwrap();
+        assert_eq!(cell, vec![3., 4., 5.]);
+        let cell: Vec<Complex<f32>> = t.get_cell_as_vec("DATA", 4).unwrap();
+        assert_eq!(
+            cell,
+            vec![
+                Complex::new(110., 0.),
+                Complex::new(110., 1.),
+                Complex::new(111., 0.),
+                Complex::new(111., 1.),
+            ]
+        );
+        let cell: Vec<f32> = t.get_cell_as_vec("SIGMA", 4).unwrap();
+        assert_eq!(cell, vec![0.5, 0.5]);
+        assert_eq!(t.get_cell_shape("FLAG", 5).unwrap(), vec![2, 2]);
+    }
+
+    #[test]
+    fn background_writer() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("test.ms");
+        let ms_path = path.clone();
+        let mut writer = BackgroundStreamWriter::new(
+            move || {
+                let table = Table::create_with_default_subtables(ms_path, 0)?;
+                Ok(StreamWriter::new(table, vec![(0, 0), (0, 1)], 1.0).grow_rows(3))
+            },
+            2,
+        )
+        .unwrap();
+
+        let uvw = Array2::zeros((2, 3));
+        let flags = Array3::from_elem((2, 4, 1), false);
+        let weights = Array2::from_elem((2, 1), 1f32);
+
+        for i in 0..5 {
+            let data = Array3::from_elem((2, 4, 1), Complex::new(i as f32, 0.));
+            writer
+                .write_integration(
+                    i as f64,
+                    uvw.view(),
+                    data.view(),
+                    flags.view(),
+                    weights.view(),
+                )
+                .unwrap();
+        }
+
+        let data = Array3::zeros((2, 3, 1));
+        assert!(matches!(
+            writer.write_integration(5., uvw.view(), data.view(), flags.view(), weights.view()),
+            Err(StreamError::BlockShape { name: "flag", .. })
+        ));
+
+        let stats = writer.queue_stats();
+        assert_eq!(stats.capacity, 2);
+        assert_eq!(stats.n_submitted, 5);
+        assert!(stats.max_depth <= 3);
+
+        writer.finish().unwrap();
+        let mut t = Table::open(&path, TableOpenMode::Read).unwrap();
+        assert_eq!(t.n_rows(), 10);
+        assert_eq!(t.get_cell::<f64>("TIME", 9).unwrap(), 4.);
+        let cell: Vec<Complex<f32>> = t.get_cell_as_vec("DATA", 6).unwrap();
+        assert_eq!(cell, vec![Complex::new(3., 0.); 4]);
+
+        // A table without the expected columns: the error surfaces when the
+        // writer finishes.
+        let bad_path = tmp_dir.path().join("bad.tab");
+        let mut writer = BackgroundStreamWriter::new(
+            move || {
+                let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH)?;
+                desc.add_scalar_column(GlueDataType::TpDouble, "TIME", None, false, false)?;
+                let table = Table::new(bad_path, desc, 0, TableCreateMode::New)?;
+                Ok(StreamWriter::new(table, vec![(0, 0), (0, 1)], 1.0))
+            },
+            1,
+        )
+        .unwrap();
+        let data = Array3::zeros((2, 4, 1));
+        writer
+            .write_integration(0., uvw.view(), data.view(), flags.view(), weights.view())
+            .unwrap();
+        assert!(matches!(writer.finish(), Err(StreamError::Table(_))));
+    }
+
+    #[test]
+    fn row_checksums() {
+        let tmp_dir = tempdir().unwrap();
+        let table =
+            Table::create_with_default_subtables(tmp_dir.path().join("test.ms"), 0).unwrap();
+        let mut writer = StreamWriter::new(table, vec![(0, 1), (1, 2)], 1.0)
+            .grow_rows(8)
+            .checksum_column("CRC", &["TIME", "ANTENNA2", "DATA", "FLAG"]);
+
+        let uvw = Array2::zeros((2, 3));
+        let flags = Array3::from_elem((2, 3, 2), false);
+        let weights = Array2::from_elem((2, 2), 1f32);
+
+        for i in 0..3 {
+            let data = Array3::from_shape_fn((2, 3, 2), |(b, c, p)| {
+                Complex::new((i * 100 + b * 10 + c) as f32, p as f32)
+            });
+            writer
+                .write_integration(
+                    i as f64,
+                    uvw.view(),
+                    data.view(),
+                    flags.view(),
+                    weights.view(),
+                )
+                .unwrap();
+        }
+
+        let mut t = writer.finish().unwrap();
+        let report = t.verify_row_checksums("CRC").unwrap();
+        assert_eq!(report.n_rows, 6);
+        assert!(report.is_ok());
+
+        t.put_cell("DATA", 3, &Array2::<Complex<f32>>::zeros((3, 2)))
+            .unwrap();
+        let report = t.verify_row_checksums("CRC").unwrap();
+        assert_eq!(report.mismatched_rows, vec![3]);
+
+        assert!(matches!(
+            t.verify_row_checksums("TIME"),
+            Err(crate::ms::ChecksumError::NotChecksumColumn(_))
+        ));
+
+        // Appending with a different selection is refused.
+        let mut writer =
+            StreamWriter::new(t, vec![(0, 1), (1, 2)], 1.0).checksum_column("CRC", &["TIME"]);
+        let data = Array3::zeros((2, 3, 2));
+        assert!(matches!(
+            writer.write_integration(3., uvw.view(), data.view(), flags.view(), weights.view()),
+            Err(StreamError::Checksum(_))
+        ));
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Reading the sub-tables of a Measurement Set.
+//!
+//! These functions take the path of the main table, and find each sub-table
+//! in the conventional place described in the [module docs](super).
+
+use rubbl_core::array_layout::AntennaLayout;
+use std::path::Path;
+
+use super::{
+    join::{check_key, FieldInfo, PolarizationInfo},
+    read_antenna_layout, JoinError, SpectralWindow,
+};
+use crate::{casaimages::coordinates::StokesType, Table, TableError, TableOpenMode};
+
+/// Open the sub-table *name* of the data set at *ms_path* for reading.
+pub(super) fn open_subtable(ms_path: &Path, name: &str) -> Result<Table, TableError> {
+    Table::open(ms_path.join(name), TableOpenMode::Read)
+}
+
+/// Read the `NAME` column of the sub-table *name*.
+pub(super) fn read_names(ms_path: &Path, name: &str) -> Result<Vec<String>, TableError> {
+    open_subtable(ms_path, name)?.get_col_as_vec("NAME")
+}
+
+/// Read the array layout from the `ANTENNA` table.
+pub(super) fn read_antennas(ms_path: &Path) -> Result<AntennaLayout, TableError> {
+    read_antenna_layout(&mut open_subtable(ms_path, "ANTENNA")?)
+}
+
+/// Read the `FIELD` table.
+pub(super) fn read_fields(ms_path: &Path) -> Result<Vec<FieldInfo>, TableError> {
+    let mut t = open_subtable(ms_path, "FIELD")?;
+    let mut result = Vec::with_capacity(t.n_rows() as usize);
+
+    for id in 0..t.n_rows() as usize {
+        let name: String = t.get_cell("NAME", id as u64)?;
+
+        // PHASE_DIR has shape [NUM_POLY + 1, 2] in C order, so the constant
+        // term comes first.
+        let dir: Vec<f64> = t.get_cell_as_vec("PHASE_DIR", id as u64)?;
+        let phase_dir = [
+            dir.first().copied().unwrap_or(0.),
+            dir.get(1).copied().unwrap_or(0.),
+        ];
+
+        result.push(FieldInfo {
+            id,
+            name,
+            phase_dir,
+        });
+    }
+
+    Ok(result)
+}
+
+/// Read the `SPECTRAL_WINDOW` table.
+pub(super) fn read_spectral_windows(ms_path: &Path) -> Result<Vec<SpectralWindow>, TableError> {
+    SpectralWindow::read_all(&mut open_subtable(ms_path, "SPECTRAL_WINDOW")?)
+}
+
+/// Read the `POLARIZATION` table.
+pub(super) fn read_polarizations(ms_path: &Path) -> Result<Vec<PolarizationInfo>, TableError> {
+    let mut t = open_subtable(ms_path, "POLARIZATION")?;
+    let mut result = Vec::with_capacity(t.n_rows() as usize);
+
+    for id in 0..t.n_rows() {
+        let corr_type: Vec<i32> = t.get_cell_as_vec("CORR_TYPE", id)?;
+        let stokes = corr_type
+            .iter()
+            .map(|c| StokesType::from_code(*c))
+            .collect();
+
+        result.push(PolarizationInfo {
+            id: id as usize,
+            corr_type,
+            stokes,
+        });
+    }
+
+    Ok(result)
+}
+
+/// Read the `SPECTRAL_WINDOW_ID` column of the `DATA_DESCRIPTION` table,
+/// without checking its values.
+pub(super) fn read_data_desc_spws(ms_path: &Path) -> Result<Vec<i32>, TableError> {
+    open_subtable(ms_path, "DATA_DESCRIPTION")?.get_col_as_vec("SPECTRAL_WINDOW_ID")
+}
+
+/// Read the `DATA_DESCRIPTION` table, returning the spectral window and
+/// polarization IDs of each row after checking that they are valid.
+pub(super) fn read_data_descs(
+    ms_path: &Path,
+    n_spws: usize,
+    n_pols: usize,
+) -> Result<Vec<(usize, usize)>, JoinError> {
+    let mut dd = open_subtable(ms_path, "DATA_DESCRIPTION")?;
+    let spw_ids: Vec<i32> = dd.get_col_as_vec("SPECTRAL_WINDOW_ID")?;
+    let pol_ids: Vec<i32> = dd.get_col_as_vec("POLARIZATION_ID")?;
+
+    spw_ids
+        .iter()
+        .zip(pol_ids.iter())
+        .enumerate()
+        .map(|(row, (&spw, &pol))| {
+            let spw = check_key(
+                row as u64,
+                "SPECTRAL_WINDOW_ID",
+                spw,
+                "SPECTRAL_WINDOW",
+                n_spws,
+            )?;
+            let pol = check_key(row as u64, "POLARIZATION_ID", pol, "POLARIZATION", n_pols)?;
+            Ok((spw, pol))
+        })
+        .collect()
+}
@@ -0,0 +1,743 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Summarizing the contents of Measurement Sets.
+//!
+//! CASA’s `listobs` task is the usual way to take an inventory of a data
+//! set: which scans observed which fields, when, with which spectral
+//! windows and antennas. [`summary`] gathers the same information without
+//! needing CASA, as an [`ObservationSummary`] that can be printed as text in
+//! the style of `listobs` or serialized to JSON for other tools.
+
+use std::{collections::HashMap, convert::TryFrom, fmt, path::Path};
+
+use super::{
+    join::check_key,
+    subtables::{
+        open_subtable, read_antennas, read_data_descs, read_fields, read_polarizations,
+        read_spectral_windows,
+    },
+    JoinError,
+};
+use crate::{json::JsonValue, Table, TableError};
+
+/// A summary of a Measurement Set, like that printed by CASA’s `listobs`.
+///
+/// Times are in CASA’s convention of seconds since MJD 0, in UTC.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObservationSummary {
+    /// The path of the data set.
+    pub path: String,
+
+    /// The number of rows in the main table.
+    pub n_rows: u64,
+
+    /// The start and end of the data, taking the integration time of each
+    /// row into account.
+    pub time_range: (f64, f64),
+
+    /// The observations in the `OBSERVATION` table.
+    pub observations: Vec<ObservationInfo>,
+
+    /// The scans, in order of their start times.
+    pub scans: Vec<ScanSummary>,
+
+    /// The fields in the `FIELD` table.
+    pub fields: Vec<FieldSummary>,
+
+    /// The spectral windows that are used by the main table.
+    pub spectral_windows: Vec<SpwSummary>,
+
+    /// The antennas in the `ANTENNA` table.
+    pub antennas: Vec<AntennaSummary>,
+}
+
+/// One row of the `OBSERVATION` table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObservationInfo {
+    /// The row number of the observation, i.e. its ID.
+    pub id: usize,
+
+    /// The name of the telescope.
+    pub telescope_name: String,
+
+    /// The name of the observer.
+    pub observer: String,
+
+    /// The name or code of the project.
+    pub project: String,
+}
+
+/// One scan of a data set.
+///
+/// Rows are grouped into scans by their `SCAN_NUMBER` and `FIELD_ID`, as in
+/// `listobs`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScanSummary {
+    /// The scan number.
+    pub scan_number: i32,
+
+    /// The ID of the field observed.
+    pub field_id: usize,
+
+    /// The name of the field observed.
+    pub field_name: String,
+
+    /// The start and end of the scan.
+    pub time_range: (f64, f64),
+
+    /// The number of rows in the scan.
+    pub n_rows: u64,
+
+    /// The IDs of the spectral windows in the scan, in increasing order.
+    pub spectral_window_ids: Vec<usize>,
+
+    /// The mean integration time of the rows of the scan, in seconds.
+    pub mean_interval: f64,
+}
+
+/// One field of a data set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldSummary {
+    /// The row number of the field, i.e. its ID.
+    pub id: usize,
+
+    /// The code of the field, such as `"T"` for a target.
+    pub code: String,
+
+    /// The name of the field.
+    pub name: String,
+
+    /// The phase center of the field, as longitude and latitude in radians.
+    pub phase_dir: [f64; 2],
+
+    /// The number of main-table rows that refer to the field.
+    pub n_rows: u64,
+}
+
+/// One spectral window of a data set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpwSummary {
+    /// The row number of the spectral window, i.e. its ID.
+    pub id: usize,
+
+    /// The name of the window.
+    pub name: String,
+
+    /// The number of channels.
+    pub n_chans: usize,
+
+    /// The name of the frequency reference frame, such as `"TOPO"`.
+    pub frame: String,
+
+    /// The frequency of the first channel, in Hz.
+    pub first_freq: f64,
+
+    /// The width of the first channel, in Hz.
+    pub chan_width: f64,
+
+    /// The total bandwidth, in Hz.
+    pub total_bandwidth: f64,
+
+    /// The mean frequency of the channels, in Hz.
+    pub center_freq: f64,
+
+    /// The correlation products recorded with this window, such as
+    /// `["XX", "YY"]`.
+    pub correlations: Vec<String>,
+
+    /// The number of main-table rows that use this window.
+    pub n_rows: u64,
+}
+
+/// One antenna of a data set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AntennaSummary {
+    /// The row number of the antenna, i.e. its ID.
+    pub id: usize,
+
+    /// The name of the antenna.
+    pub name: String,
+
+    /// The name of the station that the antenna occupies.
+    pub station: String,
+
+    /// The diameter of the dish, in meters.
+    pub diameter: f64,
+
+    /// The ITRF position of the antenna, in meters.
+    pub position: [f64; 3],
+}
+
+/// Summarize the contents of a Measurement Set.
+///
+/// This reads the `TIME`, `INTERVAL`, `SCAN_NUMBER`, `FIELD_ID`, and
+/// `DATA_DESC_ID` columns of the main table, along with the `OBSERVATION`,
+/// `FIELD`, `DATA_DESCRIPTION`, `SPECTRAL_WINDOW`, `POLARIZATION`, and
+/// `ANTENNA` sub-tables.
+///
+/// ```no_run
+/// use rubbl_casatables::{Table, TableOpenMode};
+/// use rubbl_casatables::ms::summary;
+///
+/// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+/// let s = summary(&mut t).unwrap();
+/// println!("{}", s);
+/// std::fs::write("vis.json", s.to_json()).unwrap();
+/// ```
+pub fn summary(table: &mut Table) -> Result<ObservationSummary, JoinError> {
+    let path = table.file_name().map_err(TableError::from)?;
+    let ms_path = Path::new(&path);
+
+    let observations = read_observations(ms_path)?;
+    let mut fields = read_field_summaries(ms_path)?;
+    let spectral_windows = read_spectral_windows(ms_path)?;
+    let polarizations = read_polarizations(ms_path)?;
+    let antennas = read_antenna_summaries(ms_path)?;
+
+    let data_descs = read_data_descs(ms_path, spectral_windows.len(), polarizations.len())?;
+
+    let time: Vec<f64> = table.get_col_as_vec("TIME")?;
+    let interval: Vec<f64> = table.get_col_as_vec("INTERVAL")?;
+    let scan_number: Vec<i32> = table.get_col_as_vec("SCAN_NUMBER")?;
+    let field_id: Vec<i32> = table.get_col_as_vec("FIELD_ID")?;
+    let data_desc_id: Vec<i32> = table.get_col_as_vec("DATA_DESC_ID")?;
+
+    let mut scans: Vec<ScanSummary> = Vec::new();
+    let mut scan_index: HashMap<(i32, usize), usize> = HashMap::new();
+    let mut interval_sums: Vec<f64> = Vec::new();
+    let mut spw_rows = vec![0u64; spectral_windows.len()];
+    let mut spw_corrs: Vec<Option<usize>> = vec![None; spectral_windows.len()];
+    let mut time_range = (f64::INFINITY, f64::NEG_INFINITY);
+
+    for (row, &t) in time.iter().enumerate() {
+        let field = check_key(row as u64, "FIELD_ID", field_id[row], "FIELD", fields.len())?;
+        let dd = check_key(
+            row as u64,
+            "DATA_DESC_ID",
+            data_desc_id[row],
+            "DATA_DESCRIPTION",
+            data_descs.len(),
+        )?;
+        let (spw, pol) = data_descs[dd];
+        let start = t - 0.5 * interval[row];
+        let end = t + 0.5 * interval[row];
+
+        time_range.0 = time_range.0.min(start);
+        time_range.1 = time_range.1.max(end);
+        fields[field].n_rows += 1;
+        spw_rows[spw] += 1;
+        spw_corrs[spw].get_or_insert(pol);
+
+        let i = *scan_index
+            .entry((scan_number[row], field))
+            .or_insert_with(|| {
+                scans.push(ScanSummary {
+                    scan_number: scan_number[row],
+                    field_id: field,
+                    field_name: fields[field].name.clone(),
+                    time_range: (start, end),
+                    n_rows: 0,
+                    spectral_window_ids: Vec::new(),
+                    mean_interval: 0.,
+                });
+                interval_sums.push(0.);
+                scans.len() - 1
+            });
+
+        let scan = &mut scans[i];
+        scan.time_range.0 = scan.time_range.0.min(start);
+        scan.time_range.1 = scan.time_range.1.max(end);
+        scan.n_rows += 1;
+        interval_sums[i] += interval[row];
+
+        if let Err(pos) = scan.spectral_window_ids.binary_search(&spw) {
+            scan.spectral_window_ids.insert(pos, spw);
+        }
+    }
+
+    for (scan, sum) in scans.iter_mut().zip(interval_sums) {
+        scan.mean_interval = sum / scan.n_rows as f64;
+    }
+
+    scans.sort_by(|a, b| {
+        a.time_range
+            .0
+            .partial_cmp(&b.time_range.0)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.scan_number.cmp(&b.scan_number))
+    });
+
+    if time.is_empty() {
+        time_range = (0., 0.);
+    }
+
+    let spectral_windows = spectral_windows
+        .iter()
+        .enumerate()
+        .filter(|(id, _)| spw_rows[*id] > 0)
+        .map(|(id, spw)| {
+            let n_chans = spw.n_chans();
+
+            SpwSummary {
+                id,
+                name: spw.name.clone(),
+                n_chans,
+                frame: frame_name(spw.meas_freq_ref),
+                first_freq: spw.chan_freq.first().copied().unwrap_or(f64::NAN),
+                chan_width: spw.chan_width.first().copied().unwrap_or(f64::NAN),
+                total_bandwidth: spw.total_bandwidth(),
+                center_freq: spw.chan_freq.iter().sum::<f64>() / n_chans as f64,
+                correlations: spw_corrs[id]
+                    .map(|pol| polarizations[pol].labels())
+                    .unwrap_or_default(),
+                n_rows: spw_rows[id],
+            }
+        })
+        .collect();
+
+    Ok(ObservationSummary {
+        path,
+        n_rows: time.len() as u64,
+        time_range,
+        observations,
+        scans,
+        fields,
+        spectral_windows,
+        antennas,
+    })
+}
+
+fn read_observations(path: &Path) -> Result<Vec<ObservationInfo>, TableError> {
+    let mut t = open_subtable(path, "OBSERVATION")?;
+
+    (0..t.n_rows())
+        .map(|row| {
+            Ok(ObservationInfo {
+                id: row as usize,
+                telescope_name: t.get_cell("TELESCOPE_NAME", row)?,
+                observer: t.get_cell("OBSERVER", row)?,
+                project: t.get_cell("PROJECT", row)?,
+            })
+        })
+        .collect()
+}
+
+fn read_field_summaries(path: &Path) -> Result<Vec<FieldSummary>, TableError> {
+    let codes: Vec<String> = open_subtable(path, "FIELD")?.get_col_as_vec("CODE")?;
+
+    Ok(read_fields(path)?
+        .into_iter()
+        .zip(codes)
+        .map(|(f, code)| FieldSummary {
+            id: f.id,
+            code,
+            name: f.name,
+            phase_dir: f.phase_dir,
+            n_rows: 0,
+        })
+        .collect())
+}
+
+fn read_antenna_summaries(path: &Path) -> Result<Vec<AntennaSummary>, TableError> {
+    let layout = read_antennas(path)?;
+    let stations: Vec<String> = open_subtable(path, "ANTENNA")?.get_col_as_vec("STATION")?;
+
+    Ok((0..layout.len())
+        .map(|i| AntennaSummary {
+            id: i,
+            name: layout.names[i].clone(),
+            station: stations.get(i).cloned().unwrap_or_default(),
+            diameter: layout.diameters[i],
+            position: layout.positions[i],
+        })
+        .collect())
+}
+
+/// Get the name of a casacore `MFrequency` reference frame code.
+fn frame_name(code: i32) -> String {
+    const NAMES: [&str; 9] = [
+        "REST", "LSRK", "LSRD", "BARY", "GEO", "TOPO", "GALACTO", "LGROUP", "CMB",
+    ];
+
+    usize::try_from(code)
+        .ok()
+        .and_then(|i| NAMES.get(i))
+        .map(|n| (*n).to_owned())
+        .unwrap_or_else(|| format!("#{code}"))
+}
+
+/// Format a CASA time as a UTC date and time, `2024-01-01 00:00:00.0`.
+fn format_time(seconds: f64) -> String {
+    if !seconds.is_finite() {
+        return "?".to_owned();
+    }
+
+    let tenths = (seconds * 10.).round() as i64;
+    let days = tenths.div_euclid(864_000);
+    let tod = tenths.rem_euclid(864_000);
+
+    // Convert the MJD to a civil date, following the algorithm of Howard
+    // Hinnant's `civil_from_days`, shifted from the Unix epoch to MJD 0.
+    let z = days - 40587 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{}",
+        year,
+        month,
+        day,
+        tod / 36_000,
+        (tod / 600) % 60,
+        (tod / 10) % 60,
+        tod % 10
+    )
+}
+
+/// Format an angle in radians as degrees.
+fn degrees(rad: f64) -> f64 {
+    rad.to_degrees()
+}
+
+/// Convert a floating-point number to JSON, mapping values that JSON cannot
+/// represent to null.
+fn json_f64(x: f64) -> JsonValue {
+    if x.is_finite() {
+        JsonValue::number(x)
+    } else {
+        JsonValue::Null
+    }
+}
+
+fn json_str(s: &str) -> JsonValue {
+    JsonValue::String(s.to_owned())
+}
+
+fn json_object(members: Vec<(&str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(
+        members
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v))
+            .collect(),
+    )
+}
+
+fn json_range(r: (f64, f64)) -> JsonValue {
+    JsonValue::Array(vec![json_f64(r.0), json_f64(r.1)])
+}
+
+impl ObservationSummary {
+    /// Get the total time spanned by the data, in seconds.
+    pub fn elapsed(&self) -> f64 {
+        self.time_range.1 - self.time_range.0
+    }
+
+    /// Serialize the summary as a JSON document.
+    ///
+    /// The document is an object with a member for each field of this type,
+    /// using the same names. Time ranges become two-element arrays.
+    pub fn to_json(&self) -> String {
+        let observations = self
+            .observations
+            .iter()
+            .map(|o| {
+                json_object(vec![
+                    ("id", JsonValue::number(o.id)),
+                    ("telescope_name", json_str(&o.telescope_name)),
+                    ("observer", json_str(&o.observer)),
+                    ("project", json_str(&o.project)),
+                ])
+            })
+            .collect();
+
+        let scans = self
+            .scans
+            .iter()
+            .map(|s| {
+                json_object(vec![
+                    ("scan_number", JsonValue::number(s.scan_number)),
+                    ("field_id", JsonValue::number(s.field_id)),
+                    ("field_name", json_str(&s.field_name)),
+                    ("time_range", json_range(s.time_range)),
+                    ("n_rows", JsonValue::number(s.n_rows)),
+                    (
+                        "spectral_window_ids",
+                        JsonValue::Array(
+                            s.spectral_window_ids
+                                .iter()
+                                .map(JsonValue::number)
+                                .collect(),
+                        ),
+                    ),
+                    ("mean_interval", json_f64(s.mean_interval)),
+                ])
+            })
+            .collect();
+
+        let fields = self
+            .fields
+            .iter()
+            .map(|f| {
+                json_object(vec![
+                    ("id", JsonValue::number(f.id)),
+                    ("code", json_str(&f.code)),
+                    ("name", json_str(&f.name)),
+                    (
+                        "phase_dir",
+                        JsonValue::Array(f.phase_dir.iter().map(|x| json_f64(*x)).collect()),
+                    ),
+                    ("n_rows", JsonValue::number(f.n_rows)),
+                ])
+            })
+            .collect();
+
+        let spws = self
+            .spectral_windows
+            .iter()
+            .map(|w| {
+                json_object(vec![
+                    ("id", JsonValue::number(w.id)),
+                    ("name", json_str(&w.name)),
+                    ("n_chans", JsonValue::number(w.n_chans)),
+                    ("frame", json_str(&w.frame)),
+                    ("first_freq", json_f64(w.first_freq)),
+                    ("chan_width", json_f64(w.chan_width)),
+                    ("total_bandwidth", json_f64(w.total_bandwidth)),
+                    ("center_freq", json_f64(w.center_freq)),
+                    (
+                        "correlations",
+                        JsonValue::Array(w.correlations.iter().map(|c| json_str(c)).collect()),
+                    ),
+                    ("n_rows", JsonValue::number(w.n_rows)),
+                ])
+            })
+            .collect();
+
+        let antennas = self
+            .antennas
+            .iter()
+            .map(|a| {
+                json_object(vec![
+                    ("id", JsonValue::number(a.id)),
+                    ("name", json_str(&a.name)),
+                    ("station", json_str(&a.station)),
+                    ("diameter", json_f64(a.diameter)),
+                    (
+                        "position",
+                        JsonValue::Array(a.position.iter().map(|x| json_f64(*x)).collect()),
+                    ),
+                ])
+            })
+            .collect();
+
+        json_object(vec![
+            ("path", json_str(&self.path)),
+            ("n_rows", JsonValue::number(self.n_rows)),
+            ("time_range", json_range(self.time_range)),
+            ("observations", JsonValue::Array(observations)),
+            ("scans", JsonValue::Array(scans)),
+            ("fields", JsonValue::Array(fields)),
+            ("spectral_windows", JsonValue::Array(spws)),
+            ("antennas", JsonValue::Array(antennas)),
+        ])
+        .to_pretty_string()
+    }
+}
+
+impl fmt::Display for ObservationSummary {
+    /// Render the summary as text laid out like the output of `listobs`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "MeasurementSet Name:  {}", self.path)?;
+
+        for o in &self.observations {
+            writeln!(f, "   Observer: {}     Project: {}", o.observer, o.project)?;
+            writeln!(f, "Observation: {}", o.telescope_name)?;
+        }
+
+        writeln!(f, "Data records: {}", self.n_rows)?;
+        writeln!(f, "Total elapsed time = {:.1} seconds", self.elapsed())?;
+        writeln!(f, "   Observed from   {}", format_time(self.time_range.0))?;
+        writeln!(f, "   Observed to     {}", format_time(self.time_range.1))?;
+        writeln!(f)?;
+
+        writeln!(
+            f,
+            "  {:<23} {:<10} {:>5} {:>6} {:<20} {:>8}  {:<12} {:>10}",
+            "Timerange (UTC)", "", "Scan", "FldId", "FieldName", "nRows", "SpwIds", "Interval(s)"
+        )?;
+
+        for s in &self.scans {
+            let spws: Vec<String> = s
+                .spectral_window_ids
+                .iter()
+                .map(|i| i.to_string())
+                .collect();
+            let end = format_time(s.time_range.1);
+
+            writeln!(
+                f,
+                "  {:<23} - {:<8} {:>5} {:>6} {:<20} {:>8}  [{:<10}] {:>10.2}",
+                format_time(s.time_range.0),
+                &end[11..],
+                s.scan_number,
+                s.field_id,
+                s.field_name,
+                s.n_rows,
+                spws.join(","),
+                s.mean_interval
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Fields: {}", self.fields.len())?;
+        writeln!(
+            f,
+            "  {:>4} {:<5} {:<20} {:>14} {:>14} {:>10}",
+            "ID", "Code", "Name", "RA (deg)", "Decl (deg)", "nRows"
+        )?;
+
+        for fl in &self.fields {
+            writeln!(
+                f,
+                "  {:>4} {:<5} {:<20} {:>14.6} {:>14.6} {:>10}",
+                fl.id,
+                fl.code,
+                fl.name,
+                degrees(fl.phase_dir[0]).rem_euclid(360.),
+                degrees(fl.phase_dir[1]),
+                fl.n_rows
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Spectral Windows: {}", self.spectral_windows.len())?;
+        writeln!(
+            f,
+            "  {:>5} {:<12} {:>7} {:<5} {:>12} {:>12} {:>12} {:>12}  Corrs",
+            "SpwID",
+            "Name",
+            "#Chans",
+            "Frame",
+            "Ch0(MHz)",
+            "ChanWid(kHz)",
+            "TotBW(kHz)",
+            "CtrFreq(MHz)",
+        )?;
+
+        for w in &self.spectral_windows {
+            writeln!(
+                f,
+                "  {:>5} {:<12} {:>7} {:<5} {:>12.4} {:>12.3} {:>12.1} {:>12.4}  {}",
+                w.id,
+                w.name,
+                w.n_chans,
+                w.frame,
+                w.first_freq * 1e-6,
+                w.chan_width * 1e-3,
+                w.total_bandwidth * 1e-3,
+                w.center_freq * 1e-6,
+                w.correlations.join(" ")
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Antennas: {}", self.antennas.len())?;
+        writeln!(
+            f,
+            "  {:>4} {:<10} {:<10} {:>7} {:>16} {:>16} {:>16}",
+            "ID", "Name", "Station", "Diam.", "ITRF x (m)", "ITRF y (m)", "ITRF z (m)"
+        )?;
+
+        for a in &self.antennas {
+            writeln!(
+                f,
+                "  {:>4} {:<10} {:<10} {:>6.1}m {:>16.3} {:>16.3} {:>16.3}",
+                a.id, a.name, a.station, a.diameter, a.position[0], a.position[1], a.position[2]
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn time_formatting() {
+        // 2024 January 1 is MJD 60310.
+        assert_eq!(format_time(60310. * 86400.), "2024-01-01 00:00:00.0");
+        assert_eq!(
+            format_time(60310. * 86400. + 3723.3),
+            "2024-01-01 01:02:03.3"
+        );
+        assert_eq!(format_time(0.), "1858-11-17 00:00:00.0");
+        assert_eq!(
+            format_time(51544. * 86400. + 86399.),
+            "2000-01-01 23:59:59.0"
+        );
+    }
+
+    #[test]
+    fn synthetic_summary() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec::default();
+        let mut t = synthetic_ms(tmp_dir.path().join("test.ms"), &spec).unwrap();
+
+        // Split the data into two scans.
+        let rows_per_time = (spec.n_rows() / spec.n_timesteps) as u64;
+
+        for row in rows_per_time..spec.n_rows() as u64 {
+            t.put_cell("SCAN_NUMBER", row, &2i32).unwrap();
+        }
+
+        let s = summary(&mut t).unwrap();
+        assert_eq!(s.n_rows, spec.n_rows() as u64);
+        assert_eq!(s.time_range.0, spec.start_time);
+        assert_eq!(s.elapsed(), spec.n_timesteps as f64 * spec.integration_time);
+        assert_eq!(s.observations[0].telescope_name, "SYNTHETIC");
+
+        assert_eq!(s.scans.len(), 2);
+        assert_eq!(s.scans[0].scan_number, 1);
+        assert_eq!(s.scans[0].n_rows, rows_per_time);
+        assert_eq!(s.scans[1].scan_number, 2);
+        assert_eq!(s.scans[1].n_rows, spec.n_rows() as u64 - rows_per_time);
+        assert_eq!(s.scans[1].field_name, "synthetic");
+        assert_eq!(s.scans[1].spectral_window_ids, vec![0]);
+        assert_eq!(s.scans[1].mean_interval, spec.integration_time);
+
+        assert_eq!(s.fields.len(), 1);
+        assert_eq!(s.fields[0].n_rows, spec.n_rows() as u64);
+        assert_eq!(s.spectral_windows.len(), 1);
+        assert_eq!(s.spectral_windows[0].n_chans, spec.n_chans);
+        assert_eq!(s.spectral_windows[0].frame, "TOPO");
+        assert_eq!(s.spectral_windows[0].first_freq, spec.start_freq);
+        assert_eq!(
+            s.spectral_windows[0].correlations,
+            vec!["XX", "XY", "YX", "YY"]
+        );
+        assert_eq!(s.antennas.len(), spec.n_ants);
+        assert_eq!(s.antennas[1].name, "ANT001");
+
+        let text = s.to_string();
+        assert!(text.contains("Data records: "));
+        assert!(text.contains("SPW0"));
+
+        let json = JsonValue::parse(&s.to_json()).unwrap();
+        assert_eq!(
+            json.get("n_rows").unwrap().parse_number::<u64>(),
+            Some(spec.n_rows() as u64)
+        );
+        assert_eq!(json.get("scans").unwrap().as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! A pure-Rust SumThreshold RFI flagger.
+//!
+//! The SumThreshold method ([Offringa et al. 2010]) looks for runs of
+//! samples along the time and frequency axes whose average excess power is
+//! too high, using lower thresholds for longer runs. It catches both strong,
+//! short bursts and weaker, extended interference, and it is the core of
+//! AOFlagger’s default strategy. [`SumThresholdFlagger`] implements the
+//! basic method as a [`FlaggerBackend`], so that simple flagging can be done
+//! through [`super::run_flagger`] without any external dependencies.
+//!
+//! [Offringa et al. 2010]: https://doi.org/10.1111/j.1365-2966.2010.16471.x
+
+use ndarray::{Array2, Array3, ArrayView3, Axis};
+use std::convert::Infallible;
+
+use super::FlaggerBackend;
+use crate::Complex;
+
+/// A SumThreshold flagger.
+///
+/// Each polarization of a block is flagged separately, on the amplitudes of
+/// its visibilities. The median and the median absolute deviation of the
+/// unflagged amplitudes set the scale: the threshold for a single sample is
+/// [`Self::threshold`] robust standard deviations above the median, and the
+/// threshold for windows of *M* samples is that divided by
+/// [`Self::rho`]<sup>log₂ *M*</sup>, for *M* = 1, 2, 4, … up to
+/// [`Self::max_window`]. A window is flagged if the mean excess of its
+/// unflagged samples exceeds its threshold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SumThresholdFlagger {
+    /// The threshold for single samples, in units of the robust standard
+    /// deviation. The default is 6.
+    pub threshold: f32,
+
+    /// The factor by which the threshold decreases each time the window
+    /// size doubles. The default is 1.5.
+    pub rho: f32,
+
+    /// The largest window size to use. The default is 64.
+    pub max_window: usize,
+
+    /// If true, the default, a sample flagged in any polarization is flagged
+    /// in all of them.
+    pub combine_polarizations: bool,
+}
+
+impl Default for SumThresholdFlagger {
+    fn default() -> Self {
+        SumThresholdFlagger {
+            threshold: 6.,
+            rho: 1.5,
+            max_window: 64,
+            combine_polarizations: true,
+        }
+    }
+}
+
+impl SumThresholdFlagger {
+    /// Flag one time–frequency plane of amplitudes, updating *mask* in
+    /// place.
+    fn flag_plane(&self, amps: &Array2<f32>, mask: &mut Array2<bool>) {
+        let (median, sigma) = match robust_stats(amps, mask) {
+            Some(s) => s,
+            None => return,
+        };
+
+        if sigma.is_nan() || sigma <= 0. {
+            return;
+        }
+
+        let excess = amps.mapv(|a| a - median);
+        let mut window = 1;
+        let mut chi = self.threshold * sigma;
+
+        while window <= self.max_window.max(1) {
+            for axis in 0..2 {
+                let mut new_mask = mask.clone();
+
+                for (values, (old, new)) in excess.lanes(Axis(axis)).into_iter().zip(
+                    mask.lanes(Axis(axis))
+                        .into_iter()
+                        .zip(new_mask.lanes_mut(Axis(axis))),
+                ) {
+                    let values: Vec<f32> = values.iter().copied().collect();
+                    let old: Vec<bool> = old.iter().copied().collect();
+                    let mut new: Vec<&mut bool> = new.into_iter().collect();
+                    sum_threshold_1d(&values, &old, &mut new, window, chi);
+                }
+
+                *mask = new_mask;
+            }
+
+            window *= 2;
+            chi /= self.rho;
+        }
+    }
+}
+
+impl FlaggerBackend for SumThresholdFlagger {
+    type Error = Infallible;
+
+    fn flag_baseline(
+        &mut self,
+        _antennas: (i32, i32),
+        _data_desc_id: i32,
+        data: ArrayView3<Complex<f32>>,
+        flags: ArrayView3<bool>,
+    ) -> Result<Array3<bool>, Infallible> {
+        let mut out = flags.to_owned();
+
+        for pol in 0..data.shape()[2] {
+            let amps = data.index_axis(Axis(2), pol).mapv(|v| v.norm());
+            let mut mask = flags.index_axis(Axis(2), pol).to_owned();
+            self.flag_plane(&amps, &mut mask);
+            out.index_axis_mut(Axis(2), pol).assign(&mask);
+        }
+
+        if self.combine_polarizations {
+            for mut cell in out.lanes_mut(Axis(2)) {
+                if cell.iter().any(|f| *f) {
+                    cell.fill(true);
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// Get the median and robust standard deviation of the unmasked values,
+/// ignoring non-finite ones.
+fn robust_stats(values: &Array2<f32>, mask: &Array2<bool>) -> Option<(f32, f32)> {
+    let mut good: Vec<f32> = values
+        .iter()
+        .zip(mask.iter())
+        .filter(|(v, m)| !**m && v.is_finite())
+        .map(|(v, _)| *v)
+        .collect();
+
+    if good.is_empty() {
+        return None;
+    }
+
+    let median = median_in_place(&mut good);
+    good.iter_mut().for_each(|v| *v = (*v - median).abs());
+    let mad = median_in_place(&mut good);
+    Some((median, 1.4826 * mad))
+}
+
+fn median_in_place(values: &mut [f32]) -> f32 {
+    let mid = values.len() / 2;
+    values.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    values[mid]
+}
+
+/// Run one SumThreshold pass of window size *m* along a line of samples.
+///
+/// Samples already flagged in *old* are left out of the window sums, which
+/// is equivalent to the usual practice of replacing them with the threshold.
+/// Windows are flagged in *new*, so that flags raised in this pass do not
+/// affect it.
+fn sum_threshold_1d(values: &[f32], old: &[bool], new: &mut [&mut bool], m: usize, chi: f32) {
+    let n = values.len();
+
+    if m > n {
+        return;
+    }
+
+    let mut sum = 0.;
+    let mut count = 0;
+
+    for i in 0..n {
+        if !old[i] && values[i].is_finite() {
+            sum += values[i];
+            count += 1;
+        }
+
+        if i >= m {
+            let j = i - m;
+
+            if !old[j] && values[j].is_finite() {
+                sum -= values[j];
+                count -= 1;
+            }
+        }
+
+        if i + 1 >= m && count > 0 && sum / count as f32 > chi {
+            for flag in new[i + 1 - m..=i].iter_mut() {
+                **flag = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_spikes_and_lines() {
+        let (n_times, n_chans, n_pols) = (40, 32, 2);
+
+        // Smooth, deterministic "noise" with a robust sigma of about 0.1.
+        let mut data = Array3::from_shape_fn((n_times, n_chans, n_pols), |(t, c, p)| {
+            let x = ((t * 7919 + c * 104_729 + p * 31) % 1000) as f32 / 1000.;
+            Complex::new(10. + 0.3 * (x - 0.5), 0.)
+        });
+
+        // A strong spike, a weak narrowband line, and a broadband burst.
+        data[[3, 5, 0]] = Complex::new(30., 0.);
+        for t in 0..n_times {
+            data[[t, 20, 1]] = Complex::new(10.4, 0.);
+        }
+        for c in 0..n_chans {
+            data[[30, c, 0]] += Complex::new(0.5, 0.);
+        }
+
+        let flags = Array3::from_elem((n_times, n_chans, n_pols), false);
+        let mut flagger = SumThresholdFlagger {
+            combine_polarizations: false,
+            ..Default::default()
+        };
+        let out = flagger
+            .flag_baseline((0, 1), 0, data.view(), flags.view())
+            .unwrap();
+
+        assert!(out[[3, 5, 0]]);
+        assert!(!out[[3, 5, 1]]);
+        assert!((0..n_times).all(|t| out[[t, 20, 1]]));
+        assert!((0..n_chans).all(|c| out[[30, c, 0]]));
+
+        let n_flagged = out.iter().filter(|f| **f).count();
+        assert!(n_flagged < 2 * (n_times + n_chans));
+
+        flagger.combine_polarizations = true;
+        let out = flagger
+            .flag_baseline((0, 1), 0, data.view(), flags.view())
+            .unwrap();
+        assert!(out[[3, 5, 1]]);
+    }
+}
@@ -0,0 +1,238 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! System temperatures, as recorded in the `SYSCAL` sub-table.
+
+use ndarray::Array2;
+
+use super::schema;
+use crate::{Table, TableError};
+
+/// One kind of temperature measurement in a row of the `SYSCAL` sub-table,
+/// such as the system temperature.
+///
+/// The MS stores each kind of temperature in up to three columns: one with a
+/// value per receptor, such as `TSYS`, one with a value per channel and
+/// receptor, such as `TSYS_SPECTRUM`, and a flag, such as `TSYS_FLAG`. The
+/// array columns are optional, and cells that are missing or undefined are
+/// represented by `None`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SysCalValues {
+    /// The value for each receptor, in K.
+    pub per_receptor: Option<Vec<f32>>,
+
+    /// The value for each channel and receptor, in K, with shape `(n_chans,
+    /// n_receptors)`.
+    pub spectrum: Option<Array2<f32>>,
+
+    /// Whether the values are flagged as bad.
+    pub flag: bool,
+}
+
+impl SysCalValues {
+    /// Read the values of the temperature whose columns are named after
+    /// *base*, for example `"TSYS"`, from one row of a table with the
+    /// columns *col_names*.
+    fn read(
+        table: &mut Table,
+        row: u64,
+        base: &str,
+        col_names: &[String],
+    ) -> Result<Self, TableError> {
+        let has = |name: &str| col_names.iter().any(|c| c == name);
+        let flag_col = format!("{}_FLAG", base);
+        let spectrum_col = format!("{}_SPECTRUM", base);
+        let mut values = SysCalValues::default();
+
+        if has(base) && !table.get_cell_shape(base, row)?.is_empty() {
+            values.per_receptor = Some(table.get_cell_as_vec(base, row)?);
+        }
+
+        if has(&spectrum_col) && !table.get_cell_shape(&spectrum_col, row)?.is_empty() {
+            values.spectrum = Some(table.get_cell(&spectrum_col, row)?);
+        }
+
+        if has(&flag_col) {
+            values.flag = table.get_cell(&flag_col, row)?;
+        }
+
+        Ok(values)
+    }
+
+    fn write(&self, table: &mut Table, row: u64, base: &str) -> Result<(), TableError> {
+        if let Some(v) = self.per_receptor.as_ref() {
+            table.put_cell(base, row, v)?;
+        }
+
+        if let Some(s) = self.spectrum.as_ref() {
+            table.put_cell(&format!("{}_SPECTRUM", base), row, s)?;
+        }
+
+        if self.per_receptor.is_some() || self.spectrum.is_some() || self.flag {
+            table.put_cell(&format!("{}_FLAG", base), row, &self.flag)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The calibration temperatures of one feed, as recorded in a row of the
+/// `SYSCAL` sub-table of a Measurement Set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SysCal {
+    /// The antenna to which the measurements apply.
+    pub antenna_id: i32,
+
+    /// The feed to which the measurements apply.
+    pub feed_id: i32,
+
+    /// The spectral window to which the measurements apply.
+    pub spectral_window_id: i32,
+
+    /// The midpoint of the time range to which the measurements apply, in
+    /// MJD seconds.
+    pub time: f64,
+
+    /// The length of the time range to which the measurements apply, in
+    /// seconds.
+    pub interval: f64,
+
+    /// The temperature of the calibration noise source (`TCAL`).
+    pub tcal: SysCalValues,
+
+    /// The receiver temperature (`TRX`).
+    pub trx: SysCalValues,
+
+    /// The system temperature (`TSYS`).
+    pub tsys: SysCalValues,
+}
+
+impl SysCal {
+    /// Test whether this row applies to data from feed *feed_id* of antenna
+    /// *antenna_id* in spectral window *spw* at time *time*.
+    pub fn applies_to(&self, antenna_id: i32, feed_id: i32, spw: i32, time: f64) -> bool {
+        self.antenna_id == antenna_id
+            && self.feed_id == feed_id
+            && self.spectral_window_id == spw
+            && (time - self.time).abs() <= 0.5 * self.interval
+    }
+
+    /// Read a row of a `SYSCAL` table.
+    ///
+    /// Temperature columns that the table lacks are read as `None`.
+    pub fn read(table: &mut Table, row: u64) -> Result<Self, TableError> {
+        let col_names = table.column_names()?;
+        Self::read_with_columns(table, row, &col_names)
+    }
+
+    /// Read all of the rows of a `SYSCAL` table.
+    pub fn read_all(table: &mut Table) -> Result<Vec<Self>, TableError> {
+        let col_names = table.column_names()?;
+
+        (0..table.n_rows())
+            .map(|row| Self::read_with_columns(table, row, &col_names))
+            .collect()
+    }
+
+    fn read_with_columns(
+        table: &mut Table,
+        row: u64,
+        col_names: &[String],
+    ) -> Result<Self, TableError> {
+        Ok(SysCal {
+            antenna_id: table.get_cell("ANTENNA_ID", row)?,
+            feed_id: table.get_cell("FEED_ID", row)?,
+            spectral_window_id: table.get_cell("SPECTRAL_WINDOW_ID", row)?,
+            time: table.get_cell("TIME", row)?,
+            interval: table.get_cell("INTERVAL", row)?,
+            tcal: SysCalValues::read(table, row, "TCAL", col_names)?,
+            trx: SysCalValues::read(table, row, "TRX", col_names)?,
+            tsys: SysCalValues::read(table, row, "TSYS", col_names)?,
+        })
+    }
+
+    /// Write this row into an existing row of a `SYSCAL` table.
+    ///
+    /// Only the temperature columns for which there are values are written,
+    /// so the table need only have those columns.
+    pub fn write(&self, table: &mut Table, row: u64) -> Result<(), TableError> {
+        table.put_cell("ANTENNA_ID", row, &self.antenna_id)?;
+        table.put_cell("FEED_ID", row, &self.feed_id)?;
+        table.put_cell("SPECTRAL_WINDOW_ID", row, &self.spectral_window_id)?;
+        table.put_cell("TIME", row, &self.time)?;
+        table.put_cell("INTERVAL", row, &self.interval)?;
+        self.tcal.write(table, row, "TCAL")?;
+        self.trx.write(table, row, "TRX")?;
+        self.tsys.write(table, row, "TSYS")?;
+        Ok(())
+    }
+}
+
+/// Open the `SYSCAL` sub-table of a Measurement Set for writing, creating
+/// it if it does not exist.
+///
+/// A new sub-table is created with the columns listed in
+/// [`schema::SYSCAL`]; see [`schema::TableSpec::open_or_create_subtable`].
+pub fn open_or_create_syscal(ms: &mut Table) -> Result<Table, TableError> {
+    schema::SYSCAL.open_or_create_subtable(ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn create_and_round_trip() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("test.ms");
+        let mut ms = synthetic_ms(&path, &SyntheticMsSpec::default()).unwrap();
+
+        let mut t = open_or_create_syscal(&mut ms).unwrap();
+        assert!(ms
+            .table_keyword_names()
+            .unwrap()
+            .contains(&"SYSCAL".to_owned()));
+
+        let with_spectrum = SysCal {
+            antenna_id: 2,
+            spectral_window_id: 0,
+            time: 1000.,
+            interval: 10.,
+            tsys: SysCalValues {
+                per_receptor: Some(vec![50., 55.]),
+                spectrum: Some(Array2::from_shape_fn((3, 2), |(c, r)| {
+                    50. + c as f32 + 5. * r as f32
+                })),
+                flag: false,
+            },
+            ..SysCal::default()
+        };
+        let flagged_tcal = SysCal {
+            antenna_id: 3,
+            time: 1000.,
+            interval: 10.,
+            tcal: SysCalValues {
+                per_receptor: Some(vec![1.5, 1.6]),
+                spectrum: None,
+                flag: true,
+            },
+            ..SysCal::default()
+        };
+
+        t.add_rows(2).unwrap();
+        with_spectrum.write(&mut t, 0).unwrap();
+        flagged_tcal.write(&mut t, 1).unwrap();
+        drop(t);
+
+        // Opening again finds the existing table.
+        let mut t = open_or_create_syscal(&mut ms).unwrap();
+        let rows = SysCal::read_all(&mut t).unwrap();
+        assert_eq!(rows, vec![with_spectrum.clone(), flagged_tcal]);
+
+        assert!(with_spectrum.applies_to(2, 0, 0, 1004.));
+        assert!(!with_spectrum.applies_to(2, 0, 0, 1006.));
+        assert!(!with_spectrum.applies_to(2, 0, 1, 1000.));
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Extracting time–frequency “waterfalls” of single baselines.
+//!
+//! A plot of one baseline’s visibilities against time and channel is the
+//! quickest way to spot interference or a misbehaving correlator input.
+//! [`waterfall`] uses a [`BaselineIndex`] to read only the rows of the
+//! requested baseline, in chunks, so that the rest of the Measurement Set
+//! never has to be loaded.
+
+use ndarray::Array2;
+use thiserror::Error;
+
+use super::BaselineIndex;
+use crate::{glue, Complex, Table, TableError};
+
+/// An error that can occur when extracting a waterfall.
+#[derive(Error, Debug)]
+pub enum WaterfallError {
+    /// An error occurred while reading the table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The table has no rows for the requested baseline.
+    #[error("there are no rows for the baseline between antennas {0} and {1}")]
+    NoSuchBaseline(i32, i32),
+
+    /// The requested polarization index is not present in the data.
+    #[error("polarization index {0} is out of range; the data have {1} polarizations")]
+    NoSuchPolarization(usize, usize),
+
+    /// The rows of the baseline span more than one `DATA_DESC_ID`, so that
+    /// their channels are not comparable.
+    #[error("rows of the baseline have DATA_DESC_ID values of both {0} and {1}")]
+    MultipleDataDescriptions(i32, i32),
+}
+
+/// The visibilities of one baseline and polarization as a function of time
+/// and channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Waterfall {
+    /// The times of the rows, in increasing order.
+    pub times: Vec<f64>,
+
+    /// The `DATA_DESC_ID` of the rows.
+    pub data_desc_id: i32,
+
+    /// The visibility amplitudes, with shape `[n_times, n_chans]`. Flagged
+    /// samples are NaN.
+    pub amplitude: Array2<f32>,
+
+    /// The visibility phases in radians, with shape `[n_times, n_chans]`.
+    /// Flagged samples are NaN.
+    pub phase: Array2<f32>,
+}
+
+/// Extract the waterfall of one baseline and polarization of a Measurement
+/// Set.
+///
+/// The rows of the baseline between *ant1* and *ant2* are looked up in
+/// *index*, which must have been built from *table*, and the data of
+/// polarization number *pol* are read from *data_column* — `DATA`,
+/// `CORRECTED_DATA`, or `MODEL_DATA`, say — along with the `FLAG`, `TIME`,
+/// and `DATA_DESC_ID` columns. The data column must hold single-precision
+/// complex values with a fixed cell shape, and all of the baseline’s rows
+/// must share one `DATA_DESC_ID`. Rows are sorted by time; if there are
+/// several rows with the same time, they all appear.
+///
+/// ```no_run
+/// use rubbl_casatables::{Table, TableOpenMode};
+/// use rubbl_casatables::ms::{waterfall, BaselineIndex};
+///
+/// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+/// let index = BaselineIndex::build(&mut t).unwrap();
+/// let w = waterfall(&mut t, &index, (0, 1), 0, "DATA").unwrap();
+/// println!("{} times by {} channels", w.times.len(), w.amplitude.ncols());
+/// ```
+pub fn waterfall(
+    table: &mut Table,
+    index: &BaselineIndex,
+    baseline: (i32, i32),
+    pol: usize,
+    data_column: &str,
+) -> Result<Waterfall, WaterfallError> {
+    let rows = index.rows(baseline.0, baseline.1);
+
+    if rows.is_empty() {
+        return Err(WaterfallError::NoSuchBaseline(baseline.0, baseline.1));
+    }
+
+    let cell_shape = table.bulk_column_cell_shape::<Complex<f32>>(data_column)?;
+    let n_chans = cell_shape.first().copied().unwrap_or(1);
+    let n_pols: usize = cell_shape.iter().skip(1).product();
+
+    if pol >= n_pols {
+        return Err(WaterfallError::NoSuchPolarization(pol, n_pols));
+    }
+
+    let n_times = rows.len();
+    let cell_len = n_chans * n_pols;
+    let rows_per_chunk = table.suggest_chunk_rows(&[data_column, "FLAG"], None)?;
+
+    let mut times = vec![0f64; n_times];
+    let mut ddids = vec![0i32; n_times];
+    table.read_scalar_rows(&glue::StringBridge::from_rust("TIME"), rows, &mut times)?;
+    table.read_scalar_rows(
+        &glue::StringBridge::from_rust("DATA_DESC_ID"),
+        rows,
+        &mut ddids,
+    )?;
+
+    if let Some(other) = ddids.iter().find(|d| **d != ddids[0]) {
+        return Err(WaterfallError::MultipleDataDescriptions(ddids[0], *other));
+    }
+
+    let mut amplitude = Array2::from_elem((n_times, n_chans), f32::NAN);
+    let mut phase = Array2::from_elem((n_times, n_chans), f32::NAN);
+    let cdata = glue::StringBridge::from_rust(data_column);
+    let cflag = glue::StringBridge::from_rust("FLAG");
+    let chunk_len = rows_per_chunk.max(1).min(n_times);
+    let mut data = vec![Complex::<f32>::default(); chunk_len * cell_len];
+    let mut flags = vec![false; chunk_len * cell_len];
+
+    for (c, chunk_rows) in rows.chunks(chunk_len).enumerate() {
+        let data = &mut data[..chunk_rows.len() * cell_len];
+        let flags = &mut flags[..chunk_rows.len() * cell_len];
+        table.read_scalar_rows(&cdata, chunk_rows, data)?;
+        table.read_scalar_rows(&cflag, chunk_rows, flags)?;
+
+        for i in 0..chunk_rows.len() {
+            let t = c * chunk_len + i;
+
+            for chan in 0..n_chans {
+                let k = i * cell_len + chan * n_pols + pol;
+
+                if !flags[k] {
+                    amplitude[[t, chan]] = data[k].norm();
+                    phase[[t, chan]] = data[k].arg();
+                }
+            }
+        }
+    }
+
+    // The rows of a baseline are almost always in time order already, so
+    // only shuffle the outputs if they are not.
+    if times.windows(2).any(|w| w[0] > w[1]) {
+        let mut order: Vec<usize> = (0..n_times).collect();
+        order.sort_by(|a, b| times[*a].partial_cmp(&times[*b]).unwrap());
+        times = order.iter().map(|i| times[*i]).collect();
+        amplitude = amplitude.select(ndarray::Axis(0), &order);
+        phase = phase.select(ndarray::Axis(0), &order);
+    }
+
+    Ok(Waterfall {
+        times,
+        data_desc_id: ddids[0],
+        amplitude,
+        phase,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn synthetic_waterfall() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec::default();
+        let mut t = synthetic_ms(tmp_dir.path().join("test.ms"), &spec).unwrap();
+        let index = BaselineIndex::build(&mut t).unwrap();
+        let baseline = index.baselines().find(|(a1, a2)| a1 != a2).unwrap();
+        let rows = index.rows(baseline.0, baseline.1).to_vec();
+
+        // Flag one sample of the baseline's second row.
+        let mut flags: Vec<bool> = t.get_cell_as_vec("FLAG", rows[1]).unwrap();
+        flags[2 * spec.n_pols + 1] = true;
+        let flags = ndarray::Array::from_shape_vec((spec.n_chans, spec.n_pols), flags).unwrap();
+        t.put_cell("FLAG", rows[1], &flags).unwrap();
+
+        let w = waterfall(&mut t, &index, baseline, 1, "DATA").unwrap();
+        assert_eq!(w.times.len(), spec.n_timesteps);
+        assert_eq!(w.amplitude.shape(), &[spec.n_timesteps, spec.n_chans]);
+        assert_eq!(w.data_desc_id, 0);
+
+        let vis = spec.expected_vis(rows[0] as usize, 3, 1);
+        assert_eq!(w.amplitude[[0, 3]], vis.norm());
+        assert_eq!(w.phase[[0, 3]], vis.arg());
+        assert!(w.amplitude[[1, 2]].is_nan());
+        assert!(w.phase[[1, 2]].is_nan());
+        assert!(!w.amplitude[[1, 3]].is_nan());
+
+        assert!(matches!(
+            waterfall(&mut t, &index, baseline, spec.n_pols, "DATA"),
+            Err(WaterfallError::NoSuchPolarization(..))
+        ));
+        assert!(matches!(
+            waterfall(&mut t, &index, (99, 100), 0, "DATA"),
+            Err(WaterfallError::NoSuchBaseline(99, 100))
+        ));
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Meteorological measurements, as recorded in the `WEATHER` sub-table.
+
+use super::schema;
+use crate::{GlueDataType, QuantityColumn, Table, TableError};
+
+/// The meteorological conditions at an antenna or weather station, as
+/// recorded in a row of the `WEATHER` sub-table of a Measurement Set.
+///
+/// Every measurement is optional; `None` means that it was not made.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Weather {
+    /// The antenna at, or nearest to, which the measurements were made.
+    pub antenna_id: i32,
+
+    /// The midpoint of the time range of the measurements, in MJD seconds.
+    pub time: f64,
+
+    /// The length of the time range of the measurements, in seconds.
+    pub interval: f64,
+
+    /// The ambient temperature, in K.
+    pub temperature: Option<f32>,
+
+    /// The ambient pressure, in hPa.
+    pub pressure: Option<f32>,
+
+    /// The relative humidity, in percent.
+    pub rel_humidity: Option<f32>,
+
+    /// The dew point, in K.
+    pub dew_point: Option<f32>,
+
+    /// The wind speed, in m/s.
+    pub wind_speed: Option<f32>,
+
+    /// The direction from which the wind blows, in radians east of north.
+    pub wind_direction: Option<f32>,
+
+    /// The precipitable water vapor column, in m⁻².
+    pub h2o: Option<f32>,
+
+    /// The ionospheric electron column, in m⁻².
+    pub ionos_electron: Option<f32>,
+}
+
+/// An accessor for one of the optional measurements of a [`Weather`] row.
+type Field = fn(&mut Weather) -> &mut Option<f32>;
+
+/// The optional measurement columns of the `WEATHER` table, their units,
+/// and the fields that hold them.
+const MEASUREMENTS: &[(&str, &str, Field)] = &[
+    ("TEMPERATURE", "K", |w| &mut w.temperature),
+    ("PRESSURE", "hPa", |w| &mut w.pressure),
+    ("REL_HUMIDITY", "%", |w| &mut w.rel_humidity),
+    ("DEW_POINT", "K", |w| &mut w.dew_point),
+    ("WIND_SPEED", "m/s", |w| &mut w.wind_speed),
+    ("WIND_DIRECTION", "rad", |w| &mut w.wind_direction),
+    ("H2O", "m-2", |w| &mut w.h2o),
+    ("IONOS_ELECTRON", "m-2", |w| &mut w.ionos_electron),
+];
+
+/// Append rows to the `WEATHER` sub-table of a Measurement Set, creating it
+/// if it does not exist.
+///
+/// For each kind of measurement present in any of *records*, the value
+/// column and its flag column, such as `PRESSURE` and `PRESSURE_FLAG`, are
+/// added to the table if needed, with the value column labeled with its
+/// units. Where a record lacks a measurement whose column exists, the value
+/// is written as NaN and flagged.
+pub fn write_weather(ms: &mut Table, records: &[Weather]) -> Result<(), TableError> {
+    let mut table = schema::WEATHER.open_or_create_subtable(ms)?;
+    let col_names = table.column_names()?;
+    let first_row = table.n_rows();
+    let mut records = records.to_vec();
+    let mut columns = Vec::new();
+
+    for (name, unit, field) in MEASUREMENTS {
+        if !col_names.iter().any(|c| c == name) {
+            if !records.iter_mut().any(|r| field(r).is_some()) {
+                continue;
+            }
+
+            QuantityColumn::new(*name, unit).add_scalar(&mut table, GlueDataType::TpFloat)?;
+            table.add_scalar_column(
+                GlueDataType::TpBool,
+                &format!("{}_FLAG", name),
+                None,
+                true,
+                false,
+            )?;
+
+            // Existing rows lack the new measurement.
+            for row in 0..first_row {
+                table.put_cell(name, row, &f32::NAN)?;
+                table.put_cell(&format!("{}_FLAG", name), row, &true)?;
+            }
+        }
+
+        columns.push((*name, *field));
+    }
+
+    table.add_rows(records.len())?;
+
+    for (i, record) in records.iter_mut().enumerate() {
+        let row = first_row + i as u64;
+
+        table.put_cell("ANTENNA_ID", row, &record.antenna_id)?;
+        table.put_cell("TIME", row, &record.time)?;
+        table.put_cell("INTERVAL", row, &record.interval)?;
+
+        for (name, field) in &columns {
+            let value = *field(record);
+            table.put_cell(name, row, &value.unwrap_or(f32::NAN))?;
+            table.put_cell(&format!("{}_FLAG", name), row, &value.is_none())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Read all of the rows of a `WEATHER` table.
+///
+/// Measurements are `None` if the table lacks their columns or if they are
+/// flagged.
+pub fn read_weather(table: &mut Table) -> Result<Vec<Weather>, TableError> {
+    let col_names = table.column_names()?;
+    let has = |name: &str| col_names.iter().any(|c| c == name);
+    let mut records = Vec::with_capacity(table.n_rows() as usize);
+
+    for row in 0..table.n_rows() {
+        let mut record = Weather {
+            antenna_id: table.get_cell("ANTENNA_ID", row)?,
+            time: table.get_cell("TIME", row)?,
+            interval: table.get_cell("INTERVAL", row)?,
+            ..Weather::default()
+        };
+
+        for (name, _unit, field) in MEASUREMENTS {
+            if !has(name) {
+                continue;
+            }
+
+            let flag_col = format!("{}_FLAG", name);
+
+            if has(&flag_col) && table.get_cell::<bool>(&flag_col, row)? {
+                continue;
+            }
+
+            *field(&mut record) = Some(table.get_cell(name, row)?);
+        }
+
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use crate::TableOpenMode;
+    use tempfile::tempdir;
+
+    #[test]
+    fn write_and_read_back() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("test.ms");
+        let mut ms = synthetic_ms(&path, &SyntheticMsSpec::default()).unwrap();
+
+        let first = Weather {
+            antenna_id: 0,
+            time: 1000.,
+            interval: 60.,
+            temperature: Some(285.5),
+            pressure: Some(1013.25),
+            ..Weather::default()
+        };
+        let second = Weather {
+            antenna_id: 0,
+            time: 1060.,
+            interval: 60.,
+            temperature: Some(285.0),
+            ..Weather::default()
+        };
+        write_weather(&mut ms, &[first.clone(), second.clone()]).unwrap();
+
+        // A later batch can add a new kind of measurement.
+        let third = Weather {
+            antenna_id: 1,
+            time: 1000.,
+            interval: 60.,
+            wind_speed: Some(4.5),
+            ..Weather::default()
+        };
+        write_weather(&mut ms, std::slice::from_ref(&third)).unwrap();
+
+        let mut t = Table::open(path.join("WEATHER"), TableOpenMode::Read).unwrap();
+        assert_eq!(read_weather(&mut t).unwrap(), vec![first, second, third]);
+        assert_eq!(t.column_units("PRESSURE").unwrap(), vec!["hPa"]);
+        assert_eq!(t.column_units("TIME").unwrap(), vec!["s"]);
+        assert!(!t.column_names().unwrap().contains(&"H2O".to_owned()));
+    }
+}
@@ -0,0 +1,548 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Holding cell data in memory at reduced precision.
+//!
+//! casacore stores visibilities as single-precision floats, but processing
+//! very large data sets can need more host memory than that allows. The
+//! methods here let the caller work with half-precision ([`F16`]) or
+//! 8-bit quantized buffers instead: an [`ElementCodec`] expands them to the
+//! column’s element type as they are written, and reduces the column’s
+//! values as they are read, using only a bounded amount of full-precision
+//! scratch space along the way. What is stored on disk is unchanged.
+
+use ndarray::{ArrayD, ArrayViewD, IxDyn};
+use std::{fmt, mem::size_of};
+
+use crate::{
+    glue, io_stats::IoDirection, CasaDataType, CasaScalarData, Complex, Table, TableError,
+    UnexpectedDataTypeError,
+};
+
+/// The most full-precision data to hold in memory at once during a
+/// conversion, in bytes.
+const SCRATCH_BYTES: usize = 64 * 1024 * 1024;
+
+/// An IEEE 754 half-precision floating-point number.
+///
+/// This is a storage type: convert to and from `f32` to do arithmetic.
+/// Conversion from `f32` rounds to the nearest representable value, with
+/// ties to even; values too large in magnitude become infinite.
+#[derive(Clone, Copy, Default)]
+pub struct F16(u16);
+
+impl F16 {
+    /// Create a value from its bit pattern.
+    pub const fn from_bits(bits: u16) -> Self {
+        F16(bits)
+    }
+
+    /// Get the bit pattern of the value.
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    /// Convert from single precision.
+    pub fn from_f32(value: f32) -> Self {
+        let bits = value.to_bits();
+        let sign = ((bits >> 16) & 0x8000) as u16;
+        let exp = ((bits >> 23) & 0xff) as i32;
+        let man = bits & 0x7f_ffff;
+
+        if exp == 0xff {
+            // Infinity, or NaN with a nonzero payload.
+            let nan = if man != 0 {
+                0x200 | (man >> 13) as u16
+            } else {
+                0
+            };
+            return F16(sign | 0x7c00 | nan);
+        }
+
+        let exp = exp - 127 + 15;
+
+        if exp >= 0x1f {
+            return F16(sign | 0x7c00);
+        }
+
+        if exp <= 0 {
+            // Subnormal, or too small to represent at all.
+            if exp < -10 {
+                return F16(sign);
+            }
+
+            let man = man | 0x80_0000;
+            let shift = (14 - exp) as u32;
+            let mut half = man >> shift;
+            let rem = man & ((1 << shift) - 1);
+            let halfway = 1 << (shift - 1);
+
+            if rem > halfway || (rem == halfway && half & 1 != 0) {
+                half += 1;
+            }
+
+            return F16(sign | half as u16);
+        }
+
+        // A carry out of the mantissa correctly bumps the exponent, up to
+        // infinity.
+        let mut half = ((exp as u32) << 10) | (man >> 13);
+        let rem = man & 0x1fff;
+
+        if rem > 0x1000 || (rem == 0x1000 && half & 1 != 0) {
+            half += 1;
+        }
+
+        F16(sign | half as u16)
+    }
+
+    /// Convert to single precision. This is exact.
+    pub fn to_f32(self) -> f32 {
+        let sign = ((self.0 & 0x8000) as u32) << 16;
+        let exp = ((self.0 >> 10) & 0x1f) as u32;
+        let man = (self.0 & 0x3ff) as u32;
+
+        let bits = match exp {
+            0 => {
+                let magnitude = man as f32 / (1 << 24) as f32;
+                return if sign != 0 { -magnitude } else { magnitude };
+            }
+            0x1f => sign | 0x7f80_0000 | (man << 13),
+            _ => sign | ((exp + 127 - 15) << 23) | (man << 13),
+        };
+
+        f32::from_bits(bits)
+    }
+}
+
+impl From<f32> for F16 {
+    fn from(value: f32) -> Self {
+        F16::from_f32(value)
+    }
+}
+
+impl From<F16> for f32 {
+    fn from(value: F16) -> Self {
+        value.to_f32()
+    }
+}
+
+impl PartialEq for F16 {
+    fn eq(&self, other: &F16) -> bool {
+        self.to_f32() == other.to_f32()
+    }
+}
+
+impl PartialOrd for F16 {
+    fn partial_cmp(&self, other: &F16) -> Option<std::cmp::Ordering> {
+        self.to_f32().partial_cmp(&other.to_f32())
+    }
+}
+
+impl fmt::Debug for F16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_f32(), f)
+    }
+}
+
+impl fmt::Display for F16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f32(), f)
+    }
+}
+
+/// A conversion between a compact in-memory element type `T` and the
+/// element type of a column.
+pub trait ElementCodec<T> {
+    /// The element type of the column.
+    type Full: CasaScalarData + Copy + Default;
+
+    /// Convert a compact value to the column’s type, for writing.
+    fn expand(&self, value: T) -> Self::Full;
+
+    /// Convert a value of the column’s type to the compact type, for
+    /// reading.
+    fn compact(&self, value: Self::Full) -> T;
+}
+
+/// Stores [`F16`] data in single-precision columns.
+///
+/// This converts between `F16` and `f32`, and between `Complex<F16>` and
+/// `Complex<f32>`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HalfPrecision;
+
+impl ElementCodec<F16> for HalfPrecision {
+    type Full = f32;
+
+    fn expand(&self, value: F16) -> f32 {
+        value.to_f32()
+    }
+
+    fn compact(&self, value: f32) -> F16 {
+        F16::from_f32(value)
+    }
+}
+
+impl ElementCodec<Complex<F16>> for HalfPrecision {
+    type Full = Complex<f32>;
+
+    fn expand(&self, value: Complex<F16>) -> Complex<f32> {
+        Complex::new(value.re.to_f32(), value.im.to_f32())
+    }
+
+    fn compact(&self, value: Complex<f32>) -> Complex<F16> {
+        Complex::new(F16::from_f32(value.re), F16::from_f32(value.im))
+    }
+}
+
+/// Stores 8-bit quantized data in single-precision columns.
+///
+/// A quantized value *q* stands for *q* × [`Self::scale`]. On reading,
+/// values are divided by the scale, rounded to the nearest integer, and
+/// clamped to ±127; NaNs become zero. This converts between `i8` and `f32`,
+/// and between `Complex<i8>` and `Complex<f32>`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinearQuantizer {
+    /// The value of one quantization step.
+    pub scale: f32,
+}
+
+impl LinearQuantizer {
+    /// Create a quantizer whose range just covers values of magnitude up to
+    /// *max_abs*.
+    pub fn for_max_abs(max_abs: f32) -> Self {
+        LinearQuantizer {
+            scale: max_abs / 127.,
+        }
+    }
+
+    fn quantize(&self, value: f32) -> i8 {
+        let q = (value / self.scale).round();
+
+        if q.is_nan() {
+            0
+        } else {
+            q.clamp(-127., 127.) as i8
+        }
+    }
+}
+
+impl ElementCodec<i8> for LinearQuantizer {
+    type Full = f32;
+
+    fn expand(&self, value: i8) -> f32 {
+        value as f32 * self.scale
+    }
+
+    fn compact(&self, value: f32) -> i8 {
+        self.quantize(value)
+    }
+}
+
+impl ElementCodec<Complex<i8>> for LinearQuantizer {
+    type Full = Complex<f32>;
+
+    fn expand(&self, value: Complex<i8>) -> Complex<f32> {
+        Complex::new(value.re as f32 * self.scale, value.im as f32 * self.scale)
+    }
+
+    fn compact(&self, value: Complex<f32>) -> Complex<i8> {
+        Complex::new(self.quantize(value.re), self.quantize(value.im))
+    }
+}
+
+impl Table {
+    /// Read one array cell, converting its elements with *codec*.
+    ///
+    /// The cell may have any shape, which is returned in C order.
+    pub fn get_cell_with<T, C: ElementCodec<T>>(
+        &mut self,
+        codec: &C,
+        col_name: &str,
+        row: u64,
+    ) -> Result<ArrayD<T>, TableError> {
+        let shape = self.get_cell_shape(col_name, row)?;
+        let data: Vec<C::Full> = self.get_cell_as_vec(col_name, row)?;
+        let data = data.into_iter().map(|v| codec.compact(v)).collect();
+        Ok(ArrayD::from_shape_vec(IxDyn(&shape), data).unwrap())
+    }
+
+    /// Write one array cell, converting the elements of *data* with *codec*.
+    ///
+    /// The shape of *data* is given in C order. If the column has a fixed
+    /// shape, it must match.
+    pub fn put_cell_with<T: Copy, C: ElementCodec<T>>(
+        &mut self,
+        codec: &C,
+        col_name: &str,
+        row: u64,
+        data: ArrayViewD<T>,
+    ) -> Result<(), TableError> {
+        self.check_codec_column::<C::Full>(col_name)?;
+        let buf: Vec<C::Full> = data.iter().map(|v| codec.expand(*v)).collect();
+        self.put_cell_shaped(col_name, row, data.shape(), &buf)
+    }
+
+    /// Write one array cell from a flat buffer holding data of the given
+    /// C-order shape. The column type must already have been checked.
+    pub(crate) fn put_cell_shaped<T: CasaScalarData>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+        shape: &[usize],
+        data: &[T],
+    ) -> Result<(), TableError> {
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let dims: Vec<std::os::raw::c_ulong> = shape.iter().map(|d| *d as _).collect();
+
+        if unsafe {
+            glue::table_put_column_range_shaped(
+                self.handle,
+                &ccol_name,
+                row,
+                1,
+                dims.len() as _,
+                dims.as_ptr(),
+                data.as_ptr() as _,
+                &mut self.exc_info,
+            )
+        } != 0
+        {
+            return self.exc_info.as_err();
+        }
+
+        self.record_io(
+            IoDirection::Put,
+            col_name,
+            1,
+            T::DATA_TYPE,
+            data.len() as u64,
+        );
+        Ok(())
+    }
+
+    /// Read a range of rows of a column, converting its elements with
+    /// *codec*.
+    ///
+    /// The column must be scalar or have a fixed shape. The first axis of
+    /// the result indexes rows and the rest are the axes of the column’s
+    /// cells, in C order. The data are read in pieces, so that the
+    /// full-precision scratch space needed is bounded no matter how many
+    /// rows are requested.
+    pub fn get_rows_with<T, C: ElementCodec<T>>(
+        &mut self,
+        codec: &C,
+        col_name: &str,
+        start_row: u64,
+        n_rows: usize,
+    ) -> Result<ArrayD<T>, TableError> {
+        let cell_shape = self.bulk_column_cell_shape::<C::Full>(col_name)?;
+        let cell_len: usize = cell_shape.iter().product();
+        let chunk_rows = scratch_rows::<C::Full>(cell_len);
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut scratch = vec![C::Full::default(); chunk_rows.min(n_rows) * cell_len];
+        let mut out = Vec::with_capacity(n_rows * cell_len);
+        let mut done = 0;
+
+        while done < n_rows {
+            let n = chunk_rows.min(n_rows - done);
+            let buf = &mut scratch[..n * cell_len];
+
+            if unsafe {
+                glue::table_get_column_range(
+                    self.handle,
+                    &ccol_name,
+                    start_row + done as u64,
+                    n as u64,
+                    buf.as_mut_ptr() as _,
+                    &mut self.exc_info,
+                )
+            } != 0
+            {
+                return self.exc_info.as_err();
+            }
+
+            out.extend(buf.iter().map(|v| codec.compact(*v)));
+            self.record_io(
+                IoDirection::Get,
+                col_name,
+                n as u64,
+                C::Full::DATA_TYPE,
+                buf.len() as u64,
+            );
+            done += n;
+        }
+
+        let mut shape = vec![n_rows];
+        shape.extend_from_slice(&cell_shape);
+        Ok(ArrayD::from_shape_vec(IxDyn(&shape), out).unwrap())
+    }
+
+    /// Write a range of rows of a column, converting the elements of *data*
+    /// with *codec*.
+    ///
+    /// The column must be scalar or have a fixed shape. The first axis of
+    /// *data* indexes rows and the rest must match the shape of the
+    /// column’s cells, in C order. Rows are added to the table as needed.
+    /// Like [`Self::get_rows_with`], this works in pieces of bounded size.
+    pub fn put_rows_with<T: Copy, C: ElementCodec<T>>(
+        &mut self,
+        codec: &C,
+        col_name: &str,
+        start_row: u64,
+        data: ArrayViewD<T>,
+    ) -> Result<(), TableError> {
+        let cell_shape = self.bulk_column_cell_shape::<C::Full>(col_name)?;
+
+        if data.ndim() == 0 || data.shape()[1..] != cell_shape[..] {
+            return Err(TableError::ChunkShapeMismatch(
+                cell_shape,
+                data.shape().get(1..).unwrap_or(&[]).to_vec(),
+            ));
+        }
+
+        let n_rows = data.shape()[0];
+        let cell_len: usize = cell_shape.iter().product();
+        let chunk_rows = scratch_rows::<C::Full>(cell_len);
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let mut scratch = Vec::with_capacity(chunk_rows.min(n_rows) * cell_len);
+
+        if start_row + n_rows as u64 > self.n_rows() {
+            self.add_rows((start_row + n_rows as u64 - self.n_rows()) as usize)?;
+        }
+
+        for (i, chunk) in data
+            .axis_chunks_iter(ndarray::Axis(0), chunk_rows)
+            .enumerate()
+        {
+            let n = chunk.shape()[0];
+            scratch.clear();
+            scratch.extend(chunk.iter().map(|v| codec.expand(*v)));
+
+            if unsafe {
+                glue::table_put_column_range(
+                    self.handle,
+                    &ccol_name,
+                    start_row + (i * chunk_rows) as u64,
+                    n as u64,
+                    scratch.as_ptr() as _,
+                    &mut self.exc_info,
+                )
+            } != 0
+            {
+                return self.exc_info.as_err();
+            }
+
+            self.record_io(
+                IoDirection::Put,
+                col_name,
+                n as u64,
+                C::Full::DATA_TYPE,
+                scratch.len() as u64,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Check that a column holds elements of type `T`.
+    fn check_codec_column<T: CasaScalarData>(&mut self, col_name: &str) -> Result<(), TableError> {
+        let data_type = self.get_col_desc(col_name)?.data_type().element_type();
+
+        if data_type != T::DATA_TYPE {
+            return Err(UnexpectedDataTypeError(T::DATA_TYPE, data_type).into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the number of rows of cells with *cell_len* elements of type `T`
+/// that fit in the scratch space.
+fn scratch_rows<T>(cell_len: usize) -> usize {
+    (SCRATCH_BYTES / (cell_len * size_of::<T>()).max(1)).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use ndarray::Array3;
+    use tempfile::tempdir;
+
+    #[test]
+    fn f16_conversions() {
+        for &x in &[0., -0., 1., -2.5, 65504., 1. / 16384., 1. / 16_777_216.] {
+            assert_eq!(F16::from_f32(x).to_f32(), x);
+        }
+
+        assert_eq!(F16::from_f32(1.).to_bits(), 0x3c00);
+        assert_eq!(F16::from_f32(65520.).to_f32(), f32::INFINITY);
+        assert_eq!(F16::from_f32(-1e-9).to_f32(), 0.);
+        assert!(F16::from_f32(f32::NAN).to_f32().is_nan());
+
+        // 1 + 2^-11 is halfway between 1 and the next value up, and rounds
+        // to the even one.
+        assert_eq!(F16::from_f32(1. + 1. / 2048.).to_f32(), 1.);
+        assert_eq!(F16::from_f32(1. + 3. / 2048.).to_f32(), 1. + 2. / 1024.);
+    }
+
+    #[test]
+    fn round_trip_through_codecs() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_array_column(
+            GlueDataType::TpComplex,
+            "DATA",
+            None,
+            Some(&[4, 2]),
+            true,
+            false,
+        )
+        .unwrap();
+        desc.add_array_column(GlueDataType::TpFloat, "V", None, None, false, false)
+            .unwrap();
+        let mut t =
+            Table::new(tmp_dir.path().join("t.tab"), desc, 0, TableCreateMode::New).unwrap();
+
+        let data = Array3::from_shape_fn((5, 4, 2), |(r, c, p)| {
+            Complex::new(
+                F16::from_f32(r as f32 + 0.5),
+                F16::from_f32((c * 2 + p) as f32),
+            )
+        });
+        t.put_rows_with(&HalfPrecision, "DATA", 0, data.view().into_dyn())
+            .unwrap();
+        assert_eq!(t.n_rows(), 5);
+
+        let full: Vec<Complex<f32>> = t.get_cell_as_vec("DATA", 3).unwrap();
+        assert_eq!(full[5], Complex::new(3.5, 5.));
+
+        let back = t
+            .get_rows_with::<Complex<F16>, _>(&HalfPrecision, "DATA", 1, 4)
+            .unwrap();
+        assert_eq!(back.shape(), &[4, 4, 2]);
+        assert_eq!(back[[2, 3, 1]], data[[3, 3, 1]]);
+
+        let q = LinearQuantizer::for_max_abs(127.);
+        let back = t.get_rows_with::<Complex<i8>, _>(&q, "DATA", 0, 5).unwrap();
+        assert_eq!(back[[4, 1, 0]], Complex::new(5, 2));
+
+        let cell = ndarray::arr2(&[[1i8, -3, 127]]).into_dyn();
+        let q = LinearQuantizer { scale: 0.5 };
+        t.put_cell_with(&q, "V", 2, cell.view()).unwrap();
+        let full: Vec<f32> = t.get_cell_as_vec("V", 2).unwrap();
+        assert_eq!(full, vec![0.5, -1.5, 63.5]);
+        assert_eq!(t.get_cell_with::<i8, _>(&q, "V", 2).unwrap(), cell);
+
+        assert!(t
+            .put_cell_with(
+                &HalfPrecision,
+                "DATA",
+                0,
+                cell.mapv(|v| F16::from_f32(v as f32)).view()
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Reading chunks of rows ahead of time on a background thread.
+//!
+//! A sequential scan of a large table alternates between waiting for
+//! casacore to decode a chunk of rows and processing it. A
+//! [`PrefetchingReader`] moves the decoding to a background thread that
+//! keeps a configurable number of chunks in flight, so that the two overlap.
+
+use ndarray::{ArrayD, ArrayViewD, IxDyn};
+use std::{
+    any::Any,
+    sync::mpsc::{sync_channel, Receiver},
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    glue, io_stats::IoDirection, CasaScalarData, Complex, GlueDataType, SyncTable, Table,
+    TableError, UnexpectedDataTypeError,
+};
+
+/// The default number of chunks that may be in flight at once.
+pub const DEFAULT_PREFETCH_DEPTH: usize = 2;
+
+/// A chunk of rows read by a [`PrefetchingReader`].
+pub struct RowChunk {
+    start_row: u64,
+    n_rows: usize,
+    names: Vec<String>,
+    data: Vec<Box<dyn Any + Send>>,
+}
+
+impl RowChunk {
+    /// Get the number of the first row of the chunk.
+    pub fn start_row(&self) -> u64 {
+        self.start_row
+    }
+
+    /// Get the number of rows in the chunk.
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    /// Get the data of one column.
+    ///
+    /// The first axis of the array indexes rows and the rest are the axes
+    /// of the column’s cells, in C order. Returns `None` if the column was
+    /// not one of those requested or if `T` is not its element type.
+    pub fn column<T: 'static>(&self, col_name: &str) -> Option<ArrayViewD<'_, T>> {
+        let i = self.names.iter().position(|n| n == col_name)?;
+        self.data[i].downcast_ref::<ArrayD<T>>().map(|a| a.view())
+    }
+}
+
+impl std::fmt::Debug for RowChunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RowChunk")
+            .field("start_row", &self.start_row)
+            .field("n_rows", &self.n_rows)
+            .field("columns", &self.names)
+            .finish()
+    }
+}
+
+/// A column to be read by the background thread.
+struct ColumnPlan {
+    name: String,
+    data_type: GlueDataType,
+    cell_shape: Vec<usize>,
+}
+
+/// Reads chunks of rows of selected columns on a background thread.
+///
+/// The reader takes over a [`SyncTable`] and reads it from start to end on
+/// the table's own thread, staying up to *depth* chunks ahead of the
+/// consumer. Chunks are retrieved by iterating over the reader. The columns
+/// must be scalar or have fixed shapes, with numeric or boolean elements.
+///
+/// ```no_run
+/// use rubbl_casatables::{Complex, PrefetchingReader, SyncTable, TableOpenMode};
+///
+/// let t = SyncTable::open("vis.ms", TableOpenMode::Read).unwrap();
+/// let reader = PrefetchingReader::new(t, &["TIME", "DATA"], 4096, 2).unwrap();
+///
+/// for chunk in reader {
+///     let chunk = chunk.unwrap();
+///     let data = chunk.column::<Complex<f32>>("DATA").unwrap();
+///     println!("rows {}+{}: {:?}", chunk.start_row(), chunk.n_rows(), data.shape());
+/// }
+/// ```
+pub struct PrefetchingReader {
+    receiver: Option<Receiver<Result<RowChunk, TableError>>>,
+    worker: Option<JoinHandle<SyncTable>>,
+}
+
+impl PrefetchingReader {
+    /// Start reading *columns* of *table* in chunks of *rows_per_chunk* rows,
+    /// with up to *depth* chunks read ahead.
+    ///
+    /// The columns are checked before the thread is started.
+    pub fn new(
+        table: SyncTable,
+        columns: &[&str],
+        rows_per_chunk: usize,
+        depth: usize,
+    ) -> Result<Self, TableError> {
+        let columns: Vec<String> = columns.iter().map(|&c| c.to_owned()).collect();
+        let plans = table.with(move |t| plan_columns(t, &columns))?;
+
+        let rows_per_chunk = rows_per_chunk.max(1);
+        let (sender, receiver) = sync_channel(depth.max(1));
+        let worker = thread::spawn(move || {
+            table.with(move |t| {
+                let n_rows = t.n_rows();
+                let mut row = 0;
+
+                while row < n_rows {
+                    let n = (rows_per_chunk as u64).min(n_rows - row);
+                    let chunk = read_chunk(t, &plans, row, n as usize);
+                    let failed = chunk.is_err();
+
+                    // A send error means that the reader has been dropped.
+                    if sender.send(chunk).is_err() || failed {
+                        break;
+                    }
+
+                    row += n;
+                }
+            });
+
+            table
+        });
+
+        Ok(PrefetchingReader {
+            receiver: Some(receiver),
+            worker: Some(worker),
+        })
+    }
+
+    /// Stop reading and get back the table.
+    pub fn into_table(mut self) -> SyncTable {
+        self.stop()
+            .expect("prefetching reader thread already joined")
+    }
+
+    fn stop(&mut self) -> Option<SyncTable> {
+        // Dropping the receiver unblocks the thread if it is waiting to send.
+        self.receiver = None;
+
+        let worker = self.worker.take()?;
+
+        match worker.join() {
+            Ok(table) => Some(table),
+            Err(payload) => std::panic::resume_unwind(payload),
+        }
+    }
+}
+
+impl Iterator for PrefetchingReader {
+    type Item = Result<RowChunk, TableError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.as_ref()?.recv().ok()
+    }
+}
+
+impl Drop for PrefetchingReader {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.stop();
+        }
+    }
+}
+
+fn plan_columns(table: &mut Table, columns: &[String]) -> Result<Vec<ColumnPlan>, TableError> {
+    let mut plans = Vec::with_capacity(columns.len());
+
+    for name in columns {
+        let data_type = table.get_col_desc(name)?.data_type().element_type();
+
+        let cell_shape = match data_type {
+            GlueDataType::TpBool => table.bulk_column_cell_shape::<bool>(name)?,
+            GlueDataType::TpChar => table.bulk_column_cell_shape::<i8>(name)?,
+            GlueDataType::TpUChar => table.bulk_column_cell_shape::<u8>(name)?,
+            GlueDataType::TpShort => table.bulk_column_cell_shape::<i16>(name)?,
+            GlueDataType::TpUShort => table.bulk_column_cell_shape::<u16>(name)?,
+            GlueDataType::TpInt => table.bulk_column_cell_shape::<i32>(name)?,
+            GlueDataType::TpUInt => table.bulk_column_cell_shape::<u32>(name)?,
+            GlueDataType::TpFloat => table.bulk_column_cell_shape::<f32>(name)?,
+            GlueDataType::TpDouble => table.bulk_column_cell_shape::<f64>(name)?,
+            GlueDataType::TpComplex => table.bulk_column_cell_shape::<Complex<f32>>(name)?,
+            GlueDataType::TpDComplex => table.bulk_column_cell_shape::<Complex<f64>>(name)?,
+            other => {
+                return Err(UnexpectedDataTypeError(GlueDataType::TpDouble, other).into());
+            }
+        };
+
+        plans.push(ColumnPlan {
+            name: name.clone(),
+            data_type,
+            cell_shape,
+        });
+    }
+
+    Ok(plans)
+}
+
+fn read_chunk(
+    table: &mut Table,
+    plans: &[ColumnPlan],
+    start_row: u64,
+    n_rows: usize,
+) -> Result<RowChunk, TableError> {
+    let mut data = Vec::with_capacity(plans.len());
+
+    for plan in plans {
+        let column = match plan.data_type {
+            GlueDataType::TpBool => read_column::<bool>(table, plan, start_row, n_rows)?,
+            GlueDataType::TpChar => read_column::<i8>(table, plan, start_row, n_rows)?,
+            GlueDataType::TpUChar => read_column::<u8>(table, plan, start_row, n_rows)?,
+            GlueDataType::TpShort => read_column::<i16>(table, plan, start_row, n_rows)?,
+            GlueDataType::TpUShort => read_column::<u16>(table, plan, start_row, n_rows)?,
+            GlueDataType::TpInt => read_column::<i32>(table, plan, start_row, n_rows)?,
+            GlueDataType::TpUInt => read_column::<u32>(table, plan, start_row, n_rows)?,
+            GlueDataType::TpFloat => read_column::<f32>(table, plan, start_row, n_rows)?,
+            GlueDataType::TpDouble => read_column::<f64>(table, plan, start_row, n_rows)?,
+            GlueDataType::TpComplex => read_column::<Complex<f32>>(table, plan, start_row, n_rows)?,
+            GlueDataType::TpDComplex => {
+                read_column::<Complex<f64>>(table, plan, start_row, n_rows)?
+            }
+            _ => unreachable!(),
+        };
+
+        data.push(column);
+    }
+
+    Ok(RowChunk {
+        start_row,
+        n_rows,
+        names: plans.iter().map(|p| p.name.clone()).collect(),
+        data,
+    })
+}
+
+fn read_column<T: CasaScalarData + Copy + Default + Send + 'static>(
+    table: &mut Table,
+    plan: &ColumnPlan,
+    start_row: u64,
+    n_rows: usize,
+) -> Result<Box<dyn Any + Send>, TableError> {
+    let mut shape = vec![n_rows];
+    shape.extend_from_slice(&plan.cell_shape);
+    let mut buf = vec![T::default(); shape.iter().product()];
+    let ccol_name = glue::StringBridge::from_rust(&plan.name);
+
+    if unsafe {
+        glue::table_get_column_range(
+            table.handle,
+            &ccol_name,
+            start_row,
+            n_rows as u64,
+            buf.as_mut_ptr() as _,
+            &mut table.exc_info,
+        )
+    } != 0
+    {
+        return table.exc_info.as_err();
+    }
+
+    table.record_io(
+        IoDirection::Get,
+        &plan.name,
+        n_rows as u64,
+        T::DATA_TYPE,
+        buf.len() as u64,
+    );
+    Ok(Box::new(
+        ArrayD::from_shape_vec(IxDyn(&shape), buf).unwrap(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn prefetch_all_rows() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("t.tab");
+        let t = SyncTable::new(move || {
+            let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH)?;
+            desc.add_scalar_column(GlueDataType::TpDouble, "X", None, true, false)?;
+            desc.add_array_column(GlueDataType::TpInt, "A", None, Some(&[3]), true, false)?;
+            let mut t = Table::new(path, desc, 10, TableCreateMode::New)?;
+
+            for row in 0..10 {
+                t.put_cell("X", row, &(row as f64))?;
+                t.put_cell("A", row, &vec![row as i32; 3])?;
+            }
+
+            Ok::<_, TableError>(t)
+        })
+        .unwrap();
+
+        let mut reader = PrefetchingReader::new(t, &["X", "A"], 4, 2).unwrap();
+        let mut n_seen = 0;
+
+        for chunk in &mut reader {
+            let chunk = chunk.unwrap();
+            let x = chunk.column::<f64>("X").unwrap();
+            let a = chunk.column::<i32>("A").unwrap();
+            assert_eq!(x.shape(), &[chunk.n_rows()]);
+            assert_eq!(a.shape(), &[chunk.n_rows(), 3]);
+            assert!(chunk.column::<f32>("X").is_none());
+
+            for i in 0..chunk.n_rows() {
+                let row = chunk.start_row() as usize + i;
+                assert_eq!(x[[i]], row as f64);
+                assert_eq!(a[[i, 2]], row as i32);
+            }
+
+            n_seen += chunk.n_rows();
+        }
+
+        assert_eq!(n_seen, 10);
+        let t = reader.into_table();
+        assert_eq!(t.with(|t| t.n_rows()), 10);
+        assert!(PrefetchingReader::new(t, &["NOPE"], 4, 2).is_err());
+    }
+}
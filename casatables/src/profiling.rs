@@ -0,0 +1,630 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Modeling the I/O cost of storage-manager choices.
+//!
+//! Which storage manager suits a column depends on the shape of its cells
+//! and on how the data are written, and the wrong choice can multiply the
+//! amount of data that reaches the disk. Finding out usually means writing a
+//! trial data set with each candidate, as `rubbl mstable bench` does, and
+//! watching it under `strace`. [`simulate_layout`] instead estimates the
+//! bytes written, the number of write operations and seeks, and the number
+//! of files that each candidate would produce, using a simple model of how
+//! casacore's storage managers lay out and flush their data:
+//!
+//! - `StandardStMan` stores the cells of a group of rows together in a
+//!   bucket, which is written once it is full. Variable-shape arrays are
+//!   appended to a separate file, one write per cell, with a small header
+//!   giving the shape, and the bucket holds only their offsets.
+//! - The tiled storage managers store each array column as a hypercube
+//!   whose first axis is the row number, divided into tiles. A tile is
+//!   written once if the tile cache can hold all of the tiles spanning a
+//!   group of rows; otherwise every batch of rows written to it causes it to
+//!   be flushed, and returning to it costs a seek.
+//!
+//! Scalar columns, and array columns of strings, cannot be tiled and are
+//! always modeled as being stored with `StandardStMan`. The estimates ignore
+//! table metadata, reads, and the operating system's own caching, so they
+//! are most useful for comparing candidates rather than predicting exact
+//! numbers.
+//!
+//! ```no_run
+//! use rubbl_casatables::GlueDataType;
+//! use rubbl_casatables::profiling::{
+//!     simulate_layout, CandidateStorageManager, LayoutColumn, WritePlan,
+//! };
+//!
+//! let columns = [
+//!     LayoutColumn::scalar("TIME", GlueDataType::TpDouble),
+//!     LayoutColumn::fixed("DATA", GlueDataType::TpComplex, &[768, 4]),
+//! ];
+//! let plan = WritePlan {
+//!     n_rows: 100_000,
+//!     ..WritePlan::default()
+//! };
+//! let candidates = [
+//!     CandidateStorageManager::Standard { bucket_rows: None },
+//!     CandidateStorageManager::TiledColumn {
+//!         tile_shape: vec![16, 64, 4],
+//!     },
+//! ];
+//!
+//! for estimate in simulate_layout(&columns, &plan, &candidates).unwrap() {
+//!     println!("{}", estimate);
+//! }
+//! ```
+
+use std::fmt;
+use thiserror::Error;
+
+use crate::{ms::WritePattern, GlueDataType};
+
+/// The number of files that every table has regardless of its storage
+/// managers: `table.dat`, `table.info`, and `table.lock`.
+const TABLE_FILES: usize = 3;
+
+/// The number of rows per bucket that `StandardStMan` uses if no bucket
+/// size is given.
+const DEFAULT_BUCKET_ROWS: usize = 32;
+
+/// The assumed size of a string element.
+const STRING_ELEMENT_SIZE: u64 = 16;
+
+/// The size of the offset that `StandardStMan` stores in its buckets for a
+/// variable-shape array.
+const INDIRECT_OFFSET_SIZE: u64 = 8;
+
+/// An error that can occur when simulating a layout.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum LayoutError {
+    /// A tile shape does not match the dimensionality of a column.
+    #[error(
+        "tile shape {tile_shape:?} does not suit column \"{column}\", whose cells have shape \
+         {cell_shape:?}"
+    )]
+    InvalidTileShape {
+        /// The name of the column.
+        column: String,
+        /// The tile shape, in C order.
+        tile_shape: Vec<usize>,
+        /// The shape of the column's cells, in C order.
+        cell_shape: Vec<usize>,
+    },
+
+    /// A column has variable-shape cells but the storage manager requires a
+    /// fixed shape.
+    #[error("column \"{0}\" has variable-shape cells, which TiledColumnStMan cannot store")]
+    VariableShape(String),
+
+    /// The write plan is inconsistent.
+    #[error("invalid write plan: {0}")]
+    InvalidPlan(String),
+}
+
+/// A column of the schema passed to [`simulate_layout`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LayoutColumn {
+    /// The name of the column.
+    pub name: String,
+
+    /// The element type of the column.
+    pub data_type: GlueDataType,
+
+    /// The shape of the column's cells, in C order. This is empty for scalar
+    /// columns. For variable-shape columns, it should be a typical shape.
+    pub cell_shape: Vec<usize>,
+
+    /// Whether all of the column's cells have the same shape.
+    pub fixed_shape: bool,
+}
+
+impl LayoutColumn {
+    /// Describe a scalar column.
+    pub fn scalar(name: &str, data_type: GlueDataType) -> Self {
+        LayoutColumn {
+            name: name.to_owned(),
+            data_type: data_type.element_type(),
+            cell_shape: Vec::new(),
+            fixed_shape: true,
+        }
+    }
+
+    /// Describe an array column whose cells all have the shape *shape*, in
+    /// C order.
+    pub fn fixed(name: &str, data_type: GlueDataType, shape: &[usize]) -> Self {
+        LayoutColumn {
+            name: name.to_owned(),
+            data_type: data_type.element_type(),
+            cell_shape: shape.to_owned(),
+            fixed_shape: true,
+        }
+    }
+
+    /// Describe an array column whose cells may vary in shape, with
+    /// *typical_shape* being the shape assumed for every cell.
+    pub fn variable(name: &str, data_type: GlueDataType, typical_shape: &[usize]) -> Self {
+        LayoutColumn {
+            name: name.to_owned(),
+            data_type: data_type.element_type(),
+            cell_shape: typical_shape.to_owned(),
+            fixed_shape: false,
+        }
+    }
+
+    fn element_bytes(&self) -> u64 {
+        match self.data_type.element_size() {
+            n if n < 0 => STRING_ELEMENT_SIZE,
+            n => n as u64,
+        }
+    }
+
+    fn cell_bytes(&self) -> u64 {
+        self.cell_shape.iter().product::<usize>() as u64 * self.element_bytes()
+    }
+
+    /// Whether the column could be stored by a tiled storage manager.
+    fn is_tileable(&self) -> bool {
+        !self.cell_shape.is_empty() && self.data_type.element_size() > 0
+    }
+}
+
+/// How the data are to be written, for [`simulate_layout`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WritePlan {
+    /// The number of rows to write.
+    pub n_rows: u64,
+
+    /// How the writes are issued. [`WritePattern::Cell`],
+    /// [`WritePattern::CellCached`], and [`WritePattern::Grow`] write one
+    /// row at a time; [`WritePattern::Chunks`] writes [`Self::chunk_rows`]
+    /// rows at a time.
+    pub pattern: WritePattern,
+
+    /// The number of rows per chunk for [`WritePattern::Chunks`].
+    pub chunk_rows: usize,
+
+    /// The size of the cache of each tiled storage manager, in bytes. If
+    /// `None`, the cache is assumed to be large enough to avoid flushing
+    /// tiles before they are complete.
+    pub tile_cache_bytes: Option<u64>,
+}
+
+impl Default for WritePlan {
+    fn default() -> Self {
+        WritePlan {
+            n_rows: 10_000,
+            pattern: WritePattern::Cell,
+            chunk_rows: 1,
+            tile_cache_bytes: None,
+        }
+    }
+}
+
+impl WritePlan {
+    /// Get the number of rows written by each batch of writes.
+    fn batch_rows(&self) -> u64 {
+        match self.pattern {
+            WritePattern::Chunks => self.chunk_rows as u64,
+            _ => 1,
+        }
+    }
+}
+
+/// A storage manager to be modeled by [`simulate_layout`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum CandidateStorageManager {
+    /// `StandardStMan`, with buckets holding the given number of rows. If
+    /// `None`, casacore's default of 32 rows is assumed.
+    Standard {
+        /// The number of rows per bucket.
+        bucket_rows: Option<usize>,
+    },
+
+    /// `TiledColumnStMan`, with one hypercube per column. This requires
+    /// fixed-shape cells.
+    TiledColumn {
+        /// The tile shape, in C order: rows, then the axes of the cells.
+        tile_shape: Vec<usize>,
+    },
+
+    /// `TiledShapeStMan`, with one hypercube per column since every cell is
+    /// assumed to have the same shape.
+    TiledShape {
+        /// The tile shape, in C order: rows, then the axes of the cells.
+        tile_shape: Vec<usize>,
+    },
+}
+
+impl CandidateStorageManager {
+    /// Get the casacore type name of this storage manager.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            CandidateStorageManager::Standard { .. } => "StandardStMan",
+            CandidateStorageManager::TiledColumn { .. } => "TiledColumnStMan",
+            CandidateStorageManager::TiledShape { .. } => "TiledShapeStMan",
+        }
+    }
+}
+
+impl fmt::Display for CandidateStorageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CandidateStorageManager::Standard { bucket_rows: None } => {
+                write!(f, "{}", self.type_name())
+            }
+            CandidateStorageManager::Standard {
+                bucket_rows: Some(n),
+            } => write!(f, "{} ({} rows per bucket)", self.type_name(), n),
+            CandidateStorageManager::TiledColumn { tile_shape }
+            | CandidateStorageManager::TiledShape { tile_shape } => {
+                write!(f, "{} (tiles {:?})", self.type_name(), tile_shape)
+            }
+        }
+    }
+}
+
+/// The modeled cost of storing a schema with one candidate storage manager,
+/// as returned by [`simulate_layout`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LayoutEstimate {
+    /// The candidate storage manager.
+    pub storage_manager: CandidateStorageManager,
+
+    /// The number of bytes of cell data in the table.
+    pub data_bytes: u64,
+
+    /// The estimated number of bytes written to disk, including padding,
+    /// headers, and rewrites.
+    pub bytes_written: u64,
+
+    /// The estimated number of write operations.
+    pub write_ops: u64,
+
+    /// The estimated number of write operations that do not follow on from
+    /// the previous write to the same file.
+    pub seeks: u64,
+
+    /// The estimated number of files in the table directory.
+    pub n_files: usize,
+}
+
+impl LayoutEstimate {
+    /// Get the ratio of the bytes written to the bytes of cell data.
+    pub fn write_amplification(&self) -> f64 {
+        if self.data_bytes == 0 {
+            return 1.;
+        }
+
+        self.bytes_written as f64 / self.data_bytes as f64
+    }
+}
+
+impl fmt::Display for LayoutEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} bytes written ({:.2}x), {} writes, {} seeks, {} files",
+            self.storage_manager,
+            self.bytes_written,
+            self.write_amplification(),
+            self.write_ops,
+            self.seeks,
+            self.n_files
+        )
+    }
+}
+
+/// The accumulated cost of part of a layout.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct Cost {
+    bytes: u64,
+    write_ops: u64,
+    seeks: u64,
+    files: usize,
+}
+
+impl std::ops::AddAssign for Cost {
+    fn add_assign(&mut self, other: Cost) {
+        self.bytes += other.bytes;
+        self.write_ops += other.write_ops;
+        self.seeks += other.seeks;
+        self.files += other.files;
+    }
+}
+
+/// Estimate the cost of writing a table with the columns *columns* according
+/// to *plan*, for each of *candidates*.
+///
+/// Each candidate stores all of the columns that it can; see the
+/// [module-level documentation](self) for the model. The estimates are
+/// returned in the order of *candidates*.
+pub fn simulate_layout(
+    columns: &[LayoutColumn],
+    plan: &WritePlan,
+    candidates: &[CandidateStorageManager],
+) -> Result<Vec<LayoutEstimate>, LayoutError> {
+    if plan.pattern == WritePattern::Chunks && plan.chunk_rows == 0 {
+        return Err(LayoutError::InvalidPlan(
+            "chunked writes need at least one row per chunk".to_owned(),
+        ));
+    }
+
+    let data_bytes = plan.n_rows * columns.iter().map(|c| c.cell_bytes()).sum::<u64>();
+    let mut estimates = Vec::with_capacity(candidates.len());
+
+    for candidate in candidates {
+        let mut cost = Cost {
+            files: TABLE_FILES,
+            ..Cost::default()
+        };
+
+        match candidate {
+            CandidateStorageManager::Standard { bucket_rows } => {
+                let all: Vec<&LayoutColumn> = columns.iter().collect();
+                cost += standard_cost(&all, plan, bucket_rows.unwrap_or(DEFAULT_BUCKET_ROWS));
+            }
+
+            CandidateStorageManager::TiledColumn { tile_shape }
+            | CandidateStorageManager::TiledShape { tile_shape } => {
+                let (tiled, untiled): (Vec<&LayoutColumn>, Vec<&LayoutColumn>) =
+                    columns.iter().partition(|c| c.is_tileable());
+
+                cost += standard_cost(&untiled, plan, DEFAULT_BUCKET_ROWS);
+
+                for column in tiled {
+                    if !column.fixed_shape
+                        && matches!(candidate, CandidateStorageManager::TiledColumn { .. })
+                    {
+                        return Err(LayoutError::VariableShape(column.name.clone()));
+                    }
+
+                    cost += tiled_cost(column, tile_shape, plan)?;
+                }
+            }
+        }
+
+        estimates.push(LayoutEstimate {
+            storage_manager: candidate.clone(),
+            data_bytes,
+            bytes_written: cost.bytes,
+            write_ops: cost.write_ops,
+            seeks: cost.seeks,
+            n_files: cost.files,
+        });
+    }
+
+    Ok(estimates)
+}
+
+/// Model storing *columns* together in one `StandardStMan`.
+fn standard_cost(columns: &[&LayoutColumn], plan: &WritePlan, bucket_rows: usize) -> Cost {
+    if columns.is_empty() {
+        return Cost::default();
+    }
+
+    let bucket_rows = bucket_rows.max(1) as u64;
+    let row_bytes: u64 = columns
+        .iter()
+        .map(|c| {
+            if c.fixed_shape {
+                c.cell_bytes()
+            } else {
+                INDIRECT_OFFSET_SIZE
+            }
+        })
+        .sum();
+    let n_buckets = plan.n_rows.div_ceil(bucket_rows);
+
+    let mut cost = Cost {
+        bytes: n_buckets * bucket_rows * row_bytes,
+        write_ops: n_buckets,
+        seeks: 0,
+        files: 1,
+    };
+
+    let indirect: Vec<&&LayoutColumn> = columns.iter().filter(|c| !c.fixed_shape).collect();
+
+    if !indirect.is_empty() {
+        // Each array is appended with a header giving its dimensionality and
+        // shape.
+        let cell_bytes: u64 = indirect
+            .iter()
+            .map(|c| 4 * (c.cell_shape.len() as u64 + 1) + c.cell_bytes())
+            .sum();
+
+        cost += Cost {
+            bytes: plan.n_rows * cell_bytes,
+            write_ops: plan.n_rows * indirect.len() as u64,
+            seeks: 0,
+            files: 1,
+        };
+    }
+
+    cost
+}
+
+/// Model storing *column* as a hypercube with tiles of shape *tile_shape*.
+fn tiled_cost(
+    column: &LayoutColumn,
+    tile_shape: &[usize],
+    plan: &WritePlan,
+) -> Result<Cost, LayoutError> {
+    if tile_shape.len() != column.cell_shape.len() + 1 || tile_shape.contains(&0) {
+        return Err(LayoutError::InvalidTileShape {
+            column: column.name.clone(),
+            tile_shape: tile_shape.to_owned(),
+            cell_shape: column.cell_shape.clone(),
+        });
+    }
+
+    let tile_rows = tile_shape[0] as u64;
+    let tiles_per_slab: u64 = column
+        .cell_shape
+        .iter()
+        .zip(&tile_shape[1..])
+        .map(|(n, t)| n.div_ceil(*t) as u64)
+        .product();
+    let n_slabs = plan.n_rows.div_ceil(tile_rows);
+    let tile_bytes = tile_shape.iter().product::<usize>() as u64 * column.element_bytes();
+
+    let cached = plan
+        .tile_cache_bytes
+        .is_none_or(|c| c >= tiles_per_slab * tile_bytes);
+
+    // If the cache cannot hold a whole slab of tiles, each batch of rows
+    // that touches a tile flushes it. Each slab's tiles are contiguous, so
+    // every batch after the first that revisits a slab costs one seek.
+    let (slab_writes, seeks) = if cached {
+        (n_slabs, 0)
+    } else {
+        let batch = plan.batch_rows();
+        let mut writes = 0;
+        let mut seeks = 0;
+
+        for slab in 0..n_slabs {
+            let start = slab * tile_rows;
+            let end = (start + tile_rows).min(plan.n_rows);
+            let n_batches = (end - 1) / batch - start / batch + 1;
+            writes += n_batches;
+            seeks += n_batches - 1;
+        }
+
+        (writes, seeks)
+    };
+
+    Ok(Cost {
+        bytes: slab_writes * tiles_per_slab * tile_bytes,
+        write_ops: slab_writes * tiles_per_slab,
+        seeks,
+        // The data manager's header file and the hypercube's file.
+        files: 2,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns() -> Vec<LayoutColumn> {
+        vec![
+            LayoutColumn::scalar("TIME", GlueDataType::TpDouble),
+            LayoutColumn::fixed("DATA", GlueDataType::TpComplex, &[4, 2]),
+        ]
+    }
+
+    fn plan(pattern: WritePattern, chunk_rows: usize, cache: Option<u64>) -> WritePlan {
+        WritePlan {
+            n_rows: 100,
+            pattern,
+            chunk_rows,
+            tile_cache_bytes: cache,
+        }
+    }
+
+    #[test]
+    fn standard() {
+        let est = simulate_layout(
+            &columns(),
+            &plan(WritePattern::Cell, 1, None),
+            &[CandidateStorageManager::Standard {
+                bucket_rows: Some(10),
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(est[0].data_bytes, 7200);
+        assert_eq!(est[0].bytes_written, 7200);
+        assert_eq!(est[0].write_ops, 10);
+        assert_eq!(est[0].seeks, 0);
+        assert_eq!(est[0].n_files, 4);
+        assert_eq!(est[0].write_amplification(), 1.);
+
+        let mut cols = columns();
+        cols.push(LayoutColumn::variable(
+            "FLAG",
+            GlueDataType::TpBool,
+            &[4, 2],
+        ));
+        let est = simulate_layout(
+            &cols,
+            &plan(WritePattern::Cell, 1, None),
+            &[CandidateStorageManager::Standard {
+                bucket_rows: Some(10),
+            }],
+        )
+        .unwrap();
+
+        // Offsets in the buckets, plus a 12-byte header and 8 bytes of data
+        // per cell in the indirect file.
+        assert_eq!(est[0].bytes_written, 7200 + 800 + 2000);
+        assert_eq!(est[0].write_ops, 110);
+        assert_eq!(est[0].n_files, 5);
+    }
+
+    #[test]
+    fn tiled() {
+        let candidates = [CandidateStorageManager::TiledColumn {
+            tile_shape: vec![8, 4, 2],
+        }];
+
+        // TIME takes 4 buckets of 32 rows; DATA takes 13 tiles of 512 bytes.
+        let est =
+            simulate_layout(&columns(), &plan(WritePattern::Cell, 1, None), &candidates).unwrap();
+        assert_eq!(est[0].bytes_written, 1024 + 6656);
+        assert_eq!(est[0].write_ops, 4 + 13);
+        assert_eq!(est[0].seeks, 0);
+        assert_eq!(est[0].n_files, 6);
+
+        // Without a cache, every row rewrites its tile.
+        let est = simulate_layout(
+            &columns(),
+            &plan(WritePattern::Cell, 1, Some(0)),
+            &candidates,
+        )
+        .unwrap();
+        assert_eq!(est[0].bytes_written, 1024 + 100 * 512);
+        assert_eq!(est[0].write_ops, 4 + 100);
+        assert_eq!(est[0].seeks, 12 * 7 + 3);
+
+        // Chunks aligned with the tiles avoid the rewrites.
+        let est = simulate_layout(
+            &columns(),
+            &plan(WritePattern::Chunks, 8, Some(0)),
+            &candidates,
+        )
+        .unwrap();
+        assert_eq!(est[0].write_ops, 4 + 13);
+        assert_eq!(est[0].seeks, 0);
+    }
+
+    #[test]
+    fn errors() {
+        let bad_shape = simulate_layout(
+            &columns(),
+            &WritePlan::default(),
+            &[CandidateStorageManager::TiledShape {
+                tile_shape: vec![8, 4],
+            }],
+        );
+        assert!(matches!(
+            bad_shape,
+            Err(LayoutError::InvalidTileShape { .. })
+        ));
+
+        let variable = simulate_layout(
+            &[LayoutColumn::variable("DATA", GlueDataType::TpFloat, &[4])],
+            &WritePlan::default(),
+            &[CandidateStorageManager::TiledColumn {
+                tile_shape: vec![8, 4],
+            }],
+        );
+        assert_eq!(variable, Err(LayoutError::VariableShape("DATA".to_owned())));
+
+        let bad_plan = simulate_layout(
+            &columns(),
+            &plan(WritePattern::Chunks, 0, None),
+            &[CandidateStorageManager::Standard { bucket_rows: None }],
+        );
+        assert!(matches!(bad_plan, Err(LayoutError::InvalidPlan(_))));
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Progress reporting and cancellation for long-running column operations.
+//!
+//! Rewriting a visibility column of a large Measurement Set can take many
+//! minutes. The `_with_progress` variants of the bulk and streaming column
+//! methods accept a [`Progress`], which bundles an optional [`ProgressSink`]
+//! that is told how many rows have been processed after each chunk and an
+//! optional [`CancellationToken`] that is checked before each chunk.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::TableError;
+
+/// Something that is told about the progress of a long-running operation.
+///
+/// This is implemented for closures taking the same arguments as
+/// [`ProgressSink::progress`].
+pub trait ProgressSink {
+    /// Report that *rows_done* rows have been processed, out of
+    /// *rows_total* if the total is known in advance.
+    fn progress(&self, rows_done: u64, rows_total: Option<u64>);
+}
+
+impl<F: Fn(u64, Option<u64>)> ProgressSink for F {
+    fn progress(&self, rows_done: u64, rows_total: Option<u64>) {
+        self(rows_done, rows_total)
+    }
+}
+
+/// A flag used to ask a long-running operation to stop.
+///
+/// Clones of a token share the same flag, so that a token can be handed to
+/// an operation running on one thread and cancelled from another.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a new token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the operations using this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether [`Self::cancel`] has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Progress reporting and cancellation for one operation.
+///
+/// The default reports nothing and cannot be cancelled.
+///
+/// A cancelled operation returns [`TableError::Cancelled`] between two
+/// chunks, so that every chunk is either processed completely or not at
+/// all. After a cancelled write, the first `rows_done` rows have their new
+/// values, the rows after them keep their old ones, and any rows that were
+/// added to the table to hold the new data remain. The table itself stays
+/// open and usable.
+#[derive(Clone, Copy, Default)]
+pub struct Progress<'a> {
+    sink: Option<&'a dyn ProgressSink>,
+    cancel: Option<&'a CancellationToken>,
+}
+
+impl<'a> Progress<'a> {
+    /// Create a value that reports nothing and cannot be cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report progress to *sink*.
+    pub fn sink(mut self, sink: &'a dyn ProgressSink) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Stop the operation when *token* is cancelled.
+    pub fn cancellation(mut self, token: &'a CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Fail with [`TableError::Cancelled`] if cancellation has been
+    /// requested.
+    pub(crate) fn check(&self, rows_done: u64) -> Result<(), TableError> {
+        match self.cancel {
+            Some(t) if t.is_cancelled() => Err(TableError::Cancelled { rows_done }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Pass on a progress report to the sink, if there is one.
+    pub(crate) fn report(&self, rows_done: u64, rows_total: Option<u64>) {
+        if let Some(sink) = self.sink {
+            sink.progress(rows_done, rows_total);
+        }
+    }
+}
+
+impl std::fmt::Debug for Progress<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Progress")
+            .field("sink", &self.sink.is_some())
+            .field("cancel", &self.cancel)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, Table, TableCreateMode, TableDesc, TableDescCreateMode};
+    use ndarray::Array1;
+    use rubbl_core::chunked::{ArrayChunks, ArrayCollector};
+    use std::cell::RefCell;
+    use tempfile::tempdir;
+
+    #[test]
+    fn progress_and_cancellation() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "X", None, false, false)
+            .unwrap();
+        let mut t = Table::new(tmp_dir.path().join("t"), desc, 10, TableCreateMode::New).unwrap();
+
+        for row in 0..10 {
+            t.put_cell("X", row, &(row as i32)).unwrap();
+        }
+
+        let reports = RefCell::new(Vec::new());
+        let sink = |done: u64, total: Option<u64>| reports.borrow_mut().push((done, total));
+        let mut all = ArrayCollector::<i32>::new(&[]);
+        t.read_column_chunks_with_progress("X", 4, &mut all, &Progress::new().sink(&sink))
+            .unwrap();
+        assert_eq!(
+            reports.into_inner(),
+            vec![(4, Some(10)), (8, Some(10)), (10, Some(10))]
+        );
+
+        // Cancelling from the sink stops the operation before the next chunk.
+        let token = CancellationToken::new();
+        let cancel_after_first = |_: u64, _: Option<u64>| token.cancel();
+        let progress = Progress::new()
+            .sink(&cancel_after_first)
+            .cancellation(&token);
+        let values = Array1::from_elem(10, -1i32);
+        let mut source = ArrayChunks::new(values.view());
+
+        match t.write_column_chunks_with_progress("X", 0, 3, &mut source, &progress) {
+            Err(TableError::Cancelled { rows_done }) => assert_eq!(rows_done, 3),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        let x = t.get_col_as_vec::<i32>("X").unwrap();
+        assert_eq!(x, vec![-1, -1, -1, 3, 4, 5, 6, 7, 8, 9]);
+
+        // An already cancelled token stops the operation before it starts.
+        let mut none = ArrayCollector::<i32>::new(&[]);
+        assert!(matches!(
+            t.read_column_chunks_with_progress(
+                "X",
+                4,
+                &mut none,
+                &Progress::new().cancellation(&token)
+            ),
+            Err(TableError::Cancelled { rows_done: 0 })
+        ));
+    }
+}
@@ -0,0 +1,510 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Approximate quantiles of a column’s values.
+//!
+//! Choosing flagging thresholds or scaling QA plots calls for robust
+//! statistics such as medians and percentiles, but computing them exactly
+//! needs all of a column’s values in memory at once. [`Table::column_quantiles`]
+//! instead streams the column in chunks through a [`QuantileSketch`], a
+//! merging t-digest, which summarizes any number of values in a bounded
+//! amount of memory. Quantiles near the tails are estimated especially
+//! accurately; the error is largest around the median, where it is
+//! typically a small fraction of a percent in rank.
+
+use ndarray::ArrayViewD;
+use rubbl_core::chunked::ArrayChunkSink;
+use std::{borrow::Cow, convert::Infallible, f64::consts::PI};
+
+use crate::{CasaScalarData, Complex, GlueDataType, Table, TableError};
+
+/// The default compression of a [`QuantileSketch`].
+pub const DEFAULT_SKETCH_COMPRESSION: f64 = 200.;
+
+/// One cluster of values in a sketch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// A summary of a stream of numbers from which approximate quantiles can be
+/// computed.
+///
+/// This is a merging t-digest: values are gathered into a sorted set of
+/// clusters, which are kept small near the extremes of the distribution and
+/// allowed to grow towards the middle. The number of clusters is bounded by
+/// about the *compression* parameter, regardless of how many values have
+/// been added. Larger compressions give more accurate quantiles at the cost
+/// of memory and speed.
+///
+/// ```
+/// use rubbl_casatables::QuantileSketch;
+///
+/// let mut sketch = QuantileSketch::new();
+///
+/// for i in 0..10_000 {
+///     sketch.add(i as f64);
+/// }
+///
+/// let median = sketch.quantile(0.5).unwrap();
+/// assert!((median - 5000.).abs() < 50.);
+/// ```
+#[derive(Clone, Debug)]
+pub struct QuantileSketch {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        Self::with_compression(DEFAULT_SKETCH_COMPRESSION)
+    }
+}
+
+impl QuantileSketch {
+    /// Create an empty sketch with the default compression,
+    /// [`DEFAULT_SKETCH_COMPRESSION`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty sketch with the given compression.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *compression* is not a finite number of at least 10.
+    pub fn with_compression(compression: f64) -> Self {
+        assert!(
+            compression.is_finite() && compression >= 10.,
+            "sketch compression must be at least 10"
+        );
+
+        QuantileSketch {
+            compression,
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Add a value to the sketch.
+    ///
+    /// NaNs are ignored.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+
+        self.buffer.push(value);
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+
+        if self.buffer.len() >= self.buffer_capacity() {
+            self.compress();
+        }
+    }
+
+    /// Add all of the values in another sketch to this one.
+    pub fn merge(&mut self, other: &QuantileSketch) {
+        self.centroids.extend_from_slice(&other.centroids);
+        self.buffer.extend_from_slice(&other.buffer);
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.compress();
+    }
+
+    /// Get the number of values that have been added to the sketch.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Get the smallest value added to the sketch, if there have been any.
+    pub fn min(&self) -> Option<f64> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.min)
+        }
+    }
+
+    /// Get the largest value added to the sketch, if there have been any.
+    pub fn max(&self) -> Option<f64> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.max)
+        }
+    }
+
+    /// Estimate the *q*’th quantile of the values added to the sketch, so
+    /// that 0.5 gives the median.
+    ///
+    /// Returns `None` if the sketch is empty. The quantiles 0 and 1 are
+    /// exactly the smallest and largest values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *q* is not between 0 and 1.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        assert!(
+            (0. ..=1.).contains(&q),
+            "quantile {} is not between 0 and 1",
+            q
+        );
+
+        if self.count == 0 {
+            return None;
+        }
+
+        if q == 0. {
+            return Some(self.min);
+        }
+
+        if q == 1. {
+            return Some(self.max);
+        }
+
+        let centroids = self.compressed_centroids();
+        let total = self.count as f64;
+        let target = q * total;
+
+        // Each centroid is taken to stand for values spread evenly over its
+        // share of the ranks, centred on its mean. Between the outermost
+        // centroids and the extremes, interpolate towards the known minimum
+        // and maximum.
+        let first = centroids[0];
+        let last = centroids[centroids.len() - 1];
+
+        if target < first.weight / 2. {
+            return Some(lerp(self.min, first.mean, target / (first.weight / 2.)));
+        }
+
+        if target > total - last.weight / 2. {
+            let t = (target - (total - last.weight / 2.)) / (last.weight / 2.);
+            return Some(lerp(last.mean, self.max, t));
+        }
+
+        let mut cum = 0.;
+
+        for pair in centroids.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let a_centre = cum + a.weight / 2.;
+            let b_centre = cum + a.weight + b.weight / 2.;
+
+            if target <= b_centre {
+                let t = (target - a_centre) / (b_centre - a_centre);
+                return Some(lerp(a.mean, b.mean, t).clamp(self.min, self.max));
+            }
+
+            cum += a.weight;
+        }
+
+        Some(last.mean)
+    }
+
+    fn buffer_capacity(&self) -> usize {
+        (self.compression * 5.) as usize
+    }
+
+    /// Get the centroids with any buffered values merged in.
+    fn compressed_centroids(&self) -> Cow<'_, [Centroid]> {
+        if self.buffer.is_empty() {
+            Cow::Borrowed(&self.centroids)
+        } else {
+            let mut copy = self.clone();
+            copy.compress();
+            Cow::Owned(copy.centroids)
+        }
+    }
+
+    /// Merge the buffered values and existing centroids into a new set of
+    /// centroids, respecting the size limit implied by the compression.
+    fn compress(&mut self) {
+        if self.buffer.is_empty() && self.centroids.len() <= 1 {
+            return;
+        }
+
+        let mut all = std::mem::take(&mut self.centroids);
+        all.extend(
+            self.buffer
+                .drain(..)
+                .map(|mean| Centroid { mean, weight: 1. }),
+        );
+        all.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap());
+
+        let total: f64 = all.iter().map(|c| c.weight).sum();
+        let mut merged = Vec::with_capacity(self.compression as usize);
+        let mut iter = all.into_iter();
+        let mut current = iter.next().unwrap();
+        let mut done = 0.;
+        let mut limit = total * self.k_inverse(self.k(0.) + 1.);
+
+        for next in iter {
+            if done + current.weight + next.weight <= limit {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                done += current.weight;
+                merged.push(current);
+                limit = total * self.k_inverse(self.k(done / total) + 1.);
+                current = next;
+            }
+        }
+
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    /// The t-digest scale function, which maps a quantile to the index of
+    /// the centroid that should contain it.
+    fn k(&self, q: f64) -> f64 {
+        self.compression / (2. * PI) * (2. * q - 1.).clamp(-1., 1.).asin()
+    }
+
+    fn k_inverse(&self, k: f64) -> f64 {
+        let angle = (2. * PI * k / self.compression).clamp(-PI / 2., PI / 2.);
+        (angle.sin() + 1.) / 2.
+    }
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Feeds the values of a column into a sketch.
+struct SketchSink<'a, F> {
+    sketch: &'a mut QuantileSketch,
+    value: F,
+}
+
+impl<'a, T: Copy, F: Fn(T) -> f64> ArrayChunkSink<T> for SketchSink<'a, F> {
+    type Error = Infallible;
+
+    fn write_chunk(&mut self, chunk: ArrayViewD<T>) -> Result<(), Infallible> {
+        for x in chunk.iter() {
+            self.sketch.add((self.value)(*x));
+        }
+
+        Ok(())
+    }
+}
+
+impl Table {
+    fn sketch_typed<T, F>(
+        &mut self,
+        col_name: &str,
+        rows_per_chunk: usize,
+        sketch: &mut QuantileSketch,
+        value: F,
+    ) -> Result<(), TableError>
+    where
+        T: CasaScalarData + Copy + Default,
+        F: Fn(T) -> f64,
+    {
+        let mut sink = SketchSink { sketch, value };
+        self.read_column_chunks(col_name, rows_per_chunk, &mut sink)
+    }
+
+    /// Summarize all of the values in a column with a [`QuantileSketch`].
+    ///
+    /// The column is read in one pass, in chunks sized as suggested by
+    /// [`Self::suggest_chunk_rows`], so the memory used does not depend on
+    /// the size of the column. The column must be scalar or have a fixed
+    /// shape, and have a numeric data type. Every element of every cell is
+    /// included. For complex columns, the amplitudes of the values are
+    /// used. NaNs are skipped.
+    pub fn column_quantile_sketch(
+        &mut self,
+        col_name: &str,
+        compression: f64,
+    ) -> Result<QuantileSketch, TableError> {
+        let data_type = self.get_col_desc(col_name)?.data_type().element_type();
+        let rows_per_chunk = self.suggest_chunk_rows(&[col_name], None)?;
+        let mut sketch = QuantileSketch::with_compression(compression);
+        let s = &mut sketch;
+
+        match data_type {
+            GlueDataType::TpChar => {
+                self.sketch_typed(col_name, rows_per_chunk, s, |x: i8| x.into())
+            }
+            GlueDataType::TpUChar => {
+                self.sketch_typed(col_name, rows_per_chunk, s, |x: u8| x.into())
+            }
+            GlueDataType::TpShort => {
+                self.sketch_typed(col_name, rows_per_chunk, s, |x: i16| x.into())
+            }
+            GlueDataType::TpUShort => {
+                self.sketch_typed(col_name, rows_per_chunk, s, |x: u16| x.into())
+            }
+            GlueDataType::TpInt => {
+                self.sketch_typed(col_name, rows_per_chunk, s, |x: i32| x.into())
+            }
+            GlueDataType::TpUInt => {
+                self.sketch_typed(col_name, rows_per_chunk, s, |x: u32| x.into())
+            }
+            GlueDataType::TpInt64 => {
+                self.sketch_typed(col_name, rows_per_chunk, s, |x: i64| x as f64)
+            }
+            GlueDataType::TpFloat => {
+                self.sketch_typed(col_name, rows_per_chunk, s, |x: f32| x.into())
+            }
+            GlueDataType::TpDouble => self.sketch_typed(col_name, rows_per_chunk, s, |x: f64| x),
+            GlueDataType::TpComplex => {
+                self.sketch_typed(col_name, rows_per_chunk, s, |x: Complex<f32>| {
+                    x.norm().into()
+                })
+            }
+            GlueDataType::TpDComplex => {
+                self.sketch_typed(col_name, rows_per_chunk, s, |x: Complex<f64>| x.norm())
+            }
+            other => Err(TableError::UnsupportedDataType(col_name.to_owned(), other)),
+        }?;
+
+        Ok(sketch)
+    }
+
+    /// Estimate quantiles of the values in a column.
+    ///
+    /// Each of *qs* must be between 0 and 1; 0.5 gives the median. The
+    /// estimates are returned in the same order. If the column has no
+    /// values other than NaNs, the estimates are all NaN. See
+    /// [`Self::column_quantile_sketch`] for the columns that are supported
+    /// and how their values are read; use that method directly to control
+    /// the accuracy of the estimates or to combine several columns or
+    /// tables.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{Table, TableOpenMode};
+    ///
+    /// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+    /// let q = t.column_quantiles("DATA", &[0.5, 0.99]).unwrap();
+    /// println!("median amplitude {}, 99th percentile {}", q[0], q[1]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of *qs* is not between 0 and 1.
+    pub fn column_quantiles(&mut self, col_name: &str, qs: &[f64]) -> Result<Vec<f64>, TableError> {
+        let sketch = self.column_quantile_sketch(col_name, DEFAULT_SKETCH_COMPRESSION)?;
+        Ok(qs
+            .iter()
+            .map(|q| sketch.quantile(*q).unwrap_or(f64::NAN))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    /// A deterministic shuffle of 0..n, so that values do not arrive in
+    /// order.
+    fn shuffled(n: u64) -> impl Iterator<Item = f64> {
+        // 7919 is prime, and so coprime with n unless n is a multiple of it.
+        (0..n).map(move |i| ((i * 7919) % n) as f64)
+    }
+
+    #[test]
+    fn sketch_accuracy() {
+        let n = 1_000_000;
+        let mut sketch = QuantileSketch::new();
+
+        for x in shuffled(n) {
+            sketch.add(x);
+        }
+
+        sketch.add(f64::NAN);
+        assert_eq!(sketch.count(), n);
+        assert!(sketch.centroids.len() < 2 * DEFAULT_SKETCH_COMPRESSION as usize);
+        assert_eq!(sketch.quantile(0.), Some(0.));
+        assert_eq!(sketch.quantile(1.), Some((n - 1) as f64));
+
+        for &(q, tolerance) in &[(0.001, 0.0005), (0.01, 0.001), (0.5, 0.005), (0.99, 0.001)] {
+            let estimate = sketch.quantile(q).unwrap() / n as f64;
+            assert!(
+                (estimate - q).abs() < tolerance,
+                "quantile {} estimated as {}",
+                q,
+                estimate
+            );
+        }
+    }
+
+    #[test]
+    fn sketch_merge() {
+        let mut a = QuantileSketch::new();
+        let mut b = QuantileSketch::new();
+
+        for x in shuffled(20_000) {
+            if x < 5000. {
+                a.add(x);
+            } else {
+                b.add(x);
+            }
+        }
+
+        a.merge(&b);
+        assert_eq!(a.count(), 20_000);
+        assert_eq!(a.min(), Some(0.));
+        assert_eq!(a.max(), Some(19_999.));
+        assert!((a.quantile(0.5).unwrap() - 10_000.).abs() < 50.);
+
+        let empty = QuantileSketch::new();
+        assert_eq!(empty.quantile(0.5), None);
+        assert_eq!(empty.min(), None);
+    }
+
+    #[test]
+    fn column_quantiles() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "X", None, false, false)
+            .unwrap();
+        desc.add_array_column(
+            GlueDataType::TpComplex,
+            "VIS",
+            None,
+            Some(&[2]),
+            true,
+            false,
+        )
+        .unwrap();
+        desc.add_scalar_column(GlueDataType::TpString, "NAME", None, false, false)
+            .unwrap();
+        let n_rows = 1001;
+        let mut t =
+            Table::new(tmp_dir.path().join("t"), desc, n_rows, TableCreateMode::New).unwrap();
+
+        for row in 0..n_rows as u64 {
+            t.put_cell("X", row, &(row as i32 - 500)).unwrap();
+            let vis = vec![Complex::new(0., row as f32), Complex::new(row as f32, 0.)];
+            t.put_cell("VIS", row, &vis).unwrap();
+        }
+
+        let q = t.column_quantiles("X", &[0., 0.5, 1.]).unwrap();
+        assert_eq!(q[0], -500.);
+        assert!(q[1].abs() < 1.);
+        assert_eq!(q[2], 500.);
+
+        let sketch = t.column_quantile_sketch("VIS", 100.).unwrap();
+        assert_eq!(sketch.count(), 2 * n_rows as u64);
+        assert!((sketch.quantile(0.25).unwrap() - 250.).abs() < 5.);
+
+        assert!(matches!(
+            t.column_quantiles("NAME", &[0.5]),
+            Err(TableError::UnsupportedDataType(..))
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Columns that hold physical quantities.
+//!
+//! casacore records the units of a column in its `QuantumUnits` keyword, and
+//! the kind and reference frame of a measure, such as an epoch or a
+//! direction, in its `MEASINFO` keyword. Tables that lack them can still be
+//! read, but CASA and other tools then treat the values as bare numbers, or
+//! guess at their units — and leaving the keywords out is easy when a column
+//! is added by hand. A [`QuantityColumn`] keeps a column’s name and units
+//! together, so that creating the column and labeling it happen in one
+//! step.
+
+use crate::{CasaScalarData, GlueDataType, Table, TableError, TableRecord};
+
+/// A column of a physical quantity, along with its units.
+///
+/// ```rust
+/// use rubbl_casatables::{GlueDataType, QuantityColumn, Table, TableCreateMode, TableDesc, TableDescCreateMode};
+/// use tempfile::tempdir;
+///
+/// let tmp_dir = tempdir().unwrap();
+/// let desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+/// let mut t = Table::new(tmp_dir.path().join("q.tab"), desc, 0, TableCreateMode::New).unwrap();
+///
+/// QuantityColumn::new("PRESSURE", "hPa")
+///     .put_values(&mut t, &[1013.2f32, 1012.8])
+///     .unwrap();
+/// assert_eq!(t.n_rows(), 2);
+/// assert_eq!(t.column_units("PRESSURE").unwrap(), vec!["hPa"]);
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct QuantityColumn {
+    /// The name of the column.
+    pub name: String,
+
+    /// The units of the column values, as recorded in `QuantumUnits`. Array
+    /// columns whose axes have different units, such as a direction in
+    /// radians alongside a distance in meters, give one unit per element of
+    /// the first axis.
+    pub units: Vec<String>,
+
+    /// The measure kind and reference frame, such as `("epoch", "UTC")`, to
+    /// record in `MEASINFO`, if the values are a measure.
+    pub measure: Option<(String, String)>,
+}
+
+impl QuantityColumn {
+    /// Describe a column whose values are all in *unit*.
+    pub fn new<S: Into<String>>(name: S, unit: &str) -> Self {
+        Self::with_units(name, &[unit])
+    }
+
+    /// Describe a column with a unit for each element of its first axis.
+    pub fn with_units<S: Into<String>>(name: S, units: &[&str]) -> Self {
+        QuantityColumn {
+            name: name.into(),
+            units: units.iter().map(|u| (*u).to_owned()).collect(),
+            measure: None,
+        }
+    }
+
+    /// Record that the column values are measures of kind *kind*, such as
+    /// `"epoch"` or `"direction"`, in reference frame *reference*.
+    pub fn measure(mut self, kind: &str, reference: &str) -> Self {
+        self.measure = Some((kind.to_owned(), reference.to_owned()));
+        self
+    }
+
+    /// Add this column to *table* as a scalar column of type *data_type*,
+    /// along with its keywords.
+    pub fn add_scalar(&self, table: &mut Table, data_type: GlueDataType) -> Result<(), TableError> {
+        table.add_scalar_column(data_type, &self.name, None, true, false)?;
+        self.put_keywords(table)
+    }
+
+    /// Add this column to *table* as an array column of type *data_type*,
+    /// along with its keywords.
+    ///
+    /// If *dims* is given, the cells have that fixed shape; otherwise their
+    /// shapes may vary.
+    pub fn add_array(
+        &self,
+        table: &mut Table,
+        data_type: GlueDataType,
+        dims: Option<&[u64]>,
+    ) -> Result<(), TableError> {
+        table.add_array_column(data_type, &self.name, None, dims, dims.is_some(), false)?;
+        self.put_keywords(table)
+    }
+
+    /// Write the `QuantumUnits` and `MEASINFO` keywords of this column,
+    /// which must already exist in *table*.
+    pub fn put_keywords(&self, table: &mut Table) -> Result<(), TableError> {
+        if !self.units.is_empty() {
+            table.put_column_keyword(&self.name, "QuantumUnits", &self.units)?;
+        }
+
+        if let Some((kind, reference)) = self.measure.as_ref() {
+            let mut meas_info = TableRecord::new()?;
+            meas_info.put_field("type", kind)?;
+            meas_info.put_field("Ref", reference)?;
+            table.put_column_keyword(&self.name, "MEASINFO", &meas_info)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a value to each row of this column in *table*.
+    ///
+    /// The column is created as a scalar column of the type of *values*,
+    /// with its keywords, if the table does not have it yet. Rows are added
+    /// to the table if it has fewer than there are values.
+    pub fn put_values<T: CasaScalarData>(
+        &self,
+        table: &mut Table,
+        values: &[T],
+    ) -> Result<(), TableError> {
+        if !table.column_names()?.contains(&self.name) {
+            self.add_scalar(table, T::DATA_TYPE)?;
+        }
+
+        let n_rows = table.n_rows() as usize;
+
+        if values.len() > n_rows {
+            table.add_rows(values.len() - n_rows)?;
+        }
+
+        for (row, value) in values.iter().enumerate() {
+            table.put_cell(&self.name, row as u64, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Table {
+    /// Get the units of a column, as recorded in its `QuantumUnits` keyword.
+    ///
+    /// The result is empty if the column has no units.
+    pub fn column_units(&mut self, col_name: &str) -> Result<Vec<String>, TableError> {
+        let mut keywords = self.get_column_keyword_record(col_name)?;
+
+        if keywords
+            .keyword_names()?
+            .iter()
+            .any(|k| k == "QuantumUnits")
+        {
+            keywords.get_field("QuantumUnits")
+        } else {
+            Ok(Vec::new())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn add_and_read_back() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "ID", None, true, false)
+            .unwrap();
+        let mut t =
+            Table::new(tmp_dir.path().join("q.tab"), desc, 3, TableCreateMode::New).unwrap();
+
+        assert!(t.column_units("ID").unwrap().is_empty());
+
+        QuantityColumn::new("TIME", "s")
+            .measure("epoch", "UTC")
+            .add_scalar(&mut t, GlueDataType::TpDouble)
+            .unwrap();
+        QuantityColumn::with_units("DIR", &["rad", "rad"])
+            .measure("direction", "J2000")
+            .add_array(&mut t, GlueDataType::TpDouble, Some(&[2]))
+            .unwrap();
+
+        assert_eq!(t.column_units("TIME").unwrap(), vec!["s"]);
+        assert_eq!(t.column_units("DIR").unwrap(), vec!["rad", "rad"]);
+
+        let mut meas_info: TableRecord = t
+            .get_column_keyword_record("DIR")
+            .unwrap()
+            .get_field("MEASINFO")
+            .unwrap();
+        let kind: String = meas_info.get_field("type").unwrap();
+        let reference: String = meas_info.get_field("Ref").unwrap();
+        assert_eq!(kind, "direction");
+        assert_eq!(reference, "J2000");
+
+        // Writing more values than rows extends the table.
+        QuantityColumn::new("TEMP", "K")
+            .put_values(&mut t, &[270f32, 271., 272., 273.])
+            .unwrap();
+        assert_eq!(t.n_rows(), 4);
+        assert_eq!(t.get_cell::<f32>("TEMP", 3).unwrap(), 273.);
+        assert_eq!(t.column_units("TEMP").unwrap(), vec!["K"]);
+    }
+}
@@ -0,0 +1,437 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Whole-column I/O for columns whose cells vary in shape.
+//!
+//! The bulk column methods of [`Table`] need every cell to have the same
+//! shape, so that a chunk of rows maps onto one array. The main table of a
+//! Measurement Set with several spectral windows breaks this assumption:
+//! the shape of a `DATA` cell depends on the row’s spectral window, and
+//! reading such a column cell by cell is slow. A [`RaggedArray`] instead
+//! holds the cells one after another in a single buffer, along with each
+//! cell’s shape, and [`Table::get_column`] and [`Table::put_column`] move
+//! whole runs of equally shaped rows at once.
+
+use ndarray::{ArrayViewD, ArrayViewMutD};
+use std::os::raw::c_ulong;
+
+use crate::{
+    glue, io_stats::IoDirection, CasaScalarData, Progress, Table, TableError,
+    UnexpectedDataTypeError,
+};
+
+/// How many cell shapes to look up between checks for cancellation.
+const SHAPE_CHECK_INTERVAL: u64 = 4096;
+
+/// A sequence of arrays of possibly different shapes, stored contiguously.
+///
+/// Cell *i* holds the elements `values()[offsets()[i]..offsets()[i + 1]]` in
+/// C order, with the shape `shape(i)`. A cell with an empty shape stands for
+/// an undefined cell: it has no elements, and [`Table::put_column`] leaves
+/// the corresponding row untouched.
+///
+/// ```rust
+/// use ndarray::array;
+/// use rubbl_casatables::RaggedArray;
+///
+/// let mut r = RaggedArray::new();
+/// r.push(array![[1, 2], [3, 4]].view().into_dyn());
+/// r.push(array![5, 6, 7].view().into_dyn());
+/// assert_eq!(r.len(), 2);
+/// assert_eq!(r.shape(1), &[3]);
+/// assert_eq!(r.cell(0)[[1, 0]], 3);
+/// assert_eq!(r.values(), &[1, 2, 3, 4, 5, 6, 7]);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RaggedArray<T> {
+    values: Vec<T>,
+    shapes: Vec<Vec<usize>>,
+    offsets: Vec<usize>,
+}
+
+impl<T> Default for RaggedArray<T> {
+    fn default() -> Self {
+        RaggedArray {
+            values: Vec::new(),
+            shapes: Vec::new(),
+            offsets: vec![0],
+        }
+    }
+}
+
+/// Get the number of elements in a cell of the given shape.
+fn cell_len(shape: &[usize]) -> usize {
+    if shape.is_empty() {
+        0
+    } else {
+        shape.iter().product()
+    }
+}
+
+impl<T> RaggedArray<T> {
+    /// Create an empty container.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a container from the flattened cell data and the cells’
+    /// shapes.
+    ///
+    /// Returns `None` if the number of values is not the total number of
+    /// elements of the cells.
+    pub fn from_parts(values: Vec<T>, shapes: Vec<Vec<usize>>) -> Option<Self> {
+        let mut offsets = Vec::with_capacity(shapes.len() + 1);
+        offsets.push(0);
+
+        for shape in &shapes {
+            offsets.push(offsets[offsets.len() - 1] + cell_len(shape));
+        }
+
+        if offsets[shapes.len()] != values.len() {
+            return None;
+        }
+
+        Some(RaggedArray {
+            values,
+            shapes,
+            offsets,
+        })
+    }
+
+    /// Get the number of cells.
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    /// Check whether there are no cells.
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// Get the elements of all of the cells, one cell after another.
+    pub fn values(&self) -> &[T] {
+        &self.values[..]
+    }
+
+    /// Get the elements of all of the cells mutably.
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values[..]
+    }
+
+    /// Get the shapes of all of the cells.
+    pub fn shapes(&self) -> &[Vec<usize>] {
+        &self.shapes[..]
+    }
+
+    /// Get the offset of each cell’s first element in [`Self::values`],
+    /// followed by the total number of elements.
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets[..]
+    }
+
+    /// Get the shape of cell *index*.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *index* is out of bounds.
+    pub fn shape(&self, index: usize) -> &[usize] {
+        &self.shapes[index][..]
+    }
+
+    /// Get a view of cell *index*.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *index* is out of bounds.
+    pub fn cell(&self, index: usize) -> ArrayViewD<'_, T> {
+        let data = &self.values[self.offsets[index]..self.offsets[index + 1]];
+        ArrayViewD::from_shape(&self.shapes[index][..], data).unwrap()
+    }
+
+    /// Get a mutable view of cell *index*.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *index* is out of bounds.
+    pub fn cell_mut(&mut self, index: usize) -> ArrayViewMutD<'_, T> {
+        let data = &mut self.values[self.offsets[index]..self.offsets[index + 1]];
+        ArrayViewMutD::from_shape(&self.shapes[index][..], data).unwrap()
+    }
+
+    /// Iterate over views of the cells.
+    pub fn iter(&self) -> impl Iterator<Item = ArrayViewD<'_, T>> {
+        (0..self.len()).map(move |i| self.cell(i))
+    }
+
+    /// Split the cells into runs of consecutive cells with the same shape,
+    /// returning the index of the first cell and the length of each run.
+    fn runs(&self) -> Vec<(usize, usize)> {
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+
+        for (i, shape) in self.shapes.iter().enumerate() {
+            match runs.last_mut() {
+                Some((start, n)) if self.shapes[*start] == *shape => *n += 1,
+                _ => runs.push((i, 1)),
+            }
+        }
+
+        runs
+    }
+}
+
+impl<T: Clone> RaggedArray<T> {
+    /// Append a cell.
+    ///
+    /// A zero-dimensional view is taken to be an undefined cell, and its
+    /// element is not stored.
+    pub fn push(&mut self, cell: ArrayViewD<T>) {
+        if cell.ndim() > 0 {
+            self.values.extend(cell.iter().cloned());
+        }
+
+        self.shapes.push(cell.shape().to_vec());
+        self.offsets.push(self.values.len());
+    }
+}
+
+impl Table {
+    /// Check that a column is an array column holding elements of type `T`.
+    fn check_array_column<T: CasaScalarData>(&mut self, col_name: &str) -> Result<(), TableError> {
+        let desc = self.get_col_desc(col_name)?;
+
+        if desc.is_scalar() {
+            return Err(TableError::ScalarColumnError(col_name.to_owned()));
+        }
+
+        if desc.data_type() != T::DATA_TYPE {
+            return Err(UnexpectedDataTypeError(T::DATA_TYPE, desc.data_type()).into());
+        }
+
+        Ok(())
+    }
+
+    /// Read every cell of an array column, whatever their shapes.
+    ///
+    /// The column’s cells may vary in shape from row to row. The shape of
+    /// each cell is looked up, and then each run of consecutive rows whose
+    /// cells share a shape is read with a single bulk read, so that tables
+    /// whose shapes change only occasionally — such as Measurement Sets
+    /// sorted by spectral window — are read nearly as fast as fixed-shape
+    /// columns.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{Complex, Table, TableOpenMode};
+    ///
+    /// let mut t = Table::open("multi_spw.ms", TableOpenMode::Read).unwrap();
+    /// let data = t.get_column::<Complex<f32>>("DATA").unwrap();
+    /// println!("row 0 has shape {:?}", data.shape(0));
+    /// ```
+    pub fn get_column<T: CasaScalarData + Copy + Default>(
+        &mut self,
+        col_name: &str,
+    ) -> Result<RaggedArray<T>, TableError> {
+        self.get_column_with_progress(col_name, &Progress::new())
+    }
+
+    /// Read every cell of an array column, reporting progress and allowing
+    /// cancellation.
+    ///
+    /// This works like [`Self::get_column`], except that *progress* is told
+    /// the number of rows read after each run of equally shaped cells, and
+    /// the read stops with [`TableError::Cancelled`] if cancellation is
+    /// requested.
+    pub fn get_column_with_progress<T: CasaScalarData + Copy + Default>(
+        &mut self,
+        col_name: &str,
+        progress: &Progress,
+    ) -> Result<RaggedArray<T>, TableError> {
+        self.check_array_column::<T>(col_name)?;
+        let n_rows = self.n_rows();
+        let mut shapes = Vec::with_capacity(n_rows as usize);
+
+        for row in 0..n_rows {
+            if row % SHAPE_CHECK_INTERVAL == 0 {
+                progress.check(0)?;
+            }
+
+            shapes.push(self.get_cell_shape(col_name, row)?);
+        }
+
+        let n_values = shapes.iter().map(|s| cell_len(s)).sum();
+        let mut ragged = RaggedArray::from_parts(vec![T::default(); n_values], shapes).unwrap();
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+
+        for (start, n) in ragged.runs() {
+            progress.check(start as u64)?;
+            let shape = &ragged.shapes[start];
+
+            if shape.is_empty() {
+                progress.report((start + n) as u64, Some(n_rows));
+                continue;
+            }
+
+            let dims: Vec<c_ulong> = shape.iter().map(|d| *d as _).collect();
+            let data = &mut ragged.values[ragged.offsets[start]..ragged.offsets[start + n]];
+
+            if unsafe {
+                glue::table_get_column_range_shaped(
+                    self.handle,
+                    &ccol_name,
+                    start as u64,
+                    n as u64,
+                    dims.len() as _,
+                    dims.as_ptr(),
+                    data.as_mut_ptr() as _,
+                    &mut self.exc_info,
+                )
+            } != 0
+            {
+                return self.exc_info.as_err();
+            }
+
+            self.record_io(
+                IoDirection::Get,
+                col_name,
+                n as u64,
+                T::DATA_TYPE,
+                data.len() as u64,
+            );
+            progress.report((start + n) as u64, Some(n_rows));
+        }
+
+        Ok(ragged)
+    }
+
+    /// Write every cell of an array column, whatever their shapes.
+    ///
+    /// Cell *i* of *values* is written to row *i*, and rows are added to the
+    /// table if it has fewer rows than *values* has cells. As with
+    /// [`Self::get_column`], each run of equally shaped cells is written with
+    /// a single bulk write. In a variable-shape column, each cell takes on the
+    /// shape of its value; in a fixed-shape column, every value must have the
+    /// column’s shape. Cells with an empty shape are skipped.
+    pub fn put_column<T: CasaScalarData + Copy + Default>(
+        &mut self,
+        col_name: &str,
+        values: &RaggedArray<T>,
+    ) -> Result<(), TableError> {
+        self.put_column_with_progress(col_name, values, &Progress::new())
+    }
+
+    /// Write every cell of an array column, reporting progress and allowing
+    /// cancellation.
+    ///
+    /// This works like [`Self::put_column`], except that *progress* is told
+    /// the number of rows written after each run of equally shaped cells,
+    /// and the write stops with [`TableError::Cancelled`] before the next run
+    /// if cancellation is requested. Any rows needed to hold *values* are
+    /// added before anything is written, so a cancelled write leaves the
+    /// table at its full new length.
+    pub fn put_column_with_progress<T: CasaScalarData + Copy + Default>(
+        &mut self,
+        col_name: &str,
+        values: &RaggedArray<T>,
+        progress: &Progress,
+    ) -> Result<(), TableError> {
+        self.check_array_column::<T>(col_name)?;
+
+        if values.len() as u64 > self.n_rows() {
+            self.add_rows(values.len() - self.n_rows() as usize)?;
+        }
+
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+
+        let n_total = values.len() as u64;
+
+        for (start, n) in values.runs() {
+            progress.check(start as u64)?;
+            let shape = &values.shapes[start];
+
+            if shape.is_empty() {
+                progress.report((start + n) as u64, Some(n_total));
+                continue;
+            }
+
+            let dims: Vec<c_ulong> = shape.iter().map(|d| *d as _).collect();
+            let data = &values.values[values.offsets[start]..values.offsets[start + n]];
+
+            if unsafe {
+                glue::table_put_column_range_shaped(
+                    self.handle,
+                    &ccol_name,
+                    start as u64,
+                    n as u64,
+                    dims.len() as _,
+                    dims.as_ptr(),
+                    data.as_ptr() as _,
+                    &mut self.exc_info,
+                )
+            } != 0
+            {
+                return self.exc_info.as_err();
+            }
+
+            self.record_io(
+                IoDirection::Put,
+                col_name,
+                n as u64,
+                T::DATA_TYPE,
+                data.len() as u64,
+            );
+            progress.report((start + n) as u64, Some(n_total));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Complex, GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use ndarray::Array2;
+    use tempfile::tempdir;
+
+    #[test]
+    fn ragged_column_round_trip() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("test.ms");
+
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_array_column(GlueDataType::TpComplex, "DATA", None, None, false, false)
+            .unwrap();
+        let mut t = Table::new(&path, desc, 0, TableCreateMode::New).unwrap();
+
+        // Two spectral windows of different sizes, plus an undefined cell.
+        let mut data = RaggedArray::new();
+        let cell = |n_chans: usize, base: f32| {
+            Array2::from_shape_fn((n_chans, 2), |(c, p)| {
+                Complex::new(base + c as f32, p as f32)
+            })
+        };
+        data.push(cell(4, 0.).view().into_dyn());
+        data.push(cell(4, 10.).view().into_dyn());
+        data.push(cell(8, 20.).view().into_dyn());
+        data.push(cell(8, 30.).view().into_dyn());
+        data.push(cell(4, 40.).view().into_dyn());
+
+        t.put_column("DATA", &data).unwrap();
+        assert_eq!(t.n_rows(), 5);
+        assert_eq!(t.get_cell_shape("DATA", 2).unwrap(), vec![8, 2]);
+
+        let back = t.get_column::<Complex<f32>>("DATA").unwrap();
+        assert_eq!(back, data);
+        assert_eq!(back.cell(3)[[7, 1]], Complex::new(37., 1.));
+
+        // Undefined cells are skipped on write.
+        let mut partial = RaggedArray::new();
+        partial.push(ndarray::arr0(Complex::new(0f32, 0.)).view().into_dyn());
+        partial.push(cell(4, 50.).view().into_dyn());
+        t.put_column("DATA", &partial).unwrap();
+        let back = t.get_column::<Complex<f32>>("DATA").unwrap();
+        assert_eq!(back.cell(0), data.cell(0));
+        assert_eq!(back.cell(1), partial.cell(1));
+
+        assert!(t.get_column::<f64>("DATA").is_err());
+    }
+}
@@ -0,0 +1,247 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Keeping track of open tables.
+//!
+//! casacore keeps process-wide state for every open table — file
+//! descriptors, locks, and entries in its table cache — so a long-lived
+//! service that forgets to drop its [`Table`]s eventually runs into
+//! mysterious lock failures or "too many open files" errors. Every `Table`
+//! is recorded in a registry while it is open. [`open_tables`] lists them,
+//! and a handler installed with [`set_open_table_warning_handler`] is called
+//! when their number passes a threshold that can be adjusted with
+//! [`set_open_table_warning_threshold`].
+
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::SystemTime,
+};
+
+use crate::{glue, Table, TableOpenMode};
+
+/// The default number of open tables above which the warning handler is
+/// called.
+pub const DEFAULT_OPEN_TABLE_WARNING_THRESHOLD: usize = 512;
+
+/// How an open table came into being.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TableOrigin {
+    /// The table was opened from disk, in the given mode.
+    Opened(TableOpenMode),
+
+    /// The table was newly created.
+    Created,
+
+    /// The table was derived from another one, such as a snapshot or the
+    /// result of a query.
+    Derived,
+}
+
+/// Information about a table that is currently open.
+#[derive(Clone, Debug)]
+pub struct OpenTableInfo {
+    /// The path of the table.
+    pub path: String,
+
+    /// How the table was obtained.
+    pub origin: TableOrigin,
+
+    /// When the table was opened.
+    pub opened_at: SystemTime,
+
+    handle: usize,
+}
+
+/// A function that is told about the open tables when there are too many of
+/// them. See [`set_open_table_warning_handler`].
+pub type OpenTableWarningHandler = Box<dyn Fn(&[OpenTableInfo]) + Send + Sync>;
+
+/// A handler as stored in the registry, so that it can be called after the
+/// registry's lock has been released.
+type SharedWarningHandler = Arc<dyn Fn(&[OpenTableInfo]) + Send + Sync>;
+
+struct Registry {
+    tables: Vec<OpenTableInfo>,
+    threshold: Option<usize>,
+    warned: bool,
+    handler: Option<SharedWarningHandler>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    tables: Vec::new(),
+    threshold: Some(DEFAULT_OPEN_TABLE_WARNING_THRESHOLD),
+    warned: false,
+    handler: None,
+});
+
+fn registry() -> MutexGuard<'static, Registry> {
+    // The registry is only used for diagnostics, so carry on even if some
+    // other thread panicked while holding the lock.
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// List the tables that are currently open in this process, oldest first.
+///
+/// Every [`Table`] is included until it is dropped, including snapshots and
+/// the results of queries.
+///
+/// ```rust
+/// use rubbl_casatables::open_tables;
+///
+/// for info in open_tables() {
+///     println!("{} ({:?}, open since {:?})", info.path, info.origin, info.opened_at);
+/// }
+/// ```
+pub fn open_tables() -> Vec<OpenTableInfo> {
+    registry().tables.clone()
+}
+
+/// Set the number of open tables above which the warning handler is called.
+///
+/// The handler installed with [`set_open_table_warning_handler`] is called
+/// once each time the number of open tables rises above *threshold*. `None`
+/// disables it. The default is [`DEFAULT_OPEN_TABLE_WARNING_THRESHOLD`].
+pub fn set_open_table_warning_threshold(threshold: Option<usize>) {
+    let mut reg = registry();
+    reg.threshold = threshold;
+    reg.warned = false;
+}
+
+/// Set the function that is called when too many tables are open.
+///
+/// The handler is passed the list of open tables, as returned by
+/// [`open_tables`], and can report it however the application reports its
+/// other diagnostics. It is called on the thread that opened the table that
+/// crossed the threshold, after the registry's lock has been released, so it
+/// may itself open or close tables. By default there is no handler and
+/// nothing is reported; `None` removes a handler.
+///
+/// ```rust
+/// use rubbl_casatables::set_open_table_warning_handler;
+///
+/// set_open_table_warning_handler(Some(Box::new(|tables| {
+///     eprintln!("warning: {} casacore tables are open", tables.len());
+/// })));
+/// ```
+pub fn set_open_table_warning_handler(handler: Option<OpenTableWarningHandler>) {
+    registry().handler = handler.map(Arc::from);
+}
+
+/// Record a newly opened table.
+pub(crate) fn register(table: &Table, origin: TableOrigin) {
+    let path = table.file_name().unwrap_or_default();
+    let mut reg = registry();
+
+    reg.tables.push(OpenTableInfo {
+        path,
+        origin,
+        opened_at: SystemTime::now(),
+        handle: table.handle as usize,
+    });
+
+    let warning = match reg.threshold {
+        Some(t) if reg.tables.len() > t => {
+            if reg.warned {
+                None
+            } else {
+                reg.warned = true;
+                reg.handler
+                    .clone()
+                    .map(|handler| (handler, reg.tables.clone()))
+            }
+        }
+
+        _ => {
+            reg.warned = false;
+            None
+        }
+    };
+
+    drop(reg);
+
+    if let Some((handler, tables)) = warning {
+        handler(&tables);
+    }
+}
+
+/// Change the recorded origin of an open table.
+pub(crate) fn set_origin(table: &Table, origin: TableOrigin) {
+    let handle = table.handle as usize;
+
+    if let Some(info) = registry().tables.iter_mut().find(|t| t.handle == handle) {
+        info.origin = origin;
+    }
+}
+
+/// Forget about a table that is being closed.
+pub(crate) fn unregister(handle: *mut glue::GlueTable) {
+    let mut reg = registry();
+
+    if let Some(i) = reg.tables.iter().position(|t| t.handle == handle as usize) {
+        reg.tables.remove(i);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::tempdir;
+
+    fn find(path: &str) -> Vec<TableOrigin> {
+        open_tables()
+            .into_iter()
+            .filter(|t| t.path == path)
+            .map(|t| t.origin)
+            .collect()
+    }
+
+    #[test]
+    fn tracks_open_tables() {
+        let tmp_dir = tempdir().unwrap();
+        let table_path = tmp_dir.path().join("t");
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "X", None, false, false)
+            .unwrap();
+        let t = Table::new(&table_path, desc, 2, TableCreateMode::New).unwrap();
+        let path = t.file_name().unwrap();
+        assert_eq!(find(&path), vec![TableOrigin::Created]);
+        drop(t);
+        assert!(find(&path).is_empty());
+
+        let mut t = Table::open(&table_path, TableOpenMode::Read).unwrap();
+        let snap = t.snapshot().unwrap();
+        let snap_path = snap.file_name().unwrap();
+        let origins = find(&path);
+        assert!(origins.contains(&TableOrigin::Opened(TableOpenMode::Read)));
+        assert!(find(&snap_path).contains(&TableOrigin::Derived));
+
+        drop(snap);
+        drop(t);
+        assert!(find(&path).is_empty());
+        assert!(find(&snap_path).is_empty());
+    }
+
+    #[test]
+    fn calls_warning_handler() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        set_open_table_warning_handler(Some(Box::new(|tables| {
+            assert!(!tables.is_empty());
+            CALLS.fetch_add(1, Ordering::SeqCst);
+        })));
+        set_open_table_warning_threshold(Some(0));
+
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "X", None, false, false)
+            .unwrap();
+        let tmp_dir = tempdir().unwrap();
+        let t = Table::new(tmp_dir.path().join("t"), desc, 2, TableCreateMode::New).unwrap();
+
+        set_open_table_warning_threshold(Some(DEFAULT_OPEN_TABLE_WARNING_THRESHOLD));
+        set_open_table_warning_handler(None);
+        drop(t);
+        assert!(CALLS.load(Ordering::SeqCst) >= 1);
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Opening tables that live in cloud object stores.
+//!
+//! With the `remote` feature enabled, [`Table::open_remote`] opens a table
+//! stored under a URL such as `s3://bucket/archive/vis.ms`,
+//! `gs://bucket/vis.ms`, or `https://host/vis.ms` by copying its files into
+//! a local cache and opening the copy. casacore reads tables with ordinary
+//! file I/O, and opens every storage manager file of a table as soon as the
+//! table itself is opened, so the files of the table are fetched in full.
+//! Sub-tables, however, are separate directories that casacore does not
+//! touch until they are used, so by default they are not fetched until they
+//! are opened with `open_remote` in turn. A Measurement Set can therefore be
+//! browsed without copying every sub-table, and without a full `aws s3 sync`
+//! beforehand.
+//!
+//! Credentials and other settings for the object store are taken from the
+//! environment in the usual way, such as `AWS_ACCESS_KEY_ID` and
+//! `AWS_REGION` for S3, or `GOOGLE_SERVICE_ACCOUNT` for GCS.
+
+use futures::{StreamExt, TryStreamExt};
+use object_store::{ObjectMeta, ObjectStore};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use url::Url;
+
+use crate::{Table, TableError, TableOpenMode};
+
+/// The number of files that are downloaded at once.
+const MAX_CONCURRENT_FETCHES: usize = 8;
+
+/// The name of the file, kept next to each cached table directory, that
+/// records the versions of the files in the cache.
+const MANIFEST_SUFFIX: &str = ".rubbl-remote";
+
+/// An error that can occur when opening a remote table.
+#[derive(Error, Debug)]
+pub enum RemoteError {
+    /// An error occurred while opening the cached table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The object store could not be reached or reported an error.
+    #[error(transparent)]
+    Store(#[from] object_store::Error),
+
+    /// The URL could not be parsed.
+    #[error("invalid table URL: {0}")]
+    Url(#[from] url::ParseError),
+
+    /// The local cache could not be read or written.
+    #[error("error accessing the remote table cache")]
+    Io(#[from] std::io::Error),
+
+    /// There is no table at the URL.
+    #[error("there is no table at \"{0}\"")]
+    NoSuchTable(String),
+}
+
+/// How [`Table::open_remote`] uses its local cache.
+#[derive(Clone, Debug)]
+pub struct CachePolicy {
+    /// The directory in which to cache fetched files. Each table is cached
+    /// in a subdirectory whose path mirrors its URL, so that the cached
+    /// copy of a sub-table is always found inside the cached copy of its
+    /// parent.
+    pub cache_dir: PathBuf,
+
+    /// If true, a table that has already been cached is opened without
+    /// contacting the object store at all. Otherwise, the default, the
+    /// store is listed and any files that have changed since they were
+    /// cached are fetched again.
+    pub offline: bool,
+
+    /// If true, fetch the table’s sub-tables along with it. This is off by
+    /// default, so that sub-tables are only fetched when they are opened.
+    pub subtables: bool,
+}
+
+impl CachePolicy {
+    /// Cache tables in *cache_dir*, revalidating cached files and fetching
+    /// sub-tables only on demand.
+    pub fn new<P: Into<PathBuf>>(cache_dir: P) -> Self {
+        CachePolicy {
+            cache_dir: cache_dir.into(),
+            offline: false,
+            subtables: false,
+        }
+    }
+}
+
+impl Table {
+    /// Open a table stored in a cloud object store, read-only.
+    ///
+    /// The files of the table at *url* are fetched into the cache described
+    /// by *policy*, skipping any that are already cached and unchanged, and
+    /// the cached copy is then opened. Any URL that the `object_store` crate
+    /// understands can be used.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{CachePolicy, Table};
+    ///
+    /// let policy = CachePolicy::new("/scratch/ms-cache");
+    /// let url = "s3://my-archive/2024/obs1234.ms";
+    /// let mut t = Table::open_remote(url, &policy).unwrap();
+    /// let times = t.get_col_as_vec::<f64>("TIME").unwrap();
+    ///
+    /// // Sub-tables are fetched when they are opened.
+    /// let mut ants = Table::open_remote(&format!("{url}/ANTENNA"), &policy).unwrap();
+    /// ```
+    ///
+    /// This runs the transfers on a private single-threaded async runtime.
+    /// Code that is already running inside an async runtime should use
+    /// [`open_remote_async`] instead.
+    pub fn open_remote(url: &str, policy: &CachePolicy) -> Result<Table, RemoteError> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        rt.block_on(open_remote_async(url, policy))
+    }
+
+    /// Get the local directory in which [`Self::open_remote`] caches the
+    /// table at *url* under *policy*.
+    pub fn remote_cache_path(url: &str, policy: &CachePolicy) -> Result<PathBuf, RemoteError> {
+        let url = Url::parse(url)?;
+        Ok(cache_path(&url, policy))
+    }
+}
+
+/// Open a table stored in a cloud object store, read-only, asynchronously.
+///
+/// This is the asynchronous counterpart of [`Table::open_remote`], and must
+/// be run inside a tokio runtime. Only the transfers are asynchronous:
+/// opening the cached table at the end blocks, as all casacore calls do.
+pub async fn open_remote_async(url: &str, policy: &CachePolicy) -> Result<Table, RemoteError> {
+    let parsed = Url::parse(url)?;
+    let local = cache_path(&parsed, policy);
+
+    if !(policy.offline && local.join("table.dat").is_file()) {
+        sync_table(&parsed, &local, policy).await?;
+    }
+
+    Ok(Table::open(&local, TableOpenMode::Read)?)
+}
+
+/// Work out where the table at *url* is cached.
+fn cache_path(url: &Url, policy: &CachePolicy) -> PathBuf {
+    let mut path = policy.cache_dir.join(url.scheme());
+
+    if let Some(host) = url.host_str() {
+        path.push(host);
+    }
+
+    for segment in url.path().split('/') {
+        // Guard against URLs that would escape the cache.
+        if !segment.is_empty() && segment != "." && segment != ".." {
+            path.push(segment);
+        }
+    }
+
+    path
+}
+
+/// Bring the cached copy of the table at *url* up to date.
+async fn sync_table(url: &Url, local: &Path, policy: &CachePolicy) -> Result<(), RemoteError> {
+    let options = std::env::vars().map(|(k, v)| (k.to_ascii_lowercase(), v));
+    let (store, prefix) = object_store::parse_url_opts(url, options)?;
+
+    let mut files: Vec<(String, ObjectMeta)> = store
+        .list(Some(&prefix))
+        .map_ok(|meta| {
+            let rel = meta
+                .location
+                .prefix_match(&prefix)
+                .map(|parts| parts.map(|p| p.as_ref().to_owned()).collect::<Vec<_>>())
+                .unwrap_or_default()
+                .join("/");
+            (rel, meta)
+        })
+        .try_collect()
+        .await?;
+
+    // Sub-tables are subdirectories of the table directory.
+    if !policy.subtables {
+        files.retain(|(rel, _)| !rel.contains('/'));
+    }
+
+    if !files.iter().any(|(rel, _)| rel == "table.dat") {
+        return Err(RemoteError::NoSuchTable(url.to_string()));
+    }
+
+    let manifest_path = manifest_path(local);
+    let mut manifest = read_manifest(&manifest_path);
+    let stale: Vec<&(String, ObjectMeta)> = files
+        .iter()
+        .filter(|(rel, meta)| {
+            manifest.get(rel) != Some(&version(meta)) || !local.join(rel).is_file()
+        })
+        .collect();
+
+    let fetched: Vec<String> = futures::stream::iter(stale)
+        .map(|(rel, meta)| fetch_file(store.as_ref(), meta, local.join(rel), rel))
+        .buffer_unordered(MAX_CONCURRENT_FETCHES)
+        .try_collect()
+        .await?;
+
+    if !fetched.is_empty() {
+        for (rel, meta) in &files {
+            manifest.insert(rel.clone(), version(meta));
+        }
+
+        write_manifest(&manifest_path, &manifest)?;
+    }
+
+    Ok(())
+}
+
+/// Download one file, writing it to a temporary file first so that an
+/// interrupted transfer never leaves a truncated file in the cache.
+async fn fetch_file(
+    store: &dyn ObjectStore,
+    meta: &ObjectMeta,
+    dest: PathBuf,
+    rel: &str,
+) -> Result<String, RemoteError> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut tmp_name = dest.file_name().unwrap_or_default().to_owned();
+    tmp_name.push(".partial");
+    let tmp = dest.with_file_name(tmp_name);
+    let mut f = fs::File::create(&tmp)?;
+    let mut stream = store.get(&meta.location).await?.into_stream();
+
+    while let Some(bytes) = stream.next().await {
+        f.write_all(&bytes?)?;
+    }
+
+    f.sync_all()?;
+    fs::rename(&tmp, &dest)?;
+    Ok(rel.to_owned())
+}
+
+/// Describe the version of a remote file, so that changes can be noticed.
+fn version(meta: &ObjectMeta) -> String {
+    format!(
+        "{} {} {}",
+        meta.size,
+        meta.e_tag.as_deref().unwrap_or("-"),
+        meta.last_modified.timestamp_millis()
+    )
+}
+
+fn manifest_path(local: &Path) -> PathBuf {
+    let mut name = local.file_name().unwrap_or_default().to_owned();
+    name.push(MANIFEST_SUFFIX);
+    local.with_file_name(name)
+}
+
+/// Read a cache manifest, which has a line for each cached file giving its
+/// path within the table and its version, separated by a tab. A manifest
+/// that is missing or unreadable is treated as empty, so that everything is
+/// fetched again.
+fn read_manifest(path: &Path) -> HashMap<String, String> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(rel, version)| (rel.to_owned(), version.to_owned()))
+        .collect()
+}
+
+fn write_manifest(path: &Path, manifest: &HashMap<String, String>) -> Result<(), RemoteError> {
+    let mut entries: Vec<_> = manifest.iter().collect();
+    entries.sort();
+    let mut text = String::new();
+
+    for (rel, version) in entries {
+        text.push_str(rel);
+        text.push('\t');
+        text.push_str(version);
+        text.push('\n');
+    }
+
+    fs::write(path, text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use tempfile::tempdir;
+
+    #[test]
+    fn fetch_and_cache() {
+        let tmp_dir = tempdir().unwrap();
+        let remote_path = tmp_dir.path().join("remote").join("vis.ms");
+        let spec = SyntheticMsSpec::default();
+        drop(synthetic_ms(&remote_path, &spec).unwrap());
+
+        let url = Url::from_directory_path(&remote_path).unwrap();
+        let url = url.as_str().trim_end_matches('/');
+        let policy = CachePolicy::new(tmp_dir.path().join("cache"));
+        let local = Table::remote_cache_path(url, &policy).unwrap();
+
+        let mut t = Table::open_remote(url, &policy).unwrap();
+        assert_eq!(t.n_rows(), spec.n_rows() as u64);
+        assert_eq!(
+            t.get_col_as_vec::<f64>("TIME").unwrap().len(),
+            spec.n_rows()
+        );
+        assert!(local.join("table.dat").is_file());
+        assert!(!local.join("ANTENNA").exists());
+        drop(t);
+
+        // Sub-tables are cached inside their parent.
+        let ants = Table::open_remote(&format!("{url}/ANTENNA"), &policy).unwrap();
+        assert_eq!(ants.n_rows(), spec.n_ants as u64);
+        assert!(local.join("ANTENNA").join("table.dat").is_file());
+        drop(ants);
+
+        // Offline opens use the cache even if the original has gone.
+        fs::remove_dir_all(&remote_path).unwrap();
+        let offline = CachePolicy {
+            offline: true,
+            ..policy.clone()
+        };
+        let t = Table::open_remote(url, &offline).unwrap();
+        assert_eq!(t.n_rows(), spec.n_rows() as u64);
+        drop(t);
+
+        assert!(Table::open_remote(url, &policy).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Retrying reads on unreliable filesystems.
+//!
+//! Observatory data often live on Lustre or NFS servers, which occasionally
+//! fail a read with an I/O error or a stale file handle and then recover a
+//! moment later. Without help, such a hiccup aborts a scan of a large data
+//! set partway through. A [`RetryPolicy`] attached to a [`Table`] makes the
+//! table retry its reads, with exponential backoff, when they fail in a way
+//! that [`is_transient_error`] recognizes as temporary.
+//!
+//! ```no_run
+//! use rubbl_casatables::{Table, TableOpenMode};
+//!
+//! // Open with the default retry policy.
+//! let mut t = Table::open("/lustre/obs/vis.ms", TableOpenMode::ReadTolerant).unwrap();
+//! let times: Vec<f64> = t.get_col_as_vec("TIME").unwrap();
+//! ```
+
+use std::{io, path::Path, thread, time::Duration};
+
+use crate::{
+    registry::{self, TableOrigin},
+    Table, TableError, TableOpenMode,
+};
+
+/// Fragments of the messages of casacore exceptions caused by transient
+/// filesystem problems. casacore reports system errors by including the
+/// text of `strerror` in its messages.
+const TRANSIENT_MESSAGES: &[&str] = &[
+    "Stale file handle",
+    "Stale NFS file handle",
+    "Input/output error",
+    "Resource temporarily unavailable",
+    "Interrupted system call",
+    "Connection timed out",
+    "Connection reset",
+    "No locks available",
+    "Host is down",
+];
+
+/// How to retry reads that fail because of transient filesystem problems.
+///
+/// After the first failure, the operation is retried after
+/// `initial_backoff`; each further failure multiplies the wait by
+/// `backoff_factor`, up to `max_backoff`. Once `max_attempts` attempts have
+/// failed, the last error is returned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// The total number of attempts to make, including the first. The
+    /// default is 5.
+    pub max_attempts: u32,
+
+    /// How long to wait before the first retry. The default is 100 ms.
+    pub initial_backoff: Duration,
+
+    /// The longest to wait between attempts. The default is 10 s.
+    pub max_backoff: Duration,
+
+    /// The factor by which the wait grows after each failure. The default
+    /// is 2.
+    pub backoff_factor: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            backoff_factor: 2.,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Get how long to wait after failed attempt number *attempt*, counting
+    /// from 1.
+    ///
+    /// ```rust
+    /// use rubbl_casatables::RetryPolicy;
+    /// use std::time::Duration;
+    ///
+    /// let policy = RetryPolicy::default();
+    /// assert_eq!(policy.backoff(1), Duration::from_millis(100));
+    /// assert_eq!(policy.backoff(3), Duration::from_millis(400));
+    /// assert_eq!(policy.backoff(20), Duration::from_secs(10));
+    /// ```
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self
+            .backoff_factor
+            .max(1.)
+            .powi(attempt.saturating_sub(1) as i32);
+        let secs = self.initial_backoff.as_secs_f64() * factor;
+        Duration::from_secs_f64(secs.min(self.max_backoff.as_secs_f64()))
+    }
+}
+
+/// Check whether an error looks like the result of a transient filesystem
+/// problem, so that the operation that caused it is worth retrying.
+///
+/// This recognizes I/O errors that are interruptions or timeouts, and
+/// casacore exceptions whose messages mention system errors such as
+/// stale file handles and generic I/O failures. Errors that will not go
+/// away by themselves, such as missing columns or type mismatches, are not
+/// transient.
+pub fn is_transient_error(err: &TableError) -> bool {
+    match err {
+        TableError::Io(e) => matches!(
+            e.kind(),
+            io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+        ),
+
+        TableError::Casacore(e) => TRANSIENT_MESSAGES.iter().any(|m| e.0.contains(m)),
+
+        _ => false,
+    }
+}
+
+/// Open a table for reading, retrying transient failures, and give it
+/// *policy*.
+pub(crate) fn open_tolerant(path: &Path, policy: RetryPolicy) -> Result<Table, TableError> {
+    let mut attempt = 1;
+
+    loop {
+        match Table::open(path, TableOpenMode::Read) {
+            Ok(mut table) => {
+                table.retry = Some(policy);
+                registry::set_origin(&table, TableOrigin::Opened(TableOpenMode::ReadTolerant));
+                return Ok(table);
+            }
+
+            Err(e) if attempt < policy.max_attempts && is_transient_error(&e) => {
+                thread::sleep(policy.backoff(attempt));
+                attempt += 1;
+            }
+
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+impl Table {
+    /// Set how reads from this table are retried after transient failures.
+    ///
+    /// With `None`, the default for tables not opened with
+    /// [`TableOpenMode::ReadTolerant`], failures are returned immediately.
+    /// Otherwise, the methods that read cell and column data —
+    /// [`Self::get_cell`], [`Self::get_cell_as_vec`],
+    /// [`Self::get_col_as_vec`], [`Self::get_cell_slice`], and each chunk of
+    /// [`Self::read_column_chunks`] — are retried according to *policy*
+    /// when they fail with an error that [`is_transient_error`] accepts.
+    /// Writes are never retried, since a failed write may have been partly
+    /// carried out.
+    pub fn set_retry_policy(&mut self, policy: Option<RetryPolicy>) {
+        self.retry = policy;
+    }
+
+    /// Get the policy for retrying reads from this table, if any.
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry.as_ref()
+    }
+
+    /// Run a read operation, retrying it according to the table’s retry
+    /// policy.
+    pub(crate) fn with_retry<T, F>(&mut self, mut op: F) -> Result<T, TableError>
+    where
+        F: FnMut(&mut Table) -> Result<T, TableError>,
+    {
+        let policy = match self.retry {
+            None => return op(self),
+            Some(ref p) => p.clone(),
+        };
+
+        let mut attempt = 1;
+
+        loop {
+            match op(self) {
+                Err(e) if attempt < policy.max_attempts && is_transient_error(&e) => {
+                    // Cached column objects may hold on to state from the
+                    // failed access.
+                    self.clear_column_cache();
+                    thread::sleep(policy.backoff(attempt));
+                    attempt += 1;
+                }
+
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CasacoreError, GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn retries() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("t");
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpDouble, "TIME", None, false, false)
+            .unwrap();
+        let mut t = Table::new(&path, desc, 3, TableCreateMode::New).unwrap();
+        t.put_cell("TIME", 1, &4.5).unwrap();
+        drop(t);
+
+        let mut t = Table::open(&path, TableOpenMode::ReadTolerant).unwrap();
+        assert_eq!(t.retry_policy(), Some(&RetryPolicy::default()));
+        assert_eq!(t.get_cell::<f64>("TIME", 1).unwrap(), 4.5);
+
+        t.set_retry_policy(Some(RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        }));
+
+        // A transient failure that clears up is hidden.
+        let mut calls = 0;
+        let value = t
+            .with_retry(|t| {
+                calls += 1;
+
+                if calls < 3 {
+                    Err(CasacoreError("read failed: Stale file handle".to_owned()).into())
+                } else {
+                    t.get_cell::<f64>("TIME", 1)
+                }
+            })
+            .unwrap();
+        assert_eq!(value, 4.5);
+        assert_eq!(calls, 3);
+
+        // One that doesn't is returned after the last attempt.
+        let mut calls = 0;
+        let result: Result<(), _> = t.with_retry(|_| {
+            calls += 1;
+            Err(CasacoreError("Input/output error".to_owned()).into())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+
+        // Other errors are returned at once.
+        let mut calls = 0;
+        assert!(t
+            .with_retry(|t| {
+                calls += 1;
+                t.get_cell::<f64>("NO_SUCH_COLUMN", 0)
+            })
+            .is_err());
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Reading several scalar columns at once.
+//!
+//! Scans of Measurement Set metadata — building an index of times and
+//! baselines, say — need a handful of scalar columns for every row. Reading
+//! them a cell at a time costs one trip through the glue per column per row,
+//! which quickly dominates the runtime. [`Table::get_scalar_columns`] reads
+//! a range of rows of each column with a single bulk call and returns the
+//! results as a tuple of vectors.
+
+use std::ops::Range;
+
+use crate::{glue, io_stats::IoDirection, CasaScalarData, Table, TableError};
+
+/// A tuple of scalar column types that can be read together with
+/// [`Table::get_scalar_columns`].
+///
+/// This is implemented for tuples of up to eight numeric, boolean, or
+/// complex types, such as `(f64, i32, i32)`.
+pub trait ScalarColumns {
+    /// The tuple of vectors holding the columns’ values, such as
+    /// `(Vec<f64>, Vec<i32>, Vec<i32>)`.
+    type Vecs;
+
+    /// The number of columns in the tuple.
+    const N_COLUMNS: usize;
+
+    /// Read the named columns over *rows*.
+    #[doc(hidden)]
+    fn read_columns(
+        table: &mut Table,
+        col_names: &[&str],
+        rows: Range<u64>,
+    ) -> Result<Self::Vecs, TableError>;
+}
+
+macro_rules! impl_scalar_columns {
+    ($n:expr; $($t:ident $i:tt),+) => {
+        impl<$($t: CasaScalarData + Copy + Default),+> ScalarColumns for ($($t,)+) {
+            type Vecs = ($(Vec<$t>,)+);
+
+            const N_COLUMNS: usize = $n;
+
+            fn read_columns(
+                table: &mut Table,
+                col_names: &[&str],
+                rows: Range<u64>,
+            ) -> Result<Self::Vecs, TableError> {
+                Ok(($(table.read_scalar_column::<$t>(col_names[$i], rows.clone())?,)+))
+            }
+        }
+    };
+}
+
+impl_scalar_columns! { 1; A 0 }
+impl_scalar_columns! { 2; A 0, B 1 }
+impl_scalar_columns! { 3; A 0, B 1, C 2 }
+impl_scalar_columns! { 4; A 0, B 1, C 2, D 3 }
+impl_scalar_columns! { 5; A 0, B 1, C 2, D 3, E 4 }
+impl_scalar_columns! { 6; A 0, B 1, C 2, D 3, E 4, F 5 }
+impl_scalar_columns! { 7; A 0, B 1, C 2, D 3, E 4, F 5, G 6 }
+impl_scalar_columns! { 8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7 }
+
+impl Table {
+    /// Read a range of rows of several scalar columns.
+    ///
+    /// The type parameter is a tuple giving the type of each column, and the
+    /// result is a tuple of vectors with one element per row in *rows*. Each
+    /// column is read with a single bulk call, which is far faster than
+    /// reading it one cell at a time.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{Table, TableOpenMode};
+    ///
+    /// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+    /// let n_rows = t.n_rows();
+    /// let (time, ant1, ant2) = t
+    ///     .get_scalar_columns::<(f64, i32, i32)>(&["TIME", "ANTENNA1", "ANTENNA2"], 0..n_rows)
+    ///     .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TableError::RowsOutOfBounds`] if *rows* extends past the end
+    /// of the table, [`TableError::UnexpectedDataType`] if a column’s type
+    /// does not match, and [`TableError::NotScalarColumnError`] if a column is
+    /// not scalar.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of names in *col_names* is not the number of
+    /// types in the tuple.
+    pub fn get_scalar_columns<C: ScalarColumns>(
+        &mut self,
+        col_names: &[&str],
+        rows: Range<u64>,
+    ) -> Result<C::Vecs, TableError> {
+        assert_eq!(
+            col_names.len(),
+            C::N_COLUMNS,
+            "expected one column name per type in the tuple"
+        );
+
+        let n_rows = self.n_rows();
+
+        if rows.start > rows.end || rows.end > n_rows {
+            return Err(TableError::RowsOutOfBounds {
+                start: rows.start,
+                end: rows.end,
+                n_rows,
+            });
+        }
+
+        C::read_columns(self, col_names, rows)
+    }
+
+    /// Read a range of rows of a single scalar column.
+    fn read_scalar_column<T: CasaScalarData + Copy + Default>(
+        &mut self,
+        col_name: &str,
+        rows: Range<u64>,
+    ) -> Result<Vec<T>, TableError> {
+        if !self.bulk_column_cell_shape::<T>(col_name)?.is_empty() {
+            return Err(TableError::NotScalarColumnError(T::DATA_TYPE));
+        }
+
+        let ccol_name = glue::StringBridge::from_rust(col_name);
+        let n = (rows.end - rows.start) as usize;
+        let mut data = vec![T::default(); n];
+
+        if n > 0 {
+            self.with_retry(|t| t.read_scalar_range(&ccol_name, rows.start, &mut data))?;
+        }
+
+        self.record_io(IoDirection::Get, col_name, n as u64, T::DATA_TYPE, n as u64);
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn scalar_columns() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpDouble, "TIME", None, false, false)
+            .unwrap();
+        desc.add_scalar_column(GlueDataType::TpInt, "ANTENNA1", None, false, false)
+            .unwrap();
+        desc.add_scalar_column(GlueDataType::TpBool, "FLAG_ROW", None, false, false)
+            .unwrap();
+        desc.add_array_column(GlueDataType::TpDouble, "UVW", None, Some(&[3]), true, false)
+            .unwrap();
+        let mut t = Table::new(tmp_dir.path().join("t"), desc, 5, TableCreateMode::New).unwrap();
+
+        for row in 0..5u64 {
+            t.put_cell("TIME", row, &(row as f64 * 1.5)).unwrap();
+            t.put_cell("ANTENNA1", row, &(row as i32 - 2)).unwrap();
+            t.put_cell("FLAG_ROW", row, &(row == 3)).unwrap();
+        }
+
+        let (time, ant1, flag) = t
+            .get_scalar_columns::<(f64, i32, bool)>(&["TIME", "ANTENNA1", "FLAG_ROW"], 1..4)
+            .unwrap();
+        assert_eq!(time, vec![1.5, 3., 4.5]);
+        assert_eq!(ant1, vec![-1, 0, 1]);
+        assert_eq!(flag, vec![false, false, true]);
+
+        let (ant1,) = t.get_scalar_columns::<(i32,)>(&["ANTENNA1"], 5..5).unwrap();
+        assert!(ant1.is_empty());
+
+        assert!(matches!(
+            t.get_scalar_columns::<(f64,)>(&["TIME"], 3..6),
+            Err(TableError::RowsOutOfBounds { .. })
+        ));
+        assert!(t
+            .get_scalar_columns::<(f64, f64)>(&["TIME", "ANTENNA1"], 0..5)
+            .is_err());
+        assert!(matches!(
+            t.get_scalar_columns::<(f64,)>(&["UVW"], 0..5),
+            Err(TableError::NotScalarColumnError(_))
+        ));
+    }
+}
@@ -0,0 +1,1065 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Typed row and column selections.
+//!
+//! casacore’s Table Query Language (TaQL) can select rows and columns of a
+//! table, but a query written as a string is only checked when casacore
+//! parses it, and a misspelled column name or a comparison between a number
+//! and a string produces an error from deep inside casacore — or, worse, a
+//! query that runs but does not mean what was intended. The [`Select`]
+//! builder assembles a query from typed pieces and checks it against the
+//! table’s schema. [`Table::select`] then evaluates it directly, without
+//! going through TaQL, and [`Select::to_taql`] compiles it to the
+//! equivalent TaQL.
+//!
+//! ```rust
+//! use rubbl_casatables::select::{col, Select};
+//!
+//! let sel = Select::cols(["TIME", "DATA"])
+//!     .filter(col("ANTENNA1").ne(col("ANTENNA2")))
+//!     .filter(col("FLAG_ROW").not())
+//!     .order_by("TIME")
+//!     .limit(100);
+//!
+//! assert_eq!(
+//!     sel.to_taql(),
+//!     "SELECT TIME, DATA FROM $1 WHERE ((ANTENNA1 != ANTENNA2) && (!FLAG_ROW)) \
+//!      ORDERBY TIME ASC LIMIT 100"
+//! );
+//! ```
+//!
+//! Selections work with any casacore. TaQL written by hand can be run with
+//! `Table::query`, which is only available with the `system-casacore`
+//! feature, since the bundled casacore does not include TaQL.
+
+use std::{cell::RefCell, cmp::Ordering, collections::HashMap, fmt};
+use thiserror::Error;
+
+use crate::{glue, CasaScalarData, Complex, GlueDataType, Table, TableError, TableOrigin};
+
+/// An error that can occur when checking or running a [`Select`].
+#[derive(Error, Debug)]
+pub enum SelectError {
+    /// An error occurred while inspecting the table or running the query.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The selection refers to a column that the table does not have.
+    #[error("the table has no column named \"{0}\"")]
+    NoSuchColumn(String),
+
+    /// The name of a column is not a plain identifier, and so cannot be
+    /// written in TaQL.
+    #[error("\"{0}\" cannot be used as a column name in a selection")]
+    BadColumnName(String),
+
+    /// An expression uses an array-valued column where a scalar is needed.
+    #[error("column \"{0}\" holds arrays, but a scalar is needed")]
+    ArrayColumn(String),
+
+    /// A comparison mixes values that cannot be compared.
+    #[error("cannot compare {left} with {right} in `{expr}`")]
+    IncomparableTypes {
+        /// The comparison, in TaQL.
+        expr: String,
+
+        /// The type of its left operand.
+        left: GlueDataType,
+
+        /// The type of its right operand.
+        right: GlueDataType,
+    },
+
+    /// An expression that must be a boolean — a filter, or an operand of a
+    /// logical operator — is not.
+    #[error("`{expr}` is of type {found}, but a boolean is needed")]
+    NotBoolean {
+        /// The expression, in TaQL.
+        expr: String,
+
+        /// Its type.
+        found: GlueDataType,
+    },
+
+    /// A string literal contains both kinds of quotation mark, which TaQL
+    /// cannot express.
+    #[error("the string {0:?} cannot be written in TaQL")]
+    BadString(String),
+
+    /// A number is infinite or NaN, which TaQL cannot express.
+    #[error("the number {0} cannot be written in TaQL")]
+    BadNumber(f64),
+
+    /// A sort key is a column whose values have no natural order, such as
+    /// complex numbers.
+    #[error("column \"{0}\" cannot be used as a sort key")]
+    NotOrderable(String),
+}
+
+/// A comparison operator.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    fn taql(self) -> &'static str {
+        match self {
+            CmpOp::Eq => "==",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        !matches!(self, CmpOp::Eq | CmpOp::Ne)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Column(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Compare(CmpOp, Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+/// An expression over the columns of a table.
+///
+/// Expressions are built from column references, made with [`col`], and
+/// literal values, converted from Rust integers, floats, booleans, and
+/// strings with [`From`]. The methods that combine expressions accept
+/// anything convertible to an `Expr`, so that
+/// `col("TIME").gt(4.8e9)` compares a column with a number.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr(Node);
+
+/// Refer to the column named *name*.
+pub fn col(name: &str) -> Expr {
+    Expr(Node::Column(name.to_owned()))
+}
+
+impl Expr {
+    fn compare(self, op: CmpOp, other: impl Into<Expr>) -> Expr {
+        Expr(Node::Compare(op, Box::new(self), Box::new(other.into())))
+    }
+
+    /// Test whether this expression equals *other*.
+    pub fn eq(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Eq, other)
+    }
+
+    /// Test whether this expression differs from *other*.
+    pub fn ne(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Ne, other)
+    }
+
+    /// Test whether this expression is less than *other*.
+    pub fn lt(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Lt, other)
+    }
+
+    /// Test whether this expression is less than or equal to *other*.
+    pub fn le(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Le, other)
+    }
+
+    /// Test whether this expression is greater than *other*.
+    pub fn gt(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Gt, other)
+    }
+
+    /// Test whether this expression is greater than or equal to *other*.
+    pub fn ge(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Ge, other)
+    }
+
+    /// Test whether both this expression and *other* are true.
+    pub fn and(self, other: impl Into<Expr>) -> Expr {
+        Expr(Node::And(Box::new(self), Box::new(other.into())))
+    }
+
+    /// Test whether either this expression or *other* is true.
+    pub fn or(self, other: impl Into<Expr>) -> Expr {
+        Expr(Node::Or(Box::new(self), Box::new(other.into())))
+    }
+
+    /// Negate this expression.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Expr {
+        Expr(Node::Not(Box::new(self)))
+    }
+
+    /// Work out the type of this expression, checking it against the
+    /// table’s schema.
+    fn check(&self, schema: &Schema) -> Result<GlueDataType, SelectError> {
+        Ok(match &self.0 {
+            Node::Column(name) => schema.scalar_type(name)?,
+            Node::Int(_) => GlueDataType::TpInt64,
+
+            Node::Float(v) => {
+                if !v.is_finite() {
+                    return Err(SelectError::BadNumber(*v));
+                }
+
+                GlueDataType::TpDouble
+            }
+
+            Node::Bool(_) => GlueDataType::TpBool,
+
+            Node::Str(s) => {
+                if s.contains('"') && s.contains('\'') {
+                    return Err(SelectError::BadString(s.clone()));
+                }
+
+                GlueDataType::TpString
+            }
+
+            Node::Compare(op, left, right) => {
+                let lt = left.check(schema)?;
+                let rt = right.check(schema)?;
+
+                if !comparable(*op, lt, rt) {
+                    return Err(SelectError::IncomparableTypes {
+                        expr: self.to_string(),
+                        left: lt,
+                        right: rt,
+                    });
+                }
+
+                GlueDataType::TpBool
+            }
+
+            Node::And(left, right) | Node::Or(left, right) => {
+                left.check_bool(schema)?;
+                right.check_bool(schema)?;
+                GlueDataType::TpBool
+            }
+
+            Node::Not(inner) => {
+                inner.check_bool(schema)?;
+                GlueDataType::TpBool
+            }
+        })
+    }
+
+    fn check_bool(&self, schema: &Schema) -> Result<(), SelectError> {
+        match self.check(schema)? {
+            GlueDataType::TpBool => Ok(()),
+
+            found => Err(SelectError::NotBoolean {
+                expr: self.to_string(),
+                found,
+            }),
+        }
+    }
+
+    /// Add the names of the columns that this expression refers to to
+    /// *names*.
+    fn column_names<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match &self.0 {
+            Node::Column(name) => names.push(name),
+            Node::Int(_) | Node::Float(_) | Node::Bool(_) | Node::Str(_) => {}
+
+            Node::Compare(_, left, right) | Node::And(left, right) | Node::Or(left, right) => {
+                left.column_names(names);
+                right.column_names(names);
+            }
+
+            Node::Not(inner) => inner.column_names(names),
+        }
+    }
+
+    /// Evaluate this expression for row *row*, given the values of the
+    /// columns that it refers to. The expression must have been checked.
+    fn eval<'a>(&'a self, columns: &'a HashMap<&str, ColumnValues>, row: usize) -> Value<'a> {
+        match &self.0 {
+            Node::Column(name) => columns[name.as_str()].get(row),
+            Node::Int(v) => Value::Int(*v),
+            Node::Float(v) => Value::Float(*v),
+            Node::Bool(v) => Value::Bool(*v),
+            Node::Str(s) => Value::Str(s),
+
+            Node::Compare(op, left, right) => Value::Bool(compare(
+                *op,
+                left.eval(columns, row),
+                right.eval(columns, row),
+            )),
+
+            Node::And(left, right) => {
+                Value::Bool(left.is_true(columns, row) && right.is_true(columns, row))
+            }
+
+            Node::Or(left, right) => {
+                Value::Bool(left.is_true(columns, row) || right.is_true(columns, row))
+            }
+
+            Node::Not(inner) => Value::Bool(!inner.is_true(columns, row)),
+        }
+    }
+
+    fn is_true(&self, columns: &HashMap<&str, ColumnValues>, row: usize) -> bool {
+        matches!(self.eval(columns, row), Value::Bool(true))
+    }
+}
+
+/// Render the expression as TaQL.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            Node::Column(name) => f.write_str(name),
+            Node::Int(v) => write!(f, "{}", v),
+            Node::Float(v) => {
+                // Rust writes floats without exponents, but a whole number
+                // needs a decimal point to be read as a float.
+                let s = v.to_string();
+
+                if s.contains('.') || !v.is_finite() {
+                    f.write_str(&s)
+                } else {
+                    write!(f, "{}.0", s)
+                }
+            }
+
+            Node::Bool(v) => f.write_str(if *v { "T" } else { "F" }),
+
+            Node::Str(s) => {
+                if s.contains('"') {
+                    write!(f, "'{}'", s)
+                } else {
+                    write!(f, "\"{}\"", s)
+                }
+            }
+
+            Node::Compare(op, left, right) => write!(f, "({} {} {})", left, op.taql(), right),
+            Node::And(left, right) => write!(f, "({} && {})", left, right),
+            Node::Or(left, right) => write!(f, "({} || {})", left, right),
+            Node::Not(inner) => write!(f, "(!{})", inner),
+        }
+    }
+}
+
+macro_rules! impl_int_literal {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Expr {
+                fn from(v: $t) -> Self {
+                    Expr(Node::Int(v as i64))
+                }
+            }
+        )*
+    };
+}
+
+impl_int_literal! { i8, u8, i16, u16, i32, u32, i64 }
+
+impl From<f32> for Expr {
+    fn from(v: f32) -> Self {
+        Expr(Node::Float(v as f64))
+    }
+}
+
+impl From<f64> for Expr {
+    fn from(v: f64) -> Self {
+        Expr(Node::Float(v))
+    }
+}
+
+impl From<bool> for Expr {
+    fn from(v: bool) -> Self {
+        Expr(Node::Bool(v))
+    }
+}
+
+impl From<&str> for Expr {
+    fn from(v: &str) -> Self {
+        Expr(Node::Str(v.to_owned()))
+    }
+}
+
+impl From<String> for Expr {
+    fn from(v: String) -> Self {
+        Expr(Node::Str(v))
+    }
+}
+
+/// Broad classes of data type, for deciding what can be compared with what.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TypeClass {
+    Bool,
+    Real,
+    Complex,
+    String,
+    Other,
+}
+
+fn type_class(t: GlueDataType) -> TypeClass {
+    use GlueDataType::*;
+
+    match t {
+        TpBool => TypeClass::Bool,
+        TpChar | TpUChar | TpShort | TpUShort | TpInt | TpUInt | TpInt64 | TpFloat | TpDouble => {
+            TypeClass::Real
+        }
+        TpComplex | TpDComplex => TypeClass::Complex,
+        TpString => TypeClass::String,
+        _ => TypeClass::Other,
+    }
+}
+
+fn comparable(op: CmpOp, left: GlueDataType, right: GlueDataType) -> bool {
+    let (lc, rc) = (type_class(left), type_class(right));
+
+    match (lc, rc) {
+        (TypeClass::Other, _) | (_, TypeClass::Other) => false,
+        (TypeClass::Real, TypeClass::Real) | (TypeClass::String, TypeClass::String) => true,
+        (TypeClass::Bool, TypeClass::Bool) => !op.is_ordering(),
+
+        (TypeClass::Real, TypeClass::Complex)
+        | (TypeClass::Complex, TypeClass::Real)
+        | (TypeClass::Complex, TypeClass::Complex) => !op.is_ordering(),
+
+        _ => false,
+    }
+}
+
+/// The value of an expression for one row of a table.
+#[derive(Clone, Copy, Debug)]
+enum Value<'a> {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Complex(Complex<f64>),
+    Str(&'a str),
+}
+
+impl Value<'_> {
+    fn as_f64(self) -> f64 {
+        match self {
+            Value::Int(v) => v as f64,
+            Value::Float(v) => v,
+            _ => unreachable!("only numbers are converted to floats"),
+        }
+    }
+
+    fn as_complex(self) -> Complex<f64> {
+        match self {
+            Value::Complex(v) => v,
+            v => Complex::new(v.as_f64(), 0.),
+        }
+    }
+}
+
+/// Evaluate a comparison between two values whose types have been checked
+/// with [`comparable`].
+fn compare(op: CmpOp, left: Value, right: Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(&b)),
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(&b)),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+
+        (Value::Complex(a), b) | (b, Value::Complex(a)) => {
+            if a == b.as_complex() {
+                Some(Ordering::Equal)
+            } else {
+                None
+            }
+        }
+
+        (a, b) => a.as_f64().partial_cmp(&b.as_f64()),
+    };
+
+    // As in IEEE arithmetic, unordered values are unequal, and neither less
+    // nor greater than each other.
+    match (op, ordering) {
+        (CmpOp::Ne, o) => o != Some(Ordering::Equal),
+        (_, None) => false,
+        (CmpOp::Eq, Some(o)) => o == Ordering::Equal,
+        (CmpOp::Lt, Some(o)) => o == Ordering::Less,
+        (CmpOp::Le, Some(o)) => o != Ordering::Greater,
+        (CmpOp::Gt, Some(o)) => o == Ordering::Greater,
+        (CmpOp::Ge, Some(o)) => o != Ordering::Less,
+    }
+}
+
+/// The values of a scalar column, read in full to evaluate a selection.
+enum ColumnValues {
+    Bool(Vec<bool>),
+    Int(Vec<i64>),
+    Float(Vec<f64>),
+    Complex(Vec<Complex<f64>>),
+    Str(Vec<String>),
+}
+
+impl ColumnValues {
+    fn read(table: &mut Table, name: &str) -> Result<Self, TableError> {
+        use GlueDataType::*;
+
+        fn ints<T: CasaScalarData + Into<i64>>(
+            table: &mut Table,
+            name: &str,
+        ) -> Result<ColumnValues, TableError> {
+            let values = table.get_col_as_vec::<T>(name)?;
+            Ok(ColumnValues::Int(values.into_iter().map(Into::into).collect()))
+        }
+
+        let data_type = table.get_col_desc(name)?.data_type();
+
+        Ok(match data_type {
+            TpBool => ColumnValues::Bool(table.get_col_as_vec(name)?),
+            TpChar => ints::<i8>(table, name)?,
+            TpUChar => ints::<u8>(table, name)?,
+            TpShort => ints::<i16>(table, name)?,
+            TpUShort => ints::<u16>(table, name)?,
+            TpInt => ints::<i32>(table, name)?,
+            TpUInt => ints::<u32>(table, name)?,
+            TpInt64 => ints::<i64>(table, name)?,
+
+            TpFloat => {
+                let values = table.get_col_as_vec::<f32>(name)?;
+                ColumnValues::Float(values.into_iter().map(f64::from).collect())
+            }
+
+            TpDouble => ColumnValues::Float(table.get_col_as_vec(name)?),
+
+            TpComplex => {
+                let values = table.get_col_as_vec::<Complex<f32>>(name)?;
+                ColumnValues::Complex(
+                    values
+                        .into_iter()
+                        .map(|v| Complex::new(v.re as f64, v.im as f64))
+                        .collect(),
+                )
+            }
+
+            TpDComplex => ColumnValues::Complex(table.get_col_as_vec(name)?),
+            TpString => ColumnValues::Str(table.get_col_as_vec(name)?),
+            _ => unreachable!("only columns of comparable types are read"),
+        })
+    }
+
+    fn get(&self, row: usize) -> Value<'_> {
+        match self {
+            ColumnValues::Bool(v) => Value::Bool(v[row]),
+            ColumnValues::Int(v) => Value::Int(v[row]),
+            ColumnValues::Float(v) => Value::Float(v[row]),
+            ColumnValues::Complex(v) => Value::Complex(v[row]),
+            ColumnValues::Str(v) => Value::Str(&v[row]),
+        }
+    }
+
+    /// Compare the values in two rows, for sorting.
+    fn cmp_rows(&self, a: usize, b: usize) -> Ordering {
+        match self {
+            ColumnValues::Bool(v) => v[a].cmp(&v[b]),
+            ColumnValues::Int(v) => v[a].cmp(&v[b]),
+            ColumnValues::Float(v) => v[a].total_cmp(&v[b]),
+            ColumnValues::Str(v) => v[a].cmp(&v[b]),
+            ColumnValues::Complex(_) => unreachable!("complex columns are not sort keys"),
+        }
+    }
+}
+
+/// The column types of a table, looked up as needed.
+struct Schema<'a> {
+    table: RefCell<&'a mut Table>,
+    names: Vec<String>,
+    cache: RefCell<HashMap<String, (GlueDataType, bool)>>,
+}
+
+impl<'a> Schema<'a> {
+    fn new(table: &'a mut Table) -> Result<Self, SelectError> {
+        let names = table.column_names().map_err(TableError::from)?;
+
+        Ok(Schema {
+            table: RefCell::new(table),
+            names,
+            cache: Default::default(),
+        })
+    }
+
+    /// Get the element type of a column and whether it is scalar.
+    fn column(&self, name: &str) -> Result<(GlueDataType, bool), SelectError> {
+        if !is_identifier(name) {
+            return Err(SelectError::BadColumnName(name.to_owned()));
+        }
+
+        if let Some(info) = self.cache.borrow().get(name) {
+            return Ok(*info);
+        }
+
+        if !self.names.iter().any(|n| n == name) {
+            return Err(SelectError::NoSuchColumn(name.to_owned()));
+        }
+
+        let desc = self
+            .table
+            .borrow_mut()
+            .get_col_desc(name)
+            .map_err(TableError::from)?;
+        let info = (desc.data_type(), desc.is_scalar());
+        self.cache.borrow_mut().insert(name.to_owned(), info);
+        Ok(info)
+    }
+
+    fn scalar_type(&self, name: &str) -> Result<GlueDataType, SelectError> {
+        match self.column(name)? {
+            (t, true) => Ok(t),
+            (_, false) => Err(SelectError::ArrayColumn(name.to_owned())),
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A selection of rows and columns from a table.
+///
+/// A `Select` chooses columns with [`Self::cols`] (or all of them, with
+/// [`Self::all`]), keeps rows matching its filters, sorts them, and
+/// optionally limits how many are returned. [`Table::select`] checks it
+/// against a table’s schema and runs it, producing a reference table.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Select {
+    columns: Vec<String>,
+    filter: Option<Expr>,
+    order: Vec<(String, bool)>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+impl Select {
+    /// Select all of a table’s columns.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Select the named columns, in the given order.
+    pub fn cols<I, S>(cols: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Select {
+            columns: cols.into_iter().map(Into::into).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Keep only rows for which *expr* is true.
+    ///
+    /// If this is called more than once, rows must satisfy all of the
+    /// filters.
+    pub fn filter(mut self, expr: Expr) -> Self {
+        self.filter = Some(match self.filter.take() {
+            None => expr,
+            Some(prev) => prev.and(expr),
+        });
+        self
+    }
+
+    /// Sort the rows by increasing values of the named column.
+    ///
+    /// Later sort keys break ties in earlier ones.
+    pub fn order_by(mut self, col_name: &str) -> Self {
+        self.order.push((col_name.to_owned(), true));
+        self
+    }
+
+    /// Sort the rows by decreasing values of the named column.
+    pub fn order_by_desc(mut self, col_name: &str) -> Self {
+        self.order.push((col_name.to_owned(), false));
+        self
+    }
+
+    /// Return at most *n* rows.
+    pub fn limit(mut self, n: u64) -> Self {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Skip the first *n* rows that would otherwise be returned.
+    pub fn offset(mut self, n: u64) -> Self {
+        self.offset = Some(n);
+        self
+    }
+
+    /// Compile the selection to a TaQL command, in which the table is
+    /// referred to as `$1`.
+    ///
+    /// This does not check the selection; see [`Self::check`].
+    pub fn to_taql(&self) -> String {
+        let mut taql = "SELECT ".to_owned();
+
+        if !self.columns.is_empty() {
+            taql.push_str(&self.columns.join(", "));
+            taql.push(' ');
+        }
+
+        taql.push_str("FROM $1");
+
+        if let Some(filter) = &self.filter {
+            taql.push_str(&format!(" WHERE {}", filter));
+        }
+
+        if !self.order.is_empty() {
+            let keys: Vec<String> = self
+                .order
+                .iter()
+                .map(|(name, asc)| format!("{} {}", name, if *asc { "ASC" } else { "DESC" }))
+                .collect();
+            taql.push_str(&format!(" ORDERBY {}", keys.join(", ")));
+        }
+
+        if let Some(n) = self.limit {
+            taql.push_str(&format!(" LIMIT {}", n));
+        }
+
+        if let Some(n) = self.offset {
+            taql.push_str(&format!(" OFFSET {}", n));
+        }
+
+        taql
+    }
+
+    /// Check the selection against the schema of *table*.
+    ///
+    /// Every column that the selection mentions must exist; the filter must
+    /// be a boolean expression over scalar columns; comparisons must be
+    /// between compatible types; and sort keys must be scalar columns whose
+    /// values can be ordered.
+    pub fn check(&self, table: &mut Table) -> Result<(), SelectError> {
+        let schema = Schema::new(table)?;
+
+        for name in &self.columns {
+            schema.column(name)?;
+        }
+
+        if let Some(filter) = &self.filter {
+            filter.check_bool(&schema)?;
+        }
+
+        for (name, _) in &self.order {
+            match type_class(schema.scalar_type(name)?) {
+                TypeClass::Bool | TypeClass::Real | TypeClass::String => {}
+                _ => return Err(SelectError::NotOrderable(name.clone())),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Work out which rows of *table* the selection picks, in order. The
+    /// selection must have been checked.
+    fn row_numbers(&self, table: &mut Table) -> Result<Vec<u64>, TableError> {
+        let n_rows = table.n_rows();
+        let offset = self.offset.unwrap_or(0).min(n_rows);
+        let limit = self.limit.unwrap_or(u64::MAX).min(n_rows - offset);
+
+        // With no filter or sort keys, nothing needs to be read, which
+        // matters for very large tables.
+        if self.filter.is_none() && self.order.is_empty() {
+            return Ok((offset..offset + limit).collect());
+        }
+
+        let mut names = Vec::new();
+
+        if let Some(filter) = &self.filter {
+            filter.column_names(&mut names);
+        }
+
+        names.extend(self.order.iter().map(|(name, _)| name.as_str()));
+        let mut columns = HashMap::new();
+
+        for name in names {
+            if !columns.contains_key(name) {
+                columns.insert(name, ColumnValues::read(table, name)?);
+            }
+        }
+
+        let mut rows: Vec<usize> = (0..n_rows as usize)
+            .filter(|&row| {
+                self.filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.is_true(&columns, row))
+            })
+            .collect();
+
+        // The sort is stable, so rows with equal keys stay in table order.
+        rows.sort_by(|&a, &b| {
+            self.order
+                .iter()
+                .map(|(name, asc)| {
+                    let o = columns[name.as_str()].cmp_rows(a, b);
+                    if *asc {
+                        o
+                    } else {
+                        o.reverse()
+                    }
+                })
+                .find(|o| o.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+
+        Ok(rows
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|row| row as u64)
+            .collect())
+    }
+}
+
+/// Render the selection as TaQL, as with [`Select::to_taql`].
+impl fmt::Display for Select {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_taql())
+    }
+}
+
+impl Table {
+    /// Run a TaQL command on this table.
+    ///
+    /// In the command, `$1` refers to this table. The command must produce
+    /// a table, such as the result of a `SELECT`; that is returned. Results
+    /// that select rows or columns are casacore reference tables, which share
+    /// the data of this table.
+    ///
+    /// The bundled casacore does not include TaQL, so this is only available
+    /// when the crate is built with the `system-casacore` feature.
+    ///
+    /// ```no_run
+    /// use rubbl_casatables::{Table, TableOpenMode};
+    ///
+    /// let mut t = Table::open("vis.ms", TableOpenMode::Read).unwrap();
+    /// let crosses = t.query("SELECT FROM $1 WHERE ANTENNA1 != ANTENNA2").unwrap();
+    /// ```
+    #[cfg(feature = "system-casacore")]
+    pub fn query(&mut self, taql: &str) -> Result<Table, TableError> {
+        let ccommand = glue::StringBridge::from_rust(taql);
+        let handle = unsafe { glue::table_query(self.handle, &ccommand, &mut self.exc_info) };
+
+        if handle.is_null() {
+            return self.exc_info.as_err();
+        }
+
+        Ok(Table::from_handle(
+            handle,
+            unsafe { std::mem::zeroed::<glue::ExcInfo>() },
+            TableOrigin::Derived,
+        ))
+    }
+
+    /// Check a selection against this table and run it.
+    ///
+    /// Mistakes such as misspelled column names and comparisons between
+    /// numbers and strings are reported as [`SelectError`]s before anything
+    /// is read. The selection is then evaluated by reading the columns that
+    /// its filter and sort keys refer to, and the result is a casacore
+    /// reference table of the chosen rows and columns, which shares the data
+    /// of this table. This does not need TaQL, so it works with the bundled
+    /// casacore.
+    pub fn select(&mut self, selection: &Select) -> Result<Table, SelectError> {
+        selection.check(self)?;
+        let rows = selection.row_numbers(self)?;
+        let cols: Vec<glue::StringBridge> = selection
+            .columns
+            .iter()
+            .map(|name| glue::StringBridge::from_rust(name))
+            .collect();
+
+        let handle = unsafe {
+            glue::table_select_rows(
+                self.handle,
+                rows.as_ptr(),
+                rows.len() as _,
+                cols.as_ptr(),
+                cols.len() as _,
+                &mut self.exc_info,
+            )
+        };
+
+        if handle.is_null() {
+            return Err(TableError::from(self.exc_info.as_error()).into());
+        }
+
+        Ok(Table::from_handle(
+            handle,
+            unsafe { std::mem::zeroed::<glue::ExcInfo>() },
+            TableOrigin::Derived,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TableCreateMode, TableDesc, TableDescCreateMode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn taql() {
+        let sel = Select::all()
+            .filter(col("NAME").eq("it's").or(col("X").lt(-1.5)))
+            .order_by_desc("X")
+            .offset(3);
+        assert_eq!(
+            sel.to_taql(),
+            "SELECT FROM $1 WHERE ((NAME == \"it's\") || (X < -1.5)) ORDERBY X DESC OFFSET 3"
+        );
+        assert_eq!(col("FLAG_ROW").eq(false).to_string(), "(FLAG_ROW == F)");
+        assert_eq!(col("S").ne("say \"hi\"").to_string(), "(S != 'say \"hi\"')");
+        assert_eq!(col("X").gt(1e-7).to_string(), "(X > 0.0000001)");
+        assert_eq!(col("X").le(3e20).to_string(), "(X <= 300000000000000000000.0)");
+    }
+
+    #[test]
+    fn selections() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpDouble, "TIME", None, false, false)
+            .unwrap();
+
+        for name in ["ANTENNA1", "ANTENNA2"] {
+            desc.add_scalar_column(GlueDataType::TpInt, name, None, false, false)
+                .unwrap();
+        }
+
+        desc.add_scalar_column(GlueDataType::TpString, "NAME", None, false, false)
+            .unwrap();
+        desc.add_array_column(
+            GlueDataType::TpFloat,
+            "WEIGHT",
+            None,
+            Some(&[2]),
+            true,
+            false,
+        )
+        .unwrap();
+        let mut t = Table::new(tmp_dir.path().join("t"), desc, 6, TableCreateMode::New).unwrap();
+
+        for row in 0..6u64 {
+            t.put_cell("TIME", row, &(10. - row as f64)).unwrap();
+            t.put_cell("ANTENNA1", row, &((row % 2) as i32)).unwrap();
+            t.put_cell("ANTENNA2", row, &1i32).unwrap();
+        }
+
+        let sel = Select::cols(["TIME", "WEIGHT"])
+            .filter(col("ANTENNA1").ne(col("ANTENNA2")))
+            .filter(col("TIME").gt(5))
+            .order_by("TIME")
+            .limit(2);
+
+        // Mistakes are caught before casacore sees them.
+        assert!(matches!(
+            t.select(&Select::cols(["TIME", "ANTENA1"])),
+            Err(SelectError::NoSuchColumn(name)) if name == "ANTENA1"
+        ));
+        assert!(matches!(
+            t.select(&Select::all().filter(col("NAME").lt(3))),
+            Err(SelectError::IncomparableTypes { .. })
+        ));
+        assert!(matches!(
+            t.select(&Select::all().filter(col("TIME"))),
+            Err(SelectError::NotBoolean { .. })
+        ));
+        assert!(matches!(
+            t.select(&Select::all().filter(col("WEIGHT").gt(0))),
+            Err(SelectError::ArrayColumn(_))
+        ));
+        assert!(matches!(
+            t.select(&Select::all().order_by("TIME; DROP")),
+            Err(SelectError::BadColumnName(_))
+        ));
+        assert!(matches!(
+            t.select(&Select::all().filter(col("TIME").lt(f64::NAN))),
+            Err(SelectError::BadNumber(_))
+        ));
+
+        let mut result = t.select(&sel).unwrap();
+        assert_eq!(result.column_names().unwrap(), vec!["TIME", "WEIGHT"]);
+        assert_eq!(result.get_col_as_vec::<f64>("TIME").unwrap(), vec![6., 8.]);
+        assert_eq!(result.row_numbers().unwrap(), vec![4, 2]);
+
+        let all = t.select(&Select::all()).unwrap();
+        assert_eq!(all.n_rows(), 6);
+        assert_eq!(all.n_columns(), 5);
+
+        let mut paged = t
+            .select(
+                &Select::cols(["ANTENNA1"])
+                    .order_by_desc("ANTENNA1")
+                    .order_by("TIME")
+                    .offset(2)
+                    .limit(3),
+            )
+            .unwrap();
+        assert_eq!(paged.row_numbers().unwrap(), vec![1, 4, 2]);
+        assert_eq!(t.select(&Select::all().offset(10)).unwrap().n_rows(), 0);
+
+        t.put_cell("NAME", 2, &"it's".to_owned()).unwrap();
+        let mut named = t
+            .select(&Select::all().filter(col("NAME").eq("it's").or(col("TIME").ge(10))))
+            .unwrap();
+        assert_eq!(named.row_numbers().unwrap(), vec![0, 2]);
+    }
+
+    #[cfg(feature = "system-casacore")]
+    #[test]
+    fn queries() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+
+        for name in ["ANTENNA1", "ANTENNA2"] {
+            desc.add_scalar_column(GlueDataType::TpInt, name, None, false, false)
+                .unwrap();
+        }
+
+        let mut t = Table::new(tmp_dir.path().join("t"), desc, 6, TableCreateMode::New).unwrap();
+
+        for row in 0..6u64 {
+            t.put_cell("ANTENNA1", row, &((row % 2) as i32)).unwrap();
+            t.put_cell("ANTENNA2", row, &1i32).unwrap();
+        }
+
+        // Hand-written TaQL is passed through.
+        let crosses = t
+            .query("SELECT FROM $1 WHERE ANTENNA1 != ANTENNA2")
+            .unwrap();
+        assert_eq!(crosses.n_rows(), 3);
+        assert!(t.query("SELECT FROM $1 WHERE").is_err());
+
+        // Compiled selections mean the same thing in TaQL.
+        let sel = Select::all()
+            .filter(col("ANTENNA1").lt(0.5))
+            .order_by_desc("ANTENNA1");
+        let mut via_taql = t.query(&sel.to_taql()).unwrap();
+        let mut direct = t.select(&sel).unwrap();
+        assert_eq!(
+            via_taql.row_numbers().unwrap(),
+            direct.row_numbers().unwrap()
+        );
+    }
+}
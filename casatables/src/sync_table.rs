@@ -0,0 +1,358 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Sharing a table between threads.
+//!
+//! casacore tables are not thread-safe. Beyond the objects behind a table
+//! itself, casacore keeps process-wide state, such as its cache of open
+//! tables, that is only guarded against concurrent use if casacore was built
+//! with threading support — which cannot be checked for an external
+//! installation. A [`Table`] is therefore neither [`Send`] nor [`Sync`]: it
+//! is used, and dropped, on the thread that opened it.
+//!
+//! To use a table from other threads, such as rayon workers, open it in a
+//! [`SyncTable`]. This starts a thread that owns the table for its whole
+//! life, and runs closures sent to it from any thread, one at a time.
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::mpsc::{channel, sync_channel, Sender},
+    thread::{self, JoinHandle},
+};
+
+use crate::{Table, TableError, TableOpenMode};
+
+/// A job for an [`OwnerThread`].
+enum Job<T> {
+    /// Run a closure with the state.
+    Run(Box<dyn FnOnce(&mut T) + Send>),
+
+    /// Hand over the state and stop.
+    Finish(Box<dyn FnOnce(T) + Send>),
+}
+
+/// A thread that owns a value that cannot leave it, such as a [`Table`],
+/// and runs closures on it on behalf of other threads.
+///
+/// The value is created on the thread and dropped there, so it never
+/// crosses a thread boundary. Closures run in the order in which they are
+/// submitted; a panic in one is passed on to the thread that submitted it.
+pub(crate) struct OwnerThread<T: 'static> {
+    sender: Option<Sender<Job<T>>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<T: 'static> OwnerThread<T> {
+    /// Start a thread and create its value with *init*, which runs on it.
+    pub(crate) fn spawn<E, F>(init: F) -> Result<Self, E>
+    where
+        E: Send + 'static,
+        F: FnOnce() -> Result<T, E> + Send + 'static,
+    {
+        let (sender, receiver) = channel::<Job<T>>();
+        let (ready_sender, ready) = sync_channel(1);
+
+        let worker = thread::spawn(move || {
+            let mut state = match init() {
+                Ok(state) => {
+                    let _ = ready_sender.send(Ok(()));
+                    state
+                }
+
+                Err(e) => {
+                    let _ = ready_sender.send(Err(e));
+                    return;
+                }
+            };
+
+            for job in receiver {
+                match job {
+                    Job::Run(f) => f(&mut state),
+
+                    Job::Finish(f) => {
+                        f(state);
+                        return;
+                    }
+                }
+            }
+        });
+
+        match ready.recv() {
+            Ok(Ok(())) => Ok(OwnerThread {
+                sender: Some(sender),
+                worker: Some(worker),
+            }),
+
+            Ok(Err(e)) => {
+                let _ = worker.join();
+                Err(e)
+            }
+
+            Err(_) => match worker.join() {
+                Err(payload) => panic::resume_unwind(payload),
+                Ok(()) => unreachable!("the owner thread always reports whether it started"),
+            },
+        }
+    }
+
+    /// Run *f* with the value on the owner thread, and wait for its result.
+    pub(crate) fn with<R, F>(&self, f: F) -> R
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut T) -> R + Send + 'static,
+    {
+        let (result_sender, result) = sync_channel(1);
+
+        self.submit(Job::Run(Box::new(move |state| {
+            let r = panic::catch_unwind(AssertUnwindSafe(|| f(state)));
+            let _ = result_sender.send(r);
+        })));
+
+        wait(result.recv())
+    }
+
+    /// Stop the thread, passing the value to *f* on it, and wait for the
+    /// result.
+    pub(crate) fn finish<R, F>(mut self, f: F) -> R
+    where
+        R: Send + 'static,
+        F: FnOnce(T) -> R + Send + 'static,
+    {
+        let (result_sender, result) = sync_channel(1);
+
+        self.submit(Job::Finish(Box::new(move |state| {
+            let r = panic::catch_unwind(AssertUnwindSafe(|| f(state)));
+            let _ = result_sender.send(r);
+        })));
+
+        let r = wait(result.recv());
+        self.stop();
+        r
+    }
+
+    fn submit(&self, job: Job<T>) {
+        // The thread only stops when it is told to, or when the value is
+        // handed over, after which `self` is gone.
+        self.sender
+            .as_ref()
+            .and_then(|s| s.send(job).ok())
+            .expect("the owner thread has stopped");
+    }
+
+    fn stop(&mut self) {
+        self.sender = None;
+
+        if let Some(worker) = self.worker.take() {
+            // Panics in jobs are caught and passed on, so the thread itself
+            // only panics if dropping the value does.
+            if let Err(payload) = worker.join() {
+                if !thread::panicking() {
+                    panic::resume_unwind(payload);
+                }
+            }
+        }
+    }
+}
+
+impl<T: 'static> Drop for OwnerThread<T> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Get the result of a job, re-raising any panic that it caused.
+fn wait<R>(r: Result<thread::Result<R>, std::sync::mpsc::RecvError>) -> R {
+    match r {
+        Ok(Ok(r)) => r,
+        Ok(Err(payload)) => panic::resume_unwind(payload),
+        Err(_) => panic!("the owner thread stopped without finishing a job"),
+    }
+}
+
+/// A [`Table`] that can be used from any thread.
+///
+/// The table lives on a thread of its own, from when it is opened until the
+/// `SyncTable` is dropped. [`Self::with`] sends a closure to that thread and
+/// waits for its result; closures sent from different threads run one at a
+/// time, in the order in which they arrive. This makes a `SyncTable` useful
+/// for spreading work that is dominated by processing, rather than table
+/// I/O, across threads. Work that is dominated by I/O is better done by one
+/// thread, perhaps with a [`crate::PrefetchingReader`].
+///
+/// ```no_run
+/// use rubbl_casatables::{SyncTable, TableOpenMode};
+/// use std::{sync::Arc, thread};
+///
+/// let shared = Arc::new(SyncTable::open("vis.ms", TableOpenMode::Read).unwrap());
+/// let n_rows = shared.with(|t| t.n_rows());
+///
+/// let workers: Vec<_> = (0..4u64)
+///     .map(|i| {
+///         let shared = shared.clone();
+///
+///         thread::spawn(move || {
+///             let mut sum = 0.;
+///
+///             for row in (i..n_rows).step_by(4) {
+///                 sum += shared.with(move |t| t.get_cell::<f64>("TIME", row)).unwrap();
+///             }
+///
+///             sum
+///         })
+///     })
+///     .collect();
+///
+/// let total: f64 = workers.into_iter().map(|w| w.join().unwrap()).sum();
+/// ```
+///
+/// casacore shares the underlying state of tables opened more than once
+/// from the same path, so a `SyncTable` only protects a table against
+/// concurrent use through itself. Opening the same table separately on two
+/// threads and using both copies at once is not safe.
+pub struct SyncTable {
+    owner: OwnerThread<Table>,
+}
+
+impl SyncTable {
+    /// Start a thread for a table, and get the table by calling *open* on
+    /// it.
+    ///
+    /// *open* may create the table, or open it in any way; errors that it
+    /// returns are passed back.
+    pub fn new<E, F>(open: F) -> Result<Self, E>
+    where
+        E: Send + 'static,
+        F: FnOnce() -> Result<Table, E> + Send + 'static,
+    {
+        Ok(SyncTable {
+            owner: OwnerThread::spawn(open)?,
+        })
+    }
+
+    /// Open the table at *path* on a thread of its own.
+    pub fn open<P: Into<PathBuf>>(path: P, mode: TableOpenMode) -> Result<Self, TableError> {
+        let path = path.into();
+        Self::new(move || Table::open(path, mode))
+    }
+
+    /// Run *f* with exclusive access to the table, waiting for any other
+    /// thread that is using it to finish.
+    ///
+    /// *f* runs on the table's own thread. If it panics, the panic is passed
+    /// on to the caller, and the table remains usable.
+    pub fn with<R, F>(&self, f: F) -> R
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Table) -> R + Send + 'static,
+    {
+        self.owner.with(f)
+    }
+}
+
+impl std::fmt::Debug for SyncTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SyncTable").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GlueDataType, TableCreateMode, TableDesc, TableDescCreateMode};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    // These tests are also run under ThreadSanitizer in CI, so that any data
+    // race between casacore calls made through a `SyncTable` is reported.
+
+    #[test]
+    fn shared_between_threads() {
+        const N_THREADS: u64 = 4;
+        const ROWS_PER_THREAD: u64 = 50;
+
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("t");
+        let shared = Arc::new(
+            SyncTable::new(move || {
+                let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH)?;
+                desc.add_scalar_column(GlueDataType::TpUInt, "ROW", None, false, false)?;
+                desc.add_array_column(
+                    GlueDataType::TpDouble,
+                    "VALUES",
+                    None,
+                    Some(&[3]),
+                    true,
+                    false,
+                )?;
+                Table::new(
+                    path,
+                    desc,
+                    (N_THREADS * ROWS_PER_THREAD) as usize,
+                    TableCreateMode::New,
+                )
+            })
+            .unwrap(),
+        );
+
+        let workers: Vec<_> = (0..N_THREADS)
+            .map(|i| {
+                let shared = shared.clone();
+
+                thread::spawn(move || {
+                    for row in (i..N_THREADS * ROWS_PER_THREAD).step_by(N_THREADS as usize) {
+                        shared
+                            .with(move |t| {
+                                t.put_cell("ROW", row, &(row as u32))?;
+                                t.put_cell("VALUES", row, &vec![row as f64; 3])
+                            })
+                            .unwrap();
+                    }
+
+                    let mut n = 0;
+
+                    for row in (i..N_THREADS * ROWS_PER_THREAD).step_by(N_THREADS as usize) {
+                        let values: Vec<f64> = shared
+                            .with(move |t| t.get_cell_as_vec("VALUES", row))
+                            .unwrap();
+                        assert_eq!(values, vec![row as f64; 3]);
+                        n += 1;
+                    }
+
+                    n
+                })
+            })
+            .collect();
+
+        let n: u64 = workers.into_iter().map(|w| w.join().unwrap()).sum();
+        assert_eq!(n, N_THREADS * ROWS_PER_THREAD);
+
+        let rows = shared.with(|t| t.get_col_as_vec::<u32>("ROW")).unwrap();
+        assert_eq!(
+            rows,
+            (0..(N_THREADS * ROWS_PER_THREAD) as u32).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn errors_and_panics() {
+        let tmp_dir = tempdir().unwrap();
+        assert!(SyncTable::open(tmp_dir.path().join("nope"), TableOpenMode::Read).is_err());
+
+        let path = tmp_dir.path().join("t");
+        let shared = SyncTable::new(move || {
+            let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH)?;
+            desc.add_scalar_column(GlueDataType::TpInt, "X", None, false, false)?;
+            Table::new(path, desc, 2, TableCreateMode::New)
+        })
+        .unwrap();
+
+        let caught = panic::catch_unwind(AssertUnwindSafe(|| {
+            shared.with(|_| panic!("oops"));
+        }));
+        assert!(caught.is_err());
+
+        shared.with(|t| t.put_cell("X", 1, &5i32)).unwrap();
+        assert_eq!(shared.with(|t| t.get_cell::<i32>("X", 1)).unwrap(), 5);
+    }
+}
@@ -0,0 +1,857 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Converting small tables to and from JSON.
+//!
+//! Subtables such as a Measurement Set’s `ANTENNA`, `FIELD`, and `SOURCE`
+//! tables hold a handful of rows that are worth reading, editing by hand, and
+//! keeping under version control, especially when setting up simulations.
+//! [`Table::to_json`] writes a table’s schema, keywords, and contents as a
+//! JSON document, and [`Table::from_json`] creates a table from one.
+
+use std::path::Path;
+use thiserror::Error;
+
+use crate::{
+    json::{JsonSyntaxError, JsonValue},
+    CasaDataType, CasaScalarData, CasacoreError, ColumnShapeInfo, Complex, GlueDataType, Table,
+    TableCreateMode, TableDesc, TableDescCreateMode, TableError, TableRecord,
+};
+
+/// An error that can occur when converting a table to or from JSON.
+#[derive(Error, Debug)]
+pub enum TableJsonError {
+    /// An error occurred while reading or writing the table.
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// casacore raised an exception.
+    #[error(transparent)]
+    Casacore(#[from] CasacoreError),
+
+    /// The document is not valid JSON.
+    #[error(transparent)]
+    Syntax(#[from] JsonSyntaxError),
+
+    /// The document is valid JSON, but does not describe a table.
+    #[error("invalid table JSON: {0}")]
+    Format(String),
+
+    /// The table has more rows than the export limit.
+    #[error("the table has {0} rows, more than the limit of {1} for JSON export")]
+    TooManyRows(u64, u64),
+}
+
+/// Options for [`Table::to_json`].
+#[derive(Clone, Debug)]
+pub struct JsonExportOptions {
+    /// The largest number of rows to export. JSON is meant for small tables,
+    /// so exporting a bigger table is an error. The default is 10,000.
+    pub max_rows: u64,
+
+    /// If true, the default, table and column keywords are included.
+    pub keywords: bool,
+}
+
+impl Default for JsonExportOptions {
+    fn default() -> Self {
+        JsonExportOptions {
+            max_rows: 10_000,
+            keywords: true,
+        }
+    }
+}
+
+const TYPE_NAMES: &[(GlueDataType, &str)] = &[
+    (GlueDataType::TpBool, "Bool"),
+    (GlueDataType::TpChar, "Char"),
+    (GlueDataType::TpUChar, "UChar"),
+    (GlueDataType::TpShort, "Short"),
+    (GlueDataType::TpUShort, "UShort"),
+    (GlueDataType::TpInt, "Int"),
+    (GlueDataType::TpUInt, "UInt"),
+    (GlueDataType::TpInt64, "Int64"),
+    (GlueDataType::TpFloat, "Float"),
+    (GlueDataType::TpDouble, "Double"),
+    (GlueDataType::TpComplex, "Complex"),
+    (GlueDataType::TpDComplex, "DComplex"),
+    (GlueDataType::TpString, "String"),
+    (GlueDataType::TpRecord, "Record"),
+    (GlueDataType::TpArrayBool, "Bool[]"),
+    (GlueDataType::TpArrayChar, "Char[]"),
+    (GlueDataType::TpArrayUChar, "UChar[]"),
+    (GlueDataType::TpArrayShort, "Short[]"),
+    (GlueDataType::TpArrayUShort, "UShort[]"),
+    (GlueDataType::TpArrayInt, "Int[]"),
+    (GlueDataType::TpArrayUInt, "UInt[]"),
+    (GlueDataType::TpArrayInt64, "Int64[]"),
+    (GlueDataType::TpArrayFloat, "Float[]"),
+    (GlueDataType::TpArrayDouble, "Double[]"),
+    (GlueDataType::TpArrayComplex, "Complex[]"),
+    (GlueDataType::TpArrayDComplex, "DComplex[]"),
+    (GlueDataType::TpArrayString, "String[]"),
+];
+
+fn type_name(data_type: GlueDataType) -> Option<&'static str> {
+    TYPE_NAMES
+        .iter()
+        .find(|(t, _)| *t == data_type)
+        .map(|(_, n)| *n)
+}
+
+fn type_from_name(name: &str) -> Result<GlueDataType, TableJsonError> {
+    TYPE_NAMES
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(t, _)| *t)
+        .ok_or_else(|| format_error(format!("unknown type \"{}\"", name)))
+}
+
+fn format_error<S: Into<String>>(message: S) -> TableJsonError {
+    TableJsonError::Format(message.into())
+}
+
+/// Call a function generic over the element type corresponding to a
+/// [`GlueDataType`], or evaluate *other* for unsupported types.
+macro_rules! dispatch {
+    ($data_type:expr, $func:ident($($arg:expr),*), $other:expr) => {
+        match $data_type {
+            GlueDataType::TpBool => $func::<bool>($($arg),*),
+            GlueDataType::TpChar => $func::<i8>($($arg),*),
+            GlueDataType::TpUChar => $func::<u8>($($arg),*),
+            GlueDataType::TpShort => $func::<i16>($($arg),*),
+            GlueDataType::TpUShort => $func::<u16>($($arg),*),
+            GlueDataType::TpInt => $func::<i32>($($arg),*),
+            GlueDataType::TpUInt => $func::<u32>($($arg),*),
+            GlueDataType::TpInt64 => $func::<i64>($($arg),*),
+            GlueDataType::TpFloat => $func::<f32>($($arg),*),
+            GlueDataType::TpDouble => $func::<f64>($($arg),*),
+            GlueDataType::TpComplex => $func::<Complex<f32>>($($arg),*),
+            GlueDataType::TpDComplex => $func::<Complex<f64>>($($arg),*),
+            GlueDataType::TpString => $func::<String>($($arg),*),
+            _ => $other,
+        }
+    };
+}
+
+/// An element type that can be converted to and from JSON.
+trait JsonElement: CasaScalarData + Sized {
+    /// Whether values of this type are written as JSON arrays.
+    const IS_ARRAY: bool = false;
+
+    fn to_json(&self) -> JsonValue;
+
+    fn from_json(value: &JsonValue) -> Option<Self>;
+
+    /// Write an array cell.
+    fn put_array(
+        table: &mut Table,
+        col_name: &str,
+        row: u64,
+        shape: &[usize],
+        data: Vec<Self>,
+    ) -> Result<(), TableJsonError> {
+        Ok(table.put_cell_shaped(col_name, row, shape, &data)?)
+    }
+}
+
+impl JsonElement for bool {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Bool(*self)
+    }
+
+    fn from_json(value: &JsonValue) -> Option<Self> {
+        match value {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_json_int {
+    ($($t:ty),*) => {
+        $(
+            impl JsonElement for $t {
+                fn to_json(&self) -> JsonValue {
+                    JsonValue::number(self)
+                }
+
+                fn from_json(value: &JsonValue) -> Option<Self> {
+                    value.parse_number()
+                }
+            }
+        )*
+    };
+}
+
+impl_json_int! { i8, u8, i16, u16, i32, u32, i64 }
+
+macro_rules! impl_json_float {
+    ($($t:ty),*) => {
+        $(
+            impl JsonElement for $t {
+                fn to_json(&self) -> JsonValue {
+                    if self.is_finite() {
+                        JsonValue::Number(format!("{:?}", self))
+                    } else if self.is_nan() {
+                        JsonValue::String("NaN".to_owned())
+                    } else if *self > 0. {
+                        JsonValue::String("Infinity".to_owned())
+                    } else {
+                        JsonValue::String("-Infinity".to_owned())
+                    }
+                }
+
+                fn from_json(value: &JsonValue) -> Option<Self> {
+                    match value.as_str() {
+                        Some("NaN") => Some(<$t>::NAN),
+                        Some("Infinity") => Some(<$t>::INFINITY),
+                        Some("-Infinity") => Some(<$t>::NEG_INFINITY),
+                        _ => value.parse_number(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_json_float! { f32, f64 }
+
+impl<T: JsonElement + Copy> JsonElement for Complex<T>
+where
+    Complex<T>: CasaScalarData,
+{
+    const IS_ARRAY: bool = true;
+
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(vec![self.re.to_json(), self.im.to_json()])
+    }
+
+    fn from_json(value: &JsonValue) -> Option<Self> {
+        match value.as_array()? {
+            [re, im] => Some(Complex::new(T::from_json(re)?, T::from_json(im)?)),
+            _ => None,
+        }
+    }
+}
+
+impl JsonElement for String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.clone())
+    }
+
+    fn from_json(value: &JsonValue) -> Option<Self> {
+        value.as_str().map(|s| s.to_owned())
+    }
+
+    fn put_array(
+        table: &mut Table,
+        col_name: &str,
+        row: u64,
+        shape: &[usize],
+        data: Vec<Self>,
+    ) -> Result<(), TableJsonError> {
+        if shape.len() != 1 {
+            return Err(format_error(format!(
+                "cell {} of column \"{}\": string array cells must be one-dimensional",
+                row, col_name
+            )));
+        }
+
+        Ok(table.put_cell(col_name, row, &data)?)
+    }
+}
+
+/// Convert a flat C-order buffer into nested JSON arrays.
+fn nest<T: JsonElement>(shape: &[usize], data: &[T]) -> JsonValue {
+    match shape {
+        [] => data[0].to_json(),
+        [_] => JsonValue::Array(data.iter().map(|v| v.to_json()).collect()),
+        [n, rest @ ..] => {
+            let step: usize = rest.iter().product();
+            JsonValue::Array(
+                (0..*n)
+                    .map(|i| nest(rest, &data[i * step..(i + 1) * step]))
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// Convert nested JSON arrays into a C-order shape and flat buffer.
+fn flatten<T: JsonElement>(value: &JsonValue) -> Option<(Vec<usize>, Vec<T>)> {
+    fn is_element<T: JsonElement>(value: &JsonValue) -> bool {
+        match value.as_array() {
+            None => true,
+            Some(items) => {
+                T::IS_ARRAY && !items.is_empty() && items.iter().all(|i| i.as_array().is_none())
+            }
+        }
+    }
+
+    fn fill<T: JsonElement>(value: &JsonValue, shape: &[usize], out: &mut Vec<T>) -> bool {
+        match shape {
+            [] => match T::from_json(value) {
+                Some(v) => {
+                    out.push(v);
+                    true
+                }
+                None => false,
+            },
+
+            [n, rest @ ..] => match value.as_array() {
+                Some(items) if items.len() == *n && !is_element::<T>(value) => {
+                    items.iter().all(|i| fill(i, rest, out))
+                }
+                _ => false,
+            },
+        }
+    }
+
+    let mut shape = Vec::new();
+    let mut v = value;
+
+    while !is_element::<T>(v) {
+        let items = v.as_array().unwrap();
+        shape.push(items.len());
+
+        match items.first() {
+            Some(first) => v = first,
+            None => break,
+        }
+    }
+
+    let mut out = Vec::new();
+
+    if shape.is_empty() || !fill(value, &shape, &mut out) {
+        return None;
+    }
+
+    Some((shape, out))
+}
+
+fn cell_to_json<T: JsonElement>(
+    table: &mut Table,
+    col_name: &str,
+    row: u64,
+    is_scalar: bool,
+) -> Result<JsonValue, TableJsonError> {
+    if is_scalar {
+        return Ok(table.get_cell::<T>(col_name, row)?.to_json());
+    }
+
+    let shape = table.get_cell_shape(col_name, row)?;
+
+    if shape.is_empty() {
+        return Ok(JsonValue::Null);
+    }
+
+    let data = table.get_cell_as_vec::<T>(col_name, row)?;
+    Ok(nest(&shape, &data))
+}
+
+fn cell_from_json<T: JsonElement>(
+    table: &mut Table,
+    col_name: &str,
+    row: u64,
+    shape_info: &ColumnShapeInfo,
+    value: &JsonValue,
+) -> Result<(), TableJsonError> {
+    let bad_value = || {
+        format_error(format!(
+            "bad value for cell {} of column \"{}\"",
+            row, col_name
+        ))
+    };
+
+    if let ColumnShapeInfo::Scalar = shape_info {
+        let v = T::from_json(value).ok_or_else(bad_value)?;
+        return Ok(table.put_cell(col_name, row, &v)?);
+    }
+
+    let (shape, data) = flatten::<T>(value).ok_or_else(bad_value)?;
+
+    if let Some(fixed) = shape_info.fixed_shape() {
+        if fixed != &shape[..] {
+            return Err(bad_value());
+        }
+    }
+
+    T::put_array(table, col_name, row, &shape, data)
+}
+
+fn keyword_to_json<T: JsonElement>(
+    record: &mut TableRecord,
+    name: &str,
+    is_array: bool,
+) -> Result<JsonValue, TableJsonError>
+where
+    Vec<T>: CasaDataType,
+{
+    Ok(if is_array {
+        JsonValue::Array(
+            record
+                .get_field::<Vec<T>>(name)?
+                .iter()
+                .map(|v| v.to_json())
+                .collect(),
+        )
+    } else {
+        record.get_field::<T>(name)?.to_json()
+    })
+}
+
+fn record_to_json(record: &mut TableRecord) -> Result<JsonValue, TableJsonError> {
+    let mut members = Vec::new();
+
+    for (name, data_type, _) in record.keyword_names_types_reprs()? {
+        let value = if data_type == GlueDataType::TpRecord {
+            record_to_json(&mut record.get_field::<TableRecord>(&name)?)?
+        } else if type_name(data_type).is_none() {
+            // Subtables and other exotica.
+            continue;
+        } else {
+            dispatch!(
+                data_type.element_type(),
+                keyword_to_json(record, &name, data_type.is_array()),
+                unreachable!()
+            )?
+        };
+
+        members.push((
+            name,
+            JsonValue::Object(vec![
+                (
+                    "type".to_owned(),
+                    JsonValue::String(type_name(data_type).unwrap().to_owned()),
+                ),
+                ("value".to_owned(), value),
+            ]),
+        ));
+    }
+
+    Ok(JsonValue::Object(members))
+}
+
+/// Where to put keywords read from JSON.
+enum KeywordTarget<'a> {
+    Table(&'a mut Table),
+    Column(&'a mut Table, &'a str),
+    Record(&'a mut TableRecord),
+}
+
+impl<'a> KeywordTarget<'a> {
+    fn put<T: CasaDataType>(&mut self, name: &str, value: &T) -> Result<(), CasacoreError> {
+        match self {
+            KeywordTarget::Table(t) => t.put_keyword(name, value),
+            KeywordTarget::Column(t, col) => t.put_column_keyword(col, name, value),
+            KeywordTarget::Record(r) => r.put_field(name, value),
+        }
+    }
+}
+
+fn keyword_from_json<T: JsonElement>(
+    target: &mut KeywordTarget,
+    name: &str,
+    is_array: bool,
+    value: &JsonValue,
+) -> Result<(), TableJsonError>
+where
+    Vec<T>: CasaDataType,
+{
+    let bad_value = || format_error(format!("bad value for keyword \"{}\"", name));
+
+    if is_array {
+        let values = value
+            .as_array()
+            .ok_or_else(bad_value)?
+            .iter()
+            .map(T::from_json)
+            .collect::<Option<Vec<T>>>()
+            .ok_or_else(bad_value)?;
+        target.put(name, &values)?;
+    } else {
+        target.put(name, &T::from_json(value).ok_or_else(bad_value)?)?;
+    }
+
+    Ok(())
+}
+
+fn keywords_from_json(
+    target: &mut KeywordTarget,
+    keywords: &JsonValue,
+) -> Result<(), TableJsonError> {
+    let members = keywords
+        .as_object()
+        .ok_or_else(|| format_error("keywords must be an object"))?;
+
+    for (name, entry) in members {
+        let data_type = type_from_name(
+            entry
+                .get("type")
+                .and_then(|t| t.as_str())
+                .ok_or_else(|| format_error(format!("keyword \"{}\" has no type", name)))?,
+        )?;
+        let value = entry
+            .get("value")
+            .ok_or_else(|| format_error(format!("keyword \"{}\" has no value", name)))?;
+
+        if data_type == GlueDataType::TpRecord {
+            let mut record = TableRecord::new()?;
+            keywords_from_json(&mut KeywordTarget::Record(&mut record), value)?;
+            target.put(name, &record)?;
+        } else {
+            dispatch!(
+                data_type.element_type(),
+                keyword_from_json(target, name, data_type.is_array(), value),
+                unreachable!()
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A column described in a JSON document.
+struct ColumnPlan<'a> {
+    name: &'a str,
+    data_type: GlueDataType,
+    shape: ColumnShapeInfo,
+    keywords: Option<&'a JsonValue>,
+}
+
+fn parse_column(value: &JsonValue) -> Result<ColumnPlan<'_>, TableJsonError> {
+    let name = value
+        .get("name")
+        .and_then(|n| n.as_str())
+        .ok_or_else(|| format_error("a column has no name"))?;
+    let data_type = type_from_name(
+        value
+            .get("type")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| format_error(format!("column \"{}\" has no type", name)))?,
+    )?;
+
+    if data_type.is_array() || data_type == GlueDataType::TpRecord {
+        return Err(format_error(format!(
+            "column \"{}\" must have a scalar element type",
+            name
+        )));
+    }
+
+    let shape = match value.get("shape") {
+        None => ColumnShapeInfo::Scalar,
+        Some(JsonValue::String(s)) if s == "variable" => ColumnShapeInfo::Variable { ndim: None },
+        Some(JsonValue::Array(dims)) if !dims.is_empty() => ColumnShapeInfo::Fixed(
+            dims.iter()
+                .map(|d| d.parse_number())
+                .collect::<Option<Vec<usize>>>()
+                .ok_or_else(|| format_error(format!("column \"{}\" has a bad shape", name)))?,
+        ),
+        Some(_) => return Err(format_error(format!("column \"{}\" has a bad shape", name))),
+    };
+
+    Ok(ColumnPlan {
+        name,
+        data_type,
+        shape,
+        keywords: value.get("keywords"),
+    })
+}
+
+impl Table {
+    /// Write the schema, keywords, and contents of the table as a JSON
+    /// document.
+    ///
+    /// The document is an object with three members. `keywords` maps each table
+    /// keyword name to an object giving its `type` and `value`. `columns` lists
+    /// the columns, each with a `name`, an element `type`, a `shape` — an array
+    /// for fixed-shape columns, `"variable"` for other array columns, and absent
+    /// for scalar columns — and optionally `keywords`. `rows` lists the rows, as
+    /// objects mapping column names to cell values:
+    ///
+    /// ```json
+    /// {
+    ///   "keywords": {
+    ///     "TELESCOPE": {"type": "String", "value": "VLA"}
+    ///   },
+    ///   "columns": [
+    ///     {"name": "NAME", "type": "String"},
+    ///     {"name": "POSITION", "type": "Double", "shape": [3]}
+    ///   ],
+    ///   "rows": [
+    ///     {"NAME": "ea01", "POSITION": [-1601185.4, -5041977.5, 3554875.9]}
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// Types are named as in casacore: `Bool`, `Char`, `UChar`, `Short`,
+    /// `UShort`, `Int`, `UInt`, `Int64`, `Float`, `Double`, `Complex`,
+    /// `DComplex`, and `String`, with `[]` appended for array-valued keywords,
+    /// and `Record` for keywords holding sub-records. Array cells are nested
+    /// arrays in C order, complex values are `[re, im]` pairs, non-finite
+    /// floating-point values are the strings `"NaN"`, `"Infinity"`, and
+    /// `"-Infinity"`, and cells that have no value are `null`. Keywords that
+    /// refer to subtables are not exported.
+    ///
+    /// This is meant for small tables: if the table has more than
+    /// [`JsonExportOptions::max_rows`] rows, an error is returned.
+    pub fn to_json(&mut self, options: &JsonExportOptions) -> Result<String, TableJsonError> {
+        let n_rows = self.n_rows();
+
+        if n_rows > options.max_rows {
+            return Err(TableJsonError::TooManyRows(n_rows, options.max_rows));
+        }
+
+        let mut doc = Vec::new();
+
+        if options.keywords {
+            let keywords = record_to_json(&mut self.get_keyword_record()?)?;
+            doc.push(("keywords".to_owned(), keywords));
+        }
+
+        let names = self.column_names()?;
+        let mut columns = Vec::with_capacity(names.len());
+        let mut plans = Vec::with_capacity(names.len());
+
+        for name in &names {
+            let data_type = self.get_col_desc(name)?.data_type().element_type();
+            let shape = self.column_shape(name)?;
+
+            let type_name = match type_name(data_type) {
+                Some(n) if data_type != GlueDataType::TpRecord => n,
+                _ => return Err(TableError::UnsupportedDataType(name.clone(), data_type).into()),
+            };
+
+            let mut members = vec![
+                ("name".to_owned(), JsonValue::String(name.clone())),
+                ("type".to_owned(), JsonValue::String(type_name.to_owned())),
+            ];
+
+            match shape {
+                ColumnShapeInfo::Scalar => {}
+                ColumnShapeInfo::Fixed(ref dims) => members.push((
+                    "shape".to_owned(),
+                    JsonValue::Array(dims.iter().map(JsonValue::number).collect()),
+                )),
+                ColumnShapeInfo::Variable { .. } => {
+                    members.push(("shape".to_owned(), JsonValue::String("variable".to_owned())))
+                }
+            }
+
+            if options.keywords {
+                let keywords = record_to_json(&mut self.get_column_keyword_record(name)?)?;
+
+                if keywords.as_object().is_some_and(|k| !k.is_empty()) {
+                    members.push(("keywords".to_owned(), keywords));
+                }
+            }
+
+            columns.push(JsonValue::Object(members));
+            plans.push((data_type, shape == ColumnShapeInfo::Scalar));
+        }
+
+        let mut rows = Vec::with_capacity(n_rows as usize);
+
+        for row in 0..n_rows {
+            let mut cells = Vec::with_capacity(names.len());
+
+            for (name, (data_type, is_scalar)) in names.iter().zip(plans.iter()) {
+                let value = dispatch!(
+                    *data_type,
+                    cell_to_json(self, name, row, *is_scalar),
+                    unreachable!()
+                )?;
+                cells.push((name.clone(), value));
+            }
+
+            rows.push(JsonValue::Object(cells));
+        }
+
+        doc.push(("columns".to_owned(), JsonValue::Array(columns)));
+        doc.push(("rows".to_owned(), JsonValue::Array(rows)));
+        Ok(JsonValue::Object(doc).to_pretty_string())
+    }
+
+    /// Create a table at *path* from a JSON document.
+    ///
+    /// The document has the format written by [`Self::to_json`]. Cells that
+    /// are `null` or missing from a row are left undefined.
+    ///
+    /// ```rust
+    /// use rubbl_casatables::{Table, TableCreateMode};
+    /// use tempfile::tempdir;
+    ///
+    /// let json = r#"{
+    ///   "columns": [{"name": "NAME", "type": "String"},
+    ///               {"name": "DISH_DIAMETER", "type": "Double"}],
+    ///   "rows": [{"NAME": "A0", "DISH_DIAMETER": 12.0},
+    ///            {"NAME": "A1", "DISH_DIAMETER": 12.0}]
+    /// }"#;
+    ///
+    /// let tmp_dir = tempdir().unwrap();
+    /// let mut t = Table::from_json(tmp_dir.path().join("ANTENNA"), json, TableCreateMode::New).unwrap();
+    /// assert_eq!(t.n_rows(), 2);
+    /// assert_eq!(t.get_cell::<String>("NAME", 1).unwrap(), "A1");
+    /// ```
+    pub fn from_json<P: AsRef<Path>>(
+        path: P,
+        json: &str,
+        mode: TableCreateMode,
+    ) -> Result<Table, TableJsonError> {
+        let doc = JsonValue::parse(json)?;
+
+        let columns = doc
+            .get("columns")
+            .and_then(|c| c.as_array())
+            .ok_or_else(|| format_error("missing \"columns\" array"))?
+            .iter()
+            .map(parse_column)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let rows = match doc.get("rows") {
+            None => &[][..],
+            Some(rows) => rows
+                .as_array()
+                .ok_or_else(|| format_error("\"rows\" must be an array"))?,
+        };
+
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH)?;
+
+        for col in &columns {
+            match col.shape {
+                ColumnShapeInfo::Scalar => {
+                    desc.add_scalar_column(col.data_type, col.name, None, true, false)?
+                }
+                ColumnShapeInfo::Fixed(ref dims) => {
+                    let dims: Vec<u64> = dims.iter().map(|d| *d as u64).collect();
+                    desc.add_array_column(col.data_type, col.name, None, Some(&dims), true, false)?
+                }
+                ColumnShapeInfo::Variable { .. } => {
+                    desc.add_array_column(col.data_type, col.name, None, None, false, false)?
+                }
+            }
+        }
+
+        let mut table = Table::new(path, desc, rows.len(), mode)?;
+
+        if let Some(keywords) = doc.get("keywords") {
+            keywords_from_json(&mut KeywordTarget::Table(&mut table), keywords)?;
+        }
+
+        for col in &columns {
+            if let Some(keywords) = col.keywords {
+                keywords_from_json(&mut KeywordTarget::Column(&mut table, col.name), keywords)?;
+            }
+        }
+
+        for (i, row) in rows.iter().enumerate() {
+            if row.as_object().is_none() {
+                return Err(format_error(format!("row {} is not an object", i)));
+            }
+
+            for col in &columns {
+                let value = match row.get(col.name) {
+                    None | Some(JsonValue::Null) => continue,
+                    Some(v) => v,
+                };
+
+                dispatch!(
+                    col.data_type,
+                    cell_from_json(&mut table, col.name, i as u64, &col.shape, value),
+                    Err(TableError::UnsupportedDataType(col.name.to_owned(), col.data_type).into())
+                )?;
+            }
+        }
+
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn json_round_trip() {
+        let tmp_dir = tempdir().unwrap();
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH).unwrap();
+        desc.add_scalar_column(GlueDataType::TpString, "NAME", None, true, false)
+            .unwrap();
+        desc.add_array_column(
+            GlueDataType::TpDouble,
+            "POSITION",
+            None,
+            Some(&[3]),
+            true,
+            false,
+        )
+        .unwrap();
+        desc.add_array_column(GlueDataType::TpComplex, "GAIN", None, None, false, false)
+            .unwrap();
+        desc.add_scalar_column(GlueDataType::TpFloat, "X", None, true, false)
+            .unwrap();
+        let mut t =
+            Table::new(tmp_dir.path().join("a.tab"), desc, 2, TableCreateMode::New).unwrap();
+
+        t.put_cell("NAME", 0, &"ea01".to_owned()).unwrap();
+        t.put_cell("NAME", 1, &"ea\"02".to_owned()).unwrap();
+        t.put_cell("POSITION", 0, &vec![1.5, -2., 3e10]).unwrap();
+        t.put_cell("POSITION", 1, &vec![0.1, 0.2, 0.3]).unwrap();
+        let gain =
+            ndarray::Array2::from_shape_fn((2, 3), |(i, j)| Complex::new(i as f32, j as f32 - 0.5));
+        t.put_cell("GAIN", 0, &gain).unwrap();
+        t.put_cell("X", 0, &f32::NAN).unwrap();
+        t.put_cell("X", 1, &f32::NEG_INFINITY).unwrap();
+        t.put_keyword("TELESCOPE", &"VLA".to_owned()).unwrap();
+        t.put_keyword("IDS", &vec![1i32, 2, 3]).unwrap();
+        t.put_column_keyword("POSITION", "QuantumUnits", &vec!["m".to_owned(); 3])
+            .unwrap();
+
+        let json = t.to_json(&JsonExportOptions::default()).unwrap();
+        let mut u =
+            Table::from_json(tmp_dir.path().join("b.tab"), &json, TableCreateMode::New).unwrap();
+
+        assert_eq!(u.n_rows(), 2);
+        assert_eq!(u.get_cell::<String>("NAME", 1).unwrap(), "ea\"02");
+        assert_eq!(
+            u.get_cell_as_vec::<f64>("POSITION", 0).unwrap(),
+            vec![1.5, -2., 3e10]
+        );
+        assert_eq!(u.get_cell_shape("GAIN", 0).unwrap(), vec![2, 3]);
+        assert_eq!(
+            u.get_cell_as_vec::<Complex<f32>>("GAIN", 0).unwrap()[4],
+            Complex::new(1., 0.5)
+        );
+        assert!(u.get_cell_shape("GAIN", 1).unwrap().is_empty());
+        assert!(u.get_cell::<f32>("X", 0).unwrap().is_nan());
+        assert_eq!(u.get_cell::<f32>("X", 1).unwrap(), f32::NEG_INFINITY);
+        assert_eq!(
+            u.get_keyword_record()
+                .unwrap()
+                .get_field::<Vec<i32>>("IDS")
+                .unwrap(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            u.get_column_keyword_record("POSITION")
+                .unwrap()
+                .get_field::<Vec<String>>("QuantumUnits")
+                .unwrap(),
+            vec!["m".to_owned(); 3]
+        );
+
+        // A second export is identical to the first.
+        assert_eq!(u.to_json(&JsonExportOptions::default()).unwrap(), json);
+
+        let options = JsonExportOptions {
+            max_rows: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            t.to_json(&options),
+            Err(TableJsonError::TooManyRows(2, 1))
+        ));
+    }
+}
@@ -0,0 +1,516 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Utilities for testing code that works with CASA tables.
+//!
+//! These are intended for use in the test suites of this crate and of
+//! downstream crates, so that they can exercise their code on realistic data
+//! sets without needing to bundle any.
+
+use ndarray::Array2;
+use rubbl_core::{array_layout::AntennaLayout, stokes::Stokes};
+use std::{collections::BTreeMap, fmt::Debug, fs, path::Path};
+
+use crate::{
+    ms::{
+        simulate::{fill_subtables, SubtableContents},
+        SpectralWindow,
+    },
+    CasaDataType, Complex, GlueDataType, Table, TableCreateMode, TableDesc, TableDescCreateMode,
+    TableError, TableOpenMode,
+};
+
+/// The parameters of a synthetic Measurement Set created by [`synthetic_ms`].
+#[derive(Clone, Debug)]
+pub struct SyntheticMsSpec {
+    /// The number of antennas.
+    pub n_ants: usize,
+
+    /// The number of frequency channels.
+    pub n_chans: usize,
+
+    /// The number of polarization products: 1 (XX only), 2 (XX and YY), or 4
+    /// (XX, XY, YX, and YY).
+    pub n_pols: usize,
+
+    /// The number of integrations.
+    pub n_timesteps: usize,
+
+    /// Whether to include autocorrelation baselines.
+    pub autocorrelations: bool,
+
+    /// The start of the first integration, as an MJD in seconds.
+    pub start_time: f64,
+
+    /// The duration of each integration, in seconds.
+    pub integration_time: f64,
+
+    /// The center frequency of the first channel, in Hz.
+    pub start_freq: f64,
+
+    /// The width of each channel, in Hz.
+    pub chan_width: f64,
+}
+
+impl Default for SyntheticMsSpec {
+    fn default() -> Self {
+        SyntheticMsSpec {
+            n_ants: 4,
+            n_chans: 16,
+            n_pols: 4,
+            n_timesteps: 3,
+            autocorrelations: true,
+            start_time: 5.0e9,
+            integration_time: 10.,
+            start_freq: 150e6,
+            chan_width: 40e3,
+        }
+    }
+}
+
+impl SyntheticMsSpec {
+    /// Get the number of baselines in each integration.
+    pub fn n_baselines(&self) -> usize {
+        if self.autocorrelations {
+            self.n_ants * (self.n_ants + 1) / 2
+        } else {
+            self.n_ants * self.n_ants.saturating_sub(1) / 2
+        }
+    }
+
+    /// Get the number of rows in the main table.
+    pub fn n_rows(&self) -> usize {
+        self.n_baselines() * self.n_timesteps
+    }
+
+    /// Get the expected visibility in the `DATA` column for the specified row,
+    /// channel, and polarization.
+    ///
+    /// The values are chosen so that every visibility in the data set is
+    /// distinct, which makes it straightforward to check that data have been
+    /// read back in the expected order.
+    pub fn expected_vis(&self, row: usize, chan: usize, pol: usize) -> Complex<f32> {
+        Complex::new(row as f32, (chan * self.n_pols + pol) as f32)
+    }
+}
+
+/// Create a small but complete Measurement Set with synthetic contents.
+///
+/// The data set has a single field, spectral window, and polarization setup,
+/// and contains the standard sub-tables defined in [`crate::ms::schema`]. The
+/// main table rows are ordered by time and then by baseline, with `ANTENNA1 <=
+/// ANTENNA2`. The `DATA` column is filled according to
+/// [`SyntheticMsSpec::expected_vis`]; weights are unity and nothing is flagged.
+///
+/// Returns [`TableError::InvalidArgument`] if `spec.n_pols` is not 1, 2, or 4.
+pub fn synthetic_ms<P: AsRef<Path>>(path: P, spec: &SyntheticMsSpec) -> Result<Table, TableError> {
+    let corr_types: &[Stokes] = match spec.n_pols {
+        1 => &[Stokes::XX],
+        2 => &[Stokes::XX, Stokes::YY],
+        4 => &[Stokes::XX, Stokes::XY, Stokes::YX, Stokes::YY],
+        n => {
+            return Err(TableError::InvalidArgument(format!(
+                "unsupported number of polarizations for synthetic MS: {}",
+                n
+            )))
+        }
+    };
+
+    let path = path.as_ref();
+    let mut main = Table::create_with_default_subtables(path, spec.n_rows())?;
+    let positions = antenna_positions(spec.n_ants);
+    let mut layout = AntennaLayout::new();
+
+    for (i, pos) in positions.iter().enumerate() {
+        layout.push(format!("ANT{:03}", i), *pos, 4.);
+    }
+
+    let mut spw = SpectralWindow::uniform(spec.n_chans, spec.start_freq, spec.chan_width);
+    spw.name = "SPW0".to_owned();
+
+    fill_subtables(
+        path,
+        &SubtableContents {
+            layout: &layout,
+            telescope_name: "SYNTHETIC",
+            field_name: "synthetic",
+            phase_dir: [0., -0.5],
+            spw: &spw,
+            corr_types,
+            start_time: spec.start_time,
+            end_time: spec.start_time + spec.n_timesteps as f64 * spec.integration_time,
+        },
+    )?;
+
+    // Now the main table.
+
+    let ones = vec![1f32; spec.n_pols];
+    let flags = Array2::from_elem((spec.n_chans, spec.n_pols), false);
+    let mut row = 0;
+
+    for t in 0..spec.n_timesteps {
+        let time = spec.start_time + (t as f64 + 0.5) * spec.integration_time;
+
+        for ant1 in 0..spec.n_ants {
+            for ant2 in ant1..spec.n_ants {
+                if ant1 == ant2 && !spec.autocorrelations {
+                    continue;
+                }
+
+                let uvw: Vec<f64> = (0..3)
+                    .map(|i| positions[ant2][i] - positions[ant1][i])
+                    .collect();
+                let data = Array2::from_shape_fn((spec.n_chans, spec.n_pols), |(c, p)| {
+                    spec.expected_vis(row, c, p)
+                });
+                let r = row as u64;
+
+                main.put_cell("TIME", r, &time)?;
+                main.put_cell("TIME_CENTROID", r, &time)?;
+                main.put_cell("INTERVAL", r, &spec.integration_time)?;
+                main.put_cell("EXPOSURE", r, &spec.integration_time)?;
+                main.put_cell("ANTENNA1", r, &(ant1 as i32))?;
+                main.put_cell("ANTENNA2", r, &(ant2 as i32))?;
+                main.put_cell("FEED1", r, &0i32)?;
+                main.put_cell("FEED2", r, &0i32)?;
+                main.put_cell("DATA_DESC_ID", r, &0i32)?;
+                main.put_cell("PROCESSOR_ID", r, &0i32)?;
+                main.put_cell("FIELD_ID", r, &0i32)?;
+                main.put_cell("SCAN_NUMBER", r, &1i32)?;
+                main.put_cell("ARRAY_ID", r, &0i32)?;
+                main.put_cell("OBSERVATION_ID", r, &0i32)?;
+                main.put_cell("STATE_ID", r, &-1i32)?;
+                main.put_cell("UVW", r, &uvw)?;
+                main.put_cell("SIGMA", r, &ones)?;
+                main.put_cell("WEIGHT", r, &ones)?;
+                main.put_cell("FLAG", r, &flags)?;
+                main.put_cell("FLAG_ROW", r, &false)?;
+                main.put_cell("DATA", r, &data)?;
+                row += 1;
+            }
+        }
+    }
+
+    Ok(main)
+}
+
+/// Lay out antennas along a line running east from a point on the equator,
+/// in ITRF coordinates.
+fn antenna_positions(n_ants: usize) -> Vec<[f64; 3]> {
+    (0..n_ants)
+        .map(|i| [6378137., 10. * i as f64, 0.])
+        .collect()
+}
+
+/// Check that a value survives being written to a table and read back.
+///
+/// Two identical tables are created inside the directory `dir`, each with a
+/// column holding `value` in every row. For array-valued types there are two
+/// columns, one with a fixed shape matching `value` and one with a variable
+/// shape. The value is also stored as a table keyword, except that casacore
+/// records cannot hold `Char` or `uShort` values, so for those types the
+/// keyword must instead be rejected with an error. Each table is then closed
+/// and reopened, and every copy of the value is read back and compared to the
+/// original. Finally, the files making up the two tables are compared
+/// byte-for-byte, to check that the on-disk representation is deterministic.
+/// The index files of variable-shape columns (`table.f<N>i`) are left out,
+/// because casacore does not initialize the alignment padding in them.
+/// The first table is left in *dir* as `roundtrip_a.tab`, so that callers can
+/// compare it with a reference copy.
+///
+/// This is intended to be called with many different types and shapes, so
+/// that regressions in the glue between Rust and casacore are caught.
+///
+/// # Panics
+///
+/// Panics if any value read back differs from `value`, if a keyword that
+/// should be rejected is accepted, or if the two tables differ on disk.
+pub fn roundtrip_check<T, P>(dir: P, value: &T) -> Result<(), TableError>
+where
+    T: CasaDataType + Debug,
+    P: AsRef<Path>,
+{
+    const N_ROWS: u64 = 2;
+
+    let dir = dir.as_ref();
+    let mut shape = Vec::new();
+    value.casatables_put_shape(&mut shape);
+
+    let mut col_names = vec!["VALUE"];
+
+    if T::DATA_TYPE.is_array() {
+        col_names.push("VALUE_VAR");
+    }
+
+    let mut paths = Vec::new();
+
+    for copy in &["a", "b"] {
+        let path = dir.join(format!("roundtrip_{}.tab", copy));
+        let mut desc = TableDesc::new("", TableDescCreateMode::TDM_SCRATCH)?;
+
+        if T::DATA_TYPE.is_array() {
+            let elem_type = T::DATA_TYPE.element_type();
+            desc.add_array_column(elem_type, "VALUE", None, Some(&shape), true, false)?;
+            desc.add_array_column(elem_type, "VALUE_VAR", None, None, false, false)?;
+            desc.set_ndims("VALUE_VAR", shape.len() as u64)?;
+        } else {
+            desc.add_scalar_column(T::DATA_TYPE, "VALUE", None, false, false)?;
+        }
+
+        let mut table = Table::new(&path, desc, N_ROWS as usize, TableCreateMode::New)?;
+
+        if keyword_supported(T::DATA_TYPE) {
+            table.put_keyword("VALUE", value)?;
+        } else {
+            assert!(
+                table.put_keyword("VALUE", value).is_err(),
+                "a {} keyword was accepted, but casacore records cannot hold it",
+                T::DATA_TYPE
+            );
+        }
+
+        for row in 0..N_ROWS {
+            for col_name in &col_names {
+                table.put_cell(col_name, row, value)?;
+            }
+        }
+
+        paths.push(path);
+    }
+
+    for path in &paths {
+        let mut table = Table::open(path, TableOpenMode::Read)?;
+        assert_eq!(table.n_rows(), N_ROWS);
+
+        for row in 0..N_ROWS {
+            for col_name in &col_names {
+                let read: T = table.get_cell(col_name, row)?;
+                assert_eq!(
+                    &read,
+                    value,
+                    "value mismatch in column {} row {} of {}",
+                    col_name,
+                    row,
+                    path.display()
+                );
+            }
+        }
+
+        if keyword_supported(T::DATA_TYPE) {
+            let read: T = table.get_keyword_record()?.get_field("VALUE")?;
+            assert_eq!(&read, value, "keyword value mismatch in {}", path.display());
+        }
+    }
+
+    let files_a = table_files(&paths[0]);
+    let files_b = table_files(&paths[1]);
+    assert_eq!(
+        files_a.keys().collect::<Vec<_>>(),
+        files_b.keys().collect::<Vec<_>>(),
+        "table copies contain different files"
+    );
+
+    for (name, contents) in &files_a {
+        assert!(
+            contents == &files_b[name],
+            "table copies differ in file {}",
+            name
+        );
+    }
+
+    Ok(())
+}
+
+/// Casacore records cannot hold `Char` or `uShort` values, so those types can
+/// only be stored in columns.
+fn keyword_supported(data_type: GlueDataType) -> bool {
+    !matches!(
+        data_type.element_type(),
+        GlueDataType::TpChar | GlueDataType::TpUShort
+    )
+}
+
+/// Read all of the files in a table directory, except for the lock file,
+/// which records process-specific information, and the index files of
+/// indirect array columns, whose padding casacore leaves uninitialized.
+fn table_files(path: &Path) -> BTreeMap<String, Vec<u8>> {
+    let mut files = BTreeMap::new();
+
+    for entry in fs::read_dir(path).expect("failed to list table directory") {
+        let entry = entry.expect("failed to list table directory");
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if name == "table.lock" || is_array_index_file(&name) || !entry.path().is_file() {
+            continue;
+        }
+
+        let contents = fs::read(entry.path()).expect("failed to read table file");
+        files.insert(name, contents);
+    }
+
+    files
+}
+
+/// Test whether *name* is that of a `table.f<N>i` file, in which casacore
+/// stores the shapes and data of indirect arrays.
+fn is_array_index_file(name: &str) -> bool {
+    name.strip_prefix("table.f")
+        .and_then(|rest| rest.strip_suffix('i'))
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ms::schema;
+    use tempfile::tempdir;
+
+    #[test]
+    fn synthetic_ms_roundtrip() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("synth.ms");
+        let spec = SyntheticMsSpec {
+            n_ants: 3,
+            n_chans: 5,
+            n_pols: 2,
+            n_timesteps: 2,
+            autocorrelations: false,
+            ..Default::default()
+        };
+
+        drop(synthetic_ms(&path, &spec).unwrap());
+
+        let mut t = Table::open(&path, TableOpenMode::Read).unwrap();
+        assert_eq!(t.n_rows(), 6);
+        assert_eq!(
+            t.table_keyword_names().unwrap().len(),
+            schema::REQUIRED_SUBTABLES.len()
+        );
+
+        let ant1: Vec<i32> = t.get_col_as_vec("ANTENNA1").unwrap();
+        let ant2: Vec<i32> = t.get_col_as_vec("ANTENNA2").unwrap();
+        assert_eq!(ant1, [0, 0, 1, 0, 0, 1]);
+        assert_eq!(ant2, [1, 2, 2, 1, 2, 2]);
+
+        let data: Array2<Complex<f32>> = t.get_cell("DATA", 4).unwrap();
+        assert_eq!(data.shape(), &[5, 2]);
+        assert_eq!(data[[3, 1]], spec.expected_vis(4, 3, 1));
+
+        let mut spw = Table::open(path.join("SPECTRAL_WINDOW"), TableOpenMode::Read).unwrap();
+        let n_chan: i32 = spw.get_cell("NUM_CHAN", 0).unwrap();
+        assert_eq!(n_chan, 5);
+    }
+
+    #[test]
+    fn synthetic_ms_bad_pols() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec {
+            n_pols: 3,
+            ..SyntheticMsSpec::default()
+        };
+
+        assert!(matches!(
+            synthetic_ms(tmp_dir.path().join("bad.ms"), &spec),
+            Err(TableError::InvalidArgument(_))
+        ));
+        assert!(!tmp_dir.path().join("bad.ms").exists());
+    }
+
+    /// Compare the files of the table at *path* with the reference copy
+    /// *name* in `tests/golden`, so that changes in how casacore lays out
+    /// data on disk are noticed. If the environment variable
+    /// `RUBBL_BLESS_GOLDEN` is set, the reference copy is rewritten instead.
+    fn check_golden(path: &Path, name: &str) {
+        let golden = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("golden")
+            .join(name);
+        let files = table_files(path);
+
+        if std::env::var_os("RUBBL_BLESS_GOLDEN").is_some() {
+            let _ = fs::remove_dir_all(&golden);
+            fs::create_dir_all(&golden).unwrap();
+
+            for (file_name, contents) in &files {
+                fs::write(golden.join(file_name), contents).unwrap();
+            }
+
+            return;
+        }
+
+        assert!(
+            golden.is_dir(),
+            "missing golden table {}; create it by running the tests with RUBBL_BLESS_GOLDEN=1",
+            golden.display()
+        );
+
+        let expected = table_files(&golden);
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            expected.keys().collect::<Vec<_>>(),
+            "table {} contains different files than its golden copy",
+            name
+        );
+
+        for (file_name, contents) in &files {
+            assert!(
+                contents == &expected[file_name],
+                "file {} of table {} differs from its golden copy",
+                file_name,
+                name
+            );
+        }
+    }
+
+    /// casacore's storage managers cannot store `Char` columns, so `i8` is
+    /// not covered.
+    #[test]
+    fn roundtrip_all_types() {
+        macro_rules! check {
+            ($($name:literal => $value:expr),+ $(,)?) => {
+                $(
+                    let tmp_dir = tempdir().unwrap();
+                    roundtrip_check(tmp_dir.path(), &$value).unwrap();
+                    check_golden(&tmp_dir.path().join("roundtrip_a.tab"), $name);
+                )+
+            };
+        }
+
+        check!(
+            "bool" => true,
+            "u8" => 200u8,
+            "i16" => -300i16,
+            "u16" => 60000u16,
+            "i32" => -70000i32,
+            "u32" => 4000000000u32,
+            "i64" => -5000000000i64,
+            "f32" => 1.5f32,
+            "f64" => -2.25f64,
+            "c32" => Complex::<f32>::new(1., -1.),
+            "c64" => Complex::<f64>::new(-2., 2.),
+            "string" => "hello".to_owned(),
+        );
+
+        check!(
+            "vec_bool" => vec![true, false, true],
+            "vec_u8" => vec![0u8, 128, 255],
+            "vec_i16" => vec![-1i16, 0, 1],
+            "vec_u16" => vec![0u16, 1, 65535],
+            "vec_i32" => vec![-1i32, 0, 1],
+            "vec_u32" => vec![0u32, 1, 4294967295],
+            "vec_i64" => vec![-1i64, 0, 1],
+            "vec_f32" => vec![0.5f32, -0.5],
+            "vec_f64" => vec![0.25f64, -0.25],
+            "vec_c32" => vec![Complex::<f32>::new(1., 2.), Complex::new(3., 4.)],
+            "vec_c64" => vec![Complex::<f64>::new(1., 2.), Complex::new(3., 4.)],
+            "vec_string" => vec!["a".to_owned(), "bc".to_owned(), "".to_owned()],
+        );
+
+        check!(
+            "array2_bool" => Array2::from_shape_fn((3, 2), |(i, j)| (i + j) % 2 == 0),
+            "array2_i32" => Array2::from_shape_fn((3, 2), |(i, j)| (10 * i + j) as i32),
+            "array2_c32" => Array2::from_shape_fn((4, 3), |(i, j)| Complex::<f32>::new(i as f32, j as f32)),
+            "array3_f64" => ndarray::Array3::from_shape_fn((2, 3, 4), |(i, j, k)| (i * 12 + j * 4 + k) as f64),
+        );
+    }
+}
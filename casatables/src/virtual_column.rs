@@ -0,0 +1,635 @@
+// Copyright 2024 Peter Williams <peter@newton.cx> and collaborators
+// Licensed under the MIT License.
+
+//! Computed, read-only columns.
+//!
+//! Much of the analysis of a Measurement Set works with quantities derived
+//! from its columns rather than the columns themselves: the amplitudes of the
+//! visibilities, say, or the length of each baseline. A virtual column,
+//! registered with [`Table::virtual_column`], gives such a quantity a column
+//! name, so that it can be read with [`Table::get_cell`],
+//! [`Table::get_cell_as_vec`], and [`Table::get_col_as_vec`] like any other
+//! column. Its values are computed from a [`VirtualExpr`] each time they are
+//! read and are never stored, so a virtual column costs nothing until it is
+//! used and exists only in the [`Table`] object on which it was registered.
+//!
+//! ```rust
+//! use rubbl_casatables::{testing::{synthetic_ms, SyntheticMsSpec}, VirtualExpr};
+//! use tempfile::tempdir;
+//!
+//! let tmp_dir = tempdir().unwrap();
+//! let mut t = synthetic_ms(tmp_dir.path().join("vis.ms"), &SyntheticMsSpec::default()).unwrap();
+//!
+//! t.virtual_column("AMPLITUDE", VirtualExpr::column("DATA").amplitude()).unwrap();
+//! t.virtual_column("BASELINE_LENGTH", VirtualExpr::column("UVW").norm()).unwrap();
+//!
+//! let amps: Vec<f32> = t.get_cell_as_vec("AMPLITUDE", 0).unwrap();
+//! let lengths: Vec<f64> = t.get_col_as_vec("BASELINE_LENGTH").unwrap();
+//! ```
+
+use ndarray::{ArrayD, Axis, IxDyn, Zip};
+use std::sync::Arc;
+
+use crate::{
+    glue::GlueDataType, CasaDataType, CasaScalarData, Complex, Table, TableError,
+    UnexpectedDataTypeError,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum UnaryOp {
+    Amplitude,
+    Phase,
+    Real,
+    Imag,
+    Conj,
+    Sqrt,
+    Neg,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Column(String),
+    Constant(f64),
+    Unary(UnaryOp, Box<VirtualExpr>),
+    Binary(BinaryOp, Box<VirtualExpr>, Box<VirtualExpr>),
+    Norm(Box<VirtualExpr>),
+}
+
+/// An expression that computes the values of a virtual column.
+///
+/// Expressions are built from references to other columns, made with
+/// [`Self::column`], and constants, converted from `f64` with [`From`]. They
+/// are evaluated one row at a time, on the cells of the referenced columns.
+/// Numeric columns of any type can be used; integer and single-precision
+/// values are promoted to double precision, so that every expression
+/// produces either real values of type `f64` or complex values of type
+/// `Complex<f64>`. Arithmetic works element by element, on cells of the
+/// same shape or between a cell and a scalar.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VirtualExpr(Node);
+
+impl VirtualExpr {
+    /// Refer to the column named *name*, which may be another virtual
+    /// column.
+    pub fn column(name: &str) -> Self {
+        VirtualExpr(Node::Column(name.to_owned()))
+    }
+
+    /// A constant value.
+    pub fn constant(value: f64) -> Self {
+        VirtualExpr(Node::Constant(value))
+    }
+
+    fn unary(self, op: UnaryOp) -> Self {
+        VirtualExpr(Node::Unary(op, Box::new(self)))
+    }
+
+    fn binary(self, op: BinaryOp, other: impl Into<VirtualExpr>) -> Self {
+        VirtualExpr(Node::Binary(op, Box::new(self), Box::new(other.into())))
+    }
+
+    /// The magnitudes of the values.
+    pub fn amplitude(self) -> Self {
+        self.unary(UnaryOp::Amplitude)
+    }
+
+    /// The phases of complex values, in radians. Real values have a phase of
+    /// zero or π.
+    pub fn phase(self) -> Self {
+        self.unary(UnaryOp::Phase)
+    }
+
+    /// The real parts of the values.
+    pub fn real(self) -> Self {
+        self.unary(UnaryOp::Real)
+    }
+
+    /// The imaginary parts of the values.
+    pub fn imag(self) -> Self {
+        self.unary(UnaryOp::Imag)
+    }
+
+    /// The complex conjugates of the values.
+    pub fn conj(self) -> Self {
+        self.unary(UnaryOp::Conj)
+    }
+
+    /// The square roots of the values.
+    pub fn sqrt(self) -> Self {
+        self.unary(UnaryOp::Sqrt)
+    }
+
+    /// The negations of the values.
+    #[allow(clippy::should_implement_trait)]
+    pub fn neg(self) -> Self {
+        self.unary(UnaryOp::Neg)
+    }
+
+    /// The Euclidean norm along the last axis of each cell, in Rust (C)
+    /// order. For a `UVW` cell, this is the length of the baseline; for a
+    /// `DATA` cell of shape `(n_chan, n_pol)`, it is a vector over channels.
+    pub fn norm(self) -> Self {
+        VirtualExpr(Node::Norm(Box::new(self)))
+    }
+
+    /// The sum of this expression and *other*.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, other: impl Into<VirtualExpr>) -> Self {
+        self.binary(BinaryOp::Add, other)
+    }
+
+    /// The difference of this expression and *other*.
+    #[allow(clippy::should_implement_trait)]
+    pub fn sub(self, other: impl Into<VirtualExpr>) -> Self {
+        self.binary(BinaryOp::Sub, other)
+    }
+
+    /// The product of this expression and *other*.
+    #[allow(clippy::should_implement_trait)]
+    pub fn mul(self, other: impl Into<VirtualExpr>) -> Self {
+        self.binary(BinaryOp::Mul, other)
+    }
+
+    /// The quotient of this expression and *other*.
+    #[allow(clippy::should_implement_trait)]
+    pub fn div(self, other: impl Into<VirtualExpr>) -> Self {
+        self.binary(BinaryOp::Div, other)
+    }
+
+    /// List the names of the columns that this expression refers to.
+    pub fn columns(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_columns(&mut names);
+        names
+    }
+
+    fn collect_columns<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match &self.0 {
+            Node::Column(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Node::Constant(_) => {}
+            Node::Unary(_, e) | Node::Norm(e) => e.collect_columns(names),
+            Node::Binary(_, a, b) => {
+                a.collect_columns(names);
+                b.collect_columns(names);
+            }
+        }
+    }
+}
+
+impl From<f64> for VirtualExpr {
+    fn from(value: f64) -> Self {
+        VirtualExpr::constant(value)
+    }
+}
+
+/// The value of an expression in one row.
+enum Value {
+    Real(ArrayD<f64>),
+    Complex(ArrayD<Complex<f64>>),
+}
+
+impl Value {
+    fn into_complex(self) -> ArrayD<Complex<f64>> {
+        match self {
+            Value::Real(a) => a.mapv(|x| Complex::new(x, 0.)),
+            Value::Complex(a) => a,
+        }
+    }
+
+    fn shape(&self) -> &[usize] {
+        match self {
+            Value::Real(a) => a.shape(),
+            Value::Complex(a) => a.shape(),
+        }
+    }
+
+    /// The data type that a cell holding this value would have.
+    fn data_type(&self) -> GlueDataType {
+        match (self, self.shape().is_empty()) {
+            (Value::Real(_), true) => GlueDataType::TpDouble,
+            (Value::Real(_), false) => GlueDataType::TpArrayDouble,
+            (Value::Complex(_), true) => GlueDataType::TpDComplex,
+            (Value::Complex(_), false) => GlueDataType::TpArrayDComplex,
+        }
+    }
+}
+
+/// Apply a binary operation element by element, broadcasting scalars.
+fn combine<T: Clone, F: Fn(T, T) -> T>(
+    a: ArrayD<T>,
+    b: ArrayD<T>,
+    f: F,
+) -> Result<ArrayD<T>, (Vec<usize>, Vec<usize>)> {
+    if a.shape() == b.shape() {
+        let mut a = a;
+        Zip::from(&mut a)
+            .and(&b)
+            .for_each(|x, y| *x = f(x.clone(), y.clone()));
+        Ok(a)
+    } else if b.ndim() == 0 {
+        let y = b.iter().next().unwrap().clone();
+        Ok(a.mapv(|x| f(x, y.clone())))
+    } else if a.ndim() == 0 {
+        let x = a.iter().next().unwrap().clone();
+        Ok(b.mapv(|y| f(x.clone(), y)))
+    } else {
+        Err((a.shape().to_vec(), b.shape().to_vec()))
+    }
+}
+
+impl Table {
+    /// Register a read-only virtual column named *name*, whose values are
+    /// computed from *expr*.
+    ///
+    /// See [the module documentation](crate::VirtualExpr) for an example.
+    /// The column can then be read with [`Self::get_cell`],
+    /// [`Self::get_cell_as_vec`], [`Self::get_col_as_vec`], and
+    /// [`Self::get_cell_shape`]. Real-valued columns can be read as `f64` or
+    /// `f32`, and complex-valued ones as `Complex<f64>` or `Complex<f32>`.
+    /// Virtual columns are not stored in the table, do not appear in
+    /// [`Self::column_names`], and cannot be written or used by the bulk
+    /// I/O methods.
+    ///
+    /// The name must not be that of a real or virtual column of the table,
+    /// and every column that *expr* refers to must exist.
+    pub fn virtual_column(&mut self, name: &str, expr: VirtualExpr) -> Result<(), TableError> {
+        let real_columns = self.column_names()?;
+
+        if real_columns.iter().any(|c| c == name) || self.virtual_columns.contains_key(name) {
+            return Err(TableError::VirtualColumn {
+                column: name.to_owned(),
+                reason: "a column with this name already exists".to_owned(),
+            });
+        }
+
+        for input in expr.columns() {
+            if !real_columns.iter().any(|c| c == input) && !self.virtual_columns.contains_key(input)
+            {
+                return Err(TableError::VirtualColumn {
+                    column: name.to_owned(),
+                    reason: format!("it refers to \"{}\", which does not exist", input),
+                });
+            }
+        }
+
+        // A column that was removed may still be referred to by others, so
+        // registering it again could create a cycle.
+        if self.refers_to(&expr, name) {
+            return Err(TableError::VirtualColumn {
+                column: name.to_owned(),
+                reason: "its definition refers to itself".to_owned(),
+            });
+        }
+
+        self.virtual_columns.insert(name.to_owned(), Arc::new(expr));
+        Ok(())
+    }
+
+    /// Unregister the virtual column named *name*, returning its expression
+    /// if it existed. Virtual columns that refer to it can no longer be read.
+    pub fn remove_virtual_column(&mut self, name: &str) -> Option<VirtualExpr> {
+        self.virtual_columns
+            .remove(name)
+            .map(|e| Arc::try_unwrap(e).unwrap_or_else(|e| (*e).clone()))
+    }
+
+    /// List the names of the virtual columns registered on this table, in
+    /// no particular order.
+    pub fn virtual_column_names(&self) -> Vec<String> {
+        self.virtual_columns.keys().cloned().collect()
+    }
+
+    /// Determine whether *expr* refers to the column *name*, directly or
+    /// through other virtual columns.
+    fn refers_to(&self, expr: &VirtualExpr, name: &str) -> bool {
+        expr.columns().into_iter().any(|input| {
+            input == name
+                || self
+                    .virtual_columns
+                    .get(input)
+                    .is_some_and(|inner| self.refers_to(inner, name))
+        })
+    }
+
+    fn virtual_expr(&self, col_name: &str) -> Option<Arc<VirtualExpr>> {
+        self.virtual_columns.get(col_name).cloned()
+    }
+
+    /// Evaluate the cell of a virtual column, if *col_name* is one.
+    pub(crate) fn get_virtual_cell<T: CasaDataType>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+    ) -> Option<Result<T, TableError>> {
+        let expr = self.virtual_expr(col_name)?;
+        Some(
+            self.evaluate(col_name, &expr, row)
+                .and_then(value_into_cell),
+        )
+    }
+
+    /// Evaluate the cell of a virtual column as a flat vector, if
+    /// *col_name* is one.
+    pub(crate) fn get_virtual_cell_as_vec<T: CasaScalarData>(
+        &mut self,
+        col_name: &str,
+        row: u64,
+    ) -> Option<Result<Vec<T>, TableError>> {
+        let expr = self.virtual_expr(col_name)?;
+        Some(self.evaluate(col_name, &expr, row).and_then(value_into_vec))
+    }
+
+    /// Evaluate a whole scalar virtual column, if *col_name* is one.
+    pub(crate) fn get_virtual_col_as_vec<T: CasaScalarData>(
+        &mut self,
+        col_name: &str,
+    ) -> Option<Result<Vec<T>, TableError>> {
+        let expr = self.virtual_expr(col_name)?;
+        let result = (0..self.n_rows())
+            .map(|row| {
+                self.evaluate(col_name, &expr, row)
+                    .and_then(value_into_cell)
+            })
+            .collect();
+        Some(result)
+    }
+
+    /// Get the shape of a cell of a virtual column, if *col_name* is one.
+    pub(crate) fn get_virtual_cell_shape(
+        &mut self,
+        col_name: &str,
+        row: u64,
+    ) -> Option<Result<Vec<usize>, TableError>> {
+        let expr = self.virtual_expr(col_name)?;
+        Some(
+            self.evaluate(col_name, &expr, row)
+                .map(|v| v.shape().to_vec()),
+        )
+    }
+
+    fn evaluate(
+        &mut self,
+        column: &str,
+        expr: &VirtualExpr,
+        row: u64,
+    ) -> Result<Value, TableError> {
+        let shape_error = |a: Vec<usize>, b: Vec<usize>| TableError::VirtualColumn {
+            column: column.to_owned(),
+            reason: format!("cannot combine cells of shapes {:?} and {:?}", a, b),
+        };
+
+        Ok(match &expr.0 {
+            Node::Column(name) => match self.virtual_expr(name) {
+                Some(inner) => self.evaluate(name, &inner, row)?,
+                None => self.read_value(name, row)?,
+            },
+
+            Node::Constant(x) => Value::Real(ArrayD::from_elem(IxDyn(&[]), *x)),
+
+            Node::Unary(op, e) => {
+                let v = self.evaluate(column, e, row)?;
+
+                match (op, v) {
+                    (UnaryOp::Amplitude, Value::Real(a)) => Value::Real(a.mapv(f64::abs)),
+                    (UnaryOp::Amplitude, Value::Complex(a)) => Value::Real(a.mapv(|z| z.norm())),
+                    (UnaryOp::Phase, Value::Real(a)) => {
+                        Value::Real(a.mapv(|x| if x < 0. { std::f64::consts::PI } else { 0. }))
+                    }
+                    (UnaryOp::Phase, Value::Complex(a)) => Value::Real(a.mapv(|z| z.arg())),
+                    (UnaryOp::Real, Value::Real(a)) => Value::Real(a),
+                    (UnaryOp::Real, Value::Complex(a)) => Value::Real(a.mapv(|z| z.re)),
+                    (UnaryOp::Imag, Value::Real(a)) => Value::Real(a.mapv(|_| 0.)),
+                    (UnaryOp::Imag, Value::Complex(a)) => Value::Real(a.mapv(|z| z.im)),
+                    (UnaryOp::Conj, Value::Real(a)) => Value::Real(a),
+                    (UnaryOp::Conj, Value::Complex(a)) => Value::Complex(a.mapv(|z| z.conj())),
+                    (UnaryOp::Sqrt, Value::Real(a)) => Value::Real(a.mapv(f64::sqrt)),
+                    (UnaryOp::Sqrt, Value::Complex(a)) => Value::Complex(a.mapv(|z| z.sqrt())),
+                    (UnaryOp::Neg, Value::Real(a)) => Value::Real(a.mapv(|x| -x)),
+                    (UnaryOp::Neg, Value::Complex(a)) => Value::Complex(a.mapv(|z| -z)),
+                }
+            }
+
+            Node::Binary(op, a, b) => {
+                let a = self.evaluate(column, a, row)?;
+                let b = self.evaluate(column, b, row)?;
+
+                match (a, b) {
+                    (Value::Real(a), Value::Real(b)) => {
+                        let f: fn(f64, f64) -> f64 = match op {
+                            BinaryOp::Add => |x, y| x + y,
+                            BinaryOp::Sub => |x, y| x - y,
+                            BinaryOp::Mul => |x, y| x * y,
+                            BinaryOp::Div => |x, y| x / y,
+                        };
+                        Value::Real(combine(a, b, f).map_err(|(a, b)| shape_error(a, b))?)
+                    }
+                    (a, b) => {
+                        let f: fn(Complex<f64>, Complex<f64>) -> Complex<f64> = match op {
+                            BinaryOp::Add => |x, y| x + y,
+                            BinaryOp::Sub => |x, y| x - y,
+                            BinaryOp::Mul => |x, y| x * y,
+                            BinaryOp::Div => |x, y| x / y,
+                        };
+                        Value::Complex(
+                            combine(a.into_complex(), b.into_complex(), f)
+                                .map_err(|(a, b)| shape_error(a, b))?,
+                        )
+                    }
+                }
+            }
+
+            Node::Norm(e) => {
+                let a = match self.evaluate(column, e, row)? {
+                    Value::Real(a) => a.mapv(|x| x * x),
+                    Value::Complex(a) => a.mapv(|z| z.norm_sqr()),
+                };
+
+                if a.ndim() == 0 {
+                    Value::Real(a.mapv(f64::sqrt))
+                } else {
+                    Value::Real(a.sum_axis(Axis(a.ndim() - 1)).mapv(f64::sqrt))
+                }
+            }
+        })
+    }
+
+    /// Read a cell of a real column as a [`Value`].
+    fn read_value(&mut self, col_name: &str, row: u64) -> Result<Value, TableError> {
+        let (data_type, shape) = self.cell_info(col_name, row)?;
+
+        macro_rules! read_as {
+            ($t:ty, $conv:expr) => {{
+                let flat: Vec<$t> = if data_type.is_array() {
+                    self.get_cell_as_vec(col_name, row)?
+                } else {
+                    vec![self.get_cell(col_name, row)?]
+                };
+                ArrayD::from_shape_vec(IxDyn(&shape), flat.into_iter().map($conv).collect())
+                    .expect("cell shape matches its contents")
+            }};
+        }
+
+        Ok(match data_type.element_type() {
+            GlueDataType::TpDouble => Value::Real(read_as!(f64, |x| x)),
+            GlueDataType::TpFloat => Value::Real(read_as!(f32, |x| x as f64)),
+            GlueDataType::TpInt => Value::Real(read_as!(i32, |x| x as f64)),
+            GlueDataType::TpUInt => Value::Real(read_as!(u32, |x| x as f64)),
+            GlueDataType::TpShort => Value::Real(read_as!(i16, |x| x as f64)),
+            GlueDataType::TpUShort => Value::Real(read_as!(u16, |x| x as f64)),
+            GlueDataType::TpUChar => Value::Real(read_as!(u8, |x| x as f64)),
+            GlueDataType::TpInt64 => Value::Real(read_as!(i64, |x| x as f64)),
+            GlueDataType::TpBool => Value::Real(read_as!(bool, |x| if x { 1. } else { 0. })),
+            GlueDataType::TpDComplex => Value::Complex(read_as!(Complex<f64>, |z| z)),
+            GlueDataType::TpComplex => Value::Complex(read_as!(Complex<f32>, |z| Complex::new(
+                z.re as f64,
+                z.im as f64
+            ))),
+            other => return Err(TableError::UnsupportedDataType(col_name.to_owned(), other)),
+        })
+    }
+}
+
+/// Convert an evaluated value into a cell of type `T`, which may be a
+/// scalar, a `Vec`, or an `ndarray` array of one of the real or complex
+/// floating-point types.
+fn value_into_cell<T: CasaDataType>(value: Value) -> Result<T, TableError> {
+    if T::DATA_TYPE.is_array() == value.shape().is_empty() {
+        return Err(UnexpectedDataTypeError(T::DATA_TYPE, value.data_type()).into());
+    }
+
+    let shape: Vec<u64> = value.shape().iter().map(|n| *n as u64).collect();
+    let mut result = T::casatables_alloc(&shape)?;
+    let buf = result.casatables_as_mut_buf();
+
+    // SAFETY: `casatables_alloc` gives a buffer of the requested shape in
+    // standard (C) order, as filled in by casacore when reading real
+    // columns, and the element type is checked against `T::DATA_TYPE`
+    // before writing.
+    unsafe {
+        match (T::DATA_TYPE.element_type(), &value) {
+            (GlueDataType::TpDouble, Value::Real(a)) => {
+                write_elements(buf as *mut f64, a.iter().copied())
+            }
+            (GlueDataType::TpFloat, Value::Real(a)) => {
+                write_elements(buf as *mut f32, a.iter().map(|x| *x as f32))
+            }
+            (GlueDataType::TpDComplex, Value::Real(a)) => write_elements(
+                buf as *mut Complex<f64>,
+                a.iter().map(|x| Complex::new(*x, 0.)),
+            ),
+            (GlueDataType::TpDComplex, Value::Complex(a)) => {
+                write_elements(buf as *mut Complex<f64>, a.iter().copied())
+            }
+            (GlueDataType::TpComplex, Value::Real(a)) => write_elements(
+                buf as *mut Complex<f32>,
+                a.iter().map(|x| Complex::new(*x as f32, 0.)),
+            ),
+            (GlueDataType::TpComplex, Value::Complex(a)) => write_elements(
+                buf as *mut Complex<f32>,
+                a.iter().map(|z| Complex::new(z.re as f32, z.im as f32)),
+            ),
+            _ => return Err(UnexpectedDataTypeError(T::DATA_TYPE, value.data_type()).into()),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Convert an evaluated value into a flat vector in C order.
+fn value_into_vec<T: CasaScalarData>(value: Value) -> Result<Vec<T>, TableError> {
+    match value {
+        Value::Real(a) => a
+            .iter()
+            .map(|x| value_into_cell(Value::Real(ArrayD::from_elem(IxDyn(&[]), *x))))
+            .collect(),
+        Value::Complex(a) => a
+            .iter()
+            .map(|z| value_into_cell(Value::Complex(ArrayD::from_elem(IxDyn(&[]), *z))))
+            .collect(),
+    }
+}
+
+unsafe fn write_elements<E, I: Iterator<Item = E>>(buf: *mut E, values: I) {
+    for (i, v) in values.enumerate() {
+        buf.add(i).write(v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{synthetic_ms, SyntheticMsSpec};
+    use ndarray::Array2;
+    use tempfile::tempdir;
+
+    #[test]
+    fn derived_quantities() {
+        let tmp_dir = tempdir().unwrap();
+        let spec = SyntheticMsSpec::default();
+        let mut t = synthetic_ms(tmp_dir.path().join("vis.ms"), &spec).unwrap();
+
+        t.virtual_column("AMP", VirtualExpr::column("DATA").amplitude())
+            .unwrap();
+        t.virtual_column("BL_LENGTH", VirtualExpr::column("UVW").norm())
+            .unwrap();
+        t.virtual_column("BL_KM", VirtualExpr::column("BL_LENGTH").div(1000.))
+            .unwrap();
+        t.virtual_column("DOUBLED", VirtualExpr::column("DATA").mul(2.))
+            .unwrap();
+
+        let row = 5;
+        let data: Array2<Complex<f32>> = t.get_cell("DATA", row).unwrap();
+        let amp: Array2<f32> = t.get_cell("AMP", row).unwrap();
+        assert_eq!(amp.shape(), data.shape());
+
+        for (a, z) in amp.iter().zip(data.iter()) {
+            assert!((a - z.norm()).abs() < 1e-5);
+        }
+
+        let amp_flat: Vec<f64> = t.get_cell_as_vec("AMP", row).unwrap();
+        assert_eq!(amp_flat.len(), spec.n_chans * spec.n_pols);
+        assert_eq!(
+            t.get_cell_shape("AMP", row).unwrap(),
+            vec![spec.n_chans, spec.n_pols]
+        );
+
+        let uvw: Vec<f64> = t.get_cell_as_vec("UVW", row).unwrap();
+        let expected = uvw.iter().map(|x| x * x).sum::<f64>().sqrt();
+        let lengths: Vec<f64> = t.get_col_as_vec("BL_LENGTH").unwrap();
+        assert_eq!(lengths.len(), spec.n_rows());
+        assert!((lengths[row as usize] - expected).abs() < 1e-9);
+        let km: f64 = t.get_cell("BL_KM", row).unwrap();
+        assert!((km - expected / 1000.).abs() < 1e-12);
+
+        let doubled: Array2<Complex<f32>> = t.get_cell("DOUBLED", row).unwrap();
+        assert_eq!(doubled[[1, 1]], data[[1, 1]] * 2.);
+
+        // Type and shape mismatches are reported, not papered over.
+        assert!(t.get_cell::<f64>("AMP", row).is_err());
+        assert!(t.get_cell::<i32>("BL_LENGTH", row).is_err());
+
+        // Names must be new and inputs must exist.
+        assert!(t
+            .virtual_column("TIME", VirtualExpr::column("TIME"))
+            .is_err());
+        assert!(t
+            .virtual_column("X", VirtualExpr::column("NO_SUCH_COLUMN"))
+            .is_err());
+
+        assert!(!t.column_names().unwrap().contains(&"AMP".to_owned()));
+        assert!(t.remove_virtual_column("AMP").is_some());
+        assert!(t.get_cell::<Array2<f32>>("AMP", row).is_err());
+    }
+}
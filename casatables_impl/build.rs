@@ -1,22 +1,78 @@
 // Copyright 2017-2021 Peter Williams <peter@newton.cx> and collaborators
 // Licensed under the MIT License.
 
-use std::{env, fs, path::PathBuf};
+use sha2::{Digest, Sha256};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The name of the static library that we build.
+const ARCHIVE_NAME: &str = "libcasatables_impl.a";
+
+/// If set, a directory in which compiled archives are cached between builds.
+const CACHE_DIR_VAR: &str = "RUBBL_CASACORE_CACHE_DIR";
+
+/// If set, a base URL from which prebuilt archives can be downloaded. It
+/// must use HTTPS, and is only used if [`CACHE_SHA256_VAR`] is set too.
+const CACHE_URL_VAR: &str = "RUBBL_CASACORE_CACHE_URL";
+
+/// The hex SHA-256 digest that a downloaded archive must have in order to be
+/// used. Nothing is downloaded unless this is set.
+const CACHE_SHA256_VAR: &str = "RUBBL_CASACORE_CACHE_SHA256";
 
 fn main() {
-    cc::Build::new()
+    println!("cargo:rustc-check-cfg=cfg(rubbl_casacore_unsupported)");
+
+    if cfg!(feature = "system-casacore") {
+        link_system_casacore();
+        return;
+    }
+
+    // casacore relies on POSIX file locking and memory mapping, and has never
+    // been ported to Windows. Say so up front rather than failing halfway
+    // through a long C++ build: skip the build, and have the crate itself
+    // fail to compile with an explanation. `DEP_CASA_UNSUPPORTED` tells
+    // dependents not to expect any headers.
+    if let Ok(os @ "windows") = env::var("CARGO_CFG_TARGET_OS").as_deref() {
+        println!(
+            "cargo:warning=the bundled casacore does not support target OS `{}`; \
+             not building it",
+            os
+        );
+        println!("cargo:rustc-cfg=rubbl_casacore_unsupported");
+        println!("cargo:unsupported={}", os);
+        return;
+    }
+
+    let mut builder = cc::Build::new();
+
+    builder
         .cpp(true)
         .warnings(true)
         .flag_if_supported("-std=c++11")
-        // This allows us to treat rubbl's modified casacore as a separate 
+        // This allows us to treat rubbl's modified casacore as a separate
         // namespace, so that both vanilla casacore and rubbl can be linked
-        // at the same time. 
+        // at the same time.
         .define("casacore", "rubbl_casacore")
         // Without this, using casa in multiple threads causes segfaults
         .define("USE_THREADS", "1")
-        .include(".")
-        .files(FILES)
-        .compile("libcasatables_impl.a");
+        .include(".");
+
+    // Compiling casacore takes a long time, so builds can opt into reusing
+    // an archive compiled earlier with the same toolchain and sources.
+    println!("cargo:rerun-if-env-changed={}", CACHE_DIR_VAR);
+    println!("cargo:rerun-if-env-changed={}", CACHE_URL_VAR);
+    println!("cargo:rerun-if-env-changed={}", CACHE_SHA256_VAR);
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let key = archive_key(&builder);
+
+    if !use_cached_archive(&key, &out_dir) {
+        builder.files(FILES).compile(ARCHIVE_NAME);
+        store_cached_archive(&key, &out_dir);
+    }
 
     for file in FILES {
         println!("cargo:rerun-if-changed={}", file);
@@ -39,6 +95,261 @@ fn main() {
 
     println!("cargo:root={}", dst.to_str().unwrap());
     println!("cargo:include={}/include", dst.to_str().unwrap());
+
+    // Advertise the optional casacore features that this build enables, as
+    // `DEP_CASA_FEATURES`, so that dependents can report them. This must be
+    // kept in sync with the defines above.
+    println!("cargo:features=threads");
+    println!("cargo:unknown_features=");
+}
+
+/// Compute the cache key for the compiled archive.
+///
+/// The key identifies the target, the compiler and its settings, and the
+/// contents of all of the sources, so that an archive is only reused when
+/// compiling would produce an equivalent one.
+fn archive_key(builder: &cc::Build) -> String {
+    let target = env::var("TARGET").unwrap();
+    let mut hash = Sha256::new();
+
+    // Prefix every item with its length so that their boundaries matter.
+    let mut write = |bytes: &[u8]| {
+        hash.update((bytes.len() as u64).to_le_bytes());
+        hash.update(bytes);
+    };
+
+    for var in ["TARGET", "OPT_LEVEL", "DEBUG", "CARGO_PKG_VERSION"] {
+        write(env::var(var).unwrap_or_default().as_bytes());
+    }
+
+    let compiler = builder.get_compiler();
+    write(compiler.path().to_string_lossy().as_bytes());
+
+    for arg in compiler.args() {
+        write(arg.to_string_lossy().as_bytes());
+    }
+
+    if let Ok(output) = Command::new(compiler.path()).arg("--version").output() {
+        write(&output.stdout);
+    }
+
+    for file in FILES.iter().chain(HEADERS) {
+        write(file.as_bytes());
+        write(&fs::read(file).unwrap_or_default());
+    }
+
+    format!("{}-{}", target, to_hex(&hash.finalize()))
+}
+
+/// Format bytes as lowercase hexadecimal.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Check that the file at *path* has the SHA-256 digest *expected*, given in
+/// hexadecimal.
+fn digest_matches(path: &Path, expected: &str) -> bool {
+    match fs::read(path) {
+        Ok(data) => to_hex(&Sha256::digest(data)).eq_ignore_ascii_case(expected.trim()),
+        Err(_) => false,
+    }
+}
+
+/// Try to link with a previously compiled archive, downloading it if
+/// necessary. Returns true on success.
+fn use_cached_archive(key: &str, out_dir: &Path) -> bool {
+    let cache_dir = env::var_os(CACHE_DIR_VAR).map(PathBuf::from);
+    let archive = out_dir.join(ARCHIVE_NAME);
+
+    let cached = cache_dir
+        .as_ref()
+        .map(|d| d.join(key).join(ARCHIVE_NAME))
+        .filter(|p| p.exists());
+
+    let found = match cached {
+        Some(path) => fs::copy(path, &archive).is_ok(),
+        None => match (env::var(CACHE_URL_VAR), env::var(CACHE_SHA256_VAR)) {
+            (Ok(base), Ok(sha256)) => download_archive(&base, key, &sha256, &archive),
+            (Ok(_), Err(_)) => {
+                println!(
+                    "cargo:warning=not downloading prebuilt casacore because \
+                     {CACHE_SHA256_VAR} is not set; compiling it"
+                );
+                false
+            }
+            (Err(_), _) => false,
+        },
+    };
+
+    if !found {
+        return false;
+    }
+
+    // A download should also populate the local cache.
+    store_cached_archive(key, out_dir);
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+    println!("cargo:rustc-link-lib=static=casatables_impl");
+    true
+}
+
+/// Download a prebuilt archive to *dest* using `curl`.
+///
+/// Only HTTPS URLs are accepted, including when following redirects, and the
+/// archive is only kept if its SHA-256 digest is *sha256*.
+fn download_archive(base: &str, key: &str, sha256: &str, dest: &Path) -> bool {
+    if !base.starts_with("https://") {
+        println!("cargo:warning=ignoring {CACHE_URL_VAR} because it is not an https:// URL");
+        return false;
+    }
+
+    let url = format!("{}/{}/{}", base.trim_end_matches('/'), key, ARCHIVE_NAME);
+    let partial = dest.with_extension("partial");
+
+    let ok = Command::new("curl")
+        .args([
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--location",
+            "--proto",
+            "=https",
+            "--proto-redir",
+            "=https",
+            "--output",
+        ])
+        .arg(&partial)
+        .arg(&url)
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+
+    if !ok {
+        println!("cargo:warning=could not download prebuilt casacore from {url}; compiling it");
+        let _ignored = fs::remove_file(&partial);
+        return false;
+    }
+
+    if !digest_matches(&partial, sha256) {
+        println!(
+            "cargo:warning=prebuilt casacore from {url} does not match \
+             {CACHE_SHA256_VAR}; compiling it"
+        );
+        let _ignored = fs::remove_file(&partial);
+        return false;
+    }
+
+    if let Err(e) = fs::rename(&partial, dest) {
+        println!("cargo:warning=could not save prebuilt casacore: {e}; compiling it");
+        let _ignored = fs::remove_file(&partial);
+        return false;
+    }
+
+    true
+}
+
+/// Save the compiled archive in the cache directory, if one is configured.
+///
+/// Failures are reported as warnings since the cache is only an
+/// optimization.
+fn store_cached_archive(key: &str, out_dir: &Path) {
+    let dir = match env::var_os(CACHE_DIR_VAR) {
+        Some(d) => PathBuf::from(d).join(key),
+        None => return,
+    };
+
+    let dest = dir.join(ARCHIVE_NAME);
+
+    if dest.exists() {
+        return;
+    }
+
+    // Copy then rename so that concurrent builds never see a partial file.
+    let partial = dir.join(format!("{}.{}", ARCHIVE_NAME, std::process::id()));
+    let result = fs::create_dir_all(&dir)
+        .and_then(|_| fs::copy(out_dir.join(ARCHIVE_NAME), &partial))
+        .and_then(|_| fs::rename(&partial, &dest));
+
+    if let Err(e) = result {
+        println!(
+            "cargo:warning=could not cache casacore archive in {}: {}",
+            dir.display(),
+            e
+        );
+        let _ignored = fs::remove_file(&partial);
+    }
+}
+
+/// Link with an external casacore installation instead of building the
+/// bundled sources.
+///
+/// The installation is located with pkg-config, using the package name given
+/// by the `RUBBL_CASACORE_PKG` environment variable, or `casacore` by
+/// default. The include directories are passed on to dependents as
+/// `DEP_CASA_INCLUDE`, separated like `PATH`, and `DEP_CASA_SYSTEM` is set so
+/// that they know not to expect the rubbl-specific namespace.
+#[cfg(feature = "system-casacore")]
+fn link_system_casacore() {
+    println!("cargo:rerun-if-env-changed=RUBBL_CASACORE_PKG");
+    let pkg = env::var("RUBBL_CASACORE_PKG").unwrap_or_else(|_| "casacore".to_owned());
+
+    let lib = pkg_config::Config::new()
+        .atleast_version("3.1")
+        .probe(&pkg)
+        .unwrap_or_else(|e| panic!("cannot find system casacore package `{}`: {}", pkg, e));
+
+    let include = env::join_paths(&lib.include_paths).unwrap();
+    println!("cargo:include={}", include.to_str().unwrap());
+    println!("cargo:system=1");
+
+    // Work out how the external library was configured from the macros that
+    // it defines, either in the compiler flags of its package or in its
+    // generated `casa/config.h`. casacore records `HAVE_MPI`, `HAVE_ADIOS2`
+    // and `HAVE_HDF5` in the header, so if we find it, their absence means
+    // that they are disabled. `USE_THREADS` is only ever passed on the
+    // command line, so unless the package passes it, we can't tell.
+    let mut defines: Vec<String> = lib.defines.keys().cloned().collect();
+    let mut have_config = false;
+
+    for dir in &lib.include_paths {
+        let path = dir.join("casacore").join("casa").join("config.h");
+
+        if let Ok(text) = fs::read_to_string(&path) {
+            println!("cargo:rerun-if-changed={}", path.display());
+            have_config = true;
+            defines.extend(text.lines().filter_map(|line| {
+                let mut words = line.trim_start().strip_prefix('#')?.split_whitespace();
+                match words.next() {
+                    Some("define") => words.next().map(|w| w.to_owned()),
+                    _ => None,
+                }
+            }));
+            break;
+        }
+    }
+
+    let mut features = Vec::new();
+    let mut unknown = Vec::new();
+
+    for (define, name, in_config) in [
+        ("USE_THREADS", "threads", false),
+        ("HAVE_MPI", "mpi", true),
+        ("HAVE_ADIOS2", "adios2", true),
+        ("HAVE_HDF5", "hdf5", true),
+    ] {
+        if defines.iter().any(|d| d == define) {
+            features.push(name);
+        } else if !(in_config && have_config) {
+            unknown.push(name);
+        }
+    }
+
+    println!("cargo:features={}", features.join(","));
+    println!("cargo:unknown_features={}", unknown.join(","));
+}
+
+#[cfg(not(feature = "system-casacore"))]
+fn link_system_casacore() {
+    unreachable!()
 }
 
 const FILES: &[&str] = &[
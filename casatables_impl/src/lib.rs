@@ -8,3 +8,11 @@ that bridges C++ to Rust can be edited without having to recompile the large
 casacore codebase every time.
 
  */
+
+// The build script skips building casacore for targets that it does not
+// support, so that this explanation is the error that users see.
+#[cfg(rubbl_casacore_unsupported)]
+compile_error!(
+    "the bundled casacore cannot be built for Windows; build for Linux under \
+     WSL, or enable the `system-casacore` feature to use an external casacore"
+);
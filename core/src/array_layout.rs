@@ -0,0 +1,224 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! The positions of the antennas of an interferometer.
+//!
+//! Array layouts come in many formats: site survey spreadsheets, the
+//! `ANTENNA` tables of Measurement Sets, the metafits files of the MWA, and
+//! so on. Loaders for these produce an [`AntennaLayout`], which gives each
+//! antenna’s name, geocentric ITRF position, and dish diameter, and is
+//! accepted by the code that writes `ANTENNA` tables and computes UVW
+//! coordinates. This module reads the simplest format, a text file listing
+//! ITRF coordinates; see the `rubbl_casatables` and `rubbl_fits` crates for
+//! the others.
+//!
+//! Layouts are often given as east–north–up offsets from a reference point,
+//! which [`geodetic_to_itrf`] and [`enu_to_itrf`] convert into ITRF
+//! positions.
+//!
+//! ```rust
+//! use rubbl_core::array_layout::AntennaLayout;
+//!
+//! let text = "\
+//! # name  X           Y            Z           diameter
+//! ea01    -1601185.4  -5041977.5   3554875.9   25
+//! ea02    -1601150.1  -5042000.6   3554860.7   25
+//! ";
+//! let layout = AntennaLayout::read_itrf_csv(text.as_bytes()).unwrap();
+//! assert_eq!(layout.len(), 2);
+//! assert_eq!(layout.names[1], "ea02");
+//! assert_eq!(layout.diameters[0], 25.);
+//! ```
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+use thiserror::Error;
+
+/// The semi-major axis of the WGS84 ellipsoid, in meters.
+pub const WGS84_SEMI_MAJOR_AXIS: f64 = 6_378_137.;
+
+/// The flattening of the WGS84 ellipsoid.
+pub const WGS84_FLATTENING: f64 = 1. / 298.257_223_563;
+
+/// An error that can occur when loading an array layout.
+#[derive(Error, Debug)]
+pub enum LayoutError {
+    /// An error occurred while reading the layout.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// A line of a text layout could not be parsed.
+    #[error("cannot parse line {line} of the array layout: {message}")]
+    Parse {
+        /// The line number, starting at 1.
+        line: usize,
+
+        /// A description of the problem.
+        message: String,
+    },
+}
+
+/// The names, positions, and sizes of the antennas of an interferometer.
+///
+/// The three vectors have one entry per antenna, in the order in which the
+/// antennas are numbered.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AntennaLayout {
+    /// The names of the antennas.
+    pub names: Vec<String>,
+
+    /// The geocentric ITRF positions of the antennas, in meters.
+    pub positions: Vec<[f64; 3]>,
+
+    /// The diameters of the antennas’ dishes, in meters, or zero if unknown.
+    pub diameters: Vec<f64>,
+}
+
+impl AntennaLayout {
+    /// Create an empty layout.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an antenna to the layout.
+    pub fn push<S: Into<String>>(&mut self, name: S, position: [f64; 3], diameter: f64) {
+        self.names.push(name.into());
+        self.positions.push(position);
+        self.diameters.push(diameter);
+    }
+
+    /// Get the number of antennas.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Check whether the layout has no antennas.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Get the vector from antenna *ant1* to antenna *ant2*, in meters, in
+    /// the ITRF frame.
+    ///
+    /// This is the baseline vector whose UVW coordinates the Measurement Set
+    /// convention records for a row with `ANTENNA1 = ant1` and `ANTENNA2 =
+    /// ant2`.
+    pub fn baseline(&self, ant1: usize, ant2: usize) -> [f64; 3] {
+        let p1 = self.positions[ant1];
+        let p2 = self.positions[ant2];
+        [p2[0] - p1[0], p2[1] - p1[1], p2[2] - p1[2]]
+    }
+
+    /// Read a layout from a text file of ITRF coordinates.
+    ///
+    /// Each line gives an antenna’s name; its X, Y, and Z coordinates in
+    /// meters; and optionally its dish diameter in meters, separated by
+    /// commas, whitespace, or both. Blank lines and text following a `#` are
+    /// ignored, as is a first line whose coordinates are not numbers, so
+    /// that spreadsheets exported with a header row can be read directly.
+    /// Antennas without a diameter are given a diameter of zero.
+    pub fn read_itrf_csv<R: BufRead>(reader: R) -> Result<Self, LayoutError> {
+        let mut layout = AntennaLayout::new();
+        let mut seen_line = false;
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let content = line.split('#').next().unwrap();
+            let fields: Vec<&str> = content
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|f| !f.is_empty())
+                .collect();
+
+            if fields.is_empty() {
+                continue;
+            }
+
+            let is_first = !seen_line;
+            seen_line = true;
+            let parse_error = |message: String| LayoutError::Parse {
+                line: i + 1,
+                message,
+            };
+
+            if fields.len() < 4 || fields.len() > 5 {
+                return Err(parse_error(format!(
+                    "expected 4 or 5 fields, but found {}",
+                    fields.len()
+                )));
+            }
+
+            let numbers: Result<Vec<f64>, _> = fields[1..].iter().map(|f| f.parse()).collect();
+
+            let numbers = match numbers {
+                Ok(n) => n,
+                Err(_) if is_first => continue,
+                Err(e) => return Err(parse_error(e.to_string())),
+            };
+
+            layout.push(
+                fields[0],
+                [numbers[0], numbers[1], numbers[2]],
+                numbers.get(3).copied().unwrap_or(0.),
+            );
+        }
+
+        Ok(layout)
+    }
+
+    /// Read a layout from a text file of ITRF coordinates at *path*.
+    ///
+    /// See [`Self::read_itrf_csv`] for the format.
+    pub fn open_itrf_csv<P: AsRef<Path>>(path: P) -> Result<Self, LayoutError> {
+        Self::read_itrf_csv(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Convert geodetic coordinates on the WGS84 ellipsoid to a geocentric ITRF
+/// position.
+///
+/// *longitude* and *latitude* are in radians, with longitude increasing to
+/// the east, and *height* is the height above the ellipsoid in meters. The
+/// result is in meters.
+///
+/// ```rust
+/// use rubbl_core::array_layout::geodetic_to_itrf;
+///
+/// let [x, y, z] = geodetic_to_itrf(0., 0., 0.);
+/// assert_eq!((x, y, z), (6378137., 0., 0.));
+///
+/// let [x, _, z] = geodetic_to_itrf(0., std::f64::consts::FRAC_PI_2, 0.);
+/// assert!(x.abs() < 1e-6);
+/// assert!((z - 6356752.314).abs() < 1e-3);
+/// ```
+pub fn geodetic_to_itrf(longitude: f64, latitude: f64, height: f64) -> [f64; 3] {
+    let e2 = WGS84_FLATTENING * (2. - WGS84_FLATTENING);
+    let (slat, clat) = latitude.sin_cos();
+    let (slon, clon) = longitude.sin_cos();
+    let n = WGS84_SEMI_MAJOR_AXIS / (1. - e2 * slat * slat).sqrt();
+
+    [
+        (n + height) * clat * clon,
+        (n + height) * clat * slon,
+        (n * (1. - e2) + height) * slat,
+    ]
+}
+
+/// Rotate an east–north–up offset, in meters, at the given geodetic
+/// longitude and latitude into an ITRF offset.
+///
+/// Add the result to the ITRF position of the reference point, as computed
+/// by [`geodetic_to_itrf`], to get an antenna’s position.
+pub fn enu_to_itrf(enu: [f64; 3], longitude: f64, latitude: f64) -> [f64; 3] {
+    let (slat, clat) = latitude.sin_cos();
+    let (slon, clon) = longitude.sin_cos();
+    let [e, n, u] = enu;
+
+    [
+        -slon * e - slat * clon * n + clat * clon * u,
+        clon * e - slat * slon * n + clat * slon * u,
+        clat * n + slat * u,
+    ]
+}
@@ -0,0 +1,142 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! Weighted averaging of visibility data.
+//!
+//! Averaging visibilities in time or frequency has to combine three arrays
+//! consistently: the data, their weights, and their flags. The
+//! [`average_complex_weighted`] kernel does this along any one axis of
+//! arrays of any dimensionality, so that the same code serves time averaging
+//! (along the row axis) and channel averaging (along the frequency axis).
+//!
+//! ```rust
+//! use ndarray::{array, Axis};
+//! use rubbl_core::{average::average_complex_weighted, Complex};
+//!
+//! let c = |re| Complex::new(re, 0f32);
+//! let data = array![[c(1.), c(3.), c(5.), c(100.)]];
+//! let weights = array![[1f32, 1., 2., 1.]];
+//! let flags = array![[false, false, false, true]];
+//!
+//! let avg = average_complex_weighted(data.view(), weights.view(), flags.view(), 2, Axis(1));
+//! assert_eq!(avg.data, array![[c(2.), c(5.)]]);
+//! assert_eq!(avg.weights, array![[2., 2.]]);
+//! assert_eq!(avg.flags, array![[false, false]]);
+//! ```
+
+use ndarray::{Array, ArrayView, Axis, Dimension, Zip};
+use num_complex::Complex;
+
+/// The result of [`average_complex_weighted`].
+#[derive(Clone, Debug)]
+pub struct Averaged<D: Dimension> {
+    /// The averaged data.
+    pub data: Array<Complex<f32>, D>,
+
+    /// The summed weights.
+    pub weights: Array<f32, D>,
+
+    /// The combined flags; true means bad.
+    pub flags: Array<bool, D>,
+}
+
+/// Average data in groups of *factor* adjacent samples along *axis*,
+/// propagating weights and flags.
+///
+/// The three input arrays must have the same shape. Flags are true for bad
+/// samples. Each output sample is computed from one group of input samples
+/// as follows:
+///
+/// - If the group contains unflagged samples with nonzero total weight, the
+///   output is the weighted mean of those samples, its weight is the sum of
+///   their weights, and it is unflagged. Flagged samples are ignored.
+/// - Otherwise, the output is flagged, its value is the weighted mean of all
+///   of the samples in the group (or their plain mean, if all of the weights
+///   are zero), and its weight is the sum of all of their weights. This
+///   matches the behavior of CASA, so that the data of fully flagged
+///   regions remain meaningful if they are later unflagged.
+///
+/// If the length of *axis* is not a multiple of *factor*, the last group is
+/// shorter than the others.
+///
+/// # Panics
+///
+/// Panics if the shapes of the input arrays differ, or if *factor* is zero.
+pub fn average_complex_weighted<D: Dimension>(
+    data: ArrayView<Complex<f32>, D>,
+    weights: ArrayView<f32, D>,
+    flags: ArrayView<bool, D>,
+    factor: usize,
+    axis: Axis,
+) -> Averaged<D> {
+    assert!(factor > 0, "averaging factor must be positive");
+    assert_eq!(
+        data.shape(),
+        weights.shape(),
+        "data and weights differ in shape"
+    );
+    assert_eq!(
+        data.shape(),
+        flags.shape(),
+        "data and flags differ in shape"
+    );
+
+    let n_in = data.len_of(axis);
+    let n_out = n_in.div_ceil(factor);
+    let mut shape = data.raw_dim();
+    shape[axis.index()] = n_out;
+
+    let mut out_data = Array::from_elem(shape.clone(), Complex::new(0f32, 0.));
+    let mut out_weights = Array::from_elem(shape.clone(), 0f32);
+    let mut out_flags = Array::from_elem(shape, false);
+
+    Zip::from(data.lanes(axis))
+        .and(weights.lanes(axis))
+        .and(flags.lanes(axis))
+        .and(out_data.lanes_mut(axis))
+        .and(out_weights.lanes_mut(axis))
+        .and(out_flags.lanes_mut(axis))
+        .for_each(|d, w, f, mut od, mut ow, mut of| {
+            for g in 0..n_out {
+                let lo = g * factor;
+                let hi = (lo + factor).min(n_in);
+
+                // Accumulate in double precision: visibility sums over many
+                // samples lose precision quickly in single precision.
+                let mut good = (Complex::new(0f64, 0.), 0f64);
+                let mut all = (Complex::new(0f64, 0.), 0f64);
+                let mut plain = Complex::new(0f64, 0.);
+
+                for i in lo..hi {
+                    let v = Complex::new(d[i].re as f64, d[i].im as f64);
+                    let wt = w[i] as f64;
+                    all.0 += v * wt;
+                    all.1 += wt;
+                    plain += v;
+
+                    if !f[i] {
+                        good.0 += v * wt;
+                        good.1 += wt;
+                    }
+                }
+
+                let (value, weight, flag) = if good.1 != 0. {
+                    (good.0 / good.1, good.1, false)
+                } else if all.1 != 0. {
+                    (all.0 / all.1, all.1, true)
+                } else {
+                    (plain / (hi - lo) as f64, 0., true)
+                };
+
+                od[g] = Complex::new(value.re as f32, value.im as f32);
+                ow[g] = weight as f32;
+                of[g] = flag;
+            }
+        });
+
+    Averaged {
+        data: out_data,
+        weights: out_weights,
+        flags: out_flags,
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! Helpers for numbering baselines.
+//!
+//! Interferometric data are commonly stored with one record per baseline,
+//! ordered by the first antenna and then by the second antenna, with the first
+//! antenna number never exceeding the second. That is, the “upper triangle” of
+//! the antenna–antenna matrix is stored row by row. Depending on the data set,
+//! the diagonal (the autocorrelations) may or may not be included. The
+//! [`BaselineOrder`] type converts between antenna pairs and indices in these
+//! two conventions, and iterates over the baselines for a given number of
+//! antennas.
+//!
+//! Antennas are numbered from zero.
+//!
+//! ```rust
+//! use rubbl_core::baseline::BaselineOrder;
+//!
+//! let order = BaselineOrder::WithAutos;
+//! assert_eq!(order.n_baselines(128), 8256);
+//! assert_eq!(order.index(128, 1, 1), Some(128));
+//! assert_eq!(order.antennas(128, 128), Some((1, 1)));
+//!
+//! let pairs: Vec<_> = BaselineOrder::CrossOnly.baselines(3).collect();
+//! assert_eq!(pairs, vec![(0, 1), (0, 2), (1, 2)]);
+//! ```
+
+/// A convention for numbering the baselines formed by a set of antennas.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BaselineOrder {
+    /// Baselines include the autocorrelations, so that *N* antennas form
+    /// *N (N + 1) / 2* baselines: (0, 0), (0, 1), …, (0, N-1), (1, 1), ….
+    WithAutos,
+
+    /// Baselines exclude the autocorrelations, so that *N* antennas form
+    /// *N (N - 1) / 2* baselines: (0, 1), (0, 2), …, (0, N-1), (1, 2), ….
+    CrossOnly,
+}
+
+impl BaselineOrder {
+    /// The difference between the first and second antenna of the first
+    /// baseline in each row of the triangle.
+    fn offset(self) -> usize {
+        match self {
+            BaselineOrder::WithAutos => 0,
+            BaselineOrder::CrossOnly => 1,
+        }
+    }
+
+    /// Get the number of baselines formed by *n_ant* antennas.
+    pub fn n_baselines(self, n_ant: usize) -> usize {
+        match self {
+            BaselineOrder::WithAutos => n_ant * (n_ant + 1) / 2,
+            BaselineOrder::CrossOnly => n_ant * n_ant.saturating_sub(1) / 2,
+        }
+    }
+
+    /// Get the index of the baseline between antennas *ant1* and *ant2*.
+    ///
+    /// Returns `None` if either antenna number is not less than *n_ant*, if
+    /// *ant1* is greater than *ant2*, or if the antennas are the same and
+    /// autocorrelations are excluded. Callers that do not care about the
+    /// order of the antennas should sort them first.
+    pub fn index(self, n_ant: usize, ant1: usize, ant2: usize) -> Option<usize> {
+        let offset = self.offset();
+
+        if ant2 >= n_ant || ant1 + offset > ant2 {
+            return None;
+        }
+
+        // The number of baselines in the rows before `ant1`, plus the
+        // position within the row.
+        let row_len = n_ant - offset;
+        Some(ant1 * row_len - ant1 * ant1.saturating_sub(1) / 2 + (ant2 - ant1 - offset))
+    }
+
+    /// Get the antennas forming the baseline with the given index.
+    ///
+    /// Returns `None` if the index is not less than the number of baselines.
+    /// This takes time proportional to the number of antennas.
+    pub fn antennas(self, n_ant: usize, mut index: usize) -> Option<(usize, usize)> {
+        let offset = self.offset();
+
+        for ant1 in 0..n_ant {
+            let row_len = n_ant.saturating_sub(ant1 + offset);
+
+            if index < row_len {
+                return Some((ant1, ant1 + offset + index));
+            }
+
+            index -= row_len;
+        }
+
+        None
+    }
+
+    /// Iterate over the baselines formed by *n_ant* antennas, in index
+    /// order.
+    pub fn baselines(self, n_ant: usize) -> Baselines {
+        Baselines {
+            n_ant,
+            offset: self.offset(),
+            ant1: 0,
+            ant2: self.offset(),
+            remaining: self.n_baselines(n_ant),
+        }
+    }
+}
+
+/// An iterator over the antenna pairs of a set of baselines.
+///
+/// This is created by [`BaselineOrder::baselines`].
+#[derive(Clone, Debug)]
+pub struct Baselines {
+    n_ant: usize,
+    offset: usize,
+    ant1: usize,
+    ant2: usize,
+    remaining: usize,
+}
+
+impl Iterator for Baselines {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let item = (self.ant1, self.ant2);
+        self.remaining -= 1;
+        self.ant2 += 1;
+
+        if self.ant2 == self.n_ant {
+            self.ant1 += 1;
+            self.ant2 = self.ant1 + self.offset;
+        }
+
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Baselines {}
+
+impl std::iter::FusedIterator for Baselines {}
@@ -0,0 +1,178 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! Streaming large arrays in chunks.
+//!
+//! Radio data sets are often much larger than memory, so bulk I/O operations
+//! in the Rubbl framework move arrays one chunk at a time. An array is viewed
+//! as a sequence of *items* along its first (slowest-varying) axis — for a
+//! table column, the items are the cells of successive rows. An
+//! [`ArrayChunkSource`] produces the items in order and an [`ArrayChunkSink`]
+//! consumes them, so that readers and writers for different formats can be
+//! piped together without materializing the full array.
+//!
+//! The format crates implement these traits for their bulk I/O: CASA table
+//! columns can be read into sinks and written from sources, the arrays of
+//! FITS HDUs can be streamed with `rubbl_fits::HduArrayChunks`, and the
+//! correlator data of MIRIAD UV data sets with
+//! `rubbl_miriad::visdata::UvChunks`.
+//!
+//! This module also provides [`ArrayChunks`], which streams out an existing
+//! in-memory array, and [`ArrayCollector`], which assembles a stream into one.
+//!
+//! ```rust
+//! use rubbl_core::{
+//!     chunked::{ArrayChunkSink, ArrayChunkSource, ArrayChunks, ArrayCollector},
+//!     ndarray::{Array, Array3, Axis, IxDyn},
+//! };
+//!
+//! let data = Array3::from_shape_fn((10, 4, 2), |(i, j, k)| (i * 100 + j * 10 + k) as f32);
+//! let mut source = ArrayChunks::new(data.view());
+//! let mut sink = ArrayCollector::new(source.item_shape());
+//! let mut buf = Array::zeros(IxDyn(&[3, 4, 2]));
+//!
+//! loop {
+//!     let n = source.read_chunk(buf.view_mut()).unwrap();
+//!     if n == 0 {
+//!         break;
+//!     }
+//!     sink.write_chunk(buf.slice_axis(Axis(0), (0..n).into())).unwrap();
+//! }
+//!
+//! assert_eq!(sink.into_array(), data.into_dyn());
+//! ```
+
+use ndarray::{ArrayD, ArrayView, ArrayViewD, ArrayViewMutD, Axis, Dimension, IxDyn, RemoveAxis};
+use std::convert::Infallible;
+
+/// A producer of array data, one chunk of items at a time.
+pub trait ArrayChunkSource<T> {
+    /// The error type returned when the source cannot be read.
+    type Error;
+
+    /// The shape of each item, i.e. of all axes but the first.
+    fn item_shape(&self) -> &[usize];
+
+    /// Read the next chunk of items into *dest*.
+    ///
+    /// The first axis of *dest* gives the maximum number of items to read, and
+    /// its remaining axes must match [`Self::item_shape`]. Returns the number
+    /// of items actually read, which are stored at the start of *dest*. Fewer
+    /// items than requested are only returned once the source is exhausted,
+    /// after which this returns zero.
+    fn read_chunk(&mut self, dest: ArrayViewMutD<T>) -> Result<usize, Self::Error>;
+}
+
+/// A consumer of array data, one chunk of items at a time.
+pub trait ArrayChunkSink<T> {
+    /// The error type returned when the sink cannot be written.
+    type Error;
+
+    /// Write the next chunk of items.
+    ///
+    /// The first axis of *chunk* indexes the items, which may be zero in
+    /// number.
+    fn write_chunk(&mut self, chunk: ArrayViewD<T>) -> Result<(), Self::Error>;
+}
+
+/// An [`ArrayChunkSource`] that streams out an in-memory array.
+#[derive(Debug)]
+pub struct ArrayChunks<'a, T, D: Dimension> {
+    view: ArrayView<'a, T, D>,
+    item_shape: Vec<usize>,
+    next_item: usize,
+}
+
+impl<'a, T, D: RemoveAxis> ArrayChunks<'a, T, D> {
+    /// Stream out the items of *view* along its first axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *view* is zero-dimensional.
+    pub fn new(view: ArrayView<'a, T, D>) -> Self {
+        assert!(view.ndim() > 0, "cannot stream a zero-dimensional array");
+        let item_shape = view.shape()[1..].to_vec();
+
+        ArrayChunks {
+            view,
+            item_shape,
+            next_item: 0,
+        }
+    }
+}
+
+impl<'a, T: Clone, D: RemoveAxis> ArrayChunkSource<T> for ArrayChunks<'a, T, D> {
+    type Error = Infallible;
+
+    fn item_shape(&self) -> &[usize] {
+        &self.item_shape[..]
+    }
+
+    fn read_chunk(&mut self, mut dest: ArrayViewMutD<T>) -> Result<usize, Self::Error> {
+        let n_left = self.view.len_of(Axis(0)) - self.next_item;
+        let n = dest.len_of(Axis(0)).min(n_left);
+        let start = self.next_item;
+
+        dest.slice_axis_mut(Axis(0), (0..n).into()).assign(
+            &self
+                .view
+                .slice_axis(Axis(0), (start..start + n).into())
+                .into_dyn(),
+        );
+
+        self.next_item += n;
+        Ok(n)
+    }
+}
+
+/// An [`ArrayChunkSink`] that assembles its items into an in-memory array.
+#[derive(Clone, Debug)]
+pub struct ArrayCollector<T> {
+    item_shape: Vec<usize>,
+    n_items: usize,
+    data: Vec<T>,
+}
+
+impl<T: Clone> ArrayCollector<T> {
+    /// Create a new collector for items of the specified shape.
+    pub fn new(item_shape: &[usize]) -> Self {
+        ArrayCollector {
+            item_shape: item_shape.to_vec(),
+            n_items: 0,
+            data: Vec::new(),
+        }
+    }
+
+    /// The number of items collected so far.
+    pub fn n_items(&self) -> usize {
+        self.n_items
+    }
+
+    /// Consume the collector, returning the array of all of the items that
+    /// were written to it.
+    pub fn into_array(self) -> ArrayD<T> {
+        let mut shape = Vec::with_capacity(self.item_shape.len() + 1);
+        shape.push(self.n_items);
+        shape.extend_from_slice(&self.item_shape[..]);
+        ArrayD::from_shape_vec(IxDyn(&shape), self.data).unwrap()
+    }
+}
+
+impl<T: Clone> ArrayChunkSink<T> for ArrayCollector<T> {
+    type Error = Infallible;
+
+    /// # Panics
+    ///
+    /// Panics if the item shape of *chunk* does not match the one that the
+    /// collector was created with.
+    fn write_chunk(&mut self, chunk: ArrayViewD<T>) -> Result<(), Self::Error> {
+        assert_eq!(
+            &chunk.shape()[1..],
+            &self.item_shape[..],
+            "chunk item shape does not match collector"
+        );
+        self.n_items += chunk.len_of(Axis(0));
+        self.data.extend(chunk.iter().cloned());
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! Physical constants.
+
+/// The speed of light in vacuum, in meters per second.
+pub const SPEED_OF_LIGHT: f64 = 299_792_458.;
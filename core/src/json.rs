@@ -0,0 +1,46 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! Helpers for writing JSON.
+//!
+//! Several parts of Rubbl emit small JSON documents by hand. They all encode
+//! strings with the functions here, so that they agree on escaping.
+
+use std::fmt::Write;
+
+/// Append *s* to *dest* as a JSON string literal, with surrounding quotes.
+///
+/// ```rust
+/// let mut out = String::from("[");
+/// rubbl_core::json::push_string(&mut out, "say \"hi\"\n");
+/// out.push(']');
+/// assert_eq!(out, r#"["say \"hi\"\n"]"#);
+/// ```
+pub fn push_string(dest: &mut String, s: &str) {
+    dest.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => dest.push_str("\\\""),
+            '\\' => dest.push_str("\\\\"),
+            '\n' => dest.push_str("\\n"),
+            '\r' => dest.push_str("\\r"),
+            '\t' => dest.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(dest, "\\u{:04x}", c as u32).unwrap(),
+            c => dest.push(c),
+        }
+    }
+
+    dest.push('"');
+}
+
+/// Encode *s* as a JSON string literal, with surrounding quotes.
+///
+/// ```rust
+/// assert_eq!(rubbl_core::json::quote("a\u{1}b"), r#""a\u0001b""#);
+/// ```
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    push_string(&mut out, s);
+    out
+}
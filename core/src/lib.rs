@@ -30,10 +30,19 @@ pub use anyhow;
 pub use ndarray::{self, Array, CowArray};
 pub use num_complex::{self, Complex};
 
+pub mod array_layout;
+pub mod average;
+pub mod baseline;
+pub mod chunked;
+pub mod constants;
 pub mod io;
+pub mod json;
 #[cfg(feature = "notifications")]
 pub mod notify;
 pub mod num;
+pub mod stokes;
+pub mod time;
+pub mod transpose;
 
 /// A “contextualized try” macro.
 ///
@@ -0,0 +1,131 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+/*!
+A notification backend that emits machine-readable JSON.
+
+This is intended for programs that run as part of a larger service, where
+notifications and progress reports should be forwarded to some other system
+rather than shown to a person at a terminal. Each notification or progress
+event is written as a single JSON object on its own line.
+
+*/
+
+use anyhow::Error;
+use std::fmt::{Arguments, Write as FmtWrite};
+use std::io::Write;
+
+use super::{NotificationBackend, NotificationKind, ProgressEvent};
+use crate::json::push_string;
+
+/// A notification backend that writes JSON Lines to a stream.
+///
+/// Notifications are written as objects like
+/// `{"type":"notification","kind":"warning","message":"...","causes":["..."]}`,
+/// and progress events as objects like
+/// `{"type":"progress","task":"...","current":10,"total":100,"unit":"rows",
+/// "bytes_per_sec":1.5e6,"finished":false}`, where the `total` and
+/// `bytes_per_sec` fields are `null` if unknown.
+#[derive(Debug)]
+pub struct JsonNotificationBackend<W: Write> {
+    dest: W,
+}
+
+impl<W: Write> JsonNotificationBackend<W> {
+    /// Create a new backend writing to *dest*.
+    pub fn new(dest: W) -> Self {
+        JsonNotificationBackend { dest }
+    }
+
+    /// Consume this backend, returning the underlying stream.
+    pub fn into_inner(self) -> W {
+        self.dest
+    }
+
+    fn emit(&mut self, line: &str) {
+        // Like the terminal backend, there's nothing useful we can do if
+        // notifications can't be written.
+        writeln!(self.dest, "{}", line).expect("failed to write notification");
+        self.dest.flush().expect("failed to write notification");
+    }
+}
+
+impl<W: Write> NotificationBackend for JsonNotificationBackend<W> {
+    fn notify(&mut self, kind: NotificationKind, args: Arguments, err: Option<Error>) {
+        let kind = match kind {
+            NotificationKind::Note => "note",
+            NotificationKind::Warning => "warning",
+            NotificationKind::Severe => "severe",
+            NotificationKind::Fatal => "fatal",
+        };
+
+        let mut line = String::from("{\"type\":\"notification\",\"kind\":\"");
+        line.push_str(kind);
+        line.push_str("\",\"message\":");
+        push_string(&mut line, &args.to_string());
+        line.push_str(",\"causes\":[");
+
+        if let Some(e) = err {
+            for (i, cause) in e.chain().enumerate() {
+                if i != 0 {
+                    line.push(',');
+                }
+
+                push_string(&mut line, &cause.to_string());
+            }
+        }
+
+        line.push_str("]}");
+        self.emit(&line);
+    }
+
+    fn progress(&mut self, event: &ProgressEvent) {
+        let line = progress_event_to_json(event);
+        self.emit(&line);
+    }
+}
+
+/// Serialize a progress event as a single-line JSON object.
+///
+/// The format is described in the documentation of
+/// [`JsonNotificationBackend`].
+///
+/// ```rust
+/// use rubbl_core::notify::{json::progress_event_to_json, ProgressEvent};
+///
+/// let event = ProgressEvent {
+///     task: "copying \"DATA\"".to_owned(),
+///     current: 10,
+///     total: Some(40),
+///     unit: "rows".to_owned(),
+///     bytes_per_sec: None,
+///     finished: false,
+/// };
+///
+/// assert_eq!(
+///     progress_event_to_json(&event),
+///     r#"{"type":"progress","task":"copying \"DATA\"","current":10,"total":40,"unit":"rows","bytes_per_sec":null,"finished":false}"#
+/// );
+/// ```
+pub fn progress_event_to_json(event: &ProgressEvent) -> String {
+    let mut line = String::from("{\"type\":\"progress\",\"task\":");
+    push_string(&mut line, &event.task);
+    write!(line, ",\"current\":{},\"total\":", event.current).unwrap();
+
+    match event.total {
+        Some(t) => write!(line, "{}", t).unwrap(),
+        None => line.push_str("null"),
+    }
+
+    line.push_str(",\"unit\":");
+    push_string(&mut line, &event.unit);
+    line.push_str(",\"bytes_per_sec\":");
+
+    match event.bytes_per_sec {
+        Some(r) if r.is_finite() => write!(line, "{:e}", r).unwrap(),
+        _ => line.push_str("null"),
+    }
+
+    write!(line, ",\"finished\":{}}}", event.finished).unwrap();
+    line
+}
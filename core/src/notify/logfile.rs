@@ -0,0 +1,92 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+/*!
+A notification backend that keeps a plain-text log.
+
+Programs run by batch schedulers such as SLURM usually have their terminal
+output redirected to files that are awkward to find and interleave standard
+output with standard error. This backend writes an uncolored copy of every
+notification to a separate stream, such as a log file, before forwarding it to
+another backend.
+
+*/
+
+use anyhow::Error;
+use std::fmt::Arguments;
+use std::io::Write;
+
+use super::{NotificationBackend, NotificationKind, ProgressEvent};
+
+/// A notification backend that copies notifications to a log stream.
+///
+/// Each notification is written as a line with a prefix like `warning:`,
+/// followed by one `caused by:` line for each error in its causal chain.
+/// Progress events are only logged when their operations finish. All
+/// notifications are then passed on to the wrapped backend, which may filter
+/// them according to its own settings: the log always gets everything.
+#[derive(Debug)]
+pub struct LoggingNotificationBackend<B, W: Write> {
+    inner: B,
+    log: W,
+}
+
+impl<B: NotificationBackend, W: Write> LoggingNotificationBackend<B, W> {
+    /// Create a new backend that logs to *log* and forwards to *inner*.
+    pub fn new(inner: B, log: W) -> Self {
+        LoggingNotificationBackend { inner, log }
+    }
+
+    /// Consume this backend, returning the wrapped backend and log stream.
+    pub fn into_inner(self) -> (B, W) {
+        (self.inner, self.log)
+    }
+
+    /// Write an error to the log without forwarding it.
+    ///
+    /// This is intended for the final error that causes a program to exit,
+    /// which is generally reported to the user by other means.
+    pub fn log_error(&mut self, err: &Error) {
+        self.write_chain("error:", &err.to_string(), Some(err));
+    }
+
+    fn write_chain(&mut self, prefix: &str, message: &str, err: Option<&Error>) {
+        // Like the terminal backend, there's nothing useful we can do if
+        // notifications can't be written.
+        writeln!(self.log, "{} {}", prefix, message).expect("failed to write to log");
+
+        if let Some(e) = err {
+            // If the message is just the top-level error, don't repeat it.
+            let skip = if e.to_string() == message { 1 } else { 0 };
+
+            for cause in e.chain().skip(skip) {
+                writeln!(self.log, "caused by: {}", cause).expect("failed to write to log");
+            }
+        }
+
+        self.log.flush().expect("failed to write to log");
+    }
+}
+
+impl<B: NotificationBackend, W: Write> NotificationBackend for LoggingNotificationBackend<B, W> {
+    fn notify(&mut self, kind: NotificationKind, args: Arguments, err: Option<Error>) {
+        let prefix = match kind {
+            NotificationKind::Note => "note:",
+            NotificationKind::Warning => "warning:",
+            NotificationKind::Severe => "severe:",
+            NotificationKind::Fatal => "fatal:",
+        };
+
+        self.write_chain(prefix, &args.to_string(), err.as_ref());
+        self.inner.notify(kind, args, err);
+    }
+
+    fn progress(&mut self, event: &ProgressEvent) {
+        if event.finished {
+            writeln!(self.log, "progress: {}", event).expect("failed to write to log");
+            self.log.flush().expect("failed to write to log");
+        }
+
+        self.inner.progress(event);
+    }
+}
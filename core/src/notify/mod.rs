@@ -14,14 +14,21 @@ engine. (Which the author of this module also wrote.)
 
 */
 
+pub mod json;
+pub mod logfile;
 #[macro_use]
 pub mod termcolor;
+#[cfg(feature = "tracing-bridge")]
+pub mod tracing_bridge;
 
-use anyhow::Error;
+use anyhow::{Context, Error};
 use clap;
 use std::cmp;
-use std::fmt::Arguments;
+use std::fmt::{self, Arguments};
+use std::fs::File;
+use std::path::PathBuf;
 use std::result::Result as StdResult;
+use std::time::{Duration, Instant};
 
 /// How chatty the notification system should be.
 #[repr(usize)]
@@ -55,6 +62,19 @@ impl Ord for ChatterLevel {
     }
 }
 
+/// Whether notifications written to the terminal should be colorized.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorMode {
+    /// Use color if the output stream is a terminal that supports it.
+    Auto,
+
+    /// Always use color.
+    Always,
+
+    /// Never use color.
+    Never,
+}
+
 /// The kind of notification that is being produced.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum NotificationKind {
@@ -71,6 +91,68 @@ pub enum NotificationKind {
     Fatal,
 }
 
+/// A report on the progress of a long-running operation.
+///
+/// Operations that can take a while, such as copying table columns or
+/// converting data sets, emit these periodically through
+/// [`NotificationBackend::progress`]. They are usually created by a
+/// [`ProgressTracker`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgressEvent {
+    /// A short description of the operation, such as "copying rows".
+    pub task: String,
+
+    /// The number of units of work completed so far.
+    pub current: u64,
+
+    /// The total number of units of work, if known.
+    pub total: Option<u64>,
+
+    /// The name of the units of work, such as "rows".
+    pub unit: String,
+
+    /// The average data throughput so far, in bytes per second, if known.
+    pub bytes_per_sec: Option<f64>,
+
+    /// Whether the operation has finished. This is true for the last event
+    /// emitted for each operation, and only that one.
+    pub finished: bool,
+}
+
+impl ProgressEvent {
+    /// Get the fraction of the operation that has been completed, if the
+    /// total amount of work is known.
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(1.),
+            Some(t) => Some((self.current as f64 / t as f64).min(1.)),
+            None => None,
+        }
+    }
+}
+
+impl fmt::Display for ProgressEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.task, self.current)?;
+
+        if let Some(t) = self.total {
+            write!(f, "/{}", t)?;
+        }
+
+        write!(f, " {}", self.unit)?;
+
+        if let Some(frac) = self.fraction() {
+            write!(f, " ({:.0}%)", frac * 100.)?;
+        }
+
+        if let Some(r) = self.bytes_per_sec {
+            write!(f, ", {:.1} MiB/s", r / (1024. * 1024.))?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Trait for type that handle notifications to the user.
 pub trait NotificationBackend {
     /// Notify the user about an event.
@@ -78,6 +160,105 @@ pub trait NotificationBackend {
     /// If `err` is not `None`, the information contained in the object should
     /// be reported after the main message.
     fn notify(&mut self, kind: NotificationKind, args: Arguments, err: Option<Error>);
+
+    /// Report on the progress of a long-running operation.
+    ///
+    /// The default implementation ignores the event.
+    fn progress(&mut self, _event: &ProgressEvent) {}
+}
+
+impl<B: NotificationBackend + ?Sized> NotificationBackend for &mut B {
+    fn notify(&mut self, kind: NotificationKind, args: Arguments, err: Option<Error>) {
+        (**self).notify(kind, args, err)
+    }
+
+    fn progress(&mut self, event: &ProgressEvent) {
+        (**self).progress(event)
+    }
+}
+
+/// A helper for emitting [`ProgressEvent`]s from a long-running operation.
+///
+/// The tracker keeps count of the work done and the bytes processed, and
+/// emits an event through a notification backend at most once per
+/// [`Self::set_min_interval`] (a quarter of a second by default), so that it
+/// can be advanced cheaply in tight loops.
+#[derive(Clone, Debug)]
+pub struct ProgressTracker {
+    task: String,
+    unit: String,
+    total: Option<u64>,
+    current: u64,
+    n_bytes: u64,
+    start: Instant,
+    last_emit: Option<Instant>,
+    min_interval: Duration,
+}
+
+impl ProgressTracker {
+    /// Start tracking an operation that involves *total* units of work, if
+    /// known, named *unit*.
+    pub fn new<T: Into<String>, U: Into<String>>(task: T, unit: U, total: Option<u64>) -> Self {
+        ProgressTracker {
+            task: task.into(),
+            unit: unit.into(),
+            total,
+            current: 0,
+            n_bytes: 0,
+            start: Instant::now(),
+            last_emit: None,
+            min_interval: Duration::from_millis(250),
+        }
+    }
+
+    /// Set the minimum time between the emission of progress events.
+    pub fn set_min_interval(&mut self, interval: Duration) -> &mut Self {
+        self.min_interval = interval;
+        self
+    }
+
+    /// Record that *n* more units of work have been done, processing *n_bytes*
+    /// bytes of data, and emit an event if one is due.
+    pub fn advance<B: NotificationBackend + ?Sized>(&mut self, nbe: &mut B, n: u64, n_bytes: u64) {
+        self.current += n;
+        self.n_bytes += n_bytes;
+
+        let now = Instant::now();
+        let due = match self.last_emit {
+            Some(t) => now.duration_since(t) >= self.min_interval,
+            None => true,
+        };
+
+        if due {
+            self.last_emit = Some(now);
+            nbe.progress(&self.event(false));
+        }
+    }
+
+    /// Record that the operation has finished, and emit a final event.
+    pub fn finish<B: NotificationBackend + ?Sized>(&mut self, nbe: &mut B) {
+        nbe.progress(&self.event(true));
+    }
+
+    /// Get an event describing the current state of the operation.
+    pub fn event(&self, finished: bool) -> ProgressEvent {
+        let elapsed = self.start.elapsed().as_secs_f64();
+
+        let bytes_per_sec = if self.n_bytes > 0 && elapsed > 0. {
+            Some(self.n_bytes as f64 / elapsed)
+        } else {
+            None
+        };
+
+        ProgressEvent {
+            task: self.task.clone(),
+            current: self.current,
+            total: self.total,
+            unit: self.unit.clone(),
+            bytes_per_sec,
+            finished,
+        }
+    }
 }
 
 /// Send an informational notification to the user.
@@ -206,51 +387,147 @@ impl NotificationBackend for BufferingNotificationBackend {
             err,
         });
     }
+
+    // Progress events are only meaningful when they are delivered promptly,
+    // so they are not buffered.
 }
 
 /// An extension trait for adding standard notification arguments to a clap
 /// Command object.
 pub trait ClapNotificationArgsExt {
     /// Add standard Rubbl notification-related arguments to this Command.
+    ///
+    /// These are interpreted by [`run_with_notifications`].
     fn rubbl_notify_args(self) -> Self;
 }
 
 impl ClapNotificationArgsExt for clap::Command {
     fn rubbl_notify_args(self) -> Self {
-        self.arg(
-            clap::Arg::new("chatter_level")
-                .long("chatter")
-                .short('c')
-                .value_name("LEVEL")
-                .help("How much chatter to print when running")
-                .value_parser(["default", "minimal"])
-                .default_value("default"),
-        )
+        let cmd = self
+            .arg(
+                clap::Arg::new("chatter_level")
+                    .long("chatter")
+                    .short('c')
+                    .value_name("LEVEL")
+                    .help("How much chatter to print when running")
+                    .value_parser(["default", "minimal"])
+                    .default_value("default"),
+            )
+            .arg(
+                clap::Arg::new("quiet")
+                    .long("quiet")
+                    .short('q')
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Only report warnings and errors; the same as \"--chatter=minimal\""),
+            )
+            .arg(
+                clap::Arg::new("color_mode")
+                    .long("color")
+                    .value_name("WHEN")
+                    .help("Whether to colorize terminal output")
+                    .value_parser(["auto", "always", "never"])
+                    .default_value("auto"),
+            )
+            .arg(
+                clap::Arg::new("log_file")
+                    .long("log-file")
+                    .value_name("PATH")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help("Also write all notifications to this file, without color"),
+            );
+
+        #[cfg(feature = "tracing-bridge")]
+        let cmd = cmd.arg(
+            clap::Arg::new("tracing")
+                .long("tracing")
+                .action(clap::ArgAction::SetTrue)
+                .help("Send notifications to the tracing framework instead of the terminal"),
+        );
+
+        cmd
     }
 }
 
 /// Run a function with colorized reporting of errors.
+///
+/// The notification backend passed to *inner* is configured according to the
+/// arguments added by [`ClapNotificationArgsExt::rubbl_notify_args`]. By
+/// default it writes to the terminal, colorizing its output if it is a
+/// terminal. If a log file is requested, all notifications are also written
+/// there. If this crate’s `tracing-bridge` feature is enabled and the
+/// `--tracing` option is given, notifications are instead emitted as
+/// [tracing](https://docs.rs/tracing) events, and the program is responsible
+/// for installing a subscriber to collect them.
 pub fn run_with_notifications<E, F>(matches: clap::ArgMatches, inner: F) -> i32
 where
     E: Into<Error>,
     F: FnOnce(clap::ArgMatches, &mut dyn NotificationBackend) -> StdResult<i32, E>,
 {
-    let chatter = match matches.get_one::<String>("chatter_level").unwrap().as_ref() {
+    let mut chatter = match matches.get_one::<String>("chatter_level").unwrap().as_ref() {
         "default" => ChatterLevel::Normal,
         "minimal" => ChatterLevel::Minimal,
         _ => unreachable!(),
     };
 
-    // Set up colorized output. At one point we might add an option to disable
-    // this, which is why the inner function takes a boxed trait object.
+    if matches.get_flag("quiet") {
+        chatter = ChatterLevel::Minimal;
+    }
+
+    let color = match matches.get_one::<String>("color_mode").unwrap().as_ref() {
+        "auto" => ColorMode::Auto,
+        "always" => ColorMode::Always,
+        "never" => ColorMode::Never,
+        _ => unreachable!(),
+    };
+
+    #[cfg(feature = "tracing-bridge")]
+    {
+        if matches.get_flag("tracing") {
+            let mut tnb = tracing_bridge::TracingNotificationBackend::new(chatter);
+
+            return match inner(matches, &mut tnb) {
+                Ok(ret) => ret,
+
+                Err(e) => {
+                    tnb.bare_error(e);
+                    1
+                }
+            };
+        }
+    }
+
+    let mut tnb = termcolor::TermcolorNotificationBackend::new_with_color(chatter, color);
+
+    // The log file, if any, gets a copy of everything, including the final
+    // error if there is one.
+
+    let log_path = matches.get_one::<PathBuf>("log_file").cloned();
 
-    let mut tnb = termcolor::TermcolorNotificationBackend::new(chatter);
+    let result = match log_path {
+        None => inner(matches, &mut tnb).map_err(|e| e.into()),
 
-    // Now that we've got colorized output, we're ready to pass off to the
-    // inner function ... all so that we can print out the word "error:" in
-    // red.
+        Some(path) => {
+            let log = match File::create(&path)
+                .with_context(|| format!("failed to create log file `{}`", path.display()))
+            {
+                Ok(f) => f,
+                Err(e) => {
+                    tnb.bare_error(e);
+                    return 1;
+                }
+            };
+
+            let mut lnb = logfile::LoggingNotificationBackend::new(&mut tnb, log);
+
+            inner(matches, &mut lnb).map_err(|e| {
+                let e = e.into();
+                lnb.log_error(&e);
+                e
+            })
+        }
+    };
 
-    match inner(matches, &mut tnb) {
+    match result {
         Ok(ret) => ret,
 
         Err(e) => {
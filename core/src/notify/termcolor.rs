@@ -15,18 +15,25 @@ engine. (Which the author of this module also wrote.)
 
 use anyhow::Error;
 use std::fmt::Arguments;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
-use super::{ChatterLevel, NotificationBackend, NotificationKind};
+use super::{ChatterLevel, ColorMode, NotificationBackend, NotificationKind, ProgressEvent};
+
+/// The width of the bar drawn for progress events, in characters.
+const PROGRESS_BAR_WIDTH: usize = 30;
 
 /// A notification backend that writes colorized output to the terminal.
 ///
 /// This struct implements the NotificationBackend trait, and emits
 /// notifications to standard output and standard error with colorized
-/// prefixes.
+/// prefixes. Progress events are drawn as a progress bar on standard error if
+/// it is a terminal; otherwise, only the completion of each operation is
+/// reported.
 pub struct TermcolorNotificationBackend {
     chatter: ChatterLevel,
+    progress_tty: bool,
+    progress_active: bool,
     stdout: StandardStream,
     stderr: StandardStream,
     note_spec: ColorSpec,
@@ -39,6 +46,23 @@ pub struct TermcolorNotificationBackend {
 impl TermcolorNotificationBackend {
     /// Create a new TermcolorNotificationBackend.
     pub fn new(chatter: ChatterLevel) -> TermcolorNotificationBackend {
+        Self::new_with_color(chatter, ColorMode::Auto)
+    }
+
+    /// Create a new TermcolorNotificationBackend with the specified coloring
+    /// behavior.
+    ///
+    /// In the [`ColorMode::Auto`] mode, each stream is colorized only if it is
+    /// a terminal, so that redirected output (e.g., to the log files of a
+    /// batch scheduler) doesn't contain ANSI escape sequences.
+    pub fn new_with_color(chatter: ChatterLevel, color: ColorMode) -> TermcolorNotificationBackend {
+        let choice = |is_tty: bool| match color {
+            ColorMode::Auto if is_tty => ColorChoice::Auto,
+            ColorMode::Auto => ColorChoice::Never,
+            ColorMode::Always => ColorChoice::Always,
+            ColorMode::Never => ColorChoice::Never,
+        };
+
         let mut note_spec = ColorSpec::new();
         note_spec.set_fg(Some(Color::Green)).set_bold(true);
 
@@ -56,8 +80,10 @@ impl TermcolorNotificationBackend {
 
         TermcolorNotificationBackend {
             chatter,
-            stdout: StandardStream::stdout(ColorChoice::Auto),
-            stderr: StandardStream::stderr(ColorChoice::Auto),
+            progress_tty: std::io::stderr().is_terminal(),
+            progress_active: false,
+            stdout: StandardStream::stdout(choice(std::io::stdout().is_terminal())),
+            stderr: StandardStream::stderr(choice(std::io::stderr().is_terminal())),
             note_spec,
             //highlight_spec: highlight_spec,
             warning_spec,
@@ -104,7 +130,18 @@ impl TermcolorNotificationBackend {
         f(stream);
     }
 
+    /// If a progress bar is being drawn, move past it so that other output
+    /// doesn't overwrite it.
+    fn end_progress_line(&mut self) {
+        if self.progress_active {
+            writeln!(self.stderr).expect("failed to write to standard stream");
+            self.progress_active = false;
+        }
+    }
+
     fn generic_message(&mut self, kind: NotificationKind, prefix: Option<&str>, args: Arguments) {
+        self.end_progress_line();
+
         let text = match prefix {
             Some(s) => s,
             None => match kind {
@@ -156,6 +193,32 @@ impl TermcolorNotificationBackend {
     }
 }
 
+fn format_progress(event: &ProgressEvent) -> String {
+    let mut text = format!("{}: ", event.task);
+
+    if let Some(frac) = event.fraction() {
+        let n_full = (frac * PROGRESS_BAR_WIDTH as f64).round() as usize;
+        text.push('[');
+        text.extend(std::iter::repeat_n('#', n_full));
+        text.extend(std::iter::repeat_n(' ', PROGRESS_BAR_WIDTH - n_full));
+        text.push_str(&format!("] {:3.0}% ", frac * 100.));
+    }
+
+    text.push_str(&format!("{}", event.current));
+
+    if let Some(t) = event.total {
+        text.push_str(&format!("/{}", t));
+    }
+
+    text.push_str(&format!(" {}", event.unit));
+
+    if let Some(r) = event.bytes_per_sec {
+        text.push_str(&format!(", {:.1} MiB/s", r / (1024. * 1024.)));
+    }
+
+    text
+}
+
 impl NotificationBackend for TermcolorNotificationBackend {
     fn notify(&mut self, kind: NotificationKind, args: Arguments, err: Option<Error>) {
         self.generic_message(kind, None, args);
@@ -172,4 +235,31 @@ impl NotificationBackend for TermcolorNotificationBackend {
             });
         }
     }
+
+    fn progress(&mut self, event: &ProgressEvent) {
+        if self.chatter <= ChatterLevel::Minimal {
+            return;
+        }
+
+        if self.progress_tty {
+            // Redraw the bar in place, clearing any leftovers from a longer
+            // previous line.
+            write!(self.stderr, "\r{}\x1b[K", format_progress(event))
+                .expect("failed to write to standard stream");
+            self.stderr
+                .flush()
+                .expect("failed to write to standard stream");
+            self.progress_active = true;
+
+            if event.finished {
+                self.end_progress_line();
+            }
+        } else if event.finished {
+            self.generic_message(
+                NotificationKind::Note,
+                None,
+                format_args!("{}", format_progress(event)),
+            );
+        }
+    }
 }
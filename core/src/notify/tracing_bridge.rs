@@ -0,0 +1,77 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+/*!
+A notification backend that emits [tracing](https://docs.rs/tracing) events.
+
+This allows Rubbl notifications to be integrated with the logging
+infrastructure of larger programs, which can filter, format, and route them
+using the subscriber of their choice. This module is only available if this
+crate’s `tracing-bridge` feature is enabled.
+
+*/
+
+use anyhow::Error;
+use std::fmt::Arguments;
+use tracing::{debug, error, info, warn};
+
+use super::{ChatterLevel, NotificationBackend, NotificationKind, ProgressEvent};
+
+/// A notification backend that emits tracing events with the target `rubbl`.
+///
+/// Notes are emitted at the `INFO` level, warnings at the `WARN` level, and
+/// severe and fatal notifications at the `ERROR` level. If an error is
+/// associated with a notification, its causal chain is attached as the
+/// `error` field. Progress events are emitted at the `DEBUG` level, except
+/// for the completion of each operation, which is emitted at the `INFO`
+/// level. At the minimal chatter level, notes and progress events are
+/// dropped.
+#[derive(Debug)]
+pub struct TracingNotificationBackend {
+    chatter: ChatterLevel,
+}
+
+impl TracingNotificationBackend {
+    /// Create a new TracingNotificationBackend.
+    pub fn new(chatter: ChatterLevel) -> Self {
+        TracingNotificationBackend { chatter }
+    }
+
+    /// Emit an error, including its causal chain, at the `ERROR` level.
+    pub fn bare_error<E: Into<Error>>(&mut self, err: E) {
+        let err = err.into();
+        error!(target: "rubbl", "{:#}", err);
+    }
+}
+
+impl NotificationBackend for TracingNotificationBackend {
+    fn notify(&mut self, kind: NotificationKind, args: Arguments, err: Option<Error>) {
+        if kind == NotificationKind::Note && self.chatter <= ChatterLevel::Minimal {
+            return;
+        }
+
+        // The alternate formatting of anyhow errors includes all causes.
+        let err = err.map(|e| format!("{:#}", e));
+
+        match (kind, err) {
+            (NotificationKind::Note, None) => info!(target: "rubbl", "{}", args),
+            (NotificationKind::Note, Some(e)) => info!(target: "rubbl", error = %e, "{}", args),
+            (NotificationKind::Warning, None) => warn!(target: "rubbl", "{}", args),
+            (NotificationKind::Warning, Some(e)) => warn!(target: "rubbl", error = %e, "{}", args),
+            (_, None) => error!(target: "rubbl", "{}", args),
+            (_, Some(e)) => error!(target: "rubbl", error = %e, "{}", args),
+        }
+    }
+
+    fn progress(&mut self, event: &ProgressEvent) {
+        if self.chatter <= ChatterLevel::Minimal {
+            return;
+        }
+
+        if event.finished {
+            info!(target: "rubbl", task = %event.task, current = event.current, "{}", event);
+        } else {
+            debug!(target: "rubbl", task = %event.task, current = event.current, "{}", event);
+        }
+    }
+}
@@ -0,0 +1,427 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! Polarization products and conversions between them.
+//!
+//! The [`Stokes`] type enumerates the polarization products known to
+//! casacore, using the codes found in the `CORR_TYPE` column of the
+//! `POLARIZATION` table of a Measurement Set. The [`PolConverter`] type
+//! converts visibilities between sets of products, for instance from the
+//! correlations of linear feeds to Stokes parameters:
+//!
+//! ```rust
+//! use rubbl_core::{stokes::{PolConverter, Stokes}, Complex};
+//!
+//! let conv = PolConverter::new(
+//!     &[Stokes::XX, Stokes::XY, Stokes::YX, Stokes::YY],
+//!     &[Stokes::I, Stokes::Q],
+//! ).unwrap();
+//!
+//! let vis = [
+//!     Complex::new(3f32, 0.),
+//!     Complex::new(0., 0.),
+//!     Complex::new(0., 0.),
+//!     Complex::new(1., 0.),
+//! ];
+//! let mut iq = [Complex::new(0f32, 0.); 2];
+//! conv.convert(&vis, &mut iq);
+//! assert_eq!(iq, [Complex::new(2., 0.), Complex::new(1., 0.)]);
+//! ```
+//!
+//! The conventions are those of casacore and CASA, in which the parallel-hand
+//! correlations are *XX = I + Q* and *RR = I + V*, so that, for instance,
+//! *I = (XX + YY) / 2*.
+
+use ndarray::{Array, ArrayView, Axis, Dimension, Zip};
+use num_complex::Complex;
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+/// An error type for polarization handling.
+#[derive(Error, Debug)]
+pub enum StokesError {
+    /// A polarization name was not recognized.
+    #[error("unrecognized polarization product \"{0}\"")]
+    UnknownName(String),
+
+    /// A requested product cannot be computed from the available ones.
+    #[error("polarization product {0} cannot be computed from the input products")]
+    NotConvertible(Stokes),
+}
+
+macro_rules! stokes_types {
+    ($($variant:ident = $code:literal),* $(,)?) => {
+        /// A polarization product, following casacore’s `Stokes::StokesTypes`.
+        #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+        #[allow(missing_docs)]
+        pub enum Stokes {
+            $($variant,)*
+        }
+
+        impl Stokes {
+            /// Get the casacore code for this polarization product, as used
+            /// in the `CORR_TYPE` column.
+            pub fn code(self) -> i32 {
+                match self {
+                    $(Stokes::$variant => $code,)*
+                }
+            }
+
+            /// Get the polarization product with the given casacore code.
+            pub fn from_code(code: i32) -> Option<Self> {
+                match code {
+                    $($code => Some(Stokes::$variant),)*
+                    _ => None,
+                }
+            }
+
+            /// Get the casacore name of this polarization product.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Stokes::$variant => stringify!($variant),)*
+                }
+            }
+        }
+
+        impl FromStr for Stokes {
+            type Err = StokesError;
+
+            fn from_str(s: &str) -> Result<Self, StokesError> {
+                match s {
+                    $(stringify!($variant) => Ok(Stokes::$variant),)*
+                    _ => Err(StokesError::UnknownName(s.to_owned())),
+                }
+            }
+        }
+    };
+}
+
+stokes_types! {
+    I = 1, Q = 2, U = 3, V = 4,
+    RR = 5, RL = 6, LR = 7, LL = 8,
+    XX = 9, XY = 10, YX = 11, YY = 12,
+    RX = 13, RY = 14, LX = 15, LY = 16,
+    XR = 17, XL = 18, YR = 19, YL = 20,
+    PP = 21, PQ = 22, QP = 23, QQ = 24,
+    RCircular = 25, LCircular = 26, Linear = 27,
+    Ptotal = 28, Plinear = 29, PFtotal = 30, PFlinear = 31, Pangle = 32,
+}
+
+impl fmt::Display for Stokes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Stokes {
+    /// Get the polarization product with the given AIPS code, as also used
+    /// by MIRIAD and FITS-IDI.
+    pub fn from_aips_code(code: i32) -> Option<Self> {
+        Some(match code {
+            1 => Stokes::I,
+            2 => Stokes::Q,
+            3 => Stokes::U,
+            4 => Stokes::V,
+            -1 => Stokes::RR,
+            -2 => Stokes::LL,
+            -3 => Stokes::RL,
+            -4 => Stokes::LR,
+            -5 => Stokes::XX,
+            -6 => Stokes::YY,
+            -7 => Stokes::XY,
+            -8 => Stokes::YX,
+            _ => return None,
+        })
+    }
+
+    /// Get the AIPS code for this polarization product, if it has one.
+    pub fn aips_code(self) -> Option<i32> {
+        Some(match self {
+            Stokes::I => 1,
+            Stokes::Q => 2,
+            Stokes::U => 3,
+            Stokes::V => 4,
+            Stokes::RR => -1,
+            Stokes::LL => -2,
+            Stokes::RL => -3,
+            Stokes::LR => -4,
+            Stokes::XX => -5,
+            Stokes::YY => -6,
+            Stokes::XY => -7,
+            Stokes::YX => -8,
+            _ => return None,
+        })
+    }
+
+    /// Get the indices of the two receptors whose correlation gives this
+    /// product, as used in the `CORR_PRODUCT` column.
+    ///
+    /// Receptor 0 is R, X, or P and receptor 1 is L, Y, or Q. Returns `None`
+    /// for products that are not correlations, such as the Stokes parameters.
+    pub fn receptors(self) -> Option<[i32; 2]> {
+        Some(match self {
+            Stokes::RR | Stokes::XX | Stokes::PP | Stokes::RX | Stokes::XR => [0, 0],
+            Stokes::RL | Stokes::XY | Stokes::PQ | Stokes::RY | Stokes::XL => [0, 1],
+            Stokes::LR | Stokes::YX | Stokes::QP | Stokes::LX | Stokes::YR => [1, 0],
+            Stokes::LL | Stokes::YY | Stokes::QQ | Stokes::LY | Stokes::YL => [1, 1],
+            _ => return None,
+        })
+    }
+
+    /// Whether this is a correlation of two linear feeds.
+    pub fn is_linear(self) -> bool {
+        matches!(self, Stokes::XX | Stokes::XY | Stokes::YX | Stokes::YY)
+    }
+
+    /// Whether this is a correlation of two circular feeds.
+    pub fn is_circular(self) -> bool {
+        matches!(self, Stokes::RR | Stokes::RL | Stokes::LR | Stokes::LL)
+    }
+
+    /// Whether this is one of the Stokes parameters I, Q, U, and V.
+    pub fn is_stokes_parameter(self) -> bool {
+        matches!(self, Stokes::I | Stokes::Q | Stokes::U | Stokes::V)
+    }
+
+    /// Express this product as a combination of I, Q, U, and V, if it is
+    /// linear in them.
+    fn iquv_coefficients(self) -> Option<[Complex<f64>; 4]> {
+        let o = Complex::new(0., 0.);
+        let p = Complex::new(1., 0.);
+        let m = Complex::new(-1., 0.);
+        let i = Complex::new(0., 1.);
+        let mi = Complex::new(0., -1.);
+
+        Some(match self {
+            Stokes::I => [p, o, o, o],
+            Stokes::Q => [o, p, o, o],
+            Stokes::U => [o, o, p, o],
+            Stokes::V => [o, o, o, p],
+            Stokes::XX => [p, p, o, o],
+            Stokes::YY => [p, m, o, o],
+            Stokes::XY => [o, o, p, i],
+            Stokes::YX => [o, o, p, mi],
+            Stokes::RR => [p, o, o, p],
+            Stokes::LL => [p, o, o, m],
+            Stokes::RL => [o, p, i, o],
+            Stokes::LR => [o, p, mi, o],
+            _ => return None,
+        })
+    }
+}
+
+/// A sample type that can be converted by a [`PolConverter`].
+pub trait PolSample: Copy {
+    /// Convert to double-precision complex.
+    fn to_c64(self) -> Complex<f64>;
+
+    /// Convert from double-precision complex.
+    fn from_c64(c: Complex<f64>) -> Self;
+}
+
+impl PolSample for Complex<f32> {
+    fn to_c64(self) -> Complex<f64> {
+        Complex::new(self.re as f64, self.im as f64)
+    }
+
+    fn from_c64(c: Complex<f64>) -> Self {
+        Complex::new(c.re as f32, c.im as f32)
+    }
+}
+
+impl PolSample for Complex<f64> {
+    fn to_c64(self) -> Complex<f64> {
+        self
+    }
+
+    fn from_c64(c: Complex<f64>) -> Self {
+        c
+    }
+}
+
+/// A linear transformation from one set of polarization products to
+/// another.
+#[derive(Clone, Debug)]
+pub struct PolConverter {
+    input: Vec<Stokes>,
+    output: Vec<Stokes>,
+
+    /// Row-major matrix of shape (output.len(), input.len()).
+    matrix: Vec<Complex<f64>>,
+}
+
+impl PolConverter {
+    /// Create a converter from the products *input* to the products
+    /// *output*.
+    ///
+    /// Each output product must be computable from the input products. For
+    /// instance, Stokes I can be computed from XX and YY alone, but Stokes U
+    /// cannot. Only the Stokes parameters and the linear and circular
+    /// correlations are supported.
+    pub fn new(input: &[Stokes], output: &[Stokes]) -> Result<Self, StokesError> {
+        let n_in = input.len();
+        let mut a = Vec::with_capacity(n_in);
+
+        for s in input {
+            // Inputs that can't be expressed in IQUV can't be used, but they
+            // don't prevent the other inputs from being used.
+            a.push(s.iquv_coefficients().unwrap_or_default());
+        }
+
+        let mut matrix = Vec::with_capacity(output.len() * n_in);
+
+        for &s in output {
+            let b = s
+                .iquv_coefficients()
+                .ok_or(StokesError::NotConvertible(s))?;
+            let row = solve_combination(&a, &b).ok_or(StokesError::NotConvertible(s))?;
+            matrix.extend(row);
+        }
+
+        Ok(PolConverter {
+            input: input.to_vec(),
+            output: output.to_vec(),
+            matrix,
+        })
+    }
+
+    /// Get the input products.
+    pub fn input(&self) -> &[Stokes] {
+        &self.input[..]
+    }
+
+    /// Get the output products.
+    pub fn output(&self) -> &[Stokes] {
+        &self.output[..]
+    }
+
+    /// Convert one set of samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lengths of *input* and *output* do not match the
+    /// numbers of input and output products.
+    pub fn convert<T: PolSample>(&self, input: &[T], output: &mut [T]) {
+        let n_in = self.input.len();
+        assert_eq!(input.len(), n_in, "wrong number of input polarizations");
+        assert_eq!(
+            output.len(),
+            self.output.len(),
+            "wrong number of output polarizations"
+        );
+
+        for (o, row) in output.iter_mut().zip(self.matrix.chunks(n_in.max(1))) {
+            let mut acc = Complex::new(0., 0.);
+
+            for (c, x) in row.iter().zip(input.iter()) {
+                acc += c * x.to_c64();
+            }
+
+            *o = T::from_c64(acc);
+        }
+    }
+
+    /// Convert an array of samples whose polarization axis is *axis*.
+    ///
+    /// The result has the same shape as *data*, except that the length of
+    /// the polarization axis is the number of output products.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of *axis* is not the number of input products.
+    pub fn convert_array<T, D>(&self, data: ArrayView<T, D>, axis: Axis) -> Array<T, D>
+    where
+        T: PolSample,
+        D: Dimension,
+    {
+        let mut shape = data.raw_dim();
+        shape[axis.index()] = self.output.len();
+        let mut result = Array::from_elem(shape, T::from_c64(Complex::new(0., 0.)));
+        let mut ibuf = vec![T::from_c64(Complex::new(0., 0.)); self.input.len()];
+        let mut obuf = vec![T::from_c64(Complex::new(0., 0.)); self.output.len()];
+
+        Zip::from(data.lanes(axis))
+            .and(result.lanes_mut(axis))
+            .for_each(|src, mut dest| {
+                for (b, x) in ibuf.iter_mut().zip(src.iter()) {
+                    *b = *x;
+                }
+
+                self.convert(&ibuf, &mut obuf);
+
+                for (x, b) in dest.iter_mut().zip(obuf.iter()) {
+                    *x = *b;
+                }
+            });
+
+        result
+    }
+}
+
+/// Find coefficients `m` such that `sum_k m[k] a[k] = b`, if there are any.
+fn solve_combination(a: &[[Complex<f64>; 4]], b: &[Complex<f64>; 4]) -> Option<Vec<Complex<f64>>> {
+    const EPS: f64 = 1e-9;
+    let n = a.len();
+
+    // Augmented matrix of the system A^T m = b: four rows, n + 1 columns.
+    let mut rows: Vec<Vec<Complex<f64>>> = (0..4)
+        .map(|j| {
+            let mut r: Vec<_> = a.iter().map(|coeffs| coeffs[j]).collect();
+            r.push(b[j]);
+            r
+        })
+        .collect();
+
+    let mut pivots = Vec::new();
+    let mut rank = 0;
+
+    for col in 0..n {
+        let best = (rank..4).max_by(|&i, &j| rows[i][col].norm().total_cmp(&rows[j][col].norm()));
+
+        let p = match best {
+            Some(p) if rows[p][col].norm() > EPS => p,
+            _ => continue,
+        };
+
+        rows.swap(rank, p);
+        let pivot = rows[rank][col];
+
+        for x in rows[rank].iter_mut() {
+            *x /= pivot;
+        }
+
+        for r in 0..4 {
+            if r != rank {
+                let f = rows[r][col];
+
+                if f.norm() > 0. {
+                    let pivot_row = rows[rank].clone();
+
+                    for (x, y) in rows[r].iter_mut().zip(pivot_row.iter()) {
+                        *x -= f * y;
+                    }
+                }
+            }
+        }
+
+        pivots.push(col);
+        rank += 1;
+
+        if rank == 4 {
+            break;
+        }
+    }
+
+    // Any leftover nonzero right-hand side means the system is inconsistent.
+    if rows[rank..].iter().any(|r| r[n].norm() > EPS) {
+        return None;
+    }
+
+    let mut m = vec![Complex::new(0., 0.); n];
+
+    for (r, &col) in pivots.iter().enumerate() {
+        m[col] = rows[r][n];
+    }
+
+    Some(m)
+}
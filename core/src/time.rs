@@ -0,0 +1,189 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! Helpers for the time values used in CASA data sets.
+//!
+//! CASA tables such as Measurement Sets record times as floating-point
+//! numbers of seconds since MJD 0 (1858 November 17, 00:00), usually in the
+//! UTC time scale. Present-day values are about 5×10⁹, which is why the
+//! number `4.5e9` tends to appear in code that fabricates test data. This
+//! module provides conversions between these values and other
+//! representations, and helpers for generating the `TIME`-like columns of
+//! regularly sampled data.
+//!
+//! Conversions to and from `chrono` types are available with the `chrono`
+//! feature of this crate, and to and from `hifitime` types with the
+//! `hifitime` feature.
+//!
+//! ```rust
+//! use rubbl_core::time::{unix_to_mjd_seconds, RegularSampling};
+//!
+//! // 2024 January 1, 00:00 UTC.
+//! let start = unix_to_mjd_seconds(1704067200.);
+//! assert_eq!(start, 60310. * 86400.);
+//!
+//! let sampling = RegularSampling::new(start, 2., 3);
+//! assert_eq!(
+//!     sampling.time_column(2),
+//!     vec![start + 1., start + 1., start + 3., start + 3., start + 5., start + 5.]
+//! );
+//! ```
+
+/// The number of seconds in a day.
+pub const SECONDS_PER_DAY: f64 = 86400.;
+
+/// The MJD of the Unix epoch, 1970 January 1.
+pub const UNIX_EPOCH_MJD: f64 = 40587.;
+
+/// Convert a Modified Julian Date in days to CASA seconds.
+pub fn mjd_days_to_seconds(mjd: f64) -> f64 {
+    mjd * SECONDS_PER_DAY
+}
+
+/// Convert CASA seconds to a Modified Julian Date in days.
+pub fn mjd_seconds_to_days(seconds: f64) -> f64 {
+    seconds / SECONDS_PER_DAY
+}
+
+/// Convert a Unix timestamp to CASA seconds in the UTC time scale.
+///
+/// Unix time ignores leap seconds, as do CASA UTC times, so this is a simple
+/// offset.
+pub fn unix_to_mjd_seconds(unix: f64) -> f64 {
+    unix + UNIX_EPOCH_MJD * SECONDS_PER_DAY
+}
+
+/// Convert CASA seconds in the UTC time scale to a Unix timestamp.
+pub fn mjd_seconds_to_unix(seconds: f64) -> f64 {
+    seconds - UNIX_EPOCH_MJD * SECONDS_PER_DAY
+}
+
+/// A regular sequence of integrations.
+///
+/// In a Measurement Set, the `TIME` and `TIME_CENTROID` columns give the
+/// midpoint of each integration, while `INTERVAL` and `EXPOSURE` give its
+/// duration. For data without flagging or dead time these are all determined
+/// by the start time, the integration time, and the number of integrations,
+/// which this type captures.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RegularSampling {
+    /// The start of the first integration, in CASA seconds.
+    pub start: f64,
+
+    /// The duration of each integration, in seconds.
+    pub interval: f64,
+
+    /// The number of integrations.
+    pub n_times: usize,
+}
+
+impl RegularSampling {
+    /// Create a new sampling description.
+    pub fn new(start: f64, interval: f64, n_times: usize) -> Self {
+        RegularSampling {
+            start,
+            interval,
+            n_times,
+        }
+    }
+
+    /// Get the midpoint of integration number *index*.
+    pub fn time(&self, index: usize) -> f64 {
+        self.start + (index as f64 + 0.5) * self.interval
+    }
+
+    /// Get the midpoints of all of the integrations.
+    pub fn times(&self) -> Vec<f64> {
+        (0..self.n_times).map(|i| self.time(i)).collect()
+    }
+
+    /// Get the end of the last integration, in CASA seconds.
+    pub fn end(&self) -> f64 {
+        self.start + self.n_times as f64 * self.interval
+    }
+
+    /// Generate the contents of a `TIME` or `TIME_CENTROID` column for a
+    /// table with *rows_per_time* consecutive rows per integration, such as
+    /// one row per baseline.
+    pub fn time_column(&self, rows_per_time: usize) -> Vec<f64> {
+        let mut col = Vec::with_capacity(self.n_times * rows_per_time);
+
+        for i in 0..self.n_times {
+            let t = self.time(i);
+            col.extend(std::iter::repeat_n(t, rows_per_time));
+        }
+
+        col
+    }
+
+    /// Generate the contents of an `INTERVAL` or `EXPOSURE` column for a
+    /// table with *rows_per_time* consecutive rows per integration.
+    pub fn interval_column(&self, rows_per_time: usize) -> Vec<f64> {
+        vec![self.interval; self.n_times * rows_per_time]
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_impl {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    /// Convert CASA seconds in the UTC time scale to a [`chrono`] date-time.
+    ///
+    /// The result is rounded to the nearest nanosecond. Returns `None` if the
+    /// time is out of the range that [`DateTime`] can represent.
+    pub fn mjd_seconds_to_datetime(seconds: f64) -> Option<DateTime<Utc>> {
+        let unix = mjd_seconds_to_unix(seconds);
+        let whole = unix.floor();
+        let nanos = ((unix - whole) * 1e9).round();
+
+        if !whole.is_finite() || whole.abs() > i64::MAX as f64 / 2. {
+            return None;
+        }
+
+        let (whole, nanos) = if nanos >= 1e9 {
+            (whole as i64 + 1, 0)
+        } else {
+            (whole as i64, nanos as u32)
+        };
+
+        DateTime::from_timestamp(whole, nanos)
+    }
+
+    /// Convert a [`chrono`] date-time to CASA seconds in the UTC time scale.
+    pub fn datetime_to_mjd_seconds(dt: &DateTime<Utc>) -> f64 {
+        unix_to_mjd_seconds(dt.timestamp() as f64 + dt.timestamp_subsec_nanos() as f64 * 1e-9)
+    }
+}
+
+#[cfg(feature = "chrono")]
+pub use chrono_impl::*;
+
+#[cfg(feature = "hifitime")]
+mod hifitime_impl {
+    use super::*;
+    use hifitime::Epoch;
+
+    /// Convert CASA seconds in the UTC time scale to a [`hifitime`] epoch.
+    pub fn utc_mjd_seconds_to_epoch(seconds: f64) -> Epoch {
+        Epoch::from_mjd_utc(mjd_seconds_to_days(seconds))
+    }
+
+    /// Convert CASA seconds in the TAI time scale to a [`hifitime`] epoch.
+    pub fn tai_mjd_seconds_to_epoch(seconds: f64) -> Epoch {
+        Epoch::from_mjd_tai(mjd_seconds_to_days(seconds))
+    }
+
+    /// Convert a [`hifitime`] epoch to CASA seconds in the UTC time scale.
+    pub fn epoch_to_utc_mjd_seconds(epoch: &Epoch) -> f64 {
+        mjd_days_to_seconds(epoch.to_mjd_utc_days())
+    }
+
+    /// Convert a [`hifitime`] epoch to CASA seconds in the TAI time scale.
+    pub fn epoch_to_tai_mjd_seconds(epoch: &Epoch) -> f64 {
+        mjd_days_to_seconds(epoch.to_mjd_tai_days())
+    }
+}
+
+#[cfg(feature = "hifitime")]
+pub use hifitime_impl::*;
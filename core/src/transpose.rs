@@ -0,0 +1,120 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! Fast transposes between visibility axis orders.
+//!
+//! Correlators and their native file formats usually emit data for one
+//! timestep with all baselines varying fastest, i.e. in (channel,
+//! polarization, row) order, while a Measurement Set stores one (channel,
+//! polarization) cell per row, i.e. in (row, channel, polarization) order.
+//! Reshuffling between the two is a plain matrix transpose, but a naive one
+//! thrashes the cache badly and tends to dominate the CPU time of conversion
+//! pipelines.
+//!
+//! The functions here transpose in cache-sized blocks. If this crate’s
+//! `parallel` feature is enabled, the blocks are processed in parallel using
+//! [rayon](https://docs.rs/rayon).
+//!
+//! ```rust
+//! use rubbl_core::{ndarray::Array3, transpose};
+//!
+//! let rcp = Array3::from_shape_fn((5, 3, 2), |(r, c, p)| 100 * r + 10 * c + p);
+//! let cpr = transpose::to_chan_pol_row(rcp.view());
+//! assert_eq!(cpr.shape(), &[3, 2, 5]);
+//! assert_eq!(cpr[[2, 1, 4]], 421);
+//! assert_eq!(transpose::to_row_chan_pol(cpr.view()), rcp);
+//! ```
+
+use ndarray::{Array3, ArrayView3};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// The edge length of the square blocks in which transposes are performed.
+const BLOCK: usize = 32;
+
+/// Transpose a C-ordered matrix.
+///
+/// *src* holds a matrix of *rows* × *cols* elements, and is written into
+/// *dest* as a matrix of *cols* × *rows* elements, so that
+/// `dest[c * rows + r] == src[r * cols + c]`.
+///
+/// # Panics
+///
+/// Panics if the length of either slice is not `rows * cols`.
+pub fn transpose<T: Copy + Send + Sync>(src: &[T], dest: &mut [T], rows: usize, cols: usize) {
+    assert_eq!(src.len(), rows * cols, "source has the wrong size");
+    assert_eq!(dest.len(), rows * cols, "destination has the wrong size");
+
+    if rows == 0 || cols == 0 {
+        return;
+    }
+
+    // Each chunk holds BLOCK output rows, i.e. BLOCK columns of the input.
+    let chunk_size = BLOCK * rows;
+
+    #[cfg(feature = "parallel")]
+    let chunks = dest.par_chunks_mut(chunk_size);
+    #[cfg(not(feature = "parallel"))]
+    let chunks = dest.chunks_mut(chunk_size);
+
+    chunks
+        .enumerate()
+        .for_each(|(i, chunk)| transpose_chunk(src, chunk, rows, cols, i * BLOCK));
+}
+
+/// Fill in the output rows `col0..` of a transpose, iterating over the input
+/// in square blocks so that both sides stay in cache.
+fn transpose_chunk<T: Copy>(src: &[T], chunk: &mut [T], rows: usize, cols: usize, col0: usize) {
+    let n_cols = chunk.len() / rows;
+
+    for r0 in (0..rows).step_by(BLOCK) {
+        let r1 = (r0 + BLOCK).min(rows);
+
+        for c in 0..n_cols {
+            let out = &mut chunk[c * rows + r0..c * rows + r1];
+
+            for (k, o) in out.iter_mut().enumerate() {
+                *o = src[(r0 + k) * cols + col0 + c];
+            }
+        }
+    }
+}
+
+/// Reorder data from (row, channel, polarization) to (channel, polarization,
+/// row) order.
+///
+/// This is the conversion from the layout of a Measurement Set’s `DATA`
+/// column to the layout that correlators typically produce.
+pub fn to_chan_pol_row<T: Copy + Send + Sync>(src: ArrayView3<T>) -> Array3<T> {
+    let (n_rows, n_chans, n_pols) = src.dim();
+    transpose_view(src, n_rows, n_chans * n_pols, (n_chans, n_pols, n_rows))
+}
+
+/// Reorder data from (channel, polarization, row) to (row, channel,
+/// polarization) order.
+///
+/// This is the conversion from the layout that correlators typically produce
+/// to that of a Measurement Set’s `DATA` column.
+pub fn to_row_chan_pol<T: Copy + Send + Sync>(src: ArrayView3<T>) -> Array3<T> {
+    let (n_chans, n_pols, n_rows) = src.dim();
+    transpose_view(src, n_chans * n_pols, n_rows, (n_rows, n_chans, n_pols))
+}
+
+fn transpose_view<T: Copy + Send + Sync>(
+    src: ArrayView3<T>,
+    rows: usize,
+    cols: usize,
+    out_shape: (usize, usize, usize),
+) -> Array3<T> {
+    let src = src.as_standard_layout();
+    let src = src.as_slice().unwrap();
+
+    let mut dest = match src.first() {
+        Some(x) => vec![*x; src.len()],
+        None => Vec::new(),
+    };
+
+    transpose(src, &mut dest[..], rows, cols);
+    Array3::from_shape_vec(out_shape, dest).unwrap()
+}
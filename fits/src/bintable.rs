@@ -0,0 +1,1171 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! Reading and writing FITS binary tables.
+//!
+//! A binary table (`BINTABLE`) extension consists of a main table of
+//! fixed-width rows followed by an optional "heap" that holds the contents of
+//! variable-length array columns. This module loads a whole table into memory
+//! with [`BinTable`], from which columns can be extracted as ndarray arrays
+//! and rows can be converted into user-defined structs using
+//! [`FromBinTableRow`]. New tables are created with [`BinTableBuilder`].
+//!
+//! The `TDIMn` keywords are honored when shaping array cells, and the
+//! `TSCALn` and `TZEROn` scaling keywords are applied by
+//! [`BinTable::column_f64`] and [`BinTableRow::get_f64`].
+//!
+//! ```rust
+//! use rubbl_core::ndarray::{array, Array1};
+//! use rubbl_fits::{bintable::{BinTable, BinTableBuilder}, header, FitsParser};
+//! use std::io::Cursor;
+//!
+//! let mut file = Vec::new();
+//! header::write_header(&mut file, &header::empty_primary_header()).unwrap();
+//!
+//! let mut builder = BinTableBuilder::new("EXAMPLE");
+//! builder.add_column("ID", Array1::from(vec![1i32, 2]).view()).unwrap();
+//! builder.add_column("UVW", array![[0f64, 1., 2.], [3., 4., 5.]].view()).unwrap();
+//! builder.add_string_column("NAME", &["a", "bc"]).unwrap();
+//! builder.write(&mut file).unwrap();
+//!
+//! let mut parser = FitsParser::new(Cursor::new(file)).unwrap();
+//! let table = BinTable::read(&mut parser, 1).unwrap();
+//! assert_eq!(table.n_rows(), 2);
+//! assert_eq!(table.column_array::<f64>("UVW").unwrap()[[1, 2]], 5.);
+//! assert_eq!(table.string_column("NAME").unwrap(), vec!["a", "bc"]);
+//! ```
+
+use rubbl_core::{
+    ndarray::{Array2, ArrayD, ArrayView, Axis, Dimension, IxDyn},
+    Complex,
+};
+use std::convert::TryInto;
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    header::{self, find_value, HeaderCard, HeaderValue},
+    FitsError, FitsParser, HduKind,
+};
+
+/// A Rust type that can be stored in a binary table column.
+pub trait BinTableType: Copy {
+    /// The `TFORM` type code of this type.
+    const CODE: u8;
+
+    /// The size of one value in the table, in bytes.
+    const SIZE: usize;
+
+    /// Decode a value from the start of a big-endian buffer.
+    fn decode(buf: &[u8]) -> Self;
+
+    /// Encode a value into the start of a big-endian buffer.
+    fn encode(&self, buf: &mut [u8]);
+}
+
+macro_rules! impl_bintable_type {
+    ($ty:ty, $code:expr) => {
+        impl BinTableType for $ty {
+            const CODE: u8 = $code;
+            const SIZE: usize = std::mem::size_of::<$ty>();
+
+            fn decode(buf: &[u8]) -> Self {
+                <$ty>::from_be_bytes(buf[..Self::SIZE].try_into().unwrap())
+            }
+
+            fn encode(&self, buf: &mut [u8]) {
+                buf[..Self::SIZE].copy_from_slice(&self.to_be_bytes());
+            }
+        }
+    };
+}
+
+impl_bintable_type! {u8, b'B'}
+impl_bintable_type! {i16, b'I'}
+impl_bintable_type! {i32, b'J'}
+impl_bintable_type! {i64, b'K'}
+impl_bintable_type! {f32, b'E'}
+impl_bintable_type! {f64, b'D'}
+
+impl BinTableType for bool {
+    const CODE: u8 = b'L';
+    const SIZE: usize = 1;
+
+    fn decode(buf: &[u8]) -> Self {
+        buf[0] == b'T'
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0] = if *self { b'T' } else { b'F' };
+    }
+}
+
+impl BinTableType for Complex<f32> {
+    const CODE: u8 = b'C';
+    const SIZE: usize = 8;
+
+    fn decode(buf: &[u8]) -> Self {
+        Complex::new(f32::decode(buf), f32::decode(&buf[4..]))
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        self.re.encode(buf);
+        self.im.encode(&mut buf[4..]);
+    }
+}
+
+impl BinTableType for Complex<f64> {
+    const CODE: u8 = b'M';
+    const SIZE: usize = 16;
+
+    fn decode(buf: &[u8]) -> Self {
+        Complex::new(f64::decode(buf), f64::decode(&buf[8..]))
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        self.re.encode(buf);
+        self.im.encode(&mut buf[8..]);
+    }
+}
+
+/// The kind of array descriptor used by a variable-length array column.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Descriptor {
+    /// A `P` descriptor: two 32-bit integers.
+    P,
+
+    /// A `Q` descriptor: two 64-bit integers.
+    Q,
+}
+
+/// The storage format of a binary table column, as given by its `TFORMn`
+/// keyword.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ColumnFormat {
+    /// The repeat count: the number of elements per cell, or for
+    /// variable-length array columns, the number of descriptors.
+    pub repeat: usize,
+
+    /// The type code of the elements, such as `b'J'` or `b'A'`.
+    pub code: u8,
+
+    /// If this is a variable-length array column, the kind of descriptor
+    /// that it uses.
+    pub descriptor: Option<Descriptor>,
+}
+
+impl ColumnFormat {
+    /// Parse a `TFORMn` value.
+    pub fn parse(tform: &str) -> Result<Self, FitsError> {
+        let bad = || FitsError::UnsupportedTform(tform.to_owned());
+        let tform = tform.trim();
+        let n_digits = tform.bytes().take_while(|c| c.is_ascii_digit()).count();
+
+        let repeat = if n_digits == 0 {
+            1
+        } else {
+            tform[..n_digits].parse().map_err(|_| bad())?
+        };
+
+        let mut codes = tform[n_digits..].bytes();
+        let first = codes.next().ok_or_else(bad)?;
+
+        let (code, descriptor) = match first {
+            b'P' => (codes.next().ok_or_else(bad)?, Some(Descriptor::P)),
+            b'Q' => (codes.next().ok_or_else(bad)?, Some(Descriptor::Q)),
+            c => (c, None),
+        };
+
+        if element_size(code).is_none() {
+            return Err(bad());
+        }
+
+        Ok(ColumnFormat {
+            repeat,
+            code,
+            descriptor,
+        })
+    }
+
+    /// The number of bytes that a cell of this format occupies in a row of
+    /// the main table.
+    pub fn width(&self) -> usize {
+        match self.descriptor {
+            Some(Descriptor::P) => 8 * self.repeat,
+            Some(Descriptor::Q) => 16 * self.repeat,
+            None if self.code == b'X' => self.repeat.div_ceil(8),
+            None => self.repeat * element_size(self.code).unwrap(),
+        }
+    }
+}
+
+/// The size of an element with the given type code, or None if the code is
+/// not recognized. Bit (`X`) columns are counted in whole bytes elsewhere.
+fn element_size(code: u8) -> Option<usize> {
+    match code {
+        b'L' | b'X' | b'B' | b'A' => Some(1),
+        b'I' => Some(2),
+        b'J' | b'E' => Some(4),
+        b'K' | b'D' | b'C' => Some(8),
+        b'M' => Some(16),
+        _ => None,
+    }
+}
+
+/// Information about one column of a binary table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BinTableColumn {
+    /// The name of the column, from `TTYPEn`. Empty if not specified.
+    pub name: String,
+
+    /// The storage format of the column.
+    pub format: ColumnFormat,
+
+    /// The shape of each cell, from `TDIMn`, in Rust (C) order. `None` if
+    /// not specified.
+    pub dims: Option<Vec<usize>>,
+
+    /// The scale factor from `TSCALn`, or 1.
+    pub scale: f64,
+
+    /// The zero point from `TZEROn`, or 0.
+    pub zero: f64,
+
+    /// The physical units of the column, from `TUNITn`. Empty if not
+    /// specified.
+    pub unit: String,
+
+    offset: usize,
+}
+
+impl BinTableColumn {
+    /// The shape of the values in one cell of this column. This is empty for
+    /// scalar columns.
+    pub fn cell_shape(&self) -> Vec<usize> {
+        match self.dims {
+            Some(ref d) => d.clone(),
+            None if self.format.repeat == 1 => Vec::new(),
+            None => vec![self.format.repeat],
+        }
+    }
+}
+
+/// A binary table loaded into memory.
+#[derive(Clone, Debug)]
+pub struct BinTable {
+    headers: Vec<HeaderCard>,
+    columns: Vec<BinTableColumn>,
+    n_rows: usize,
+    row_width: usize,
+    heap_offset: usize,
+    data: Vec<u8>,
+}
+
+impl BinTable {
+    /// Load a binary table HDU from a FITS file.
+    pub fn read<R: Read + Seek>(
+        parser: &mut FitsParser<R>,
+        hdu_num: usize,
+    ) -> Result<Self, FitsError> {
+        match parser.hdus().get(hdu_num) {
+            None => return Err(FitsError::NoSuchHdu(hdu_num)),
+            Some(hdu) if hdu.kind() != HduKind::BinaryTableExtension => {
+                return Err(FitsError::NotBinTable(hdu_num))
+            }
+            _ => {}
+        }
+
+        let headers = parser.read_hdu_header(hdu_num)?;
+        let data = parser.read_hdu_data(hdu_num)?;
+        Self::from_parts(headers, data)
+    }
+
+    /// Interpret the headers and data of a binary table HDU.
+    pub fn from_parts(headers: Vec<HeaderCard>, data: Vec<u8>) -> Result<Self, FitsError> {
+        let get_int = |kw: &str| -> Result<Option<i64>, FitsError> {
+            match find_value(&headers, kw) {
+                None => Ok(None),
+                Some(v) => v
+                    .as_i64()
+                    .map(Some)
+                    .ok_or_else(|| FitsError::BadHeaderValue(kw.to_owned())),
+            }
+        };
+
+        let require_int = |kw: &str| -> Result<usize, FitsError> {
+            let v = get_int(kw)?.ok_or_else(|| FitsError::MissingHeader(kw.to_owned()))?;
+
+            if v < 0 {
+                return Err(FitsError::BadHeaderValue(kw.to_owned()));
+            }
+
+            Ok(v as usize)
+        };
+
+        let get_str = |kw: &str| -> Result<Option<String>, FitsError> {
+            match find_value(&headers, kw) {
+                None => Ok(None),
+                Some(v) => v
+                    .as_str()
+                    .map(|s| Some(s.to_owned()))
+                    .ok_or_else(|| FitsError::BadHeaderValue(kw.to_owned())),
+            }
+        };
+
+        let get_float = |kw: &str, default: f64| -> Result<f64, FitsError> {
+            match find_value(&headers, kw) {
+                None => Ok(default),
+                Some(v) => v
+                    .as_f64()
+                    .ok_or_else(|| FitsError::BadHeaderValue(kw.to_owned())),
+            }
+        };
+
+        let row_width = require_int("NAXIS1")?;
+        let n_rows = require_int("NAXIS2")?;
+        let n_fields = require_int("TFIELDS")?;
+        let main_size = row_width * n_rows;
+        let heap_offset = match get_int("THEAP")? {
+            Some(h) if h >= 0 => h as usize,
+            Some(_) => return Err(FitsError::BadHeaderValue("THEAP".to_owned())),
+            None => main_size,
+        };
+
+        if data.len() < main_size || heap_offset < main_size {
+            return Err(FitsError::Truncated);
+        }
+
+        let mut columns = Vec::with_capacity(n_fields);
+        let mut offset = 0;
+
+        for i in 1..=n_fields {
+            let tform_kw = format!("TFORM{i}");
+            let tform = get_str(&tform_kw)?.ok_or(FitsError::MissingHeader(tform_kw))?;
+            let format = ColumnFormat::parse(&tform)?;
+
+            let dims = match get_str(&format!("TDIM{i}"))? {
+                None => None,
+                Some(s) => Some(parse_tdim(&s)?),
+            };
+
+            if let Some(ref d) = dims {
+                if format.descriptor.is_none() && d.iter().product::<usize>() != format.repeat {
+                    return Err(FitsError::BadHeaderValue(format!("TDIM{i}")));
+                }
+            }
+
+            columns.push(BinTableColumn {
+                name: get_str(&format!("TTYPE{i}"))?.unwrap_or_default(),
+                format,
+                dims,
+                scale: get_float(&format!("TSCAL{i}"), 1.)?,
+                zero: get_float(&format!("TZERO{i}"), 0.)?,
+                unit: get_str(&format!("TUNIT{i}"))?.unwrap_or_default(),
+                offset,
+            });
+
+            offset += format.width();
+        }
+
+        if offset != row_width {
+            return Err(FitsError::BadHeaderValue("NAXIS1".to_owned()));
+        }
+
+        Ok(BinTable {
+            headers,
+            columns,
+            n_rows,
+            row_width,
+            heap_offset,
+            data,
+        })
+    }
+
+    /// Get the header cards of this table's HDU.
+    pub fn headers(&self) -> &[HeaderCard] {
+        &self.headers[..]
+    }
+
+    /// Get the name of this table, from its `EXTNAME` keyword.
+    pub fn extname(&self) -> &str {
+        find_value(&self.headers, "EXTNAME")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+    }
+
+    /// Get the number of rows in this table.
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    /// Get information about the columns of this table.
+    pub fn columns(&self) -> &[BinTableColumn] {
+        &self.columns[..]
+    }
+
+    /// Look up a column by name.
+    pub fn column(&self, name: &str) -> Option<&BinTableColumn> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    fn lookup(&self, name: &str) -> Result<&BinTableColumn, FitsError> {
+        self.column(name)
+            .ok_or_else(|| FitsError::NoSuchColumn(name.to_owned()))
+    }
+
+    fn cell(&self, col: &BinTableColumn, row: usize) -> &[u8] {
+        let start = row * self.row_width + col.offset;
+        &self.data[start..start + col.format.width()]
+    }
+
+    /// Check that *col* is a fixed-size column of values of type *T*.
+    fn check_fixed<T: BinTableType>(col: &BinTableColumn) -> Result<(), FitsError> {
+        if col.format.descriptor.is_some() || col.format.code != T::CODE {
+            return Err(FitsError::ColumnTypeMismatch {
+                column: col.name.clone(),
+                expected: T::CODE as char,
+                found: col.format.code as char,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Read an entire fixed-size column as an array.
+    ///
+    /// The first axis of the result indexes rows, and the remaining axes have
+    /// the shape of the column's cells. No scaling is applied.
+    pub fn column_array<T: BinTableType>(&self, name: &str) -> Result<ArrayD<T>, FitsError> {
+        let col = self.lookup(name)?;
+        Self::check_fixed::<T>(col)?;
+        let mut values = Vec::with_capacity(self.n_rows * col.format.repeat);
+
+        for row in 0..self.n_rows {
+            decode_into(self.cell(col, row), col.format.repeat, &mut values);
+        }
+
+        let mut shape = vec![self.n_rows];
+        shape.extend(col.cell_shape());
+        Ok(ArrayD::from_shape_vec(IxDyn(&shape), values).unwrap())
+    }
+
+    /// Read an entire numeric column as floats, applying the `TSCALn` and
+    /// `TZEROn` scaling.
+    ///
+    /// The result is shaped as in [`Self::column_array`].
+    pub fn column_f64(&self, name: &str) -> Result<ArrayD<f64>, FitsError> {
+        let col = self.lookup(name)?;
+        let mut values = Vec::with_capacity(self.n_rows * col.format.repeat);
+
+        for row in 0..self.n_rows {
+            decode_scaled(col, self.cell(col, row), &mut values)?;
+        }
+
+        let mut shape = vec![self.n_rows];
+        shape.extend(col.cell_shape());
+        Ok(ArrayD::from_shape_vec(IxDyn(&shape), values).unwrap())
+    }
+
+    /// Read an entire bit (`X`) column. The result has shape `(n_rows,
+    /// repeat)`.
+    pub fn bit_column(&self, name: &str) -> Result<Array2<bool>, FitsError> {
+        let col = self.lookup(name)?;
+
+        if col.format.descriptor.is_some() || col.format.code != b'X' {
+            return Err(FitsError::ColumnTypeMismatch {
+                column: col.name.clone(),
+                expected: 'X',
+                found: col.format.code as char,
+            });
+        }
+
+        let n = col.format.repeat;
+
+        Ok(Array2::from_shape_fn((self.n_rows, n), |(row, i)| {
+            self.cell(col, row)[i / 8] & (0x80 >> (i % 8)) != 0
+        }))
+    }
+
+    /// Read an entire character (`A`) column. Each cell is returned as a
+    /// single string, with trailing spaces and NULs removed.
+    pub fn string_column(&self, name: &str) -> Result<Vec<String>, FitsError> {
+        let col = self.lookup(name)?;
+
+        if col.format.descriptor.is_some() || col.format.code != b'A' {
+            return Err(FitsError::ColumnTypeMismatch {
+                column: col.name.clone(),
+                expected: 'A',
+                found: col.format.code as char,
+            });
+        }
+
+        Ok((0..self.n_rows)
+            .map(|row| decode_string(self.cell(col, row)))
+            .collect())
+    }
+
+    /// Read an entire variable-length array column. No scaling is applied.
+    pub fn var_column<T: BinTableType>(&self, name: &str) -> Result<Vec<Vec<T>>, FitsError> {
+        let col = self.lookup(name)?;
+        (0..self.n_rows)
+            .map(|row| self.var_cell(col, row))
+            .collect()
+    }
+
+    fn var_cell<T: BinTableType>(
+        &self,
+        col: &BinTableColumn,
+        row: usize,
+    ) -> Result<Vec<T>, FitsError> {
+        let descriptor = match col.format.descriptor {
+            Some(d) if col.format.code == T::CODE => d,
+            _ => {
+                return Err(FitsError::ColumnTypeMismatch {
+                    column: col.name.clone(),
+                    expected: T::CODE as char,
+                    found: col.format.code as char,
+                })
+            }
+        };
+
+        let cell = self.cell(col, row);
+
+        // Descriptor values are formally signed, but negative values are
+        // meaningless, so we read them as unsigned.
+        let (n, offset) = match descriptor {
+            Descriptor::P => (
+                i32::decode(cell) as u32 as usize,
+                i32::decode(&cell[4..]) as u32 as usize,
+            ),
+            Descriptor::Q => (
+                i64::decode(cell) as u64 as usize,
+                i64::decode(&cell[8..]) as u64 as usize,
+            ),
+        };
+
+        let start = self.heap_offset + offset;
+        let end = start + n * T::SIZE;
+
+        if end > self.data.len() {
+            return Err(FitsError::HeapOutOfBounds(col.name.clone()));
+        }
+
+        let mut values = Vec::with_capacity(n);
+        decode_into(&self.data[start..end], n, &mut values);
+        Ok(values)
+    }
+
+    /// Get a view of one row of the table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *index* is out of bounds.
+    pub fn row(&self, index: usize) -> BinTableRow<'_> {
+        assert!(index < self.n_rows, "row index out of bounds");
+        BinTableRow { table: self, index }
+    }
+
+    /// Convert every row of the table into a struct.
+    pub fn rows<S: FromBinTableRow>(&self) -> Result<Vec<S>, FitsError> {
+        (0..self.n_rows)
+            .map(|i| S::from_row(&self.row(i)))
+            .collect()
+    }
+}
+
+fn decode_into<T: BinTableType>(buf: &[u8], n: usize, dest: &mut Vec<T>) {
+    for i in 0..n {
+        dest.push(T::decode(&buf[i * T::SIZE..]));
+    }
+}
+
+fn decode_scaled(col: &BinTableColumn, buf: &[u8], dest: &mut Vec<f64>) -> Result<(), FitsError> {
+    let n = col.format.repeat;
+    let start = dest.len();
+
+    match (col.format.descriptor, col.format.code) {
+        (None, b'B') => dest.extend(buf[..n].iter().map(|v| *v as f64)),
+        (None, b'I') => (0..n).for_each(|i| dest.push(i16::decode(&buf[2 * i..]) as f64)),
+        (None, b'J') => (0..n).for_each(|i| dest.push(i32::decode(&buf[4 * i..]) as f64)),
+        (None, b'K') => (0..n).for_each(|i| dest.push(i64::decode(&buf[8 * i..]) as f64)),
+        (None, b'E') => (0..n).for_each(|i| dest.push(f32::decode(&buf[4 * i..]) as f64)),
+        (None, b'D') => (0..n).for_each(|i| dest.push(f64::decode(&buf[8 * i..]))),
+        (_, code) => {
+            return Err(FitsError::ColumnTypeMismatch {
+                column: col.name.clone(),
+                expected: 'D',
+                found: code as char,
+            })
+        }
+    }
+
+    if col.scale != 1. || col.zero != 0. {
+        for v in &mut dest[start..] {
+            *v = *v * col.scale + col.zero;
+        }
+    }
+
+    Ok(())
+}
+
+fn decode_string(buf: &[u8]) -> String {
+    let end = buf.iter().position(|c| *c == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..end]).trim_end().to_owned()
+}
+
+/// Parse a `TDIMn` value like `(4,3)` into a shape in Rust (C) order.
+fn parse_tdim(tdim: &str) -> Result<Vec<usize>, FitsError> {
+    let bad = || FitsError::BadHeaderValue(format!("TDIM value {tdim:?}"));
+    let inner = tdim
+        .trim()
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .ok_or_else(bad)?;
+
+    let mut dims = inner
+        .split(',')
+        .map(|s| s.trim().parse::<usize>().map_err(|_| bad()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    dims.reverse();
+    Ok(dims)
+}
+
+/// A view of one row of a [`BinTable`].
+#[derive(Clone, Copy, Debug)]
+pub struct BinTableRow<'a> {
+    table: &'a BinTable,
+    index: usize,
+}
+
+impl<'a> BinTableRow<'a> {
+    /// The index of this row in its table.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the first value of a fixed-size column in this row. No scaling is
+    /// applied.
+    pub fn get<T: BinTableType>(&self, name: &str) -> Result<T, FitsError> {
+        let col = self.table.lookup(name)?;
+        BinTable::check_fixed::<T>(col)?;
+
+        if col.format.repeat == 0 {
+            return Err(FitsError::BadHeaderValue(format!("TFORM of column {name}")));
+        }
+
+        Ok(T::decode(self.table.cell(col, self.index)))
+    }
+
+    /// Get the first value of a numeric column in this row, applying the
+    /// `TSCALn` and `TZEROn` scaling.
+    pub fn get_f64(&self, name: &str) -> Result<f64, FitsError> {
+        let col = self.table.lookup(name)?;
+        let mut values = Vec::with_capacity(col.format.repeat);
+        decode_scaled(col, self.table.cell(col, self.index), &mut values)?;
+        values
+            .first()
+            .copied()
+            .ok_or_else(|| FitsError::BadHeaderValue(format!("TFORM of column {name}")))
+    }
+
+    /// Get the cell of a fixed-size column in this row as an array with the
+    /// column's cell shape. No scaling is applied.
+    pub fn get_array<T: BinTableType>(&self, name: &str) -> Result<ArrayD<T>, FitsError> {
+        let col = self.table.lookup(name)?;
+        BinTable::check_fixed::<T>(col)?;
+        let mut values = Vec::with_capacity(col.format.repeat);
+        decode_into(
+            self.table.cell(col, self.index),
+            col.format.repeat,
+            &mut values,
+        );
+        Ok(ArrayD::from_shape_vec(IxDyn(&col.cell_shape()), values).unwrap())
+    }
+
+    /// Get the cell of a character column in this row.
+    pub fn get_string(&self, name: &str) -> Result<String, FitsError> {
+        let col = self.table.lookup(name)?;
+
+        if col.format.descriptor.is_some() || col.format.code != b'A' {
+            return Err(FitsError::ColumnTypeMismatch {
+                column: col.name.clone(),
+                expected: 'A',
+                found: col.format.code as char,
+            });
+        }
+
+        Ok(decode_string(self.table.cell(col, self.index)))
+    }
+
+    /// Get the cell of a variable-length array column in this row.
+    pub fn get_var<T: BinTableType>(&self, name: &str) -> Result<Vec<T>, FitsError> {
+        let col = self.table.lookup(name)?;
+        self.table.var_cell(col, self.index)
+    }
+}
+
+/// A type that can be constructed from a row of a binary table.
+///
+/// ```rust
+/// use rubbl_fits::{bintable::{BinTableRow, FromBinTableRow}, FitsError};
+///
+/// struct Source {
+///     name: String,
+///     ra: f64,
+///     dec: f64,
+/// }
+///
+/// impl FromBinTableRow for Source {
+///     fn from_row(row: &BinTableRow) -> Result<Self, FitsError> {
+///         Ok(Source {
+///             name: row.get_string("SOURCE")?,
+///             ra: row.get_f64("RAEPO")?,
+///             dec: row.get_f64("DECEPO")?,
+///         })
+///     }
+/// }
+/// ```
+pub trait FromBinTableRow: Sized {
+    /// Construct a value from a table row.
+    fn from_row(row: &BinTableRow) -> Result<Self, FitsError>;
+}
+
+#[derive(Clone, Debug)]
+struct BuilderColumn {
+    name: String,
+    tform: String,
+    tdim: Option<String>,
+    unit: Option<String>,
+    scaling: Option<(f64, f64)>,
+    width: usize,
+    cells: Vec<u8>,
+    var_arrays: Option<VarArrays>,
+}
+
+/// The contents of a variable-length array column: for each row, the
+/// element count and the offset of its data in `heap`.
+#[derive(Clone, Debug)]
+struct VarArrays {
+    descriptors: Vec<(usize, usize)>,
+    heap: Vec<u8>,
+}
+
+/// A builder for writing new binary table HDUs.
+///
+/// Columns are added in full, and the table is written with
+/// [`Self::write`]. All columns must have the same number of rows.
+#[derive(Clone, Debug)]
+pub struct BinTableBuilder {
+    extname: String,
+    n_rows: Option<usize>,
+    columns: Vec<BuilderColumn>,
+    extra_headers: Vec<HeaderCard>,
+}
+
+impl BinTableBuilder {
+    /// Start building a table with the specified `EXTNAME`.
+    pub fn new<S: Into<String>>(extname: S) -> Self {
+        BinTableBuilder {
+            extname: extname.into(),
+            n_rows: None,
+            columns: Vec::new(),
+            extra_headers: Vec::new(),
+        }
+    }
+
+    fn check_n_rows(&mut self, name: &str, n_rows: usize) -> Result<(), FitsError> {
+        match self.n_rows {
+            Some(n) if n != n_rows => Err(FitsError::ColumnLengthMismatch(name.to_owned())),
+            _ => {
+                self.n_rows = Some(n_rows);
+                Ok(())
+            }
+        }
+    }
+
+    fn column_mut(&mut self, name: &str) -> Result<&mut BuilderColumn, FitsError> {
+        self.columns
+            .iter_mut()
+            .find(|c| c.name == name)
+            .ok_or_else(|| FitsError::NoSuchColumn(name.to_owned()))
+    }
+
+    /// Add a fixed-size column.
+    ///
+    /// The first axis of *data* indexes rows, and the remaining axes give the
+    /// shape of each cell, which is recorded in a `TDIMn` keyword if there is
+    /// more than one of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if *data* is zero-dimensional.
+    pub fn add_column<T: BinTableType, D: Dimension>(
+        &mut self,
+        name: &str,
+        data: ArrayView<T, D>,
+    ) -> Result<&mut Self, FitsError> {
+        assert!(data.ndim() > 0, "column data must have a row axis");
+        let n_rows = data.len_of(Axis(0));
+        self.check_n_rows(name, n_rows)?;
+
+        let cell_shape = &data.shape()[1..];
+        let repeat: usize = cell_shape.iter().product();
+        let width = repeat * T::SIZE;
+        let mut cells = vec![0u8; n_rows * width];
+
+        for (i, v) in data.iter().enumerate() {
+            v.encode(&mut cells[i * T::SIZE..]);
+        }
+
+        let tdim = if cell_shape.len() > 1 {
+            let dims: Vec<String> = cell_shape.iter().rev().map(|d| d.to_string()).collect();
+            Some(format!("({})", dims.join(",")))
+        } else {
+            None
+        };
+
+        self.columns.push(BuilderColumn {
+            name: name.to_owned(),
+            tform: format!("{}{}", repeat, T::CODE as char),
+            tdim,
+            unit: None,
+            scaling: None,
+            width,
+            cells,
+            var_arrays: None,
+        });
+
+        Ok(self)
+    }
+
+    /// Add a character column. The column is as wide as the longest value.
+    pub fn add_string_column<S: AsRef<str>>(
+        &mut self,
+        name: &str,
+        values: &[S],
+    ) -> Result<&mut Self, FitsError> {
+        self.check_n_rows(name, values.len())?;
+        let width = values
+            .iter()
+            .map(|s| s.as_ref().len())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let mut cells = vec![b' '; values.len() * width];
+
+        for (i, s) in values.iter().enumerate() {
+            let s = s.as_ref().as_bytes();
+            cells[i * width..i * width + s.len()].copy_from_slice(s);
+        }
+
+        self.columns.push(BuilderColumn {
+            name: name.to_owned(),
+            tform: format!("{}A", width),
+            tdim: None,
+            unit: None,
+            scaling: None,
+            width,
+            cells,
+            var_arrays: None,
+        });
+
+        Ok(self)
+    }
+
+    /// Add a variable-length array column, stored using `P` descriptors.
+    pub fn add_var_column<T: BinTableType>(
+        &mut self,
+        name: &str,
+        values: &[Vec<T>],
+    ) -> Result<&mut Self, FitsError> {
+        self.check_n_rows(name, values.len())?;
+        let mut descriptors = Vec::with_capacity(values.len());
+        let mut heap = Vec::new();
+        let mut max_len = 0;
+
+        for v in values {
+            let start = heap.len();
+            heap.resize(start + v.len() * T::SIZE, 0);
+
+            for (i, x) in v.iter().enumerate() {
+                x.encode(&mut heap[start + i * T::SIZE..]);
+            }
+
+            descriptors.push((v.len(), start));
+            max_len = max_len.max(v.len());
+        }
+
+        self.columns.push(BuilderColumn {
+            name: name.to_owned(),
+            tform: format!("1P{}({})", T::CODE as char, max_len),
+            tdim: None,
+            unit: None,
+            scaling: None,
+            width: 8,
+            cells: Vec::new(),
+            var_arrays: Some(VarArrays { descriptors, heap }),
+        });
+
+        Ok(self)
+    }
+
+    /// Record `TSCALn` and `TZEROn` keywords for a column that has already
+    /// been added. The stored values are not changed.
+    pub fn set_scaling(
+        &mut self,
+        name: &str,
+        scale: f64,
+        zero: f64,
+    ) -> Result<&mut Self, FitsError> {
+        self.column_mut(name)?.scaling = Some((scale, zero));
+        Ok(self)
+    }
+
+    /// Record a `TUNITn` keyword for a column that has already been added.
+    pub fn set_unit(&mut self, name: &str, unit: &str) -> Result<&mut Self, FitsError> {
+        self.column_mut(name)?.unit = Some(unit.to_owned());
+        Ok(self)
+    }
+
+    /// Add an extra header card, written after the column definitions.
+    pub fn add_header(&mut self, card: HeaderCard) -> &mut Self {
+        self.extra_headers.push(card);
+        self
+    }
+
+    /// Write the table as a FITS extension HDU.
+    pub fn write<W: Write>(&self, mut dest: W) -> Result<(), FitsError> {
+        let n_rows = self.n_rows.unwrap_or(0);
+        let row_width: usize = self.columns.iter().map(|c| c.width).sum();
+
+        // Lay out the heap, with each column's arrays stored contiguously.
+
+        let mut heap_bases = Vec::with_capacity(self.columns.len());
+        let mut heap_size = 0;
+
+        for col in &self.columns {
+            heap_bases.push(heap_size);
+
+            if let Some(ref va) = col.var_arrays {
+                heap_size += va.heap.len();
+            }
+        }
+
+        let int = |v: usize| HeaderValue::Integer(v as i64);
+        let string = |s: &str| HeaderValue::String(s.to_owned());
+
+        let mut cards = vec![
+            HeaderCard::new("XTENSION", string("BINTABLE")),
+            HeaderCard::new("BITPIX", int(8)),
+            HeaderCard::new("NAXIS", int(2)),
+            HeaderCard::new("NAXIS1", int(row_width)),
+            HeaderCard::new("NAXIS2", int(n_rows)),
+            HeaderCard::new("PCOUNT", int(heap_size)),
+            HeaderCard::new("GCOUNT", int(1)),
+            HeaderCard::new("TFIELDS", int(self.columns.len())),
+        ];
+
+        for (i, col) in self.columns.iter().enumerate() {
+            let n = i + 1;
+            cards.push(HeaderCard::new(format!("TTYPE{n}"), string(&col.name)));
+            cards.push(HeaderCard::new(format!("TFORM{n}"), string(&col.tform)));
+
+            if let Some(ref tdim) = col.tdim {
+                cards.push(HeaderCard::new(format!("TDIM{n}"), string(tdim)));
+            }
+
+            if let Some(ref unit) = col.unit {
+                cards.push(HeaderCard::new(format!("TUNIT{n}"), string(unit)));
+            }
+
+            if let Some((scale, zero)) = col.scaling {
+                cards.push(HeaderCard::new(
+                    format!("TSCAL{n}"),
+                    HeaderValue::Float(scale),
+                ));
+                cards.push(HeaderCard::new(
+                    format!("TZERO{n}"),
+                    HeaderValue::Float(zero),
+                ));
+            }
+        }
+
+        cards.push(HeaderCard::new("EXTNAME", string(&self.extname)));
+        cards.extend(self.extra_headers.iter().cloned());
+        header::write_header(&mut dest, &cards)?;
+
+        // The main table.
+
+        let mut row = vec![0u8; row_width];
+
+        for r in 0..n_rows {
+            let mut ofs = 0;
+
+            for (col, base) in self.columns.iter().zip(&heap_bases) {
+                let cell = &mut row[ofs..ofs + col.width];
+
+                match col.var_arrays {
+                    Some(ref va) => {
+                        let (count, start) = va.descriptors[r];
+                        (count as i32).encode(cell);
+                        ((base + start) as i32).encode(&mut cell[4..]);
+                    }
+
+                    None => {
+                        cell.copy_from_slice(&col.cells[r * col.width..(r + 1) * col.width]);
+                    }
+                }
+
+                ofs += col.width;
+            }
+
+            dest.write_all(&row)?;
+        }
+
+        // The heap, and padding.
+
+        for col in &self.columns {
+            if let Some(ref va) = col.var_arrays {
+                dest.write_all(&va.heap)?;
+            }
+        }
+
+        let n = row_width * n_rows + heap_size;
+        dest.write_all(&vec![0u8; (2880 - n % 2880) % 2880])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rubbl_core::ndarray::{Array1, Array3};
+    use std::io::Cursor;
+
+    fn round_trip(builder: &BinTableBuilder) -> BinTable {
+        let mut file = Vec::new();
+        header::write_header(&mut file, &header::empty_primary_header()).unwrap();
+        builder.write(&mut file).unwrap();
+        assert_eq!(file.len() % 2880, 0);
+
+        let mut parser = FitsParser::new(Cursor::new(file)).unwrap();
+        assert_eq!(parser.hdus().len(), 2);
+        assert_eq!(parser.hdus()[1].kind(), HduKind::BinaryTableExtension);
+        BinTable::read(&mut parser, 1).unwrap()
+    }
+
+    #[test]
+    fn tform_parsing() {
+        let f = ColumnFormat::parse("3D").unwrap();
+        assert_eq!(
+            (f.repeat, f.code, f.descriptor, f.width()),
+            (3, b'D', None, 24)
+        );
+        let f = ColumnFormat::parse("E").unwrap();
+        assert_eq!((f.repeat, f.width()), (1, 4));
+        let f = ColumnFormat::parse("1PE(100)").unwrap();
+        assert_eq!(
+            (f.code, f.descriptor, f.width()),
+            (b'E', Some(Descriptor::P), 8)
+        );
+        let f = ColumnFormat::parse("11X").unwrap();
+        assert_eq!(f.width(), 2);
+        assert!(ColumnFormat::parse("3Z").is_err());
+        assert!(ColumnFormat::parse("").is_err());
+        assert_eq!(parse_tdim("(4,3)").unwrap(), vec![3, 4]);
+    }
+
+    #[test]
+    fn bintable_round_trip() {
+        let flux = Array3::from_shape_fn((3, 2, 4), |(r, i, j)| (r * 100 + i * 10 + j) as f32);
+        let vis = Array1::from(vec![
+            Complex::new(1f32, -1.),
+            Complex::new(2., -2.),
+            Complex::new(3., -3.),
+        ]);
+
+        let mut builder = BinTableBuilder::new("TEST");
+        builder
+            .add_column("ANT", Array1::from(vec![1i16, 2, 3]).view())
+            .unwrap()
+            .add_column("FLUX", flux.view())
+            .unwrap()
+            .add_column("VIS", vis.view())
+            .unwrap()
+            .add_column("SCALED", Array1::from(vec![0i32, 10, -10]).view())
+            .unwrap()
+            .add_column("FLAG", Array1::from(vec![true, false, true]).view())
+            .unwrap()
+            .add_string_column("NAME", &["ANT01", "A2", ""])
+            .unwrap()
+            .add_var_column("LAGS", &[vec![1f64], vec![], vec![2., 3., 4.]])
+            .unwrap()
+            .set_scaling("SCALED", 0.5, 100.)
+            .unwrap()
+            .set_unit("FLUX", "Jy")
+            .unwrap();
+
+        assert!(builder
+            .add_column("BAD", Array1::from(vec![1u8]).view())
+            .is_err());
+
+        let table = round_trip(&builder);
+        assert_eq!(table.extname(), "TEST");
+        assert_eq!(table.n_rows(), 3);
+        assert_eq!(table.column("FLUX").unwrap().dims, Some(vec![2, 4]));
+        assert_eq!(table.column("FLUX").unwrap().unit, "Jy");
+
+        assert_eq!(
+            table.column_array::<i16>("ANT").unwrap(),
+            Array1::from(vec![1i16, 2, 3]).into_dyn()
+        );
+        assert_eq!(table.column_array::<f32>("FLUX").unwrap(), flux.into_dyn());
+        assert_eq!(
+            table.column_array::<Complex<f32>>("VIS").unwrap(),
+            vis.into_dyn()
+        );
+        assert_eq!(
+            table.column_f64("SCALED").unwrap(),
+            Array1::from(vec![100., 105., 95.]).into_dyn()
+        );
+        assert_eq!(
+            table.column_array::<bool>("FLAG").unwrap(),
+            Array1::from(vec![true, false, true]).into_dyn()
+        );
+        assert_eq!(
+            table.string_column("NAME").unwrap(),
+            vec!["ANT01", "A2", ""]
+        );
+        assert_eq!(
+            table.var_column::<f64>("LAGS").unwrap(),
+            vec![vec![1.], vec![], vec![2., 3., 4.]]
+        );
+        assert!(table.column_array::<f64>("ANT").is_err());
+        assert!(table.column_array::<f64>("NOPE").is_err());
+
+        struct Row {
+            ant: i16,
+            name: String,
+            scaled: f64,
+            lags: Vec<f64>,
+        }
+
+        impl FromBinTableRow for Row {
+            fn from_row(row: &BinTableRow) -> Result<Self, FitsError> {
+                Ok(Row {
+                    ant: row.get("ANT")?,
+                    name: row.get_string("NAME")?,
+                    scaled: row.get_f64("SCALED")?,
+                    lags: row.get_var("LAGS")?,
+                })
+            }
+        }
+
+        let rows: Vec<Row> = table.rows().unwrap();
+        assert_eq!(rows[2].ant, 3);
+        assert_eq!(rows[0].name, "ANT01");
+        assert_eq!(rows[1].scaled, 105.);
+        assert_eq!(rows[2].lags, vec![2., 3., 4.]);
+        assert_eq!(table.row(1).get_array::<f32>("FLUX").unwrap()[[1, 2]], 112.);
+    }
+}
@@ -0,0 +1,321 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! Parsing and formatting FITS header cards.
+//!
+//! The low-level decoder in this crate only looks at the few header keywords
+//! needed to understand the overall structure of a FITS file. This module
+//! provides access to the values of all of the keywords of an HDU, as well as
+//! the ability to create new header records.
+
+use std::io::Write;
+use std::str;
+
+use crate::{FitsError, FitsFormatError};
+
+/// The value of a FITS header keyword.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HeaderValue {
+    /// A logical value, `T` or `F`.
+    Logical(bool),
+
+    /// An integer value.
+    Integer(i64),
+
+    /// A floating-point value.
+    Float(f64),
+
+    /// A character string value.
+    String(String),
+
+    /// A value that is not understood by this module, such as a complex
+    /// number, as its raw text.
+    Other(String),
+}
+
+impl HeaderValue {
+    /// Get this value as a boolean, if it is a logical.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            HeaderValue::Logical(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Get this value as an integer, if it is one.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            HeaderValue::Integer(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// Get this value as a float. Integers are converted.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            HeaderValue::Integer(i) => Some(i as f64),
+            HeaderValue::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    /// Get this value as a string, if it is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            HeaderValue::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// A single 80-byte FITS header record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeaderCard {
+    /// The keyword, without trailing spaces.
+    pub keyword: String,
+
+    /// The value of the keyword. This is `None` for commentary records such as
+    /// `COMMENT` and `HISTORY`, and for keywords with undefined values.
+    pub value: Option<HeaderValue>,
+
+    /// The comment, or the text of a commentary record.
+    pub comment: String,
+}
+
+impl HeaderCard {
+    /// Create a new card with a value and no comment.
+    pub fn new<S: Into<String>>(keyword: S, value: HeaderValue) -> Self {
+        HeaderCard {
+            keyword: keyword.into(),
+            value: Some(value),
+            comment: String::new(),
+        }
+    }
+
+    /// Set the comment of this card.
+    pub fn with_comment<S: Into<String>>(mut self, comment: S) -> Self {
+        self.comment = comment.into();
+        self
+    }
+
+    /// Parse an 80-byte header record.
+    pub fn parse(record: &[u8]) -> Result<Self, FitsFormatError> {
+        if record.iter().any(|c| *c < 0x20 || *c > 0x7E) {
+            return Err(FitsFormatError::IllegalAscii);
+        }
+
+        let text = str::from_utf8(record)?;
+        let keyword = text[..8].trim_end().to_owned();
+
+        if &record[8..10] != b"= " {
+            return Ok(HeaderCard {
+                keyword,
+                value: None,
+                comment: text[8..].trim_end().to_owned(),
+            });
+        }
+
+        let rest = text[10..].trim_start();
+
+        let (value, comment) = if let Some(quoted) = rest.strip_prefix('\'') {
+            let mut s = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = None;
+
+            while let Some((i, c)) = chars.next() {
+                if c == '\'' {
+                    if quoted[i + 1..].starts_with('\'') {
+                        s.push('\'');
+                        chars.next();
+                    } else {
+                        end = Some(i + 1);
+                        break;
+                    }
+                } else {
+                    s.push(c);
+                }
+            }
+
+            let end = end.ok_or(FitsFormatError::Unterminated)?;
+            let after = quoted[end..].trim();
+
+            let comment = match after.strip_prefix('/') {
+                Some(c) => c.trim(),
+                None if after.is_empty() => "",
+                None => {
+                    return Err(FitsFormatError::IllegalAsciiAfterString(
+                        after.as_bytes()[0],
+                    ));
+                }
+            };
+
+            // Trailing spaces are not significant in FITS strings.
+            let len = s.trim_end().len();
+            s.truncate(len);
+            (Some(HeaderValue::String(s)), comment)
+        } else {
+            let (v, c) = match rest.find('/') {
+                Some(i) => (rest[..i].trim(), rest[i + 1..].trim()),
+                None => (rest.trim(), ""),
+            };
+
+            let value = if v.is_empty() {
+                None
+            } else if v == "T" {
+                Some(HeaderValue::Logical(true))
+            } else if v == "F" {
+                Some(HeaderValue::Logical(false))
+            } else if let Ok(i) = v.parse::<i64>() {
+                Some(HeaderValue::Integer(i))
+            } else if let Ok(f) = v.replace('D', "E").replace('d', "e").parse::<f64>() {
+                Some(HeaderValue::Float(f))
+            } else {
+                Some(HeaderValue::Other(v.to_owned()))
+            };
+
+            (value, c)
+        };
+
+        Ok(HeaderCard {
+            keyword,
+            value,
+            comment: comment.to_owned(),
+        })
+    }
+
+    /// Format this card as an 80-byte header record.
+    ///
+    /// Values are written in the FITS fixed format. Comments are truncated if
+    /// they don't fit in the record.
+    pub fn to_record(&self) -> Result<[u8; 80], FitsFormatError> {
+        if self.keyword.len() > 8 || !self.keyword.is_ascii() {
+            return Err(FitsFormatError::CardTooLong(self.keyword.clone()));
+        }
+
+        let mut text = format!("{:<8}", self.keyword);
+
+        match self.value {
+            None => {
+                if !self.comment.is_empty() {
+                    text.push_str(&self.comment);
+                }
+            }
+
+            Some(ref v) => {
+                text.push_str("= ");
+
+                match *v {
+                    HeaderValue::Logical(b) => {
+                        text.push_str(&format!("{:>20}", if b { "T" } else { "F" }))
+                    }
+                    HeaderValue::Integer(i) => text.push_str(&format!("{:>20}", i)),
+                    HeaderValue::Float(f) => text.push_str(&format!("{:>20}", format_float(f))),
+                    HeaderValue::String(ref s) => {
+                        let quoted = format!("'{:<8}'", s.replace('\'', "''"));
+                        text.push_str(&format!("{:<20}", quoted));
+                    }
+                    HeaderValue::Other(ref s) => text.push_str(&format!("{:>20}", s)),
+                }
+
+                if text.len() > 80 {
+                    return Err(FitsFormatError::CardTooLong(self.keyword.clone()));
+                }
+
+                if !self.comment.is_empty() && text.len() < 77 {
+                    text.push_str(" / ");
+                    text.push_str(&self.comment);
+                }
+            }
+        }
+
+        if !text.is_ascii() {
+            return Err(FitsFormatError::IllegalAscii);
+        }
+
+        text.truncate(80);
+        let mut record = [b' '; 80];
+        record[..text.len()].copy_from_slice(text.as_bytes());
+        Ok(record)
+    }
+}
+
+/// Format a float so that FITS readers will recognize it as one.
+fn format_float(f: f64) -> String {
+    let s = format!("{:E}", f);
+
+    match s.find('E') {
+        Some(i) if !s[..i].contains('.') => format!("{}.0{}", &s[..i], &s[i..]),
+        _ => s,
+    }
+}
+
+/// Look up the value of a keyword in a list of header cards.
+pub fn find_value<'a>(cards: &'a [HeaderCard], keyword: &str) -> Option<&'a HeaderValue> {
+    cards
+        .iter()
+        .find(|c| c.keyword == keyword)
+        .and_then(|c| c.value.as_ref())
+}
+
+/// Write out a complete header: the cards, an `END` record, and padding to a
+/// multiple of 2880 bytes.
+pub fn write_header<W: Write>(mut dest: W, cards: &[HeaderCard]) -> Result<(), FitsError> {
+    let mut n = 0;
+
+    for card in cards {
+        dest.write_all(&card.to_record()?)?;
+        n += 80;
+    }
+
+    dest.write_all(crate::END_MARKER)?;
+    n += 80;
+
+    let padding = (2880 - n % 2880) % 2880;
+    dest.write_all(&vec![b' '; padding])?;
+    Ok(())
+}
+
+/// The header cards of a primary HDU that contains no data, as is usual for
+/// FITS files whose contents are in extensions.
+pub fn empty_primary_header() -> Vec<HeaderCard> {
+    vec![
+        HeaderCard::new("SIMPLE", HeaderValue::Logical(true)),
+        HeaderCard::new("BITPIX", HeaderValue::Integer(8)),
+        HeaderCard::new("NAXIS", HeaderValue::Integer(0)),
+        HeaderCard::new("EXTEND", HeaderValue::Logical(true)),
+    ]
+}
+
+#[cfg(test)]
+#[test]
+fn header_card_round_trip() {
+    let cards = [
+        HeaderCard::new("NAXIS1", HeaderValue::Integer(-12)),
+        HeaderCard::new("EXTNAME", HeaderValue::String("UV_DATA".to_owned())),
+        HeaderCard::new("OBSERVER", HeaderValue::String("O'Brien".to_owned())).with_comment("PI"),
+        HeaderCard::new("EXTEND", HeaderValue::Logical(true)),
+        HeaderCard::new("TSCAL1", HeaderValue::Float(1.5e-3)),
+        HeaderCard::new("TZERO1", HeaderValue::Float(32768.)),
+        HeaderCard {
+            keyword: "HISTORY".to_owned(),
+            value: None,
+            comment: "made by hand".to_owned(),
+        },
+    ];
+
+    for card in &cards {
+        let record = card.to_record().unwrap();
+        assert_eq!(&HeaderCard::parse(&record).unwrap(), card);
+    }
+
+    // The fixed-format parsers used to scan the file structure must agree.
+    let record = cards[0].to_record().unwrap();
+    assert_eq!(crate::parse_fixed_int(&record).unwrap(), -12);
+    let record = cards[1].to_record().unwrap();
+    assert_eq!(crate::parse_fixed_string(&record).unwrap(), "UV_DATA");
+
+    let r = b"CRVAL1  =  1.234D+02 / a Fortran-style exponent                                  ";
+    let card = HeaderCard::parse(r).unwrap();
+    assert_eq!(card.value, Some(HeaderValue::Float(123.4)));
+    assert_eq!(card.comment, "a Fortran-style exponent");
+}
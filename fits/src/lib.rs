@@ -8,12 +8,21 @@
 
 #![deny(missing_docs)]
 
-use rubbl_core::io::EofReadExactExt;
+use rubbl_core::{
+    chunked::ArrayChunkSource,
+    io::EofReadExactExt,
+    ndarray::{ArrayViewMutD, Axis},
+};
 use std::io::prelude::*;
 use std::io::SeekFrom;
+use std::marker::PhantomData;
 use std::str;
 use thiserror::Error;
 
+pub mod bintable;
+pub mod header;
+pub mod metafits;
+
 /// An error type for when a FITS file is malformed.
 #[allow(missing_docs)]
 #[derive(Debug, Error)]
@@ -57,6 +66,50 @@ pub enum FitsError {
     #[error("malformed FITS header keyword")]
     MalformedHeader,
 
+    #[error("no such HDU number {0} in FITS file")]
+    NoSuchHdu(usize),
+
+    #[error("FITS HDU number {0} is not a binary table")]
+    NotBinTable(usize),
+
+    #[error("FITS HDU number {0} does not contain an N-dimensional array")]
+    NotArray(usize),
+
+    #[error("FITS HDU number {hdu} has BITPIX {found:?}, not {expected:?}")]
+    BitpixMismatch {
+        hdu: usize,
+        expected: Bitpix,
+        found: Bitpix,
+    },
+
+    #[error("no FITS extension named {0:?}")]
+    NoSuchExtension(String),
+
+    #[error("missing required FITS header keyword {0}")]
+    MissingHeader(String),
+
+    #[error("bad value for FITS header keyword {0}")]
+    BadHeaderValue(String),
+
+    #[error("unsupported FITS binary table column format {0:?}")]
+    UnsupportedTform(String),
+
+    #[error("no such FITS binary table column {0:?}")]
+    NoSuchColumn(String),
+
+    #[error("FITS binary table column {column:?} has type code {found}, not {expected}")]
+    ColumnTypeMismatch {
+        column: String,
+        expected: char,
+        found: char,
+    },
+
+    #[error("new FITS binary table column {0:?} has a different number of rows than the others")]
+    ColumnLengthMismatch(String),
+
+    #[error("variable-length array in FITS binary table column {0:?} extends beyond the heap")]
+    HeapOutOfBounds(String),
+
     #[error("{0}")]
     FitsFormat(#[from] FitsFormatError),
 
@@ -108,6 +161,9 @@ pub enum FitsFormatError {
     #[error("illegal negative NAXIS{value} value {n}")]
     NegativeNaxisValue { value: usize, n: isize },
 
+    #[error("FITS header keyword {0} or its value is too long")]
+    CardTooLong(String),
+
     #[error("{0}")]
     Utf8(#[from] std::str::Utf8Error),
 }
@@ -363,7 +419,10 @@ impl<R: Read> FitsDecoder<R> {
 
                 self.gcount = n as usize;
             } else if record == END_MARKER {
-                let group_size = if self.hdu_num == 0 && self.primary_seen_groups {
+                let group_size = if self.naxis.is_empty() {
+                    // With NAXIS = 0 there is no data array at all.
+                    self.pcount
+                } else if self.hdu_num == 0 && self.primary_seen_groups {
                     self.pcount + self.naxis.iter().skip(1).fold(1, |p, n| p * n) as isize
                 } else {
                     self.pcount + self.naxis.iter().fold(1, |p, n| p * n) as isize
@@ -478,10 +537,7 @@ pub enum HduKind {
 pub struct ParsedHdu {
     kind: HduKind,
     name: String,
-
-    #[allow(dead_code)]
     header_offset: u64,
-    #[allow(dead_code)]
     n_header_records: usize,
 
     bitpix: Bitpix,
@@ -648,7 +704,14 @@ impl<R: Read + Seek> FitsParser<R> {
                 naxis.remove(0); // dummy 0 value when primary HDU is random-groups
             }
 
-            let group_size = pcount + naxis.iter().fold(1, |p, n| p * n) as isize;
+            // With NAXIS = 0 there is no data array at all, rather than one
+            // with a single element.
+            let array_size = if naxis_value == 0 {
+                0
+            } else {
+                naxis.iter().fold(1, |p, n| p * n)
+            };
+            let group_size = pcount + array_size as isize;
 
             if group_size < 0 {
                 return Err(FitsError::NegativeGroupSize);
@@ -701,12 +764,177 @@ impl<R: Read + Seek> FitsParser<R> {
         &self.hdus[..]
     }
 
+    /// Read and parse all of the header records of an HDU.
+    ///
+    /// The returned list does not include the `END` record.
+    pub fn read_hdu_header(
+        &mut self,
+        hdu_num: usize,
+    ) -> Result<Vec<header::HeaderCard>, FitsError> {
+        let hdu = self
+            .hdus
+            .get(hdu_num)
+            .ok_or(FitsError::NoSuchHdu(hdu_num))?;
+        let mut buf = vec![0u8; hdu.n_header_records * 80];
+        self.inner.seek(SeekFrom::Start(hdu.header_offset))?;
+        self.inner.read_exact(&mut buf)?;
+
+        buf.chunks(80)
+            .map(|r| header::HeaderCard::parse(r).map_err(|e| e.into()))
+            .collect()
+    }
+
+    /// Read the complete data section of an HDU into memory.
+    ///
+    /// The returned buffer does not include the padding at the end of the
+    /// data.
+    pub fn read_hdu_data(&mut self, hdu_num: usize) -> Result<Vec<u8>, FitsError> {
+        let hdu = self
+            .hdus
+            .get(hdu_num)
+            .ok_or(FitsError::NoSuchHdu(hdu_num))?;
+        let mut buf = vec![0u8; hdu.data_size()];
+        self.inner.seek(SeekFrom::Start(hdu.data_offset()))?;
+        self.inner.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Stream the N-dimensional array of an HDU in chunks.
+    ///
+    /// The HDU must be the primary HDU or an image extension, and `T` must
+    /// match its BITPIX. See [`HduArrayChunks`] for how the array is divided
+    /// into items.
+    pub fn array_chunks<T: FitsArrayType>(
+        &mut self,
+        hdu_num: usize,
+    ) -> Result<HduArrayChunks<'_, R, T>, FitsError> {
+        let hdu = self
+            .hdus
+            .get(hdu_num)
+            .ok_or(FitsError::NoSuchHdu(hdu_num))?;
+
+        match hdu.kind {
+            HduKind::PrimaryArray | HduKind::ImageExtension if !hdu.naxis.is_empty() => {}
+            _ => return Err(FitsError::NotArray(hdu_num)),
+        }
+
+        if hdu.bitpix != T::BITPIX {
+            return Err(FitsError::BitpixMismatch {
+                hdu: hdu_num,
+                expected: T::BITPIX,
+                found: hdu.bitpix,
+            });
+        }
+
+        // FITS lists the fastest-varying axis first.
+        let item_shape: Vec<usize> = hdu.naxis[..hdu.naxis.len() - 1]
+            .iter()
+            .rev()
+            .copied()
+            .collect();
+        let n_items = hdu.naxis[hdu.naxis.len() - 1];
+        self.inner.seek(SeekFrom::Start(hdu.data_offset()))?;
+
+        Ok(HduArrayChunks {
+            inner: &mut self.inner,
+            item_bytes: T::SIZE * item_shape.iter().product::<usize>(),
+            item_shape,
+            n_items,
+            next_item: 0,
+            buf: Vec::new(),
+            _type: PhantomData,
+        })
+    }
+
     /// Consume this parser and return the inner stream.
     pub fn into_inner(self) -> R {
         self.inner
     }
 }
 
+/// A Rust type that can hold the elements of a FITS N-dimensional array.
+pub trait FitsArrayType: bintable::BinTableType {
+    /// The BITPIX of arrays with elements of this type.
+    const BITPIX: Bitpix;
+}
+
+impl FitsArrayType for u8 {
+    const BITPIX: Bitpix = Bitpix::U8;
+}
+
+impl FitsArrayType for i16 {
+    const BITPIX: Bitpix = Bitpix::I16;
+}
+
+impl FitsArrayType for i32 {
+    const BITPIX: Bitpix = Bitpix::I32;
+}
+
+impl FitsArrayType for i64 {
+    const BITPIX: Bitpix = Bitpix::I64;
+}
+
+impl FitsArrayType for f32 {
+    const BITPIX: Bitpix = Bitpix::F32;
+}
+
+impl FitsArrayType for f64 {
+    const BITPIX: Bitpix = Bitpix::F64;
+}
+
+/// An [`ArrayChunkSource`] that reads the N-dimensional array of an HDU from
+/// disk, created by [`FitsParser::array_chunks`].
+///
+/// The array is presented in C order, so that its shape is the reverse of
+/// the `NAXISn` values, and its items are the planes along the last FITS
+/// axis. The stored values are returned as they are, without applying any
+/// `BSCALE` or `BZERO` scaling.
+#[derive(Debug)]
+pub struct HduArrayChunks<'a, R, T> {
+    inner: &'a mut R,
+    item_shape: Vec<usize>,
+    item_bytes: usize,
+    n_items: usize,
+    next_item: usize,
+    buf: Vec<u8>,
+    _type: PhantomData<T>,
+}
+
+impl<'a, R: Read, T: FitsArrayType> ArrayChunkSource<T> for HduArrayChunks<'a, R, T> {
+    type Error = FitsError;
+
+    fn item_shape(&self) -> &[usize] {
+        &self.item_shape[..]
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the item shape of *dest* does not match
+    /// [`Self::item_shape`].
+    fn read_chunk(&mut self, mut dest: ArrayViewMutD<T>) -> Result<usize, Self::Error> {
+        assert_eq!(
+            &dest.shape()[1..],
+            &self.item_shape[..],
+            "chunk item shape does not match FITS array"
+        );
+
+        let n = dest.len_of(Axis(0)).min(self.n_items - self.next_item);
+        self.buf.resize(n * self.item_bytes, 0);
+        self.inner.read_exact(&mut self.buf)?;
+
+        for (value, bytes) in dest
+            .slice_axis_mut(Axis(0), (0..n).into())
+            .iter_mut()
+            .zip(self.buf.chunks_exact(T::SIZE))
+        {
+            *value = T::decode(bytes);
+        }
+
+        self.next_item += n;
+        Ok(n)
+    }
+}
+
 impl ParsedHdu {
     /// Get the "name" of this HDU. If this is an extension HDU, this is the
     /// value of the EXTNAME header keyword. For the primary HDU, it is an
@@ -730,6 +958,20 @@ impl ParsedHdu {
     pub fn shape(&self) -> (usize, isize, &[usize]) {
         (self.gcount, self.pcount, &self.naxis[..])
     }
+
+    /// Get the file offset at which this HDU's data begin. The header
+    /// records, including the `END` record, are padded to a multiple of 2880
+    /// bytes.
+    fn data_offset(&self) -> u64 {
+        let header_size = (self.n_header_records + 1) * 80;
+        self.header_offset + (header_size.div_ceil(2880) * 2880) as u64
+    }
+
+    /// Get the size of this HDU's data in bytes, excluding padding.
+    fn data_size(&self) -> usize {
+        let group_size = self.pcount as usize + self.naxis.iter().product::<usize>();
+        self.bitpix.n_bytes() * self.gcount * group_size
+    }
 }
 
 fn parse_fixed_int(record: &[u8]) -> Result<isize, FitsFormatError> {
@@ -813,6 +1055,54 @@ fn parse_fixed_int(record: &[u8]) -> Result<isize, FitsFormatError> {
     Ok(value)
 }
 
+#[cfg(test)]
+#[test]
+fn array_chunks() {
+    use header::{HeaderCard, HeaderValue};
+    use rubbl_core::{
+        chunked::{ArrayChunkSink, ArrayCollector},
+        ndarray::{Array, Array3, IxDyn},
+    };
+    use std::io::Cursor;
+
+    let data = Array3::from_shape_fn((5, 4, 2), |(i, j, k)| (i * 100 + j * 10 + k) as f32);
+    let mut file = Vec::new();
+    header::write_header(
+        &mut file,
+        &[
+            HeaderCard::new("SIMPLE", HeaderValue::Logical(true)),
+            HeaderCard::new("BITPIX", HeaderValue::Integer(-32)),
+            HeaderCard::new("NAXIS", HeaderValue::Integer(3)),
+            HeaderCard::new("NAXIS1", HeaderValue::Integer(2)),
+            HeaderCard::new("NAXIS2", HeaderValue::Integer(4)),
+            HeaderCard::new("NAXIS3", HeaderValue::Integer(5)),
+        ],
+    )
+    .unwrap();
+    file.extend(data.iter().flat_map(|v| v.to_be_bytes()));
+    file.resize(file.len().div_ceil(2880) * 2880, 0);
+
+    let mut parser = FitsParser::new(Cursor::new(file)).unwrap();
+    assert!(parser.array_chunks::<i16>(0).is_err());
+    assert!(parser.array_chunks::<f32>(1).is_err());
+
+    let mut source = parser.array_chunks::<f32>(0).unwrap();
+    assert_eq!(source.item_shape(), &[4, 2]);
+    let mut sink = ArrayCollector::new(source.item_shape());
+    let mut buf = Array::zeros(IxDyn(&[3, 4, 2]));
+
+    loop {
+        let n = source.read_chunk(buf.view_mut()).unwrap();
+        if n == 0 {
+            break;
+        }
+        sink.write_chunk(buf.slice_axis(Axis(0), (0..n).into()))
+            .unwrap();
+    }
+
+    assert_eq!(sink.into_array(), data.into_dyn());
+}
+
 #[cfg(test)]
 #[test]
 fn fixed_int_parsing() {
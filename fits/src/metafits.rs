@@ -0,0 +1,342 @@
+// Copyright 2024 Peter Williams and collaborators
+// Licensed under the MIT License.
+
+//! Reading the metafits files that describe MWA observations.
+//!
+//! Every observation made with the Murchison Widefield Array comes with a
+//! “metafits” file: a FITS file whose primary header describes the
+//! observation as a whole — pointing, timing, and frequency setup — and whose
+//! `TILEDATA` binary table describes the tiles, with one row per tile and
+//! polarization.
+//!
+//! ```no_run
+//! use rubbl_fits::metafits::Metafits;
+//!
+//! let metafits = Metafits::open("1065880128.metafits").unwrap();
+//! let layout = metafits.antenna_layout().unwrap();
+//! println!("{} tiles", layout.len());
+//! ```
+
+use rubbl_core::{
+    array_layout::{enu_to_itrf, geodetic_to_itrf, AntennaLayout},
+    time::unix_to_mjd_seconds,
+};
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    path::Path,
+};
+
+use crate::{
+    bintable::BinTable,
+    header::{find_value, HeaderCard, HeaderValue},
+    FitsError, FitsParser,
+};
+
+/// The geodetic longitude of the MWA array center, in radians.
+pub const MWA_LONGITUDE: f64 = 2.036_289_866_856_104;
+
+/// The geodetic latitude of the MWA array center, in radians.
+pub const MWA_LATITUDE: f64 = -0.466_060_844_838_639_4;
+
+/// The height of the MWA array center above sea level, in meters.
+pub const MWA_HEIGHT: f64 = 377.827;
+
+/// The nominal diameter of an MWA tile, in meters.
+pub const MWA_TILE_DIAMETER: f64 = 4.;
+
+/// The contents of an MWA metafits file.
+#[derive(Clone, Debug)]
+pub struct Metafits {
+    primary: Vec<HeaderCard>,
+    tiles: BinTable,
+}
+
+impl Metafits {
+    /// Read the metafits file at *path*.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, FitsError> {
+        let mut parser = FitsParser::new(File::open(path)?)?;
+        Self::from_parser(&mut parser)
+    }
+
+    /// Read a metafits file from a parsed FITS stream.
+    pub fn from_parser<R: Read + Seek>(parser: &mut FitsParser<R>) -> Result<Self, FitsError> {
+        let primary = parser.read_hdu_header(0)?;
+        let tiledata = parser
+            .hdus()
+            .iter()
+            .position(|h| h.extname() == "TILEDATA")
+            .ok_or_else(|| FitsError::NoSuchExtension("TILEDATA".to_owned()))?;
+        let tiles = BinTable::read(parser, tiledata)?;
+        Ok(Metafits { primary, tiles })
+    }
+
+    /// Get the header cards of the primary HDU.
+    pub fn primary_header(&self) -> &[HeaderCard] {
+        &self.primary
+    }
+
+    /// Get the `TILEDATA` table.
+    pub fn tile_data(&self) -> &BinTable {
+        &self.tiles
+    }
+
+    /// Look up a keyword of the primary header.
+    pub fn header_value(&self, keyword: &str) -> Option<&HeaderValue> {
+        find_value(&self.primary, keyword)
+    }
+
+    fn required(&self, keyword: &str) -> Result<&HeaderValue, FitsError> {
+        self.header_value(keyword)
+            .ok_or_else(|| FitsError::MissingHeader(keyword.to_owned()))
+    }
+
+    fn required_f64(&self, keyword: &str) -> Result<f64, FitsError> {
+        self.required(keyword)?
+            .as_f64()
+            .ok_or_else(|| FitsError::BadHeaderValue(keyword.to_owned()))
+    }
+
+    /// Get the observation ID, from the `GPSTIME` keyword.
+    pub fn obs_id(&self) -> Result<i64, FitsError> {
+        self.required("GPSTIME")?
+            .as_i64()
+            .ok_or_else(|| FitsError::BadHeaderValue("GPSTIME".to_owned()))
+    }
+
+    /// Get the name of the observation, from the `FILENAME` keyword.
+    pub fn obs_name(&self) -> Result<&str, FitsError> {
+        self.required("FILENAME")?
+            .as_str()
+            .ok_or_else(|| FitsError::BadHeaderValue("FILENAME".to_owned()))
+    }
+
+    /// Get the start of the observation, as an MJD in seconds in the UTC
+    /// time scale, from the `DATE-OBS` keyword.
+    pub fn start_time(&self) -> Result<f64, FitsError> {
+        let text = self
+            .required("DATE-OBS")?
+            .as_str()
+            .ok_or_else(|| FitsError::BadHeaderValue("DATE-OBS".to_owned()))?;
+        parse_isot(text)
+            .map(unix_to_mjd_seconds)
+            .ok_or_else(|| FitsError::BadHeaderValue("DATE-OBS".to_owned()))
+    }
+
+    /// Get the duration of each integration, in seconds, from the `INTTIME`
+    /// keyword.
+    pub fn integration_time(&self) -> Result<f64, FitsError> {
+        self.required_f64("INTTIME")
+    }
+
+    /// Get the number of integrations in the observation, the `EXPOSURE`
+    /// divided by the `INTTIME`.
+    pub fn n_timesteps(&self) -> Result<usize, FitsError> {
+        let n = self.required_f64("EXPOSURE")? / self.integration_time()?;
+        Ok(n.round().max(0.) as usize)
+    }
+
+    /// Get the right ascension and declination of the pointing center, in
+    /// radians, from the `RA` and `DEC` keywords.
+    pub fn pointing_center(&self) -> Result<(f64, f64), FitsError> {
+        Ok((
+            self.required_f64("RA")?.to_radians(),
+            self.required_f64("DEC")?.to_radians(),
+        ))
+    }
+
+    /// Get the right ascension and declination of the phase center, in
+    /// radians, from the `RAPHASE` and `DECPHASE` keywords, or the pointing
+    /// center if they are absent.
+    pub fn phase_center(&self) -> Result<(f64, f64), FitsError> {
+        if self.header_value("RAPHASE").is_none() {
+            return self.pointing_center();
+        }
+
+        Ok((
+            self.required_f64("RAPHASE")?.to_radians(),
+            self.required_f64("DECPHASE")?.to_radians(),
+        ))
+    }
+
+    /// Get the width of each fine channel, in Hz, from the `FINECHAN`
+    /// keyword.
+    pub fn fine_channel_width(&self) -> Result<f64, FitsError> {
+        Ok(self.required_f64("FINECHAN")? * 1e3)
+    }
+
+    /// Get the center frequencies of the fine channels, in Hz.
+    ///
+    /// The observation’s `NCHANS` fine channels are taken to evenly fill its
+    /// band, `BANDWDTH` wide and centered on `FREQCENT`. This holds for
+    /// observations whose coarse channels are contiguous.
+    pub fn fine_channel_freqs(&self) -> Result<Vec<f64>, FitsError> {
+        let n_chans = self.required_f64("NCHANS")? as usize;
+        let width = self.fine_channel_width()?;
+        let low = (self.required_f64("FREQCENT")? - self.required_f64("BANDWDTH")? / 2.) * 1e6;
+        Ok((0..n_chans)
+            .map(|i| low + (i as f64 + 0.5) * width)
+            .collect())
+    }
+
+    /// Get the layout of the tiles.
+    ///
+    /// Tiles are ordered by their `Antenna` index and named by `TileName`.
+    /// The `North` and `East` columns give their offsets from the array
+    /// center, and `Height` their height above sea level, which is compared
+    /// to [`MWA_HEIGHT`] to find their offsets upwards. Every tile is given
+    /// a diameter of [`MWA_TILE_DIAMETER`].
+    pub fn antenna_layout(&self) -> Result<AntennaLayout, FitsError> {
+        let antennas = self.tiles.column_f64("Antenna")?;
+        let names = self.tiles.string_column("TileName")?;
+        let north = self.tiles.column_f64("North")?;
+        let east = self.tiles.column_f64("East")?;
+        let height = self.tiles.column_f64("Height")?;
+
+        // Each tile appears once per polarization; keep its first row.
+        let mut rows: Vec<(i64, usize)> = Vec::new();
+
+        for (row, antenna) in antennas.iter().enumerate() {
+            let antenna = *antenna as i64;
+
+            if !rows.iter().any(|(a, _)| *a == antenna) {
+                rows.push((antenna, row));
+            }
+        }
+
+        rows.sort_unstable();
+
+        let center = geodetic_to_itrf(MWA_LONGITUDE, MWA_LATITUDE, MWA_HEIGHT);
+        let mut layout = AntennaLayout::new();
+
+        for (_, row) in rows {
+            let enu = [east[row], north[row], height[row] - MWA_HEIGHT];
+            let offset = enu_to_itrf(enu, MWA_LONGITUDE, MWA_LATITUDE);
+            layout.push(
+                names[row].clone(),
+                [
+                    center[0] + offset[0],
+                    center[1] + offset[1],
+                    center[2] + offset[2],
+                ],
+                MWA_TILE_DIAMETER,
+            );
+        }
+
+        Ok(layout)
+    }
+}
+
+/// Parse a UTC date and time of the form `YYYY-MM-DDThh:mm:ss[.sss]` into
+/// seconds since the Unix epoch.
+fn parse_isot(text: &str) -> Option<f64> {
+    let (date, time) = text.trim().split_once('T')?;
+    let mut date = date.split('-').map(|p| p.parse::<i64>());
+    let (y, m, d) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time.split(':');
+    let hours: f64 = time.next()?.parse().ok()?;
+    let minutes: f64 = time.next()?.parse().ok()?;
+    let seconds: f64 = time.next().unwrap_or("0").parse().ok()?;
+
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return None;
+    }
+
+    // Days since 1970 January 1 in the proleptic Gregorian calendar, after
+    // Howard Hinnant's `days_from_civil`.
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    Some(days as f64 * 86400. + hours * 3600. + minutes * 60. + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bintable::BinTableBuilder, header};
+    use rubbl_core::ndarray::Array1;
+    use std::io::Cursor;
+
+    /// Make a minimal metafits file with the given primary header cards.
+    fn fake_metafits(cards: Vec<HeaderCard>) -> Metafits {
+        let mut file = Vec::new();
+        let mut primary = header::empty_primary_header();
+        primary.extend(cards);
+        header::write_header(&mut file, &primary).unwrap();
+
+        let mut builder = BinTableBuilder::new("TILEDATA");
+        builder
+            .add_column("Antenna", Array1::from(vec![1i16, 1, 0, 0]).view())
+            .unwrap()
+            .add_string_column("TileName", &["Tile012", "Tile012", "Tile011", "Tile011"])
+            .unwrap()
+            .add_string_column("Pol", &["X", "Y", "X", "Y"])
+            .unwrap()
+            .add_column("North", Array1::from(vec![10f32, 10., 0., 0.]).view())
+            .unwrap()
+            .add_column("East", Array1::from(vec![0f32, 0., 0., 0.]).view())
+            .unwrap()
+            .add_column(
+                "Height",
+                Array1::from(vec![377.827f32, 377.827, 377.827, 377.827]).view(),
+            )
+            .unwrap();
+        builder.write(&mut file).unwrap();
+
+        let mut parser = FitsParser::new(Cursor::new(file)).unwrap();
+        Metafits::from_parser(&mut parser).unwrap()
+    }
+
+    #[test]
+    fn tile_layout() {
+        let metafits = fake_metafits(Vec::new());
+        let layout = metafits.antenna_layout().unwrap();
+
+        assert_eq!(layout.names, vec!["Tile011", "Tile012"]);
+        let b = layout.baseline(0, 1);
+        let len = b.iter().map(|x| x * x).sum::<f64>().sqrt();
+        assert!((len - 10.).abs() < 1e-3);
+        // Tile012 is north of Tile011, and moving north always increases Z.
+        assert!(b[2] > 0.);
+    }
+
+    #[test]
+    fn observation_parameters() {
+        let metafits = fake_metafits(vec![
+            HeaderCard::new("GPSTIME", HeaderValue::Integer(1065880128)),
+            HeaderCard::new(
+                "DATE-OBS",
+                HeaderValue::String("2013-10-15T13:48:32".to_owned()),
+            ),
+            HeaderCard::new("EXPOSURE", HeaderValue::Integer(112)),
+            HeaderCard::new("INTTIME", HeaderValue::Float(0.5)),
+            HeaderCard::new("RA", HeaderValue::Float(0.)),
+            HeaderCard::new("DEC", HeaderValue::Float(-27.)),
+            HeaderCard::new("NCHANS", HeaderValue::Integer(768)),
+            HeaderCard::new("FINECHAN", HeaderValue::Float(40.)),
+            HeaderCard::new("FREQCENT", HeaderValue::Float(154.24)),
+            HeaderCard::new("BANDWDTH", HeaderValue::Float(30.72)),
+        ]);
+
+        assert_eq!(metafits.obs_id().unwrap(), 1065880128);
+        // MJD 56580.
+        assert_eq!(
+            metafits.start_time().unwrap(),
+            56580. * 86400. + 13. * 3600. + 48. * 60. + 32.
+        );
+        assert_eq!(metafits.n_timesteps().unwrap(), 224);
+        assert_eq!(metafits.phase_center().unwrap(), (0., -27f64.to_radians()));
+
+        let freqs = metafits.fine_channel_freqs().unwrap();
+        assert_eq!(freqs.len(), 768);
+        assert!((freqs[0] - 138.9e6).abs() < 1.);
+        assert!((freqs[767] - 169.58e6).abs() < 1.);
+
+        assert!(parse_isot("2000-01-01T00:00:00").unwrap() == 946684800.);
+        assert!(parse_isot("2000-13-01T00:00:00").is_none());
+    }
+}
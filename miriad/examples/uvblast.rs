@@ -3,7 +3,12 @@
 
 use anyhow::{Context, Error};
 use clap::{Arg, Command};
-use std::ffi::{OsStr, OsString};
+use rubbl_core::{
+    ctry,
+    notify::{ClapNotificationArgsExt, NotificationBackend, ProgressTracker},
+    rn_note,
+};
+use std::path::{Path, PathBuf};
 use std::process;
 use std::time::Instant;
 
@@ -11,44 +16,50 @@ fn main() {
     let matches = Command::new("uvblast")
         .version("0.1.0")
         .about("Read a MIRIAD UV data set as fast as possible.")
+        .rubbl_notify_args()
         .arg(
             Arg::new("PATH")
+                .value_parser(clap::value_parser!(PathBuf))
                 .help("The path to the dataset directory")
                 .required(true)
                 .index(1),
         )
         .get_matches();
 
-    let path = matches.get_one::<OsString>("PATH").unwrap();
-
-    process::exit(match inner(path.as_ref()) {
-        Ok(code) => code,
-
-        Err(e) => {
-            println!("fatal error while processing {}", path.to_string_lossy());
-            for cause in e.chain() {
-                println!("  caused by: {}", cause);
-            }
-            1
-        }
-    });
+    process::exit(rubbl_core::notify::run_with_notifications(
+        matches,
+        |matches, nbe| -> Result<i32, Error> {
+            let path = matches.get_one::<PathBuf>("PATH").unwrap();
+            Ok(ctry!(
+                inner(path, nbe);
+                "fatal error while processing {}", path.display()
+            ))
+        },
+    ));
 }
 
-fn inner(path: &OsStr) -> Result<i32, Error> {
+fn inner(path: &Path, nbe: &mut dyn NotificationBackend) -> Result<i32, Error> {
     let mut ds = rubbl_miriad::DataSet::open(path).context("error opening dataset")?;
     let mut uv = ds.open_uv().context("could not open as UV dataset")?;
-    let mib = uv.visdata_bytes() as f64 / (1024. * 1024.);
+    let n_bytes = uv.visdata_bytes();
+    let mib = n_bytes as f64 / (1024. * 1024.);
+    let mut progress = ProgressTracker::new("reading", "bytes", Some(n_bytes));
     let mut n = 0usize;
     let t0 = Instant::now();
+    let mut last_pos = uv.position();
 
     while uv.next().context("could not read UV data")? {
-        n += 1
+        n += 1;
+        let pos = uv.position();
+        progress.advance(nbe, pos - last_pos, pos - last_pos);
+        last_pos = pos;
     }
 
-    let dur = t0.elapsed();
-    let dur_secs = dur.subsec_nanos() as f64 * 1e-9 + dur.as_secs() as f64;
+    progress.finish(nbe);
+    let dur_secs = t0.elapsed().as_secs_f64();
 
-    println!(
+    rn_note!(
+        nbe,
         "{} records, {:.1} MiB in {:.3} seconds = {:.3} MiB/s",
         n,
         mib,
@@ -984,6 +984,17 @@ impl DataSet {
         visdata::Decoder::create(self)
     }
 
+    /// Open the UV data of this data set as a stream of typed records.
+    pub fn open_uv_stream(&mut self) -> Result<visdata::UvStream, MiriadFormatError> {
+        visdata::UvStream::create(self)
+    }
+
+    /// Start writing UV data with the variables listed in *vars*, given as
+    /// pairs of types and names.
+    pub fn new_uv(&mut self, vars: &[(Type, &str)]) -> Result<visdata::Encoder, MiriadFormatError> {
+        visdata::Encoder::new(self, vars)
+    }
+
     pub fn new_uv_like(
         &mut self,
         template: &visdata::Decoder,
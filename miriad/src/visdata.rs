@@ -10,7 +10,6 @@ TODO:
 - overrides
 - writing UV data
 - upcasting of data types
-- special handling of j-format "corr" variable?
 
  */
 
@@ -18,13 +17,18 @@ TODO:
 
 use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
 use rubbl_core::io::{AligningReader, AligningWriter, OpenResultExt};
+use rubbl_core::{
+    chunked::ArrayChunkSource,
+    ndarray::{Array1, ArrayView2, ArrayViewMutD, Axis},
+    Complex,
+};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 use std::slice;
 
-use super::{AnyMiriadValue, DataSet, MiriadMappedType, Type};
+use super::{AnyMiriadValue, DataSet, MiriadMappedType, ReadStream, Type};
 use crate::{mask::MaskDecoder, MiriadFormatError};
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
@@ -157,14 +161,24 @@ impl Decoder {
 
     /// Returns Ok(false) on EOF, Ok(true) if there are more data.
     pub fn next(&mut self) -> Result<bool, MiriadFormatError> {
-        let mut keep_going = true;
         let mut header_buf = [0u8; 4];
 
         for var in &mut self.vars {
             var.just_updated = false;
         }
 
-        while keep_going {
+        loop {
+            // The "vislen" variable is what we should use to determine when
+            // to stop reading, rather than EOF -- it's insurance to save
+            // datasets if some extra vis data are written out when a
+            // data-taker crashes. "vislen" should always be set to land on
+            // the end of a UV record.
+
+            if self.stream.offset() >= self.eff_vislen {
+                return Ok(false);
+            }
+
+            self.stream.align_to(8)?;
             self.stream.read_exact(&mut header_buf)?;
             let varnum = header_buf[0];
             let entry_type = header_buf[2];
@@ -212,7 +226,9 @@ impl Decoder {
                     var.just_updated = true;
                 }
                 EOR => {
-                    keep_going = false;
+                    // A complete record has been read, even if it is the
+                    // last one before "vislen".
+                    return Ok(true);
                 }
                 z => {
                     return Err(MiriadFormatError::Generic(format!(
@@ -220,21 +236,7 @@ impl Decoder {
                     )));
                 }
             }
-
-            // The "vislen" variable is what we should use to determine when
-            // to stop reading, rather than EOF -- it's insurance to save
-            // datasets if some extra vis data are written out when a
-            // data-taker crashes. "vislen" should always be set to land on
-            // the end of a UV record.
-
-            if self.stream.offset() >= self.eff_vislen {
-                return Ok(false);
-            }
-
-            self.stream.align_to(8)?;
         }
-
-        Ok(true)
     }
 
     pub fn variables<'a>(&'a self) -> UvVariablesIterator<'a> {
@@ -521,6 +523,346 @@ impl Reader {
     }
 }
 
+/// The "preamble" of a MIRIAD visibility record.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Preamble {
+    /// The (u, v, w) coordinates of the baseline, in nanoseconds. If the data
+    /// set only records (u, v), `w` is zero.
+    pub uvw: [f64; 3],
+
+    /// The timestamp of the record, as a Julian date.
+    pub time: f64,
+
+    /// The first antenna of the baseline, using Rubbl's zero-based numbering.
+    pub ant1: usize,
+
+    /// The second antenna of the baseline, using Rubbl's zero-based numbering.
+    pub ant2: usize,
+
+    /// The MIRIAD polarization code of the record, if the data set records
+    /// one.
+    pub pol: Option<i32>,
+}
+
+/// A single record of visibility data from a MIRIAD UV data set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UvRecord {
+    /// The preamble of the record.
+    pub preamble: Preamble,
+
+    /// The correlator data, one value per channel.
+    pub data: Array1<Complex<f32>>,
+
+    /// Per-channel flags, where `true` means that the datum is bad. Note that
+    /// this is the opposite of the sense of the MIRIAD "flags" mask item. If
+    /// the data set has no flags, everything is unflagged.
+    pub flags: Array1<bool>,
+}
+
+/// A stream of typed visibility records from a MIRIAD UV data set.
+///
+/// This wraps a [`Decoder`] and handles the bookkeeping of extracting the
+/// standard variables from each record and pairing them with the flags. The
+/// variables making up the preamble are looked up once, and slowly-varying
+/// ones such as the polarization are only decoded when they change. Other
+/// UV variables can be examined through [`Self::decoder`] after each record
+/// is read.
+#[derive(Debug)]
+pub struct UvStream {
+    decoder: Decoder,
+    flags: Option<MaskDecoder<ReadStream>>,
+    coord_var: UvVariableReference,
+    time_var: UvVariableReference,
+    baseline_var: UvVariableReference,
+    corr_var: UvVariableReference,
+    tscale_var: Option<UvVariableReference>,
+    pol_var: Option<UvVariableReference>,
+    pol: Option<i32>,
+    coord_buf: Vec<f64>,
+    corr_buf: Vec<f32>,
+    scaled_buf: Vec<i16>,
+    cplx_buf: Vec<Complex<f32>>,
+}
+
+impl UvStream {
+    /// Open the UV data of a data set as a stream of records.
+    ///
+    /// The data set must define the standard "coord", "time", "baseline",
+    /// and "corr" UV variables. The correlations may be stored as complex
+    /// values, as pairs of reals, or as pairs of scaled 16-bit integers. In
+    /// the last case, they are multiplied by the "tscale" UV variable, or
+    /// used as-is if the data set does not define it.
+    pub fn create(ds: &mut DataSet) -> Result<Self, MiriadFormatError> {
+        let decoder = Decoder::create(ds)?;
+
+        let flags = match ds.get("flags")? {
+            Some(iii) => Some(MaskDecoder::new(iii.into_byte_stream()?)),
+            None => None,
+        };
+
+        let lookup =
+            |name: &str, types: &[Type]| -> Result<UvVariableReference, MiriadFormatError> {
+                let var = decoder.lookup_variable(name).ok_or_else(|| {
+                    MiriadFormatError::Generic(format!("no \"{name}\" UV variable"))
+                })?;
+
+                let ty = decoder.get_var(var).type_();
+
+                if !types.contains(&ty) {
+                    return Err(MiriadFormatError::Generic(format!(
+                        "UV variable \"{name}\" has unexpected type {}",
+                        ty.abbrev_char()
+                    )));
+                }
+
+                Ok(var)
+            };
+
+        let coord_var = lookup("coord", &[Type::Float64])?;
+        let time_var = lookup("time", &[Type::Float64])?;
+        let baseline_var = lookup("baseline", &[Type::Float32])?;
+        let corr_var = lookup("corr", &[Type::Float32, Type::Complex64, Type::Int16])?;
+
+        let tscale_var = match decoder.lookup_variable("tscale") {
+            None => None,
+            Some(_) => Some(lookup("tscale", &[Type::Float32])?),
+        };
+
+        let pol_var = match decoder.lookup_variable("pol") {
+            None => None,
+            Some(_) => Some(lookup("pol", &[Type::Int32])?),
+        };
+
+        Ok(UvStream {
+            decoder,
+            flags,
+            coord_var,
+            time_var,
+            baseline_var,
+            corr_var,
+            tscale_var,
+            pol_var,
+            pol: None,
+            coord_buf: Vec::new(),
+            corr_buf: Vec::new(),
+            scaled_buf: Vec::new(),
+            cplx_buf: Vec::new(),
+        })
+    }
+
+    /// Get the underlying decoder, to access UV variables not included in
+    /// the records.
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    /// Convert this stream into a source of chunks of correlator data.
+    ///
+    /// The first record is read to determine the number of channels.
+    pub fn into_chunks(mut self) -> Result<UvChunks, MiriadFormatError> {
+        let pending = self.next_record()?;
+        let n_chans = pending.as_ref().map_or(0, |r| r.data.len());
+
+        Ok(UvChunks {
+            done: pending.is_none(),
+            stream: self,
+            pending,
+            item_shape: [n_chans],
+            preambles: Vec::new(),
+            flags: Vec::new(),
+        })
+    }
+
+    /// Read the next record. Returns `Ok(None)` at the end of the data.
+    pub fn next_record(&mut self) -> Result<Option<UvRecord>, MiriadFormatError> {
+        if !self.decoder.next()? {
+            return Ok(None);
+        }
+
+        self.decoder.get_data(self.coord_var, &mut self.coord_buf);
+
+        let mut uvw = [0.; 3];
+
+        if self.coord_buf.len() < 2 || self.coord_buf.len() > 3 {
+            return Err(MiriadFormatError::Generic(format!(
+                "UV variable \"coord\" should have 2 or 3 values but has {}",
+                self.coord_buf.len()
+            )));
+        }
+
+        uvw[..self.coord_buf.len()].copy_from_slice(&self.coord_buf);
+
+        for &var in &[self.time_var, self.baseline_var] {
+            let var = self.decoder.get_var(var);
+
+            if var.n_vals() < 1 {
+                return Err(MiriadFormatError::Generic(format!(
+                    "UV variable \"{}\" has not been given a value",
+                    var.name()
+                )));
+            }
+        }
+
+        let time = self.decoder.get_scalar::<f64>(self.time_var);
+        let (ant1, ant2) = decode_baseline(self.decoder.get_scalar::<f32>(self.baseline_var))?;
+
+        if let Some(pv) = self.pol_var {
+            if self.decoder.get_var(pv).just_updated() {
+                self.pol = Some(self.decoder.get_scalar::<i32>(pv));
+            }
+        }
+
+        // The correlations are usually stored as pairs of reals.
+
+        let data = match self.decoder.get_var(self.corr_var).type_() {
+            Type::Complex64 => {
+                self.decoder.get_data(self.corr_var, &mut self.cplx_buf);
+                Array1::from(self.cplx_buf.clone())
+            }
+
+            Type::Int16 => {
+                self.decoder.get_data(self.corr_var, &mut self.scaled_buf);
+                check_corr_pairs(self.scaled_buf.len())?;
+
+                let scale = match self.tscale_var {
+                    Some(tv) if self.decoder.get_var(tv).n_vals() > 0 => {
+                        self.decoder.get_scalar::<f32>(tv)
+                    }
+                    _ => 1.,
+                };
+
+                self.scaled_buf
+                    .chunks_exact(2)
+                    .map(|p| Complex::new(f32::from(p[0]) * scale, f32::from(p[1]) * scale))
+                    .collect()
+            }
+
+            _ => {
+                self.decoder.get_data(self.corr_var, &mut self.corr_buf);
+                check_corr_pairs(self.corr_buf.len())?;
+
+                self.corr_buf
+                    .chunks_exact(2)
+                    .map(|p| Complex::new(p[0], p[1]))
+                    .collect()
+            }
+        };
+
+        let mut flags = vec![false; data.len()];
+
+        if let Some(ref mut fd) = self.flags {
+            fd.expand(&mut flags)?;
+
+            for f in &mut flags {
+                *f = !*f;
+            }
+        }
+
+        Ok(Some(UvRecord {
+            preamble: Preamble {
+                uvw,
+                time,
+                ant1,
+                ant2,
+                pol: self.pol,
+            },
+            data,
+            flags: Array1::from(flags),
+        }))
+    }
+}
+
+fn check_corr_pairs(n_vals: usize) -> Result<(), MiriadFormatError> {
+    if n_vals % 2 != 0 {
+        return Err(MiriadFormatError::Generic(
+            "UV variable \"corr\" has an odd number of real values".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+impl Iterator for UvStream {
+    type Item = Result<UvRecord, MiriadFormatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// An [`ArrayChunkSource`] of the correlator data of a MIRIAD UV data set,
+/// created by [`UvStream::into_chunks`].
+///
+/// Each item is the data of one record, with shape `[n_chans]`, so all of
+/// the records must have the same number of channels as the first one. The
+/// preambles and flags of the records in the most recent chunk are available
+/// from [`Self::preambles`] and [`Self::flags`].
+#[derive(Debug)]
+pub struct UvChunks {
+    stream: UvStream,
+    pending: Option<UvRecord>,
+    done: bool,
+    item_shape: [usize; 1],
+    preambles: Vec<Preamble>,
+    flags: Vec<bool>,
+}
+
+impl UvChunks {
+    /// Get the preambles of the records in the most recent chunk.
+    pub fn preambles(&self) -> &[Preamble] {
+        &self.preambles[..]
+    }
+
+    /// Get the flags of the records in the most recent chunk, with shape
+    /// `[n_records, n_chans]`.
+    pub fn flags(&self) -> ArrayView2<'_, bool> {
+        ArrayView2::from_shape((self.preambles.len(), self.item_shape[0]), &self.flags[..]).unwrap()
+    }
+}
+
+impl ArrayChunkSource<Complex<f32>> for UvChunks {
+    type Error = MiriadFormatError;
+
+    fn item_shape(&self) -> &[usize] {
+        &self.item_shape[..]
+    }
+
+    fn read_chunk(&mut self, mut dest: ArrayViewMutD<Complex<f32>>) -> Result<usize, Self::Error> {
+        self.preambles.clear();
+        self.flags.clear();
+
+        let mut n = 0;
+
+        while n < dest.len_of(Axis(0)) && !self.done {
+            let record = match self.pending.take() {
+                Some(r) => r,
+                None => match self.stream.next_record()? {
+                    Some(r) => r,
+                    None => {
+                        self.done = true;
+                        break;
+                    }
+                },
+            };
+
+            if record.data.len() != self.item_shape[0] {
+                return Err(MiriadFormatError::Generic(format!(
+                    "UV record has {} channels, but earlier ones have {}",
+                    record.data.len(),
+                    self.item_shape[0]
+                )));
+            }
+
+            dest.index_axis_mut(Axis(0), n).assign(&record.data);
+            self.preambles.push(record.preamble);
+            self.flags.extend(record.flags.iter());
+            n += 1;
+        }
+
+        Ok(n)
+    }
+}
+
 /// A struct that holds state for writing a variable stream in the MIRIAD UV
 /// data format.
 #[derive(Debug)]
@@ -537,24 +879,35 @@ pub struct Encoder {
 }
 
 impl Encoder {
-    /// Create a new Encoder that has the same variables as some input Decoder
-    /// struct.
-    pub fn new_like(ds: &mut DataSet, template: &Decoder) -> Result<Self, MiriadFormatError> {
-        let mut vars = template.vars.clone();
-        let vars_by_name = template.vars_by_name.clone();
+    /// Create a new Encoder that writes the UV variables listed in *vars*,
+    /// given as pairs of types and names.
+    pub fn new(ds: &mut DataSet, vars: &[(Type, &str)]) -> Result<Self, MiriadFormatError> {
+        if vars.len() > 256 {
+            return Err(MiriadFormatError::Generic(
+                "too many UV variables".to_string(),
+            ));
+        }
+
+        let mut uv_vars = Vec::with_capacity(vars.len());
+        let mut vars_by_name = HashMap::new();
         let mut vartable = ds.create_large_item("vartable", Type::Text)?;
 
-        for var in &mut vars {
-            var.n_vals = -1;
-            var.data.clear();
-            writeln!(vartable, "{} {}", var.ty.abbrev_char(), var.name)?;
+        for (num, (ty, name)) in vars.iter().enumerate() {
+            if vars_by_name.insert((*name).to_owned(), num as u8).is_some() {
+                return Err(MiriadFormatError::Generic(format!(
+                    "duplicate UV variable \"{name}\""
+                )));
+            }
+
+            uv_vars.push(UvVariable::new(*ty, name, num as u8));
+            writeln!(vartable, "{} {}", ty.abbrev_char(), name)?;
         }
 
         let stream = ds.create_large_item("visdata", Type::Binary)?;
 
         Ok(Encoder {
             eff_vislen: 0,
-            vars: vars,
+            vars: uv_vars,
             vars_by_name: vars_by_name,
             stream: stream,
             tot_nschan: 0,
@@ -565,6 +918,17 @@ impl Encoder {
         })
     }
 
+    /// Create a new Encoder that has the same variables as some input Decoder
+    /// struct.
+    pub fn new_like(ds: &mut DataSet, template: &Decoder) -> Result<Self, MiriadFormatError> {
+        let vars: Vec<_> = template
+            .vars
+            .iter()
+            .map(|v| (v.ty, v.name.as_str()))
+            .collect();
+        Self::new(ds, &vars)
+    }
+
     pub fn write_var(&mut self, var: &UvVariable) -> Result<(), MiriadFormatError> {
         let our_num = self
             .vars_by_name
@@ -713,3 +1077,117 @@ impl Encoder {
 // anything that panics inside drop() because that can lead to aborts if
 // something is dropped during unwinding. So we just silently let things go
 // wrong. Cf. https://github.com/rust-lang/rust/issues/32677 .
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Write a UV data set with two records whose correlations are stored as
+    /// scaled 16-bit integers.
+    fn write_scaled(path: &std::path::Path) {
+        std::fs::create_dir(path).unwrap();
+        std::fs::write(path.join("header"), b"").unwrap();
+
+        let mut ds = DataSet::open(path).unwrap();
+        let mut enc = ds
+            .new_uv(&[
+                (Type::Float64, "coord"),
+                (Type::Float64, "time"),
+                (Type::Float32, "baseline"),
+                (Type::Int32, "pol"),
+                (Type::Float32, "tscale"),
+                (Type::Int16, "corr"),
+            ])
+            .unwrap();
+
+        enc.write("coord", &[1., 2., 3.]).unwrap();
+        enc.write_scalar("time", 2_460_000.5).unwrap();
+        enc.write_scalar("baseline", encode_baseline(0, 1).unwrap())
+            .unwrap();
+        enc.write_scalar("pol", -5i32).unwrap();
+        enc.write_scalar("tscale", 0.5f32).unwrap();
+        enc.write("corr", &[2i16, -4, 6, 8]).unwrap();
+        enc.finish_record().unwrap();
+
+        enc.write("coord", &[4., 5.]).unwrap();
+        enc.write_scalar("time", 2_460_000.75).unwrap();
+        enc.write_scalar("baseline", encode_baseline(1, 2).unwrap())
+            .unwrap();
+        enc.write("corr", &[10i16, 12, -14, 16]).unwrap();
+        enc.finish_record().unwrap();
+
+        enc.flush(&mut ds).unwrap();
+    }
+
+    #[test]
+    fn data_chunks() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("vis.uv");
+        write_scaled(&path);
+
+        let mut ds = DataSet::open(&path).unwrap();
+        let mut chunks = ds.open_uv_stream().unwrap().into_chunks().unwrap();
+        assert_eq!(chunks.item_shape(), &[2]);
+
+        let mut buf = rubbl_core::ndarray::Array2::zeros((1, 2)).into_dyn();
+        assert_eq!(chunks.read_chunk(buf.view_mut()).unwrap(), 1);
+        assert_eq!(
+            buf.iter().copied().collect::<Vec<_>>(),
+            vec![Complex::new(1., -2.), Complex::new(3., 4.)]
+        );
+        assert_eq!(
+            (chunks.preambles()[0].ant1, chunks.preambles()[0].ant2),
+            (0, 1)
+        );
+        assert_eq!(chunks.flags().shape(), &[1, 2]);
+
+        let mut buf = rubbl_core::ndarray::Array2::zeros((3, 2)).into_dyn();
+        assert_eq!(chunks.read_chunk(buf.view_mut()).unwrap(), 1);
+        assert_eq!(buf[[0, 1]], Complex::new(-7., 8.));
+        assert_eq!(chunks.preambles()[0].time, 2_460_000.75);
+        assert_eq!(chunks.read_chunk(buf.view_mut()).unwrap(), 0);
+        assert!(chunks.preambles().is_empty());
+    }
+
+    #[test]
+    fn scaled_corr_records() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("vis.uv");
+        write_scaled(&path);
+
+        let mut ds = DataSet::open(&path).unwrap();
+        let records: Vec<UvRecord> = ds
+            .open_uv_stream()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(
+            records[0].preamble,
+            Preamble {
+                uvw: [1., 2., 3.],
+                time: 2_460_000.5,
+                ant1: 0,
+                ant2: 1,
+                pol: Some(-5),
+            }
+        );
+        assert_eq!(
+            records[0].data.to_vec(),
+            vec![Complex::new(1., -2.), Complex::new(3., 4.)]
+        );
+        assert_eq!(records[0].flags.to_vec(), vec![false, false]);
+
+        // The polarization and scale factor carry over to the second record.
+        assert_eq!(records[1].preamble.uvw, [4., 5., 0.]);
+        assert_eq!((records[1].preamble.ant1, records[1].preamble.ant2), (1, 2));
+        assert_eq!(records[1].preamble.pol, Some(-5));
+        assert_eq!(
+            records[1].data.to_vec(),
+            vec![Complex::new(5., 6.), Complex::new(-7., 8.)]
+        );
+    }
+}